@@ -6,6 +6,7 @@ use bevy_ecs::{
     schedule::IntoSystemConfig,
     system::{Query, Res},
 };
+use nalgebra::{UnitQuaternion, Vector3};
 use scene::transform::Transform;
 use time::time_manager::{
     game_change::GameChangeHistoryPlugin, level_time::LevelTime, TimeManager, TimeTrackedId,
@@ -14,11 +15,65 @@ use time::time_manager::{
 use crate::animation_change::{
     animations_rewind, animations_start_track, animations_track, PlayingAnimationChange,
 };
+use crate::animation_events::send_animation_events;
 
 pub struct Animation {
     pub start_transform: Transform,
-    pub end_transform: Transform,
+    /// Gets added to the position, in world space
+    pub translation: Vector3<f32>,
+    /// Turns around this axis by its length in radians, relative to the start rotation.
+    /// Unlike a rotation between two transforms, it can turn by more than half a turn.
+    pub rotation: Vector3<f32>,
+    /// Multiplies the scale at the end
+    pub scale: Vector3<f32>,
     pub duration: Duration,
+    pub repeat: AnimationRepeat,
+    /// Passing one of them sends an `AnimationMarkerReached`
+    pub markers: Vec<AnimationMarker>,
+}
+
+impl Animation {
+    pub fn new(start_transform: Transform, duration: Duration) -> Self {
+        Self {
+            start_transform,
+            translation: Vector3::zeros(),
+            rotation: Vector3::zeros(),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+            duration,
+            repeat: AnimationRepeat::Once,
+            markers: Vec::new(),
+        }
+    }
+
+    /// From the start transform at 0 to the end at 1
+    pub fn transform_at(&self, progress: f32) -> Transform {
+        let start = &self.start_transform;
+        Transform {
+            position: start.position + self.translation * progress,
+            rotation: start.rotation * UnitQuaternion::from_scaled_axis(self.rotation * progress),
+            scale: start
+                .scale
+                .component_mul(&Vector3::new(1.0, 1.0, 1.0).lerp(&self.scale, progress)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationRepeat {
+    /// Plays when the game tells it to, like a door
+    Once,
+    /// Starts over at the end, like a fan that turns by a full turn
+    Loop,
+    /// Plays forwards and backwards, like a pulsing light
+    PingPong,
+}
+
+/// A named point along an animation, like the moment where a door is open wide enough to walk through
+#[derive(Debug, Clone)]
+pub struct AnimationMarker {
+    pub name: String,
+    /// From 0 at the start transform to 1 at the end
+    pub progress: f32,
 }
 
 /// An entity with a PlayingAnimation should not have a TimeTracked component!
@@ -29,6 +84,8 @@ pub struct PlayingAnimation {
     pub(crate) end_time: LevelTime,
     /// Also can be used to keep the animation frozen at the start.
     pub(crate) reverse: bool,
+    /// Where the animation was when the events were last sent, this is not a part of the history
+    pub(crate) previous_position: Option<f32>,
 }
 
 impl PlayingAnimation {
@@ -38,28 +95,36 @@ impl PlayingAnimation {
             animation,
             end_time: LevelTime::zero(),
             reverse: true,
+            previous_position: None,
         }
     }
 
     pub fn get_transform(&self, time: LevelTime) -> Transform {
-        let progress = self.get_progress(time);
+        self.animation.transform_at(self.get_position(time) as f32)
+    }
 
-        let (start, end) = if self.reverse {
-            (
-                &self.animation.end_transform,
-                &self.animation.start_transform,
-            )
-        } else {
-            (
-                &self.animation.start_transform,
-                &self.animation.end_transform,
-            )
-        };
+    /// Where the animation is, from 0 at the start transform to 1 at the end.
+    /// Repeating animations play all the time, starting with the level.
+    pub fn get_position(&self, time: LevelTime) -> f64 {
+        match self.animation.repeat {
+            AnimationRepeat::Once if self.reverse => 1.0 - self.get_progress(time),
+            AnimationRepeat::Once => self.get_progress(time),
+            AnimationRepeat::Loop => self.get_cycles(time).fract(),
+            AnimationRepeat::PingPong => 1.0 - (self.get_cycles(time).fract() * 2.0 - 1.0).abs(),
+        }
+    }
+
+    pub fn animation(&self) -> &Animation {
+        &self.animation
+    }
 
-        start.lerp(&end, progress as f32)
+    /// How often a repeating animation has played since the start of the level
+    fn get_cycles(&self, time: LevelTime) -> f64 {
+        LevelTime::zero().inverse_lerp(&(LevelTime::zero() + self.animation.duration), time)
     }
 
-    fn get_progress(&self, time: LevelTime) -> f64 {
+    /// From 0 to 1, how far the animation has played in its current direction
+    pub fn get_progress(&self, time: LevelTime) -> f64 {
         if time > self.end_time {
             return 1.0;
         }
@@ -77,6 +142,11 @@ impl PlayingAnimation {
         progress
     }
 
+    /// Whether the animation is playing, or has played, towards the end transform
+    pub fn is_forwards(&self) -> bool {
+        !self.reverse
+    }
+
     pub fn play_forwards(&mut self, time: LevelTime) {
         let remaining_progress = if self.reverse {
             self.get_progress(time)
@@ -112,7 +182,8 @@ impl Plugin for AnimationPlugin {
             .with_system(
                 play_animations
                     .after(GameChangeHistoryPlugin::<PlayingAnimationChange>::system_set()),
-            );
+            )
+            .with_system(send_animation_events.after(play_animations));
     }
 }
 
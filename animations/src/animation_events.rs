@@ -0,0 +1,96 @@
+use app::entity_event::EntityEvent;
+use bevy_ecs::system::{Query, Res};
+use time::time_manager::TimeManager;
+
+use crate::animation::{AnimationRepeat, PlayingAnimation};
+
+/// Sent as an `EntityEvent<AnimationFinished>` on the animated entity,
+/// when an animation that plays once arrives at its end, or at its start when played backwards.
+#[derive(Debug, Clone)]
+pub struct AnimationFinished {
+    /// Whether the animation arrived at the end transform
+    pub forwards: bool,
+}
+
+/// Sent as an `EntityEvent<AnimationMarkerReached>` on the animated entity,
+/// when the animation passes one of its markers in either direction.
+#[derive(Debug, Clone)]
+pub struct AnimationMarkerReached {
+    pub name: String,
+    /// Whether the animation was moving towards the end transform
+    pub forwards: bool,
+}
+
+/// Nothing gets sent while rewinding. Afterwards the markers that were rewound past get sent again,
+/// once the animation passes them again.
+pub(crate) fn send_animation_events(
+    time_manager: Res<TimeManager>,
+    mut query: Query<(
+        &mut PlayingAnimation,
+        Option<&mut EntityEvent<AnimationFinished>>,
+        Option<&mut EntityEvent<AnimationMarkerReached>>,
+    )>,
+) {
+    for (mut playing_animation, mut finished_events, mut marker_events) in query.iter_mut() {
+        if let Some(finished_events) = finished_events.as_mut() {
+            finished_events.clear();
+        }
+        if let Some(marker_events) = marker_events.as_mut() {
+            marker_events.clear();
+        }
+
+        let position = playing_animation.get_position(*time_manager.level_time()) as f32;
+        // Bypassing the change detection, since the history only has to know when the animation gets played
+        let previous_position = playing_animation
+            .bypass_change_detection()
+            .previous_position
+            .replace(position);
+        let previous_position = match previous_position {
+            Some(previous_position) => previous_position,
+            None => continue,
+        };
+        if time_manager.is_rewinding() || previous_position == position {
+            continue;
+        }
+
+        let animation = playing_animation.animation();
+        // A looping animation jumps from the end back to the start
+        let wrapped = animation.repeat == AnimationRepeat::Loop && position < previous_position;
+        let forwards = wrapped || position > previous_position;
+
+        if let Some(marker_events) = marker_events.as_mut() {
+            for marker in animation.markers.iter() {
+                let progress = marker.progress;
+                let passed = if wrapped {
+                    previous_position < progress || progress <= position
+                } else if forwards {
+                    previous_position < progress && progress <= position
+                } else {
+                    position <= progress && progress < previous_position
+                };
+                if passed {
+                    marker_events.add(AnimationMarkerReached {
+                        name: marker.name.clone(),
+                        forwards,
+                    });
+                }
+            }
+        }
+
+        if animation.repeat != AnimationRepeat::Once {
+            continue;
+        }
+        let end_position = if playing_animation.is_forwards() {
+            1.0
+        } else {
+            0.0
+        };
+        if position == end_position {
+            if let Some(finished_events) = finished_events.as_mut() {
+                finished_events.add(AnimationFinished {
+                    forwards: playing_animation.is_forwards(),
+                });
+            }
+        }
+    }
+}
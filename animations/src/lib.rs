@@ -1,2 +1,5 @@
 pub mod animation;
 pub mod animation_change;
+pub mod animation_events;
+pub mod light_animation;
+pub mod light_animation_change;
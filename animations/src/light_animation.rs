@@ -0,0 +1,256 @@
+use std::time::Duration;
+
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::{
+    prelude::{Commands, Component, Entity},
+    query::{Added, Without},
+    schedule::IntoSystemConfig,
+    system::{Query, Res},
+};
+use nalgebra::Vector3;
+use scene::light::Light;
+use time::time_manager::{
+    game_change::GameChangeHistoryPlugin, level_time::LevelTime, TimeManager, TimeTrackedId,
+};
+
+use crate::light_animation_change::{
+    light_animations_rewind, light_animations_start_track, light_animations_track, LightChange,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightKeyframe {
+    /// Time since the animation started playing
+    pub time: Duration,
+    pub color: Vector3<f32>,
+    pub intensity: f32,
+}
+
+/// Multiplies the intensity of an animated light
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flicker {
+    None,
+    /// Slight, soft variations
+    Candle,
+    /// Mostly on, with short random dropouts
+    Broken,
+    /// Hard on/off switching
+    Strobe,
+}
+
+impl Flicker {
+    /// Only depends on the time, so that flickering lights rewind exactly
+    fn sample(&self, seed: f32, time: f32) -> f32 {
+        match self {
+            Flicker::None => 1.0,
+            Flicker::Candle => 0.85 + 0.15 * value_noise(seed, time * 8.0),
+            Flicker::Broken => {
+                if value_noise(seed, time * 12.0) < 0.25 {
+                    0.1
+                } else {
+                    1.0
+                }
+            }
+            Flicker::Strobe => {
+                if (time * 10.0).fract() < 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+/// Smooth noise from 0 to 1
+fn value_noise(seed: f32, x: f32) -> f32 {
+    fn hash(seed: f32, x: f32) -> f32 {
+        ((x * 12.9898 + seed * 78.233).sin() * 43758.547)
+            .fract()
+            .abs()
+    }
+    let t = x.fract();
+    let t = t * t * (3.0 - 2.0 * t);
+    let a = hash(seed, x.floor());
+    let b = hash(seed, x.floor() + 1.0);
+    a + (b - a) * t
+}
+
+/// Animates the color and intensity of a light over the level time.
+/// Every light gets one when it is loaded. Scripts change the light by
+/// replacing the keyframes and calling [`LightAnimation::play`], which gets tracked for rewinding.
+#[derive(Component, Debug, Clone)]
+pub struct LightAnimation {
+    pub(crate) id: TimeTrackedId,
+    pub(crate) keyframes: Vec<LightKeyframe>,
+    pub(crate) start_time: LevelTime,
+    pub(crate) looping: bool,
+    pub(crate) flicker: Flicker,
+}
+
+impl LightAnimation {
+    /// A light that stays the same
+    pub fn constant(color: Vector3<f32>, intensity: f32) -> Self {
+        Self::new(vec![LightKeyframe {
+            time: Duration::ZERO,
+            color,
+            intensity,
+        }])
+    }
+
+    pub fn new(keyframes: Vec<LightKeyframe>) -> Self {
+        assert!(
+            !keyframes.is_empty(),
+            "A light animation needs at least one keyframe"
+        );
+        Self {
+            id: TimeTrackedId::new_v4(),
+            keyframes,
+            start_time: LevelTime::zero(),
+            looping: false,
+            flicker: Flicker::None,
+        }
+    }
+
+    pub fn with_looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    pub fn with_flicker(mut self, flicker: Flicker) -> Self {
+        self.flicker = flicker;
+        self
+    }
+
+    /// Smoothly goes from the current color and intensity to the new ones
+    pub fn fade_to(
+        &mut self,
+        time: LevelTime,
+        color: Vector3<f32>,
+        intensity: f32,
+        duration: Duration,
+    ) {
+        let (start_color, start_intensity) = self.sample_keyframes(time);
+        self.keyframes = vec![
+            LightKeyframe {
+                time: Duration::ZERO,
+                color: start_color,
+                intensity: start_intensity,
+            },
+            LightKeyframe {
+                time: duration,
+                color,
+                intensity,
+            },
+        ];
+        self.looping = false;
+        self.play(time);
+    }
+
+    pub fn set_flicker(&mut self, flicker: Flicker) {
+        self.flicker = flicker;
+    }
+
+    pub fn set_keyframes(&mut self, keyframes: Vec<LightKeyframe>, looping: bool) {
+        assert!(
+            !keyframes.is_empty(),
+            "A light animation needs at least one keyframe"
+        );
+        self.keyframes = keyframes;
+        self.looping = looping;
+    }
+
+    /// Starts the keyframes from the beginning
+    pub fn play(&mut self, time: LevelTime) {
+        self.start_time = time;
+    }
+
+    fn sample_keyframes(&self, time: LevelTime) -> (Vector3<f32>, f32) {
+        let duration = self.keyframes.last().unwrap().time.as_secs_f32();
+        let mut elapsed = (time.as_secs_f32() - self.start_time.as_secs_f32()).max(0.0);
+        if self.looping && duration > 0.0 {
+            elapsed %= duration;
+        }
+
+        let next_index = self
+            .keyframes
+            .iter()
+            .position(|keyframe| keyframe.time.as_secs_f32() > elapsed);
+        match next_index {
+            None => {
+                let last = self.keyframes.last().unwrap();
+                (last.color, last.intensity)
+            }
+            Some(0) => {
+                let first = &self.keyframes[0];
+                (first.color, first.intensity)
+            }
+            Some(index) => {
+                let previous = &self.keyframes[index - 1];
+                let next = &self.keyframes[index];
+                let start = previous.time.as_secs_f32();
+                let t = (elapsed - start) / (next.time.as_secs_f32() - start);
+                (
+                    previous.color.lerp(&next.color, t),
+                    previous.intensity + (next.intensity - previous.intensity) * t,
+                )
+            }
+        }
+    }
+
+    pub fn sample(&self, time: LevelTime) -> (Vector3<f32>, f32) {
+        let (color, intensity) = self.sample_keyframes(time);
+        let seed = self.id.as_u128() as u16 as f32;
+        (
+            color,
+            intensity * self.flicker.sample(seed, time.as_secs_f32()),
+        )
+    }
+}
+
+fn add_light_animations(
+    mut commands: Commands,
+    query: Query<(Entity, &Light), (Added<Light>, Without<LightAnimation>)>,
+) {
+    for (entity, light) in query.iter() {
+        let animation = match light {
+            Light::Point(point_light) => {
+                LightAnimation::constant(point_light.color, point_light.intensity)
+            }
+        };
+        commands.entity(entity).insert(animation);
+    }
+}
+
+/// Runs before the time of day and the alarms, so that those can still override the lights
+fn play_light_animations(time: Res<TimeManager>, mut query: Query<(&LightAnimation, &mut Light)>) {
+    for (animation, mut light) in query.iter_mut() {
+        let (color, intensity) = animation.sample(*time.level_time());
+        match light.as_mut() {
+            Light::Point(point_light) => {
+                if point_light.color != color || point_light.intensity != intensity {
+                    point_light.color = color;
+                    point_light.intensity = intensity;
+                }
+            }
+        }
+    }
+}
+
+pub struct LightAnimationPlugin;
+impl Plugin for LightAnimationPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_plugin(
+                GameChangeHistoryPlugin::<LightChange>::new()
+                    .with_tracker(light_animations_start_track)
+                    .with_tracker(light_animations_track.after(light_animations_start_track))
+                    .with_rewinder(light_animations_rewind),
+            )
+            .with_system(add_light_animations)
+            .with_system(
+                play_light_animations
+                    .after(add_light_animations)
+                    .after(GameChangeHistoryPlugin::<LightChange>::system_set()),
+            );
+    }
+}
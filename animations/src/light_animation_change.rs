@@ -0,0 +1,92 @@
+use bevy_ecs::prelude::EventReader;
+use bevy_ecs::query::Changed;
+use bevy_ecs::system::Query;
+use bevy_ecs::system::Res;
+use bevy_ecs::system::ResMut;
+use bevy_ecs::world::Mut;
+use levels::current_level::CurrentLevel;
+use levels::current_level::NextLevel;
+use levels::level_id::LevelId;
+use std::collections::HashMap;
+use time::time_manager::game_change::GameChange;
+use time::time_manager::game_change::GameChangeHistory;
+use time::time_manager::level_time::LevelTime;
+use time::time_manager::TimeManager;
+use time::time_manager::TimeTrackedId;
+
+use crate::light_animation::{Flicker, LightAnimation, LightKeyframe};
+
+#[derive(Debug, Clone)]
+pub struct LightChange {
+    pub(crate) id: TimeTrackedId,
+    pub(crate) keyframes: Vec<LightKeyframe>,
+    pub(crate) start_time: LevelTime,
+    pub(crate) looping: bool,
+    pub(crate) flicker: Flicker,
+}
+
+impl GameChange for LightChange {}
+
+impl LightChange {
+    fn new(animation: &LightAnimation) -> Self {
+        Self {
+            id: animation.id,
+            keyframes: animation.keyframes.clone(),
+            start_time: animation.start_time,
+            looping: animation.looping,
+            flicker: animation.flicker,
+        }
+    }
+}
+
+pub(super) fn light_animations_track(
+    mut history: ResMut<GameChangeHistory<LightChange>>,
+    current_level: Res<CurrentLevel>,
+    query: Query<(&LightAnimation, &LevelId), Changed<LightAnimation>>,
+) {
+    for (animation, level_id) in &query {
+        if level_id != &current_level.level_id {
+            continue;
+        }
+        history.add_command(LightChange::new(animation));
+    }
+}
+
+pub(super) fn light_animations_start_track(
+    mut next_level_events: EventReader<NextLevel>,
+    mut history: ResMut<GameChangeHistory<LightChange>>,
+    query: Query<(&LightAnimation, &LevelId)>,
+) {
+    for next_level_event in next_level_events.iter() {
+        for (animation, level_id) in &query {
+            if level_id != &next_level_event.level_id {
+                continue;
+            }
+            history.add_command(LightChange::new(animation));
+        }
+    }
+}
+
+pub(crate) fn light_animations_rewind(
+    time_manager: Res<TimeManager>,
+    mut history: ResMut<GameChangeHistory<LightChange>>,
+    mut query: Query<&mut LightAnimation>,
+) {
+    let mut entities: HashMap<_, Mut<LightAnimation>> = query
+        .iter_mut()
+        .map(|animation| (animation.id, animation))
+        .collect();
+
+    let commands = history.take_commands_to_apply(&time_manager);
+
+    for command_collection in commands {
+        for command in command_collection.commands {
+            if let Some(v) = entities.get_mut(&command.id) {
+                v.keyframes = command.keyframes;
+                v.start_time = command.start_time;
+                v.looping = command.looping;
+                v.flicker = command.flicker;
+            }
+        }
+    }
+}
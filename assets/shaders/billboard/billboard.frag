@@ -0,0 +1,20 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+layout(location = 1) flat in vec3 v_color;
+layout(location = 2) flat in float v_additive;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 1, binding = 0) uniform sampler2D billboardTexture;
+
+void main() {
+    vec4 textureColor = texture(billboardTexture, v_uv);
+
+    // The blending of the additive pipeline multiplies the color with the alpha
+    if (v_additive < 0.5 && textureColor.a < 0.5) {
+        discard;
+    }
+
+    f_color = vec4(textureColor.rgb * v_color, textureColor.a);
+}
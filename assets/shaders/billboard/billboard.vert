@@ -0,0 +1,51 @@
+#version 450
+
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec2 uv;
+
+layout(location = 0) out vec2 v_uv;
+layout(location = 1) flat out vec3 v_color;
+layout(location = 2) flat out float v_additive;
+
+layout(set = 0, binding = 0) uniform Scene {
+    mat4 projView;
+    vec3 cameraPosition;
+    float rewindTime;
+    vec3 cameraUp;
+} scene;
+
+layout(push_constant) uniform Billboard {
+    vec3 center;
+    float width;
+    vec3 color;
+    float height;
+    vec3 lockedAxis;
+    // 1 if the billboard only turns around the locked axis
+    float axisLocked;
+    // 1 for additive blending, 0 for cutouts
+    float additive;
+} billboard;
+
+#include "../time_rewinding.glsl"
+
+void main() {
+    vec3 toCamera = normalize(scene.cameraPosition - billboard.center);
+    vec3 up = billboard.axisLocked > 0.5 ? normalize(billboard.lockedAxis) : scene.cameraUp;
+    vec3 right = normalize(cross(up, toCamera));
+    if (billboard.axisLocked < 0.5) {
+        // The camera up vector isn't always perpendicular to the direction towards the camera
+        up = cross(toCamera, right);
+    }
+
+    vec3 worldPos = billboard.center
+        + right * (position.x * billboard.width * 0.5)
+        + up * (position.y * billboard.height * 0.5);
+    worldPos = timeRewindPosition(worldPos, scene.cameraPosition);
+
+    gl_Position = scene.projView * vec4(worldPos, 1.0);
+
+    // The top of the texture is at the top of the billboard
+    v_uv = vec2(uv.x, 1.0 - uv.y);
+    v_color = billboard.color;
+    v_additive = billboard.additive;
+}
@@ -0,0 +1,42 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D image;
+layout(set = 1, binding = 0) uniform sampler3D lut;
+layout(set = 1, binding = 1) uniform sampler3D blendLut;
+
+layout(push_constant) uniform Pass {
+    // 0 only uses the lut, 1 only uses the blendLut
+    float blend;
+    float lutSize;
+    float blendLutSize;
+} pass;
+
+vec3 linearToSrgb(vec3 color) {
+    return mix(color * 12.92, 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, color));
+}
+
+vec3 srgbToLinear(vec3 color) {
+    return mix(color / 12.92, pow((color + 0.055) / 1.055, vec3(2.4)), step(0.04045, color));
+}
+
+// Samples the centers of the outer entries, so that 0 and 1 map exactly onto the ends of the table
+vec3 sampleLut(sampler3D table, float size, vec3 color) {
+    vec3 uvw = color * ((size - 1.0) / size) + 0.5 / size;
+    return texture(table, uvw).rgb;
+}
+
+void main() {
+    // the image is already tonemapped, and lookup tables are made for sRGB colors
+    vec3 color = linearToSrgb(clamp(texture(image, v_uv).rgb, 0.0, 1.0));
+
+    vec3 graded = sampleLut(lut, pass.lutSize, color);
+    if (pass.blend > 0.0) {
+        graded = mix(graded, sampleLut(blendLut, pass.blendLutSize, color), pass.blend);
+    }
+
+    f_color = vec4(srgbToLinear(graded), 1.0);
+}
@@ -0,0 +1,15 @@
+#version 450
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 color;
+
+layout(location = 0) out vec3 v_color;
+
+layout(push_constant) uniform PushConstants {
+    mat4 viewProj;
+};
+
+void main() {
+    gl_Position = viewProj * vec4(position, 1.0);
+    v_color = color;
+}
@@ -0,0 +1,36 @@
+#version 450
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform Scene {
+    mat4 viewProj;
+    mat4 inverseViewProj;
+    vec2 screenSize;
+} scene;
+
+// The depth of the prepass
+layout(input_attachment_index = 0, set = 0, binding = 1) uniform subpassInput depthInput;
+
+layout(set = 1, binding = 0) uniform sampler2D decalTexture;
+
+layout(push_constant) uniform Decal {
+    mat4 model;
+    mat4 inverseModel;
+} decal;
+
+// Finds the surface behind the pixel, and checks if it is inside of the box of the decal
+void main() {
+    float depth = subpassLoad(depthInput).r;
+    vec2 uv = gl_FragCoord.xy / scene.screenSize;
+    vec4 worldPos = scene.inverseViewProj * vec4(uv * 2.0 - 1.0, depth, 1.0);
+    worldPos /= worldPos.w;
+
+    vec3 localPos = (decal.inverseModel * worldPos).xyz;
+    if (any(greaterThan(abs(localPos), vec3(0.5)))) {
+        discard;
+    }
+
+    vec4 color = texture(decalTexture, localPos.xz + 0.5);
+    // Gets blended, the shading then puts it on top of the albedo
+    f_color = color;
+}
@@ -0,0 +1,19 @@
+#version 450
+
+layout(location = 0) in vec3 position;
+
+layout(set = 0, binding = 0) uniform Scene {
+    mat4 viewProj;
+    mat4 inverseViewProj;
+    vec2 screenSize;
+} scene;
+
+layout(push_constant) uniform Decal {
+    // from a unit cube to the box of the decal
+    mat4 model;
+    mat4 inverseModel;
+} decal;
+
+void main() {
+    gl_Position = scene.viewProj * decal.model * vec4(position, 1.0);
+}
@@ -0,0 +1,34 @@
+#version 450
+
+layout(location = 0) in vec3 v_normal;
+layout(location = 1) in vec3 v_toCamera;
+
+layout(location = 0) out vec4 f_color;
+
+const vec3 GHOST_COLOR = vec3(0.4, 0.7, 1.0);
+const float GHOST_OPACITY = 0.35;
+
+// 4x4 Bayer matrix, the ghosts are opaque but only every few pixels get drawn
+const float DITHER_THRESHOLDS[16] = float[](
+    0.0, 8.0, 2.0, 10.0,
+    12.0, 4.0, 14.0, 6.0,
+    3.0, 11.0, 1.0, 9.0,
+    15.0, 7.0, 13.0, 5.0
+);
+
+void main() {
+    vec3 n = normalize(v_normal);
+    vec3 v = normalize(v_toCamera);
+
+    // The edges are more visible, which makes the outline of the ghost easier to see
+    float rim = 1.0 - abs(dot(n, v));
+    float opacity = mix(GHOST_OPACITY, 1.0, rim * rim);
+
+    ivec2 pixel = ivec2(gl_FragCoord.xy) % 4;
+    float threshold = (DITHER_THRESHOLDS[pixel.y * 4 + pixel.x] + 0.5) / 16.0;
+    if (opacity < threshold) {
+        discard;
+    }
+
+    f_color = vec4(GHOST_COLOR * (0.5 + rim), 1.0);
+}
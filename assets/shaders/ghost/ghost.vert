@@ -0,0 +1,29 @@
+#version 450
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+
+layout(location = 0) out vec3 v_normal;
+layout(location = 1) out vec3 v_toCamera;
+
+layout(set = 0, binding = 0) uniform Scene {
+    mat4 projView;
+    vec3 cameraPosition;
+    float rewindTime;
+} scene;
+
+layout(push_constant) uniform Entity {
+    mat4 model;
+} entity;
+
+#include "../time_rewinding.glsl"
+
+void main() {
+    vec4 worldPos = entity.model * vec4(position, 1.0); // world space
+    worldPos = vec4(timeRewindPosition(worldPos.xyz, scene.cameraPosition), worldPos.w);
+
+    gl_Position = scene.projView * worldPos;
+
+    v_normal = transpose(inverse(mat3(entity.model))) * normal; // world space
+    v_toCamera = scene.cameraPosition - worldPos.xyz;
+}
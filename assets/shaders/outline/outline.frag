@@ -0,0 +1,10 @@
+#version 450
+
+layout(location = 0) out vec4 f_color;
+
+// Slightly above 1, so that the bloom makes it glow a bit
+const vec3 OUTLINE_COLOR = vec3(1.4, 1.1, 0.5);
+
+void main() {
+    f_color = vec4(OUTLINE_COLOR, 1.0);
+}
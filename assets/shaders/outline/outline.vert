@@ -0,0 +1,29 @@
+#version 450
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+
+layout(set = 0, binding = 0) uniform Scene {
+    mat4 projView;
+    vec3 cameraPosition;
+    float rewindTime;
+    float outlineWidth;
+} scene;
+
+layout(push_constant) uniform Entity {
+    mat4 model;
+} entity;
+
+#include "../time_rewinding.glsl"
+
+void main() {
+    vec4 worldPos = entity.model * vec4(position, 1.0); // world space
+    worldPos = vec4(timeRewindPosition(worldPos.xyz, scene.cameraPosition), worldPos.w);
+
+    // The hull gets pushed out along the normals, further away it has to grow to keep the same width on the screen
+    vec3 worldNormal = normalize(transpose(inverse(mat3(entity.model))) * normal);
+    float distanceToCamera = length(scene.cameraPosition - worldPos.xyz);
+    worldPos.xyz += worldNormal * scene.outlineWidth * distanceToCamera;
+
+    gl_Position = scene.projView * worldPos;
+}
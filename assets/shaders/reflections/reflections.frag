@@ -0,0 +1,108 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D image;
+// From the depth prepass
+layout(set = 0, binding = 1) uniform sampler2D depthImage;
+// World space normals, the alpha channel is the reflectivity of the material
+layout(set = 0, binding = 2) uniform sampler2D normalImage;
+
+layout(push_constant) uniform Pass {
+    mat4 viewProj;
+    mat4 inverseViewProj;
+} pass;
+
+const int MAX_STEPS = 64;
+const int REFINEMENT_STEPS = 8;
+// in meters
+const float MAX_DISTANCE = 20.0;
+// How far a ray can be behind the depth buffer and still count as a hit, in meters
+const float THICKNESS = 0.3;
+
+vec3 worldPosition(vec2 uv, float depth) {
+    vec4 position = pass.inverseViewProj * vec4(uv * 2.0 - 1.0, depth, 1.0);
+    return position.xyz / position.w;
+}
+
+// xy are the texture coordinates, z is the depth
+vec3 screenPosition(vec3 worldPos) {
+    vec4 position = pass.viewProj * vec4(worldPos, 1.0);
+    position.xyz /= position.w;
+    return vec3(position.xy * 0.5 + 0.5, position.z);
+}
+
+bool isOnScreen(vec3 screen) {
+    return all(greaterThanEqual(screen, vec3(0.0))) && all(lessThanEqual(screen, vec3(1.0)));
+}
+
+float sceneDepth(vec2 uv) {
+    return texelFetch(depthImage, ivec2(uv * vec2(textureSize(depthImage, 0))), 0).r;
+}
+
+void main() {
+    vec3 color = texture(image, v_uv).rgb;
+
+    ivec2 pixel = ivec2(gl_FragCoord.xy);
+    vec4 normalAndReflectivity = texelFetch(normalImage, pixel, 0);
+    float reflectivity = normalAndReflectivity.a;
+    float depth = texelFetch(depthImage, pixel, 0).r;
+    if (reflectivity <= 0.0 || depth >= 1.0) {
+        f_color = vec4(color, 1.0);
+        return;
+    }
+
+    vec3 position = worldPosition(v_uv, depth);
+    vec3 v = normalize(position - worldPosition(v_uv, 0.0));
+    vec3 n = normalize(normalAndReflectivity.xyz);
+    vec3 r = reflect(v, n);
+
+    float stepSize = MAX_DISTANCE / float(MAX_STEPS);
+    vec3 previous = position;
+    for (int i = 1; i <= MAX_STEPS; ++i) {
+        vec3 current = position + r * stepSize * float(i);
+        vec3 screen = screenPosition(current);
+        if (!isOnScreen(screen)) {
+            break;
+        }
+
+        if (screen.z <= sceneDepth(screen.xy)) {
+            previous = current;
+            continue;
+        }
+
+        // The ray went behind something, so binary search for where exactly that happened
+        vec3 start = previous;
+        vec3 end = current;
+        for (int j = 0; j < REFINEMENT_STEPS; ++j) {
+            vec3 middle = (start + end) * 0.5;
+            vec3 middleScreen = screenPosition(middle);
+            if (middleScreen.z <= sceneDepth(middleScreen.xy)) {
+                start = middle;
+            } else {
+                end = middle;
+            }
+        }
+
+        vec3 hitScreen = screenPosition(end);
+        vec3 hitScenePosition = worldPosition(hitScreen.xy, sceneDepth(hitScreen.xy));
+        if (distance(hitScenePosition, end) > THICKNESS) {
+            // The ray passed behind an object instead of hitting it
+            break;
+        }
+
+        // Fade out towards the edges of the screen and the end of the ray, where the information is missing
+        vec2 edgeFade = smoothstep(0.0, 0.1, hitScreen.xy) * (1.0 - smoothstep(0.9, 1.0, hitScreen.xy));
+        float distanceFade = 1.0 - float(i) / float(MAX_STEPS);
+        float facingFade = 1.0 - smoothstep(0.5, 1.0, dot(r, -v));
+        float fade = edgeFade.x * edgeFade.y * distanceFade * facingFade;
+
+        vec3 reflectedColor = texture(image, hitScreen.xy).rgb;
+        color = mix(color, reflectedColor, reflectivity * fade);
+        break;
+    }
+
+    f_color = vec4(color, 1.0);
+}
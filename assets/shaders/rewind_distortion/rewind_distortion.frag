@@ -0,0 +1,59 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D image;
+
+layout(push_constant) uniform Pass {
+    // how many seconds have been rewound, zero when not rewinding
+    float rewindTime;
+    // between 0 and 1, a short burst of distortion, like when teleporting
+    float glitch;
+} pass;
+
+const float PI = 3.14159265359;
+
+// Makes the screen look like an old CRT monitor while rewinding
+void main() {
+    float strength = clamp(pass.rewindTime * 2.0, 0.0, 1.0);
+
+    // barrel distortion
+    vec2 centered = v_uv * 2.0 - 1.0;
+    vec2 warped = centered * (1.0 + strength * 0.08 * dot(centered, centered));
+    vec2 uv = warped * 0.5 + 0.5;
+
+    // horizontal bands that jump sideways while glitching
+    float band = floor(uv.y * 24.0);
+    float bandNoise = fract(sin(band * 12.9898 + floor(pass.glitch * 20.0) * 78.233) * 43758.5453);
+    uv.x += (bandNoise - 0.5) * pass.glitch * pass.glitch * 0.1;
+
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+        f_color = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    // slight color fringing
+    vec2 offset = vec2(strength * 0.003 + pass.glitch * 0.01, 0.0);
+    vec3 color = vec3(
+        texture(image, uv + offset).r,
+        texture(image, uv).g,
+        texture(image, uv - offset).b
+    );
+
+    // desaturation
+    float luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+    color = mix(color, vec3(luminance), strength * 0.6);
+
+    // scanlines that scroll with the rewind time
+    float height = float(textureSize(image, 0).y);
+    float scanline = 0.5 + 0.5 * sin((uv.y * height * 0.5 + pass.rewindTime * 30.0) * PI);
+    color *= mix(1.0, 0.75 + 0.25 * scanline, strength);
+
+    // darker corners
+    float vignette = 1.0 - dot(centered, centered) * 0.25;
+    color *= mix(1.0, vignette, strength);
+
+    f_color = vec4(color, 1.0);
+}
@@ -3,19 +3,25 @@ struct PointLight {
     vec3 color;
     float range;
     float intensity;
+    int shadowMapIndex; // -1 if the light doesn't have a shadow map
 };
 
 #define MAX_NUM_TOTAL_LIGHTS 32
+#define MAX_SHADOW_LIGHTS 4
 
 layout(set = 0, binding = 0) uniform Scene {
     PointLight pointLights[MAX_NUM_TOTAL_LIGHTS];
-    vec3 nearestShadowLight;
-    int hasShadowLight;
     int numLights;
     float rewindTime;
+    vec3 ambientLight;
+    int shadowPcfSamples;
+    vec3 fogColor;
+    float fogStart; // the fog is disabled when it ends before it starts
+    float fogEnd;
+    float rewindStartTime; // the level time, which stays the same while rewinding
 } scene;
 
-layout(set = 0, binding = 1) uniform samplerCubeShadow shadowMap;
+layout(set = 0, binding = 1) uniform samplerCubeShadow shadowMaps[MAX_SHADOW_LIGHTS];
 
 layout(set = 1, binding = 0) uniform Camera {
     mat4 view;
@@ -28,11 +34,22 @@ layout(set = 2, binding = 0) uniform Material {
     float roughness;
     float metallic;
     vec3 emissivity;
+    int hasLightmap;
+    float reflectivity;
+    int hasWind;
+    float windAmplitude;
+    float windFrequency;
+    int flipbookFrames; // 0 if the base color texture isn't a flipbook
+    int flipbookColumns;
+    float flipbookFps;
+    float opacity; // below 1 for translucent materials, which get dithered
 } material;
 
 layout(set = 2, binding = 1) uniform sampler2D baseColorTexture;
+layout(set = 2, binding = 2) uniform sampler2D lightmapTexture;
 
 layout(set = 3, binding = 0) uniform Entity {
     mat4 model;
     mat4 normalMatrix;
+    vec2 uvOffset;
 } entity;
\ No newline at end of file
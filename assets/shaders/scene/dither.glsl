@@ -0,0 +1,14 @@
+// 4x4 Bayer matrix, translucent materials are opaque but only every few pixels get drawn.
+// The prepass has to discard the same pixels, or their depth would hide what is behind them.
+const float DITHER_THRESHOLDS[16] = float[](
+    0.0, 8.0, 2.0, 10.0,
+    12.0, 4.0, 14.0, 6.0,
+    3.0, 11.0, 1.0, 9.0,
+    15.0, 7.0, 13.0, 5.0
+);
+
+bool isDitheredAway(float opacity) {
+    ivec2 pixel = ivec2(gl_FragCoord.xy) % 4;
+    float threshold = (DITHER_THRESHOLDS[pixel.y * 4 + pixel.x] + 0.5) / 16.0;
+    return opacity < threshold;
+}
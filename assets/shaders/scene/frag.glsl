@@ -3,14 +3,17 @@
 layout(location = 0) in vec3 v_position;
 layout(location = 1) in vec3 v_normal;
 layout(location = 2) in vec2 v_uv;
+layout(location = 3) in vec2 v_uv1;
 
 layout(location = 0) out vec4 f_color;
 
 const float PI = 3.14159265359;
 
 #include "common.glsl"
+#include "dither.glsl"
 
-vec3 ambientLightColor = vec3(1.0, 1.0, 1.0);
+// The decals that were projected onto this pixel, with premultiplied alpha
+layout(input_attachment_index = 0, set = 4, binding = 0) uniform subpassInput decals;
 
 // n: normalized normal
 // l: normalized vector pointing to the light source
@@ -27,13 +30,61 @@ float vectorToDepthValue(vec3 direction) {
     return normalizedZ;
 }
 
-float computeShadowFactor(vec3 l) {
+// Has to match MAX_PCF_SAMPLES
+const vec2 poissonDisk[16] = vec2[](
+    vec2(-0.94201624, -0.39906216),
+    vec2(0.94558609, -0.76890725),
+    vec2(-0.094184101, -0.92938870),
+    vec2(0.34495938, 0.29387760),
+    vec2(-0.91588581, 0.45771432),
+    vec2(-0.81544232, -0.87912464),
+    vec2(-0.38277543, 0.27676845),
+    vec2(0.97484398, 0.75648379),
+    vec2(0.44323325, -0.97511554),
+    vec2(0.53742981, -0.47373420),
+    vec2(-0.26496911, -0.41893023),
+    vec2(0.79197514, 0.19090188),
+    vec2(-0.24188840, 0.99706507),
+    vec2(-0.81409955, 0.91437590),
+    vec2(0.19984126, 0.78641367),
+    vec2(0.14383161, -0.14100790)
+);
+
+// How far the PCF taps are spread out, in shadow map texels
+const float pcfRadius = 1.5;
+
+float sampleShadowMap(vec3 l, float depth, int shadowMapIndex) {
+    if (scene.shadowPcfSamples <= 1) {
+        return texture(shadowMaps[shadowMapIndex], vec4(l, depth)).r;
+    }
+
+    // Spread the taps out on the plane that is perpendicular to the lookup direction
+    vec3 lightDirection = normalize(l);
+    vec3 up = abs(lightDirection.y) < 0.99 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0);
+    vec3 tangent = normalize(cross(lightDirection, up));
+    vec3 bitangent = cross(lightDirection, tangent);
+
+    // A cube map face spans 2 units at a distance of 1 unit
+    vec3 absDirection = abs(l);
+    float localZ = max(absDirection.x, max(absDirection.y, absDirection.z));
+    float texelSize = 2.0 * localZ / float(textureSize(shadowMaps[shadowMapIndex], 0).x);
+
+    float shadow = 0.0;
+    for (int i = 0; i < scene.shadowPcfSamples; ++i) {
+        vec2 offset = poissonDisk[i] * pcfRadius * texelSize;
+        vec3 sampleDirection = l + tangent * offset.x + bitangent * offset.y;
+        shadow += texture(shadowMaps[shadowMapIndex], vec4(sampleDirection, depth)).r;
+    }
+    return shadow / float(scene.shadowPcfSamples);
+}
+
+float computeShadowFactor(vec3 l, int shadowMapIndex) {
     vec3 lightDirection = normalize(l);
     vec3 normal = normalize(v_normal);
 
     float normalLightDot = max(dot(normal, lightDirection),0.00001);
     float bias = max(0.001 * (1.0 - normalLightDot), 0.0);
-    float shadow = texture(shadowMap, vec4(l, vectorToDepthValue(l - lightDirection * bias))).r;
+    float shadow = sampleShadowMap(l, vectorToDepthValue(l - lightDirection * bias), shadowMapIndex);
 
     // When the dot product between light and normal < 0, 
     //   we have an surface pointing away from the light => shadow
@@ -150,6 +201,10 @@ vec3 computeGridColor(vec3 worldPos, float time) {
 }
 
 void main() {
+    if (isDitheredAway(material.opacity)) {
+        discard;
+    }
+
     vec3 worldPos = v_position;
 
     vec3 n = normalize(v_normal);
@@ -157,6 +212,8 @@ void main() {
     vec3 v = normalize(camera.position - worldPos); // world space
 
     vec3 albedo = texture(baseColorTexture, v_uv).rgb * material.baseColor;
+    vec4 decal = subpassLoad(decals);
+    albedo = decal.rgb + albedo * (1.0 - decal.a);
 
     // reflectance at normal incidence (base reflectance)
     // if dia-electric (like plastic) use F0 of 0.04 and if it's a metal, use the albedo as F0 (metallic workflow)
@@ -167,24 +224,34 @@ void main() {
     vec3 Lo = vec3(0.0);
 
     for (int i = 0; i < scene.numLights; ++i) {
-        Lo += pbr(scene.pointLights[i], n, v, worldPos, albedo, f0);
+        PointLight pointLight = scene.pointLights[i];
+        vec3 lightContribution = pbr(pointLight, n, v, worldPos, albedo, f0);
+        if (pointLight.shadowMapIndex >= 0) {
+            lightContribution *= computeShadowFactor(pointLight.position - worldPos, pointLight.shadowMapIndex);
+        }
+        Lo += lightContribution;
     }
 
-    float ka = 0.03;
-    vec3 ambient = (ambientLightColor * ka) * albedo;
+    vec3 ambient = scene.ambientLight * albedo;
+    if (material.hasLightmap != 0) {
+        // The baked global illumination replaces the flat ambient light
+        ambient = texture(lightmapTexture, v_uv1).rgb * albedo;
+    }
 
-    vec3 positionToNearestShadowLight = scene.nearestShadowLight - worldPos;
-    vec3 l = positionToNearestShadowLight;
+    vec3 color = Lo + ambient + material.emissivity;
 
-    vec3 color = Lo * max(computeShadowFactor(l),1-scene.hasShadowLight)  + ambient;
+    if (scene.fogEnd > scene.fogStart) {
+        float fogFactor = smoothstep(scene.fogStart, scene.fogEnd, distance(camera.position, worldPos));
+        color = mix(color, scene.fogColor, fogFactor);
+    }
 
     float gridBlendFactor = min(scene.rewindTime * 0.4, 0.2);
     vec3 gridColor = computeGridColor(worldPos.xyz, scene.rewindTime) * computeGrid(worldPos.xyz, n.xyz);
 
-    f_color = vec4(mix(color + material.emissivity, gridColor, gridBlendFactor), 1.0);
+    f_color = vec4(mix(color, gridColor, gridBlendFactor), 1.0);
 
 // Shadow debugging
-//    f_color = vec4(vec3(1.0) * computeShadowFactor(l), 1.0);
+//    f_color = vec4(vec3(1.0) * computeShadowFactor(scene.pointLights[0].position - worldPos, 0), 1.0);
 
 // Grid debugging
 //    f_color = vec4(gridColor, 1.0);
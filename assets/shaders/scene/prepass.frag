@@ -0,0 +1,19 @@
+#version 450
+
+layout(location = 0) in vec3 v_position;
+layout(location = 1) in vec3 v_normal;
+layout(location = 2) in vec2 v_uv;
+
+layout(location = 0) out vec4 f_normal;
+
+#include "common.glsl"
+#include "dither.glsl"
+
+void main() {
+    if (isDitheredAway(material.opacity)) {
+        discard;
+    }
+
+    // world space normals, and the reflectivity for the screen space reflections
+    f_normal = vec4(normalize(v_normal), material.reflectivity);
+}
@@ -3,16 +3,41 @@
 layout(location = 0) in vec3 position;
 layout(location = 1) in vec3 normal;
 layout(location = 2) in vec2 uv;
+layout(location = 3) in vec2 uv1;
 
 layout(location = 0) out vec3 v_position;
 layout(location = 1) out vec3 v_normal;
 layout(location = 2) out vec2 v_uv;
+layout(location = 3) out vec2 v_uv1;
 
 #include "common.glsl"
 #include "../time_rewinding.glsl"
 
+// Sways the vertex with the level time, which goes backwards while rewinding
+vec3 windDisplacement(vec3 worldPos) {
+    float levelTime = scene.rewindStartTime - scene.rewindTime;
+    // Neighbouring blades of grass shouldn't move in lockstep
+    float phase = levelTime * material.windFrequency + worldPos.x * 0.7 + worldPos.z * 0.3;
+    float height = max(position.y, 0.0);
+    return vec3(sin(phase), 0.0, cos(phase * 0.8)) * material.windAmplitude * height;
+}
+
+// Picks the frame of the flipbook with the level time, which goes backwards while rewinding
+vec2 flipbookUv(vec2 frameUv) {
+    float levelTime = scene.rewindStartTime - scene.rewindTime;
+    // mod instead of %, which isn't defined for negative numbers
+    float frame = mod(floor(levelTime * material.flipbookFps), float(material.flipbookFrames));
+    float columns = float(material.flipbookColumns);
+    float rows = ceil(float(material.flipbookFrames) / columns);
+    vec2 cell = vec2(mod(frame, columns), floor(frame / columns));
+    return (frameUv + cell) / vec2(columns, rows);
+}
+
 void main() {
     vec4 worldPos = entity.model * vec4(position, 1.0); // world space
+    if (material.hasWind != 0) {
+        worldPos.xyz += windDisplacement(worldPos.xyz);
+    }
     worldPos = vec4(timeRewindPosition(worldPos.xyz, camera.position), worldPos.w);
 
     vec3 n = mat3(entity.normalMatrix) * normal; // world space
@@ -22,5 +47,9 @@ void main() {
 
     v_position = worldPos.xyz;
     v_normal = n;
-    v_uv = uv;
+    v_uv = uv + entity.uvOffset;
+    if (material.flipbookFrames > 0) {
+        v_uv = flipbookUv(v_uv);
+    }
+    v_uv1 = uv1;
 }
\ No newline at end of file
@@ -0,0 +1,76 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D image;
+
+layout(push_constant) uniform Pass {
+    // rgb is the color of the flash, a is how strong it is
+    vec4 flash;
+    float exposure;
+    // Projectors are usually very dark, so this gets multiplied with the exposure
+    float brightness;
+    // 0 = ACES, 1 = filmic
+    uint tonemapper;
+} pass;
+
+const float FLASH_EXPOSURE = 3.0;
+
+// Source: https://github.com/Shot511/RapidGL/blob/65d1202a5926acad9816483b141fb24480e81668/src/demos/22_pbr/tmo.frag
+// sRGB => XYZ => D65_2_D60 => AP1 => RRT_SAT
+mat3 ACESInputMatrix = {
+    {0.59719, 0.07600, 0.02840},
+    {0.35458, 0.90834, 0.13383},
+    {0.04823, 0.01566, 0.83777}
+};
+
+// ODT_SAT => XYZ => D60_2_D65 => sRGB
+mat3 ACESOutputMatrix = {
+    { 1.60475, -0.10208, -0.00327},
+    {-0.53108,  1.10813, -0.07276},
+    {-0.07367, -0.00605,  1.07602 }
+};
+
+vec3 RRTAndODTFit(vec3 v) {
+    vec3 a = v * (v + 0.0245786f) - 0.000090537f;
+    vec3 b = v * (0.983729f * v + 0.4329510f) + 0.238081f;
+    return a / b;
+}
+
+vec3 aces(vec3 color) {
+    color = ACESInputMatrix * color;
+    color = RRTAndODTFit(color);
+    return ACESOutputMatrix * color;
+}
+
+// Uncharted 2 filmic curve, see http://filmicworlds.com/blog/filmic-tonemapping-operators/
+vec3 uncharted2Curve(vec3 x) {
+    const float A = 0.15;
+    const float B = 0.50;
+    const float C = 0.10;
+    const float D = 0.20;
+    const float E = 0.02;
+    const float F = 0.30;
+    return ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F;
+}
+
+vec3 filmic(vec3 color) {
+    const float whitePoint = 11.2;
+    return uncharted2Curve(color) / uncharted2Curve(vec3(whitePoint));
+}
+
+void main() {
+    // a flash overexposes the scene and washes it out with its color
+    float exposure = pass.exposure * (1.0 + FLASH_EXPOSURE * pass.flash.a);
+    vec3 color = pass.brightness * exposure * texture(image, v_uv).rgb + pass.flash.rgb * pass.flash.a;
+
+    if (pass.tonemapper == 1) {
+        color = filmic(color);
+    } else {
+        color = aces(color);
+    }
+
+    f_color = vec4(clamp(color, 0.0, 1.0), 1.0);
+}
@@ -3,14 +3,48 @@
 layout(location = 0) out vec4 f_color;
 
 layout(location = 1) in vec2 v_uv;
+layout(location = 2) in vec2 v_fill_coordinate;
+layout(location = 3) flat in float v_fill;
+layout(location = 4) flat in float v_highlight;
+layout(location = 5) flat in float v_opacity;
+layout(location = 6) flat in float v_radial;
 
 layout(set = 0, binding = 0) uniform sampler2D image;
 
+const vec3 highlight_color = vec3(1.0, 0.1, 0.1);
+const float PI = 3.14159265359;
+
+// 4x4 Bayer matrix, for fading components without blending
+const float dither_thresholds[16] = float[](
+    0.0, 8.0, 2.0, 10.0,
+    12.0, 4.0, 14.0, 6.0,
+    3.0, 11.0, 1.0, 9.0,
+    15.0, 7.0, 13.0, 5.0
+);
+
 void main() {
+    if (v_radial > 0.5) {
+        // radial progress bars fill clockwise, starting at the top of the component
+        vec2 direction = v_fill_coordinate - vec2(0.5);
+        float fill_angle = fract(atan(direction.x, -direction.y) / (2.0 * PI) + 1.0);
+        if (fill_angle > v_fill) {
+            discard;
+        }
+    } else if (v_fill_coordinate.y < 1.0 - v_fill) {
+        // progress bars fill up from the bottom of the component
+        discard;
+    }
+
     vec4 image_color = texture(image, v_uv);
     if (image_color.a < 0.5) {
         discard;
     }
 
-    f_color = vec4(image_color.rgb, 1.0);
-}
\ No newline at end of file
+    // The UI relies on the depth test instead of blending, so faded components get dithered
+    ivec2 dither_position = ivec2(gl_FragCoord.xy) % 4;
+    if (v_opacity * 16.0 <= dither_thresholds[dither_position.y * 4 + dither_position.x]) {
+        discard;
+    }
+
+    f_color = vec4(mix(image_color.rgb, highlight_color, v_highlight), 1.0);
+}
@@ -1,16 +1,28 @@
 #version 450
 
-layout(location = 0) in vec2 position;
+// already in clip space
+layout(location = 0) in vec4 position;
 layout(location = 1) in vec2 uv;
+layout(location = 2) in vec2 fill_coordinate;
+layout(location = 3) in float fill;
+layout(location = 4) in float highlight;
+layout(location = 5) in float opacity;
+layout(location = 6) in float radial;
 
 layout(location = 1) out vec2 v_uv;
-
-layout(push_constant) uniform UIComponent {
-    mat4 MVP; 
-} comp;
+layout(location = 2) out vec2 v_fill_coordinate;
+layout(location = 3) flat out float v_fill;
+layout(location = 4) flat out float v_highlight;
+layout(location = 5) flat out float v_opacity;
+layout(location = 6) flat out float v_radial;
 
 void main() {
-    gl_Position = comp.MVP * vec4(position, 0.0, 1.0);
+    gl_Position = position;
 
     v_uv = uv;
-}
\ No newline at end of file
+    v_fill_coordinate = fill_coordinate;
+    v_fill = fill;
+    v_highlight = highlight;
+    v_opacity = opacity;
+    v_radial = radial;
+}
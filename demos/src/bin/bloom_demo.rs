@@ -24,9 +24,14 @@ fn spawn_bloom_demo(mut commands: Commands) {
         id: AssetId::new_v4(),
         base_color: [1.0, 1.0, 1.0].into(),
         base_color_texture: None,
+        lightmap_texture: None,
         roughness_factor: 0.9,
         metallic_factor: 0.1,
         emissivity: Default::default(),
+        reflectivity: 0.0,
+        wind: None,
+        flipbook: None,
+        opacity: 1.0,
     };
 
     let model = Model {
@@ -81,9 +86,14 @@ fn spawn_bloom_demo(mut commands: Commands) {
                         id: AssetId::new_v4(),
                         base_color: Vector3::new(1.0, 1.0, 1.0),
                         base_color_texture: None,
+                        lightmap_texture: None,
                         roughness_factor: 0.9,
                         metallic_factor: 0.1,
                         emissivity: color * intensity,
+                        reflectivity: 0.0,
+                        wind: None,
+                        flipbook: None,
+                        opacity: 1.0,
                     }),
                 }],
             },
@@ -52,9 +52,14 @@ fn spawn_pbr_demo(mut commands: Commands) {
                             id: AssetId::new_v4(),
                             base_color: Vector3::new(1.0, 0.0, 0.0),
                             base_color_texture: None,
+                            lightmap_texture: None,
                             roughness_factor: roughness,
                             metallic_factor: metallic,
                             emissivity: Default::default(),
+                            reflectivity: 0.0,
+                            wind: None,
+                            flipbook: None,
+                            opacity: 1.0,
                         }),
                     }],
                 },
@@ -3,6 +3,7 @@ use bevy_ecs::prelude::{Component, Res, With};
 use bevy_ecs::schedule::IntoSystemConfig;
 use loader::config_loader::LoadableConfig;
 
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Instant;
 
@@ -27,6 +28,7 @@ fn spawn_world(mut commands: Commands, scene_loader: Res<SceneLoader>) {
         .load_default_scene(
             "./assets/scene/demo/shadow_demo/shadow_test.glb",
             &mut commands,
+            &HashMap::new(),
         )
         .unwrap();
     println!(
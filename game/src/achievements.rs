@@ -0,0 +1,217 @@
+use std::collections::{HashSet, VecDeque};
+use std::path::PathBuf;
+
+use app::entity_event::EntityEvent;
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::*;
+use levels::current_level::{CurrentLevel, NextLevel};
+use levels::level_id::LevelId;
+use loader::achievements_loader::{LoadableAchievementCondition, LoadableAchievements};
+use nalgebra::{Point2, Vector2};
+use scene::debug_name::DebugName;
+use scene::interactable::Interacted;
+use scene::ui_component::{UIAnchor, UIComponent, UITexturePosition};
+use scene::ui_text::UIText;
+use scene::ui_tween::UITween;
+use time::time::Time;
+use time::time_manager::TimeManager;
+
+use crate::progress::Progress;
+
+/// In seconds, how long an unlocked achievement stays on the screen
+const TOAST_DURATION: f32 = 4.0;
+
+pub enum AchievementCondition {
+    Rewind,
+    LevelWithoutRewinding(LevelId),
+    /// The name of the object in the level file
+    Interact(String),
+}
+
+impl From<LoadableAchievementCondition> for AchievementCondition {
+    fn from(condition: LoadableAchievementCondition) -> Self {
+        match condition {
+            LoadableAchievementCondition::Rewind => AchievementCondition::Rewind,
+            LoadableAchievementCondition::LevelWithoutRewinding(level_id) => {
+                AchievementCondition::LevelWithoutRewinding(LevelId::new(level_id))
+            }
+            LoadableAchievementCondition::Interact(name) => AchievementCondition::Interact(name),
+        }
+    }
+}
+
+pub struct Achievement {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub condition: AchievementCondition,
+}
+
+/// Unlocks achievements from what happens in the game, they get saved in the [`Progress`]
+#[derive(Resource)]
+pub struct Achievements {
+    achievements: Vec<Achievement>,
+    /// The levels that have been rewound in since they were started
+    rewound_levels: HashSet<LevelId>,
+    /// The indices of the unlocked achievements that haven't been shown yet
+    toasts: VecDeque<usize>,
+    /// In seconds, how long the shown achievement stays on the screen
+    toast_remaining: f32,
+}
+
+impl Achievements {
+    pub fn new(achievements: Vec<Achievement>) -> Self {
+        Self {
+            achievements,
+            rewound_levels: HashSet::new(),
+            toasts: VecDeque::new(),
+            toast_remaining: 0.0,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Achievement> {
+        self.achievements.iter()
+    }
+
+    fn unlock_where(
+        &mut self,
+        progress: &mut Progress,
+        is_met: impl Fn(&AchievementCondition) -> bool,
+    ) {
+        for (index, achievement) in self.achievements.iter().enumerate() {
+            if is_met(&achievement.condition) && progress.unlock_achievement(&achievement.id) {
+                self.toasts.push_back(index);
+            }
+        }
+    }
+}
+
+fn unlock_achievements(
+    mut achievements: ResMut<Achievements>,
+    mut progress: ResMut<Progress>,
+    time_manager: Res<TimeManager>,
+    current_level: Res<CurrentLevel>,
+    mut next_level_events: EventReader<NextLevel>,
+    interactables: Query<(&DebugName, &EntityEvent<Interacted>)>,
+) {
+    for next_level in next_level_events.iter() {
+        let old_level_id = next_level.old_level_id;
+        // The level select can also skip levels
+        let is_finished = next_level.level_id.id() == old_level_id.id() + 1;
+        if is_finished && !achievements.rewound_levels.contains(&old_level_id) {
+            achievements.unlock_where(&mut progress, |condition| {
+                matches!(condition, AchievementCondition::LevelWithoutRewinding(level_id) if *level_id == old_level_id)
+            });
+        }
+        achievements.rewound_levels.remove(&next_level.level_id);
+    }
+
+    if time_manager.is_rewinding() {
+        achievements.rewound_levels.insert(current_level.level_id);
+        achievements.unlock_where(&mut progress, |condition| {
+            matches!(condition, AchievementCondition::Rewind)
+        });
+    }
+
+    for (name, interacted) in interactables.iter() {
+        if interacted.iter().next().is_some() {
+            achievements.unlock_where(&mut progress, |condition| {
+                matches!(condition, AchievementCondition::Interact(other) if other == &name.0)
+            });
+        }
+    }
+}
+
+#[derive(Component)]
+struct UIAchievementToast;
+
+fn spawn_achievement_toast(mut commands: Commands) {
+    let text = UIText::new("");
+    let toast = UIComponent {
+        texture: text.create_texture(),
+        region: None,
+        texture_position: UITexturePosition {
+            scale: Vector2::new(2.0, 2.0),
+            texture_origin: Point2::new(1.0, 0.0),
+            ..UITexturePosition::default()
+        },
+        anchor: UIAnchor::TopRight,
+        // Below the rewind power
+        offset: Vector2::new(-16.0, 96.0),
+        depth: 0.0,
+        opacity: 1.0,
+        visible: false,
+    };
+    commands.spawn((
+        UITween::fade(&toast, 0.3).with_hidden_offset(Vector2::new(400.0, 96.0)),
+        toast,
+        text,
+        UIAchievementToast,
+    ));
+}
+
+/// Shows the unlocked achievements one after the other
+fn update_achievement_toast(
+    mut achievements: ResMut<Achievements>,
+    time: Res<Time>,
+    mut query: Query<(&mut UITween, &mut UIText), With<UIAchievementToast>>,
+) {
+    let (mut tween, mut text) = query.single_mut();
+    achievements.toast_remaining -= time.delta_seconds();
+    if achievements.toast_remaining > 0.0 {
+        return;
+    }
+
+    tween.is_shown = false;
+    // The next one waits until the last one has slid out
+    if !tween.is_finished() {
+        return;
+    }
+    if let Some(index) = achievements.toasts.pop_front() {
+        let achievement = &achievements.achievements[index];
+        text.text = format!(
+            "Achievement unlocked: {} - {}",
+            achievement.title, achievement.description
+        );
+        tween.is_shown = true;
+        achievements.toast_remaining = TOAST_DURATION;
+    }
+}
+
+pub struct AchievementsPlugin {
+    path: PathBuf,
+    /// The directory with the level files, for checking that every achievement can be unlocked
+    levels_directory: PathBuf,
+}
+
+impl AchievementsPlugin {
+    pub fn new(path: impl Into<PathBuf>, levels_directory: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            levels_directory: levels_directory.into(),
+        }
+    }
+}
+
+impl Plugin for AchievementsPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        let loadable_achievements = LoadableAchievements::load(&self.path);
+        loadable_achievements.warn_about_unreachable(&self.levels_directory);
+        let achievements = loadable_achievements
+            .achievements
+            .into_iter()
+            .map(|achievement| Achievement {
+                id: achievement.id,
+                title: achievement.title,
+                description: achievement.description,
+                condition: achievement.condition.into(),
+            })
+            .collect();
+
+        app //
+            .with_resource(Achievements::new(achievements))
+            .with_startup_system(spawn_achievement_toast)
+            .with_system(unlock_achievements)
+            .with_system(update_achievement_toast.after(unlock_achievements));
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::*;
+use levels::level_id::LevelId;
+use loader::loader::Door;
+use nalgebra::Vector3;
+use scene::level::FlagId;
+use scene::light::Light;
+use time::time_manager::{is_rewinding, TimeManager};
+
+use crate::level_flags::LevelFlags;
+
+pub struct Alarm {
+    /// The alarm goes off while this flag of the level is set.
+    /// Rewinding the flag also turns off the alarm.
+    pub trigger: FlagId,
+    /// All lights of the level switch to this color
+    pub color: Vector3<f32>,
+    /// Pulses per second
+    pub pulse_frequency: f32,
+    /// How far the light intensity drops during a pulse, from 0 to 1
+    pub pulse_depth: f32,
+}
+
+impl Alarm {
+    pub fn new(trigger: FlagId) -> Self {
+        Self {
+            trigger,
+            color: Vector3::new(1.0, 0.05, 0.02),
+            pulse_frequency: 1.0,
+            pulse_depth: 0.8,
+        }
+    }
+
+    fn intensity_factor(&self, level_time: f32) -> f32 {
+        let pulse = (level_time * self.pulse_frequency * std::f32::consts::TAU).cos() * 0.5 + 0.5;
+        1.0 - self.pulse_depth * (1.0 - pulse)
+    }
+}
+
+/// The startup systems that pick which flag sets off the alarm of a level.
+/// Since a level can only have one alarm, [`Alarms::add`] never overwrites the alarm of another level.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AlarmsSetup;
+
+/// At most one alarm per level
+#[derive(Resource)]
+pub struct Alarms {
+    alarms: HashMap<LevelId, Alarm>,
+    /// Whether the alarm of a level is going off
+    active: HashMap<LevelId, bool>,
+}
+
+impl Alarms {
+    pub fn new() -> Self {
+        Self {
+            alarms: HashMap::new(),
+            active: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, level_id: LevelId, alarm: Alarm) -> &mut Self {
+        self.alarms.insert(level_id, alarm);
+        self
+    }
+
+    pub fn is_active(&self, level_id: LevelId) -> bool {
+        self.active.get(&level_id).copied().unwrap_or(false)
+    }
+}
+
+/// Sent when an alarm starts going off, for playing the siren
+pub struct AlarmTriggered {
+    pub level_id: LevelId,
+}
+
+fn update_alarms(
+    mut alarms: ResMut<Alarms>,
+    level_flags: Res<LevelFlags>,
+    time_manager: Res<TimeManager>,
+    mut alarm_triggered: EventWriter<AlarmTriggered>,
+) {
+    let alarms = alarms.as_mut();
+    for (level_id, alarm) in alarms.alarms.iter() {
+        let is_active = level_flags.get(*level_id, alarm.trigger);
+        let was_active = alarms.active.insert(*level_id, is_active).unwrap_or(false);
+
+        // Rewinding to before the alarm shouldn't play the siren again
+        if is_active && !was_active && !time_manager.is_rewinding() {
+            alarm_triggered.send(AlarmTriggered {
+                level_id: *level_id,
+            });
+        }
+    }
+}
+
+/// Swaps the lights of a level to the alarm color while it is going off.
+/// The light animations reset the lights every frame, so they go back on their own once the alarm stops.
+fn update_alarm_lights(
+    alarms: Res<Alarms>,
+    time_manager: Res<TimeManager>,
+    mut query: Query<(&mut Light, &LevelId)>,
+) {
+    let level_time = time_manager.level_time().as_secs_f32();
+
+    for (mut light, level_id) in query.iter_mut() {
+        let alarm = match alarms.alarms.get(level_id) {
+            Some(alarm) => alarm,
+            None => continue,
+        };
+        if !alarms.is_active(*level_id) {
+            continue;
+        }
+
+        match light.as_mut() {
+            Light::Point(point_light) => {
+                point_light.color = alarm.color;
+                point_light.intensity *= alarm.intensity_factor(level_time);
+            }
+        }
+    }
+}
+
+/// The door lock is tracked by the door history, so this only runs when playing
+fn lock_alarm_doors(alarms: Res<Alarms>, mut query: Query<(&mut Door, &LevelId)>) {
+    for (mut door, level_id) in query.iter_mut() {
+        if !door.locked_by_alarm {
+            continue;
+        }
+
+        let locked = alarms.is_active(*level_id);
+        if door.locked != locked {
+            door.locked = locked;
+        }
+    }
+}
+
+pub struct AlarmPlugin;
+
+impl Plugin for AlarmPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(Alarms::new())
+            .with_resource(Events::<AlarmTriggered>::default())
+            .with_system(Events::<AlarmTriggered>::update_system.before(update_alarms))
+            .with_system(update_alarms)
+            .with_system(update_alarm_lights.after(update_alarms))
+            .with_system(
+                lock_alarm_doors
+                    .after(update_alarms)
+                    .run_if(not(is_rewinding)),
+            );
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::*;
+use levels::current_level::CurrentLevel;
+use levels::level_id::LevelId;
+use nalgebra::{Point2, Vector2};
+use scene::ui_component::{UIAnchor, UIComponent, UITexturePosition};
+use scene::ui_text::UIText;
+use time::time::Time;
+use time::time_manager::TimeManager;
+
+use crate::alarm::AlarmTriggered;
+use crate::door::DoorState;
+
+/// At most this many captions are on the screen, the oldest one disappears first
+const MAX_CAPTIONS: usize = 3;
+const CAPTION_DURATION: Duration = Duration::from_secs(3);
+/// Height of one line of captions, in pixels
+const CAPTION_LINE_HEIGHT: f32 = 24.0;
+
+/// Describes a sound for deaf players, like "alarm blaring".
+/// Every sound that is important for the puzzles should send one when it gets played.
+pub struct Caption {
+    pub text: String,
+    pub duration: Duration,
+}
+
+impl Caption {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            duration: CAPTION_DURATION,
+        }
+    }
+}
+
+/// The captions that are currently shown, with the newest one at the end
+#[derive(Resource)]
+pub struct Captions {
+    pub enabled: bool,
+    shown: VecDeque<(String, Duration)>,
+}
+
+impl Captions {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            shown: VecDeque::new(),
+        }
+    }
+
+    fn add(&mut self, caption: &Caption) {
+        // The same sound again only keeps its caption on the screen for longer
+        self.shown.retain(|(text, _)| text != &caption.text);
+        self.shown
+            .push_back((caption.text.clone(), caption.duration));
+        while self.shown.len() > MAX_CAPTIONS {
+            self.shown.pop_front();
+        }
+    }
+
+    fn update(&mut self, delta: Duration) {
+        for (_, remaining) in self.shown.iter_mut() {
+            *remaining = remaining.saturating_sub(delta);
+        }
+        self.shown.retain(|(_, remaining)| !remaining.is_zero());
+    }
+}
+
+fn update_captions(
+    mut captions: ResMut<Captions>,
+    mut caption_events: EventReader<Caption>,
+    time: Res<Time>,
+) {
+    captions.update(time.delta());
+    for caption in caption_events.iter() {
+        if captions.enabled {
+            captions.add(caption);
+        }
+    }
+    if !captions.enabled && !captions.shown.is_empty() {
+        captions.shown.clear();
+    }
+}
+
+fn caption_alarms(
+    mut alarm_triggered: EventReader<AlarmTriggered>,
+    current_level: Res<CurrentLevel>,
+    mut caption_events: EventWriter<Caption>,
+) {
+    for event in alarm_triggered.iter() {
+        if event.level_id == current_level.level_id {
+            caption_events.send(Caption::new("[alarm blaring]"));
+        }
+    }
+}
+
+/// Rewinding a door also changes its state, but the door doesn't make any sounds then
+fn caption_doors(
+    query: Query<(Entity, &DoorState, &LevelId), Changed<DoorState>>,
+    current_level: Res<CurrentLevel>,
+    time_manager: Res<TimeManager>,
+    mut previous_states: Local<HashMap<Entity, DoorState>>,
+    mut caption_events: EventWriter<Caption>,
+) {
+    for (entity, door_state, level_id) in query.iter() {
+        let previous_state = previous_states.insert(entity, *door_state);
+        if time_manager.is_rewinding() || level_id != &current_level.level_id {
+            continue;
+        }
+
+        let text = match (previous_state, door_state) {
+            (Some(DoorState::Locked), DoorState::Opening | DoorState::Closed) => "[door unlocks]",
+            (Some(_), DoorState::Locked) => "[door locks]",
+            (Some(_), DoorState::Opening) => "[door opens]",
+            (Some(_), DoorState::Closing) => "[door closes]",
+            _ => continue,
+        };
+        caption_events.send(Caption::new(text));
+    }
+}
+
+#[derive(Component)]
+struct UICaption {
+    /// 0 is the bottom line
+    line: usize,
+}
+
+fn spawn_caption_ui(mut commands: Commands) {
+    for line in 0..MAX_CAPTIONS {
+        let text = UIText::new("");
+        commands.spawn((
+            UIComponent {
+                texture: text.create_texture(),
+                region: None,
+                texture_position: UITexturePosition {
+                    scale: Vector2::new(2.0, 2.0),
+                    texture_origin: Point2::new(0.5, 1.0),
+                    ..UITexturePosition::default()
+                },
+                anchor: UIAnchor::Bottom,
+                offset: Vector2::new(0.0, -64.0 - line as f32 * CAPTION_LINE_HEIGHT),
+                depth: 0.0,
+                opacity: 1.0,
+                visible: false,
+            },
+            text,
+            UICaption { line },
+        ));
+    }
+}
+
+fn update_caption_ui(
+    captions: Res<Captions>,
+    mut query: Query<(&mut UIText, &mut UIComponent, &UICaption)>,
+) {
+    for (mut text, mut ui_component, ui_caption) in query.iter_mut() {
+        let caption = captions
+            .shown
+            .iter()
+            .rev()
+            .nth(ui_caption.line)
+            .map(|(text, _)| text.as_str());
+
+        let visible = caption.is_some();
+        if ui_component.visible != visible {
+            ui_component.visible = visible;
+        }
+        if let Some(caption) = caption {
+            text.set_if_neq(UIText::new(caption));
+        }
+    }
+}
+
+pub struct CaptionsPlugin {
+    enabled: bool,
+}
+
+impl CaptionsPlugin {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+}
+
+impl Plugin for CaptionsPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(Captions::new(self.enabled))
+            .with_resource(Events::<Caption>::default())
+            .with_system(
+                Events::<Caption>::update_system
+                    .before(caption_alarms)
+                    .before(caption_doors),
+            )
+            .with_startup_system(spawn_caption_ui)
+            .with_system(caption_alarms)
+            .with_system(caption_doors)
+            .with_system(update_captions.after(caption_alarms).after(caption_doors))
+            .with_system(update_caption_ui.after(update_captions));
+    }
+}
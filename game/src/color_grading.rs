@@ -0,0 +1,75 @@
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::{Res, ResMut, Resource};
+use levels::current_level::CurrentLevel;
+use loader::lut_loader::load_lut;
+use scene::color_grading::{ColorGrading, ColorLut};
+use std::sync::Arc;
+use time::time::Time;
+use time::time_manager::TimeManager;
+
+use crate::alarm::Alarms;
+
+/// How quickly the color grading shifts towards the blend LUT and back
+const BLEND_SPEED: f32 = 4.0;
+/// The alarm only tints the level, so that the puzzles stay readable
+const ALARM_BLEND: f32 = 0.6;
+
+#[derive(Resource)]
+struct BlendLuts {
+    rewind: Arc<ColorLut>,
+    alarm: Arc<ColorLut>,
+}
+
+/// Cold colors while rewinding, red colors while the alarm of the current level is going off
+fn update_color_grading(
+    time_manager: Res<TimeManager>,
+    alarms: Res<Alarms>,
+    current_level: Res<CurrentLevel>,
+    time: Res<Time>,
+    blend_luts: Res<BlendLuts>,
+    mut color_grading: ResMut<ColorGrading>,
+) {
+    let target = if time_manager.is_rewinding() {
+        Some((&blend_luts.rewind, 1.0))
+    } else if alarms.is_active(current_level.level_id) {
+        Some((&blend_luts.alarm, ALARM_BLEND))
+    } else {
+        None
+    };
+
+    let current_blend_lut = color_grading.blend_lut.clone();
+    let target_blend = match target {
+        Some((lut, blend))
+            if current_blend_lut
+                .as_ref()
+                .map_or(false, |blend_lut| Arc::ptr_eq(blend_lut, lut)) =>
+        {
+            blend
+        }
+        Some((lut, blend)) if color_grading.blend <= 0.0 => {
+            color_grading.blend_lut = Some(lut.clone());
+            blend
+        }
+        // Fades out the old blend LUT before switching to the new one
+        Some(_) | None => 0.0,
+    };
+
+    let factor = (BLEND_SPEED * time.delta_seconds()).min(1.0);
+    color_grading.blend += (target_blend - color_grading.blend) * factor;
+    if target_blend == 0.0 && color_grading.blend < 0.01 {
+        color_grading.blend = 0.0;
+    }
+}
+
+pub struct ColorGradingPlugin;
+
+impl Plugin for ColorGradingPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(BlendLuts {
+                rewind: Arc::new(load_lut("assets/luts/rewind.cube")),
+                alarm: Arc::new(load_lut("assets/luts/alarm.png")),
+            })
+            .with_system(update_color_grading);
+    }
+}
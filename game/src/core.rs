@@ -1,2 +1,9 @@
 pub mod application;
+pub mod clip_planes;
+pub mod fixed_update;
+#[cfg(feature = "dev-tools")]
+pub mod inspector;
+pub mod material_change;
+pub mod rewind_fov;
+pub mod rewind_ghosts;
 pub mod transform_change;
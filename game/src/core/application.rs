@@ -1,4 +1,5 @@
 use animations::animation::AnimationPlugin;
+use animations::light_animation::LightAnimationPlugin;
 use app::plugin::Plugin;
 use app::App;
 use input::plugin::InputPlugin;
@@ -9,41 +10,97 @@ use time::time::{Time, TimePlugin, TimePluginSet};
 use windowing::window::{EventLoopContainer, WindowPlugin};
 
 use crate::pickup_system::PickupPlugin;
+use crate::prefabs::{crate_prefab, heavy_crate_prefab};
 use crate::player::{PlayerPlugin, PlayerPluginSets};
+use crate::ui_interaction::UICursor;
 use angle::Deg;
 use bevy_ecs::prelude::*;
-use input::events::{KeyboardInput, MouseInput, MouseMovement};
+use input::events::{CursorMoved, KeyboardInput, MouseInput, MouseMovement, MouseWheel};
 use loader::loader::SceneLoader;
+use loader::name_registry::{update_name_registry, NameRegistry};
 use nalgebra::{Point3, UnitQuaternion};
 use render::context::Context;
-use render::{Renderer, RendererPlugin, RendererPluginSets, ViewFrustumCullingMode};
+use render::{
+    BloomSettings, DepthPrepassMode, DeviceSelection, OcclusionCullingMode, Renderer,
+    RendererPlugin, RendererPluginSets, ShadowSettings, ViewFrustumCullingMode,
+};
 use scene::camera::{update_camera, Camera};
-use windowing::config::WindowConfig;
+use scene::debug_lines::{clear_debug_lines, DebugLines};
+use scene::hierarchy::{detach_orphaned_children, propagate_transforms};
+use scene::material::apply_material_overrides;
+use scene::ui_text::update_ui_text;
+use windowing::config::{FullscreenMode, WindowConfig};
 use windowing::dpi::PhysicalSize;
 use windowing::event::{
-    DeviceEvent, Event, KeyboardInput as KeyboardInputWinit, VirtualKeyCode, WindowEvent,
+    DeviceEvent, Event, KeyboardInput as KeyboardInputWinit, MouseScrollDelta, VirtualKeyCode,
+    WindowEvent,
 };
 use windowing::event_loop::ControlFlow;
 use windowing::events::{WindowFocusChanged, WindowResize};
 
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use windowing::window::CursorGrabMode;
 
+use crate::core::material_change::{
+    material_override_rewind, material_override_start_track, material_override_track,
+    MaterialChange,
+};
 use crate::core::transform_change::{
-    time_manager_rewind_transform, time_manager_track_transform, TransformChange,
+    time_manager_rewind_target_transform, time_manager_rewind_transform,
+    time_manager_track_released_rewind_target, time_manager_track_transform, TransformChange,
 };
+use time::game_rng::GameRngPlugin;
 use time::time_manager::game_change::GameChangeHistoryPlugin;
-use time::time_manager::{TimeManagerPlugin, TimeManagerPluginSet};
+use time::fixed_time::FixedTime;
+use time::time_manager::{is_time_frozen, TimeManager, TimeManagerPlugin, TimeManagerPluginSet};
 use windowing::event::ElementState::Released;
-use windowing::event::VirtualKeyCode::F8;
-
+use windowing::event::VirtualKeyCode::{F11, F12, F6, F7, F8, F9};
+
+use super::clip_planes::ClipPlanesPlugin;
+#[cfg(feature = "dev-tools")]
+use super::inspector::{handle_inspector_event, is_inspector_visible, InspectorPlugin};
+use super::fixed_update::{
+    is_render_step, is_update_step, FrameStep, TransformInterpolationPlugin,
+    TransformInterpolationPluginSets,
+};
+use super::rewind_fov::RewindFovPlugin;
+use super::rewind_ghosts::RewindGhostsPlugin;
 use super::transform_change::time_manager_start_track_transform;
 
+/// How often the game logic and the physics get updated per second
+const FIXED_UPDATE_RATE: u32 = 60;
+const CAPTURE_FRAME_RATE: u32 = 60;
+
 pub struct AppConfig {
     pub window: WindowConfig,
     /// Projectors are usually very dark, this parameter should control how bright your total
     /// scene is, e.g., an illumination multiplier
     pub brightness: f32,
     pub mouse_sensitivity: f32,
+    pub camera_smoothing: bool,
+    pub mouse_acceleration: f32,
+    pub invert_y: bool,
+    pub toggle_rewind: bool,
+    pub captions: bool,
+    /// head-bob and edge tilt
+    pub camera_effects: bool,
+    pub speedrun_timer: bool,
+    pub record_replays: bool,
+    pub telemetry: bool,
+    pub fov: Deg<f32>,
+    pub near_plane: f32,
+    pub far_plane: f32,
+    pub bloom_intensity: f32,
+    pub shadow_settings: ShadowSettings,
+    pub frames_in_flight: u32,
+    pub low_latency_mode: bool,
+    /// in megabytes, None uses all of the GPU memory
+    pub gpu_memory_budget: Option<u32>,
+    pub gpu: DeviceSelection,
+    pub render_scale: f32,
+    pub dynamic_resolution: bool,
+    pub target_frame_rate: f32,
+    pub rng_seed: u64,
 }
 
 impl From<LoadableConfig> for AppConfig {
@@ -51,11 +108,44 @@ impl From<LoadableConfig> for AppConfig {
         Self {
             window: WindowConfig {
                 resolution: config.resolution,
-                fullscreen: config.fullscreen,
+                fullscreen: match (config.fullscreen, config.borderless_fullscreen) {
+                    (false, _) => FullscreenMode::Windowed,
+                    (true, true) => FullscreenMode::Borderless,
+                    (true, false) => FullscreenMode::Exclusive,
+                },
+                monitor: config.monitor,
                 refresh_rate: config.refresh_rate,
             },
             brightness: config.brightness,
             mouse_sensitivity: config.mouse_sensitivity,
+            camera_smoothing: config.camera_smoothing,
+            mouse_acceleration: config.mouse_acceleration,
+            invert_y: config.invert_y,
+            toggle_rewind: config.toggle_rewind,
+            captions: config.captions,
+            camera_effects: config.camera_effects,
+            speedrun_timer: config.speedrun_timer,
+            record_replays: config.record_replays,
+            telemetry: config.telemetry,
+            fov: Deg(config.fov),
+            near_plane: config.near_plane,
+            far_plane: config.far_plane,
+            bloom_intensity: config.bloom_intensity,
+            shadow_settings: ShadowSettings::new(
+                config.shadow_resolution,
+                config.shadow_pcf_samples,
+            ),
+            frames_in_flight: config.frames_in_flight,
+            low_latency_mode: config.low_latency_mode,
+            gpu_memory_budget: match config.gpu_memory_budget_mb {
+                0 => None,
+                megabytes => Some(megabytes),
+            },
+            gpu: DeviceSelection::parse(&config.gpu),
+            render_scale: config.render_scale,
+            dynamic_resolution: config.dynamic_resolution,
+            target_frame_rate: config.target_frame_rate,
+            rng_seed: config.rng_seed,
         }
     }
 }
@@ -77,9 +167,13 @@ pub enum AppStage {
     EndFrame,
 }
 
+/// Closes the game at the end of the frame
+pub struct AppExit;
+
 pub struct Application {
     config: AppConfig,
     pub app: App,
+    exit_on_escape: bool,
 }
 
 impl Application {
@@ -100,9 +194,40 @@ impl Application {
                 .chain(),
         );
 
+        // Everything up to the rendering runs once for every fixed step
+        app.world.insert_resource(FrameStep {
+            update: true,
+            render: true,
+        });
+        for stage in [
+            AppStage::StartFrame,
+            AppStage::EventUpdate,
+            AppStage::BeforeUpdate,
+            AppStage::Update,
+            AppStage::UpdateLevel,
+            AppStage::UpdatePhysics,
+            AppStage::BeforeRender,
+        ] {
+            app.schedule.configure_set(stage.run_if(is_update_step));
+        }
+        for stage in [AppStage::Render, AppStage::EndFrame] {
+            app.schedule.configure_set(stage.run_if(is_render_step));
+        }
+
         Self::add_default_plugins(&mut app, &config);
+        #[cfg(feature = "dev-tools")]
+        Self::add_dev_tools(&mut app);
 
-        Self { config, app }
+        Self {
+            config,
+            app,
+            exit_on_escape: true,
+        }
+    }
+
+    /// For games with a menu, which then quits with an [`AppExit`] event instead
+    pub fn disable_exit_on_escape(&mut self) {
+        self.exit_on_escape = false;
     }
 
     fn add_default_plugins(app: &mut App, config: &AppConfig) {
@@ -118,16 +243,28 @@ impl Application {
                     .after(TimePluginSet::UpdateTime)
                     .after(LevelsPlugin::system_set()),
             )
+            .with_plugin(GameRngPlugin::new(config.rng_seed))
+            // Records the random numbers after everything used them
+            .with_set(GameRngPlugin::system_set().in_set(AppStage::BeforeRender))
             .with_plugin(InputPlugin)
             .with_set(InputPlugin::system_set().in_set(AppStage::EventUpdate))
             .with_plugin(AnimationPlugin)
             .with_set(
                 AnimationPlugin::system_set()
                     .after(AppStage::UpdateLevel)
-                    .before(AppStage::UpdatePhysics),
+                    .before(AppStage::UpdatePhysics)
+                    .run_if(is_update_step)
+                    .run_if(not(is_time_frozen)),
             )
+            .with_plugin(LightAnimationPlugin)
+            .with_set(LightAnimationPlugin::system_set().in_set(AppStage::BeforeUpdate))
             .with_plugin(PhysicsPlugin)
-            .with_set(PhysicsPlugin::system_set().in_set(AppStage::UpdatePhysics))
+            // A frozen world doesn't fall down
+            .with_set(
+                PhysicsPlugin::system_set()
+                    .in_set(AppStage::UpdatePhysics)
+                    .run_if(not(is_time_frozen)),
+            )
             // Transform tracking
             .with_plugin(
                 GameChangeHistoryPlugin::<TransformChange>::new()
@@ -135,17 +272,76 @@ impl Application {
                     .with_tracker(
                         time_manager_track_transform.after(time_manager_start_track_transform),
                     )
+                    // Rewinding single objects happens while the rest of the world is tracked
+                    .with_tracker(time_manager_rewind_target_transform)
+                    .with_tracker(
+                        time_manager_track_released_rewind_target
+                            .after(time_manager_track_transform),
+                    )
                     .with_rewinder(time_manager_rewind_transform),
             )
             .with_set(
                 GameChangeHistoryPlugin::<TransformChange>::system_set()
                     .after(AppStage::UpdateLevel)
                     .after(AnimationPlugin::system_set())
+                    .before(AppStage::UpdatePhysics)
+                    .run_if(is_update_step),
+            )
+            // Material tracking
+            .with_plugin(
+                GameChangeHistoryPlugin::<MaterialChange>::new()
+                    .with_tracker(material_override_start_track)
+                    .with_tracker(material_override_track.after(material_override_start_track))
+                    .with_rewinder(material_override_rewind),
+            )
+            .with_set(
+                GameChangeHistoryPlugin::<MaterialChange>::system_set()
+                    .after(AppStage::UpdateLevel)
                     .before(AppStage::UpdatePhysics),
             )
             .with_plugin(WindowPlugin::new(config.window.clone()))
-            .with_plugin(RendererPlugin::new(config.brightness))
+            .with_plugin(
+                RendererPlugin::new(config.brightness)
+                    .with_bloom_settings(BloomSettings {
+                        intensity: config.bloom_intensity,
+                        ..BloomSettings::default()
+                    })
+                    .with_shadow_settings(config.shadow_settings.clone())
+                    .with_frames_in_flight(config.frames_in_flight)
+                    .with_low_latency_mode(config.low_latency_mode)
+                    .with_device_selection(config.gpu.clone())
+                    .with_render_scale(config.render_scale)
+                    .with_dynamic_resolution(config.dynamic_resolution, config.target_frame_rate)
+                    .with_memory_budget(
+                        config
+                            .gpu_memory_budget
+                            .map(|megabytes| megabytes as u64 * 1024 * 1024),
+                    ),
+            )
             .with_set(RendererPluginSets::Render.in_set(AppStage::Render))
+            .with_set(RendererPluginSets::DebugLines.in_set(AppStage::BeforeRender))
+            .with_plugin(TransformInterpolationPlugin)
+            .with_set(TransformInterpolationPluginSets::StorePrevious.in_set(AppStage::StartFrame))
+            .with_set(
+                TransformInterpolationPluginSets::Interpolate
+                    .in_set(AppStage::Render)
+                    .before(RendererPluginSets::Render),
+            )
+            .with_set(TransformInterpolationPluginSets::Restore.in_set(AppStage::EndFrame))
+            .with_plugin(RewindFovPlugin::new(config.fov))
+            .with_set(
+                RewindFovPlugin::system_set()
+                    .in_set(AppStage::BeforeRender)
+                    .before(update_camera),
+            )
+            .with_plugin(ClipPlanesPlugin::new(config.near_plane, config.far_plane))
+            .with_set(
+                ClipPlanesPlugin::system_set()
+                    .in_set(AppStage::BeforeRender)
+                    .before(update_camera),
+            )
+            .with_plugin(RewindGhostsPlugin)
+            .with_set(RewindGhostsPlugin::system_set().in_set(AppStage::BeforeRender))
             // Configuring the player plugin (but not adding it)
             .with_set(PlayerPluginSets::UpdateInput.in_set(AppStage::BeforeUpdate))
             .with_set(PlayerPluginSets::Update.in_set(AppStage::BeforeUpdate))
@@ -157,6 +353,17 @@ impl Application {
             );
     }
 
+    #[cfg(feature = "dev-tools")]
+    fn add_dev_tools(app: &mut App) {
+        app //
+            .with_plugin(InspectorPlugin)
+            .with_set(
+                InspectorPlugin::system_set()
+                    .in_set(AppStage::Render)
+                    .before(TransformInterpolationPluginSets::Interpolate),
+            );
+    }
+
     pub fn run(mut self)
     where
         Self: 'static,
@@ -169,16 +376,31 @@ impl Application {
 
         let aspect_ratio = config.window.resolution.0 as f32 / config.window.resolution.1 as f32;
 
-        let scene_loader = SceneLoader::new();
+        let scene_loader = SceneLoader::new()
+            .with_prefab("crate", crate_prefab())
+            .with_prefab("heavy_crate", heavy_crate_prefab());
         world.insert_resource(scene_loader);
+        world.insert_resource(NameRegistry::default());
+        schedule.add_system(update_name_registry.in_set(AppStage::StartFrame));
+        // After the animations and the physics moved the parents, and before the camera follows the player
+        schedule.add_system(
+            propagate_transforms
+                .in_set(AppStage::BeforeRender)
+                .before(PlayerPluginSets::UpdateCamera),
+        );
+        schedule.add_system(
+            detach_orphaned_children
+                .in_set(AppStage::BeforeRender)
+                .before(propagate_transforms),
+        );
 
         let camera = Camera::new(
             Point3::origin(), // Note: The player updates this
             UnitQuaternion::identity(),
             aspect_ratio,
-            Deg(60.0),
-            0.01,
-            100.0,
+            config.fov,
+            config.near_plane,
+            config.far_plane,
         );
         schedule.add_system(
             update_camera_aspect_ratio
@@ -195,6 +417,9 @@ impl Application {
         world.insert_resource(Events::<WindowResize>::default());
         schedule.add_system(Events::<WindowResize>::update_system.in_set(AppStage::EventUpdate));
 
+        world.insert_resource(Events::<AppExit>::default());
+        schedule.add_system(Events::<AppExit>::update_system.in_set(AppStage::EventUpdate));
+
         world.insert_resource(Events::<WindowFocusChanged>::default());
         schedule
             .add_system(Events::<WindowFocusChanged>::update_system.in_set(AppStage::EventUpdate));
@@ -202,10 +427,26 @@ impl Application {
         schedule.add_system(lock_mouse.in_set(AppStage::BeforeUpdate));
 
         schedule.add_system(update_view_frustum_culling_enabled.in_set(AppStage::BeforeUpdate));
+        schedule.add_system(update_occlusion_culling_enabled.in_set(AppStage::BeforeUpdate));
+        schedule.add_system(update_depth_prepass_enabled.in_set(AppStage::BeforeUpdate));
+        schedule.add_system(update_debug_lines_enabled.in_set(AppStage::BeforeUpdate));
+        schedule.add_system(take_screenshot.in_set(AppStage::BeforeUpdate));
+        schedule.add_system(toggle_frame_capture.in_set(AppStage::BeforeUpdate));
+        schedule.add_system(clear_debug_lines.in_set(AppStage::StartFrame));
+        schedule.add_system(update_ui_text.in_set(AppStage::BeforeRender));
+        schedule.add_system(apply_material_overrides.in_set(AppStage::BeforeRender));
 
         self.app.run_startup();
+        // The game logic always advances by exactly one fixed step
+        let fixed_time = FixedTime::from_rate(FIXED_UPDATE_RATE);
+        self.app
+            .world
+            .get_resource_mut::<Time>()
+            .unwrap()
+            .set_fixed_delta(Some(fixed_time.timestep()));
         // Reset time after startup
         self.app.world.get_resource_mut::<Time>().unwrap().update();
+        self.app.world.insert_resource(fixed_time);
 
         self.app
             .world
@@ -233,34 +474,55 @@ impl Application {
                         .recreate_swapchain();
                 }
 
-                Event::WindowEvent { event, .. } => match event {
-                    WindowEvent::KeyboardInput {
-                        input:
-                            KeyboardInputWinit {
-                                virtual_keycode: Some(key_code),
-                                state,
-                                ..
-                            },
-                        ..
-                    } => {
-                        if key_code == VirtualKeyCode::Escape {
-                            *control_flow = ControlFlow::Exit;
-                        }
-
-                        self.app.world.send_event(KeyboardInput { key_code, state });
+                Event::WindowEvent { event, .. } => {
+                    #[cfg(feature = "dev-tools")]
+                    if handle_inspector_event(&mut self.app.world, &event) {
+                        return;
                     }
-                    WindowEvent::MouseInput { button, state, .. } => {
-                        self.app.world.send_event(MouseInput { button, state });
-                    }
-                    WindowEvent::Focused(focused) => {
-                        self.app
-                            .world
-                            .send_event(WindowFocusChanged { has_focus: focused });
+
+                    match event {
+                        WindowEvent::KeyboardInput {
+                            input:
+                                KeyboardInputWinit {
+                                    virtual_keycode: Some(key_code),
+                                    state,
+                                    ..
+                                },
+                            ..
+                        } => {
+                            if key_code == VirtualKeyCode::Escape && self.exit_on_escape {
+                                *control_flow = ControlFlow::Exit;
+                            }
+
+                            self.app.world.send_event(KeyboardInput { key_code, state });
+                        }
+                        WindowEvent::MouseInput { button, state, .. } => {
+                            self.app.world.send_event(MouseInput { button, state });
+                        }
+                        WindowEvent::CursorMoved { position, .. } => {
+                            self.app
+                                .world
+                                .send_event(CursorMoved(position.x, position.y));
+                        }
+                        WindowEvent::MouseWheel { delta, .. } => {
+                            let lines = match delta {
+                                MouseScrollDelta::LineDelta(_, y) => y,
+                                // Touchpads scroll in pixels, a line is roughly this high
+                                MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.0,
+                            };
+                            self.app.world.send_event(MouseWheel(lines));
+                        }
+                        WindowEvent::Focused(focused) => {
+                            self.app.world.send_event(WindowFocusChanged { has_focus: focused });
+                        }
+                        _ => (),
                     }
-                    _ => (),
-                },
+                }
 
                 Event::DeviceEvent { event, .. } => match event {
+                    // The camera should not move while the inspector gets used
+                    #[cfg(feature = "dev-tools")]
+                    DeviceEvent::MouseMotion { .. } if is_inspector_visible(&self.app.world) => {}
                     DeviceEvent::MouseMotion { delta: (dx, dy) } => {
                         self.app.world.send_event(MouseMovement(dx, dy))
                     }
@@ -268,8 +530,26 @@ impl Application {
                 },
 
                 Event::RedrawEventsCleared => {
-                    self.app.schedule.run(&mut self.app.world);
-                    self.app.world.clear_trackers(); // Needs to be called for "RemovedComponents" to work properly
+                    let time_scale = self.app.world.resource::<TimeManager>().time_scale();
+                    let steps = self
+                        .app
+                        .world
+                        .resource_mut::<FixedTime>()
+                        .start_frame(time_scale);
+                    // A frame without any fixed steps still gets rendered
+                    let runs = steps.max(1);
+                    for run in 0..runs {
+                        self.app.world.insert_resource(FrameStep {
+                            update: steps > 0,
+                            render: run + 1 == runs,
+                        });
+                        self.app.schedule.run(&mut self.app.world);
+                        self.app.world.clear_trackers(); // Needs to be called for "RemovedComponents" to work properly
+                    }
+
+                    if !self.app.world.resource::<Events<AppExit>>().is_empty() {
+                        *control_flow = ControlFlow::Exit;
+                    }
                 }
 
                 _ => (),
@@ -277,13 +557,21 @@ impl Application {
     }
 }
 
-fn lock_mouse(context: NonSend<Context>, mut event: EventReader<WindowFocusChanged>) {
+fn lock_mouse(
+    context: NonSend<Context>,
+    ui_cursor: Option<Res<UICursor>>,
+    mut event: EventReader<WindowFocusChanged>,
+) {
+    // The menus need the cursor
+    let is_cursor_free = ui_cursor
+        .map(|ui_cursor| ui_cursor.is_free)
+        .unwrap_or(false);
     for WindowFocusChanged { has_focus } in event.into_iter() {
         let window = context.window();
 
         // TODO: Don't aggressively grab the cursor, instead only grab it when the user actually clicked on the window
 
-        if *has_focus {
+        if *has_focus && !is_cursor_free {
             window
                 .set_cursor_grab(CursorGrabMode::Confined)
                 .or_else(|_e| window.set_cursor_grab(CursorGrabMode::Locked))
@@ -307,6 +595,82 @@ fn update_view_frustum_culling_enabled(
     }
 }
 
+fn update_occlusion_culling_enabled(
+    mut occlusion_culling_mode: ResMut<OcclusionCullingMode>,
+    mut event_reader: EventReader<KeyboardInput>,
+) {
+    for event in event_reader.iter() {
+        if event.key_code == F7 && event.state == Released {
+            occlusion_culling_mode.enabled = !occlusion_culling_mode.enabled;
+        }
+    }
+}
+
+fn update_depth_prepass_enabled(
+    mut depth_prepass_mode: ResMut<DepthPrepassMode>,
+    mut event_reader: EventReader<KeyboardInput>,
+) {
+    for event in event_reader.iter() {
+        if event.key_code == F9 && event.state == Released {
+            depth_prepass_mode.enabled = !depth_prepass_mode.enabled;
+        }
+    }
+}
+
+fn update_debug_lines_enabled(
+    mut debug_lines: ResMut<DebugLines>,
+    mut event_reader: EventReader<KeyboardInput>,
+) {
+    for event in event_reader.iter() {
+        if event.key_code == F6 && event.state == Released {
+            debug_lines.enabled = !debug_lines.enabled;
+        }
+    }
+}
+
+fn take_screenshot(
+    mut renderer: NonSendMut<Renderer>,
+    mut event_reader: EventReader<KeyboardInput>,
+) {
+    for event in event_reader.iter() {
+        if event.key_code == F12 && event.state == Released {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis();
+            renderer.capture_screenshot(format!("screenshots/screenshot_{timestamp}.png"));
+        }
+    }
+}
+
+/// Records every frame with a fixed timestep, so that videos are smooth regardless of the GPU
+fn toggle_frame_capture(
+    mut renderer: NonSendMut<Renderer>,
+    mut fixed_time: ResMut<FixedTime>,
+    mut event_reader: EventReader<KeyboardInput>,
+) {
+    for event in event_reader.iter() {
+        if event.key_code == F11 && event.state == Released {
+            if renderer.is_capturing_frames() {
+                let frame_count = renderer.stop_frame_capture();
+                fixed_time.set_fixed_frame_delta(None);
+                println!(
+                    "Captured {frame_count} frames, turn them into a video with \
+                    ffmpeg -framerate {CAPTURE_FRAME_RATE} -i frame_%06d.png -pix_fmt yuv420p video.mp4"
+                );
+            } else {
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_millis();
+                renderer.start_frame_capture(format!("captures/capture_{timestamp}"));
+                let frame_duration = Duration::from_secs_f64(1.0 / CAPTURE_FRAME_RATE as f64);
+                fixed_time.set_fixed_frame_delta(Some(frame_duration));
+            }
+        }
+    }
+}
+
 fn update_camera_aspect_ratio(mut camera: ResMut<Camera>, mut reader: EventReader<WindowResize>) {
     for event in reader.iter() {
         camera.update_aspect_ratio(event.width as f32 / event.height as f32);
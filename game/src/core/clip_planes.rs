@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::{Res, ResMut, Resource};
+use levels::current_level::CurrentLevel;
+use levels::level_id::LevelId;
+use scene::camera::Camera;
+
+/// The near and far plane of the camera.
+/// Levels can override them, for example when they are larger than the default far plane.
+#[derive(Resource)]
+pub struct ClipPlanes {
+    pub near: f32,
+    pub far: f32,
+    levels: HashMap<LevelId, (f32, f32)>,
+}
+
+impl ClipPlanes {
+    pub fn set_for_level(&mut self, level_id: LevelId, near: f32, far: f32) -> &mut Self {
+        self.levels.insert(level_id, (near, far));
+        self
+    }
+
+    pub fn reset_for_level(&mut self, level_id: LevelId) -> &mut Self {
+        self.levels.remove(&level_id);
+        self
+    }
+
+    pub fn get(&self, level_id: LevelId) -> (f32, f32) {
+        self.levels
+            .get(&level_id)
+            .copied()
+            .unwrap_or((self.near, self.far))
+    }
+}
+
+fn update_clip_planes(
+    clip_planes: Res<ClipPlanes>,
+    current_level: Res<CurrentLevel>,
+    mut camera: ResMut<Camera>,
+) {
+    let (near, far) = clip_planes.get(current_level.level_id);
+    if camera.near() != near || camera.far() != far {
+        camera.set_clip_planes(near, far);
+    }
+}
+
+pub struct ClipPlanesPlugin {
+    near: f32,
+    far: f32,
+}
+
+impl ClipPlanesPlugin {
+    pub fn new(near: f32, far: f32) -> Self {
+        Self { near, far }
+    }
+}
+
+impl Plugin for ClipPlanesPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(ClipPlanes {
+                near: self.near,
+                far: self.far,
+                levels: HashMap::new(),
+            })
+            .with_system(update_clip_planes);
+    }
+}
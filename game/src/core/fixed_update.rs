@@ -0,0 +1,136 @@
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::change_detection::DetectChangesMut;
+use bevy_ecs::prelude::{Entity, Query, Res, ResMut, Resource};
+use bevy_ecs::schedule::{IntoSystemConfig, SystemSet};
+use nalgebra::{Point3, UnitQuaternion};
+use scene::camera::Camera;
+use scene::transform::Transform;
+use std::collections::HashMap;
+use time::fixed_time::FixedTime;
+
+/// The schedule runs once for every fixed step, and the last run of a frame also renders.
+/// A frame without any fixed steps only renders.
+#[derive(Resource)]
+pub struct FrameStep {
+    pub update: bool,
+    pub render: bool,
+}
+
+pub fn is_update_step(frame_step: Res<FrameStep>) -> bool {
+    frame_step.update
+}
+
+pub fn is_render_step(frame_step: Res<FrameStep>) -> bool {
+    frame_step.render
+}
+
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum TransformInterpolationPluginSets {
+    /// at the start of every fixed step
+    StorePrevious,
+    /// before rendering
+    Interpolate,
+    /// after rendering
+    Restore,
+}
+
+/// The game logic runs at a fixed rate, so rendering interpolates the transforms and the camera
+/// between the last two fixed steps
+#[derive(Resource, Default)]
+struct TransformInterpolation {
+    previous: HashMap<Entity, Transform>,
+    simulated: HashMap<Entity, Transform>,
+    previous_camera: Option<(Point3<f32>, UnitQuaternion<f32>)>,
+    simulated_camera: Option<(Point3<f32>, UnitQuaternion<f32>)>,
+}
+
+fn store_previous_transforms(
+    mut interpolation: ResMut<TransformInterpolation>,
+    camera: Res<Camera>,
+    query: Query<(Entity, &Transform)>,
+) {
+    interpolation.previous.clear();
+    for (entity, transform) in &query {
+        interpolation.previous.insert(entity, transform.clone());
+    }
+    interpolation.previous_camera = Some((camera.position, camera.orientation));
+}
+
+fn interpolate_transforms(
+    mut interpolation: ResMut<TransformInterpolation>,
+    fixed_time: Res<FixedTime>,
+    mut camera: ResMut<Camera>,
+    mut query: Query<(Entity, &mut Transform)>,
+) {
+    let interpolation = interpolation.as_mut();
+    let factor = fixed_time.overstep_fraction();
+
+    // Bypassing the change detection, since the transforms get restored right after rendering
+    for (entity, mut transform) in &mut query {
+        let previous = match interpolation.previous.get(&entity) {
+            Some(previous) if previous != transform.as_ref() => previous,
+            _ => continue,
+        };
+
+        interpolation.simulated.insert(entity, transform.clone());
+        let transform = transform.bypass_change_detection();
+        transform.position = previous
+            .position
+            .coords
+            .lerp(&transform.position.coords, factor)
+            .into();
+        transform.rotation = slerp(&previous.rotation, &transform.rotation, factor);
+        transform.scale = previous.scale.lerp(&transform.scale, factor);
+    }
+
+    if let Some((previous_position, previous_orientation)) = interpolation.previous_camera {
+        let camera = camera.bypass_change_detection();
+        interpolation.simulated_camera = Some((camera.position, camera.orientation));
+        camera.position = previous_position
+            .coords
+            .lerp(&camera.position.coords, factor)
+            .into();
+        camera.orientation = slerp(&previous_orientation, &camera.orientation, factor);
+        camera.update();
+    }
+}
+
+/// Falls back to the target rotation when the two rotations are exactly opposite
+fn slerp(from: &UnitQuaternion<f32>, to: &UnitQuaternion<f32>, factor: f32) -> UnitQuaternion<f32> {
+    from.try_slerp(to, factor, f32::EPSILON).unwrap_or(*to)
+}
+
+fn restore_transforms(
+    mut interpolation: ResMut<TransformInterpolation>,
+    mut camera: ResMut<Camera>,
+    mut query: Query<&mut Transform>,
+) {
+    for (entity, simulated) in interpolation.simulated.drain() {
+        if let Ok(mut transform) = query.get_mut(entity) {
+            *transform.bypass_change_detection() = simulated;
+        }
+    }
+
+    if let Some((position, orientation)) = interpolation.simulated_camera.take() {
+        let camera = camera.bypass_change_detection();
+        camera.position = position;
+        camera.orientation = orientation;
+        camera.update();
+    }
+}
+
+pub struct TransformInterpolationPlugin;
+
+impl Plugin for TransformInterpolationPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(TransformInterpolation::default())
+            .with_system(
+                store_previous_transforms.in_set(TransformInterpolationPluginSets::StorePrevious),
+            )
+            .with_system(
+                interpolate_transforms.in_set(TransformInterpolationPluginSets::Interpolate),
+            )
+            .with_system(restore_transforms.in_set(TransformInterpolationPluginSets::Restore));
+    }
+}
@@ -0,0 +1,268 @@
+use crate::level_flags::LevelFlags;
+use crate::rewind_power::RewindPower;
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::{Entity, EventReader, Local, Query, Res};
+use bevy_ecs::schedule::IntoSystemConfig;
+use bevy_ecs::system::{NonSend, NonSendMut};
+use bevy_ecs::world::{Mut, World};
+use input::events::KeyboardInput;
+use levels::level_id::LevelId;
+use nalgebra::{Point3, UnitQuaternion, Vector3};
+use render::context::Context;
+use render::dev_gui::{egui, DevGui};
+use render::{GpuMemoryUsage, MemoryCategory};
+use scene::debug_name::DebugName;
+use scene::material::CpuMaterial;
+use scene::model::Model;
+use scene::transform::Transform;
+use std::sync::Arc;
+use windowing::event::ElementState::Released;
+use windowing::event::VirtualKeyCode::F10;
+use windowing::event::WindowEvent;
+use windowing::window::CursorGrabMode;
+
+/// Lists all entities and some resources, and allows editing them while the game is running
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_system(toggle_inspector)
+            .with_system(inspector_ui.after(toggle_inspector));
+    }
+}
+
+/// Returns true if the inspector consumed the event, so that the game should ignore it
+pub fn handle_inspector_event(world: &mut World, event: &WindowEvent) -> bool {
+    world
+        .get_non_send_resource_mut::<DevGui>()
+        .map(|mut dev_gui| dev_gui.handle_event(event))
+        .unwrap_or(false)
+}
+
+pub fn is_inspector_visible(world: &World) -> bool {
+    world
+        .get_non_send_resource::<DevGui>()
+        .map(|dev_gui| dev_gui.visible)
+        .unwrap_or(false)
+}
+
+fn toggle_inspector(
+    mut dev_gui: NonSendMut<DevGui>,
+    context: NonSend<Context>,
+    mut event_reader: EventReader<KeyboardInput>,
+) {
+    for event in event_reader.iter() {
+        if event.key_code == F10 && event.state == Released {
+            dev_gui.visible = !dev_gui.visible;
+
+            let window = context.window();
+            if dev_gui.visible {
+                window.set_cursor_grab(CursorGrabMode::None).unwrap();
+                window.set_cursor_visible(true);
+            } else {
+                window
+                    .set_cursor_grab(CursorGrabMode::Confined)
+                    .or_else(|_e| window.set_cursor_grab(CursorGrabMode::Locked))
+                    .unwrap();
+                window.set_cursor_visible(false);
+            }
+        }
+    }
+}
+
+type InspectedEntity<'a> = (
+    Entity,
+    Option<&'a DebugName>,
+    Option<&'a LevelId>,
+    Option<&'a mut Transform>,
+    Option<&'a mut Model>,
+);
+
+fn inspector_ui(
+    mut dev_gui: NonSendMut<DevGui>,
+    mut query: Query<InspectedEntity>,
+    level_flags: Option<Res<LevelFlags>>,
+    rewind_power: Option<Res<RewindPower>>,
+    memory_usage: Res<GpuMemoryUsage>,
+    mut selected_entity: Local<Option<Entity>>,
+) {
+    dev_gui.ui(|ctx| {
+        egui::Window::new("Inspector")
+            .default_width(320.0)
+            .show(ctx, |ui| {
+                ui.collapsing("Resources", |ui| {
+                    if let Some(rewind_power) = &rewind_power {
+                        ui.label(format!(
+                            "Rewind power: {:.0}% of {:.1}s",
+                            rewind_power.get_percent() * 100.0,
+                            rewind_power.max_seconds
+                        ));
+                    }
+                    if let Some(level_flags) = &level_flags {
+                        for (level_id, flags) in level_flags.iter() {
+                            ui.label(format!("Level {} flags: {:?}", level_id.id(), flags));
+                        }
+                    }
+                });
+
+                ui.collapsing("GPU memory", |ui| {
+                    for (label, category) in [
+                        ("Meshes", MemoryCategory::Meshes),
+                        ("Textures", MemoryCategory::Textures),
+                        ("Attachments", MemoryCategory::Attachments),
+                    ] {
+                        ui.label(format!(
+                            "{label}: {:.1} MB",
+                            to_megabytes(memory_usage.get(category))
+                        ));
+                    }
+                    ui.label(format!(
+                        "Total: {:.1} of {:.1} MB",
+                        to_megabytes(memory_usage.total()),
+                        to_megabytes(memory_usage.budget)
+                    ));
+                });
+
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(240.0)
+                    .show(ui, |ui| {
+                        for (entity, debug_name, _, _, _) in query.iter() {
+                            let label = match debug_name {
+                                Some(DebugName(name)) => format!("{name} ({entity:?})"),
+                                None => format!("{entity:?}"),
+                            };
+                            let is_selected = *selected_entity == Some(entity);
+                            if ui.selectable_label(is_selected, label).clicked() {
+                                *selected_entity = Some(entity);
+                            }
+                        }
+                    });
+
+                ui.separator();
+                match selected_entity.map(|entity| query.get_mut(entity)) {
+                    Some(Ok((_, _, level_id, transform, model))) => {
+                        if let Some(level_id) = level_id {
+                            ui.label(format!("Level: {}", level_id.id()));
+                        }
+                        if let Some(transform) = transform {
+                            transform_ui(ui, transform);
+                        }
+                        if let Some(model) = model {
+                            model_ui(ui, model);
+                        }
+                    }
+                    // The entity got despawned
+                    Some(Err(_)) => *selected_entity = None,
+                    None => {
+                        ui.label("No entity selected");
+                    }
+                }
+            });
+    });
+}
+
+/// Only writes back edits, otherwise the transform would count as changed every frame
+fn transform_ui(ui: &mut egui::Ui, mut transform: Mut<Transform>) {
+    ui.label("Transform");
+
+    let mut position = transform.position.coords;
+    let (roll, pitch, yaw) = transform.rotation.euler_angles();
+    let mut rotation = Vector3::new(roll, pitch, yaw).map(f32::to_degrees);
+    let mut scale = transform.scale;
+
+    let mut changed = vector_ui(ui, "Position", &mut position, 0.05);
+    changed |= vector_ui(ui, "Rotation", &mut rotation, 1.0);
+    changed |= vector_ui(ui, "Scale", &mut scale, 0.01);
+
+    if changed {
+        let rotation = rotation.map(f32::to_radians);
+        transform.position = Point3::from(position);
+        transform.rotation = UnitQuaternion::from_euler_angles(rotation.x, rotation.y, rotation.z);
+        transform.scale = scale;
+    }
+}
+
+/// Edited materials get replaced with a copy, which then gets uploaded to the GPU
+fn model_ui(ui: &mut egui::Ui, mut model: Mut<Model>) {
+    for index in 0..model.primitives.len() {
+        let material = model.primitives[index].material.clone();
+        let mut base_color: [f32; 3] = material.base_color.into();
+        let mut roughness_factor = material.roughness_factor;
+        let mut metallic_factor = material.metallic_factor;
+        let mut emissivity = material.emissivity;
+        let mut reflectivity = material.reflectivity;
+        let mut wind = material.wind;
+        let mut flipbook = material.flipbook;
+        let mut opacity = material.opacity;
+
+        let mut changed = false;
+        ui.push_id(index, |ui| {
+            ui.label(format!("Material {index}"));
+            ui.horizontal(|ui| {
+                ui.label("Base color");
+                changed |=
+                    egui::color_picker::color_edit_button_rgb(ui, &mut base_color).changed();
+            });
+            changed |= ui
+                .add(egui::Slider::new(&mut roughness_factor, 0.0..=1.0).text("Roughness"))
+                .changed();
+            changed |= ui
+                .add(egui::Slider::new(&mut metallic_factor, 0.0..=1.0).text("Metallic"))
+                .changed();
+            changed |= vector_ui(ui, "Emissivity", &mut emissivity, 0.05);
+            changed |= ui
+                .add(egui::Slider::new(&mut reflectivity, 0.0..=1.0).text("Reflectivity"))
+                .changed();
+            changed |= ui
+                .add(egui::Slider::new(&mut opacity, 0.0..=1.0).text("Opacity"))
+                .changed();
+            if let Some(wind) = &mut wind {
+                changed |= ui
+                    .add(egui::Slider::new(&mut wind.amplitude, 0.0..=0.5).text("Wind amplitude"))
+                    .changed();
+                changed |= ui
+                    .add(egui::Slider::new(&mut wind.frequency, 0.0..=10.0).text("Wind frequency"))
+                    .changed();
+            }
+            if let Some(flipbook) = &mut flipbook {
+                changed |= ui
+                    .add(egui::Slider::new(&mut flipbook.fps, 0.0..=60.0).text("Flipbook fps"))
+                    .changed();
+            }
+        });
+
+        if changed {
+            model.primitives[index].material = Arc::new(CpuMaterial {
+                base_color: base_color.into(),
+                base_color_texture: material.base_color_texture.clone(),
+                lightmap_texture: material.lightmap_texture.clone(),
+                roughness_factor,
+                metallic_factor,
+                emissivity,
+                reflectivity,
+                wind,
+                flipbook,
+                opacity,
+                ..CpuMaterial::default()
+            });
+        }
+    }
+}
+
+fn to_megabytes(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+fn vector_ui(ui: &mut egui::Ui, label: &str, vector: &mut Vector3<f32>, speed: f32) -> bool {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        let mut changed = false;
+        for value in vector.iter_mut() {
+            changed |= ui.add(egui::DragValue::new(value).speed(speed)).changed();
+        }
+        changed
+    })
+    .inner
+}
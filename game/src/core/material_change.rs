@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use bevy_ecs::{
+    prelude::{Entity, EventReader},
+    query::Changed,
+    system::{Query, Res, ResMut},
+    world::Mut,
+};
+
+use levels::{
+    current_level::{CurrentLevel, NextLevel},
+    level_id::LevelId,
+};
+use scene::material::{CpuMaterial, MaterialOverride};
+
+use time::time_manager::{
+    game_change::{GameChange, GameChangeHistory},
+    TimeManager,
+};
+
+#[derive(Clone)]
+pub struct MaterialChange {
+    entity: Entity,
+    material: Option<Arc<CpuMaterial>>,
+}
+
+impl GameChange for MaterialChange {}
+
+pub fn material_override_track(
+    mut history: ResMut<GameChangeHistory<MaterialChange>>,
+    current_level: Res<CurrentLevel>,
+    query: Query<(Entity, &MaterialOverride, &LevelId), Changed<MaterialOverride>>,
+) {
+    for (entity, material_override, level_id) in &query {
+        if level_id != &current_level.level_id {
+            continue;
+        }
+        history.add_command(MaterialChange {
+            entity,
+            material: material_override.material.clone(),
+        });
+    }
+}
+
+pub fn material_override_start_track(
+    mut next_level_events: EventReader<NextLevel>,
+    mut history: ResMut<GameChangeHistory<MaterialChange>>,
+    query: Query<(Entity, &MaterialOverride, &LevelId)>,
+) {
+    for next_level_event in next_level_events.iter() {
+        for (entity, material_override, level_id) in &query {
+            if level_id != &next_level_event.level_id {
+                continue;
+            }
+            history.add_command(MaterialChange {
+                entity,
+                material: material_override.material.clone(),
+            });
+        }
+    }
+}
+
+pub fn material_override_rewind(
+    time_manager: Res<TimeManager>,
+    mut history: ResMut<GameChangeHistory<MaterialChange>>,
+    mut query: Query<(Entity, &mut MaterialOverride)>,
+) {
+    let mut entities: HashMap<_, Mut<MaterialOverride>> = query.iter_mut().collect();
+
+    let commands = history.take_commands_to_apply(&time_manager);
+
+    for command_collection in commands {
+        for command in command_collection.commands {
+            if let Some(material_override) = entities.get_mut(&command.entity) {
+                material_override.set(command.material);
+            }
+        }
+    }
+}
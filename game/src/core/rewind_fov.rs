@@ -0,0 +1,58 @@
+use angle::Deg;
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::{Res, ResMut, Resource};
+use scene::camera::Camera;
+use time::time::Time;
+use time::time_manager::TimeManager;
+
+/// Smoothly widens the field of view while rewinding
+#[derive(Resource)]
+pub struct RewindFov {
+    pub base_fov: Deg<f32>,
+    /// how much wider the field of view gets while rewinding
+    pub rewind_fov_kick: Deg<f32>,
+    pub smoothing: f32,
+    /// in degrees
+    current_kick: f32,
+}
+
+fn update_rewind_fov(
+    time_manager: Res<TimeManager>,
+    time: Res<Time>,
+    mut rewind_fov: ResMut<RewindFov>,
+    mut camera: ResMut<Camera>,
+) {
+    let target_kick = if time_manager.is_rewinding() {
+        rewind_fov.rewind_fov_kick.0
+    } else {
+        0.0
+    };
+
+    let factor = (rewind_fov.smoothing * time.delta_seconds()).min(1.0);
+    rewind_fov.current_kick += (target_kick - rewind_fov.current_kick) * factor;
+
+    camera.set_fov(Deg(rewind_fov.base_fov.0 + rewind_fov.current_kick));
+}
+
+pub struct RewindFovPlugin {
+    base_fov: Deg<f32>,
+}
+
+impl RewindFovPlugin {
+    pub fn new(base_fov: Deg<f32>) -> Self {
+        Self { base_fov }
+    }
+}
+
+impl Plugin for RewindFovPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(RewindFov {
+                base_fov: self.base_fov,
+                rewind_fov_kick: Deg(10.0),
+                smoothing: 5.0,
+                current_kick: 0.0,
+            })
+            .with_system(update_rewind_fov);
+    }
+}
@@ -0,0 +1,65 @@
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::{Entity, Query, Res, ResMut, With};
+use levels::current_level::CurrentLevel;
+use levels::level_id::LevelId;
+use scene::model::Model;
+use scene::rewind_ghosts::{RewindGhost, RewindGhosts};
+use scene::transform::Transform;
+use time::time_manager::game_change::GameChangeHistory;
+use time::time_manager::{TimeManager, TimeTracked};
+
+use super::transform_change::{sample_transform, TransformChange};
+
+/// How far ahead of the rewinding the ghosts are
+const GHOST_SECONDS_IN_PAST: f64 = 2.0;
+
+/// Objects that barely moved don't get a ghost, it would only flicker inside of them
+const MIN_GHOST_DISTANCE: f32 = 0.05;
+const MIN_GHOST_ANGLE: f32 = 0.05;
+
+fn update_rewind_ghosts(
+    time_manager: Res<TimeManager>,
+    current_level: Res<CurrentLevel>,
+    history: Res<GameChangeHistory<TransformChange>>,
+    mut rewind_ghosts: ResMut<RewindGhosts>,
+    query: Query<(Entity, &TimeTracked, &Transform, &LevelId), With<Model>>,
+) {
+    rewind_ghosts.ghosts.clear();
+    if !time_manager.is_interpolating() || time_manager.tick_duration().is_zero() {
+        return;
+    }
+
+    let ticks_in_past = GHOST_SECONDS_IN_PAST / time_manager.tick_duration().as_secs_f64();
+    let tick = (time_manager.fractional_level_tick() - ticks_in_past).max(0.0);
+
+    for (entity, time_tracked, transform, level_id) in &query {
+        if level_id != &current_level.level_id {
+            continue;
+        }
+
+        let ghost_transform = match sample_transform(&history, time_tracked.id(), tick) {
+            Some(ghost_transform)
+                if (ghost_transform.position - transform.position).norm() > MIN_GHOST_DISTANCE
+                    || ghost_transform.rotation.angle_to(&transform.rotation) > MIN_GHOST_ANGLE =>
+            {
+                ghost_transform
+            }
+            _ => continue,
+        };
+
+        rewind_ghosts.ghosts.push(RewindGhost {
+            entity,
+            transform: ghost_transform,
+        });
+    }
+}
+
+/// Shows translucent copies of the time tracked objects while rewinding,
+/// at where they were a few seconds before the current rewind position
+pub struct RewindGhostsPlugin;
+
+impl Plugin for RewindGhostsPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app.with_system(update_rewind_ghosts);
+    }
+}
@@ -1,8 +1,8 @@
 use std::collections::HashMap;
 
 use bevy_ecs::{
-    prelude::EventReader,
-    query::Changed,
+    prelude::{EventReader, RemovedComponents},
+    query::{Changed, With, Without},
     system::{Query, Res, ResMut},
 };
 
@@ -14,7 +14,7 @@ use scene::transform::Transform;
 
 use time::time_manager::{
     game_change::{GameChange, GameChangeHistory},
-    TimeManager, TimeTracked,
+    RewindTarget, TimeManager, TimeTracked, TimeTrackedId,
 };
 
 // TODO: Am not sure if this is the best place for this code.
@@ -22,7 +22,7 @@ use time::time_manager::{
 pub fn time_manager_track_transform(
     mut history: ResMut<GameChangeHistory<TransformChange>>,
     current_level: Res<CurrentLevel>,
-    query: Query<(&TimeTracked, &Transform, &LevelId), Changed<Transform>>,
+    query: Query<(&TimeTracked, &Transform, &LevelId), (Changed<Transform>, Without<RewindTarget>)>,
 ) {
     for (time_tracked, transform, level_id) in &query {
         if level_id != &current_level.level_id {
@@ -66,7 +66,72 @@ pub fn time_manager_rewind_transform(
         }
     }
 
-    // TODO: Interpolation logic
+    if let Some(interpolation) = history.interpolation(&time_manager) {
+        let from_transforms: HashMap<_, _> = interpolation
+            .from
+            .commands
+            .iter()
+            .map(|command| (command.id, &command.new_transform))
+            .collect();
+
+        for command in &interpolation.to.commands {
+            if let (Some(v), Some(from)) = (
+                entities.get_mut(&command.id),
+                from_transforms.get(&command.id),
+            ) {
+                *v.as_mut() = from.lerp(&command.new_transform, interpolation.factor);
+            }
+        }
+    }
+}
+
+/// Moves the rewind targets back through their own history, while the world keeps running
+pub fn time_manager_rewind_target_transform(
+    time_manager: Res<TimeManager>,
+    history: Res<GameChangeHistory<TransformChange>>,
+    mut query: Query<(&TimeTracked, &mut Transform), With<RewindTarget>>,
+) {
+    let target_level_tick = match time_manager.target_level_tick() {
+        Some(target_level_tick) => target_level_tick,
+        None => return,
+    };
+
+    for (time_tracked, mut transform) in &mut query {
+        if let Some(sampled) = sample_transform(&history, time_tracked.id(), target_level_tick) {
+            *transform = sampled;
+        }
+    }
+}
+
+/// A released rewind target stays where it was rewound to, which its history doesn't know about yet.
+/// Without this, rewinding the whole world later would replay its old future.
+pub fn time_manager_track_released_rewind_target(
+    mut removals: RemovedComponents<RewindTarget>,
+    mut history: ResMut<GameChangeHistory<TransformChange>>,
+    query: Query<(&TimeTracked, &Transform)>,
+) {
+    for entity in &mut removals {
+        if let Ok((time_tracked, transform)) = query.get(entity) {
+            history.add_command(TransformChange::new(time_tracked, transform.clone()));
+        }
+    }
+}
+
+/// Where an object was at a tick, without rewinding anything
+pub fn sample_transform(
+    history: &GameChangeHistory<TransformChange>,
+    id: TimeTrackedId,
+    tick: f64,
+) -> Option<Transform> {
+    history
+        .sample(tick, |command| command.id == id)
+        .map(|sample| match sample.to {
+            Some(to) => sample
+                .from
+                .new_transform
+                .lerp(&to.new_transform, sample.factor),
+            None => sample.from.new_transform.clone(),
+        })
 }
 
 #[derive(Debug, Clone)]
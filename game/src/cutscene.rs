@@ -0,0 +1,251 @@
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::*;
+use input::events::{ElementState, KeyboardInput, VirtualKeyCode};
+use input::gamepad::{GamepadButton, GamepadButtonInput};
+use levels::current_level::CurrentLevel;
+use levels::level_id::LevelId;
+use nalgebra::{Point3, UnitQuaternion};
+use scene::camera::Camera;
+use scene::cutscene::{CutscenePoint, CutscenePointKind};
+use scene::transform::Transform;
+use scene::ui_component::UIVisibility;
+use std::collections::HashMap;
+use time::time::Time;
+
+use crate::game_state::{is_in_cutscene, GameState};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    /// In seconds since the start of the cutscene
+    pub time: f32,
+    pub position: Point3<f32>,
+}
+
+/// A Catmull-Rom spline through the keyframes, so the camera moves smoothly through every point
+#[derive(Debug, Clone, Default)]
+pub struct SplinePath {
+    keyframes: Vec<Keyframe>,
+}
+
+impl SplinePath {
+    pub fn new(mut keyframes: Vec<Keyframe>) -> Self {
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self { keyframes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    /// When the last keyframe is reached
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+    }
+
+    /// Stays at the first and the last keyframe before and after the path
+    pub fn sample(&self, time: f32) -> Option<Point3<f32>> {
+        let next_index = self
+            .keyframes
+            .iter()
+            .position(|keyframe| keyframe.time > time);
+        let next_index = match next_index {
+            Some(0) => return self.keyframes.first().map(|keyframe| keyframe.position),
+            Some(next_index) => next_index,
+            None => return self.keyframes.last().map(|keyframe| keyframe.position),
+        };
+
+        let start = &self.keyframes[next_index - 1];
+        let end = &self.keyframes[next_index];
+        // The first and the last keyframe get repeated at the ends of the path
+        let before = &self.keyframes[next_index.saturating_sub(2)];
+        let after = &self.keyframes[(next_index + 1).min(self.keyframes.len() - 1)];
+
+        let t = (time - start.time) / (end.time - start.time);
+        let t2 = t * t;
+        let t3 = t2 * t;
+        let position = (before.position.coords * (-t3 + 2.0 * t2 - t)
+            + start.position.coords * (3.0 * t3 - 5.0 * t2 + 2.0)
+            + end.position.coords * (-3.0 * t3 + 4.0 * t2 + t)
+            + after.position.coords * (t3 - t2))
+            * 0.5;
+        Some(position.into())
+    }
+}
+
+/// Where the camera is during a cutscene, and what it looks at
+#[derive(Debug, Clone, Default)]
+pub struct CameraPath {
+    pub camera: SplinePath,
+    pub look_at: SplinePath,
+}
+
+impl CameraPath {
+    pub fn new(camera: Vec<Keyframe>, look_at: Vec<Keyframe>) -> Self {
+        Self {
+            camera: SplinePath::new(camera),
+            look_at: SplinePath::new(look_at),
+        }
+    }
+
+    /// From the empties in the level, see [`CutscenePoint`]
+    fn from_points<'a>(points: impl Iterator<Item = (&'a CutscenePoint, &'a Transform)>) -> Self {
+        let mut camera = vec![];
+        let mut look_at = vec![];
+        for (point, transform) in points {
+            let keyframe = Keyframe {
+                time: point.time,
+                position: transform.position,
+            };
+            match point.kind {
+                CutscenePointKind::Camera => camera.push(keyframe),
+                CutscenePointKind::LookAt => look_at.push(keyframe),
+            }
+        }
+        Self::new(camera, look_at)
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.camera.duration().max(self.look_at.duration())
+    }
+
+    /// The position and the orientation of the camera
+    pub fn sample(&self, time: f32) -> Option<(Point3<f32>, UnitQuaternion<f32>)> {
+        let position = self.camera.sample(time)?;
+        let target = self.look_at.sample(time)?;
+        // face_towards points the +z axis at the target, but the camera looks along -z
+        let orientation = UnitQuaternion::face_towards(&(position - target), &Camera::up());
+        Some((position, orientation))
+    }
+}
+
+/// Starts the cutscene with the name. Cutscenes that were added in Rust take precedence over
+/// the ones in the current level.
+pub struct PlayCutscene {
+    pub name: String,
+}
+
+impl PlayCutscene {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+struct PlayingCutscene {
+    path: CameraPath,
+    elapsed: f32,
+}
+
+/// Takes the camera away from the player for a moment.
+/// The player can't move while a cutscene plays, and Escape, Enter, Space or the south or the
+/// start button of a gamepad skip it.
+#[derive(Resource, Default)]
+pub struct Cutscenes {
+    paths: HashMap<String, CameraPath>,
+    playing: Option<PlayingCutscene>,
+}
+
+impl Cutscenes {
+    pub fn add(&mut self, name: impl Into<String>, path: CameraPath) -> &mut Self {
+        self.paths.insert(name.into(), path);
+        self
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing.is_some()
+    }
+}
+
+fn start_cutscenes(
+    mut cutscenes: ResMut<Cutscenes>,
+    mut game_state: ResMut<GameState>,
+    mut ui_visibility: ResMut<UIVisibility>,
+    current_level: Res<CurrentLevel>,
+    mut play_cutscene_reader: EventReader<PlayCutscene>,
+    points_query: Query<(&CutscenePoint, &Transform, &LevelId)>,
+) {
+    for PlayCutscene { name } in play_cutscene_reader.iter() {
+        if !game_state.is_playing() {
+            continue;
+        }
+
+        let path = match cutscenes.paths.get(name) {
+            Some(path) => path.clone(),
+            None => CameraPath::from_points(
+                points_query
+                    .iter()
+                    .filter(|(point, _, level_id)| {
+                        &point.cutscene == name && level_id == &&current_level.level_id
+                    })
+                    .map(|(point, transform, _)| (point, transform)),
+            ),
+        };
+        if path.camera.is_empty() || path.look_at.is_empty() {
+            println!(
+                "The cutscene {:?} needs at least one camera point and one look at point",
+                name
+            );
+            continue;
+        }
+
+        cutscenes.playing = Some(PlayingCutscene { path, elapsed: 0.0 });
+        *game_state = GameState::Cutscene;
+        ui_visibility.hidden = true;
+    }
+}
+
+fn play_cutscene(
+    time: Res<Time>,
+    mut cutscenes: ResMut<Cutscenes>,
+    mut game_state: ResMut<GameState>,
+    mut ui_visibility: ResMut<UIVisibility>,
+    mut camera: ResMut<Camera>,
+    mut keyboard_reader: EventReader<KeyboardInput>,
+    mut gamepad_reader: EventReader<GamepadButtonInput>,
+) {
+    let key_pressed = keyboard_reader.iter().any(|event| {
+        event.state == ElementState::Pressed
+            && matches!(
+                event.key_code,
+                VirtualKeyCode::Escape | VirtualKeyCode::Return | VirtualKeyCode::Space
+            )
+    });
+    let button_pressed = gamepad_reader.iter().any(|event| {
+        event.state == ElementState::Pressed
+            && matches!(event.button, GamepadButton::South | GamepadButton::Start)
+    });
+
+    let playing = match &mut cutscenes.playing {
+        Some(playing) => playing,
+        None => return,
+    };
+    // The key that started the cutscene, for example in the main menu, doesn't skip it
+    let is_skipped = (key_pressed || button_pressed) && playing.elapsed > 0.0;
+    playing.elapsed += time.delta_seconds();
+
+    let is_finished = playing.elapsed >= playing.path.duration();
+    if is_finished || is_skipped {
+        // The player camera takes over again
+        cutscenes.playing = None;
+        *game_state = GameState::Playing;
+        ui_visibility.hidden = false;
+        return;
+    }
+
+    if let Some((position, orientation)) = playing.path.sample(playing.elapsed) {
+        camera.position = position;
+        camera.orientation = orientation;
+    }
+}
+
+pub struct CutscenePlugin;
+
+impl Plugin for CutscenePlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(Cutscenes::default())
+            .with_resource(Events::<PlayCutscene>::default())
+            .with_system(Events::<PlayCutscene>::update_system)
+            .with_system(start_cutscenes.after(Events::<PlayCutscene>::update_system))
+            .with_system(play_cutscene.after(start_cutscenes).run_if(is_in_cutscene));
+    }
+}
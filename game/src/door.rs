@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use animations::animation::PlayingAnimation;
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::*;
+use bevy_ecs::world::Mut;
+use levels::current_level::{CurrentLevel, NextLevel};
+use levels::level_id::LevelId;
+use loader::loader::Door;
+use scene::level::FlagId;
+use time::time_manager::game_change::{GameChange, GameChangeHistory, GameChangeHistoryPlugin};
+use time::time_manager::{is_rewinding, TimeManager};
+
+use crate::level_flags::LevelFlags;
+
+/// Derived from the lock and the animation of a door, so it rewinds together with them
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoorState {
+    Locked,
+    Closed,
+    Opening,
+    Open,
+    Closing,
+}
+
+impl DoorState {
+    pub fn is_open(&self) -> bool {
+        *self == DoorState::Open
+    }
+}
+
+fn all_flags_set(level_flags: &LevelFlags, flags: &[(LevelId, FlagId)]) -> bool {
+    flags
+        .iter()
+        .all(|(level_id, flag_id)| level_flags.get(*level_id, *flag_id))
+}
+
+/// Whether playing the animation forwards opens the door
+fn opens_forwards(door: &Door) -> bool {
+    door.closes_when.is_empty()
+}
+
+fn add_door_state(mut commands: Commands, query: Query<Entity, (With<Door>, Without<DoorState>)>) {
+    for entity in query.iter() {
+        commands.entity(entity).insert(DoorState::Closed);
+    }
+}
+
+/// Plays the door animations whenever the linked flags change
+fn update_doors(
+    level_flags: Res<LevelFlags>,
+    time_manager: Res<TimeManager>,
+    mut query: Query<(&Door, &mut PlayingAnimation)>,
+) {
+    for (door, mut animation) in query.iter_mut() {
+        let should_open = if door.locked {
+            false
+        } else if !door.opens_when.is_empty() {
+            all_flags_set(&level_flags, &door.opens_when)
+        } else if !door.closes_when.is_empty() {
+            !all_flags_set(&level_flags, &door.closes_when)
+        } else {
+            // A door without any links is controlled by the level
+            continue;
+        };
+
+        let should_play_forwards = should_open == opens_forwards(door);
+        if animation.is_forwards() == should_play_forwards {
+            continue;
+        }
+
+        if should_play_forwards {
+            animation.play_forwards(*time_manager.level_time());
+        } else {
+            animation.play_backwards(*time_manager.level_time());
+        }
+    }
+}
+
+fn update_door_states(
+    time_manager: Res<TimeManager>,
+    mut query: Query<(&Door, &PlayingAnimation, &mut DoorState)>,
+) {
+    for (door, animation, mut door_state) in query.iter_mut() {
+        let is_opening = animation.is_forwards() == opens_forwards(door);
+        let is_done = animation.get_progress(*time_manager.level_time()) >= 1.0;
+
+        let new_state = match (door.locked, is_opening, is_done) {
+            (true, _, true) => DoorState::Locked,
+            (_, true, false) => DoorState::Opening,
+            (_, true, true) => DoorState::Open,
+            (_, false, false) => DoorState::Closing,
+            (_, false, true) => DoorState::Closed,
+        };
+
+        if *door_state != new_state {
+            *door_state = new_state;
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DoorChange {
+    entity: Entity,
+    locked: bool,
+}
+
+impl GameChange for DoorChange {}
+
+fn doors_track(
+    mut history: ResMut<GameChangeHistory<DoorChange>>,
+    current_level: Res<CurrentLevel>,
+    query: Query<(Entity, &Door, &LevelId), Changed<Door>>,
+) {
+    for (entity, door, level_id) in &query {
+        if level_id != &current_level.level_id {
+            continue;
+        }
+        history.add_command(DoorChange {
+            entity,
+            locked: door.locked,
+        });
+    }
+}
+
+fn doors_start_track(
+    mut next_level_events: EventReader<NextLevel>,
+    mut history: ResMut<GameChangeHistory<DoorChange>>,
+    query: Query<(Entity, &Door, &LevelId)>,
+) {
+    for next_level_event in next_level_events.iter() {
+        for (entity, door, level_id) in &query {
+            if level_id != &next_level_event.level_id {
+                continue;
+            }
+            history.add_command(DoorChange {
+                entity,
+                locked: door.locked,
+            });
+        }
+    }
+}
+
+fn doors_rewind(
+    time_manager: Res<TimeManager>,
+    mut history: ResMut<GameChangeHistory<DoorChange>>,
+    mut query: Query<(Entity, &mut Door)>,
+) {
+    let mut entities: HashMap<_, Mut<Door>> = query.iter_mut().collect();
+
+    let commands = history.take_commands_to_apply(&time_manager);
+
+    for command_collection in commands {
+        for command in command_collection.commands {
+            if let Some(door) = entities.get_mut(&command.entity) {
+                if door.locked != command.locked {
+                    door.locked = command.locked;
+                }
+            }
+        }
+    }
+}
+
+pub struct DoorPlugin;
+
+impl Plugin for DoorPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_plugin(
+                GameChangeHistoryPlugin::<DoorChange>::new()
+                    .with_tracker(doors_start_track)
+                    .with_tracker(doors_track.after(doors_start_track))
+                    .with_rewinder(doors_rewind),
+            )
+            .with_system(add_door_state)
+            .with_system(
+                update_doors
+                    .after(GameChangeHistoryPlugin::<DoorChange>::system_set())
+                    .run_if(not(is_rewinding)),
+            )
+            .with_system(update_door_states.after(add_door_state).after(update_doors));
+    }
+}
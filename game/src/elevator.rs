@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use animations::animation::{AnimationRepeat, PlayingAnimation};
+use app::entity_event::EntityEvent;
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::*;
+use levels::level_id::LevelId;
+use loader::loader::{Elevator, ElevatorButton, ElevatorStop};
+use loader::name_registry::NameRegistry;
+use scene::interactable::Interacted;
+use time::time_manager::{is_rewinding, TimeManager};
+
+/// Derived from the animation of an elevator, so it rewinds and plays backwards together with it
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElevatorState {
+    AtStart,
+    MovingToEnd,
+    AtEnd,
+    MovingToStart,
+}
+
+impl ElevatorState {
+    pub fn is_moving(&self) -> bool {
+        *self == ElevatorState::MovingToEnd || *self == ElevatorState::MovingToStart
+    }
+}
+
+fn add_elevator_state(
+    mut commands: Commands,
+    query: Query<Entity, (With<Elevator>, Without<ElevatorState>)>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).insert(ElevatorState::AtStart);
+    }
+}
+
+/// A button with a stop sends the elevator there, a button without one sends it to the other stop.
+/// Buttons don't do anything while the elevator is moving, or when it moves on its own.
+fn press_elevator_buttons(
+    time_manager: Res<TimeManager>,
+    registry: Res<NameRegistry>,
+    buttons: Query<(&ElevatorButton, &LevelId, &EntityEvent<Interacted>)>,
+    mut elevators: Query<(&mut PlayingAnimation, &ElevatorState), With<Elevator>>,
+) {
+    for (button, level_id, interacted) in buttons.iter() {
+        if interacted.iter().next().is_none() {
+            continue;
+        }
+        let elevator = match registry.get_in_level(*level_id, &button.elevator) {
+            Some(elevator) => elevator,
+            None => continue,
+        };
+        let (mut animation, elevator_state) = match elevators.get_mut(elevator) {
+            Ok(elevator) => elevator,
+            Err(_) => continue,
+        };
+        if animation.animation().repeat != AnimationRepeat::Once || elevator_state.is_moving() {
+            continue;
+        }
+
+        let stop = button.stop.unwrap_or(match elevator_state {
+            ElevatorState::AtStart => ElevatorStop::End,
+            _ => ElevatorStop::Start,
+        });
+        match stop {
+            ElevatorStop::End if *elevator_state != ElevatorState::AtEnd => {
+                animation.play_forwards(*time_manager.level_time());
+            }
+            ElevatorStop::Start if *elevator_state != ElevatorState::AtStart => {
+                animation.play_backwards(*time_manager.level_time());
+            }
+            _ => {}
+        }
+    }
+}
+
+fn update_elevator_states(
+    time_manager: Res<TimeManager>,
+    mut query: Query<(&PlayingAnimation, &mut ElevatorState)>,
+) {
+    let time = *time_manager.level_time();
+    for (animation, mut elevator_state) in query.iter_mut() {
+        let position = animation.get_position(time);
+        // Repeating animations turn around on their own, so the direction comes from a moment later
+        let next_position = animation.get_position(time + Duration::from_millis(1));
+
+        let new_state = if position >= 1.0 {
+            ElevatorState::AtEnd
+        } else if position <= 0.0 {
+            ElevatorState::AtStart
+        } else if animation.animation().repeat == AnimationRepeat::Once {
+            if animation.is_forwards() {
+                ElevatorState::MovingToEnd
+            } else {
+                ElevatorState::MovingToStart
+            }
+        } else if next_position >= position {
+            ElevatorState::MovingToEnd
+        } else {
+            ElevatorState::MovingToStart
+        };
+
+        if *elevator_state != new_state {
+            *elevator_state = new_state;
+        }
+    }
+}
+
+pub struct ElevatorPlugin;
+
+impl Plugin for ElevatorPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_system(add_elevator_state)
+            .with_system(
+                press_elevator_buttons
+                    .after(add_elevator_state)
+                    .run_if(not(is_rewinding)),
+            )
+            .with_system(
+                update_elevator_states
+                    .after(add_elevator_state)
+                    .after(press_elevator_buttons),
+            );
+    }
+}
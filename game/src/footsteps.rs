@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::*;
+use physics::physics_context::{PhysicsContext, RapierRigidBodyHandle, Ray};
+use physics::player_physics::PlayerCharacterController;
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Source};
+use scene::surface::SurfaceType;
+use scene::transform::Transform;
+use time::game_rng::GameRng;
+use time::time::Time;
+use time::time_manager::is_rewinding;
+
+use crate::player::Player;
+
+/// In meters, the distance between two steps at walking speed
+const STEP_DISTANCE: f32 = 0.7;
+/// In meters per second, slower falls don't make a landing sound
+const MIN_LANDING_SPEED: f32 = 2.0;
+/// In meters per second, the landing is as loud as it gets from this speed on
+const MAX_LANDING_SPEED: f32 = 10.0;
+/// In meters per second, walking at this speed is as loud as it gets
+const MAX_STEP_SPEED: f32 = 4.0;
+/// Every surface has this many recordings of a step and of a landing
+pub const SOUND_VARIATIONS: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FootstepKind {
+    Step,
+    Landing,
+}
+
+/// Sent whenever the foot of the player hits the ground
+#[derive(Debug, Clone)]
+pub struct Footstep {
+    pub surface: SurfaceType,
+    pub kind: FootstepKind,
+    /// Which of the [`SOUND_VARIATIONS`] recordings to play, never the same one twice in a row
+    pub variation: u32,
+    /// Between 0 and 1
+    pub volume: f32,
+}
+
+#[derive(Default)]
+struct FootstepState {
+    /// In meters, walked since the last step
+    distance: f32,
+    was_grounded: bool,
+    /// In meters per second, the vertical speed just before touching the ground
+    fall_speed: f32,
+    last_variation: u32,
+}
+
+impl FootstepState {
+    fn next_variation(&mut self, rng: &mut GameRng) -> u32 {
+        // Skipping ahead by at least one never repeats the last variation
+        let skip = 1 + rng.index(SOUND_VARIATIONS as usize - 1) as u32;
+        self.last_variation = (self.last_variation + skip) % SOUND_VARIATIONS;
+        self.last_variation
+    }
+}
+
+/// Takes a step every [`STEP_DISTANCE`] meters on the ground, so that running has a faster cadence
+/// than walking, and lands when the player hits the ground after a fall.
+fn detect_footsteps(
+    query: Query<(
+        &Player,
+        &PlayerCharacterController,
+        &RapierRigidBodyHandle,
+        &Transform,
+    )>,
+    surfaces: Query<&SurfaceType>,
+    physics_context: Res<PhysicsContext>,
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+    mut state: Local<FootstepState>,
+    mut footsteps: EventWriter<Footstep>,
+) {
+    let (player, character_controller, rigid_body_handle, transform) = match query.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+
+    let up = character_controller.up.into_inner();
+    let grounded = character_controller.grounded;
+    let was_grounded = state.was_grounded;
+    state.was_grounded = grounded;
+    if !grounded {
+        // The gravity can be inverted, so only the amount of the speed matters
+        state.fall_speed = player.velocity.dot(&up).abs();
+        state.distance = 0.0;
+        return;
+    }
+
+    let horizontal_speed = (player.velocity - up * player.velocity.dot(&up)).norm();
+    let landing_speed = if was_grounded { 0.0 } else { state.fall_speed };
+    let kind = if landing_speed >= MIN_LANDING_SPEED {
+        state.distance = 0.0;
+        FootstepKind::Landing
+    } else {
+        state.distance += horizontal_speed * time.delta_seconds();
+        if state.distance < STEP_DISTANCE {
+            return;
+        }
+        state.distance -= STEP_DISTANCE;
+        FootstepKind::Step
+    };
+
+    // The ground below the feet decides the sound
+    let ray = Ray::new(transform.position + up * 0.5, -up);
+    let surface = physics_context
+        .cast_ray(&ray, 1.5, true, vec![rigid_body_handle])
+        .and_then(|(entity, _)| surfaces.get(entity).ok().copied())
+        .unwrap_or_default();
+
+    // Crouching is slower, and so it is also quieter
+    let volume = match kind {
+        FootstepKind::Step => horizontal_speed / MAX_STEP_SPEED,
+        FootstepKind::Landing => landing_speed / MAX_LANDING_SPEED,
+    };
+    footsteps.send(Footstep {
+        surface,
+        kind,
+        variation: state.next_variation(&mut rng),
+        volume: volume.clamp(0.0, 1.0),
+    });
+}
+
+/// The recordings of every surface, named like `metal_landing_2.wav`
+struct FootstepSounds {
+    /// The stream has to stay alive while sounds are playing, there is none without a sound device
+    output: Option<(OutputStream, OutputStreamHandle)>,
+    recordings: HashMap<(SurfaceType, FootstepKind, u32), Arc<[u8]>>,
+}
+
+impl FootstepSounds {
+    fn load(directory: &Path) -> Self {
+        let output = match OutputStream::try_default() {
+            Ok(output) => Some(output),
+            Err(error) => {
+                println!("No sound device, the footsteps are silent: {}", error);
+                None
+            }
+        };
+
+        let mut recordings = HashMap::new();
+        for surface in [SurfaceType::Metal, SurfaceType::Plastic, SurfaceType::Glass] {
+            for kind in [FootstepKind::Step, FootstepKind::Landing] {
+                for variation in 0..SOUND_VARIATIONS {
+                    let path = directory.join(recording_name(surface, kind, variation));
+                    match fs::read(&path) {
+                        Ok(bytes) => {
+                            recordings.insert((surface, kind, variation), bytes.into());
+                        }
+                        Err(error) => {
+                            println!("Could not load footstep sound {:?}: {}", path, error)
+                        }
+                    }
+                }
+            }
+        }
+
+        Self { output, recordings }
+    }
+}
+
+fn recording_name(surface: SurfaceType, kind: FootstepKind, variation: u32) -> String {
+    let surface = match surface {
+        SurfaceType::Metal => "metal",
+        SurfaceType::Plastic => "plastic",
+        SurfaceType::Glass => "glass",
+    };
+    let kind = match kind {
+        FootstepKind::Step => "step",
+        FootstepKind::Landing => "landing",
+    };
+    format!("{}_{}_{}.wav", surface, kind, variation)
+}
+
+/// Plays the recording of the surface for every footstep, at the volume of the footstep
+fn play_footsteps(sounds: NonSend<FootstepSounds>, mut footsteps: EventReader<Footstep>) {
+    for footstep in footsteps.iter() {
+        let handle = match &sounds.output {
+            Some((_, handle)) => handle,
+            None => continue,
+        };
+        let key = (footstep.surface, footstep.kind, footstep.variation);
+        let recording = match sounds.recordings.get(&key) {
+            Some(recording) => recording.clone(),
+            None => continue,
+        };
+
+        let result = Decoder::new(Cursor::new(recording))
+            .map_err(|error| error.to_string())
+            .and_then(|decoder| {
+                handle
+                    .play_raw(decoder.convert_samples().amplify(footstep.volume))
+                    .map_err(|error| error.to_string())
+            });
+        if let Err(error) = result {
+            println!("Could not play footstep sound {:?}: {}", key, error);
+        }
+    }
+}
+
+pub struct FootstepsPlugin {
+    /// The directory with the recordings
+    sounds_directory: PathBuf,
+}
+
+impl FootstepsPlugin {
+    pub fn new(sounds_directory: impl Into<PathBuf>) -> Self {
+        Self {
+            sounds_directory: sounds_directory.into(),
+        }
+    }
+}
+
+impl Plugin for FootstepsPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(Events::<Footstep>::default())
+            .with_non_send_resource(FootstepSounds::load(&self.sounds_directory))
+            .with_system(Events::<Footstep>::update_system)
+            .with_system(
+                detect_footsteps
+                    .after(Events::<Footstep>::update_system)
+                    .run_if(not(is_rewinding)),
+            )
+            .with_system(play_footsteps.after(detect_footsteps));
+    }
+}
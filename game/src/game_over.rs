@@ -1,31 +1,67 @@
 use std::time::{Duration, Instant};
 
 use app::plugin::Plugin;
-use bevy_ecs::{
-    prelude::EventWriter,
-    query::{With, Without},
-    system::{Query, Res, ResMut, Resource},
-};
-use levels::{
-    current_level::{CurrentLevel, ResetLevel},
-    level_id::LevelId,
-};
-use scene::{level::Spawnpoint, transform::Transform};
+use bevy_ecs::prelude::*;
+use input::events::{ElementState, KeyboardInput, VirtualKeyCode};
+use input::gamepad::{GamepadButton, GamepadButtonInput};
+use levels::current_level::NextLevel;
+use loader::atlas_loader::LoadableAtlas;
+use nalgebra::Vector2;
+use scene::ui_component::{UIAnchor, UIComponent, UITexturePosition};
+use scene::ui_text::UIText;
+use scene::ui_tween::UITween;
 use time::time_manager::TimeManager;
 
-use crate::{player::Player, rewind_power::RewindPower};
+use crate::game_state::GameState;
+use crate::level_restart::LevelRestart;
+use crate::rewind_power::RewindPower;
 
+/// How long the game slows down before the game over menu shows up
+const SLOW_MOTION_DURATION: Duration = Duration::from_millis(1500);
+/// How slow the game gets in the end
+const MIN_TIME_SCALE: f32 = 0.1;
+/// Space between the menu entries, in pixels of the reference resolution
+const ENTRY_SPACING: f32 = 36.0;
+/// Where the first menu entry is, below the game over text
+const FIRST_ENTRY_OFFSET: f32 = 96.0;
+const PANEL_WIDTH: f32 = 320.0;
+/// How long the menu takes to fade in, in seconds
+const FADE_DURATION: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GameOverEntry {
+    /// Rewinds the whole level to its start and resets it
+    Retry,
+    /// Goes to the main menu, which leads back to the game over menu when resuming
+    Quit,
+}
+
+impl GameOverEntry {
+    const ALL: [GameOverEntry; 2] = [GameOverEntry::Retry, GameOverEntry::Quit];
+
+    fn label(&self) -> &'static str {
+        match self {
+            GameOverEntry::Retry => "Retry level",
+            GameOverEntry::Quit => "Quit to menu",
+        }
+    }
+}
+
+/// Running out of rewind power ends the game. The game slows down until it almost stands still,
+/// then it freezes and a menu offers to retry the level.
 #[derive(Resource)]
 pub struct GameOver {
     is_game_over: bool,
-    respawn_start_time: Instant,
+    start_time: Instant,
+    selected: usize,
 }
 
 impl GameOver {
     pub fn new() -> Self {
         Self {
             is_game_over: false,
-            respawn_start_time: Instant::now(),
+            start_time: Instant::now(),
+            selected: 0,
         }
     }
 
@@ -33,8 +69,22 @@ impl GameOver {
         self.is_game_over
     }
 
-    pub fn is_game_over_rewinding(&self) -> bool {
-        self.is_game_over && Instant::now() > self.respawn_start_time
+    /// The slow motion is over and the menu is shown
+    pub fn is_menu_shown(&self) -> bool {
+        self.is_game_over && self.start_time.elapsed() >= SLOW_MOTION_DURATION
+    }
+
+    /// From 1 when the game ends to [`MIN_TIME_SCALE`] when the menu shows up
+    fn time_scale(&self) -> f32 {
+        // The menu needs the fixed steps at the normal rate, so the game gets frozen instead
+        if !self.is_game_over || self.is_menu_shown() {
+            return 1.0;
+        }
+        let progress =
+            (self.start_time.elapsed().as_secs_f32() / SLOW_MOTION_DURATION.as_secs_f32()).min(1.0);
+        // Slows down quickly at first, then eases into the slowest speed
+        let eased_progress = 1.0 - (1.0 - progress) * (1.0 - progress);
+        1.0 + (MIN_TIME_SCALE - 1.0) * eased_progress
     }
 
     fn set_game_over(&mut self) {
@@ -42,44 +92,189 @@ impl GameOver {
             return;
         }
         self.is_game_over = true;
-        self.respawn_start_time = Instant::now() + Duration::from_secs(2);
+        self.start_time = Instant::now();
+        self.selected = 0;
+    }
+}
+
+#[derive(Component)]
+struct UIGameOverEntry {
+    index: usize,
+}
+
+#[derive(Component)]
+struct UIGameOverPanel;
+
+fn spawn_game_over_ui(mut commands: Commands) {
+    let entry_offset =
+        |index: usize| Vector2::new(0.0, FIRST_ENTRY_OFFSET + index as f32 * ENTRY_SPACING);
+
+    for (index, entry) in GameOverEntry::ALL.iter().enumerate() {
+        let text = UIText::new(entry.label());
+        let ui_component = UIComponent {
+            texture: text.create_texture(),
+            region: None,
+            texture_position: UITexturePosition {
+                scale: Vector2::new(3.0, 3.0),
+                ..UITexturePosition::centered()
+            },
+            anchor: UIAnchor::Center,
+            offset: entry_offset(index),
+            depth: 0.0,
+            opacity: 1.0,
+            visible: false,
+        };
+        commands.spawn((
+            UITween::fade(&ui_component, FADE_DURATION),
+            ui_component,
+            text,
+            UIGameOverEntry { index },
+        ));
     }
+
+    let atlas = LoadableAtlas::load("assets/textures/ui_atlas.json").create_atlas();
+    let mut panel = UIComponent {
+        texture: atlas.texture.clone(),
+        region: atlas.region("panel"),
+        texture_position: UITexturePosition::centered(),
+        anchor: UIAnchor::Center,
+        offset: entry_offset(0)
+            + Vector2::new(
+                0.0,
+                (GameOverEntry::ALL.len() - 1) as f32 * ENTRY_SPACING / 2.0,
+            ),
+        depth: -0.9,
+        opacity: 1.0,
+        visible: false,
+    };
+    if let Some(region) = panel.region {
+        let size = Vector2::new(
+            PANEL_WIDTH,
+            GameOverEntry::ALL.len() as f32 * ENTRY_SPACING + 24.0,
+        );
+        panel.texture_position.scale =
+            size.component_div(&Vector2::new(region.width as f32, region.height as f32));
+    }
+    commands.spawn((UITween::fade(&panel, FADE_DURATION), panel, UIGameOverPanel));
 }
 
 fn update_game_over(
     mut game_over: ResMut<GameOver>,
+    game_state: Res<GameState>,
     rewind_power: Res<RewindPower>,
-    time_manager: Res<TimeManager>,
-    current_level: Res<CurrentLevel>,
-    mut event_reset_level: EventWriter<ResetLevel>,
-    // Player spawnpoint resetting
-    mut players_query: Query<&mut Transform, With<Player>>,
-    spawnpoints: Query<(&Transform, &LevelId), (With<Spawnpoint>, Without<Player>)>,
+    level_restart: Res<LevelRestart>,
+    mut time_manager: ResMut<TimeManager>,
+    mut next_level_reader: EventReader<NextLevel>,
 ) {
-    if game_over.is_game_over() {
-        if !game_over.is_game_over_rewinding() {
-            // wait for respawn
-        } else if time_manager.level_time().as_secs_f32() > 0.0 {
-            let rewind_speed = (time_manager.level_time().as_secs_f32() / 2.0).max(16.0);
-            // rewind time
-            time_manager.rewind_next_frame(rewind_speed);
-        } else {
-            // respawn
-            game_over.is_game_over = false;
-            event_reset_level.send(ResetLevel {
-                level_id: current_level.level_id,
-            });
-            for mut transform in players_query.iter_mut() {
-                let spawnpoint = spawnpoints
-                    .iter()
-                    .find(|(_, level_id)| level_id == &&current_level.level_id)
-                    .unwrap()
-                    .0;
-                transform.position = spawnpoint.position;
+    // Starting another level from the main menu is a fresh start
+    if next_level_reader.iter().next().is_some() && game_over.is_game_over() {
+        game_over.is_game_over = false;
+        time_manager.set_frozen(false);
+    }
+
+    // The rewinding of a restart is free, but the rewind power only gets refilled at the end
+    if !game_over.is_game_over() && rewind_power.is_empty() && !level_restart.is_restarting() {
+        game_over.set_game_over();
+    }
+
+    // Leaving the photo mode unfreezes the game
+    if game_over.is_menu_shown() && !time_manager.is_frozen() {
+        time_manager.set_frozen(true);
+    }
+
+    let time_scale = if game_state.is_playing() {
+        game_over.time_scale()
+    } else {
+        1.0
+    };
+    if time_manager.time_scale() != time_scale {
+        time_manager.set_time_scale(time_scale);
+    }
+}
+
+enum GameOverInput {
+    Up,
+    Down,
+    Confirm,
+}
+
+/// Works like the main menu, with the arrow keys or W and S, Enter or Space,
+/// and the d-pad and the south button of a gamepad
+fn game_over_menu_input(
+    mut game_over: ResMut<GameOver>,
+    mut game_state: ResMut<GameState>,
+    mut level_restart: ResMut<LevelRestart>,
+    mut time_manager: ResMut<TimeManager>,
+    mut keyboard_reader: EventReader<KeyboardInput>,
+    mut gamepad_reader: EventReader<GamepadButtonInput>,
+) {
+    let keyboard_inputs = keyboard_reader
+        .iter()
+        .filter(|event| event.state == ElementState::Pressed)
+        .filter_map(|event| match event.key_code {
+            VirtualKeyCode::Up | VirtualKeyCode::W => Some(GameOverInput::Up),
+            VirtualKeyCode::Down | VirtualKeyCode::S => Some(GameOverInput::Down),
+            VirtualKeyCode::Return | VirtualKeyCode::Space => Some(GameOverInput::Confirm),
+            _ => None,
+        });
+    let gamepad_inputs = gamepad_reader
+        .iter()
+        .filter(|event| event.state == ElementState::Pressed)
+        .filter_map(|event| match event.button {
+            GamepadButton::DPadUp => Some(GameOverInput::Up),
+            GamepadButton::DPadDown => Some(GameOverInput::Down),
+            GamepadButton::South => Some(GameOverInput::Confirm),
+            _ => None,
+        });
+    let inputs: Vec<_> = keyboard_inputs.chain(gamepad_inputs).collect();
+
+    if !game_over.is_menu_shown() || !game_state.is_playing() {
+        return;
+    }
+
+    let entry_count = GameOverEntry::ALL.len();
+    for input in inputs {
+        match input {
+            GameOverInput::Up => {
+                game_over.selected = (game_over.selected + entry_count - 1) % entry_count;
+            }
+            GameOverInput::Down => {
+                game_over.selected = (game_over.selected + 1) % entry_count;
             }
+            GameOverInput::Confirm => match GameOverEntry::ALL[game_over.selected] {
+                GameOverEntry::Retry => {
+                    game_over.is_game_over = false;
+                    time_manager.set_frozen(false);
+                    level_restart.request_restart();
+                    return;
+                }
+                GameOverEntry::Quit => {
+                    *game_state = GameState::MainMenu;
+                    return;
+                }
+            },
         }
-    } else if rewind_power.is_empty() {
-        game_over.set_game_over();
+    }
+}
+
+fn update_game_over_ui(
+    game_over: Res<GameOver>,
+    mut entries_query: Query<(&UIGameOverEntry, &mut UIText, &mut UITween)>,
+    mut panel_query: Query<&mut UITween, (With<UIGameOverPanel>, Without<UIGameOverEntry>)>,
+) {
+    let is_shown = game_over.is_menu_shown();
+    panel_query.single_mut().is_shown = is_shown;
+
+    for (ui_entry, mut text, mut tween) in entries_query.iter_mut() {
+        tween.is_shown = is_shown;
+
+        let label = GameOverEntry::ALL[ui_entry.index].label();
+        let label = if ui_entry.index == game_over.selected {
+            format!("> {}", label)
+        } else {
+            format!("  {}", label)
+        };
+        text.set_if_neq(UIText::new(label));
     }
 }
 
@@ -89,6 +284,9 @@ impl Plugin for GameOverPlugin {
     fn build(&mut self, app: &mut app::plugin::PluginAppAccess) {
         app //
             .with_resource(GameOver::new())
-            .with_system(update_game_over);
+            .with_startup_system(spawn_game_over_ui)
+            .with_system(update_game_over)
+            .with_system(game_over_menu_input.after(update_game_over))
+            .with_system(update_game_over_ui.after(game_over_menu_input));
     }
 }
@@ -0,0 +1,48 @@
+use bevy_ecs::system::{Res, Resource};
+
+/// Whether the player is in the main menu or in the levels.
+/// The gameplay only runs while playing, the world keeps going in the background of the menu.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameState {
+    MainMenu,
+    Playing,
+    /// Opened from the main menu, freezes the world and lets the camera fly around
+    PhotoMode,
+    /// The camera follows a path and the player can't move
+    Cutscene,
+}
+
+impl GameState {
+    pub fn is_playing(&self) -> bool {
+        *self == GameState::Playing
+    }
+
+    pub fn is_in_main_menu(&self) -> bool {
+        *self == GameState::MainMenu
+    }
+
+    pub fn is_in_photo_mode(&self) -> bool {
+        *self == GameState::PhotoMode
+    }
+
+    pub fn is_in_cutscene(&self) -> bool {
+        *self == GameState::Cutscene
+    }
+}
+
+/// Run condition for the gameplay systems
+pub fn is_playing(game_state: Res<GameState>) -> bool {
+    game_state.is_playing()
+}
+
+pub fn is_in_main_menu(game_state: Res<GameState>) -> bool {
+    game_state.is_in_main_menu()
+}
+
+pub fn is_in_photo_mode(game_state: Res<GameState>) -> bool {
+    game_state.is_in_photo_mode()
+}
+
+pub fn is_in_cutscene(game_state: Res<GameState>) -> bool {
+    game_state.is_in_cutscene()
+}
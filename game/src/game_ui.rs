@@ -3,22 +3,30 @@ use app::plugin::Plugin;
 use bevy_ecs::prelude::*;
 use bevy_ecs::system::Res;
 use image::{DynamicImage, GenericImageView};
-use nalgebra::{Point2, Point3, Vector2};
+use nalgebra::{Point2, Vector2};
 use scene::asset::AssetId;
 use scene::texture::{
     AddressMode, BytesTextureData, CpuTexture, Filter, MipmapMode, SamplerInfo, TextureFormat,
 };
-use scene::ui_component::{UIComponent, UITexturePosition};
+use scene::ui_component::{UIAnchor, UIComponent, UIProgressBar, UITexturePosition};
+use scene::ui_tween::UITween;
 use std::sync::Arc;
 use time::time::Time;
 use time::time_manager::TimeManager;
 
 use crate::game_over::GameOver;
-use crate::pickup_system::PickupInfo;
+use crate::game_state::is_playing;
+use crate::pickup_system::{PickupInfo, Reticle};
 use crate::rewind_power::RewindPower;
+use crate::rewind_speed::{RewindSpeed, MAX_REWIND_SPEED};
 
+/// Only the crosshair with the current [`Reticle`] is shown
 #[derive(Component)]
-struct UICrosshair;
+struct UICrosshair(Reticle);
+
+/// Fills up around the crosshair while the rewinding speeds up
+#[derive(Component)]
+struct UICrosshairRing;
 
 #[derive(Component)]
 struct UIRewind;
@@ -26,7 +34,10 @@ struct UIRewind;
 #[derive(Component)]
 struct UIProgressFill;
 #[derive(Component)]
-struct UIProgressBar;
+struct UIProgressOutline;
+/// Shows how fast the rewinding is
+#[derive(Component)]
+struct UIRewindSpeedFill;
 #[derive(Component)]
 struct UIGameOver;
 
@@ -52,30 +63,74 @@ fn spawn_ui_components(mut commands: Commands) {
         })
     };
 
+    let crosshairs = [
+        (Reticle::Crosshair, crosshair_texture),
+        (
+            Reticle::Hover,
+            image::open("assets/textures/reticle_hover.png").unwrap(),
+        ),
+        (
+            Reticle::Holding,
+            image::open("assets/textures/reticle_hand.png").unwrap(),
+        ),
+    ];
+    for (reticle, texture) in crosshairs {
+        commands.spawn((
+            UIComponent {
+                texture: create_cpu_texture(texture),
+                region: None,
+                anchor: UIAnchor::Center,
+                offset: Vector2::zeros(),
+                depth: -0.5,
+                texture_position: UITexturePosition {
+                    scale: Vector2::new(1.0, 1.0),
+                    ..UITexturePosition::centered()
+                },
+                opacity: 1.0,
+                visible: reticle == Reticle::Crosshair,
+            },
+            UICrosshair(reticle),
+        ));
+    }
+
+    let crosshair_ring = image::open("assets/textures/reticle_ring.png").unwrap();
     commands.spawn((
         UIComponent {
-            texture: create_cpu_texture(crosshair_texture),
-            position: Point3::new(0.5, 0.5, -0.5),
+            texture: create_cpu_texture(crosshair_ring),
+            region: None,
+            anchor: UIAnchor::Center,
+            offset: Vector2::zeros(),
+            depth: -0.1,
             texture_position: UITexturePosition {
-                scale: Vector2::new(1.0, 1.0),
+                scale: Vector2::new(2.5, 2.5),
+                // Around the rewind arrow
                 ..UITexturePosition::centered()
             },
-            visible: true,
+            opacity: 1.0,
+            visible: false,
         },
-        UICrosshair,
+        UIProgressBar::new(0.0).with_radial(),
+        UICrosshairRing,
     ));
 
     let game_over_texture = image::open("assets/textures/game_over.png").unwrap();
-    commands.spawn((
-        UIComponent {
-            texture: create_cpu_texture(game_over_texture),
-            position: Point3::new(0.5, 0.5, 0.0),
-            texture_position: UITexturePosition {
-                scale: Vector2::new(10.0, 10.0),
-                ..UITexturePosition::centered()
-            },
-            visible: false,
+    let game_over = UIComponent {
+        texture: create_cpu_texture(game_over_texture),
+        region: None,
+        anchor: UIAnchor::Center,
+        // Above the game over menu
+        offset: Vector2::new(0.0, -80.0),
+        depth: 0.0,
+        texture_position: UITexturePosition {
+            scale: Vector2::new(8.0, 8.0),
+            ..UITexturePosition::centered()
         },
+        opacity: 1.0,
+        visible: false,
+    };
+    commands.spawn((
+        UITween::fade(&game_over, 0.3).with_hidden_scale(Vector2::new(12.0, 12.0)),
+        game_over,
         UIGameOver,
     ));
 
@@ -83,11 +138,15 @@ fn spawn_ui_components(mut commands: Commands) {
     commands.spawn((
         UIComponent {
             texture: create_cpu_texture(rewind_texture),
-            position: Point3::new(0.5, 0.5, -0.1),
+            region: None,
+            anchor: UIAnchor::Center,
+            offset: Vector2::zeros(),
+            depth: -0.1,
             texture_position: UITexturePosition {
                 scale: Vector2::new(2.0, 2.0),
                 ..UITexturePosition::centered()
             },
+            opacity: 1.0,
             visible: false,
         },
         UIRewind,
@@ -97,14 +156,19 @@ fn spawn_ui_components(mut commands: Commands) {
     commands.spawn((
         UIComponent {
             texture: create_cpu_texture(progress_fill),
-            position: Point3::new(0.95, 0.05, 0.0),
+            region: None,
+            anchor: UIAnchor::TopRight,
+            offset: Vector2::new(-64.0, 36.0),
+            depth: 0.0,
             texture_position: UITexturePosition {
                 scale: Vector2::new(1.0, 1.0),
                 texture_origin: Point2::new(0.5, 1.0),
                 angle: Rad(std::f32::consts::FRAC_PI_2),
             },
+            opacity: 1.0,
             visible: true,
         },
+        UIProgressBar::new(1.0),
         UIProgressFill,
     ));
 
@@ -112,35 +176,58 @@ fn spawn_ui_components(mut commands: Commands) {
     commands.spawn((
         UIComponent {
             texture: create_cpu_texture(progress),
-            position: Point3::new(0.95, 0.05, 0.0),
+            region: None,
+            anchor: UIAnchor::TopRight,
+            offset: Vector2::new(-64.0, 36.0),
+            depth: 0.0,
             texture_position: UITexturePosition {
                 scale: Vector2::new(1.0, 1.0),
                 texture_origin: Point2::new(0.5, 1.0),
                 angle: Rad(std::f32::consts::FRAC_PI_2),
             },
+            opacity: 1.0,
             visible: true,
         },
-        UIProgressBar,
+        UIProgressOutline,
+    ));
+
+    let rewind_speed_fill = image::open("assets/textures/progress_fill.png").unwrap();
+    let rewind_speed_fill = UIComponent {
+        texture: create_cpu_texture(rewind_speed_fill),
+        region: None,
+        anchor: UIAnchor::TopRight,
+        offset: Vector2::new(-64.0, 72.0),
+        depth: 0.0,
+        texture_position: UITexturePosition {
+            scale: Vector2::new(0.5, 1.0),
+            texture_origin: Point2::new(0.5, 1.0),
+            angle: Rad(std::f32::consts::FRAC_PI_2),
+        },
+        opacity: 1.0,
+        visible: false,
+    };
+    commands.spawn((
+        // Slides in from the right edge of the screen
+        UITween::fade(&rewind_speed_fill, 0.2).with_hidden_offset(Vector2::new(64.0, 72.0)),
+        rewind_speed_fill,
+        UIProgressBar::new(0.0),
+        UIRewindSpeedFill,
     ));
 }
 
 fn update_rewind(
     time_manager: Res<TimeManager>,
-    mut crosshair_query: Query<&mut UIComponent, With<UICrosshair>>,
-    mut rewind_query: Query<&mut UIComponent, (With<UIRewind>, Without<UICrosshair>)>,
+    mut rewind_query: Query<&mut UIComponent, With<UIRewind>>,
 ) {
     let mut rewind = rewind_query.single_mut();
-    let mut crosshair = crosshair_query.single_mut();
 
     if time_manager.is_rewinding() {
         rewind.visible = true;
-        crosshair.visible = false;
 
         rewind.texture_position.angle +=
             Rad(time_manager.level_delta_time().duration().as_secs_f32() * 4.0);
     } else {
         rewind.visible = false;
-        crosshair.visible = true;
     }
 }
 
@@ -148,21 +235,31 @@ fn update_rewind_power(
     time_manager: Res<TimeManager>,
     time: Res<Time>,
     rewind_power: Res<RewindPower>,
-    mut progress_fill_query: Query<&mut UIComponent, With<UIProgressFill>>,
-    mut progress_bar_query: Query<&mut UIComponent, (With<UIProgressBar>, Without<UIProgressFill>)>,
+    mut progress_fill_query: Query<(&mut UIComponent, &mut UIProgressBar), With<UIProgressFill>>,
+    mut progress_bar_query: Query<
+        &mut UIComponent,
+        (With<UIProgressOutline>, Without<UIProgressFill>),
+    >,
 ) {
-    let mut progress_fill = progress_fill_query.single_mut();
+    let (mut progress_fill, mut progress_fill_bar) = progress_fill_query.single_mut();
     let mut progress_bar = progress_bar_query.single_mut();
 
-    progress_fill.texture_position.scale.y = rewind_power.get_percent();
+    progress_fill_bar.target_fill = rewind_power.get_percent();
     let start_angle = std::f32::consts::FRAC_PI_2;
     progress_fill.texture_position.angle = Rad(start_angle);
     progress_bar.texture_position.angle = Rad(start_angle);
 
+    let elapsed_time = time.time_since_startup().as_secs_f32();
+    progress_fill_bar.highlight = if rewind_power.is_empty() {
+        // flashing, so that players notice why they cannot rewind anymore
+        (elapsed_time * 8.0).sin() * 0.5 + 0.5
+    } else {
+        0.0
+    };
+
     if time_manager.is_rewinding() {
         if time_manager.level_delta_time().duration().is_zero() {
             // if we cannot rewind anymore
-            let elapsed_time = time.time_since_startup().as_secs_f32();
             let angle = Rad(start_angle + (elapsed_time * 50.0).sin() * 0.02);
             progress_fill.texture_position.angle = angle;
             progress_bar.texture_position.angle = angle;
@@ -170,25 +267,64 @@ fn update_rewind_power(
     }
 }
 
-fn update_pickup_crosshair(
+fn update_rewind_speed(
+    time_manager: Res<TimeManager>,
+    mut rewind_speed_query: Query<(&mut UITween, &mut UIProgressBar), With<UIRewindSpeedFill>>,
+) {
+    let (mut rewind_speed_tween, mut rewind_speed_bar) = rewind_speed_query.single_mut();
+
+    rewind_speed_tween.is_shown = time_manager.is_rewinding();
+    rewind_speed_bar.target_fill = (time_manager.rewind_speed() / MAX_REWIND_SPEED).min(1.0);
+}
+
+fn animate_progress_bars(time: Res<Time>, mut query: Query<&mut UIProgressBar>) {
+    for mut progress_bar in query.iter_mut() {
+        progress_bar.animate(time.delta_seconds());
+    }
+}
+
+/// Only while playing, so that the tweens pause together with the game
+fn animate_ui_tweens(time: Res<Time>, mut query: Query<(&mut UITween, &mut UIComponent)>) {
+    for (mut tween, mut ui_component) in query.iter_mut() {
+        tween.animate(time.delta_seconds(), &mut ui_component);
+    }
+}
+
+/// The rewind arrow replaces the crosshair while rewinding
+fn update_crosshair(
+    time_manager: Res<TimeManager>,
     pickup_info: Res<PickupInfo>,
-    mut crosshair_query: Query<&mut UIComponent, With<UICrosshair>>,
+    mut crosshair_query: Query<(&mut UIComponent, &UICrosshair)>,
+) {
+    for (mut crosshair, UICrosshair(reticle)) in crosshair_query.iter_mut() {
+        crosshair.visible = !time_manager.is_rewinding() && *reticle == pickup_info.reticle;
+    }
+}
+
+fn update_crosshair_ring(
+    time_manager: Res<TimeManager>,
+    rewind_speed: Res<RewindSpeed>,
+    mut ring_query: Query<(&mut UIComponent, &mut UIProgressBar), With<UICrosshairRing>>,
 ) {
-    let mut crosshair = crosshair_query.single_mut();
+    let (mut ring, mut ring_bar) = ring_query.single_mut();
 
-    if pickup_info.can_pickup {
-        crosshair.texture_position.scale = Vector2::new(1.5, 1.5);
+    let is_rewinding = time_manager.is_rewinding() || time_manager.is_rewinding_target();
+    ring.visible = is_rewinding;
+    if is_rewinding {
+        ring_bar.target_fill = (rewind_speed.speed() / MAX_REWIND_SPEED).min(1.0);
     } else {
-        crosshair.texture_position.scale = Vector2::new(1.0, 1.0);
+        // Starts out empty every time
+        ring_bar.target_fill = 0.0;
+        ring_bar.fill = 0.0;
     }
 }
 
 fn update_game_over(
     game_over: Res<GameOver>,
-    mut game_over_query: Query<&mut UIComponent, With<UIGameOver>>,
+    mut game_over_query: Query<&mut UITween, With<UIGameOver>>,
 ) {
-    let mut game_over_component = game_over_query.single_mut();
-    game_over_component.visible = game_over.is_game_over() && !game_over.is_game_over_rewinding();
+    let mut game_over_tween = game_over_query.single_mut();
+    game_over_tween.is_shown = game_over.is_menu_shown();
 }
 
 pub struct UIPlugin;
@@ -198,7 +334,11 @@ impl Plugin for UIPlugin {
         app.with_startup_system(spawn_ui_components)
             .with_system(update_rewind)
             .with_system(update_rewind_power.after(update_rewind))
-            .with_system(update_pickup_crosshair.after(update_rewind_power))
-            .with_system(update_game_over.after(update_pickup_crosshair));
+            .with_system(update_rewind_speed.after(update_rewind_power))
+            .with_system(update_crosshair.after(update_rewind_speed))
+            .with_system(update_crosshair_ring.after(update_crosshair))
+            .with_system(update_game_over.after(update_crosshair_ring))
+            .with_system(animate_progress_bars.after(update_crosshair_ring))
+            .with_system(animate_ui_tweens.after(update_game_over).run_if(is_playing));
     }
 }
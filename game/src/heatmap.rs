@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::*;
+use loader::telemetry_loader::{load_telemetry, TelemetryEvent};
+use nalgebra::{Point3, Vector3};
+use scene::debug_lines::DebugLines;
+
+/// In meters, the samples in the same cell get added up
+const CELL_SIZE: f32 = 1.0;
+const DEATH_COLOR: Vector3<f32> = Vector3::new(1.0, 0.0, 1.0);
+
+/// The recorded positions of playtests, drawn with the debug lines.
+/// Cells where the testers spent a lot of time are large and red, rarely visited cells are small and blue.
+#[derive(Resource, Default)]
+pub struct Heatmap {
+    /// How many position samples are in each cell
+    cells: HashMap<[i32; 3], u32>,
+    max_count: u32,
+    deaths: Vec<Point3<f32>>,
+}
+
+impl Heatmap {
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let mut heatmap = Heatmap::default();
+        for record in load_telemetry(&path) {
+            match record.event {
+                TelemetryEvent::Position { position, .. } => heatmap.add_sample(position.into()),
+                TelemetryEvent::Death { position, .. } => heatmap.deaths.push(position.into()),
+                _ => {}
+            }
+        }
+        println!(
+            "Loaded {} heatmap cells and {} deaths from {:?}",
+            heatmap.cells.len(),
+            heatmap.deaths.len(),
+            path
+        );
+        heatmap
+    }
+
+    fn add_sample(&mut self, position: Point3<f32>) {
+        let cell = position
+            .coords
+            .map(|coordinate| (coordinate / CELL_SIZE).floor() as i32)
+            .into();
+        let count = self.cells.entry(cell).or_default();
+        *count += 1;
+        self.max_count = self.max_count.max(*count);
+    }
+}
+
+/// From blue over green to red
+fn heat_color(heat: f32) -> Vector3<f32> {
+    Vector3::new(heat, 1.0 - (2.0 * heat - 1.0).abs(), 1.0 - heat)
+}
+
+/// Only visible together with the other debug lines
+fn draw_heatmap(heatmap: Res<Heatmap>, mut debug_lines: ResMut<DebugLines>) {
+    if !debug_lines.enabled {
+        return;
+    }
+
+    for (cell, count) in heatmap.cells.iter() {
+        let heat = *count as f32 / heatmap.max_count as f32;
+        let center = Point3::new(cell[0] as f32, cell[1] as f32, cell[2] as f32)
+            .map(|coordinate| (coordinate + 0.5) * CELL_SIZE);
+        let half_size = CELL_SIZE * (0.1 + 0.35 * heat);
+        let color = heat_color(heat);
+        for axis in [Vector3::x(), Vector3::y(), Vector3::z()] {
+            debug_lines.line(center - axis * half_size, center + axis * half_size, color);
+        }
+    }
+
+    for death in heatmap.deaths.iter() {
+        debug_lines.sphere(*death, CELL_SIZE * 0.25, DEATH_COLOR);
+    }
+}
+
+pub struct HeatmapPlugin {
+    path: PathBuf,
+}
+
+impl HeatmapPlugin {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Plugin for HeatmapPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(Heatmap::load(self.path.clone()))
+            .with_system(draw_heatmap);
+    }
+}
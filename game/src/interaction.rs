@@ -0,0 +1,127 @@
+use app::entity_event::EntityEvent;
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::*;
+use input::events::{ElementState, KeyboardInput, VirtualKeyCode};
+use input::gamepad::{GamepadButton, GamepadButtonInput};
+use nalgebra::Vector2;
+use physics::physics_context::{PhysicsContext, RapierRigidBodyHandle, Ray};
+use scene::camera::Camera;
+use scene::interactable::{Interactable, Interacted};
+use scene::ui_component::{UIAnchor, UIComponent, UITexturePosition};
+use scene::ui_text::UIText;
+use scene::ui_tween::UITween;
+use time::time_manager::is_rewinding;
+
+use crate::game_over::GameOver;
+use crate::player::Player;
+
+/// How far away an interactable can be
+pub(crate) const MAX_INTERACTION_DISTANCE: f32 = 3.0;
+
+#[derive(Component)]
+struct UIInteractionPrompt;
+
+fn spawn_interaction_prompt(mut commands: Commands) {
+    let text = UIText::new("");
+    let prompt = UIComponent {
+        texture: text.create_texture(),
+        region: None,
+        texture_position: UITexturePosition {
+            scale: Vector2::new(2.0, 2.0),
+            ..UITexturePosition::centered()
+        },
+        anchor: UIAnchor::Center,
+        offset: Vector2::new(0.0, 48.0),
+        depth: 0.0,
+        opacity: 1.0,
+        visible: false,
+    };
+    commands.spawn((
+        UITween::fade(&prompt, 0.15).with_hidden_offset(Vector2::new(0.0, 56.0)),
+        prompt,
+        text,
+        UIInteractionPrompt,
+    ));
+}
+
+fn clear_interactions(mut query: Query<&mut EntityEvent<Interacted>>) {
+    for mut event in query.iter_mut() {
+        event.clear();
+    }
+}
+
+/// Looking at an interactable shows a prompt, pressing E or the north gamepad button interacts with it
+fn interaction_system(
+    mut keyboard_reader: EventReader<KeyboardInput>,
+    mut gamepad_reader: EventReader<GamepadButtonInput>,
+    physics_context: Res<PhysicsContext>,
+    camera: Res<Camera>,
+    game_over: Res<GameOver>,
+    mut interactables: Query<(&Interactable, &mut EntityEvent<Interacted>)>,
+    exclude_query: Query<&RapierRigidBodyHandle, With<Player>>,
+    mut prompt_query: Query<(&mut UITween, &mut UIText), With<UIInteractionPrompt>>,
+) {
+    let ray = Ray::new(
+        camera.position,
+        camera.orientation * Camera::forward().into_inner(),
+    );
+    let entity = physics_context
+        .cast_ray(
+            &ray,
+            MAX_INTERACTION_DISTANCE,
+            true,
+            exclude_query.iter().collect(),
+        )
+        .map(|(entity, _toi)| entity)
+        .filter(|entity| interactables.contains(*entity))
+        .filter(|_| !game_over.is_game_over());
+
+    let key_pressed = keyboard_reader
+        .iter()
+        .any(|event| event.key_code == VirtualKeyCode::E && event.state == ElementState::Pressed);
+    let button_pressed = gamepad_reader
+        .iter()
+        .any(|event| event.button == GamepadButton::North && event.state == ElementState::Pressed);
+
+    let (mut prompt, mut prompt_text) = prompt_query.single_mut();
+    let entity = match entity {
+        Some(entity) => entity,
+        None => {
+            prompt.is_shown = false;
+            return;
+        }
+    };
+
+    let (interactable, mut interacted) = interactables.get_mut(entity).unwrap();
+    let text = format!("Press E to {}", interactable.prompt);
+    prompt_text.set_if_neq(UIText::new(text));
+    prompt.is_shown = true;
+
+    if key_pressed || button_pressed {
+        interacted.add(Interacted);
+    }
+}
+
+fn hide_interaction_prompt(mut prompt_query: Query<&mut UITween, With<UIInteractionPrompt>>) {
+    prompt_query.single_mut().is_shown = false;
+}
+
+pub struct InteractionPlugin;
+
+impl Plugin for InteractionPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_startup_system(spawn_interaction_prompt)
+            .with_system(clear_interactions)
+            .with_system(
+                interaction_system
+                    .after(clear_interactions)
+                    .run_if(not(is_rewinding)),
+            )
+            .with_system(
+                hide_interaction_prompt
+                    .run_if(is_rewinding)
+                    .ambiguous_with(interaction_system),
+            );
+    }
+}
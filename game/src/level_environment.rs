@@ -0,0 +1,120 @@
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::{DetectChanges, EventReader, Local, Res, ResMut, Resource};
+use bevy_ecs::schedule::IntoSystemConfig;
+use levels::current_level::{CurrentLevel, NextLevel, ResetLevel};
+use levels::level_id::LevelId;
+use loader::environment_loader::EnvironmentProfile;
+use loader::lut_loader::load_lut;
+use render::BloomSettings;
+use scene::color_grading::{ColorGrading, ColorLut};
+use scene::light::AmbientLight;
+use scene::sky::Sky;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The environment profile of the current level, so that every level can have its own mood.
+/// Gets loaded from `assets/scene/levels/environments/level<id>.json`, levels without one use the defaults.
+#[derive(Resource, Default)]
+pub struct LevelEnvironment {
+    pub profile: Option<EnvironmentProfile>,
+    /// Loaded from the path in the profile
+    pub color_lut: Option<Arc<ColorLut>>,
+    /// The level that the profile belongs to
+    level_id: Option<LevelId>,
+    /// By their path, a LUT that gets loaded again would get a new id and be uploaded to the GPU again
+    loaded_luts: HashMap<String, Arc<ColorLut>>,
+}
+
+/// The first level and levels that the player goes back to don't get a [`NextLevel`] event,
+/// so the profile gets loaded whenever the current level is a different one
+fn load_level_environment(
+    current_level: Res<CurrentLevel>,
+    mut level_environment: ResMut<LevelEnvironment>,
+) {
+    if level_environment.level_id == Some(current_level.level_id) {
+        return;
+    }
+
+    level_environment.level_id = Some(current_level.level_id);
+    level_environment.profile = EnvironmentProfile::load(format!(
+        "./assets/scene/levels/environments/level{}.json",
+        current_level.level_id.id()
+    ));
+    let level_environment = level_environment.as_mut();
+    level_environment.color_lut = level_environment
+        .profile
+        .as_ref()
+        .and_then(|profile| profile.color_grading_lut.as_ref())
+        .map(|path| {
+            level_environment
+                .loaded_luts
+                .entry(path.clone())
+                .or_insert_with(|| Arc::new(load_lut(path)))
+                .clone()
+        });
+}
+
+/// The fog gets applied by the [`LevelFogPlugin`](crate::level_fog::LevelFogPlugin), since it can also come from the glTF scene
+fn apply_level_environment(
+    mut next_level_events: EventReader<NextLevel>,
+    mut reset_level_events: EventReader<ResetLevel>,
+    current_level: Res<CurrentLevel>,
+    level_environment: Res<LevelEnvironment>,
+    mut ambient_light: ResMut<AmbientLight>,
+    mut bloom_settings: ResMut<BloomSettings>,
+    mut sky: ResMut<Sky>,
+    mut color_grading: ResMut<ColorGrading>,
+    mut default_bloom_settings: Local<Option<BloomSettings>>,
+) {
+    // The bloom settings from the config
+    let default_bloom_settings = default_bloom_settings
+        .get_or_insert_with(|| bloom_settings.clone())
+        .clone();
+
+    let has_next_level = next_level_events.iter().count() > 0;
+    let has_reset_level = reset_level_events
+        .iter()
+        .any(|reset_level| reset_level.level_id == current_level.level_id);
+    if !has_next_level && !has_reset_level && !level_environment.is_changed() {
+        return;
+    }
+
+    let profile = level_environment.profile.clone().unwrap_or_default();
+
+    // Otherwise the time of day already reset it, or a light track animates it
+    if let Some(profile_ambient_light) = profile.ambient_light {
+        *ambient_light = AmbientLight {
+            color: profile_ambient_light.color.into(),
+            intensity: profile_ambient_light.intensity,
+        };
+    }
+
+    *bloom_settings = match profile.bloom {
+        Some(bloom) => BloomSettings {
+            threshold: bloom.threshold,
+            knee: bloom.knee,
+            intensity: bloom.intensity,
+        },
+        None => default_bloom_settings,
+    };
+
+    *sky = match profile.sky_color {
+        Some(color) => Sky {
+            color: color.into(),
+        },
+        None => Sky::default(),
+    };
+
+    color_grading.lut = level_environment.color_lut.clone();
+}
+
+pub struct LevelEnvironmentPlugin;
+
+impl Plugin for LevelEnvironmentPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(LevelEnvironment::default())
+            .with_system(load_level_environment)
+            .with_system(apply_level_environment.after(load_level_environment));
+    }
+}
@@ -0,0 +1,61 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use levels::level_id::LevelId;
+use loader::loader::{LoadedScenes, SceneLoader};
+
+/// Levels can also live in their own glTF file, called `level{id}.gltf`, next to the levels file.
+/// A level is a scene in the levels file, or its own file, or both.
+#[derive(Clone)]
+pub struct LevelFiles {
+    levels_path: PathBuf,
+}
+
+impl LevelFiles {
+    pub fn new<P>(levels_path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            levels_path: levels_path.into(),
+        }
+    }
+
+    /// The file with the scenes of all levels
+    pub fn levels_path(&self) -> &Path {
+        &self.levels_path
+    }
+
+    /// The own file of a level, which doesn't have to exist
+    pub fn level_path(&self, level_id: LevelId) -> PathBuf {
+        self.levels_path
+            .with_file_name(format!("level{}.gltf", level_id.id()))
+    }
+
+    /// Reads every part of the level without spawning anything, so that it can happen on another thread
+    pub fn read(
+        &self,
+        scene_loader: &SceneLoader,
+        level_id: LevelId,
+        flag_counts: &HashMap<LevelId, usize>,
+    ) -> Result<Vec<LoadedScenes>, String> {
+        let mut scenes = vec![scene_loader
+            .read_level(&self.levels_path, flag_counts, level_id)
+            .map_err(|error| error.to_string())?];
+        let level_path = self.level_path(level_id);
+        if level_path.exists() {
+            scenes.push(
+                scene_loader
+                    .read_additive(&level_path, level_id, flag_counts)
+                    .map_err(|error| error.to_string())?,
+            );
+        }
+
+        if scenes.iter().all(|loaded_scenes| loaded_scenes.is_empty()) {
+            return Err(format!("There is no scene for level {}", level_id.id()));
+        }
+        Ok(scenes)
+    }
+}
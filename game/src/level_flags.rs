@@ -72,6 +72,20 @@ impl LevelFlags {
         flags[flag_id] = value;
     }
 
+    /// How many flags every level has, for checking the levels file
+    pub fn flag_counts(&self) -> HashMap<LevelId, usize> {
+        self.flags
+            .iter()
+            .map(|(level_id, flags)| (*level_id, flags.len()))
+            .collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (LevelId, &[bool])> {
+        self.flags
+            .iter()
+            .map(|(level_id, flags)| (*level_id, flags.as_slice()))
+    }
+
     pub fn get(&self, level_id: LevelId, flag_id: FlagId) -> bool {
         self.flags
             .get(&level_id)
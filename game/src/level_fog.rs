@@ -0,0 +1,49 @@
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::{Query, Res, ResMut};
+use levels::current_level::CurrentLevel;
+use levels::level_id::LevelId;
+use nalgebra::Vector3;
+use scene::fog::{Fog, LevelFog};
+
+use crate::level_environment::LevelEnvironment;
+
+/// Uses the fog of the current level, or no fog if the level does not have any.
+/// The environment profile of the level takes precedence over the fog from the glTF scene.
+/// Looks it up every frame, so that the fog also changes when a level gets reloaded.
+fn apply_level_fog(
+    current_level: Res<CurrentLevel>,
+    level_environment: Res<LevelEnvironment>,
+    mut fog: ResMut<Fog>,
+    level_fogs: Query<(&LevelFog, &LevelId)>,
+) {
+    let profile_fog = level_environment
+        .profile
+        .as_ref()
+        .and_then(|profile| profile.fog.as_ref())
+        .map(|fog| Fog {
+            color: Vector3::from(fog.color),
+            start: fog.start,
+            end: fog.end,
+        });
+    let level_fog = profile_fog
+        .or_else(|| {
+            level_fogs
+                .iter()
+                .find(|(_, level_id)| level_id == &&current_level.level_id)
+                .map(|(level_fog, _)| level_fog.fog.clone())
+        })
+        .unwrap_or_default();
+
+    if *fog != level_fog {
+        *fog = level_fog;
+    }
+}
+
+pub struct LevelFogPlugin;
+
+impl Plugin for LevelFogPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_system(apply_level_fog);
+    }
+}
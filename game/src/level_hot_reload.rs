@@ -0,0 +1,99 @@
+use std::{collections::HashMap, time::Duration};
+
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::{
+    prelude::{Commands, Entity},
+    system::{Query, Res, ResMut, Resource},
+};
+use levels::level_id::LevelId;
+use loader::{file_watcher::FileWatcher, loader::SceneLoader};
+
+use crate::level_flags::LevelFlags;
+use crate::level_streaming::LevelStreaming;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Resource)]
+struct LevelHotReload {
+    levels_file: FileWatcher,
+    /// The own files of the levels that are currently loaded
+    level_files: HashMap<LevelId, FileWatcher>,
+}
+
+/// Saving a level file reloads the loaded levels that are in it, while the player stays where they are.
+/// If the file has errors, they get printed and the old level stays.
+fn hot_reload_levels(
+    mut commands: Commands,
+    mut hot_reload: ResMut<LevelHotReload>,
+    scene_loader: Res<SceneLoader>,
+    level_streaming: Res<LevelStreaming>,
+    level_flags: Res<LevelFlags>,
+    query: Query<(Entity, &LevelId)>,
+) {
+    let hot_reload = hot_reload.as_mut();
+    let level_files = level_streaming.level_files();
+    let loaded_levels: Vec<LevelId> = level_streaming.loaded_levels().collect();
+    hot_reload
+        .level_files
+        .retain(|level_id, _| loaded_levels.contains(level_id));
+    for level_id in &loaded_levels {
+        hot_reload
+            .level_files
+            .entry(*level_id)
+            .or_insert_with(|| FileWatcher::new(level_files.level_path(*level_id), POLL_INTERVAL));
+    }
+
+    // Every level has a scene in the levels file
+    let levels_file_changed = hot_reload.levels_file.poll();
+    let changed_levels: Vec<LevelId> = hot_reload
+        .level_files
+        .iter_mut()
+        .filter_map(|(level_id, watcher)| {
+            let level_file_changed = watcher.poll();
+            (level_file_changed || levels_file_changed).then_some(*level_id)
+        })
+        .collect();
+
+    for level_id in changed_levels {
+        let scenes = match level_files.read(&scene_loader, level_id, &level_flags.flag_counts()) {
+            Ok(scenes) => scenes,
+            Err(error) => {
+                println!("Could not reload level {}: {}", level_id.id(), error);
+                continue;
+            }
+        };
+
+        for (entity, entity_level_id) in query.iter() {
+            if entity_level_id == &level_id {
+                commands.entity(entity).despawn();
+            }
+        }
+        for loaded_scenes in scenes {
+            scene_loader.spawn(loaded_scenes, &mut commands);
+        }
+        println!("Reloaded level {}", level_id.id());
+    }
+}
+
+pub struct LevelHotReloadPlugin {
+    path: String,
+}
+
+impl LevelHotReloadPlugin {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+        }
+    }
+}
+
+impl Plugin for LevelHotReloadPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(LevelHotReload {
+                levels_file: FileWatcher::new(&self.path, POLL_INTERVAL),
+                level_files: HashMap::new(),
+            })
+            .with_system(hot_reload_levels);
+    }
+}
@@ -0,0 +1,129 @@
+use app::plugin::Plugin;
+use bevy_ecs::{
+    prelude::EventWriter,
+    query::{With, Without},
+    system::{Query, Res, ResMut, Resource},
+};
+use input::{
+    gamepad::{GamepadButton, GamepadState},
+    input_map::InputMap,
+};
+use levels::{
+    current_level::{CurrentLevel, ResetLevel},
+    level_id::LevelId,
+};
+use nalgebra::Vector3;
+use scene::{level::Spawnpoint, transform::Transform};
+use time::{time::Time, time_manager::TimeManager};
+use windowing::event::VirtualKeyCode;
+
+use crate::{game_over::GameOver, player::Player};
+
+/// How long the restart button has to be held, in seconds
+const RESTART_HOLD_DURATION: f32 = 1.0;
+
+#[derive(Resource)]
+pub struct LevelRestart {
+    hold_duration: f32,
+    is_restarting: bool,
+    /// Restarts the level the next time the restart system runs
+    is_requested: bool,
+}
+
+impl LevelRestart {
+    pub fn new() -> Self {
+        Self {
+            hold_duration: 0.0,
+            is_restarting: false,
+            is_requested: false,
+        }
+    }
+
+    /// The rewinding of a restart is free
+    pub fn is_restarting(&self) -> bool {
+        self.is_restarting || self.is_requested
+    }
+
+    /// For example from the game over screen
+    pub fn request_restart(&mut self) {
+        self.is_requested = true;
+    }
+
+    fn start_restart(&mut self, time_manager: &TimeManager) {
+        self.is_requested = false;
+        self.is_restarting = true;
+        // Goes back by one more tick than there are, so that the level time ends up at zero
+        time_manager.rewind_next_frame(time_manager.fractional_level_tick() as f32 + 1.0);
+    }
+}
+
+/// Holding backspace or the select button restarts the level.
+/// The whole level gets rewound to its start in a single frame, which undoes every recorded
+/// change, like the flags and the positions of the objects. Afterwards the level gets reset,
+/// which also refills the rewind power, and the player goes back to the spawnpoint.
+fn update_level_restart(
+    mut level_restart: ResMut<LevelRestart>,
+    time_manager: Res<TimeManager>,
+    input: Res<InputMap>,
+    gamepad: Res<GamepadState>,
+    time: Res<Time>,
+    game_over: Res<GameOver>,
+    current_level: Res<CurrentLevel>,
+    mut event_reset_level: EventWriter<ResetLevel>,
+    mut players_query: Query<(&mut Transform, &mut Player)>,
+    spawnpoints: Query<(&Transform, &LevelId), (With<Spawnpoint>, Without<Player>)>,
+) {
+    if level_restart.is_restarting {
+        if time_manager.is_rewinding() {
+            // wait until the rewinding has stopped
+            return;
+        }
+
+        level_restart.is_restarting = false;
+        event_reset_level.send(ResetLevel {
+            level_id: current_level.level_id,
+        });
+        for (mut transform, mut player) in players_query.iter_mut() {
+            let spawnpoint = spawnpoints
+                .iter()
+                .find(|(_, level_id)| level_id == &&current_level.level_id)
+                .unwrap()
+                .0;
+            transform.position = spawnpoint.position;
+            player.velocity = Vector3::zeros();
+        }
+        return;
+    }
+
+    if level_restart.is_requested {
+        level_restart.start_restart(&time_manager);
+        return;
+    }
+
+    let wants_to_restart =
+        input.is_pressed(VirtualKeyCode::Back) || gamepad.is_pressed(GamepadButton::Select);
+    if !wants_to_restart || game_over.is_game_over() {
+        level_restart.hold_duration = 0.0;
+        return;
+    }
+
+    // The button has to be released before the next restart
+    if level_restart.hold_duration >= RESTART_HOLD_DURATION {
+        return;
+    }
+
+    level_restart.hold_duration += time.delta_seconds();
+    if level_restart.hold_duration >= RESTART_HOLD_DURATION {
+        level_restart.start_restart(&time_manager);
+    }
+}
+
+pub struct LevelRestartPlugin;
+
+impl Plugin for LevelRestartPlugin {
+    fn build(&mut self, app: &mut app::plugin::PluginAppAccess) {
+        app //
+            .with_resource(LevelRestart::new())
+            .with_system(update_level_restart);
+    }
+}
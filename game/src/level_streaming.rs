@@ -0,0 +1,234 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    thread::JoinHandle,
+};
+
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::{
+    prelude::{Commands, Entity, EventReader},
+    query::With,
+    schedule::IntoSystemConfig,
+    system::{Query, Res, ResMut, Resource},
+};
+use levels::{
+    current_level::{CurrentLevel, NextLevel},
+    level_id::LevelId,
+};
+use loader::loader::{LoadedScenes, SceneLoader};
+use scene::{level::NextLevelTrigger, spatial_grid::SpatialGrid, transform::Transform};
+
+use crate::{level_files::LevelFiles, level_flags::LevelFlags, player::Player};
+
+/// How close the player has to get to the exit of the current level, in meters,
+/// before the level after the next one starts loading
+const PRELOAD_DISTANCE: f32 = 20.0;
+
+type LevelLoadingResult = Result<Vec<LoadedScenes>, String>;
+
+/// Only the current and the next level are spawned, which saves memory and startup time.
+/// The next level always has to be there, since its trigger is the exit of the current level.
+/// Once the player gets close to that exit, the level after it gets read on another thread,
+/// and walking into the next level despawns the old one.
+#[derive(Resource)]
+pub struct LevelStreaming {
+    level_files: LevelFiles,
+    loaded_levels: HashSet<LevelId>,
+    loading_levels: HashMap<LevelId, JoinHandle<LevelLoadingResult>>,
+    /// Gets loaded right away, for example when a level gets started from the menu
+    requested_level: Option<LevelId>,
+}
+
+impl LevelStreaming {
+    pub fn new<P>(levels_path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            level_files: LevelFiles::new(levels_path),
+            loaded_levels: HashSet::new(),
+            loading_levels: HashMap::new(),
+            requested_level: None,
+        }
+    }
+
+    pub fn level_files(&self) -> &LevelFiles {
+        &self.level_files
+    }
+
+    pub fn is_loaded(&self, level_id: LevelId) -> bool {
+        self.loaded_levels.contains(&level_id)
+    }
+
+    pub fn loaded_levels(&self) -> impl Iterator<Item = LevelId> + '_ {
+        self.loaded_levels.iter().copied()
+    }
+
+    /// Loads the level the next time the levels get streamed, even if it is behind the player
+    pub fn request(&mut self, level_id: LevelId) {
+        self.requested_level = Some(level_id);
+    }
+
+    /// Waits until the level has been read. Does nothing if the level has already been loaded,
+    /// or if it isn't a level of the game.
+    pub fn load(
+        &mut self,
+        level_id: LevelId,
+        scene_loader: &SceneLoader,
+        commands: &mut Commands,
+        flag_counts: &HashMap<LevelId, usize>,
+    ) {
+        if !flag_counts.contains_key(&level_id) || self.is_loaded(level_id) {
+            return;
+        }
+
+        let result = match self.loading_levels.remove(&level_id) {
+            Some(handle) => handle.join().expect("Reading the level panicked"),
+            None => self.level_files.read(scene_loader, level_id, flag_counts),
+        };
+        self.spawn(level_id, result, scene_loader, commands);
+    }
+
+    /// Reads the level on another thread, it gets spawned once it is done
+    pub fn preload(
+        &mut self,
+        level_id: LevelId,
+        scene_loader: &SceneLoader,
+        flag_counts: &HashMap<LevelId, usize>,
+    ) {
+        if !flag_counts.contains_key(&level_id)
+            || self.is_loaded(level_id)
+            || self.loading_levels.contains_key(&level_id)
+        {
+            return;
+        }
+
+        let scene_loader = scene_loader.clone();
+        let level_files = self.level_files.clone();
+        let flag_counts = flag_counts.clone();
+        let handle =
+            std::thread::spawn(move || level_files.read(&scene_loader, level_id, &flag_counts));
+        self.loading_levels.insert(level_id, handle);
+    }
+
+    fn spawn_preloaded_levels(&mut self, scene_loader: &SceneLoader, commands: &mut Commands) {
+        let finished_levels: Vec<_> = self
+            .loading_levels
+            .iter()
+            .filter(|(_, handle)| handle.is_finished())
+            .map(|(level_id, _)| *level_id)
+            .collect();
+
+        for level_id in finished_levels {
+            if let Some(handle) = self.loading_levels.remove(&level_id) {
+                let result = handle.join().expect("Reading the level panicked");
+                self.spawn(level_id, result, scene_loader, commands);
+            }
+        }
+    }
+
+    fn spawn(
+        &mut self,
+        level_id: LevelId,
+        result: LevelLoadingResult,
+        scene_loader: &SceneLoader,
+        commands: &mut Commands,
+    ) {
+        let scenes = result
+            .unwrap_or_else(|error| panic!("Could not load level {}: {}", level_id.id(), error));
+        for loaded_scenes in scenes {
+            scene_loader.spawn(loaded_scenes, commands);
+        }
+        self.loaded_levels.insert(level_id);
+    }
+}
+
+/// Loads the level that gets started, and despawns the levels behind the player
+fn stream_levels(
+    mut commands: Commands,
+    mut level_streaming: ResMut<LevelStreaming>,
+    mut next_level_events: EventReader<NextLevel>,
+    scene_loader: Res<SceneLoader>,
+    level_flags: Res<LevelFlags>,
+    query: Query<(Entity, &LevelId)>,
+) {
+    let flag_counts = level_flags.flag_counts();
+    if let Some(level_id) = level_streaming.requested_level.take() {
+        level_streaming.load(level_id, &scene_loader, &mut commands, &flag_counts);
+    }
+
+    for next_level in next_level_events.iter() {
+        let level_id = next_level.level_id;
+        let upcoming_level_id = LevelId::new(level_id.id() + 1);
+        level_streaming.load(level_id, &scene_loader, &mut commands, &flag_counts);
+        level_streaming.preload(upcoming_level_id, &scene_loader, &flag_counts);
+
+        let is_kept = |other_level_id: &LevelId| {
+            other_level_id == &level_id || other_level_id == &upcoming_level_id
+        };
+        level_streaming.loaded_levels.retain(is_kept);
+        // The threads can't be stopped, but their levels won't get spawned
+        level_streaming
+            .loading_levels
+            .retain(|other_level_id, _| is_kept(other_level_id));
+        for (entity, entity_level_id) in query.iter() {
+            if !is_kept(entity_level_id) {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+/// The exit of the current level is the trigger of the next level
+fn preload_levels(
+    mut commands: Commands,
+    mut level_streaming: ResMut<LevelStreaming>,
+    scene_loader: Res<SceneLoader>,
+    level_flags: Res<LevelFlags>,
+    current_level: Res<CurrentLevel>,
+    spatial_grid: Res<SpatialGrid>,
+    players_query: Query<&Transform, With<Player>>,
+    triggers_query: Query<&LevelId, With<NextLevelTrigger>>,
+) {
+    let next_level_id = LevelId::new(current_level.level_id.id() + 1);
+    let is_close_to_exit = players_query.iter().any(|player_transform| {
+        let nearby_entities =
+            spatial_grid.query_sphere(&player_transform.position.coords, PRELOAD_DISTANCE);
+        triggers_query
+            .iter_many(nearby_entities)
+            .any(|level_id| level_id == &next_level_id)
+    });
+    if is_close_to_exit {
+        level_streaming.preload(
+            LevelId::new(next_level_id.id() + 1),
+            &scene_loader,
+            &level_flags.flag_counts(),
+        );
+    }
+
+    level_streaming.spawn_preloaded_levels(&scene_loader, &mut commands);
+}
+
+pub struct LevelStreamingPlugin {
+    levels_path: PathBuf,
+}
+
+impl LevelStreamingPlugin {
+    pub fn new<P>(levels_path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            levels_path: levels_path.into(),
+        }
+    }
+}
+
+impl Plugin for LevelStreamingPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(LevelStreaming::new(self.levels_path.clone()))
+            .with_system(stream_levels)
+            .with_system(preload_levels.after(stream_levels));
+    }
+}
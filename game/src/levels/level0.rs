@@ -1,43 +1,10 @@
-use animations::animation::PlayingAnimation;
 use app::plugin::Plugin;
-use bevy_ecs::{
-    prelude::{Query, Res},
-    query::With,
-    schedule::IntoSystemConfig,
-    system::{Local, ResMut},
-};
+use bevy_ecs::{schedule::IntoSystemConfig, system::ResMut};
+use game::alarm::{Alarm, Alarms, AlarmsSetup};
 use game::level_flags::{FlagChange, LevelFlags};
+use game::objectives::{ObjectiveCondition, Objectives, ObjectivesSetup};
 use levels::level_id::LevelId;
-use loader::loader::Door;
 use time::time_manager::game_change::GameChangeHistory;
-use time::time_manager::TimeManager;
-
-fn door_system(
-    level_flags: Res<LevelFlags>,
-    time: Res<TimeManager>,
-    mut query: Query<(&mut PlayingAnimation, &LevelId), With<Door>>,
-    mut door_flag_value: Local<bool>,
-) {
-    let level_id = LevelId::new(0);
-
-    let door_should_close = level_flags.get(level_id, 1);
-    if door_should_close != *door_flag_value {
-        *door_flag_value = door_should_close;
-    } else {
-        return;
-    }
-
-    let mut animation = query
-        .iter_mut()
-        .find(|(_, level)| level == &&level_id)
-        .unwrap()
-        .0;
-    if door_should_close {
-        animation.play_forwards(*time.level_time());
-    } else if !door_should_close {
-        animation.play_backwards(*time.level_time());
-    }
-}
 
 fn laser_system(
     mut level_flags: ResMut<LevelFlags>,
@@ -50,13 +17,38 @@ fn laser_system(
     }
 }
 
+fn setup_objectives(mut objectives: ResMut<Objectives>) {
+    let level_id = LevelId::new(0);
+    objectives
+        .add(
+            level_id,
+            "Walk through the laser",
+            ObjectiveCondition::Flags(vec![0]),
+        )
+        .add(
+            level_id,
+            "Rewind time to get through the door",
+            ObjectiveCondition::ReachNextLevel,
+        );
+}
+
+/// The alarm goes off once the door closes
+fn setup_alarm(mut alarms: ResMut<Alarms>) {
+    alarms.add(LevelId::new(0), Alarm::new(1));
+}
+
 pub struct Level0Plugin;
 
 impl Plugin for Level0Plugin {
     fn build(&mut self, app: &mut app::plugin::PluginAppAccess) {
         app
             //
-            .with_system(laser_system)
-            .with_system(door_system.after(laser_system));
+            .with_startup_system(
+                setup_objectives
+                    .in_set(ObjectivesSetup)
+                    .ambiguous_with(ObjectivesSetup),
+            )
+            .with_startup_system(setup_alarm.in_set(AlarmsSetup).ambiguous_with(AlarmsSetup))
+            .with_system(laser_system);
     }
 }
@@ -2,46 +2,20 @@ use animations::animation::PlayingAnimation;
 use app::plugin::Plugin;
 use bevy_ecs::{
     prelude::{Query, Res},
-    query::With,
     schedule::IntoSystemConfig,
-    system::Local,
+    system::{Local, ResMut},
 };
 use game::level_flags::LevelFlags;
+use game::objectives::{ObjectiveCondition, Objectives, ObjectivesSetup};
 use levels::level_id::LevelId;
-use loader::loader::{Door, Platform};
+use loader::name_registry::NameRegistry;
 use time::time_manager::TimeManager;
 
-fn door_system(
-    level_flags: Res<LevelFlags>,
-    time: Res<TimeManager>,
-    mut query: Query<(&mut PlayingAnimation, &LevelId), With<Door>>,
-    mut door_flag_value: Local<bool>,
-) {
-    let level_id = LevelId::new(2);
-
-    let door_should_open = level_flags.get(level_id, 0) && level_flags.get(level_id, 1);
-    if door_should_open != *door_flag_value {
-        *door_flag_value = door_should_open;
-    } else {
-        return;
-    }
-
-    let mut animation = query
-        .iter_mut()
-        .find(|(_, level)| level == &&level_id)
-        .unwrap()
-        .0;
-    if door_should_open {
-        animation.play_forwards(*time.level_time());
-    } else if !door_should_open {
-        animation.play_backwards(*time.level_time());
-    }
-}
-
 fn platform_system(
     level_flags: Res<LevelFlags>,
     time: Res<TimeManager>,
-    mut query: Query<(&mut PlayingAnimation, &LevelId), With<Platform>>,
+    registry: Res<NameRegistry>,
+    mut query: Query<&mut PlayingAnimation>,
     mut platform_flag_value: Local<bool>,
 ) {
     let level_id = LevelId::new(2);
@@ -53,11 +27,10 @@ fn platform_system(
         return;
     }
 
-    let mut animation = query
-        .iter_mut()
-        .find(|(_, level)| level == &&level_id)
-        .unwrap()
-        .0;
+    let platform = registry
+        .get("Level2.UnitCube.004")
+        .expect("The platform of level 2 is missing");
+    let mut animation = query.get_mut(platform).unwrap();
     if platform_should_lower {
         animation.play_forwards(*time.level_time());
     } else if !platform_should_lower {
@@ -65,11 +38,31 @@ fn platform_system(
     }
 }
 
+fn setup_objectives(mut objectives: ResMut<Objectives>) {
+    let level_id = LevelId::new(2);
+    objectives
+        .add(
+            level_id,
+            "Press both pressure plates",
+            ObjectiveCondition::Flags(vec![0, 1]),
+        )
+        .add(
+            level_id,
+            "Reach the exit",
+            ObjectiveCondition::ReachNextLevel,
+        );
+}
+
 pub struct Level2Plugin;
 
 impl Plugin for Level2Plugin {
     fn build(&mut self, app: &mut app::plugin::PluginAppAccess) {
-        app.with_system(door_system)
-            .with_system(platform_system.after(door_system));
+        app //
+            .with_startup_system(
+                setup_objectives
+                    .in_set(ObjectivesSetup)
+                    .ambiguous_with(ObjectivesSetup),
+            )
+            .with_system(platform_system);
     }
 }
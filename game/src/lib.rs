@@ -1,7 +1,45 @@
+pub mod achievements;
+pub mod alarm;
+pub mod captions;
+pub mod color_grading;
 pub mod core;
+pub mod cutscene;
+pub mod door;
+pub mod elevator;
+pub mod footsteps;
 pub mod game_over;
+pub mod game_state;
 pub mod game_ui;
+pub mod heatmap;
+pub mod interaction;
+pub mod level_restart;
+pub mod level_environment;
+pub mod level_files;
 pub mod level_flags;
+pub mod level_fog;
+#[cfg(feature = "dev-tools")]
+pub mod level_hot_reload;
+pub mod level_streaming;
+pub mod magnet;
+pub mod main_menu;
+pub mod objectives;
+pub mod photo_mode;
 pub mod pickup_system;
+pub mod platform_riding;
+pub mod prefabs;
+pub mod pressure_plate_indicator;
+pub mod progress;
+pub mod replay;
 pub mod player;
 pub mod rewind_power;
+pub mod rewind_speed;
+pub mod rewind_target;
+pub mod save_states;
+pub mod screen_effects;
+pub mod settings_menu;
+pub mod speedrun_timer;
+pub mod telemetry;
+pub mod teleporter;
+pub mod time_of_day;
+pub mod ui_interaction;
+pub mod video_texture;
@@ -0,0 +1,236 @@
+use std::f32::consts::TAU;
+use std::sync::Arc;
+
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::*;
+use levels::level_id::LevelId;
+use loader::loader::Magnet;
+use nalgebra::{Vector2, Vector3};
+use physics::physics_context::{PhysicsContext, RapierRigidBodyHandle};
+use physics::pickup_physics::PickedUp;
+use scene::asset::AssetId;
+use scene::billboard::{Billboard, BillboardBlend};
+use scene::pickup::Pickupable;
+use scene::surface::SurfaceType;
+use scene::texture::{
+    AddressMode, BytesTextureData, CpuTexture, Filter, MipmapMode, SamplerInfo, TextureFormat,
+};
+use scene::transform::Transform;
+use time::time::Time;
+use time::time_manager::{is_rewinding, TimeManager};
+
+use crate::level_flags::LevelFlags;
+
+/// How many glowing particles fly towards a powered magnet
+const PARTICLE_COUNT: usize = 16;
+/// In meters per second
+const PARTICLE_SPEED: f32 = 1.5;
+/// In meters
+const PARTICLE_SIZE: f32 = 0.12;
+/// Above 1, so that the particles glow with the bloom
+const PARTICLE_COLOR: [f32; 3] = [0.8, 1.6, 3.0];
+const PARTICLE_TEXTURE_SIZE: u32 = 16;
+/// Spreads the particles evenly around the axis of the cone
+const GOLDEN_ANGLE: f32 = 2.399_963;
+
+/// Derived from the flags, so it rewinds together with them
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MagnetPowered(pub bool);
+
+/// One of the particles that show the cone of a magnet
+#[derive(Component, Debug)]
+struct MagnetParticle {
+    magnet: Entity,
+    index: usize,
+}
+
+/// The particles belong to the level of the magnet, so they get despawned together with it
+fn add_magnet_particles(
+    mut commands: Commands,
+    query: Query<(Entity, &LevelId), (With<Magnet>, Without<MagnetPowered>)>,
+    mut particle_texture: Local<Option<Arc<CpuTexture>>>,
+) {
+    for (entity, level_id) in query.iter() {
+        commands.entity(entity).insert(MagnetPowered(false));
+
+        let texture = particle_texture
+            .get_or_insert_with(create_particle_texture)
+            .clone();
+        for index in 0..PARTICLE_COUNT {
+            commands.spawn((
+                MagnetParticle {
+                    magnet: entity,
+                    index,
+                },
+                Billboard::new(texture.clone(), Vector2::new(PARTICLE_SIZE, PARTICLE_SIZE))
+                    .with_color(Vector3::zeros())
+                    .with_blend(BillboardBlend::Additive),
+                Transform::default(),
+                level_id.clone(),
+            ));
+        }
+    }
+}
+
+/// A white dot that fades out towards its edge
+fn create_particle_texture() -> Arc<CpuTexture> {
+    let center = (PARTICLE_TEXTURE_SIZE as f32 - 1.0) * 0.5;
+    let mut bytes =
+        Vec::with_capacity((PARTICLE_TEXTURE_SIZE * PARTICLE_TEXTURE_SIZE * 4) as usize);
+    for y in 0..PARTICLE_TEXTURE_SIZE {
+        for x in 0..PARTICLE_TEXTURE_SIZE {
+            let distance = Vector2::new(x as f32 - center, y as f32 - center).norm() / center;
+            let alpha = (1.0 - distance).clamp(0.0, 1.0);
+            bytes.extend_from_slice(&[255, 255, 255, (alpha * alpha * 255.0) as u8]);
+        }
+    }
+
+    Arc::new(CpuTexture {
+        id: AssetId::new_v4(),
+        data: Box::new(BytesTextureData::new(
+            (PARTICLE_TEXTURE_SIZE, PARTICLE_TEXTURE_SIZE),
+            TextureFormat::R8G8B8A8_UNORM,
+            bytes,
+        )),
+        sampler_info: SamplerInfo {
+            min_filter: Filter::Linear,
+            mag_filter: Filter::Linear,
+            mipmap_mode: MipmapMode::Nearest,
+            address_mode: [AddressMode::ClampToEdge; 3],
+        },
+    })
+}
+
+fn update_magnet_power(
+    level_flags: Res<LevelFlags>,
+    mut query: Query<(&Magnet, &mut MagnetPowered)>,
+) {
+    for (magnet, mut powered) in query.iter_mut() {
+        let is_powered = magnet
+            .powered_when
+            .iter()
+            .all(|(level_id, flag_id)| level_flags.get(*level_id, *flag_id));
+        if powered.0 != is_powered {
+            powered.0 = is_powered;
+        }
+    }
+}
+
+/// Pulls the bodies inside of the cone towards the magnet, the closer they are the stronger.
+/// Held bodies follow the player instead.
+fn attract_bodies(
+    time: Res<Time>,
+    mut physics_context: ResMut<PhysicsContext>,
+    magnets: Query<(&Magnet, &MagnetPowered, &Transform)>,
+    bodies: Query<
+        (
+            &Transform,
+            &RapierRigidBodyHandle,
+            Option<&Pickupable>,
+            Option<&SurfaceType>,
+        ),
+        Without<PickedUp>,
+    >,
+) {
+    let delta_seconds = time.delta_seconds();
+    for (magnet, powered, magnet_transform) in magnets.iter() {
+        if !powered.0 {
+            continue;
+        }
+
+        for (transform, rigid_body_handle, pickupable, surface) in bodies.iter() {
+            if pickupable.is_none() && surface != Some(&SurfaceType::Metal) {
+                continue;
+            }
+
+            let to_magnet = magnet_transform.position - transform.position;
+            let distance = to_magnet.norm();
+            if distance > magnet.range || distance <= f32::EPSILON {
+                continue;
+            }
+            let to_magnet = to_magnet / distance;
+            if (-to_magnet).dot(&magnet.direction) < magnet.cone_angle.cos() {
+                continue;
+            }
+
+            let rigid_body = match physics_context
+                .rigid_bodies
+                .get_mut(rigid_body_handle.handle)
+            {
+                Some(rigid_body) if rigid_body.is_dynamic() => rigid_body,
+                _ => continue,
+            };
+            let falloff = 1.0 - distance / magnet.range;
+            let impulse = to_magnet * magnet.strength * falloff * rigid_body.mass() * delta_seconds;
+            rigid_body.apply_impulse(impulse, true);
+        }
+    }
+}
+
+/// The particles fly from the end of the cone to the magnet. They follow the level time,
+/// so they fly backwards while rewinding, and they disappear when the magnet loses its power.
+fn update_magnet_particles(
+    time_manager: Res<TimeManager>,
+    magnets: Query<(&Magnet, &MagnetPowered, &Transform), Without<MagnetParticle>>,
+    mut particles: Query<(&MagnetParticle, &mut Transform, &mut Billboard)>,
+) {
+    let level_time = time_manager.level_time().as_secs_f32();
+    for (particle, mut transform, mut billboard) in particles.iter_mut() {
+        let (magnet, powered, magnet_transform) = match magnets.get(particle.magnet) {
+            Ok(magnet) => magnet,
+            Err(_) => continue,
+        };
+
+        let color = if powered.0 {
+            Vector3::from(PARTICLE_COLOR)
+        } else {
+            Vector3::zeros()
+        };
+        if billboard.color != color {
+            billboard.color = color;
+        }
+        if !powered.0 {
+            continue;
+        }
+
+        let offset = particle.index as f32 / PARTICLE_COUNT as f32;
+        let progress = (level_time * PARTICLE_SPEED / magnet.range + offset).fract();
+        let distance = magnet.range * (1.0 - progress);
+
+        let (tangent, bitangent) = perpendicular_axes(&magnet.direction);
+        let around = particle.index as f32 * GOLDEN_ANGLE % TAU;
+        let side = tangent * around.cos() + bitangent * around.sin();
+        // Spread out between the axis and the side of the cone
+        let angle = magnet.cone_angle * (particle.index as f32 * 0.618).fract();
+        let direction = magnet.direction * angle.cos() + side * angle.sin();
+
+        transform.position = magnet_transform.position + direction * distance;
+    }
+}
+
+fn perpendicular_axes(direction: &Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+    let helper = if direction.y.abs() < 0.99 {
+        Vector3::y()
+    } else {
+        Vector3::x()
+    };
+    let tangent = direction.cross(&helper).normalize();
+    let bitangent = direction.cross(&tangent);
+    (tangent, bitangent)
+}
+
+pub struct MagnetPlugin;
+
+impl Plugin for MagnetPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_system(add_magnet_particles)
+            .with_system(update_magnet_power.after(add_magnet_particles))
+            .with_system(
+                attract_bodies
+                    .after(update_magnet_power)
+                    .run_if(not(is_rewinding)),
+            )
+            .with_system(update_magnet_particles.after(update_magnet_power));
+    }
+}
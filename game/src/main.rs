@@ -11,40 +11,92 @@ use bevy_ecs::query::{With, Without};
 use bevy_ecs::schedule::IntoSystemConfig;
 use bevy_ecs::schedule::IntoSystemSetConfig;
 use debug::setup_debugging;
+use game::achievements::AchievementsPlugin;
+use game::alarm::AlarmPlugin;
+use game::color_grading::ColorGradingPlugin;
+use game::door::DoorPlugin;
+use game::elevator::ElevatorPlugin;
+use game::footsteps::FootstepsPlugin;
 use game::game_over::{GameOver, GameOverPlugin};
+use game::game_state::{is_playing, GameState};
+use game::interaction::InteractionPlugin;
+use game::level_environment::LevelEnvironmentPlugin;
 use game::level_flags::{FlagChange, LevelFlags, LevelFlagsPlugin};
+use game::level_fog::LevelFogPlugin;
+#[cfg(feature = "dev-tools")]
+use game::level_hot_reload::LevelHotReloadPlugin;
+use game::level_restart::LevelRestartPlugin;
+use game::level_streaming::{LevelStreaming, LevelStreamingPlugin};
+use game::magnet::MagnetPlugin;
+use game::main_menu::MainMenuPlugin;
+use game::objectives::ObjectivesPlugin;
+use game::photo_mode::PhotoModePlugin;
 use game::pickup_system::PickupPlugin;
+use game::platform_riding::PlatformRidingPlugin;
 use game::rewind_power::{RewindPower, RewindPowerPlugin};
+use game::rewind_speed::{RewindSpeed, RewindSpeedPlugin};
+use game::rewind_target::RewindTargetPlugin;
+use game::teleporter::TeleporterPlugin;
+use game::time_of_day::TimeOfDayPlugin;
+use game::ui_interaction::UIInteractionPlugin;
+use game::video_texture::VideoTexturePlugin;
+use input::gamepad::{GamepadButton, GamepadState};
 use input::input_map::InputMap;
-use loader::config_loader::LoadableConfig;
+use input::plugin::InputPlugin;
+use loader::config_loader::{LoadableConfig, PressurePlateIndicator};
 use loader::loader::{PressurePlate, SceneLoader};
 use scene::flag_trigger::FlagTrigger;
+use scene::hierarchy::propagate_transforms;
 use scene::level::{NextLevelTrigger, Spawnpoint};
 use windowing::event::{MouseButton, VirtualKeyCode};
 
+use std::path::{Path, PathBuf};
 use std::time::Instant;
+use time::game_rng::GameRngPlugin;
 use time::time::Time;
 use time::time_manager::{game_change, is_rewinding, TimeManager};
 
-use bevy_ecs::system::{Commands, Res, ResMut};
+use bevy_ecs::system::{Commands, Local, Res, ResMut};
 
+use game::captions::CaptionsPlugin;
 use game::core::application::{AppConfig, AppStage, Application};
+use game::cutscene::CutscenePlugin;
 use game::game_ui::UIPlugin;
-use game::player::{Player, PlayerControllerSettings, PlayerPlugin, PlayerSpawnSettings};
+use game::heatmap::HeatmapPlugin;
+use game::player::{
+    Player, PlayerControllerSettings, PlayerPlugin, PlayerPluginSets, PlayerSpawnSettings,
+    FALL_OUT_OF_WORLD_HEIGHT,
+};
+use game::pressure_plate_indicator::PressurePlateMaterials;
+use game::replay::{ReplayPlugin, ReplayPluginSets};
+use game::save_states::{SaveStatesPlugin, SaveStatesPluginSets};
+use game::screen_effects::ScreenEffectsPlugin;
+use game::settings_menu::SettingsMenuPlugin;
+use game::speedrun_timer::SpeedrunTimerPlugin;
+use game::telemetry::TelemetryPlugin;
 
+use physics::physics_context::{PhysicsContext, RapierRigidBodyHandle};
 use physics::physics_events::CollisionEvent;
-use scene::model::Model;
+use scene::material::MaterialOverride;
 
 use crate::levels::level0::Level0Plugin;
 use crate::levels::level1::Level1Plugin;
 use crate::levels::level2::Level2Plugin;
 use scene::transform::{Transform, TransformBuilder};
 
-fn spawn_world(mut commands: Commands, scene_loader: Res<SceneLoader>) {
+const LEVELS_PATH: &str = "./assets/scene/levels/levels.gltf";
+
+fn spawn_world(
+    mut commands: Commands,
+    scene_loader: Res<SceneLoader>,
+    level_flags: Res<LevelFlags>,
+    mut level_streaming: ResMut<LevelStreaming>,
+) {
     let before = Instant::now();
-    scene_loader
-        .load_default_scene("./assets/scene/levels/levels.gltf", &mut commands)
-        .unwrap();
+    let flag_counts = level_flags.flag_counts();
+    level_streaming.load(LevelId::new(0), &scene_loader, &mut commands, &flag_counts);
+    // The other levels get loaded when the player gets closer to them
+    level_streaming.preload(LevelId::new(1), &scene_loader, &flag_counts);
     println!(
         "Loading the scene took {}sec",
         before.elapsed().as_secs_f64()
@@ -87,21 +139,37 @@ fn flag_system(
     mut level_flags: ResMut<LevelFlags>,
     mut game_changes: ResMut<game_change::GameChangeHistory<FlagChange>>,
     mut flag_triggers: Query<(&mut FlagTrigger, &EntityEvent<CollisionEvent>)>,
+    physics_context: Res<PhysicsContext>,
+    rigid_bodies: Query<&RapierRigidBodyHandle>,
     time_manager: Res<TimeManager>,
 ) {
     let rewinding = is_rewinding(time_manager);
     for (mut flag_trigger, collision_events) in flag_triggers.iter_mut() {
         for collision_event in collision_events.iter() {
             match collision_event {
-                CollisionEvent::Started(_e2) => {
+                CollisionEvent::Started(e2) => {
                     flag_trigger.current_intersections += 1;
+                    flag_trigger.intersecting.push(*e2);
                 }
-                CollisionEvent::Stopped(_e2) => {
+                CollisionEvent::Stopped(e2) => {
                     flag_trigger.current_intersections -= 1;
+                    if let Some(index) = flag_trigger.intersecting.iter().position(|e| e == e2) {
+                        flag_trigger.intersecting.swap_remove(index);
+                    }
                 }
             };
         }
-        let level_flag_value = flag_trigger.current_intersections > 0;
+        if flag_trigger.min_mass.is_some() {
+            let current_mass = flag_trigger
+                .intersecting
+                .iter()
+                .filter_map(|entity| rigid_bodies.get(*entity).ok())
+                .filter_map(|handle| physics_context.rigid_bodies.get(handle.handle))
+                .map(|rigid_body| rigid_body.mass())
+                .sum();
+            flag_trigger.current_mass = current_mass;
+        }
+        let level_flag_value = flag_trigger.is_active();
         if !rewinding {
             level_flags.set_and_record(
                 flag_trigger.level_id,
@@ -114,18 +182,24 @@ fn flag_system(
 }
 
 fn pressure_plate_system(
-    mut query: Query<(&mut Model, &PressurePlate, &FlagTrigger)>,
+    mut query: Query<(&mut MaterialOverride, &PressurePlate, &FlagTrigger)>,
     level_flags: Res<LevelFlags>,
+    indicator: Res<PressurePlateIndicator>,
+    mut pressure_plate_materials: ResMut<PressurePlateMaterials>,
+    time: Res<Time>,
 ) {
-    for (mut model, pressure_plate, flag_trigger) in query.iter_mut() {
-        for primitive in model.primitives.iter_mut() {
-            let active = level_flags.get(flag_trigger.level_id, flag_trigger.flag_id);
-            primitive.material = if active {
-                pressure_plate.active_material.clone()
-            } else {
-                pressure_plate.inactive_material.clone()
-            };
-        }
+    let time_since_startup = time.time_since_startup().as_secs_f32();
+    for (mut material_override, pressure_plate, flag_trigger) in query.iter_mut() {
+        let active = level_flags.get(flag_trigger.level_id, flag_trigger.flag_id);
+        material_override.set(Some(if active {
+            pressure_plate_materials.active_material(
+                &pressure_plate.active_material,
+                *indicator,
+                time_since_startup,
+            )
+        } else {
+            pressure_plate.inactive_material.clone()
+        }));
     }
 }
 
@@ -154,7 +228,7 @@ fn fall_out_of_world_system(
     spawnpoints: Query<(&Transform, &LevelId), (With<Spawnpoint>, Without<Player>)>,
 ) {
     for mut transform in players_query.iter_mut() {
-        if transform.position.y < -10.0 {
+        if transform.position.y < FALL_OUT_OF_WORLD_HEIGHT {
             let spawnpoint = spawnpoints
                 .iter()
                 .find(|(_, level_id)| level_id == &&current_level.level_id)
@@ -165,29 +239,55 @@ fn fall_out_of_world_system(
     }
 }
 
+/// Rewinds while the right mouse button, V or the left trigger is held.
+/// With the toggle setting, pressing one of them starts the rewinding and pressing again stops it.
 fn read_rewind_input(
-    time_manager: Res<TimeManager>,
+    mut time_manager: ResMut<TimeManager>,
     input: Res<InputMap>,
+    gamepad: Res<GamepadState>,
     game_over: Res<GameOver>,
+    game_state: Res<GameState>,
+    rewind_speed: Res<RewindSpeed>,
+    player_settings_query: Query<&PlayerControllerSettings, With<Player>>,
+    mut was_pressed: Local<bool>,
+    mut is_toggled: Local<bool>,
 ) {
-    if game_over.is_game_over() {
-        return;
-    }
+    let is_pressed = input.is_mouse_pressed(MouseButton::Right)
+        || input.is_pressed(VirtualKeyCode::V)
+        || gamepad.button_value(GamepadButton::LeftTrigger2) > 0.0;
+    let just_pressed = is_pressed && !*was_pressed;
+    *was_pressed = is_pressed;
 
-    if input.is_mouse_pressed(MouseButton::Right) {
-        if input.is_pressed(VirtualKeyCode::LShift) || input.is_pressed(VirtualKeyCode::RShift) {
-            time_manager.rewind_next_frame(3.0);
-        } else {
-            time_manager.rewind_next_frame(1.0);
+    let toggle_rewind = player_settings_query
+        .get_single()
+        .map(|settings| settings.toggle_rewind)
+        .unwrap_or(false);
+    let can_rewind = !game_over.is_game_over() && game_state.is_playing();
+
+    let wants_to_rewind = if toggle_rewind {
+        if just_pressed {
+            *is_toggled = !*is_toggled;
         }
+        // Dying or opening the menu stops the rewinding
+        *is_toggled &= can_rewind;
+        *is_toggled
+    } else {
+        *is_toggled = false;
+        is_pressed
+    };
+    if wants_to_rewind && can_rewind {
+        time_manager.set_rewind_speed(rewind_speed.speed());
+    } else {
+        time_manager.set_rewind_speed(0.0);
     }
 }
 
 struct GamePlugin;
 impl Plugin for GamePlugin {
     fn build(&mut self, app: &mut PluginAppAccess) {
-        app.with_startup_system(spawn_world)
+        app.with_startup_system(spawn_world.after(setup_levels))
             .with_startup_system(setup_levels)
+            .with_resource(PressurePlateMaterials::default())
             .with_plugin(PickupPlugin)
             .with_plugin(GameOverPlugin)
             .with_set(GameOverPlugin::system_set().in_set(AppStage::EventUpdate))
@@ -197,12 +297,53 @@ impl Plugin for GamePlugin {
                     .in_set(AppStage::BeforeUpdate)
                     .after(GameOverPlugin::system_set()),
             )
+            .with_plugin(LevelStreamingPlugin::new(LEVELS_PATH))
+            .with_set(LevelStreamingPlugin::system_set().in_set(AppStage::EventUpdate))
+            .with_plugin(LevelRestartPlugin)
+            .with_set(
+                LevelRestartPlugin::system_set()
+                    .in_set(AppStage::BeforeUpdate)
+                    .before(reset_rewind_power)
+                    .run_if(is_playing),
+            )
             .with_plugin(RewindPowerPlugin)
             .with_set(
                 RewindPowerPlugin::system_set()
                     .in_set(AppStage::Update)
                     .before(UIPlugin::system_set()),
             )
+            .with_plugin(InteractionPlugin)
+            .with_set(
+                InteractionPlugin::system_set()
+                    .in_set(AppStage::Update)
+                    .before(UIPlugin::system_set())
+                    .run_if(is_playing),
+            )
+            .with_plugin(ObjectivesPlugin)
+            .with_set(
+                ObjectivesPlugin::system_set()
+                    .in_set(AppStage::Update)
+                    .before(UIPlugin::system_set()),
+            )
+            .with_plugin(TimeOfDayPlugin)
+            .with_set(TimeOfDayPlugin::system_set().in_set(AppStage::Update))
+            .with_plugin(LevelEnvironmentPlugin)
+            .with_set(
+                LevelEnvironmentPlugin::system_set()
+                    .in_set(AppStage::Update)
+                    .after(TimeOfDayPlugin::system_set()),
+            )
+            .with_plugin(LevelFogPlugin)
+            .with_set(
+                LevelFogPlugin::system_set()
+                    .in_set(AppStage::Update)
+                    .after(LevelEnvironmentPlugin::system_set()),
+            )
+            .with_plugin(ColorGradingPlugin)
+            // After the alarms of the levels got updated
+            .with_set(ColorGradingPlugin::system_set().in_set(AppStage::BeforeRender))
+            .with_plugin(VideoTexturePlugin)
+            .with_set(VideoTexturePlugin::system_set().in_set(AppStage::Update))
             .with_plugin(UIPlugin)
             .with_set(
                 UIPlugin::system_set()
@@ -223,6 +364,36 @@ impl Plugin for GamePlugin {
                     .in_set(AppStage::UpdateLevel)
                     .after(Level1Plugin::system_set()),
             )
+            .with_plugin(AlarmPlugin)
+            .with_set(
+                AlarmPlugin::system_set()
+                    .in_set(AppStage::UpdateLevel)
+                    .after(Level2Plugin::system_set()),
+            )
+            .with_plugin(DoorPlugin)
+            .with_set(
+                DoorPlugin::system_set()
+                    .in_set(AppStage::UpdateLevel)
+                    .after(AlarmPlugin::system_set()),
+            )
+            .with_plugin(ElevatorPlugin)
+            .with_set(
+                ElevatorPlugin::system_set()
+                    .in_set(AppStage::UpdateLevel)
+                    .after(DoorPlugin::system_set()),
+            )
+            .with_plugin(MagnetPlugin)
+            .with_set(
+                MagnetPlugin::system_set()
+                    .in_set(AppStage::UpdateLevel)
+                    .after(ElevatorPlugin::system_set()),
+            )
+            .with_plugin(TeleporterPlugin)
+            .with_set(
+                TeleporterPlugin::system_set()
+                    .in_set(AppStage::Update)
+                    .run_if(is_playing),
+            )
             .with_system(next_level_trigger_system.in_set(AppStage::Update))
             .with_system(
                 flag_system.in_set(AppStage::Update), // .run_if(not(is_rewinding)),
@@ -238,30 +409,203 @@ impl Plugin for GamePlugin {
                     .in_set(AppStage::BeforeUpdate)
                     .after(LevelFlagsPlugin::system_set()),
             )
-            .with_system(read_rewind_input.in_set(AppStage::BeforeUpdate));
+            .with_plugin(RewindSpeedPlugin)
+            .with_set(
+                RewindSpeedPlugin::system_set()
+                    .in_set(AppStage::BeforeUpdate)
+                    .before(read_rewind_input)
+                    .run_if(is_playing),
+            )
+            .with_system(read_rewind_input.in_set(AppStage::BeforeUpdate))
+            .with_plugin(RewindTargetPlugin)
+            .with_set(
+                RewindTargetPlugin::system_set()
+                    .in_set(AppStage::BeforeUpdate)
+                    .after(read_rewind_input)
+                    .run_if(is_playing),
+            );
+
+        #[cfg(feature = "dev-tools")]
+        app.with_plugin(LevelHotReloadPlugin::new(LEVELS_PATH))
+            .with_set(
+                LevelHotReloadPlugin::system_set()
+                    .in_set(AppStage::EventUpdate)
+                    .after(LevelStreamingPlugin::system_set()),
+            );
+    }
+}
+
+const CONFIG_PATH: &str = "./assets/config.json";
+const PROGRESS_PATH: &str = "./assets/progress.json";
+const ACHIEVEMENTS_PATH: &str = "./assets/achievements.json";
+const FOOTSTEP_SOUNDS_PATH: &str = "./assets/sounds/footsteps";
+const SPLITS_PATH: &str = "./splits.txt";
+const REPLAYS_DIRECTORY: &str = "./replays";
+const TELEMETRY_DIRECTORY: &str = "./telemetry";
+
+/// The path after the flag, like `--replay <path>`
+fn path_from_args(flag: &str) -> Option<PathBuf> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return args.next().map(PathBuf::from);
+        }
     }
+    None
 }
 
 fn main() {
     let _guard = setup_debugging();
 
     // Only the main project actually loads the config from the file
-    let config: AppConfig = LoadableConfig::load("./assets/config.json").into();
+    let config: AppConfig = LoadableConfig::load(CONFIG_PATH).into();
+
+    let speedrun_timer_plugin = SpeedrunTimerPlugin::new(config.speedrun_timer, SPLITS_PATH);
+    let captions_plugin = CaptionsPlugin::new(config.captions);
+    let replay_plugin = ReplayPlugin::new(
+        config
+            .record_replays
+            .then(|| PathBuf::from(REPLAYS_DIRECTORY)),
+        // `--replay <path>` plays back a recorded level run, once that level is started
+        path_from_args("--replay"),
+    );
+    let telemetry_plugin =
+        TelemetryPlugin::new(config.telemetry.then(|| PathBuf::from(TELEMETRY_DIRECTORY)));
 
     let player_spawn_settings = PlayerSpawnSettings {
         initial_transform: TransformBuilder::new()
             .position([0.0, 1.0, 3.0].into())
             .build(),
         controller_settings: PlayerControllerSettings::default()
-            .with_sensitivity(config.mouse_sensitivity),
+            .with_sensitivity(config.mouse_sensitivity)
+            .with_smooth_camera(config.camera_smoothing)
+            .with_mouse_acceleration(config.mouse_acceleration)
+            .with_invert_y(config.invert_y)
+            .with_toggle_rewind(config.toggle_rewind)
+            .with_camera_effects(config.camera_effects),
         free_cam_activated: false,
     };
 
     let mut application = Application::new(config);
+    // Escape opens the main menu instead
+    application.disable_exit_on_escape();
     application
         .app
         .with_plugin(GamePlugin)
-        .with_plugin(PlayerPlugin::new(player_spawn_settings));
+        .with_plugin(PlayerPlugin::new(player_spawn_settings))
+        .with_plugin(UIInteractionPlugin)
+        .with_set(
+            UIInteractionPlugin::system_set()
+                .in_set(AppStage::BeforeUpdate)
+                .before(SettingsMenuPlugin::system_set()),
+        )
+        .with_plugin(SettingsMenuPlugin::new(CONFIG_PATH))
+        .with_set(
+            SettingsMenuPlugin::system_set()
+                .in_set(AppStage::BeforeUpdate)
+                .before(PlayerPluginSets::UpdateInput),
+        )
+        .with_plugin(MainMenuPlugin::new(PROGRESS_PATH))
+        .with_set(
+            MainMenuPlugin::system_set()
+                .in_set(AppStage::BeforeUpdate)
+                .after(SettingsMenuPlugin::system_set())
+                .before(PlayerPluginSets::UpdateInput),
+        )
+        .with_plugin(PhotoModePlugin)
+        .with_set(
+            PhotoModePlugin::system_set()
+                .in_set(AppStage::BeforeUpdate)
+                .after(MainMenuPlugin::system_set()),
+        )
+        .with_plugin(CutscenePlugin)
+        .with_set(
+            CutscenePlugin::system_set()
+                .in_set(AppStage::BeforeUpdate)
+                .after(MainMenuPlugin::system_set())
+                .before(PlayerPluginSets::UpdateInput),
+        )
+        // The player and the held objects only react to the input while playing
+        .with_set(PlayerPluginSets::UpdateInput.run_if(is_playing))
+        .with_set(PlayerPluginSets::Update.run_if(is_playing))
+        .with_set(PlayerPluginSets::UpdateCamera.run_if(is_playing))
+        .with_set(PickupPlugin::system_set().run_if(is_playing))
+        .with_plugin(replay_plugin)
+        .with_set(
+            ReplayPluginSets::Input
+                .in_set(AppStage::EventUpdate)
+                .before(InputPlugin::system_set()),
+        )
+        .with_set(
+            ReplayPluginSets::PlayerState
+                .in_set(AppStage::BeforeRender)
+                .before(PlayerPluginSets::UpdateCamera),
+        )
+        .with_plugin(SaveStatesPlugin)
+        .with_set(
+            SaveStatesPluginSets::Input
+                .in_set(AppStage::BeforeUpdate)
+                .run_if(is_playing),
+        )
+        .with_set(SaveStatesPluginSets::Load.in_set(AppStage::BeforeRender))
+        .with_plugin(FootstepsPlugin::new(FOOTSTEP_SOUNDS_PATH))
+        .with_set(
+            FootstepsPlugin::system_set()
+                .in_set(AppStage::BeforeRender)
+                .before(GameRngPlugin::system_set())
+                .run_if(is_playing),
+        )
+        .with_plugin(ScreenEffectsPlugin)
+        .with_set(
+            ScreenEffectsPlugin::system_set()
+                .in_set(AppStage::BeforeRender)
+                .after(FootstepsPlugin::system_set()),
+        )
+        .with_plugin(PlatformRidingPlugin)
+        .with_set(
+            PlatformRidingPlugin::system_set()
+                .in_set(AppStage::BeforeRender)
+                .before(propagate_transforms)
+                .run_if(is_playing),
+        )
+        .with_plugin(telemetry_plugin)
+        .with_set(
+            TelemetryPlugin::system_set()
+                .in_set(AppStage::BeforeRender)
+                .after(PlayerPluginSets::UpdateCamera),
+        )
+        .with_plugin(AchievementsPlugin::new(
+            ACHIEVEMENTS_PATH,
+            Path::new(LEVELS_PATH).parent().unwrap(),
+        ))
+        .with_set(
+            AchievementsPlugin::system_set()
+                .in_set(AppStage::Update)
+                .after(InteractionPlugin::system_set())
+                .before(UIPlugin::system_set())
+                .run_if(is_playing),
+        )
+        .with_plugin(captions_plugin)
+        .with_set(
+            CaptionsPlugin::system_set()
+                .in_set(AppStage::UpdateLevel)
+                .after(AlarmPlugin::system_set())
+                .after(DoorPlugin::system_set()),
+        )
+        .with_plugin(speedrun_timer_plugin)
+        .with_set(
+            SpeedrunTimerPlugin::system_set()
+                .in_set(AppStage::Update)
+                .before(UIPlugin::system_set()),
+        );
+
+    // `--heatmap <path>` shows where the players of a playtest have been, with the debug lines
+    if let Some(heatmap_path) = path_from_args("--heatmap") {
+        application
+            .app
+            .with_plugin(HeatmapPlugin::new(heatmap_path))
+            .with_set(HeatmapPlugin::system_set().in_set(AppStage::BeforeRender));
+    }
 
     application.run();
 }
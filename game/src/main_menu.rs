@@ -0,0 +1,554 @@
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::*;
+use input::events::{ElementState, KeyboardInput, VirtualKeyCode};
+use input::gamepad::{GamepadButton, GamepadButtonInput};
+use levels::current_level::{CurrentLevel, NextLevel};
+use levels::level_id::LevelId;
+use loader::atlas_loader::LoadableAtlas;
+use nalgebra::{UnitQuaternion, Vector2, Vector3};
+use physics::player_physics::PlayerCharacterController;
+use scene::camera::Camera;
+use scene::level::Spawnpoint;
+use scene::transform::Transform;
+use scene::ui_component::{UIAnchor, UIComponent, UITexturePosition};
+use scene::ui_text::UIText;
+use time::time::Time;
+
+use crate::core::application::AppExit;
+use crate::cutscene::PlayCutscene;
+use crate::game_state::{is_in_main_menu, is_playing, GameState};
+use crate::level_streaming::LevelStreaming;
+use crate::player::{Player, PlayerControllerSettings};
+use crate::progress::Progress;
+use crate::settings_menu::{Setting, SettingsMenu};
+use crate::ui_interaction::{UIClicked, UIHovered, UIInteraction};
+
+/// The settings page has the most entries, all settings and the back entry
+const MAX_MENU_ENTRIES: usize = Setting::ALL.len() + 1;
+/// Space between the menu entries, in pixels of the reference resolution
+const ENTRY_SPACING: f32 = 36.0;
+/// Width of the panel behind the menu entries, in pixels of the reference resolution
+const PANEL_WIDTH: f32 = 600.0;
+/// How fast the camera turns around in the background of the menu, in radians per second
+const BACKGROUND_ROTATION_SPEED: f32 = 0.1;
+/// Height of the camera above the spawnpoint, in meters
+const BACKGROUND_CAMERA_HEIGHT: f32 = 1.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuPage {
+    Main,
+    LevelSelect,
+    Settings,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuEntry {
+    NewGame,
+    /// Goes to the furthest level that was reached
+    Continue,
+    /// Goes back to the game that was interrupted by the menu
+    Resume,
+    PhotoMode,
+    LevelSelect,
+    Settings,
+    Quit,
+    Level(LevelId),
+    Setting(Setting),
+    Back,
+}
+
+/// The menu that the game starts in, and that Escape goes back to.
+/// Levels can only be started once per session, so the level select only offers the current level
+/// and the ones after it, up to the furthest level that was ever reached.
+#[derive(Resource)]
+pub struct MainMenu {
+    page: MenuPage,
+    selected: usize,
+    /// A game is running in the background, so the menu can go back to it
+    has_started: bool,
+    /// The player gets moved there as soon as the spawnpoint of the level has been loaded
+    target_level: Option<LevelId>,
+}
+
+impl MainMenu {
+    fn new() -> Self {
+        Self {
+            page: MenuPage::Main,
+            selected: 0,
+            has_started: false,
+            target_level: None,
+        }
+    }
+
+    fn entries(&self, current_level: &CurrentLevel, progress: &Progress) -> Vec<MenuEntry> {
+        match self.page {
+            MenuPage::Main => {
+                let mut entries = vec![];
+                if self.has_started {
+                    entries.push(MenuEntry::Resume);
+                    entries.push(MenuEntry::PhotoMode);
+                } else {
+                    entries.push(MenuEntry::NewGame);
+                    if progress.unlocked_level().id() > 0 {
+                        entries.push(MenuEntry::Continue);
+                    }
+                }
+                entries.extend([MenuEntry::LevelSelect, MenuEntry::Settings, MenuEntry::Quit]);
+                entries
+            }
+            MenuPage::LevelSelect => {
+                let first_level = if self.has_started {
+                    current_level.level_id.id()
+                } else {
+                    0
+                };
+                let mut entries: Vec<_> = (first_level..=progress.unlocked_level().id())
+                    .map(|id| MenuEntry::Level(LevelId::new(id)))
+                    .take(MAX_MENU_ENTRIES - 1)
+                    .collect();
+                entries.push(MenuEntry::Back);
+                entries
+            }
+            MenuPage::Settings => Setting::ALL
+                .iter()
+                .map(|setting| MenuEntry::Setting(*setting))
+                .chain([MenuEntry::Back])
+                .collect(),
+        }
+    }
+
+    fn open_page(&mut self, page: MenuPage) {
+        self.page = page;
+        self.selected = 0;
+    }
+
+    fn start_level(&mut self, level_id: LevelId, current_level: &CurrentLevel) {
+        // The first level is already the current one when the game starts
+        if level_id != current_level.level_id {
+            current_level.start_next_level(level_id);
+        }
+        self.target_level = Some(level_id);
+        self.has_started = true;
+    }
+}
+
+#[derive(Component)]
+struct UIMainMenuTitle;
+
+#[derive(Component)]
+struct UIMainMenuEntry {
+    index: usize,
+}
+
+#[derive(Component)]
+struct UIMainMenuPanel;
+
+/// The frame around the selected entry
+#[derive(Component)]
+struct UIMainMenuSelection;
+
+fn entry_offset(index: usize) -> Vector2<f32> {
+    Vector2::new(64.0, -80.0 + index as f32 * ENTRY_SPACING)
+}
+
+/// Scales the sprite of the component to the size, in pixels of the reference resolution
+fn set_sprite_size(ui_component: &mut UIComponent, size: Vector2<f32>) {
+    if let Some(region) = ui_component.region {
+        ui_component.texture_position.scale =
+            size.component_div(&Vector2::new(region.width as f32, region.height as f32));
+    }
+}
+
+fn spawn_main_menu_ui(mut commands: Commands) {
+    let title = UIText::new("Cat to the past");
+    commands.spawn((
+        UIComponent {
+            texture: title.create_texture(),
+            region: None,
+            texture_position: UITexturePosition {
+                scale: Vector2::new(6.0, 6.0),
+                ..UITexturePosition::default()
+            },
+            anchor: UIAnchor::Left,
+            offset: Vector2::new(64.0, -200.0),
+            depth: 0.0,
+            opacity: 1.0,
+            visible: true,
+        },
+        title,
+        UIMainMenuTitle,
+    ));
+
+    for index in 0..MAX_MENU_ENTRIES {
+        let text = UIText::new("");
+        commands.spawn((
+            UIComponent {
+                texture: text.create_texture(),
+                region: None,
+                texture_position: UITexturePosition {
+                    scale: Vector2::new(3.0, 3.0),
+                    ..UITexturePosition::default()
+                },
+                anchor: UIAnchor::Left,
+                offset: entry_offset(index),
+                depth: 0.0,
+                opacity: 1.0,
+                visible: false,
+            },
+            text,
+            UIMainMenuEntry { index },
+            UIInteraction::default(),
+        ));
+    }
+
+    let atlas = LoadableAtlas::load("assets/textures/ui_atlas.json").create_atlas();
+    commands.spawn((
+        UIComponent {
+            texture: atlas.texture.clone(),
+            region: atlas.region("panel"),
+            texture_position: UITexturePosition::default(),
+            anchor: UIAnchor::Left,
+            offset: entry_offset(0) - Vector2::new(24.0, 16.0),
+            depth: -0.9,
+            opacity: 1.0,
+            visible: false,
+        },
+        UIMainMenuPanel,
+    ));
+    commands.spawn((
+        UIComponent {
+            texture: atlas.texture.clone(),
+            region: atlas.region("selection"),
+            texture_position: UITexturePosition::default(),
+            anchor: UIAnchor::Left,
+            offset: entry_offset(0),
+            depth: -0.8,
+            opacity: 1.0,
+            visible: false,
+        },
+        UIMainMenuSelection,
+    ));
+}
+
+enum MenuInput {
+    Up,
+    Down,
+    Confirm,
+    /// The cursor moved onto an entry
+    Hover(usize),
+    /// An entry got clicked, which selects and confirms it
+    Click(usize),
+    Back,
+    /// Opens the menu while playing, and otherwise works like going back
+    Pause,
+}
+
+/// Escape or the start button opens the menu while playing, and leaves the photo mode.
+/// In the menu, the arrow keys or W and S select an entry, Enter or Space confirms it,
+/// and Escape goes back. Gamepads use the d-pad, the south and the east button.
+/// The mouse selects an entry by hovering over it, and confirms it with a click.
+fn main_menu_input(
+    mut main_menu: ResMut<MainMenu>,
+    mut game_state: ResMut<GameState>,
+    mut settings_menu: ResMut<SettingsMenu>,
+    current_level: Res<CurrentLevel>,
+    progress: Res<Progress>,
+    mut keyboard_reader: EventReader<KeyboardInput>,
+    mut gamepad_reader: EventReader<GamepadButtonInput>,
+    mut hovered_reader: EventReader<UIHovered>,
+    mut clicked_reader: EventReader<UIClicked>,
+    ui_entries_query: Query<&UIMainMenuEntry>,
+    mut player_settings_query: Query<&mut PlayerControllerSettings, With<Player>>,
+    mut app_exit: EventWriter<AppExit>,
+    mut play_cutscene: EventWriter<PlayCutscene>,
+) {
+    let keyboard_inputs = keyboard_reader
+        .iter()
+        .filter(|event| event.state == ElementState::Pressed)
+        .filter_map(|event| match event.key_code {
+            VirtualKeyCode::Up | VirtualKeyCode::W => Some(MenuInput::Up),
+            VirtualKeyCode::Down | VirtualKeyCode::S => Some(MenuInput::Down),
+            VirtualKeyCode::Return | VirtualKeyCode::Space => Some(MenuInput::Confirm),
+            VirtualKeyCode::Escape => Some(MenuInput::Pause),
+            _ => None,
+        });
+    let gamepad_inputs = gamepad_reader
+        .iter()
+        .filter(|event| event.state == ElementState::Pressed)
+        .filter_map(|event| match event.button {
+            GamepadButton::DPadUp => Some(MenuInput::Up),
+            GamepadButton::DPadDown => Some(MenuInput::Down),
+            GamepadButton::South => Some(MenuInput::Confirm),
+            GamepadButton::East => Some(MenuInput::Back),
+            GamepadButton::Start => Some(MenuInput::Pause),
+            _ => None,
+        });
+    let hovered_inputs = hovered_reader
+        .iter()
+        .filter_map(|event| ui_entries_query.get(event.entity).ok())
+        .map(|ui_entry| MenuInput::Hover(ui_entry.index));
+    let clicked_inputs = clicked_reader
+        .iter()
+        .filter_map(|event| ui_entries_query.get(event.entity).ok())
+        .map(|ui_entry| MenuInput::Click(ui_entry.index));
+    let inputs: Vec<_> = keyboard_inputs
+        .chain(gamepad_inputs)
+        .chain(hovered_inputs)
+        .chain(clicked_inputs)
+        .collect();
+
+    for input in inputs {
+        if game_state.is_playing() {
+            if let MenuInput::Pause = input {
+                *game_state = GameState::MainMenu;
+                main_menu.open_page(MenuPage::Main);
+            }
+            continue;
+        }
+
+        if game_state.is_in_photo_mode() {
+            if let MenuInput::Pause | MenuInput::Back = input {
+                *game_state = GameState::MainMenu;
+            }
+            continue;
+        }
+
+        // Escape skips the cutscene instead
+        if game_state.is_in_cutscene() {
+            continue;
+        }
+
+        let entries = main_menu.entries(&current_level, &progress);
+        let input = match input {
+            MenuInput::Hover(index) | MenuInput::Click(index) if index >= entries.len() => continue,
+            MenuInput::Hover(index) => {
+                main_menu.selected = index;
+                continue;
+            }
+            MenuInput::Click(index) => {
+                main_menu.selected = index;
+                MenuInput::Confirm
+            }
+            input => input,
+        };
+        match input {
+            MenuInput::Up => {
+                main_menu.selected = (main_menu.selected + entries.len() - 1) % entries.len();
+            }
+            MenuInput::Down => {
+                main_menu.selected = (main_menu.selected + 1) % entries.len();
+            }
+            MenuInput::Back | MenuInput::Pause => match main_menu.page {
+                MenuPage::Main if main_menu.has_started => *game_state = GameState::Playing,
+                MenuPage::Main => {}
+                MenuPage::Settings => {
+                    settings_menu.save();
+                    main_menu.open_page(MenuPage::Main);
+                }
+                MenuPage::LevelSelect => main_menu.open_page(MenuPage::Main),
+            },
+            MenuInput::Hover(_) | MenuInput::Click(_) => {}
+            MenuInput::Confirm => match entries[main_menu.selected.min(entries.len() - 1)] {
+                MenuEntry::NewGame => {
+                    main_menu.start_level(LevelId::new(0), &current_level);
+                    *game_state = GameState::Playing;
+                    play_cutscene.send(PlayCutscene::new("intro"));
+                }
+                MenuEntry::Continue => {
+                    main_menu.start_level(progress.unlocked_level(), &current_level);
+                    *game_state = GameState::Playing;
+                }
+                MenuEntry::Resume => *game_state = GameState::Playing,
+                MenuEntry::PhotoMode => *game_state = GameState::PhotoMode,
+                MenuEntry::LevelSelect => main_menu.open_page(MenuPage::LevelSelect),
+                MenuEntry::Settings => main_menu.open_page(MenuPage::Settings),
+                MenuEntry::Quit => app_exit.send(AppExit),
+                MenuEntry::Level(level_id) => {
+                    main_menu.start_level(level_id, &current_level);
+                    *game_state = GameState::Playing;
+                }
+                MenuEntry::Setting(setting) => {
+                    settings_menu.change(setting);
+                    for mut settings in player_settings_query.iter_mut() {
+                        settings_menu.apply(&mut settings);
+                    }
+                }
+                MenuEntry::Back => {
+                    if main_menu.page == MenuPage::Settings {
+                        settings_menu.save();
+                    }
+                    main_menu.open_page(MenuPage::Main);
+                }
+            },
+        }
+    }
+}
+
+/// Waits until the level has been loaded, only the current and the next level are loaded
+fn move_player_to_target_level(
+    mut main_menu: ResMut<MainMenu>,
+    mut level_streaming: ResMut<LevelStreaming>,
+    mut players_query: Query<(&mut Transform, &mut Player)>,
+    spawnpoints: Query<(&Transform, &LevelId), (With<Spawnpoint>, Without<Player>)>,
+) {
+    let target_level = match main_menu.target_level {
+        Some(target_level) => target_level,
+        None => return,
+    };
+    let spawnpoint = match spawnpoints
+        .iter()
+        .find(|(_, level_id)| level_id == &&target_level)
+    {
+        Some((spawnpoint, _)) => spawnpoint,
+        None => {
+            level_streaming.request(target_level);
+            return;
+        }
+    };
+
+    for (mut transform, mut player) in players_query.iter_mut() {
+        transform.position = spawnpoint.position;
+        player.velocity = Vector3::zeros();
+    }
+    main_menu.target_level = None;
+}
+
+/// Reached levels stay unlocked, even after quitting the game
+fn unlock_levels(mut progress: ResMut<Progress>, mut next_level_events: EventReader<NextLevel>) {
+    for next_level in next_level_events.iter() {
+        progress.unlock_level(next_level.level_id);
+    }
+}
+
+/// The player stands still while the menu or the photo mode is open, with the same workaround as while rewinding
+fn stop_player(mut query: Query<(&mut Player, &mut PlayerCharacterController)>) {
+    for (mut player, mut character_controller) in query.iter_mut() {
+        player.velocity = Vector3::zeros();
+        character_controller.desired_movement = [0.0, -0.1, 0.0].into();
+    }
+}
+
+/// The background of the menu is the current level, seen from its spawnpoint
+fn update_menu_camera(
+    time: Res<Time>,
+    current_level: Res<CurrentLevel>,
+    spawnpoints: Query<(&Transform, &LevelId), With<Spawnpoint>>,
+    mut camera: ResMut<Camera>,
+) {
+    let spawnpoint = match spawnpoints
+        .iter()
+        .find(|(_, level_id)| level_id == &&current_level.level_id)
+    {
+        Some((spawnpoint, _)) => spawnpoint,
+        None => return,
+    };
+
+    let angle = time.time_since_startup().as_secs_f32() * BACKGROUND_ROTATION_SPEED;
+    camera.position = spawnpoint.position + Camera::up().into_inner() * BACKGROUND_CAMERA_HEIGHT;
+    camera.orientation = UnitQuaternion::from_axis_angle(&Camera::up(), angle);
+}
+
+fn update_main_menu_ui(
+    main_menu: Res<MainMenu>,
+    game_state: Res<GameState>,
+    current_level: Res<CurrentLevel>,
+    progress: Res<Progress>,
+    settings_menu: Res<SettingsMenu>,
+    mut title_query: Query<&mut UIComponent, With<UIMainMenuTitle>>,
+    mut entries_query: Query<
+        (&UIMainMenuEntry, &mut UIText, &mut UIComponent),
+        Without<UIMainMenuTitle>,
+    >,
+) {
+    let is_open = game_state.is_in_main_menu();
+    title_query.single_mut().visible = is_open;
+
+    let entries = main_menu.entries(&current_level, &progress);
+    for (ui_entry, mut text, mut ui_component) in entries_query.iter_mut() {
+        let entry = match entries.get(ui_entry.index) {
+            Some(entry) if is_open => entry,
+            _ => {
+                ui_component.visible = false;
+                continue;
+            }
+        };
+
+        let label = match entry {
+            MenuEntry::NewGame => "New game".to_string(),
+            MenuEntry::Continue => "Continue".to_string(),
+            MenuEntry::Resume => "Resume".to_string(),
+            MenuEntry::PhotoMode => "Photo mode".to_string(),
+            MenuEntry::LevelSelect => "Level select".to_string(),
+            MenuEntry::Settings => "Settings".to_string(),
+            MenuEntry::Quit => "Quit".to_string(),
+            MenuEntry::Level(level_id) => format!("Level {}", level_id.id()),
+            MenuEntry::Setting(setting) => settings_menu.label(*setting),
+            MenuEntry::Back => "Back".to_string(),
+        };
+        let label = if ui_entry.index == main_menu.selected {
+            format!("> {}", label)
+        } else {
+            format!("  {}", label)
+        };
+
+        ui_component.visible = true;
+        text.set_if_neq(UIText::new(label));
+    }
+}
+
+/// The panel grows with the number of entries, and the selection frame follows the selected entry
+fn update_main_menu_panel(
+    main_menu: Res<MainMenu>,
+    game_state: Res<GameState>,
+    current_level: Res<CurrentLevel>,
+    progress: Res<Progress>,
+    mut panel_query: Query<&mut UIComponent, (With<UIMainMenuPanel>, Without<UIMainMenuSelection>)>,
+    mut selection_query: Query<&mut UIComponent, With<UIMainMenuSelection>>,
+) {
+    let is_open = game_state.is_in_main_menu();
+    let entry_count = main_menu.entries(&current_level, &progress).len();
+
+    let mut panel = panel_query.single_mut();
+    panel.visible = is_open;
+    set_sprite_size(
+        &mut panel,
+        Vector2::new(PANEL_WIDTH, entry_count as f32 * ENTRY_SPACING + 24.0),
+    );
+
+    let mut selection = selection_query.single_mut();
+    selection.visible = is_open;
+    selection.offset = entry_offset(main_menu.selected) - Vector2::new(12.0, 5.0);
+    set_sprite_size(
+        &mut selection,
+        Vector2::new(PANEL_WIDTH - 24.0, ENTRY_SPACING),
+    );
+}
+
+pub struct MainMenuPlugin {
+    progress_path: String,
+}
+
+impl MainMenuPlugin {
+    pub fn new(progress_path: impl Into<String>) -> Self {
+        Self {
+            progress_path: progress_path.into(),
+        }
+    }
+}
+
+impl Plugin for MainMenuPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(GameState::MainMenu)
+            .with_resource(MainMenu::new())
+            .with_resource(Progress::load(self.progress_path.clone()))
+            .with_startup_system(spawn_main_menu_ui)
+            .with_system(main_menu_input)
+            .with_system(move_player_to_target_level.after(main_menu_input))
+            .with_system(unlock_levels)
+            .with_system(stop_player.run_if(not(is_playing)))
+            .with_system(update_menu_camera.run_if(is_in_main_menu))
+            .with_system(update_main_menu_ui.after(main_menu_input))
+            .with_system(update_main_menu_panel.after(main_menu_input));
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::*;
+use levels::{
+    current_level::{CurrentLevel, NextLevel},
+    level_id::LevelId,
+};
+use nalgebra::Vector2;
+use scene::level::FlagId;
+use scene::ui_component::{UIAnchor, UIComponent, UITexturePosition};
+use scene::ui_text::UIText;
+
+use crate::level_flags::LevelFlags;
+
+/// At most this many objectives are shown at once
+const MAX_SHOWN_OBJECTIVES: usize = 4;
+
+pub enum ObjectiveCondition {
+    /// Completed while all of these flags of the level are set.
+    /// Rewinding the flags also undoes the objective.
+    Flags(Vec<FlagId>),
+    /// Completed when the player reaches the next level
+    ReachNextLevel,
+}
+
+pub struct Objective {
+    pub text: String,
+    pub condition: ObjectiveCondition,
+    completed: bool,
+}
+
+impl Objective {
+    pub fn is_completed(&self) -> bool {
+        self.completed
+    }
+}
+
+/// Levels add their objectives at startup in this set.
+/// Every level only touches its own objectives, so the order doesn't matter.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ObjectivesSetup;
+
+/// What the player should do next, so that testers never get stuck.
+/// Levels add their objectives when the game starts.
+#[derive(Resource)]
+pub struct Objectives {
+    objectives: HashMap<LevelId, Vec<Objective>>,
+}
+
+impl Objectives {
+    pub fn new() -> Self {
+        Self {
+            objectives: HashMap::new(),
+        }
+    }
+
+    pub fn add(
+        &mut self,
+        level_id: LevelId,
+        text: impl Into<String>,
+        condition: ObjectiveCondition,
+    ) -> &mut Self {
+        self.objectives
+            .entry(level_id)
+            .or_default()
+            .push(Objective {
+                text: text.into(),
+                condition,
+                completed: false,
+            });
+        self
+    }
+
+    pub fn get(&self, level_id: LevelId) -> &[Objective] {
+        self.objectives
+            .get(&level_id)
+            .map(|objectives| objectives.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+fn update_objectives(
+    mut objectives: ResMut<Objectives>,
+    level_flags: Res<LevelFlags>,
+    mut next_level_events: EventReader<NextLevel>,
+) {
+    for next_level_event in next_level_events.iter() {
+        if let Some(level_objectives) = objectives
+            .objectives
+            .get_mut(&next_level_event.old_level_id)
+        {
+            for objective in level_objectives.iter_mut() {
+                if let ObjectiveCondition::ReachNextLevel = objective.condition {
+                    objective.completed = true;
+                }
+            }
+        }
+    }
+
+    for (level_id, level_objectives) in objectives.objectives.iter_mut() {
+        for objective in level_objectives.iter_mut() {
+            if let ObjectiveCondition::Flags(flags) = &objective.condition {
+                objective.completed = flags
+                    .iter()
+                    .all(|flag_id| level_flags.get(*level_id, *flag_id));
+            }
+        }
+    }
+}
+
+#[derive(Component)]
+struct UIObjective {
+    index: usize,
+}
+
+fn spawn_objectives_ui(mut commands: Commands) {
+    for index in 0..MAX_SHOWN_OBJECTIVES {
+        let text = UIText::new("");
+        commands.spawn((
+            UIComponent {
+                texture: text.create_texture(),
+                region: None,
+                texture_position: UITexturePosition {
+                    scale: Vector2::new(2.0, 2.0),
+                    ..UITexturePosition::default()
+                },
+                anchor: UIAnchor::TopLeft,
+                offset: Vector2::new(16.0, 16.0 + index as f32 * 24.0),
+                depth: 0.0,
+                opacity: 1.0,
+                visible: false,
+            },
+            text,
+            UIObjective { index },
+        ));
+    }
+}
+
+fn update_objectives_ui(
+    objectives: Res<Objectives>,
+    current_level: Res<CurrentLevel>,
+    mut query: Query<(&UIObjective, &mut UIText, &mut UIComponent)>,
+) {
+    let level_objectives = objectives.get(current_level.level_id);
+
+    for (ui_objective, mut text, mut ui_component) in query.iter_mut() {
+        let objective = match level_objectives.get(ui_objective.index) {
+            Some(objective) => objective,
+            None => {
+                ui_component.visible = false;
+                continue;
+            }
+        };
+
+        ui_component.visible = true;
+        text.set_if_neq(UIText {
+            text: objective.text.clone(),
+            strikethrough: objective.completed,
+        });
+    }
+}
+
+pub struct ObjectivesPlugin;
+
+impl Plugin for ObjectivesPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(Objectives::new())
+            .with_startup_system(spawn_objectives_ui)
+            .with_system(update_objectives)
+            .with_system(update_objectives_ui.after(update_objectives));
+    }
+}
@@ -0,0 +1,207 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use angle::{Deg, Rad};
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::*;
+use input::events::{
+    ElementState, MouseButton, MouseInput, MouseMovement, MouseWheel, VirtualKeyCode,
+};
+use input::input_map::InputMap;
+use nalgebra::{UnitQuaternion, Vector3};
+use render::Renderer;
+use scene::camera::Camera;
+use scene::ui_component::UIVisibility;
+use time::time::Time;
+use time::time_manager::TimeManager;
+
+use crate::core::rewind_fov::RewindFov;
+use crate::game_state::{is_in_photo_mode, GameState};
+use crate::player::{Player, PlayerControllerSettings};
+
+/// in meters per second, Shift makes it faster
+const CAMERA_SPEED: f32 = 2.0;
+const FAST_CAMERA_SPEED_FACTOR: f32 = 4.0;
+/// in degrees per second
+const ROLL_SPEED: f32 = 45.0;
+/// in degrees per line of the mouse wheel
+const FOV_STEP: f32 = 2.0;
+const MIN_FOV: f32 = 10.0;
+const MAX_FOV: f32 = 120.0;
+const MAX_PITCH: f32 = 88.0;
+
+/// Freezes the world, hides the UI and lets the camera fly around, for taking pictures.
+/// WASD moves, Space and Ctrl go up and down, Shift is faster.
+/// Q and E roll the camera, the mouse wheel changes the field of view, R resets both.
+/// The left mouse button saves a screenshot.
+#[derive(Resource)]
+pub struct PhotoMode {
+    active: bool,
+    yaw: Deg<f32>,
+    pitch: Deg<f32>,
+    roll: Deg<f32>,
+    fov: Deg<f32>,
+    /// The field of view of the game, which gets restored when leaving the photo mode
+    game_fov: Deg<f32>,
+}
+
+impl PhotoMode {
+    fn new() -> Self {
+        Self {
+            active: false,
+            yaw: Deg(0.0),
+            pitch: Deg(0.0),
+            roll: Deg(0.0),
+            fov: Deg(60.0),
+            game_fov: Deg(60.0),
+        }
+    }
+
+    fn orientation(&self) -> UnitQuaternion<f32> {
+        UnitQuaternion::from_axis_angle(&Camera::up(), self.yaw.to_rad().0)
+            * UnitQuaternion::from_axis_angle(&Camera::right(), self.pitch.to_rad().0)
+            * UnitQuaternion::from_axis_angle(&Camera::forward(), self.roll.to_rad().0)
+    }
+}
+
+/// Starts from wherever the camera currently is
+fn enter_or_leave_photo_mode(
+    game_state: Res<GameState>,
+    mut photo_mode: ResMut<PhotoMode>,
+    mut time_manager: ResMut<TimeManager>,
+    mut ui_visibility: ResMut<UIVisibility>,
+    mut rewind_fov: ResMut<RewindFov>,
+    camera: Res<Camera>,
+) {
+    let is_in_photo_mode = game_state.is_in_photo_mode();
+    if photo_mode.active == is_in_photo_mode {
+        return;
+    }
+    photo_mode.active = is_in_photo_mode;
+    time_manager.set_frozen(is_in_photo_mode);
+    ui_visibility.hidden = is_in_photo_mode;
+
+    if is_in_photo_mode {
+        let forward = camera.orientation * Camera::forward();
+        photo_mode.yaw = Rad((-forward.x).atan2(-forward.z)).into();
+        photo_mode.pitch = Rad(forward.y.clamp(-1.0, 1.0).asin()).into();
+        photo_mode.roll = Deg(0.0);
+        photo_mode.game_fov = rewind_fov.base_fov;
+        photo_mode.fov = rewind_fov.base_fov;
+    } else {
+        rewind_fov.base_fov = photo_mode.game_fov;
+    }
+}
+
+fn update_photo_camera(
+    mut photo_mode: ResMut<PhotoMode>,
+    mut camera: ResMut<Camera>,
+    mut rewind_fov: ResMut<RewindFov>,
+    mut mouse_movement_reader: EventReader<MouseMovement>,
+    mut mouse_wheel_reader: EventReader<MouseWheel>,
+    input: Res<InputMap>,
+    time: Res<Time>,
+    player_settings_query: Query<&PlayerControllerSettings, With<Player>>,
+) {
+    let (sensitivity, y_direction) = match player_settings_query.get_single() {
+        Ok(settings) => (
+            settings.sensitivity(),
+            if settings.invert_y { -1.0 } else { 1.0 },
+        ),
+        Err(_) => (0.1, 1.0),
+    };
+    let delta_time = time.delta_seconds();
+
+    for MouseMovement(dx, dy) in mouse_movement_reader.iter() {
+        photo_mode.yaw.0 -= *dx as f32 * sensitivity;
+        photo_mode.pitch.0 -= *dy as f32 * sensitivity * y_direction;
+    }
+    photo_mode.pitch.0 = photo_mode.pitch.0.clamp(-MAX_PITCH, MAX_PITCH);
+
+    if input.is_pressed(VirtualKeyCode::Q) {
+        photo_mode.roll.0 += ROLL_SPEED * delta_time;
+    }
+    if input.is_pressed(VirtualKeyCode::E) {
+        photo_mode.roll.0 -= ROLL_SPEED * delta_time;
+    }
+
+    for MouseWheel(lines) in mouse_wheel_reader.iter() {
+        photo_mode.fov.0 = (photo_mode.fov.0 - lines * FOV_STEP).clamp(MIN_FOV, MAX_FOV);
+    }
+
+    if input.is_pressed(VirtualKeyCode::R) {
+        photo_mode.roll = Deg(0.0);
+        photo_mode.fov = photo_mode.game_fov;
+    }
+
+    let mut direction = Vector3::zeros();
+    if input.is_pressed(VirtualKeyCode::W) {
+        direction += Camera::forward().into_inner();
+    }
+    if input.is_pressed(VirtualKeyCode::S) {
+        direction -= Camera::forward().into_inner();
+    }
+    if input.is_pressed(VirtualKeyCode::D) {
+        direction += Camera::right().into_inner();
+    }
+    if input.is_pressed(VirtualKeyCode::A) {
+        direction -= Camera::right().into_inner();
+    }
+    if input.is_pressed(VirtualKeyCode::Space) {
+        direction += Camera::up().into_inner();
+    }
+    if input.is_pressed(VirtualKeyCode::LControl) {
+        direction -= Camera::up().into_inner();
+    }
+    let speed = if input.is_pressed(VirtualKeyCode::LShift) {
+        CAMERA_SPEED * FAST_CAMERA_SPEED_FACTOR
+    } else {
+        CAMERA_SPEED
+    };
+
+    // Rolling the camera shouldn't change where the movement goes
+    let movement_orientation =
+        UnitQuaternion::from_axis_angle(&Camera::up(), photo_mode.yaw.to_rad().0)
+            * UnitQuaternion::from_axis_angle(&Camera::right(), photo_mode.pitch.to_rad().0);
+    if direction != Vector3::zeros() {
+        camera.position += movement_orientation * direction.normalize() * speed * delta_time;
+    }
+    camera.orientation = photo_mode.orientation();
+    rewind_fov.base_fov = photo_mode.fov;
+}
+
+/// Always reads the clicks, so that old clicks from before the photo mode don't take a photo
+fn take_photo(
+    photo_mode: Res<PhotoMode>,
+    mut renderer: NonSendMut<Renderer>,
+    mut mouse_reader: EventReader<MouseInput>,
+) {
+    let clicked = mouse_reader
+        .iter()
+        .filter(|event| event.button == MouseButton::Left && event.state == ElementState::Released)
+        .count()
+        > 0;
+
+    if clicked && photo_mode.active {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        renderer.capture_screenshot(format!("screenshots/photo_{timestamp}.png"));
+    }
+}
+
+pub struct PhotoModePlugin;
+
+impl Plugin for PhotoModePlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(PhotoMode::new())
+            .with_system(enter_or_leave_photo_mode)
+            .with_system(
+                update_photo_camera
+                    .after(enter_or_leave_photo_mode)
+                    .run_if(is_in_photo_mode),
+            )
+            .with_system(take_photo.after(enter_or_leave_photo_mode));
+    }
+}
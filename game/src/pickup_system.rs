@@ -1,32 +1,71 @@
 use app::plugin::{Plugin, PluginAppAccess};
-use bevy_ecs::prelude::{not, Commands, Entity, EventReader, Query, Res, With};
+use bevy_ecs::prelude::{not, Commands, Entity, EventReader, Query, Res, With, Without};
 use bevy_ecs::schedule::IntoSystemConfig;
 use bevy_ecs::system::{ResMut, Resource};
-use input::events::{ElementState, MouseButton, MouseInput};
+use input::events::{
+    ElementState, KeyboardInput, MouseButton, MouseInput, MouseWheel, VirtualKeyCode,
+};
+use input::gamepad::{GamepadButton, GamepadButtonInput};
+use nalgebra::UnitQuaternion;
 use physics::physics_context::{PhysicsContext, RapierRigidBodyHandle, Ray};
-use physics::pickup_physics::PickedUp;
+use physics::pickup_physics::{PickedUp, ThrowImpulse};
 use scene::camera::Camera;
+use scene::highlight::Highlighted;
+use scene::interactable::Interactable;
 use scene::pickup::Pickupable;
+use time::time::Time;
 use time::time_manager::is_rewinding;
 
+use crate::interaction::MAX_INTERACTION_DISTANCE;
 use crate::player::Player;
 
+/// How far away an object can be picked up
+const MAX_PICKUP_DISTANCE: f32 = 5.0;
+
+/// How long the throw button has to be held for the strongest throw
+const MAX_THROW_CHARGE: f32 = 1.0;
+const MIN_THROW_IMPULSE: f32 = 1.0;
+const MAX_THROW_IMPULSE: f32 = 12.0;
+/// How far a held object turns per scrolled line, or per press of the gamepad buttons
+const ROTATE_STEP: f32 = std::f32::consts::FRAC_PI_8;
+
+/// What the crosshair in the middle of the screen shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reticle {
+    Crosshair,
+    /// Looking at something that can be picked up or interacted with
+    Hover,
+    Holding,
+}
+
 #[derive(Resource)]
 pub struct PickupInfo {
     pub can_pickup: bool,
+    /// Seconds that the throw button has been held for
+    pub throw_charge: Option<f32>,
+    pub reticle: Reticle,
 }
 
 impl PickupInfo {
     pub fn new() -> Self {
-        Self { can_pickup: false }
+        Self {
+            can_pickup: false,
+            throw_charge: None,
+            reticle: Reticle::Crosshair,
+        }
     }
 }
 
+/// Clicking picks up an object, clicking again throws it.
+/// Holding the button longer throws it further, Q drops it.
 fn ray_cast(
     mut commands: Commands,
     mut event_reader: EventReader<MouseInput>,
+    mut keyboard_reader: EventReader<KeyboardInput>,
+    mut gamepad_reader: EventReader<GamepadButtonInput>,
     physics_context: Res<PhysicsContext>,
     camera: Res<Camera>,
+    time: Res<Time>,
     mut pickup_info: ResMut<PickupInfo>,
     query: Query<Entity, With<PickedUp>>,
     query_pickupable: Query<&Pickupable>,
@@ -36,36 +75,181 @@ fn ray_cast(
         camera.position,
         camera.orientation * Camera::forward().into_inner(),
     );
-    let hit = physics_context.cast_ray(&ray, 5.0, true, exclude_query.iter().collect());
+    let hit = physics_context.cast_ray(
+        &ray,
+        MAX_PICKUP_DISTANCE,
+        true,
+        exclude_query.iter().collect(),
+    );
     let entity = hit
         .map(|(entity, _toi)| entity)
         .filter(|entity| query_pickupable.contains(*entity));
 
-    pickup_info.can_pickup = entity.is_some();
+    let is_holding = !query.is_empty();
+    pickup_info.can_pickup = entity.is_some() && !is_holding;
 
-    for event in event_reader.iter() {
-        if event.button != MouseButton::Left {
-            continue;
-        }
+    if let Some(throw_charge) = pickup_info.throw_charge.as_mut() {
+        *throw_charge += time.delta_seconds();
+    }
+
+    let mouse_inputs = event_reader.iter().filter_map(|event| match event.button {
+        MouseButton::Left => Some((PickupInput::PickupOrThrow, event.state)),
+        _ => None,
+    });
+    let keyboard_inputs = keyboard_reader
+        .iter()
+        .filter(|event| event.key_code == VirtualKeyCode::Q)
+        .map(|event| (PickupInput::Drop, event.state));
+    let gamepad_inputs = gamepad_reader
+        .iter()
+        .filter_map(|event| match event.button {
+            GamepadButton::RightTrigger => Some((PickupInput::PickupOrThrow, event.state)),
+            GamepadButton::DPadDown => Some((PickupInput::Drop, event.state)),
+            _ => None,
+        });
 
-        match event.state {
-            ElementState::Pressed => {
-                if let Some(entity) = entity {
-                    commands.entity(entity).insert(PickedUp {
-                        position: camera.position,
-                    });
+    for (input, state) in mouse_inputs.chain(keyboard_inputs).chain(gamepad_inputs) {
+        match (input, state) {
+            (PickupInput::PickupOrThrow, ElementState::Pressed) => {
+                if is_holding {
+                    pickup_info.throw_charge = Some(0.0);
+                } else if let Some(entity) = entity {
+                    commands
+                        .entity(entity)
+                        .insert(PickedUp::new(camera.position));
+                }
+            }
+            (PickupInput::PickupOrThrow, ElementState::Released) => {
+                let throw_charge = match pickup_info.throw_charge.take() {
+                    Some(throw_charge) => throw_charge,
+                    None => continue,
+                };
+                let strength = (throw_charge / MAX_THROW_CHARGE).min(1.0);
+                let impulse =
+                    MIN_THROW_IMPULSE + (MAX_THROW_IMPULSE - MIN_THROW_IMPULSE) * strength;
+                let direction = camera.orientation * Camera::forward().into_inner();
+                for entity in query.iter() {
+                    commands
+                        .entity(entity)
+                        .remove::<PickedUp>()
+                        .insert(ThrowImpulse(direction * impulse));
                 }
             }
-            ElementState::Released => {
+            (PickupInput::Drop, ElementState::Pressed) => {
+                pickup_info.throw_charge = None;
                 for entity in query.iter() {
                     commands.entity(entity).remove::<PickedUp>();
                 }
             }
+            (PickupInput::Drop, ElementState::Released) => {}
         }
     }
 }
 
-fn drop_when_rewinding(mut commands: Commands, query: Query<Entity, With<PickedUp>>) {
+enum PickupInput {
+    PickupOrThrow,
+    Drop,
+}
+
+/// Scrolling or the gamepad shoulder buttons turn the held object around the view axis,
+/// unless it has to be carried carefully
+fn rotate_held_object(
+    mut mouse_wheel: EventReader<MouseWheel>,
+    mut gamepad_reader: EventReader<GamepadButtonInput>,
+    camera: Res<Camera>,
+    mut query: Query<(&mut PickedUp, &Pickupable)>,
+) {
+    let scrolled: f32 = mouse_wheel.iter().map(|MouseWheel(lines)| lines).sum();
+    let pressed: f32 = gamepad_reader
+        .iter()
+        .filter(|event| event.state == ElementState::Pressed)
+        .map(|event| match event.button {
+            GamepadButton::DPadLeft => 1.0,
+            GamepadButton::DPadRight => -1.0,
+            _ => 0.0,
+        })
+        .sum();
+
+    let steps = scrolled + pressed;
+    if steps == 0.0 {
+        return;
+    }
+
+    let view_axis = camera.orientation * Camera::forward();
+    let rotation = UnitQuaternion::from_axis_angle(&view_axis, steps * ROTATE_STEP);
+    for (mut picked_up, pickupable) in query.iter_mut() {
+        if pickupable.can_rotate {
+            picked_up.rotate(rotation);
+        }
+    }
+}
+
+/// Outlines the object that the player is looking at, if they can pick it up or interact with it
+fn highlight_looked_at(
+    mut commands: Commands,
+    mut pickup_info: ResMut<PickupInfo>,
+    physics_context: Res<PhysicsContext>,
+    camera: Res<Camera>,
+    held_query: Query<(), With<PickedUp>>,
+    pickupable_query: Query<(), (With<Pickupable>, Without<PickedUp>)>,
+    interactable_query: Query<(), With<Interactable>>,
+    highlighted_query: Query<Entity, With<Highlighted>>,
+    exclude_query: Query<&RapierRigidBodyHandle, With<Player>>,
+) {
+    let ray = Ray::new(
+        camera.position,
+        camera.orientation * Camera::forward().into_inner(),
+    );
+    let is_holding = !held_query.is_empty();
+    let looked_at = physics_context
+        .cast_ray(
+            &ray,
+            MAX_PICKUP_DISTANCE.max(MAX_INTERACTION_DISTANCE),
+            true,
+            exclude_query.iter().collect(),
+        )
+        .filter(|(entity, toi)| {
+            (!is_holding && pickupable_query.contains(*entity) && *toi <= MAX_PICKUP_DISTANCE)
+                || (interactable_query.contains(*entity) && *toi <= MAX_INTERACTION_DISTANCE)
+        })
+        .map(|(entity, _toi)| entity);
+
+    pickup_info.reticle = if is_holding {
+        Reticle::Holding
+    } else if looked_at.is_some() {
+        Reticle::Hover
+    } else {
+        Reticle::Crosshair
+    };
+    set_highlight(&mut commands, looked_at, &highlighted_query);
+}
+
+fn set_highlight(
+    commands: &mut Commands,
+    entity: Option<Entity>,
+    highlighted_query: &Query<Entity, With<Highlighted>>,
+) {
+    for highlighted in highlighted_query.iter() {
+        if Some(highlighted) != entity {
+            commands.entity(highlighted).remove::<Highlighted>();
+        }
+    }
+    if let Some(entity) = entity {
+        if !highlighted_query.contains(entity) {
+            commands.entity(entity).insert(Highlighted);
+        }
+    }
+}
+
+fn drop_when_rewinding(
+    mut commands: Commands,
+    mut pickup_info: ResMut<PickupInfo>,
+    query: Query<Entity, With<PickedUp>>,
+    highlighted_query: Query<Entity, With<Highlighted>>,
+) {
+    set_highlight(&mut commands, None, &highlighted_query);
+    pickup_info.throw_charge = None;
+    pickup_info.reticle = Reticle::Crosshair;
     for entity in query.iter() {
         commands.entity(entity).remove::<PickedUp>();
     }
@@ -78,6 +262,17 @@ impl Plugin for PickupPlugin {
         app //
             .with_resource(PickupInfo::new())
             .with_system(drop_when_rewinding.run_if(is_rewinding))
-            .with_system(ray_cast.run_if(not(is_rewinding)));
+            .with_system(
+                ray_cast
+                    .run_if(not(is_rewinding))
+                    .ambiguous_with(drop_when_rewinding),
+            )
+            .with_system(rotate_held_object.run_if(not(is_rewinding)))
+            .with_system(
+                highlight_looked_at
+                    .run_if(not(is_rewinding))
+                    .after(ray_cast)
+                    .ambiguous_with(drop_when_rewinding),
+            );
     }
 }
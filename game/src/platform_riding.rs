@@ -0,0 +1,93 @@
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::*;
+use loader::loader::Platform;
+use physics::physics_context::{PhysicsContext, RapierRigidBodyHandle, Ray};
+use physics::player_physics::PlayerCharacterController;
+use scene::hierarchy::{HierarchyCommands, Parent};
+use scene::transform::Transform;
+use time::time_manager::is_rewinding;
+
+use crate::player::Player;
+
+/// How far below the feet a platform still counts as being stood on
+const GROUND_DISTANCE: f32 = 0.3;
+
+/// The cat becomes a child of the platform that it stands on, so that the platform carries it along.
+/// Jumping off keeps the velocity of the platform, so that an elevator going up throws the cat higher.
+fn ride_platforms(
+    mut commands: Commands,
+    physics_context: Res<PhysicsContext>,
+    mut players: Query<
+        (
+            Entity,
+            &Transform,
+            &PlayerCharacterController,
+            &RapierRigidBodyHandle,
+            Option<&Parent>,
+            &mut Player,
+        ),
+        With<Player>,
+    >,
+    platforms: Query<Option<&RapierRigidBodyHandle>, With<Platform>>,
+) {
+    for (entity, transform, character_controller, rigid_body_handle, parent, mut player) in
+        players.iter_mut()
+    {
+        let platform = if character_controller.grounded {
+            let up = character_controller.up.into_inner();
+            let ray = Ray::new(transform.position + up * 0.1, -up);
+            physics_context
+                .cast_ray(&ray, GROUND_DISTANCE, true, vec![rigid_body_handle])
+                .map(|(entity, _)| entity)
+                .filter(|entity| platforms.contains(*entity))
+        } else {
+            None
+        };
+
+        match (platform, parent.map(|parent| parent.get())) {
+            (Some(platform), Some(parent)) if platform == parent => {}
+            (Some(platform), _) => {
+                commands.entity(entity).set_parent(platform);
+            }
+            (None, Some(parent)) => {
+                if !character_controller.grounded {
+                    let platform_velocity = platforms
+                        .get(parent)
+                        .ok()
+                        .flatten()
+                        .and_then(|handle| physics_context.rigid_bodies.get(handle.handle))
+                        .map(|rigid_body| *rigid_body.linvel());
+                    if let Some(platform_velocity) = platform_velocity {
+                        player.velocity += platform_velocity;
+                    }
+                }
+                commands.entity(entity).remove_parent();
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+/// The player and the platforms get rewound separately, in world space
+fn stop_riding_when_rewinding(
+    mut commands: Commands,
+    players: Query<Entity, (With<Player>, With<Parent>)>,
+) {
+    for entity in players.iter() {
+        commands.entity(entity).remove_parent();
+    }
+}
+
+pub struct PlatformRidingPlugin;
+
+impl Plugin for PlatformRidingPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_system(ride_platforms.run_if(not(is_rewinding)))
+            .with_system(
+                stop_riding_when_rewinding
+                    .run_if(is_rewinding)
+                    .ambiguous_with(ride_platforms),
+            );
+    }
+}
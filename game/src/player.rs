@@ -5,10 +5,16 @@ use angle::{Angle, Deg, Rad};
 use bevy_ecs::event::EventReader;
 use bevy_ecs::prelude::*;
 use input::events::{KeyboardInput, MouseMovement};
+use input::gamepad::{GamepadButton, GamepadState};
 use input::input_map::InputMap;
 use nalgebra::{UnitQuaternion, Vector3};
+use physics::fluid_volume::InFluid;
+use physics::gravity_zone::GravityScale;
+use physics::physics_context::{PhysicsContext, RapierRigidBodyHandle, Ray};
+use physics::pickup_physics::PickedUp;
 use physics::player_physics::PlayerCharacterController;
 use scene::camera::Camera;
+use scene::screen_shake::ScreenShake;
 use scene::transform::Transform;
 use time::time_manager::is_rewinding;
 use windowing::event::ElementState;
@@ -16,6 +22,17 @@ use windowing::event::VirtualKeyCode;
 
 use crate::game_over::GameOver;
 
+/// In meters, players below this height get put back to the spawnpoint
+pub const FALL_OUT_OF_WORLD_HEIGHT: f32 = -10.0;
+
+/// The part of the gravity that still pulls on the player in a fluid, so that they always sink back down
+const MIN_FLUID_GRAVITY: f32 = 0.25;
+
+/// In radians, how far the strongest screen shake turns the camera
+const MAX_SHAKE_ANGLE: f32 = 0.06;
+/// In radians per second
+const SHAKE_FREQUENCY: f32 = 25.0;
+
 #[derive(Component)]
 pub struct CameraMode {
     free_cam_activated: bool,
@@ -24,18 +41,64 @@ pub struct CameraMode {
 #[derive(Component, Clone)]
 pub struct PlayerControllerSettings {
     eye_height: f32,
+    crouch_eye_height: f32,
     free_cam_speed: f32,
     /// players use a different gravity
     gravity: f32,
     sensitivity: f32,
+    /// in degrees per second at full stick deflection
+    gamepad_sensitivity: f32,
 
     friction: f32,
     ground_accelerate: f32,
     air_accelerate: f32,
     max_velocity_ground: f32,
     max_velocity_air: f32,
+    max_velocity_crouched: f32,
     camera_smoothing: f32,
     jump_force: f32,
+
+    /// in kilograms, held objects up to this mass don't slow the player down
+    light_carry_mass: f32,
+    /// in kilograms, held objects with at least this mass slow the player down the most
+    heavy_carry_mass: f32,
+    /// the part of the speed that is left while holding a heavy object
+    heavy_carry_speed: f32,
+    /// in kilograms, the player can't jump while holding heavier objects
+    max_jump_carry_mass: f32,
+
+    /// some players prefer the camera to exactly follow the mouse
+    pub smooth_camera: bool,
+    /// 0 is linear, larger values make fast mouse movements turn further
+    pub mouse_acceleration: f32,
+    pub invert_y: bool,
+    /// pressing the rewind button once starts rewinding, and pressing it again stops it,
+    /// instead of having to hold it
+    pub toggle_rewind: bool,
+
+    max_slope_angle: Deg<f32>,
+    step_height: f32,
+    snap_to_ground_distance: f32,
+
+    /// head-bob and edge tilt, can be disabled for motion-sick players
+    camera_effects: bool,
+    /// in meters
+    head_bob_amplitude: f32,
+    /// head-bobs per meter walked
+    head_bob_frequency: f32,
+    /// how far the camera tilts towards a nearby ledge
+    edge_tilt_angle: Deg<f32>,
+}
+
+/// The state of the camera effects, see [`PlayerControllerSettings`] for the settings
+#[derive(Component, Default)]
+pub struct CameraEffects {
+    head_bob_phase: f32,
+    head_bob_strength: f32,
+    /// in radians
+    edge_tilt: f32,
+    /// in radians, around the right, up and forward axes of the camera
+    shake_angles: Vector3<f32>,
 }
 
 #[derive(Component, Debug)]
@@ -50,8 +113,10 @@ impl PlayerControllerSettings {
     pub fn new(speed: f32, sensitivity: f32, gravity: f32) -> Self {
         PlayerControllerSettings {
             eye_height: 1.75,
+            crouch_eye_height: 0.8,
             free_cam_speed: speed,
             sensitivity,
+            gamepad_sensitivity: 180.0,
             gravity,
 
             friction: 8.0,
@@ -59,8 +124,28 @@ impl PlayerControllerSettings {
             air_accelerate: 100.0,
             max_velocity_ground: 4.0,
             max_velocity_air: 2.0,
+            max_velocity_crouched: 2.0,
             jump_force: 6.0,
             camera_smoothing: 20.0,
+
+            light_carry_mass: 10.0,
+            heavy_carry_mass: 60.0,
+            heavy_carry_speed: 0.4,
+            max_jump_carry_mass: 30.0,
+
+            smooth_camera: true,
+            mouse_acceleration: 0.0,
+            invert_y: false,
+            toggle_rewind: false,
+
+            max_slope_angle: Deg(45.0),
+            step_height: 0.3,
+            snap_to_ground_distance: 0.2,
+
+            camera_effects: true,
+            head_bob_amplitude: 0.04,
+            head_bob_frequency: 0.5,
+            edge_tilt_angle: Deg(3.0),
         }
     }
 
@@ -68,6 +153,93 @@ impl PlayerControllerSettings {
         self.sensitivity = sensitivity;
         self
     }
+
+    /// Degrees per mouse count
+    pub fn sensitivity(&self) -> f32 {
+        self.sensitivity
+    }
+
+    pub fn with_smooth_camera(mut self, smooth_camera: bool) -> Self {
+        self.smooth_camera = smooth_camera;
+        self
+    }
+
+    pub fn with_mouse_acceleration(mut self, mouse_acceleration: f32) -> Self {
+        self.mouse_acceleration = mouse_acceleration;
+        self
+    }
+
+    pub fn with_invert_y(mut self, invert_y: bool) -> Self {
+        self.invert_y = invert_y;
+        self
+    }
+
+    pub fn with_toggle_rewind(mut self, toggle_rewind: bool) -> Self {
+        self.toggle_rewind = toggle_rewind;
+        self
+    }
+
+    pub fn with_gamepad_sensitivity(mut self, gamepad_sensitivity: f32) -> Self {
+        self.gamepad_sensitivity = gamepad_sensitivity;
+        self
+    }
+
+    pub fn with_max_slope_angle(mut self, max_slope_angle: Deg<f32>) -> Self {
+        self.max_slope_angle = max_slope_angle;
+        self
+    }
+
+    /// A step height of zero disables climbing stairs without jumping
+    pub fn with_step_height(mut self, step_height: f32) -> Self {
+        self.step_height = step_height;
+        self
+    }
+
+    pub fn with_camera_effects(mut self, camera_effects: bool) -> Self {
+        self.camera_effects = camera_effects;
+        self
+    }
+
+    pub fn with_head_bob(mut self, amplitude: f32, frequency: f32) -> Self {
+        self.head_bob_amplitude = amplitude;
+        self.head_bob_frequency = frequency;
+        self
+    }
+
+    pub fn with_edge_tilt_angle(mut self, edge_tilt_angle: Deg<f32>) -> Self {
+        self.edge_tilt_angle = edge_tilt_angle;
+        self
+    }
+
+    /// A distance of zero disables snapping to the ground
+    pub fn with_snap_to_ground_distance(mut self, snap_to_ground_distance: f32) -> Self {
+        self.snap_to_ground_distance = snap_to_ground_distance;
+        self
+    }
+
+    /// The speed goes down from the light to the heavy mass, in kilograms
+    pub fn with_carry_speed(mut self, light_mass: f32, heavy_mass: f32, heavy_speed: f32) -> Self {
+        self.light_carry_mass = light_mass;
+        self.heavy_carry_mass = heavy_mass;
+        self.heavy_carry_speed = heavy_speed;
+        self
+    }
+
+    pub fn with_max_jump_carry_mass(mut self, max_jump_carry_mass: f32) -> Self {
+        self.max_jump_carry_mass = max_jump_carry_mass;
+        self
+    }
+
+    /// What the speed gets multiplied with while holding objects with this mass
+    fn carry_speed_factor(&self, carried_mass: f32) -> f32 {
+        let heavy_range = (self.heavy_carry_mass - self.light_carry_mass).max(f32::EPSILON);
+        let heaviness = ((carried_mass - self.light_carry_mass) / heavy_range).clamp(0.0, 1.0);
+        1.0 + (self.heavy_carry_speed - 1.0) * heaviness
+    }
+
+    fn can_jump_while_carrying(&self, carried_mass: f32) -> bool {
+        carried_mass <= self.max_jump_carry_mass
+    }
 }
 
 impl Default for PlayerControllerSettings {
@@ -79,24 +251,40 @@ impl Default for PlayerControllerSettings {
 pub fn handle_mouse_movement(
     mut reader: EventReader<MouseMovement>,
     mut camera: ResMut<Camera>,
-    mut query: Query<(&mut Player, &PlayerControllerSettings)>,
+    mut query: Query<(
+        &mut Player,
+        &PlayerCharacterController,
+        &PlayerControllerSettings,
+    )>,
+    gamepad: Res<GamepadState>,
     time: Res<Time>,
 ) {
-    let (mut player, settings) = query.single_mut();
+    let (mut player, character_controller, settings) = query.single_mut();
 
     let mut pitch: Deg<f32> = player.pitch.into();
     let mut yaw: Deg<f32> = player.yaw.into();
 
-    for event in reader.iter() {
-        let MouseMovement(dx, dy) = *event;
-
-        // Note: positive rotations are counter-clockwise. Adding to yaw rotates the camera to the
-        // left. Moving the mouse to the left gives us negative dx values, so we flipped those.
-        // Same logic applies to the y coordinate
-        yaw += Deg(-dx as f32 * settings.sensitivity);
-        pitch += Deg(-dy as f32 * settings.sensitivity);
+    let (mut dx, mut dy) = (0.0, 0.0);
+    for MouseMovement(event_dx, event_dy) in reader.iter() {
+        dx += *event_dx as f32;
+        dy += *event_dy as f32;
     }
 
+    let sensitivity = settings.sensitivity * mouse_acceleration_factor(dx, dy, settings, &time);
+    let y_direction = if settings.invert_y { -1.0 } else { 1.0 };
+
+    // Note: positive rotations are counter-clockwise. Adding to yaw rotates the camera to the
+    // left. Moving the mouse to the left gives us negative dx values, so we flipped those.
+    // Same logic applies to the y coordinate
+    yaw += Deg(-dx * sensitivity);
+    pitch += Deg(-dy * sensitivity * y_direction);
+
+    // The stick is a velocity instead of a distance, so it depends on the frame time
+    let (stick_x, stick_y) = gamepad.right_stick();
+    let stick_factor = settings.gamepad_sensitivity * time.delta_seconds();
+    yaw += Deg(-stick_x * stick_factor);
+    pitch += Deg(stick_y * stick_factor * y_direction);
+
     let max_pitch: Deg<f32> = Deg(88.0);
 
     if pitch < -max_pitch {
@@ -104,26 +292,48 @@ pub fn handle_mouse_movement(
     } else if pitch > max_pitch {
         pitch = max_pitch;
     }
-    let camera_factor = settings.camera_smoothing * time.delta_seconds();
-
-    let target_orientation = UnitQuaternion::from_axis_angle(&Camera::up(), yaw.to_rad().0)
+    // The yaw and pitch are relative to the up direction of the player
+    let target_orientation = character_controller.up_rotation()
+        * UnitQuaternion::from_axis_angle(&Camera::up(), yaw.to_rad().0)
         * UnitQuaternion::from_axis_angle(&Camera::right(), pitch.to_rad().0);
 
-    camera.orientation = camera.orientation.slerp(&target_orientation, camera_factor);
+    camera.orientation = if settings.smooth_camera {
+        let camera_factor = settings.camera_smoothing * time.delta_seconds();
+        camera.orientation.slerp(&target_orientation, camera_factor)
+    } else {
+        target_orientation
+    };
 
     player.pitch = pitch.into();
     player.yaw = yaw.into();
 }
 
+/// Faster mouse movements get a higher sensitivity, measured in mouse counts per second
+fn mouse_acceleration_factor(
+    dx: f32,
+    dy: f32,
+    settings: &PlayerControllerSettings,
+    time: &Time,
+) -> f32 {
+    let delta_time = time.delta_seconds();
+    if settings.mouse_acceleration <= 0.0 || delta_time <= 0.0 {
+        return 1.0;
+    }
+
+    let speed = (dx * dx + dy * dy).sqrt() / delta_time;
+    1.0 + settings.mouse_acceleration * speed / 1000.0
+}
+
 pub fn update_camera_position(
     mut camera: ResMut<Camera>,
     query: Query<(&Player, &PlayerControllerSettings)>,
     input: Res<InputMap>,
+    gamepad: Res<GamepadState>,
     time: Res<Time>,
 ) {
     let (player, settings) = query.single();
 
-    let direction = input_to_direction(&input);
+    let direction = input_to_direction(&input, &gamepad);
 
     let horizontal_movement = normalize_if_not_zero(get_horizontal(&direction));
     let vertical_movement = Camera::up().into_inner() * direction.y;
@@ -139,7 +349,7 @@ pub fn update_camera_position(
     camera.position += vertical_movement * settings.free_cam_speed * delta_time;
 }
 
-fn input_to_direction(input: &InputMap) -> Vector3<f32> {
+fn input_to_direction(input: &InputMap, gamepad: &GamepadState) -> Vector3<f32> {
     let mut direction: Vector3<f32> = Vector3::new(0.0, 0.0, 0.0);
     if input.is_pressed(VirtualKeyCode::W) {
         direction += Camera::forward().into_inner();
@@ -161,9 +371,21 @@ fn input_to_direction(input: &InputMap) -> Vector3<f32> {
     if input.is_pressed(VirtualKeyCode::LShift) {
         direction -= Camera::up().into_inner();
     }
+
+    let (stick_x, stick_y) = gamepad.left_stick();
+    direction += Camera::forward().into_inner() * stick_y;
+    direction += Camera::right().into_inner() * stick_x;
+
+    if gamepad.is_pressed(GamepadButton::South) {
+        direction += Camera::up().into_inner();
+    }
+    if gamepad.is_pressed(GamepadButton::LeftThumb) {
+        direction -= Camera::up().into_inner();
+    }
     direction
 }
 
+/// Expects a vector where +y is up, see [`PlayerCharacterController::up_rotation`]
 fn get_horizontal(input_direction: &Vector3<f32>) -> Vector3<f32> {
     Vector3::new(input_direction.x, 0.0, input_direction.z)
 }
@@ -177,35 +399,80 @@ fn normalize_if_not_zero(vector: Vector3<f32>) -> Vector3<f32> {
     }
 }
 
+/// Keeps how far an analog stick is tilted, while diagonal keys aren't faster
+fn cap_length(vector: Vector3<f32>) -> Vector3<f32> {
+    if vector.norm() > 1.0 {
+        vector.normalize()
+    } else {
+        vector
+    }
+}
+
 fn update_player(
     mut query: Query<(
         &mut Player,
         &mut PlayerCharacterController,
         &PlayerControllerSettings,
+        Option<&GravityScale>,
+        Option<&InFluid>,
     )>,
+    held_query: Query<&RapierRigidBodyHandle, With<PickedUp>>,
+    physics_context: Res<PhysicsContext>,
     input: Res<InputMap>,
+    gamepad: Res<GamepadState>,
     time: Res<Time>,
     game_over: Res<GameOver>,
 ) {
     if game_over.is_game_over() {
         return;
     }
-    let (mut player, mut character_controller, settings) = query.single_mut();
-
-    let input_direction = input_to_direction(&input);
-    let last_velocity = player.velocity;
-    let horizontal_input: Vector3<f32> = normalize_if_not_zero(get_horizontal(&input_direction));
+    let (mut player, mut character_controller, settings, gravity_scale, in_fluid) =
+        query.single_mut();
+    let gravity_scale = gravity_scale.map_or(1.0, |v| v.0);
+    let in_fluid = in_fluid.cloned().unwrap_or_default();
+
+    // Heavy objects make the player slower while they are held
+    let carried_mass: f32 = held_query
+        .iter()
+        .filter_map(|handle| physics_context.rigid_bodies.get(handle.handle))
+        .map(|rigid_body| rigid_body.mass())
+        .sum();
+    let carry_speed = settings.carry_speed_factor(carried_mass);
+
+    // The movement is computed as if +y was up, and gets turned to the up direction of the player at the end
+    let up_rotation = character_controller.up_rotation();
+
+    let input_direction = input_to_direction(&input, &gamepad);
+    let last_velocity = up_rotation.inverse() * player.velocity;
+    let horizontal_input: Vector3<f32> = cap_length(get_horizontal(&input_direction));
     let vertical_input = input_direction.y;
     let camera_horizontal_orientation =
         UnitQuaternion::from_axis_angle(&Camera::up(), player.yaw.0 + 0.1);
 
     let mut velocity = camera_horizontal_orientation * horizontal_input;
 
+    character_controller.wants_to_crouch = input.is_pressed(VirtualKeyCode::LControl)
+        || input.is_pressed(VirtualKeyCode::C)
+        || gamepad.is_pressed(GamepadButton::East);
+
     if character_controller.grounded {
-        velocity = move_ground(&velocity, get_horizontal(&last_velocity), settings, &time);
+        velocity = move_ground(
+            &velocity,
+            get_horizontal(&last_velocity),
+            character_controller.crouched,
+            carry_speed,
+            settings,
+            &time,
+        );
         velocity.y = 0.0;
     } else {
-        velocity = move_air(&velocity, get_horizontal(&last_velocity), settings, &time);
+        velocity = move_air(
+            &velocity,
+            get_horizontal(&last_velocity),
+            carry_speed,
+            settings,
+            &time,
+        );
         velocity.y = last_velocity.y;
     }
 
@@ -214,43 +481,171 @@ fn update_player(
         velocity.z = 0.0;
     }
 
-    if character_controller.grounded && vertical_input > 0.0 {
-        velocity.y = settings.jump_force;
+    if character_controller.grounded
+        && vertical_input > 0.0
+        && settings.can_jump_while_carrying(carried_mass)
+    {
+        // With inverted gravity, jumping goes downwards
+        velocity.y = settings.jump_force * gravity_scale.signum();
     }
 
-    velocity.y -= settings.gravity * time.delta_seconds();
+    // Fluids slow the player down, and carry them up, which makes the jumps floaty
+    velocity *= in_fluid.drag_factor(time.delta_seconds());
+    let fluid_gravity = (1.0 - in_fluid.buoyancy()).max(MIN_FLUID_GRAVITY);
+    velocity.y -= settings.gravity * gravity_scale * fluid_gravity * time.delta_seconds();
 
     // player hitting their head on the roof logic could go here
 
-    player.velocity = velocity;
-    character_controller.desired_movement = velocity;
+    player.velocity = up_rotation * velocity;
+    character_controller.desired_movement = player.velocity;
 }
 
 // Dirty workaround for https://github.com/dimforge/rapier/issues/485
 fn update_player2(mut query: Query<&mut PlayerCharacterController>) {
     let mut character_controller = query.single_mut();
-    character_controller.desired_movement = [0.0, -0.1, 0.0].into();
+    character_controller.desired_movement = character_controller.up.into_inner() * -0.1;
 }
 
 fn update_player_camera(
-    query: Query<(&Transform, &PlayerControllerSettings), With<Player>>,
+    query: Query<
+        (
+            &Transform,
+            &PlayerCharacterController,
+            &PlayerControllerSettings,
+        ),
+        With<Player>,
+    >,
     mut camera: ResMut<Camera>,
 ) {
-    let (player_transform, player_settings) = query.single();
-    camera.position =
-        player_transform.position + Camera::up().into_inner() * player_settings.eye_height;
+    let (player_transform, character_controller, player_settings) = query.single();
+    let eye_height = if character_controller.crouched {
+        player_settings.crouch_eye_height
+    } else {
+        player_settings.eye_height
+    };
+    camera.position = player_transform.position + character_controller.up.into_inner() * eye_height;
+}
+
+fn has_camera_effects_enabled(query: Query<&PlayerControllerSettings, With<Player>>) -> bool {
+    query.single().camera_effects
+}
+
+/// Head-bob while walking, a slight tilt towards nearby ledges and the screen shake.
+/// Runs after the camera has been moved to the player.
+fn camera_effects_system(
+    mut query: Query<
+        (
+            &Player,
+            &PlayerCharacterController,
+            &PlayerControllerSettings,
+            &RapierRigidBodyHandle,
+            &Transform,
+            &mut CameraEffects,
+        ),
+    >,
+    mut camera: ResMut<Camera>,
+    physics_context: Res<PhysicsContext>,
+    time: Res<Time>,
+    screen_shake: Res<ScreenShake>,
+) {
+    let (player, character_controller, settings, rigid_body_handle, transform, mut effects) =
+        query.single_mut();
+    let delta_time = time.delta_seconds();
+    let smoothing_factor = (10.0 * delta_time).min(1.0);
+    let up_rotation = character_controller.up_rotation();
+    let up = character_controller.up.into_inner();
+
+    // head-bob
+    let horizontal_speed = get_horizontal(&(up_rotation.inverse() * player.velocity)).norm();
+    let target_strength = if character_controller.grounded && horizontal_speed > 0.1 {
+        1.0
+    } else {
+        0.0
+    };
+    effects.head_bob_strength += (target_strength - effects.head_bob_strength) * smoothing_factor;
+    effects.head_bob_phase += horizontal_speed
+        * settings.head_bob_frequency
+        * delta_time
+        * std::f32::consts::TAU;
+    effects.head_bob_phase %= std::f32::consts::TAU;
+
+    camera.position +=
+        up * settings.head_bob_amplitude * effects.head_bob_strength * effects.head_bob_phase.sin();
+
+    // edge tilt
+    let camera_horizontal_orientation =
+        up_rotation * UnitQuaternion::from_axis_angle(&Camera::up(), player.yaw.0);
+    let right = camera_horizontal_orientation * Camera::right().into_inner();
+    let has_ground = |offset: Vector3<f32>| {
+        let ray = Ray::new(transform.position + offset + up * 0.5, -up);
+        physics_context
+            .cast_ray(&ray, 1.5, true, vec![rigid_body_handle])
+            .is_some()
+    };
+
+    let edge_detection_distance = 0.5;
+    let target_tilt = if character_controller.grounded {
+        match (
+            has_ground(-right * edge_detection_distance),
+            has_ground(right * edge_detection_distance),
+        ) {
+            (false, true) => -settings.edge_tilt_angle.to_rad().0,
+            (true, false) => settings.edge_tilt_angle.to_rad().0,
+            _ => 0.0,
+        }
+    } else {
+        0.0
+    };
+
+    let previous_tilt = effects.edge_tilt;
+    effects.edge_tilt = previous_tilt + (target_tilt - previous_tilt) * smoothing_factor;
+
+    // the smoothed mouse movement only slowly removes the previous tilt, so we undo it ourselves
+    let tilt = if settings.smooth_camera {
+        effects.edge_tilt - previous_tilt
+    } else {
+        effects.edge_tilt
+    };
+    camera.orientation =
+        camera.orientation * UnitQuaternion::from_axis_angle(&Camera::forward(), tilt);
+
+    // screen shake, smooth noise with a different phase for every axis
+    let elapsed_time = time.time_since_startup().as_secs_f32();
+    let noise = |phase: f32| {
+        ((elapsed_time * SHAKE_FREQUENCY + phase).sin()
+            + (elapsed_time * SHAKE_FREQUENCY * 2.3 + phase * 1.7).sin())
+            * 0.5
+    };
+    let previous_shake_angles = effects.shake_angles;
+    effects.shake_angles = Vector3::new(noise(0.0), noise(10.0), noise(20.0))
+        * MAX_SHAKE_ANGLE
+        * screen_shake.strength();
+
+    let shake_rotation = |angles: Vector3<f32>| {
+        UnitQuaternion::from_axis_angle(&Camera::right(), angles.x)
+            * UnitQuaternion::from_axis_angle(&Camera::up(), angles.y)
+            * UnitQuaternion::from_axis_angle(&Camera::forward(), angles.z)
+    };
+    // just like the tilt, the smoothed mouse movement would keep the previous shake around
+    let shake = if settings.smooth_camera {
+        shake_rotation(previous_shake_angles).inverse() * shake_rotation(effects.shake_angles)
+    } else {
+        shake_rotation(effects.shake_angles)
+    };
+    camera.orientation = camera.orientation * shake;
 }
 
 fn move_air(
     velocity: &Vector3<f32>,
     last_horizontal_velocity: Vector3<f32>,
+    carry_speed: f32,
     settings: &PlayerControllerSettings,
     time: &Time,
 ) -> Vector3<f32> {
     accelerate(
         velocity,
         last_horizontal_velocity,
-        settings.max_velocity_air,
+        settings.max_velocity_air * carry_speed,
         settings.air_accelerate,
         time,
     )
@@ -259,6 +654,8 @@ fn move_air(
 fn move_ground(
     velocity: &Vector3<f32>,
     mut last_horizontal_velocity: Vector3<f32>,
+    crouched: bool,
+    carry_speed: f32,
     settings: &PlayerControllerSettings,
     time: &Time,
 ) -> Vector3<f32> {
@@ -268,10 +665,16 @@ fn move_ground(
         last_horizontal_velocity *= (speed - drop).max(0.0) / speed;
     }
 
+    let max_velocity = if crouched {
+        settings.max_velocity_crouched
+    } else {
+        settings.max_velocity_ground
+    };
+
     accelerate(
         velocity,
         last_horizontal_velocity,
-        settings.max_velocity_ground,
+        max_velocity * carry_speed,
         settings.ground_accelerate,
         time,
     )
@@ -368,6 +771,13 @@ impl Plugin for PlayerPlugin {
                     .in_set(PlayerPluginSets::UpdateCamera)
                     .run_if(not(has_free_camera_activated))
                     .ambiguous_with(update_camera_position),
+            )
+            .with_system(
+                camera_effects_system
+                    .in_set(PlayerPluginSets::UpdateCamera)
+                    .after(update_player_camera)
+                    .run_if(not(has_free_camera_activated))
+                    .run_if(has_camera_effects_enabled),
             );
     }
 }
@@ -380,18 +790,26 @@ pub struct PlayerSpawnSettings {
 }
 
 fn setup_player(mut commands: Commands, spawn_settings: Res<PlayerSpawnSettings>) {
+    let controller_settings = &spawn_settings.controller_settings;
+
     // spawn player, character-controller
     commands.spawn((
-        spawn_settings.controller_settings.clone(),
+        controller_settings.clone(),
         Player {
             velocity: Default::default(),
             yaw: Default::default(),
             pitch: Default::default(),
         },
         spawn_settings.initial_transform.clone(),
-        PlayerCharacterController::default(),
+        PlayerCharacterController {
+            max_slope_angle: controller_settings.max_slope_angle.to_rad().0,
+            step_height: controller_settings.step_height,
+            snap_to_ground_distance: controller_settings.snap_to_ground_distance,
+            ..PlayerCharacterController::default()
+        },
         CameraMode {
             free_cam_activated: spawn_settings.free_cam_activated,
         },
+        CameraEffects::default(),
     ));
 }
@@ -0,0 +1,19 @@
+use loader::{
+    loader::{ColliderKind, RigidBodyKind},
+    prefab::Prefab,
+};
+
+/// Keeps the mesh from the level, so that every crate can still look a bit different.
+/// The collider is made from that mesh, so a crate can't be placed with an empty node.
+pub fn crate_prefab() -> Prefab {
+    Prefab::new()
+        .with_collider(ColliderKind::Box)
+        .with_rigid_body(RigidBodyKind::Dynamic)
+        .with_pickupable(true)
+        .with_casts_shadow(true)
+}
+
+/// Too heavy to jump with, and slows the player down while it is carried
+pub fn heavy_crate_prefab() -> Prefab {
+    crate_prefab().with_mass(50.0)
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+use std::sync::Arc;
+
+use bevy_ecs::prelude::*;
+use loader::config_loader::PressurePlateIndicator;
+use scene::asset::{Asset, AssetId};
+use scene::material::CpuMaterial;
+use scene::texture::{
+    AddressMode, BytesTextureData, CpuTexture, Filter, MipmapMode, SamplerInfo, TextureFormat,
+};
+
+/// The glow of a pulsing plate goes through this many materials, so that it doesn't need a new one every frame
+const PULSE_STEPS: usize = 8;
+const PULSE_SECONDS: f32 = 1.5;
+const PATTERN_SIZE: u32 = 16;
+
+/// The pressure plate materials for the colorblind friendly indicators.
+/// Created from the original materials when they are needed for the first time.
+#[derive(Resource, Default)]
+pub struct PressurePlateMaterials {
+    pattern_texture: Option<Arc<CpuTexture>>,
+    pattern_materials: HashMap<AssetId, Arc<CpuMaterial>>,
+    pulse_materials: HashMap<AssetId, Vec<Arc<CpuMaterial>>>,
+}
+
+impl PressurePlateMaterials {
+    /// The material of a pressed plate, inactive plates always keep their material
+    pub fn active_material(
+        &mut self,
+        material: &Arc<CpuMaterial>,
+        indicator: PressurePlateIndicator,
+        time_since_startup: f32,
+    ) -> Arc<CpuMaterial> {
+        match indicator {
+            PressurePlateIndicator::Color => material.clone(),
+            PressurePlateIndicator::Pattern => {
+                let pattern_texture = self
+                    .pattern_texture
+                    .get_or_insert_with(create_pattern_texture)
+                    .clone();
+                self.pattern_materials
+                    .entry(material.id())
+                    .or_insert_with(|| {
+                        Arc::new(CpuMaterial {
+                            base_color_texture: Some(pattern_texture),
+                            ..copy_material(material)
+                        })
+                    })
+                    .clone()
+            }
+            PressurePlateIndicator::Pulse => {
+                let pulse_materials = self
+                    .pulse_materials
+                    .entry(material.id())
+                    .or_insert_with(|| create_pulse_materials(material));
+                let step = (time_since_startup / PULSE_SECONDS * PULSE_STEPS as f32) as usize;
+                pulse_materials[step % PULSE_STEPS].clone()
+            }
+        }
+    }
+}
+
+/// A new material with the same properties
+fn copy_material(material: &CpuMaterial) -> CpuMaterial {
+    CpuMaterial {
+        id: AssetId::new_v4(),
+        base_color: material.base_color,
+        base_color_texture: material.base_color_texture.clone(),
+        lightmap_texture: material.lightmap_texture.clone(),
+        roughness_factor: material.roughness_factor,
+        metallic_factor: material.metallic_factor,
+        emissivity: material.emissivity,
+        reflectivity: material.reflectivity,
+        wind: material.wind,
+        flipbook: material.flipbook,
+        opacity: material.opacity,
+    }
+}
+
+/// Diagonal stripes, which stay visible regardless of the colors
+fn create_pattern_texture() -> Arc<CpuTexture> {
+    let mut bytes = Vec::with_capacity((PATTERN_SIZE * PATTERN_SIZE * 4) as usize);
+    for y in 0..PATTERN_SIZE {
+        for x in 0..PATTERN_SIZE {
+            let brightness = if (x + y) % 8 < 4 { 255 } else { 64 };
+            bytes.extend_from_slice(&[brightness, brightness, brightness, 255]);
+        }
+    }
+
+    Arc::new(CpuTexture {
+        id: AssetId::new_v4(),
+        data: Box::new(BytesTextureData::new(
+            (PATTERN_SIZE, PATTERN_SIZE),
+            TextureFormat::R8G8B8A8_UNORM,
+            bytes,
+        )),
+        sampler_info: SamplerInfo {
+            min_filter: Filter::Nearest,
+            mag_filter: Filter::Nearest,
+            mipmap_mode: MipmapMode::Nearest,
+            address_mode: [AddressMode::Repeat; 3],
+        },
+    })
+}
+
+/// The glow goes from nothing to twice the original one and back
+fn create_pulse_materials(material: &CpuMaterial) -> Vec<Arc<CpuMaterial>> {
+    (0..PULSE_STEPS)
+        .map(|step| {
+            let phase = step as f32 / PULSE_STEPS as f32 * TAU;
+            let strength = 1.0 - phase.cos();
+            Arc::new(CpuMaterial {
+                emissivity: material.emissivity.scale(strength),
+                ..copy_material(material)
+            })
+        })
+        .collect()
+}
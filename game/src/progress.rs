@@ -0,0 +1,47 @@
+use bevy_ecs::system::Resource;
+use levels::level_id::LevelId;
+use loader::progress_loader::LoadableProgress;
+
+/// How far the player got over all sessions, like the unlocked levels and the achievements.
+/// Gets saved right away whenever something new is unlocked.
+#[derive(Resource)]
+pub struct Progress {
+    progress: LoadableProgress,
+    path: String,
+}
+
+impl Progress {
+    pub fn load(path: impl Into<String>) -> Self {
+        let path = path.into();
+        Self {
+            progress: LoadableProgress::load(&path),
+            path,
+        }
+    }
+
+    /// The furthest level that was reached
+    pub fn unlocked_level(&self) -> LevelId {
+        LevelId::new(self.progress.unlocked_level)
+    }
+
+    pub fn unlock_level(&mut self, level_id: LevelId) {
+        if level_id.id() > self.progress.unlocked_level {
+            self.progress.unlocked_level = level_id.id();
+            self.progress.save(&self.path);
+        }
+    }
+
+    pub fn has_achievement(&self, id: &str) -> bool {
+        self.progress.achievements.iter().any(|other| other == id)
+    }
+
+    /// Returns false if the achievement had already been unlocked before
+    pub fn unlock_achievement(&mut self, id: &str) -> bool {
+        if self.has_achievement(id) {
+            return false;
+        }
+        self.progress.achievements.push(id.to_string());
+        self.progress.save(&self.path);
+        true
+    }
+}
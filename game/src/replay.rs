@@ -0,0 +1,306 @@
+use std::path::PathBuf;
+
+use angle::Rad;
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::event::{Event, ManualEventReader};
+use bevy_ecs::prelude::*;
+use input::events::{ElementState, KeyboardInput, MouseInput, MouseMovement, MouseWheel};
+use levels::current_level::{CurrentLevel, NextLevel, ResetLevel};
+use loader::replay_loader::{LoadableReplay, ReplayInput, ReplayStep};
+use scene::transform::Transform;
+
+use crate::game_state::GameState;
+use crate::player::Player;
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ReplayPluginSets {
+    /// Records or replaces the inputs, has to run before the input handling
+    Input,
+    /// Records or replaces the player state, after the physics
+    PlayerState,
+}
+
+enum ReplayState {
+    Idle,
+    Recording(LoadableReplay),
+    /// Waits until the level of the replay gets played, for example by choosing it in the level select
+    WaitingForLevel(LoadableReplay),
+    Playing {
+        replay: LoadableReplay,
+        step: usize,
+        reached_next_level: bool,
+    },
+    Done,
+}
+
+/// Records every level run to `replays/level_<id>.json`, from the start of the level until the next
+/// level starts. Restarting the level also restarts the recording.
+///
+/// A replay takes over the inputs once its level is being played, and prints whether the run
+/// still reaches the next level. That makes it possible to check that the puzzles can still be
+/// solved after a level has been edited.
+#[derive(Resource)]
+pub struct Replay {
+    state: ReplayState,
+    /// Recording is off if this is None
+    record_directory: Option<PathBuf>,
+    /// Whether the current fixed step has been recorded, the player state gets added after the physics
+    step_pending: bool,
+}
+
+impl Replay {
+    pub fn new(record_directory: Option<PathBuf>, playback: Option<LoadableReplay>) -> Self {
+        Self {
+            state: match playback {
+                Some(replay) => ReplayState::WaitingForLevel(replay),
+                None => ReplayState::Idle,
+            },
+            record_directory,
+            step_pending: false,
+        }
+    }
+
+    fn save_recording(&self, replay: &LoadableReplay) {
+        if let Some(record_directory) = &self.record_directory {
+            let path = record_directory.join(format!("level_{}.json", replay.level_id));
+            replay.save(&path);
+            println!("Saved the replay to {:?}", path);
+        }
+    }
+}
+
+/// Starts, restarts and finishes the recordings, and starts the playback
+fn update_replay_state(
+    mut replay: ResMut<Replay>,
+    game_state: Res<GameState>,
+    current_level: Res<CurrentLevel>,
+    mut next_level_events: EventReader<NextLevel>,
+    mut reset_level_events: EventReader<ResetLevel>,
+) {
+    let replay = replay.as_mut();
+    let next_level = next_level_events.iter().last();
+    let was_reset = reset_level_events
+        .iter()
+        .filter(|reset_level| reset_level.level_id == current_level.level_id)
+        .count()
+        > 0;
+
+    match &mut replay.state {
+        ReplayState::Idle => {
+            if replay.record_directory.is_some() && game_state.is_playing() {
+                replay.state =
+                    ReplayState::Recording(LoadableReplay::new(current_level.level_id.id()));
+            }
+        }
+        ReplayState::Recording(recording) => {
+            if next_level.is_some() {
+                let finished_recording =
+                    std::mem::replace(recording, LoadableReplay::new(current_level.level_id.id()));
+                replay.save_recording(&finished_recording);
+            } else if was_reset {
+                recording.steps.clear();
+            }
+        }
+        ReplayState::WaitingForLevel(waiting_replay) => {
+            if waiting_replay.level_id == current_level.level_id.id() && game_state.is_playing() {
+                println!(
+                    "Playing back the replay of level {}",
+                    waiting_replay.level_id
+                );
+                let waiting_replay = std::mem::replace(waiting_replay, LoadableReplay::new(0));
+                replay.state = ReplayState::Playing {
+                    replay: waiting_replay,
+                    step: 0,
+                    reached_next_level: false,
+                };
+            }
+        }
+        ReplayState::Playing {
+            reached_next_level, ..
+        } => {
+            if next_level.is_some() {
+                *reached_next_level = true;
+            }
+        }
+        ReplayState::Done => {}
+    }
+}
+
+/// The recorded inputs replace the real ones during the playback
+fn record_or_replay_inputs(
+    mut replay: ResMut<Replay>,
+    game_state: Res<GameState>,
+    mut keyboard_events: ResMut<Events<KeyboardInput>>,
+    mut mouse_events: ResMut<Events<MouseInput>>,
+    mut mouse_movement_events: ResMut<Events<MouseMovement>>,
+    mut mouse_wheel_events: ResMut<Events<MouseWheel>>,
+    mut keyboard_reader: Local<ManualEventReader<KeyboardInput>>,
+    mut mouse_reader: Local<ManualEventReader<MouseInput>>,
+    mut mouse_movement_reader: Local<ManualEventReader<MouseMovement>>,
+    mut mouse_wheel_reader: Local<ManualEventReader<MouseWheel>>,
+) {
+    let replay = replay.as_mut();
+    replay.step_pending = false;
+    match &mut replay.state {
+        ReplayState::Recording(recording) if game_state.is_playing() => {
+            let mut inputs = vec![];
+            inputs.extend(
+                keyboard_reader
+                    .iter(&keyboard_events)
+                    .map(|event| ReplayInput::Key {
+                        key_code: event.key_code,
+                        pressed: event.state == ElementState::Pressed,
+                    }),
+            );
+            inputs.extend(
+                mouse_reader
+                    .iter(&mouse_events)
+                    .map(|event| ReplayInput::MouseButton {
+                        button: event.button,
+                        pressed: event.state == ElementState::Pressed,
+                    }),
+            );
+            inputs.extend(
+                mouse_movement_reader
+                    .iter(&mouse_movement_events)
+                    .map(|MouseMovement(dx, dy)| ReplayInput::MouseMovement { dx: *dx, dy: *dy }),
+            );
+            inputs.extend(
+                mouse_wheel_reader
+                    .iter(&mouse_wheel_events)
+                    .map(|MouseWheel(lines)| ReplayInput::MouseWheel { lines: *lines }),
+            );
+
+            recording.steps.push(ReplayStep {
+                inputs,
+                position: [0.0; 3],
+                yaw: 0.0,
+                pitch: 0.0,
+            });
+            replay.step_pending = true;
+        }
+        ReplayState::Playing {
+            replay: playback,
+            step,
+            ..
+        } => {
+            keyboard_events.clear();
+            mouse_events.clear();
+            mouse_movement_events.clear();
+            mouse_wheel_events.clear();
+
+            let replay_step = match playback.steps.get(*step) {
+                Some(replay_step) => replay_step,
+                None => return,
+            };
+            for input in replay_step.inputs.iter() {
+                match input {
+                    ReplayInput::Key { key_code, pressed } => keyboard_events.send(KeyboardInput {
+                        key_code: *key_code,
+                        state: to_element_state(*pressed),
+                    }),
+                    ReplayInput::MouseButton { button, pressed } => mouse_events.send(MouseInput {
+                        button: *button,
+                        state: to_element_state(*pressed),
+                    }),
+                    ReplayInput::MouseMovement { dx, dy } => {
+                        mouse_movement_events.send(MouseMovement(*dx, *dy))
+                    }
+                    ReplayInput::MouseWheel { lines } => {
+                        mouse_wheel_events.send(MouseWheel(*lines))
+                    }
+                }
+            }
+        }
+        _ => {
+            // Only the new events should get recorded once the recording starts
+            skip_events(&mut keyboard_reader, &keyboard_events);
+            skip_events(&mut mouse_reader, &mouse_events);
+            skip_events(&mut mouse_movement_reader, &mouse_movement_events);
+            skip_events(&mut mouse_wheel_reader, &mouse_wheel_events);
+        }
+    }
+}
+
+fn skip_events<E: Event>(reader: &mut ManualEventReader<E>, events: &Events<E>) {
+    for _ in reader.iter(events) {}
+}
+
+fn to_element_state(pressed: bool) -> ElementState {
+    if pressed {
+        ElementState::Pressed
+    } else {
+        ElementState::Released
+    }
+}
+
+/// Keeps the player on the recorded path, since the physics don't behave exactly the same every time
+fn record_or_replay_player_state(
+    mut replay: ResMut<Replay>,
+    mut players_query: Query<(&mut Transform, &mut Player)>,
+) {
+    let replay = replay.as_mut();
+    let (mut transform, mut player) = match players_query.get_single_mut() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+
+    match &mut replay.state {
+        ReplayState::Recording(recording) if replay.step_pending => {
+            if let Some(replay_step) = recording.steps.last_mut() {
+                replay_step.position = transform.position.into();
+                replay_step.yaw = player.yaw.0;
+                replay_step.pitch = player.pitch.0;
+            }
+        }
+        ReplayState::Playing {
+            replay: playback,
+            step,
+            reached_next_level,
+        } => match playback.steps.get(*step) {
+            Some(replay_step) => {
+                transform.position = replay_step.position.into();
+                player.yaw = Rad(replay_step.yaw);
+                player.pitch = Rad(replay_step.pitch);
+                *step += 1;
+            }
+            None => {
+                println!(
+                    "Finished the replay of level {}, reached the next level: {}",
+                    playback.level_id, reached_next_level
+                );
+                replay.state = ReplayState::Done;
+            }
+        },
+        _ => {}
+    }
+}
+
+pub struct ReplayPlugin {
+    record_directory: Option<PathBuf>,
+    playback_path: Option<PathBuf>,
+}
+
+impl ReplayPlugin {
+    pub fn new(record_directory: Option<PathBuf>, playback_path: Option<PathBuf>) -> Self {
+        Self {
+            record_directory,
+            playback_path,
+        }
+    }
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        let playback = self.playback_path.as_ref().map(LoadableReplay::load);
+        app //
+            .with_resource(Replay::new(self.record_directory.clone(), playback))
+            .with_system(update_replay_state.in_set(ReplayPluginSets::Input))
+            .with_system(
+                record_or_replay_inputs
+                    .in_set(ReplayPluginSets::Input)
+                    .after(update_replay_state),
+            )
+            .with_system(record_or_replay_player_state.in_set(ReplayPluginSets::PlayerState));
+    }
+}
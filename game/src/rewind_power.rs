@@ -1,8 +1,11 @@
 use app::plugin::Plugin;
 use bevy_ecs::system::{Res, ResMut, Resource};
+use time::time::Time;
 use time::time_manager::TimeManager;
 
-#[derive(Resource)]
+use crate::level_restart::LevelRestart;
+
+#[derive(Resource, Clone)]
 pub struct RewindPower {
     remaining_seconds: f32,
     pub max_seconds: f32,
@@ -36,12 +39,27 @@ impl RewindPower {
     }
 }
 
-fn update_rewind_power(mut rewind_power: ResMut<RewindPower>, time_manager: Res<TimeManager>) {
+fn update_rewind_power(
+    mut rewind_power: ResMut<RewindPower>,
+    time_manager: Res<TimeManager>,
+    time: Res<Time>,
+    level_restart: Res<LevelRestart>,
+) {
+    if level_restart.is_restarting() {
+        return;
+    }
+
     let consumed_power = time_manager.level_delta_time();
     if consumed_power.is_negative() {
         rewind_power.remaining_seconds =
             (rewind_power.remaining_seconds - consumed_power.duration().as_secs_f32()).max(0.0);
     }
+
+    // Rewinding a single object costs as much as holding the rewind button
+    if time_manager.is_rewinding_target() {
+        rewind_power.remaining_seconds =
+            (rewind_power.remaining_seconds - time.delta_seconds()).max(0.0);
+    }
 }
 
 pub struct RewindPowerPlugin;
@@ -0,0 +1,90 @@
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::{EventReader, Query, Res, ResMut, Resource, With};
+use input::events::MouseWheel;
+use input::gamepad::{GamepadButton, GamepadState};
+use input::input_map::InputMap;
+use physics::pickup_physics::PickedUp;
+use time::time::Time;
+use windowing::event::VirtualKeyCode;
+
+pub const MIN_REWIND_SPEED: f32 = 0.25;
+pub const MAX_REWIND_SPEED: f32 = 4.0;
+/// Holding shift rewinds at this speed, like it always did
+const SHIFT_REWIND_SPEED: f32 = 3.0;
+
+/// How much one line of the mouse wheel changes the rewind input
+const SCROLL_STEP: f32 = 0.05;
+
+/// Maps an input from 0 to 1 onto the rewind speed.
+/// Exponential, so that every step of the mouse wheel feels equally large.
+pub fn rewind_speed_curve(input: f32) -> f32 {
+    MIN_REWIND_SPEED * (MAX_REWIND_SPEED / MIN_REWIND_SPEED).powf(input.clamp(0.0, 1.0))
+}
+
+/// How fast rewinding should be. The actual speed smoothly follows the input.
+#[derive(Resource)]
+pub struct RewindSpeed {
+    /// From 0 to 1, changed with the mouse wheel
+    pub input: f32,
+    /// Takes precedence over the mouse wheel, for example a gamepad trigger
+    pub analog_input: Option<f32>,
+    pub smoothing: f32,
+    speed: f32,
+}
+
+impl RewindSpeed {
+    pub fn new() -> Self {
+        // Starts out rewinding as fast as time normally passes
+        let input = (1.0 / MIN_REWIND_SPEED).ln() / (MAX_REWIND_SPEED / MIN_REWIND_SPEED).ln();
+        Self {
+            input,
+            analog_input: None,
+            smoothing: 10.0,
+            speed: 1.0,
+        }
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+}
+
+fn update_rewind_speed(
+    mut rewind_speed: ResMut<RewindSpeed>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    input: Res<InputMap>,
+    gamepad: Res<GamepadState>,
+    time: Res<Time>,
+    held_objects: Query<(), With<PickedUp>>,
+) {
+    // Pressing the rewind trigger further rewinds faster
+    let trigger = gamepad.button_value(GamepadButton::LeftTrigger2);
+    rewind_speed.analog_input = if trigger > 0.0 { Some(trigger) } else { None };
+
+    // While holding an object, the mouse wheel rotates it instead
+    let is_holding = !held_objects.is_empty();
+    for MouseWheel(lines) in mouse_wheel.iter().filter(|_| !is_holding) {
+        rewind_speed.input = (rewind_speed.input + lines * SCROLL_STEP).clamp(0.0, 1.0);
+    }
+
+    let target_speed = if input.is_pressed(VirtualKeyCode::LShift)
+        || input.is_pressed(VirtualKeyCode::RShift)
+    {
+        SHIFT_REWIND_SPEED
+    } else {
+        rewind_speed_curve(rewind_speed.analog_input.unwrap_or(rewind_speed.input))
+    };
+
+    let factor = (rewind_speed.smoothing * time.delta_seconds()).min(1.0);
+    rewind_speed.speed += (target_speed - rewind_speed.speed) * factor;
+}
+
+pub struct RewindSpeedPlugin;
+
+impl Plugin for RewindSpeedPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(RewindSpeed::new())
+            .with_system(update_rewind_speed);
+    }
+}
@@ -0,0 +1,90 @@
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::{not, Commands, Entity, Query, Res, With};
+use bevy_ecs::schedule::IntoSystemConfig;
+use input::gamepad::{GamepadButton, GamepadState};
+use input::input_map::InputMap;
+use physics::physics_context::{PhysicsContext, RapierRigidBodyHandle, RigidBody, Ray};
+use physics::pickup_physics::PickedUp;
+use scene::camera::Camera;
+use time::time_manager::{is_rewinding, RewindTarget, TimeManager, TimeTracked};
+use windowing::event::VirtualKeyCode;
+
+use crate::game_over::GameOver;
+use crate::player::Player;
+use crate::rewind_power::RewindPower;
+use crate::rewind_speed::RewindSpeed;
+
+/// How far away an object can be to still get rewound
+const MAX_TARGET_DISTANCE: f32 = 10.0;
+
+/// Holding R or the west gamepad button while aiming at an object only rewinds that object
+fn read_rewind_target_input(
+    mut commands: Commands,
+    time_manager: Res<TimeManager>,
+    input: Res<InputMap>,
+    gamepad: Res<GamepadState>,
+    game_over: Res<GameOver>,
+    rewind_power: Res<RewindPower>,
+    rewind_speed: Res<RewindSpeed>,
+    physics_context: Res<PhysicsContext>,
+    camera: Res<Camera>,
+    targets: Query<Entity, With<RewindTarget>>,
+    rewindable_query: Query<(), (With<TimeTracked>, With<RigidBody>)>,
+    exclude_query: Query<&RapierRigidBodyHandle, With<Player>>,
+) {
+    let wants_to_rewind = (input.is_pressed(VirtualKeyCode::R)
+        || gamepad.is_pressed(GamepadButton::West))
+        && !rewind_power.is_empty()
+        && !game_over.is_game_over();
+
+    if !wants_to_rewind {
+        for entity in targets.iter() {
+            commands.entity(entity).remove::<RewindTarget>();
+        }
+        return;
+    }
+
+    if targets.is_empty() {
+        let ray = Ray::new(
+            camera.position,
+            camera.orientation * Camera::forward().into_inner(),
+        );
+        let hit = physics_context.cast_ray(
+            &ray,
+            MAX_TARGET_DISTANCE,
+            true,
+            exclude_query.iter().collect(),
+        );
+        let entity = hit
+            .map(|(entity, _toi)| entity)
+            .filter(|entity| rewindable_query.contains(*entity));
+
+        match entity {
+            Some(entity) => {
+                commands
+                    .entity(entity)
+                    .remove::<PickedUp>()
+                    .insert(RewindTarget);
+            }
+            None => return,
+        }
+    }
+
+    time_manager.rewind_target_next_frame(rewind_speed.speed());
+}
+
+fn clear_targets_when_rewinding(mut commands: Commands, query: Query<Entity, With<RewindTarget>>) {
+    for entity in query.iter() {
+        commands.entity(entity).remove::<RewindTarget>();
+    }
+}
+
+pub struct RewindTargetPlugin;
+
+impl Plugin for RewindTargetPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_system(clear_targets_when_rewinding.run_if(is_rewinding))
+            .with_system(read_rewind_target_input.run_if(not(is_rewinding)));
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use angle::Rad;
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::*;
+use input::events::KeyboardInput;
+use levels::current_level::{CurrentLevel, NextLevel};
+use levels::level_id::LevelId;
+use nalgebra::Vector3;
+use physics::physics_context::{PhysicsContext, RapierRigidBodyHandle};
+use scene::transform::Transform;
+use time::time_manager::save_state::SaveStateCommand;
+use time::time_manager::{TimeManager, TimeState};
+use windowing::event::ElementState::Released;
+use windowing::event::VirtualKeyCode::{F4, F5};
+
+use crate::game_over::GameOver;
+use crate::player::Player;
+use crate::rewind_power::RewindPower;
+
+/// What the histories don't record, they take care of the transforms, the flags and so on
+struct SaveState {
+    level_id: LevelId,
+    player_transform: Transform,
+    player_velocity: Vector3<f32>,
+    player_yaw: Rad<f32>,
+    player_pitch: Rad<f32>,
+    /// The linear and the angular velocity of every rigid body
+    velocities: HashMap<Entity, (Vector3<f32>, Vector3<f32>)>,
+    rewind_power: RewindPower,
+}
+
+/// A developer tool for puzzles, F5 saves the state of the current level and F4 loads it again.
+/// Loading isn't on F9, because that already toggles the depth prepass.
+/// There is only a single save state, and it gets dropped when the next level starts.
+/// It only lives in memory, since the entities in it are only valid while the game is running.
+#[derive(Resource, Default)]
+pub struct SaveStates {
+    save_state: Option<SaveState>,
+    /// The rewinding of the load has to finish before the velocities can be set
+    is_loading: bool,
+}
+
+fn save_or_load_state(
+    mut save_states: ResMut<SaveStates>,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut next_level_events: EventReader<NextLevel>,
+    mut save_state_commands: EventWriter<SaveStateCommand>,
+    time_manager: Res<TimeManager>,
+    game_over: Res<GameOver>,
+    current_level: Res<CurrentLevel>,
+    rewind_power: Res<RewindPower>,
+    physics_context: Res<PhysicsContext>,
+    players: Query<(&Transform, &Player)>,
+    rigid_bodies: Query<(Entity, &RapierRigidBodyHandle)>,
+) {
+    if next_level_events.iter().next().is_some() {
+        save_states.save_state = None;
+    }
+
+    for event in keyboard_events.iter() {
+        if event.state != Released || (event.key_code != F5 && event.key_code != F4) {
+            continue;
+        }
+        if time_manager.is_rewinding() || game_over.is_game_over() || save_states.is_loading {
+            println!("Save states only work while the level is running normally");
+            continue;
+        }
+
+        if event.key_code == F5 {
+            let (player_transform, player) = players.single();
+            let velocities = rigid_bodies
+                .iter()
+                .filter_map(|(entity, body_handle)| {
+                    let rigid_body = physics_context.rigid_bodies.get(body_handle.handle)?;
+                    Some((entity, (*rigid_body.linvel(), *rigid_body.angvel())))
+                })
+                .collect();
+            save_states.save_state = Some(SaveState {
+                level_id: current_level.level_id,
+                player_transform: player_transform.clone(),
+                player_velocity: player.velocity,
+                player_yaw: player.yaw,
+                player_pitch: player.pitch,
+                velocities,
+                rewind_power: rewind_power.clone(),
+            });
+            save_state_commands.send(SaveStateCommand::Save);
+            println!("Saved the state of level {}", current_level.level_id.id());
+        } else {
+            match &save_states.save_state {
+                Some(save_state) if save_state.level_id == current_level.level_id => {
+                    save_states.is_loading = true;
+                    save_state_commands.send(SaveStateCommand::Load);
+                }
+                _ => println!("There is no save state for this level, save one with F5"),
+            }
+        }
+    }
+}
+
+fn finish_loading_state(
+    mut save_states: ResMut<SaveStates>,
+    time_manager: Res<TimeManager>,
+    mut rewind_power: ResMut<RewindPower>,
+    mut physics_context: ResMut<PhysicsContext>,
+    mut players: Query<(&mut Transform, &mut Player)>,
+    rigid_bodies: Query<(Entity, &RapierRigidBodyHandle)>,
+) {
+    // The rigid bodies are dynamic again once the rewinding stops
+    if !save_states.is_loading || time_manager.time_state() != TimeState::StopRewinding {
+        return;
+    }
+    save_states.is_loading = false;
+    let save_state = match &save_states.save_state {
+        Some(save_state) => save_state,
+        None => return,
+    };
+
+    let (mut transform, mut player) = players.single_mut();
+    *transform = save_state.player_transform.clone();
+    player.velocity = save_state.player_velocity;
+    player.yaw = save_state.player_yaw;
+    player.pitch = save_state.player_pitch;
+
+    for (entity, body_handle) in rigid_bodies.iter() {
+        let (linear_velocity, angular_velocity) = match save_state.velocities.get(&entity) {
+            Some(velocities) => velocities,
+            None => continue,
+        };
+        if let Some(rigid_body) = physics_context.rigid_bodies.get_mut(body_handle.handle) {
+            rigid_body.set_linvel(*linear_velocity, true);
+            rigid_body.set_angvel(*angular_velocity, true);
+        }
+    }
+
+    *rewind_power = save_state.rewind_power.clone();
+    println!("Loaded the state of level {}", save_state.level_id.id());
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SaveStatesPluginSets {
+    /// Reads the keys, before the time manager starts the next frame
+    Input,
+    /// Restores what the histories don't, after the rewinding of the load
+    Load,
+}
+
+pub struct SaveStatesPlugin;
+
+impl Plugin for SaveStatesPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(SaveStates::default())
+            .with_system(save_or_load_state.in_set(SaveStatesPluginSets::Input))
+            .with_system(finish_loading_state.in_set(SaveStatesPluginSets::Load));
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::*;
+use levels::current_level::CurrentLevel;
+use levels::level_id::LevelId;
+use nalgebra::Vector3;
+use scene::flash::Flash;
+use scene::screen_shake::ScreenShake;
+use time::time_manager::TimeManager;
+
+use crate::alarm::AlarmTriggered;
+use crate::door::DoorState;
+use crate::footsteps::{Footstep, FootstepKind};
+
+const DOOR_SLAM_TRAUMA: f32 = 0.4;
+const ALARM_TRAUMA: f32 = 0.3;
+const ALARM_FLASH_COLOR: Vector3<f32> = Vector3::new(1.0, 0.1, 0.05);
+const ALARM_FLASH_DURATION: Duration = Duration::from_millis(600);
+/// Landings that are at least this loud shake the camera, see [`Footstep::volume`]
+const HARD_LANDING_VOLUME: f32 = 0.6;
+
+/// Rewinding a door also changes its state, but it only slams when time moves forwards
+fn shake_on_door_slam(
+    query: Query<(Entity, &DoorState, &LevelId), Changed<DoorState>>,
+    current_level: Res<CurrentLevel>,
+    time_manager: Res<TimeManager>,
+    mut previous_states: Local<HashMap<Entity, DoorState>>,
+    mut screen_shake: ResMut<ScreenShake>,
+) {
+    for (entity, door_state, level_id) in query.iter() {
+        let previous_state = previous_states.insert(entity, *door_state);
+        if time_manager.is_rewinding() || level_id != &current_level.level_id {
+            continue;
+        }
+
+        if previous_state == Some(DoorState::Closing) && *door_state == DoorState::Closed {
+            screen_shake.add_trauma(DOOR_SLAM_TRAUMA);
+        }
+    }
+}
+
+fn flash_on_alarm(
+    mut alarm_triggered: EventReader<AlarmTriggered>,
+    current_level: Res<CurrentLevel>,
+    mut flash: ResMut<Flash>,
+    mut screen_shake: ResMut<ScreenShake>,
+) {
+    for event in alarm_triggered.iter() {
+        if event.level_id == current_level.level_id {
+            flash.trigger(ALARM_FLASH_COLOR, ALARM_FLASH_DURATION);
+            screen_shake.add_trauma(ALARM_TRAUMA);
+        }
+    }
+}
+
+/// Falling further shakes the camera more
+fn shake_on_hard_landing(
+    mut footsteps: EventReader<Footstep>,
+    mut screen_shake: ResMut<ScreenShake>,
+) {
+    for footstep in footsteps.iter() {
+        if footstep.kind == FootstepKind::Landing && footstep.volume >= HARD_LANDING_VOLUME {
+            screen_shake.add_trauma(footstep.volume - HARD_LANDING_VOLUME * 0.5);
+        }
+    }
+}
+
+pub struct ScreenEffectsPlugin;
+
+impl Plugin for ScreenEffectsPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_system(shake_on_door_slam)
+            .with_system(flash_on_alarm.after(shake_on_door_slam))
+            .with_system(shake_on_hard_landing.after(flash_on_alarm));
+    }
+}
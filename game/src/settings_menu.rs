@@ -0,0 +1,254 @@
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::{
+    DetectChanges, EventReader, IntoSystemConfig, Query, Res, ResMut, Resource, With,
+};
+use input::events::{ElementState, KeyboardInput, VirtualKeyCode};
+use loader::config_loader::{LoadableConfig, PressurePlateIndicator};
+use render::{DynamicResolution, RenderScale};
+
+use crate::captions::Captions;
+use crate::player::{Player, PlayerControllerSettings};
+
+/// The mouse acceleration cycles through these values
+const MOUSE_ACCELERATION_STEPS: [f32; 4] = [0.0, 0.5, 1.0, 2.0];
+
+/// The render scale cycles through these values
+const RENDER_SCALE_STEPS: [f32; 5] = [0.5, 0.75, 1.0, 1.5, 2.0];
+
+/// The settings that can be changed while playing, also shown in the main menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    CameraSmoothing,
+    MouseAcceleration,
+    InvertY,
+    ToggleRewind,
+    PressurePlateIndicator,
+    Captions,
+    RenderScale,
+    DynamicResolution,
+}
+
+impl Setting {
+    pub const ALL: [Setting; 8] = [
+        Setting::CameraSmoothing,
+        Setting::MouseAcceleration,
+        Setting::InvertY,
+        Setting::ToggleRewind,
+        Setting::PressurePlateIndicator,
+        Setting::Captions,
+        Setting::RenderScale,
+        Setting::DynamicResolution,
+    ];
+}
+
+/// Lets players change the mouse settings while playing.
+/// F1 opens the menu, then
+/// - 1 toggles the camera smoothing
+/// - 2 cycles through the mouse acceleration curves
+/// - 3 toggles inverting the Y axis
+/// - 4 switches between holding and toggling the rewinding
+/// - 5 cycles through how the pressure plates show that they are pressed
+/// - 6 toggles the captions for sounds
+/// - 7 cycles through the render scales
+/// - 8 toggles lowering the render scale when the frame rate drops
+///
+/// Closing the menu saves the settings to the config file.
+#[derive(Resource)]
+pub struct SettingsMenu {
+    pub open: bool,
+    config_path: String,
+    config: LoadableConfig,
+}
+
+impl SettingsMenu {
+    pub fn new(config_path: impl Into<String>) -> Self {
+        let config_path = config_path.into();
+        let config = LoadableConfig::load(&config_path);
+        Self {
+            open: false,
+            config_path,
+            config,
+        }
+    }
+
+    fn print(&self) {
+        println!(
+            "Settings: [1] {}, [2] {}, [3] {}, [4] {}, [5] {}, [6] {}, [7] {}, [8] {}",
+            self.label(Setting::CameraSmoothing),
+            self.label(Setting::MouseAcceleration),
+            self.label(Setting::InvertY),
+            self.label(Setting::ToggleRewind),
+            self.label(Setting::PressurePlateIndicator),
+            self.label(Setting::Captions),
+            self.label(Setting::RenderScale),
+            self.label(Setting::DynamicResolution)
+        );
+    }
+
+    /// The name and the current value of the setting
+    pub fn label(&self, setting: Setting) -> String {
+        match setting {
+            Setting::CameraSmoothing => {
+                format!("camera smoothing: {}", self.config.camera_smoothing)
+            }
+            Setting::MouseAcceleration => {
+                format!("mouse acceleration: {}", self.config.mouse_acceleration)
+            }
+            Setting::InvertY => format!("invert Y: {}", self.config.invert_y),
+            Setting::ToggleRewind => format!("toggle rewind: {}", self.config.toggle_rewind),
+            Setting::PressurePlateIndicator => format!(
+                "pressure plates: {}",
+                self.config.pressure_plate_indicator.label()
+            ),
+            Setting::Captions => format!("captions: {}", self.config.captions),
+            Setting::RenderScale => format!(
+                "render scale: {}%",
+                (self.config.render_scale * 100.0).round()
+            ),
+            Setting::DynamicResolution => {
+                format!("dynamic resolution: {}", self.config.dynamic_resolution)
+            }
+        }
+    }
+
+    /// Toggles the setting, or goes to its next value
+    pub fn change(&mut self, setting: Setting) {
+        let config = &mut self.config;
+        match setting {
+            Setting::CameraSmoothing => config.camera_smoothing = !config.camera_smoothing,
+            Setting::MouseAcceleration => {
+                config.mouse_acceleration = next_mouse_acceleration(config.mouse_acceleration)
+            }
+            Setting::InvertY => config.invert_y = !config.invert_y,
+            Setting::ToggleRewind => config.toggle_rewind = !config.toggle_rewind,
+            Setting::PressurePlateIndicator => {
+                config.pressure_plate_indicator = config.pressure_plate_indicator.next()
+            }
+            Setting::Captions => config.captions = !config.captions,
+            Setting::RenderScale => config.render_scale = next_render_scale(config.render_scale),
+            Setting::DynamicResolution => config.dynamic_resolution = !config.dynamic_resolution,
+        }
+    }
+
+    /// Gives the player the current settings
+    pub fn apply(&self, settings: &mut PlayerControllerSettings) {
+        settings.smooth_camera = self.config.camera_smoothing;
+        settings.mouse_acceleration = self.config.mouse_acceleration;
+        settings.invert_y = self.config.invert_y;
+        settings.toggle_rewind = self.config.toggle_rewind;
+    }
+
+    pub fn pressure_plate_indicator(&self) -> PressurePlateIndicator {
+        self.config.pressure_plate_indicator
+    }
+
+    pub fn save(&self) {
+        match self.config.try_save(&self.config_path) {
+            Ok(()) => println!("Saved the settings to {}", self.config_path),
+            Err(err) => println!(
+                "Could not save the settings to {}: {}",
+                self.config_path, err
+            ),
+        }
+    }
+}
+
+fn next_mouse_acceleration(mouse_acceleration: f32) -> f32 {
+    MOUSE_ACCELERATION_STEPS
+        .iter()
+        .copied()
+        .find(|step| *step > mouse_acceleration)
+        .unwrap_or(MOUSE_ACCELERATION_STEPS[0])
+}
+
+fn next_render_scale(render_scale: f32) -> f32 {
+    RENDER_SCALE_STEPS
+        .iter()
+        .copied()
+        .find(|step| *step > render_scale)
+        .unwrap_or(RENDER_SCALE_STEPS[0])
+}
+
+fn settings_menu_input(
+    mut settings_menu: ResMut<SettingsMenu>,
+    mut event_reader: EventReader<KeyboardInput>,
+    mut query: Query<&mut PlayerControllerSettings, With<Player>>,
+) {
+    for event in event_reader.iter() {
+        if event.state != ElementState::Released {
+            continue;
+        }
+
+        if event.key_code == VirtualKeyCode::F1 {
+            settings_menu.open = !settings_menu.open;
+            if settings_menu.open {
+                settings_menu.print();
+            } else {
+                settings_menu.save();
+            }
+            continue;
+        }
+
+        if !settings_menu.open {
+            continue;
+        }
+
+        let setting = match event.key_code {
+            VirtualKeyCode::Key1 => Setting::CameraSmoothing,
+            VirtualKeyCode::Key2 => Setting::MouseAcceleration,
+            VirtualKeyCode::Key3 => Setting::InvertY,
+            VirtualKeyCode::Key4 => Setting::ToggleRewind,
+            VirtualKeyCode::Key5 => Setting::PressurePlateIndicator,
+            VirtualKeyCode::Key6 => Setting::Captions,
+            VirtualKeyCode::Key7 => Setting::RenderScale,
+            VirtualKeyCode::Key8 => Setting::DynamicResolution,
+            _ => continue,
+        };
+
+        settings_menu.change(setting);
+        for mut settings in query.iter_mut() {
+            settings_menu.apply(&mut settings);
+        }
+        settings_menu.print();
+    }
+}
+
+/// Both the F1 menu and the main menu can change the settings
+fn update_setting_resources(
+    settings_menu: Res<SettingsMenu>,
+    mut pressure_plate_indicator: ResMut<PressurePlateIndicator>,
+    mut captions: ResMut<Captions>,
+    mut render_scale: ResMut<RenderScale>,
+    mut dynamic_resolution: ResMut<DynamicResolution>,
+) {
+    if settings_menu.is_changed() {
+        *pressure_plate_indicator = settings_menu.pressure_plate_indicator();
+        captions.enabled = settings_menu.config.captions;
+        render_scale.scale = settings_menu.config.render_scale;
+        dynamic_resolution.enabled = settings_menu.config.dynamic_resolution;
+        dynamic_resolution.max_scale = settings_menu.config.render_scale;
+    }
+}
+
+pub struct SettingsMenuPlugin {
+    config_path: String,
+}
+
+impl SettingsMenuPlugin {
+    pub fn new(config_path: impl Into<String>) -> Self {
+        Self {
+            config_path: config_path.into(),
+        }
+    }
+}
+
+impl Plugin for SettingsMenuPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        let settings_menu = SettingsMenu::new(self.config_path.clone());
+        app //
+            .with_resource(settings_menu.pressure_plate_indicator())
+            .with_resource(settings_menu)
+            .with_system(settings_menu_input)
+            .with_system(update_setting_resources.after(settings_menu_input));
+    }
+}
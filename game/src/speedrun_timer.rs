@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::*;
+use levels::current_level::{CurrentLevel, NextLevel};
+use levels::level_id::LevelId;
+use nalgebra::{Point2, Vector2};
+use scene::ui_component::{UIAnchor, UIComponent, UITexturePosition};
+use scene::ui_text::UIText;
+use time::time::Time;
+
+use crate::game_state::is_playing;
+
+/// Measures how long the levels take, for speedruns.
+/// The time is real time, so rewinding doesn't give any time back.
+/// Only counts while playing, the menus pause it.
+#[derive(Resource)]
+pub struct SpeedrunTimer {
+    pub enabled: bool,
+    total: Duration,
+    level_times: BTreeMap<LevelId, Duration>,
+    /// Where the splits get written to after every finished level
+    splits_path: PathBuf,
+}
+
+impl SpeedrunTimer {
+    pub fn new(enabled: bool, splits_path: impl Into<PathBuf>) -> Self {
+        Self {
+            enabled,
+            total: Duration::ZERO,
+            level_times: BTreeMap::new(),
+            splits_path: splits_path.into(),
+        }
+    }
+
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    pub fn level_time(&self, level_id: LevelId) -> Duration {
+        self.level_times
+            .get(&level_id)
+            .copied()
+            .unwrap_or(Duration::ZERO)
+    }
+
+    fn add(&mut self, level_id: LevelId, delta: Duration) {
+        self.total += delta;
+        *self.level_times.entry(level_id).or_default() += delta;
+    }
+
+    /// One line per level, and the total at the end
+    fn splits(&self) -> String {
+        let mut splits = String::new();
+        for (level_id, level_time) in self.level_times.iter() {
+            writeln!(
+                splits,
+                "Level {}: {}",
+                level_id.id(),
+                format_time(*level_time)
+            )
+            .unwrap();
+        }
+        writeln!(splits, "Total: {}", format_time(self.total)).unwrap();
+        splits
+    }
+
+    fn save_splits(&self) {
+        std::fs::write(&self.splits_path, self.splits())
+            .unwrap_or_else(|_| panic!("Failed to write to {:?}", self.splits_path));
+    }
+}
+
+/// Minutes, seconds and hundredths, like 1:23.45
+fn format_time(time: Duration) -> String {
+    let hundredths = time.as_millis() / 10;
+    format!(
+        "{}:{:02}.{:02}",
+        hundredths / 6000,
+        (hundredths / 100) % 60,
+        hundredths % 100
+    )
+}
+
+/// Minutes and whole seconds, like 1:23. The live timer only changes once per second,
+/// since every change of a text uploads a new texture.
+fn format_whole_seconds(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn update_speedrun_timer(
+    mut speedrun_timer: ResMut<SpeedrunTimer>,
+    time: Res<Time>,
+    current_level: Res<CurrentLevel>,
+) {
+    if !speedrun_timer.enabled {
+        return;
+    }
+    speedrun_timer.add(current_level.level_id, time.delta());
+}
+
+/// The split of a level is done when the next level starts
+fn save_speedrun_splits(
+    speedrun_timer: Res<SpeedrunTimer>,
+    mut next_level_events: EventReader<NextLevel>,
+) {
+    let finished_levels = next_level_events.iter().count();
+    if finished_levels > 0 && speedrun_timer.enabled {
+        speedrun_timer.save_splits();
+    }
+}
+
+#[derive(Component)]
+struct UISpeedrunTimer;
+
+fn spawn_speedrun_timer_ui(mut commands: Commands) {
+    let text = UIText::new("");
+    commands.spawn((
+        UIComponent {
+            texture: text.create_texture(),
+            region: None,
+            texture_position: UITexturePosition {
+                scale: Vector2::new(2.0, 2.0),
+                texture_origin: Point2::new(0.5, 0.0),
+                ..UITexturePosition::default()
+            },
+            anchor: UIAnchor::Top,
+            offset: Vector2::new(0.0, 16.0),
+            depth: 0.0,
+            opacity: 1.0,
+            visible: false,
+        },
+        text,
+        UISpeedrunTimer,
+    ));
+}
+
+fn update_speedrun_timer_ui(
+    speedrun_timer: Res<SpeedrunTimer>,
+    current_level: Res<CurrentLevel>,
+    mut query: Query<(&mut UIText, &mut UIComponent), With<UISpeedrunTimer>>,
+) {
+    let (mut text, mut ui_component) = query.single_mut();
+    ui_component.visible = speedrun_timer.enabled;
+    if !speedrun_timer.enabled {
+        return;
+    }
+
+    let timer_text = format!(
+        "{} - Level {}: {}",
+        format_whole_seconds(speedrun_timer.total()),
+        current_level.level_id.id(),
+        format_whole_seconds(speedrun_timer.level_time(current_level.level_id))
+    );
+    text.set_if_neq(UIText::new(timer_text));
+}
+
+pub struct SpeedrunTimerPlugin {
+    enabled: bool,
+    splits_path: PathBuf,
+}
+
+impl SpeedrunTimerPlugin {
+    pub fn new(enabled: bool, splits_path: impl Into<PathBuf>) -> Self {
+        Self {
+            enabled,
+            splits_path: splits_path.into(),
+        }
+    }
+}
+
+impl Plugin for SpeedrunTimerPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(SpeedrunTimer::new(self.enabled, self.splits_path.clone()))
+            .with_startup_system(spawn_speedrun_timer_ui)
+            .with_system(update_speedrun_timer.run_if(is_playing))
+            .with_system(save_speedrun_splits.after(update_speedrun_timer))
+            .with_system(update_speedrun_timer_ui.after(update_speedrun_timer));
+    }
+}
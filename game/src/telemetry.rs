@@ -0,0 +1,226 @@
+use std::fs::File;
+use std::io::LineWriter;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::*;
+use levels::current_level::{CurrentLevel, NextLevel};
+use loader::telemetry_loader::{DeathCause, TelemetryEvent, TelemetryRecord};
+use scene::transform::Transform;
+use time::time_manager::TimeManager;
+
+use crate::game_over::GameOver;
+use crate::game_state::GameState;
+use crate::level_restart::LevelRestart;
+use crate::player::{Player, FALL_OUT_OF_WORLD_HEIGHT};
+
+/// In seconds, how often the position of the player gets recorded
+const POSITION_SAMPLE_INTERVAL: f32 = 1.0;
+
+/// Records what happens during a playtest to `telemetry/session_<unix time>.jsonl`,
+/// one [`TelemetryRecord`] per line. Only records anything if it was turned on in the config.
+#[derive(Resource)]
+pub struct Telemetry {
+    /// Recording is off if this is None
+    writer: Option<LineWriter<File>>,
+    start_time: Instant,
+    level_start_time: f32,
+    /// Set while rewinding
+    rewind_start_time: Option<f32>,
+    /// The rewinding of a restart doesn't count as a rewind
+    is_restart_rewind: bool,
+    last_sample_time: f32,
+    was_game_over: bool,
+    was_out_of_world: bool,
+}
+
+impl Telemetry {
+    pub fn new(directory: Option<PathBuf>) -> Self {
+        // A playtest shouldn't crash because the telemetry can't be written
+        let writer = directory.and_then(|directory| match Self::create_writer(&directory) {
+            Ok(writer) => Some(writer),
+            Err(err) => {
+                println!("Failed to start the telemetry in {:?}: {}", directory, err);
+                None
+            }
+        });
+
+        Self {
+            writer,
+            start_time: Instant::now(),
+            level_start_time: 0.0,
+            rewind_start_time: None,
+            is_restart_rewind: false,
+            last_sample_time: 0.0,
+            was_game_over: false,
+            was_out_of_world: false,
+        }
+    }
+
+    fn create_writer(directory: &Path) -> std::io::Result<LineWriter<File>> {
+        std::fs::create_dir_all(directory)?;
+        let session = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let path = directory.join(format!("session_{}.jsonl", session));
+        println!("Recording the telemetry to {:?}", path);
+        Ok(LineWriter::new(File::create(path)?))
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    /// In seconds since the game was started
+    fn time(&self) -> f32 {
+        self.start_time.elapsed().as_secs_f32()
+    }
+
+    fn record(&mut self, event: TelemetryEvent) {
+        let record = TelemetryRecord {
+            time: self.time(),
+            event,
+        };
+        if let Some(writer) = &mut self.writer {
+            if let Err(err) = record.write_line(writer) {
+                // Stops recording, instead of reporting the same error for every event
+                println!(
+                    "Failed to write the telemetry, stopping the recording: {}",
+                    err
+                );
+                self.writer = None;
+            }
+        }
+    }
+}
+
+fn record_first_level_start(mut telemetry: ResMut<Telemetry>, current_level: Res<CurrentLevel>) {
+    telemetry.record(TelemetryEvent::LevelStart {
+        level_id: current_level.level_id.id(),
+    });
+}
+
+fn record_level_changes(
+    mut telemetry: ResMut<Telemetry>,
+    mut next_level_events: EventReader<NextLevel>,
+) {
+    for next_level in next_level_events.iter() {
+        let time = telemetry.time();
+        // The level select can also skip levels
+        if next_level.level_id.id() == next_level.old_level_id.id() + 1 {
+            let duration = time - telemetry.level_start_time;
+            telemetry.record(TelemetryEvent::LevelFinish {
+                level_id: next_level.old_level_id.id(),
+                duration,
+            });
+        }
+        telemetry.level_start_time = time;
+        telemetry.record(TelemetryEvent::LevelStart {
+            level_id: next_level.level_id.id(),
+        });
+    }
+}
+
+fn record_rewinds(
+    mut telemetry: ResMut<Telemetry>,
+    time_manager: Res<TimeManager>,
+    level_restart: Res<LevelRestart>,
+    current_level: Res<CurrentLevel>,
+) {
+    let level_id = current_level.level_id.id();
+    match (time_manager.is_rewinding(), telemetry.rewind_start_time) {
+        (true, None) => {
+            // A restart rewinds the whole level in a single frame
+            telemetry.is_restart_rewind = level_restart.is_restarting();
+            if telemetry.is_restart_rewind {
+                telemetry.level_start_time = telemetry.time();
+                telemetry.record(TelemetryEvent::Restart { level_id });
+            }
+            telemetry.rewind_start_time = Some(telemetry.time());
+        }
+        (false, Some(rewind_start_time)) => {
+            telemetry.rewind_start_time = None;
+            if !telemetry.is_restart_rewind {
+                let duration = telemetry.time() - rewind_start_time;
+                telemetry.record(TelemetryEvent::Rewind { level_id, duration });
+            }
+        }
+        _ => {}
+    }
+}
+
+fn record_player(
+    mut telemetry: ResMut<Telemetry>,
+    game_state: Res<GameState>,
+    game_over: Res<GameOver>,
+    time_manager: Res<TimeManager>,
+    current_level: Res<CurrentLevel>,
+    query: Query<&Transform, With<Player>>,
+) {
+    let transform = match query.get_single() {
+        Ok(transform) => transform,
+        Err(_) => return,
+    };
+    let level_id = current_level.level_id.id();
+    let position = transform.position.into();
+
+    if game_over.is_game_over() && !telemetry.was_game_over {
+        telemetry.record(TelemetryEvent::Death {
+            level_id,
+            position,
+            cause: DeathCause::OutOfRewindPower,
+        });
+    }
+    telemetry.was_game_over = game_over.is_game_over();
+
+    // The player gets put back to the spawnpoint during the next update
+    let is_out_of_world = transform.position.y < FALL_OUT_OF_WORLD_HEIGHT;
+    if is_out_of_world && !telemetry.was_out_of_world {
+        telemetry.record(TelemetryEvent::Death {
+            level_id,
+            position,
+            cause: DeathCause::FellOutOfWorld,
+        });
+    }
+    telemetry.was_out_of_world = is_out_of_world;
+
+    // The samples only show where the testers spent their time playing
+    if !game_state.is_playing() || time_manager.is_rewinding() {
+        return;
+    }
+    if telemetry.time() - telemetry.last_sample_time >= POSITION_SAMPLE_INTERVAL {
+        telemetry.last_sample_time = telemetry.time();
+        telemetry.record(TelemetryEvent::Position { level_id, position });
+    }
+}
+
+fn is_recording(telemetry: Res<Telemetry>) -> bool {
+    telemetry.is_recording()
+}
+
+pub struct TelemetryPlugin {
+    directory: Option<PathBuf>,
+}
+
+impl TelemetryPlugin {
+    pub fn new(directory: Option<PathBuf>) -> Self {
+        Self { directory }
+    }
+}
+
+impl Plugin for TelemetryPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(Telemetry::new(self.directory.clone()))
+            .with_startup_system(record_first_level_start)
+            .with_system(record_level_changes.run_if(is_recording))
+            .with_system(
+                record_rewinds
+                    .after(record_level_changes)
+                    .run_if(is_recording),
+            )
+            .with_system(record_player.after(record_rewinds).run_if(is_recording));
+    }
+}
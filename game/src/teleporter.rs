@@ -0,0 +1,184 @@
+use angle::Rad;
+use app::entity_event::EntityEvent;
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::*;
+use levels::level_id::LevelId;
+use loader::name_registry::NameRegistry;
+use nalgebra::{Isometry3, Vector3};
+use physics::physics_context::{PhysicsContext, RapierRigidBodyHandle};
+use physics::physics_events::CollisionEvent;
+use physics::pickup_physics::PickedUp;
+use scene::screen_glitch::ScreenGlitch;
+use scene::teleporter::{TeleportedInto, Teleporter};
+use scene::transform::Transform;
+use time::time_manager::is_rewinding;
+
+use crate::player::Player;
+
+/// Moves something from the teleporter to the same place relative to the exit
+fn teleport_isometry(teleporter: &Transform, exit: &Transform) -> Isometry3<f32> {
+    exit.to_isometry() * teleporter.to_isometry().inverse()
+}
+
+/// The player stays upright, so only the rotation around the up axis gets applied to them
+fn yaw_difference(teleport_isometry: &Isometry3<f32>) -> f32 {
+    let turned = teleport_isometry.rotation * Vector3::x();
+    (-turned.z).atan2(turned.x)
+}
+
+/// Held objects go along with the player, instead of going through on their own
+fn teleport(
+    mut commands: Commands,
+    registry: Res<NameRegistry>,
+    mut physics_context: ResMut<PhysicsContext>,
+    mut screen_glitch: ResMut<ScreenGlitch>,
+    teleporters: Query<
+        (
+            Entity,
+            &Teleporter,
+            &Transform,
+            &LevelId,
+            &EntityEvent<CollisionEvent>,
+        ),
+        Without<RapierRigidBodyHandle>,
+    >,
+    exits: Query<(&Transform, Option<&Teleporter>), Without<RapierRigidBodyHandle>>,
+    mut players: Query<
+        (&mut Transform, &mut Player, Option<&TeleportedInto>),
+        With<RapierRigidBodyHandle>,
+    >,
+    mut bodies: Query<
+        (
+            &mut Transform,
+            &RapierRigidBodyHandle,
+            Option<&TeleportedInto>,
+            Option<&PickedUp>,
+        ),
+        (Without<Player>, Without<Teleporter>),
+    >,
+) {
+    for (teleporter_entity, teleporter, teleporter_transform, level_id, collision_events) in
+        teleporters.iter()
+    {
+        let exit_entity = match registry.get_in_level(*level_id, &teleporter.exit) {
+            Some(exit_entity) => exit_entity,
+            None => continue,
+        };
+        let (exit_transform, exit_teleporter) = match exits.get(exit_entity) {
+            Ok(exit) => exit,
+            Err(_) => continue,
+        };
+        let teleport_isometry = teleport_isometry(teleporter_transform, exit_transform);
+        let teleport_transform = |transform: &mut Transform| {
+            let isometry = teleport_isometry * transform.to_isometry();
+            transform.position = isometry.translation.vector.into();
+            transform.rotation = isometry.rotation;
+        };
+        let velocity_rotation = teleport_isometry.rotation;
+
+        let mut teleported = vec![];
+        for collision_event in collision_events.iter() {
+            let entity = match collision_event {
+                CollisionEvent::Started(entity) => *entity,
+                CollisionEvent::Stopped(_) => continue,
+            };
+
+            if let Ok((mut transform, mut player, teleported_into)) = players.get_mut(entity) {
+                if teleported_into.map(|into| into.0) == Some(teleporter_entity) {
+                    continue;
+                }
+                transform.position = teleport_isometry * transform.position;
+                player.velocity = velocity_rotation * player.velocity;
+                player.yaw = Rad(player.yaw.0 + yaw_difference(&teleport_isometry));
+                screen_glitch.trigger();
+                teleported.push(entity);
+
+                for (mut transform, rigid_body_handle, _, picked_up) in bodies.iter_mut() {
+                    if picked_up.is_none() {
+                        continue;
+                    }
+                    teleport_transform(&mut transform);
+                    if let Some(rigid_body) = physics_context
+                        .rigid_bodies
+                        .get_mut(rigid_body_handle.handle)
+                    {
+                        rigid_body.set_position(transform.to_isometry(), true);
+                    }
+                }
+            } else if let Ok((mut transform, rigid_body_handle, teleported_into, picked_up)) =
+                bodies.get_mut(entity)
+            {
+                if teleported_into.map(|into| into.0) == Some(teleporter_entity)
+                    || picked_up.is_some()
+                {
+                    continue;
+                }
+                let rigid_body = match physics_context
+                    .rigid_bodies
+                    .get_mut(rigid_body_handle.handle)
+                {
+                    Some(rigid_body) if rigid_body.is_dynamic() => rigid_body,
+                    _ => continue,
+                };
+                teleport_transform(&mut transform);
+                rigid_body.set_position(transform.to_isometry(), true);
+                rigid_body.set_linvel(velocity_rotation * rigid_body.linvel(), true);
+                rigid_body.set_angvel(velocity_rotation * rigid_body.angvel(), true);
+                teleported.push(entity);
+            }
+        }
+
+        // Arriving inside of the exit teleporter must not send them back
+        for entity in teleported {
+            if exit_teleporter.is_some() {
+                commands.entity(entity).insert(TeleportedInto(exit_entity));
+            } else {
+                commands.entity(entity).remove::<TeleportedInto>();
+            }
+        }
+    }
+}
+
+fn leave_teleporters(
+    mut commands: Commands,
+    teleporters: Query<(Entity, &EntityEvent<CollisionEvent>), With<Teleporter>>,
+    teleported: Query<&TeleportedInto>,
+) {
+    for (teleporter_entity, collision_events) in teleporters.iter() {
+        for collision_event in collision_events.iter() {
+            if let CollisionEvent::Stopped(entity) = collision_event {
+                if let Ok(teleported_into) = teleported.get(*entity) {
+                    if teleported_into.0 == teleporter_entity {
+                        commands.entity(*entity).remove::<TeleportedInto>();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Rewinding moves everything back in world space, so they could be anywhere afterwards
+fn forget_teleports_when_rewinding(
+    mut commands: Commands,
+    query: Query<Entity, With<TeleportedInto>>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).remove::<TeleportedInto>();
+    }
+}
+
+pub struct TeleporterPlugin;
+
+impl Plugin for TeleporterPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_system(leave_teleporters.run_if(not(is_rewinding)))
+            .with_system(teleport.after(leave_teleporters).run_if(not(is_rewinding)))
+            .with_system(
+                forget_teleports_when_rewinding
+                    .run_if(is_rewinding)
+                    .ambiguous_with(teleport)
+                    .ambiguous_with(leave_teleporters),
+            );
+    }
+}
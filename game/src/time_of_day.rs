@@ -0,0 +1,103 @@
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::{EventReader, Query, Res, ResMut, Resource};
+use bevy_ecs::schedule::IntoSystemConfig;
+use levels::current_level::{CurrentLevel, NextLevel};
+use levels::level_id::LevelId;
+use loader::light_track_loader::LightTrack;
+use nalgebra::Vector3;
+use scene::light::{AmbientLight, Light, TimeOfDayLight};
+use time::time_manager::TimeManager;
+
+/// The light track of the current level.
+/// Only depends on the level time, which means that rewinding also rewinds the lighting.
+#[derive(Resource, Default)]
+pub struct TimeOfDay {
+    light_track: Option<LightTrack>,
+}
+
+fn load_light_track(
+    mut next_level_events: EventReader<NextLevel>,
+    mut time_of_day: ResMut<TimeOfDay>,
+    mut ambient_light: ResMut<AmbientLight>,
+) {
+    for next_level in next_level_events.iter() {
+        time_of_day.light_track = LightTrack::load(format!(
+            "./assets/scene/levels/light_tracks/level{}.json",
+            next_level.level_id.id()
+        ));
+
+        if time_of_day.light_track.is_none() {
+            *ambient_light = AmbientLight::default();
+        }
+    }
+}
+
+fn apply_light_track(
+    time_of_day: Res<TimeOfDay>,
+    time_manager: Res<TimeManager>,
+    current_level: Res<CurrentLevel>,
+    mut ambient_light: ResMut<AmbientLight>,
+    mut lights: Query<(&mut Light, &TimeOfDayLight, &LevelId)>,
+) {
+    let light_track = match &time_of_day.light_track {
+        Some(light_track) => light_track,
+        None => return,
+    };
+
+    let keyframe = light_track.sample(time_manager.level_time().as_secs_f32());
+    let color = color_temperature_to_rgb(keyframe.temperature);
+
+    ambient_light.color = color;
+    ambient_light.intensity = keyframe.ambient_intensity;
+
+    for (mut light, time_of_day_light, level_id) in lights.iter_mut() {
+        if level_id != &current_level.level_id {
+            continue;
+        }
+
+        match light.as_mut() {
+            Light::Point(point_light) => {
+                point_light.color = color;
+                point_light.intensity = time_of_day_light.base_intensity * keyframe.intensity;
+            }
+        }
+    }
+}
+
+/// Approximation of the color of a black body, see https://tannerhelland.com/2012/09/18/convert-temperature-rgb-algorithm-code.html
+fn color_temperature_to_rgb(kelvin: f32) -> Vector3<f32> {
+    let temperature = kelvin.clamp(1000.0, 40000.0) / 100.0;
+
+    let red = if temperature <= 66.0 {
+        255.0
+    } else {
+        329.69873 * (temperature - 60.0).powf(-0.13320476)
+    };
+
+    let green = if temperature <= 66.0 {
+        99.4708 * temperature.ln() - 161.11957
+    } else {
+        288.12216 * (temperature - 60.0).powf(-0.07551485)
+    };
+
+    let blue = if temperature >= 66.0 {
+        255.0
+    } else if temperature <= 19.0 {
+        0.0
+    } else {
+        138.51773 * (temperature - 10.0).ln() - 305.0448
+    };
+
+    Vector3::new(red, green, blue).map(|channel| channel.clamp(0.0, 255.0) / 255.0)
+}
+
+pub struct TimeOfDayPlugin;
+
+impl Plugin for TimeOfDayPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(TimeOfDay::default())
+            .with_system(load_light_track)
+            .with_system(apply_light_track.after(load_light_track));
+    }
+}
@@ -0,0 +1,152 @@
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::*;
+use input::events::{CursorMoved, ElementState, MouseButton, MouseInput};
+use nalgebra::{Point2, Vector2};
+use render::context::Context;
+use scene::ui_component::{UIComponent, UIScaling, UIVisibility};
+use windowing::window::CursorGrabMode;
+
+use crate::game_state::GameState;
+
+/// The cursor is only free while a menu is open, otherwise it's grabbed for looking around
+#[derive(Resource, Debug, Default)]
+pub struct UICursor {
+    pub is_free: bool,
+    /// In window pixels, None until the cursor has moved
+    pub position: Option<Point2<f32>>,
+    /// The component that the left mouse button got pressed on
+    pressed: Option<Entity>,
+    hovered: Option<Entity>,
+}
+
+/// Makes a UI component react to the cursor
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum UIInteraction {
+    #[default]
+    None,
+    Hovered,
+    Pressed,
+}
+
+/// The cursor moved onto the component
+pub struct UIHovered {
+    pub entity: Entity,
+}
+
+/// The left mouse button got pressed and released on the component
+pub struct UIClicked {
+    pub entity: Entity,
+}
+
+fn update_cursor_grab(
+    context: NonSend<Context>,
+    game_state: Res<GameState>,
+    mut ui_cursor: ResMut<UICursor>,
+) {
+    let is_free = game_state.is_in_main_menu();
+    if ui_cursor.is_free == is_free {
+        return;
+    }
+    ui_cursor.is_free = is_free;
+
+    let window = context.window();
+    if is_free {
+        window.set_cursor_grab(CursorGrabMode::None).unwrap();
+        window.set_cursor_visible(true);
+    } else {
+        window
+            .set_cursor_grab(CursorGrabMode::Confined)
+            .or_else(|_e| window.set_cursor_grab(CursorGrabMode::Locked))
+            .unwrap();
+        window.set_cursor_visible(false);
+    }
+}
+
+/// Hit-tests the cursor against the components with a [`UIInteraction`].
+/// Only the topmost component under the cursor counts.
+fn update_ui_interactions(
+    context: NonSend<Context>,
+    ui_scaling: Res<UIScaling>,
+    ui_visibility: Res<UIVisibility>,
+    mut ui_cursor: ResMut<UICursor>,
+    mut cursor_reader: EventReader<CursorMoved>,
+    mut mouse_reader: EventReader<MouseInput>,
+    mut query: Query<(Entity, &UIComponent, &mut UIInteraction)>,
+    mut hovered_writer: EventWriter<UIHovered>,
+    mut clicked_writer: EventWriter<UIClicked>,
+) {
+    if let Some(CursorMoved(x, y)) = cursor_reader.iter().last() {
+        ui_cursor.position = Some(Point2::new(*x as f32, *y as f32));
+    }
+
+    let window_size = context.window().inner_size();
+    let screen_size = Vector2::new(window_size.width as f32, window_size.height as f32);
+    let scale_factor = ui_scaling.scale_factor(screen_size);
+
+    let hovered = match ui_cursor.position {
+        Some(position) if ui_cursor.is_free && !ui_visibility.hidden => query
+            .iter()
+            .filter(|(_, ui_component, _)| {
+                ui_component.visible && ui_component.contains(position, screen_size, scale_factor)
+            })
+            .max_by(|(_, a, _), (_, b, _)| a.depth.total_cmp(&b.depth))
+            .map(|(entity, _, _)| entity),
+        _ => None,
+    };
+    if hovered != ui_cursor.hovered {
+        if let Some(entity) = hovered {
+            hovered_writer.send(UIHovered { entity });
+        }
+        ui_cursor.hovered = hovered;
+    }
+
+    for event in mouse_reader.iter() {
+        if event.button != MouseButton::Left {
+            continue;
+        }
+        match event.state {
+            ElementState::Pressed => ui_cursor.pressed = hovered,
+            ElementState::Released => {
+                if let Some(entity) = ui_cursor.pressed.take() {
+                    if hovered == Some(entity) {
+                        clicked_writer.send(UIClicked { entity });
+                    }
+                }
+            }
+        }
+    }
+
+    for (entity, _, mut interaction) in query.iter_mut() {
+        let new_interaction = if hovered != Some(entity) {
+            UIInteraction::None
+        } else if ui_cursor.pressed == Some(entity) {
+            UIInteraction::Pressed
+        } else {
+            UIInteraction::Hovered
+        };
+        // Only touching the components that changed, for the change detection
+        if *interaction != new_interaction {
+            *interaction = new_interaction;
+        }
+    }
+}
+
+pub struct UIInteractionPlugin;
+
+impl Plugin for UIInteractionPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(UICursor::default())
+            .with_resource(Events::<UIHovered>::default())
+            .with_system(Events::<UIHovered>::update_system)
+            .with_resource(Events::<UIClicked>::default())
+            .with_system(Events::<UIClicked>::update_system)
+            .with_system(update_cursor_grab)
+            .with_system(
+                update_ui_interactions
+                    .after(update_cursor_grab)
+                    .after(Events::<UIHovered>::update_system)
+                    .after(Events::<UIClicked>::update_system),
+            );
+    }
+}
@@ -0,0 +1,20 @@
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::{Query, Res};
+use scene::video_texture::VideoTexture;
+use time::time::Time;
+
+/// The videos keep playing while rewinding, like the screens of the real world would
+fn play_videos(time: Res<Time>, mut query: Query<&mut VideoTexture>) {
+    for mut video_texture in query.iter_mut() {
+        video_texture.advance(time.delta_seconds());
+    }
+}
+
+pub struct VideoTexturePlugin;
+
+impl Plugin for VideoTexturePlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_system(play_videos);
+    }
+}
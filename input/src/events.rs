@@ -1,6 +1,10 @@
 pub use windowing::event::{ElementState, MouseButton, VirtualKeyCode};
 
 pub struct MouseMovement(pub f64, pub f64);
+/// Position of the cursor in the window, in pixels with 0,0 in the top left corner
+pub struct CursorMoved(pub f64, pub f64);
+/// Scrolled lines, positive is scrolling up
+pub struct MouseWheel(pub f32);
 pub struct KeyboardInput {
     pub key_code: VirtualKeyCode,
     pub state: ElementState,
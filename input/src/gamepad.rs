@@ -0,0 +1,138 @@
+use crate::events::ElementState;
+use bevy_ecs::prelude::{EventWriter, NonSendMut, ResMut, Resource};
+use gilrs::{EventType, Gilrs};
+use std::collections::{HashMap, HashSet};
+
+pub use gilrs::{Axis as GamepadAxis, Button as GamepadButton};
+
+/// Sticks and triggers never rest at exactly zero
+const DEFAULT_DEADZONE: f32 = 0.15;
+
+pub struct GamepadButtonInput {
+    pub button: GamepadButton,
+    pub state: ElementState,
+}
+
+pub struct GamepadAxisMotion {
+    pub axis: GamepadAxis,
+    pub value: f32,
+}
+
+/// The combined state of all connected gamepads, so that any of them can be used
+#[derive(Resource)]
+pub struct GamepadState {
+    /// Smaller stick and trigger values are treated as zero
+    pub deadzone: f32,
+    buttons: HashSet<GamepadButton>,
+    /// Analog buttons like the triggers, from 0 to 1
+    button_values: HashMap<GamepadButton, f32>,
+    axes: HashMap<GamepadAxis, f32>,
+}
+
+impl GamepadState {
+    pub fn new() -> Self {
+        Self {
+            deadzone: DEFAULT_DEADZONE,
+            buttons: HashSet::new(),
+            button_values: HashMap::new(),
+            axes: HashMap::new(),
+        }
+    }
+
+    pub fn is_pressed(&self, button: GamepadButton) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    /// How far an analog button like a trigger is pressed, from 0 to 1
+    pub fn button_value(&self, button: GamepadButton) -> f32 {
+        let value = self.button_values.get(&button).copied().unwrap_or(0.0);
+        self.apply_deadzone(value)
+    }
+
+    /// From -1 to 1
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        let value = self.axes.get(&axis).copied().unwrap_or(0.0);
+        if value.abs() < self.deadzone {
+            0.0
+        } else {
+            value.signum() * self.apply_deadzone(value.abs())
+        }
+    }
+
+    /// Uses a radial deadzone, so that diagonal movement isn't snapped to the axes
+    pub fn stick(&self, x_axis: GamepadAxis, y_axis: GamepadAxis) -> (f32, f32) {
+        let x = self.axes.get(&x_axis).copied().unwrap_or(0.0);
+        let y = self.axes.get(&y_axis).copied().unwrap_or(0.0);
+        let length = (x * x + y * y).sqrt();
+        if length < self.deadzone {
+            return (0.0, 0.0);
+        }
+
+        let scale = self.apply_deadzone(length.min(1.0)) / length;
+        (x * scale, y * scale)
+    }
+
+    pub fn left_stick(&self) -> (f32, f32) {
+        self.stick(GamepadAxis::LeftStickX, GamepadAxis::LeftStickY)
+    }
+
+    pub fn right_stick(&self) -> (f32, f32) {
+        self.stick(GamepadAxis::RightStickX, GamepadAxis::RightStickY)
+    }
+
+    /// Rescales the value, so that it starts at zero right outside of the deadzone
+    fn apply_deadzone(&self, value: f32) -> f32 {
+        if value < self.deadzone {
+            0.0
+        } else {
+            (value - self.deadzone) / (1.0 - self.deadzone)
+        }
+    }
+
+    fn clear(&mut self) {
+        self.buttons.clear();
+        self.button_values.clear();
+        self.axes.clear();
+    }
+}
+
+pub(crate) fn handle_gamepad_events(
+    gilrs: Option<NonSendMut<Gilrs>>,
+    mut gamepad_state: ResMut<GamepadState>,
+    mut button_events: EventWriter<GamepadButtonInput>,
+    mut axis_events: EventWriter<GamepadAxisMotion>,
+) {
+    let mut gilrs = match gilrs {
+        Some(gilrs) => gilrs,
+        None => return,
+    };
+
+    while let Some(gilrs::Event { event, .. }) = gilrs.next_event() {
+        match event {
+            EventType::ButtonPressed(button, _) => {
+                gamepad_state.buttons.insert(button);
+                button_events.send(GamepadButtonInput {
+                    button,
+                    state: ElementState::Pressed,
+                });
+            }
+            EventType::ButtonReleased(button, _) => {
+                gamepad_state.buttons.remove(&button);
+                button_events.send(GamepadButtonInput {
+                    button,
+                    state: ElementState::Released,
+                });
+            }
+            EventType::ButtonChanged(button, value, _) => {
+                gamepad_state.button_values.insert(button, value);
+            }
+            EventType::AxisChanged(axis, value, _) => {
+                gamepad_state.axes.insert(axis, value);
+                axis_events.send(GamepadAxisMotion { axis, value });
+            }
+            // Otherwise buttons of an unplugged gamepad would stay pressed
+            EventType::Disconnected => gamepad_state.clear(),
+            _ => {}
+        }
+    }
+}
@@ -1,3 +1,4 @@
 pub mod events;
+pub mod gamepad;
 pub mod input_map;
 pub mod plugin;
@@ -5,7 +5,8 @@ use bevy_ecs::{
 };
 
 use crate::{
-    events::{KeyboardInput, MouseInput, MouseMovement},
+    events::{CursorMoved, KeyboardInput, MouseInput, MouseMovement, MouseWheel},
+    gamepad::{handle_gamepad_events, GamepadAxisMotion, GamepadButtonInput, GamepadState},
     input_map::{handle_keyboard_input, handle_mouse_input, InputMap},
 };
 
@@ -23,6 +24,10 @@ impl Plugin for InputPlugin {
             .with_set(InputPluginSet::InputEvents.before(InputPluginSet::UpdateInputMap))
             .with_resource(Events::<MouseMovement>::default())
             .with_system(Events::<MouseMovement>::update_system.in_set(InputPluginSet::InputEvents))
+            .with_resource(Events::<CursorMoved>::default())
+            .with_system(Events::<CursorMoved>::update_system.in_set(InputPluginSet::InputEvents))
+            .with_resource(Events::<MouseWheel>::default())
+            .with_system(Events::<MouseWheel>::update_system.in_set(InputPluginSet::InputEvents))
             .with_resource(Events::<MouseInput>::default())
             .with_system(Events::<MouseInput>::update_system.in_set(InputPluginSet::InputEvents))
             .with_resource(Events::<KeyboardInput>::default())
@@ -33,5 +38,25 @@ impl Plugin for InputPlugin {
                     .in_set(InputPluginSet::UpdateInputMap)
                     .after(handle_keyboard_input),
             );
+
+        // Gamepads are optional, the game still works when they aren't supported
+        match gilrs::Gilrs::new() {
+            Ok(gilrs) => {
+                app.with_non_send_resource(gilrs);
+            }
+            Err(e) => println!("Gamepads are not supported: {e}"),
+        }
+
+        app //
+            .with_resource(GamepadState::new())
+            .with_resource(Events::<GamepadButtonInput>::default())
+            .with_system(
+                Events::<GamepadButtonInput>::update_system.in_set(InputPluginSet::InputEvents),
+            )
+            .with_resource(Events::<GamepadAxisMotion>::default())
+            .with_system(
+                Events::<GamepadAxisMotion>::update_system.in_set(InputPluginSet::InputEvents),
+            )
+            .with_system(handle_gamepad_events.in_set(InputPluginSet::UpdateInputMap));
     }
 }
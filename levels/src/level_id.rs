@@ -1,6 +1,6 @@
 use bevy_ecs::prelude::Component;
 
-#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct LevelId {
     id: u32,
 }
@@ -0,0 +1,112 @@
+use gltf::Gltf;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// A JSON file with the achievements of the game, like `assets/achievements.json`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LoadableAchievements {
+    pub achievements: Vec<LoadableAchievement>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LoadableAchievement {
+    /// Gets stored in the progress file, so it should never change
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub condition: LoadableAchievementCondition,
+}
+
+/// `"rewind"`, `{ "level_without_rewinding": 1 }` or `{ "interact": "hidden_cat" }`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadableAchievementCondition {
+    /// Rewinding time for the first time
+    Rewind,
+    /// Reaching the level after this one, without having rewound in it
+    LevelWithoutRewinding(u32),
+    /// Interacting with the object that has this name in the level file
+    Interact(String),
+}
+
+impl LoadableAchievements {
+    pub fn load<P>(path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let achievements_file =
+            std::fs::File::open(path).unwrap_or_else(|_| panic!("Failed to open {:?}", path));
+        serde_json::from_reader(achievements_file)
+            .unwrap_or_else(|err| panic!("Failed to read {:?}: {}", path, err))
+    }
+
+    /// Warns about the achievements that need an object that none of the level files has,
+    /// since those could never be unlocked
+    pub fn warn_about_unreachable<P>(&self, levels_directory: P)
+    where
+        P: AsRef<Path>,
+    {
+        let levels_directory = levels_directory.as_ref();
+        let interactable_names = match read_interactable_names(levels_directory) {
+            Ok(names) => names,
+            Err(err) => {
+                println!(
+                    "Failed to check the achievements in {:?}: {}",
+                    levels_directory, err
+                );
+                return;
+            }
+        };
+
+        for achievement in &self.achievements {
+            if let LoadableAchievementCondition::Interact(name) = &achievement.condition {
+                if !interactable_names.contains(name) {
+                    println!(
+                        "Achievement {:?} can never be unlocked, no level has an interactable called {:?}",
+                        achievement.id, name
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The names of the nodes with the `"interactable"` extra, in every glTF file in the directory
+fn read_interactable_names(
+    levels_directory: &Path,
+) -> Result<HashSet<String>, Box<dyn std::error::Error>> {
+    let mut names = HashSet::new();
+    for entry in std::fs::read_dir(levels_directory)? {
+        let path = entry?.path();
+        let is_gltf = matches!(
+            path.extension().and_then(|extension| extension.to_str()),
+            Some("gltf") | Some("glb")
+        );
+        if !is_gltf {
+            continue;
+        }
+
+        // Compressed files don't pass the validation, and the buffers aren't needed for the names
+        let Gltf { document, .. } = Gltf::from_slice_without_validation(&std::fs::read(&path)?)?;
+        for node in document.nodes() {
+            let extras: Option<serde_json::Value> = node
+                .extras()
+                .as_ref()
+                .and_then(|extras| serde_json::from_str(extras.get()).ok());
+            let is_interactable = extras
+                .map(|extras| extras.get("interactable").is_some())
+                .unwrap_or(false);
+            match node.name() {
+                Some(name) if is_interactable => {
+                    names.insert(name.to_string());
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(names)
+}
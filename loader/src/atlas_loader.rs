@@ -0,0 +1,93 @@
+use image::GenericImageView;
+use scene::asset::AssetId;
+use scene::texture::{
+    AddressMode, BytesTextureData, CpuTexture, Filter, MipmapMode, SamplerInfo, TextureFormat,
+};
+use scene::ui_atlas::UIAtlas;
+use scene::ui_component::{NineSlice, UITextureRegion};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A JSON file that names the sprites of an image, like `assets/textures/ui_atlas.json`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LoadableAtlas {
+    /// Relative to the JSON file
+    pub image: PathBuf,
+    pub regions: HashMap<String, LoadableAtlasRegion>,
+}
+
+/// In pixels, with 0,0 in the top left corner of the image
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LoadableAtlasRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// The left, top, right and bottom borders of a scalable sprite
+    #[serde(default)]
+    pub nine_slice: Option<[u32; 4]>,
+}
+
+impl LoadableAtlas {
+    pub fn load<P>(path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let atlas_file =
+            std::fs::File::open(path).unwrap_or_else(|_| panic!("Failed to open {:?}", path));
+        let mut atlas: Self = serde_json::from_reader(atlas_file)
+            .unwrap_or_else(|err| panic!("Failed to read {:?}: {}", path, err));
+        if let Some(directory) = path.parent() {
+            atlas.image = directory.join(&atlas.image);
+        }
+        atlas
+    }
+
+    /// Loads the image, the sprites are pixel art, so they don't get filtered
+    pub fn create_atlas(&self) -> UIAtlas {
+        let image = image::open(&self.image)
+            .unwrap_or_else(|err| panic!("Failed to open {:?}: {}", self.image, err));
+        let dimensions = image.dimensions();
+        let texture = Arc::new(CpuTexture {
+            id: AssetId::new_v4(),
+            data: Box::new(BytesTextureData::new(
+                dimensions,
+                TextureFormat::R8G8B8A8_UNORM,
+                image.to_rgba8().into_raw(),
+            )),
+            sampler_info: SamplerInfo {
+                min_filter: Filter::Nearest,
+                mag_filter: Filter::Nearest,
+                mipmap_mode: MipmapMode::Nearest,
+                address_mode: [AddressMode::ClampToEdge; 3],
+            },
+        });
+
+        let regions = self
+            .regions
+            .iter()
+            .map(|(name, region)| {
+                let texture_region = UITextureRegion {
+                    x: region.x,
+                    y: region.y,
+                    width: region.width,
+                    height: region.height,
+                    nine_slice: region
+                        .nine_slice
+                        .map(|[left, top, right, bottom]| NineSlice {
+                            left,
+                            top,
+                            right,
+                            bottom,
+                        }),
+                };
+                (name.clone(), texture_region)
+            })
+            .collect();
+
+        UIAtlas::new(texture, regions)
+    }
+}
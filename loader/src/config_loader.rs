@@ -1,15 +1,98 @@
+use bevy_ecs::prelude::Resource;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 
 /// A config that can be loaded from a file.
 /// Split into a single separate type, because serde makes compile times annoyingly long.
-#[derive(Debug, Deserialize, Serialize)]
+/// Missing options are filled in with their defaults, so that older config files keep working.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
 pub struct LoadableConfig {
     pub resolution: (u32, u32),
     pub fullscreen: bool,
+    /// a borderless window covering the monitor instead of changing its video mode
+    pub borderless_fullscreen: bool,
+    /// index of the monitor, 0 is usually the primary one
+    pub monitor: usize,
     pub refresh_rate: u32,
     pub brightness: f32,
     pub mouse_sensitivity: f32,
+    /// smoothly rotates the camera towards where the mouse points
+    pub camera_smoothing: bool,
+    /// 0 is linear, larger values make fast mouse movements turn further
+    pub mouse_acceleration: f32,
+    pub invert_y: bool,
+    /// pressing the rewind button starts and stops rewinding, instead of holding it
+    pub toggle_rewind: bool,
+    /// how pressure plates show that they are pressed, in addition to their color
+    pub pressure_plate_indicator: PressurePlateIndicator,
+    /// shows what the important sounds are, like "alarm blaring"
+    pub captions: bool,
+    pub camera_effects: bool,
+    /// shows the time of the current level and of the whole run, and saves the splits
+    pub speedrun_timer: bool,
+    /// saves a replay of every level run to the `replays` directory
+    pub record_replays: bool,
+    /// records the level times, deaths, rewinds and positions to the `telemetry` directory, for playtests
+    pub telemetry: bool,
+    /// vertical field of view in degrees
+    pub fov: f32,
+    /// distance of the camera near plane in meters
+    pub near_plane: f32,
+    /// distance of the camera far plane in meters, levels can increase it
+    pub far_plane: f32,
+    pub bloom_intensity: f32,
+    /// size of the shadow cube maps, one of 512, 1024, 2048 or 4096
+    pub shadow_resolution: u32,
+    /// number of taps for filtering the shadows, between 1 and 16
+    pub shadow_pcf_samples: u32,
+    /// how many frames the CPU can prepare while the GPU is still rendering
+    pub frames_in_flight: u32,
+    /// waits for the GPU after every frame, lowers the input delay but also the frame rate
+    pub low_latency_mode: bool,
+    /// how much GPU memory the game can use in megabytes, 0 uses all of it.
+    /// Textures lose their mip levels once the budget is used up.
+    pub gpu_memory_budget_mb: u32,
+    /// index or part of the name of the GPU, empty picks a discrete GPU if there is one
+    pub gpu: String,
+    /// resolution of the 3D scene relative to the window, between 0.5 and 2.0
+    pub render_scale: f32,
+    /// lowers the render scale when the GPU can't hold the target frame rate
+    pub dynamic_resolution: bool,
+    pub target_frame_rate: f32,
+    /// the same seed gives the same random numbers in every level
+    pub rng_seed: u64,
+}
+
+/// `"color"`, `"pattern"` or `"pulse"`.
+/// Pressed plates always change their color, which isn't enough for colorblind players.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PressurePlateIndicator {
+    /// only the color changes
+    Color,
+    /// pressed plates get a striped texture
+    Pattern,
+    /// pressed plates slowly pulse their glow
+    Pulse,
+}
+
+impl PressurePlateIndicator {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PressurePlateIndicator::Color => "color",
+            PressurePlateIndicator::Pattern => "pattern",
+            PressurePlateIndicator::Pulse => "pulse",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            PressurePlateIndicator::Color => PressurePlateIndicator::Pattern,
+            PressurePlateIndicator::Pattern => PressurePlateIndicator::Pulse,
+            PressurePlateIndicator::Pulse => PressurePlateIndicator::Color,
+        }
+    }
 }
 
 impl LoadableConfig {
@@ -27,16 +110,32 @@ impl LoadableConfig {
                 }
 
                 let config = Self::default();
-                let config_file = std::fs::File::create(path)
-                    .unwrap_or_else(|_| panic!("Failed to create {:?}", path));
-                serde_json::to_writer_pretty(config_file, &config)
-                    .unwrap_or_else(|_| panic!("Failed to write to {:?}", path));
+                config.save(path);
                 config
             }
         };
 
         config
     }
+
+    pub fn save<P>(&self, path: P)
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        self.try_save(path)
+            .unwrap_or_else(|err| panic!("Failed to save {:?}: {}", path, err));
+    }
+
+    /// Like [`LoadableConfig::save`], but for while the game is running, where a failed save shouldn't end the game
+    pub fn try_save<P>(&self, path: P) -> Result<(), Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let config_file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(config_file, self)?;
+        Ok(())
+    }
 }
 
 impl Default for LoadableConfig {
@@ -44,9 +143,35 @@ impl Default for LoadableConfig {
         Self {
             resolution: (1280, 720),
             fullscreen: false,
+            borderless_fullscreen: false,
+            monitor: 0,
             refresh_rate: 60,
             brightness: 1.0,
             mouse_sensitivity: 1.0,
+            camera_smoothing: true,
+            mouse_acceleration: 0.0,
+            invert_y: false,
+            toggle_rewind: false,
+            pressure_plate_indicator: PressurePlateIndicator::Color,
+            captions: false,
+            camera_effects: true,
+            speedrun_timer: false,
+            record_replays: false,
+            telemetry: false,
+            fov: 60.0,
+            near_plane: 0.01,
+            far_plane: 100.0,
+            bloom_intensity: 1.0,
+            shadow_resolution: 2048,
+            shadow_pcf_samples: 8,
+            frames_in_flight: 2,
+            low_latency_mode: false,
+            gpu_memory_budget_mb: 0,
+            gpu: String::new(),
+            render_scale: 1.0,
+            dynamic_resolution: false,
+            target_frame_rate: 60.0,
+            rng_seed: 0,
         }
     }
 }
@@ -0,0 +1,76 @@
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AmbientLightProfile {
+    pub color: [f32; 3],
+    pub intensity: f32,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct FogProfile {
+    pub color: [f32; 3],
+    /// in meters
+    pub start: f32,
+    /// in meters
+    pub end: f32,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct BloomProfile {
+    pub threshold: f32,
+    pub knee: f32,
+    pub intensity: f32,
+}
+
+/// The lighting and post-processing of one level.
+/// Everything is optional, missing parts keep the defaults of the game.
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct EnvironmentProfile {
+    pub ambient_light: Option<AmbientLightProfile>,
+    /// Takes precedence over the fog from the glTF scene
+    pub fog: Option<FogProfile>,
+    pub bloom: Option<BloomProfile>,
+    /// Background color wherever no geometry gets rendered
+    pub sky_color: Option<[f32; 3]>,
+    /// A `.cube` file or PNG strip, for example `assets/luts/alarm.png`
+    pub color_grading_lut: Option<String>,
+}
+
+impl EnvironmentProfile {
+    /// Returns None if the level does not have an environment profile
+    pub fn load<P>(path: P) -> Option<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        if !path.exists() {
+            return None;
+        }
+
+        let file =
+            std::fs::File::open(path).unwrap_or_else(|_| panic!("Failed to open {:?}", path));
+        let profile: EnvironmentProfile = serde_json::from_reader(file)
+            .unwrap_or_else(|err| panic!("Failed to parse {:?}: {}", path, err));
+
+        if let Some(fog) = &profile.fog {
+            assert!(
+                fog.start < fog.end,
+                "Environment profile {:?}: The fog has to start before it ends",
+                path
+            );
+        }
+        if let Some(ambient_light) = &profile.ambient_light {
+            assert!(
+                ambient_light.intensity >= 0.0,
+                "Environment profile {:?}: The ambient intensity cannot be negative",
+                path
+            );
+        }
+        Some(profile)
+    }
+}
@@ -0,0 +1,66 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Notices when a file has been saved, by checking when it was last modified.
+/// Polling a single file is cheap enough, and works the same on every platform.
+pub struct FileWatcher {
+    path: PathBuf,
+    poll_interval: Duration,
+    last_poll: Instant,
+    last_modified: Option<SystemTime>,
+    /// A change only gets reported once the file stops changing, so that half written files are skipped
+    pending_modified: Option<SystemTime>,
+}
+
+impl FileWatcher {
+    pub fn new<P>(path: P, poll_interval: Duration) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        let last_modified = modified_time(&path);
+        Self {
+            path,
+            poll_interval,
+            last_poll: Instant::now(),
+            last_modified,
+            pending_modified: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns true once after every change of the file
+    pub fn poll(&mut self) -> bool {
+        if self.last_poll.elapsed() < self.poll_interval {
+            return false;
+        }
+        self.last_poll = Instant::now();
+
+        let modified = modified_time(&self.path);
+        if modified.is_none() || modified == self.last_modified {
+            self.pending_modified = None;
+            return false;
+        }
+
+        if modified != self.pending_modified {
+            // Wait for one more poll, in case the file is still being written
+            self.pending_modified = modified;
+            return false;
+        }
+
+        self.last_modified = modified;
+        self.pending_modified = None;
+        true
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
@@ -1,2 +1,15 @@
+pub mod achievements_loader;
+pub mod atlas_loader;
 pub mod config_loader;
+pub mod environment_loader;
+pub mod file_watcher;
+pub mod light_track_loader;
 pub mod loader;
+pub mod lut_loader;
+pub mod meshopt_compression;
+pub mod name_registry;
+pub mod prefab;
+pub mod progress_loader;
+pub mod replay_loader;
+pub mod telemetry_loader;
+pub mod video_loader;
@@ -0,0 +1,75 @@
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LightKeyframe {
+    /// Level time in seconds
+    pub time: f32,
+    /// Color temperature in Kelvin
+    pub temperature: f32,
+    /// Multiplier for the intensity of the tagged lights
+    pub intensity: f32,
+    pub ambient_intensity: f32,
+}
+
+/// A global light animation for one level, keyed by level time.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LightTrack {
+    pub keyframes: Vec<LightKeyframe>,
+}
+
+impl LightTrack {
+    /// Returns None if the level does not have a light track
+    pub fn load<P>(path: P) -> Option<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        if !path.exists() {
+            return None;
+        }
+
+        let file =
+            std::fs::File::open(path).unwrap_or_else(|_| panic!("Failed to open {:?}", path));
+        let mut light_track: LightTrack = serde_json::from_reader(file)
+            .unwrap_or_else(|err| panic!("Failed to parse {:?}: {}", path, err));
+        assert!(
+            !light_track.keyframes.is_empty(),
+            "Light track {:?} has no keyframes",
+            path
+        );
+
+        light_track
+            .keyframes
+            .sort_by(|a, b| a.time.total_cmp(&b.time));
+        Some(light_track)
+    }
+
+    /// Interpolates between the keyframes, the first and last keyframes are held
+    pub fn sample(&self, time: f32) -> LightKeyframe {
+        let next_index = self
+            .keyframes
+            .iter()
+            .position(|keyframe| keyframe.time > time);
+
+        match next_index {
+            Some(0) => self.keyframes[0].clone(),
+            None => self.keyframes.last().unwrap().clone(),
+            Some(index) => {
+                let a = &self.keyframes[index - 1];
+                let b = &self.keyframes[index];
+                let t = (time - a.time) / (b.time - a.time);
+                let lerp = |a: f32, b: f32| a + (b - a) * t;
+
+                LightKeyframe {
+                    time,
+                    temperature: lerp(a.temperature, b.temperature),
+                    intensity: lerp(a.intensity, b.intensity),
+                    ambient_intensity: lerp(a.ambient_intensity, b.ambient_intensity),
+                }
+            }
+        }
+    }
+}
@@ -1,135 +1,549 @@
-use animations::animation::{Animation, PlayingAnimation};
+use animations::animation::{Animation, AnimationMarker, AnimationRepeat, PlayingAnimation};
+use animations::animation_events::{AnimationFinished, AnimationMarkerReached};
 use bevy_ecs::prelude::*;
 use gltf::khr_lights_punctual::Kind;
 use gltf::texture::{MagFilter, MinFilter, WrappingMode};
-use gltf::{import, khr_lights_punctual, Node, Semantic};
+use gltf::{khr_lights_punctual, Node, Semantic};
 use math::bounding_box::BoundingBox;
-use nalgebra::{Point3, Quaternion, UnitQuaternion, Vector3};
-use physics::physics_context::{BoxCollider, RigidBody};
+use nalgebra::{Point3, Quaternion, UnitQuaternion, UnitVector3, Vector2, Vector3};
+use physics::collision_layers::{CollisionLayer, CollisionLayers};
+use physics::conveyor_belt::ConveyorBelt;
+use physics::fluid_volume::FluidVolume;
+use physics::gravity_zone::{GravityBootZone, GravityZone};
+use physics::physics_context::{
+    BoxCollider, CapsuleCollider, CompoundCollider, Mass, MeshCollider, MeshColliderKind,
+    RigidBody, SphereCollider,
+};
 use scene::asset::AssetId;
+use scene::billboard::{Billboard, BillboardBlend};
+use scene::cutscene::{CutscenePoint, CutscenePointKind};
 use scene::debug_name::DebugName;
-use scene::light::{CastsShadow, Light, LightCastShadow, PointLight};
-use scene::material::CpuMaterial;
+use scene::decal::Decal;
+use scene::fog::{Fog, LevelFog};
+use scene::hierarchy::HierarchyCommands;
+use scene::light::{CastsShadow, Light, LightCastShadow, PointLight, TimeOfDayLight};
+use scene::material::{CpuMaterial, Flipbook, MaterialOverride, TextureScroll, Wind};
 use scene::mesh::{CpuMesh, CpuMeshVertex};
 use scene::model::{CpuPrimitive, Model};
-use scene::pickup::Pickupable;
+use scene::pickup::{Broken, Pickupable};
+use scene::spatial_grid::SpatialBounds;
+use scene::surface::SurfaceType;
+use scene::teleporter::Teleporter;
 use scene::texture::{
     AddressMode, BytesTextureData, CpuTexture, Filter, MipmapMode, SamplerInfo, TextureFormat,
 };
 use scene::transform::Transform;
 use std::hash::Hash;
 use std::iter::repeat;
-use std::ops::Add;
 use std::sync::Arc;
 use std::time::Duration;
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 use time::time_manager::TimeTracked;
 
+use crate::meshopt_compression;
+use crate::prefab::Prefab;
+use crate::video_loader;
+
 use app::entity_event::EntityEvent;
 use levels::level_id::LevelId;
 use physics::physics_context::RigidBodyType::{Dynamic, KinematicPositionBased};
 use physics::physics_events::CollisionEvent;
 use scene::flag_trigger::FlagTrigger;
-use scene::level::{NextLevelTrigger, Spawnpoint};
+use scene::interactable::{Interactable, Interacted};
+use scene::level::{FlagId, NextLevelTrigger, Spawnpoint};
 use serde::Deserialize;
 
 // scene.json -> assets
 
 // list of assets in code
 
-#[derive(Component)]
-pub struct Door {}
+/// A door that moves with its animation, the game decides when it opens and closes
+#[derive(Component, Debug, Default)]
+pub struct Door {
+    /// The door opens while all of these flags are set, by playing the animation forwards
+    pub opens_when: Vec<(LevelId, FlagId)>,
+    /// The door starts open, and closes while all of these flags are set,
+    /// by playing the animation forwards
+    pub closes_when: Vec<(LevelId, FlagId)>,
+    /// Locked doors stay closed regardless of the flags
+    pub locked: bool,
+    /// The alarm of the level locks the door while it is going off
+    pub locked_by_alarm: bool,
+}
 
 #[derive(Component)]
 pub struct Platform;
 
+/// A platform that moves with its animation and carries the player, the call buttons decide where it goes.
+/// Elevators with a repeating animation move on their own.
+#[derive(Component, Debug)]
+pub struct Elevator;
+
+/// Calls an elevator of the same level when interacted with
+#[derive(Component, Debug)]
+pub struct ElevatorButton {
+    /// The name of the elevator node
+    pub elevator: String,
+    /// Sends the elevator to this stop, or to the other stop when there is none
+    pub stop: Option<ElevatorStop>,
+}
+
+/// Pulls the pickupable and metal rigid bodies inside of a cone towards itself, while it is powered
+#[derive(Component, Debug)]
+pub struct Magnet {
+    /// The magnet is powered while all of these flags are set, and all the time without any flags
+    pub powered_when: Vec<(LevelId, FlagId)>,
+    /// The axis of the cone, pointing away from the magnet, in world space
+    pub direction: Vector3<f32>,
+    /// In meters
+    pub range: f32,
+    /// The angle between the axis and the side of the cone, in radians
+    pub cone_angle: f32,
+    /// In meters per second squared, right at the magnet. Gets weaker further away.
+    pub strength: f32,
+}
+
+/// `"start"` is where the elevator is in Blender, `"end"` is where its animation ends
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ElevatorStop {
+    Start,
+    End,
+}
+
 #[derive(Component)]
 pub struct PressurePlate {
     pub active_material: Arc<CpuMaterial>,
     pub inactive_material: Arc<CpuMaterial>,
 }
 
+/// `"collider": "box"`, `"sphere"`, `"capsule"`, `"convex_hull"` or `"mesh"`
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ColliderKind {
+    Box,
+    Sphere,
+    Capsule,
+    ConvexHull,
+    Mesh,
+}
+
+/// `"rigid_body": "kinematic"` or `"dynamic"`
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RigidBodyKind {
+    Kinematic,
+    Dynamic,
+}
+
+/// `"surface": "metal"`, `"plastic"` or `"glass"`
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SurfaceKind {
+    Metal,
+    Plastic,
+    Glass,
+}
+
+/// `"player"`, `"pickup"`, `"trigger"`, `"static"` or `"debris"`
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionLayerKind {
+    Player,
+    Pickup,
+    Trigger,
+    Static,
+    Debris,
+}
+
+impl From<CollisionLayerKind> for CollisionLayer {
+    fn from(kind: CollisionLayerKind) -> Self {
+        match kind {
+            CollisionLayerKind::Player => CollisionLayer::Player,
+            CollisionLayerKind::Pickup => CollisionLayer::Pickup,
+            CollisionLayerKind::Trigger => CollisionLayer::Trigger,
+            CollisionLayerKind::Static => CollisionLayer::Static,
+            CollisionLayerKind::Debris => CollisionLayer::Debris,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct CollisionLayersProperty {
+    /// without it, the node is on the default layer for what it is
+    pub layers: Option<Vec<CollisionLayerKind>>,
+    /// without it, the node collides with every layer
+    pub collides_with: Option<Vec<CollisionLayerKind>>,
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
 struct AnimationProperty {
+    /// in world space
+    #[serde(default)]
     pub translation: [f32; 3],
+    /// in degrees around the x, y and z axes of the node, like `[0.0, 0.0, 360.0]` for a fan
+    pub rotation: Option<[f32; 3]>,
+    /// multiplies the scale of the node at the end
+    pub scale: Option<[f32; 3]>,
+    /// in seconds, for a single play or for one repetition
     pub duration: f32,
+    pub repeat: Option<AnimationRepeatKind>,
+    /// named points along the animation, from 0 at the start to 1 at the end
+    pub markers: Option<HashMap<String, f32>>,
+}
+
+/// `"repeat": "loop"` or `"ping_pong"`, without it the game decides when the animation plays
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AnimationRepeatKind {
+    Loop,
+    PingPong,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct ElevatorButtonProperty {
+    /// the name of the elevator node in the same level
+    pub elevator: String,
+    pub stop: Option<ElevatorStop>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct MagnetProperty {
+    /// in world space, the magnet pulls things in from this direction
+    pub direction: [f32; 3],
+    /// in meters
+    pub range: f32,
+    /// in degrees, between the direction and the side of the cone, 30 by default
+    pub angle: Option<f32>,
+    /// in meters per second squared, 20 by default
+    pub strength: Option<f32>,
+    /// list of [level, flag] pairs
+    pub powered_when: Option<Vec<[u32; 2]>>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct FluidProperty {
+    /// 1 makes submerged bodies float in place, larger values push them up
+    pub buoyancy: f32,
+    /// how much of their velocity the bodies inside lose per second
+    pub drag: f32,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct ConveyorBeltProperty {
+    /// in world space
+    pub direction: [f32; 3],
+    /// in meters per second
+    pub speed: f32,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct CutscenePointProperty {
+    /// the name of the cutscene
+    pub cutscene: String,
+    /// in seconds since the start of the cutscene
+    pub time: f32,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct BillboardProperty {
+    /// multiplies the texture
+    pub color: Option<[f32; 3]>,
+    /// only turns around the up axis, like a tree
+    pub axis_locked: Option<bool>,
+    /// for glows, instead of cutting out the transparent pixels
+    pub additive: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct VideoProperty {
+    /// a directory with one PNG file per frame, relative to the glTF file
+    pub directory: PathBuf,
+    /// in frames per second
+    pub fps: f32,
+    /// filled in after reading the extras
+    #[serde(skip)]
+    pub frames: Vec<PathBuf>,
 }
 
 #[derive(Deserialize, Debug, Default)]
 #[serde(deny_unknown_fields)]
 struct GLTFModelExtras {
+    /// the name of a prefab, see [`Prefab`]
+    pub prefab: Option<String>,
     pub flag_trigger: Option<u32>,
+    /// the flag trigger only activates once the bodies inside weigh at least this much
+    pub min_mass: Option<f32>,
     pub level_trigger: Option<bool>,
+    /// the gravity scale inside of the zone, negative values invert gravity
+    pub gravity_zone: Option<f32>,
+    /// in world space, the direction that the player stands in while inside of the zone
+    pub gravity_boots: Option<[f32; 3]>,
+    /// the name of the exit, another teleporter or any other model in the same level
+    pub teleporter: Option<String>,
+    /// water or a force field, the model stays visible as its surface
+    pub fluid: Option<FluidProperty>,
     pub box_collider: Option<bool>,
-    pub rigid_body: Option<String>,
+    pub collider: Option<ColliderKind>,
+    pub compound_collider: Option<bool>,
+    /// which colliders this one collides with, see [`CollisionLayers`]
+    pub collision_layers: Option<CollisionLayersProperty>,
+    pub rigid_body: Option<RigidBodyKind>,
+    /// in kilograms, replaces the mass that the rigid body gets from the size of its collider
+    pub mass: Option<f32>,
     pub animation: Option<AnimationProperty>,
     pub door: Option<bool>,
+    /// list of [level, flag] pairs
+    pub opens_when: Option<Vec<[u32; 2]>>,
+    /// list of [level, flag] pairs
+    pub closes_when: Option<Vec<[u32; 2]>>,
+    pub locked: Option<bool>,
+    pub locked_by_alarm: Option<bool>,
     pub platform: Option<bool>,
+    /// a platform that moves with its animation, needs an animation
+    pub elevator: Option<bool>,
+    pub elevator_button: Option<ElevatorButtonProperty>,
+    pub magnet: Option<MagnetProperty>,
+    pub conveyor_belt: Option<ConveyorBeltProperty>,
     pub pickupable: Option<bool>,
+    /// in meters, how far in front of the camera the pickupable is held
+    pub hold_distance: Option<f32>,
+    /// whether the player can turn the pickupable while holding it, true by default
+    pub rotatable: Option<bool>,
+    /// in meters per second, the pickupable breaks when it hits something harder than this
+    pub break_speed: Option<f32>,
     pub casts_shadow: Option<bool>,
+    /// what the footsteps on the collider sound like
+    pub surface: Option<SurfaceKind>,
     pub pressure_plate: Option<bool>,
+    /// the prompt that gets shown when looking at the object
+    pub interactable: Option<String>,
+    /// how deep the decal gets projected, in meters.
+    /// The mesh is a plane, and its base color texture gets projected onto the geometry below it
+    pub decal: Option<f32>,
+    /// The mesh is a plane, and its base color texture gets drawn on a quad that faces the camera
+    pub billboard: Option<BillboardProperty>,
+    /// plays the frames on the base color texture of the model, for screens
+    pub video: Option<VideoProperty>,
+    /// an empty that the camera moves through during a cutscene
+    pub cutscene_camera: Option<CutscenePointProperty>,
+    /// an empty that the camera looks at during a cutscene
+    pub cutscene_look_at: Option<CutscenePointProperty>,
 }
 
 #[derive(Deserialize, Debug, Default)]
 #[serde(deny_unknown_fields)]
 struct GLTFLightExtras {
     pub shadow_caster: Option<bool>,
+    pub time_of_day: Option<bool>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct GLTFMaterialExtras {
+    /// between 0 and 1, for the screen space reflections
+    pub reflectivity: Option<f32>,
+    pub wind: Option<WindProperty>,
+    pub flipbook: Option<FlipbookProperty>,
+    /// between 0 and 1, translucent materials get dithered
+    pub opacity: Option<f32>,
+    /// the occlusion texture is baked lighting instead of ambient occlusion
+    pub lightmap: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct WindProperty {
+    /// in meters, per meter above the origin of the object
+    pub amplitude: f32,
+    /// in radians per second
+    pub frequency: f32,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct FlipbookProperty {
+    pub frames: u32,
+    /// the frames are in a grid with this many columns, all in one row by default
+    pub columns: Option<u32>,
+    /// in frames per second
+    pub fps: f32,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct FogProperty {
+    pub color: [f32; 3],
+    /// in meters
+    pub start: f32,
+    /// in meters
+    pub end: f32,
 }
 
 #[derive(Deserialize, Debug, Default)]
 struct GLFTSceneExtras {
     pub level_id: u32,
+    pub fog: Option<FogProperty>,
 }
 
-#[derive(Resource)]
-pub struct SceneLoader {}
+#[derive(Debug, Clone, Copy)]
+enum ScenesToLoad {
+    All,
+    /// Only the scene with this level id
+    Level(LevelId),
+    /// All scenes, and they all belong to this level
+    AsLevel(LevelId),
+}
+
+#[derive(Resource, Clone)]
+pub struct SceneLoader {
+    prefabs: HashMap<String, Prefab>,
+}
 
 impl SceneLoader {
     /// loads one .gltf file
+    /// The flag counts of the levels of the game are used for checking the extras,
+    /// and every one of those levels needs a spawnpoint
     pub fn load_default_scene<P>(
         &self,
         path: P,
         commands: &mut Commands,
+        flag_counts: &HashMap<LevelId, usize>,
     ) -> Result<(), Box<dyn std::error::Error>>
     where
         P: AsRef<Path>,
     {
-        // TODO: open issue on gltf repository (working with buffers and images is unintuitive and not very good documented)
-        let (doc, buffers, images) = import(path)?;
+        self.load_scenes(path, commands, flag_counts, ScenesToLoad::All)
+    }
 
-        let mut scene_loading_data = SceneLoadingData::new(buffers, images);
+    /// Only loads the scene of one level from the .gltf file, nothing gets spawned if it has errors.
+    /// The old entities of the level have to be despawned by the caller.
+    pub fn reload_level<P>(
+        &self,
+        path: P,
+        commands: &mut Commands,
+        flag_counts: &HashMap<LevelId, usize>,
+        level_id: LevelId,
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let loaded_scenes = self.read_scenes(path, flag_counts, ScenesToLoad::Level(level_id))?;
+        if loaded_scenes.is_empty() {
+            return Err(format!("The file has no scene for level {}", level_id.id()).into());
+        }
+        self.spawn(loaded_scenes, commands);
+        Ok(())
+    }
 
-        let inactive_pressure_plate_material = Arc::new(CpuMaterial {
-            base_color: [0.0, 0.5, 0.8].into(),
-            ..CpuMaterial::default()
-        });
+    /// Reads the scene of one level from the .gltf file without spawning anything,
+    /// so that it can happen on another thread. The result is empty if the file has no scene for the level.
+    pub fn read_level<P>(
+        &self,
+        path: P,
+        flag_counts: &HashMap<LevelId, usize>,
+        level_id: LevelId,
+    ) -> Result<LoadedScenes, Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        self.read_scenes(path, flag_counts, ScenesToLoad::Level(level_id))
+    }
 
-        let active_pressure_plate_material = Arc::new(CpuMaterial {
-            base_color: inactive_pressure_plate_material.base_color.into(),
-            emissivity: inactive_pressure_plate_material
-                .base_color
-                .scale(2.0)
-                .into(),
-            ..CpuMaterial::default()
-        });
+    /// Loads a .gltf file that only contains a single level, so that each level can live in its own file.
+    /// Every scene in the file becomes a part of the level, regardless of the level id in its extras.
+    pub fn load_additive<P>(
+        &self,
+        path: P,
+        level_id: LevelId,
+        commands: &mut Commands,
+        flag_counts: &HashMap<LevelId, usize>,
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        self.load_scenes(path, commands, flag_counts, ScenesToLoad::AsLevel(level_id))
+    }
+
+    /// Like [`SceneLoader::load_additive`], but only reads the file, see [`SceneLoader::read_level`]
+    pub fn read_additive<P>(
+        &self,
+        path: P,
+        level_id: LevelId,
+        flag_counts: &HashMap<LevelId, usize>,
+    ) -> Result<LoadedScenes, Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        self.read_scenes(path, flag_counts, ScenesToLoad::AsLevel(level_id))
+    }
 
+    fn load_scenes<P>(
+        &self,
+        path: P,
+        commands: &mut Commands,
+        flag_counts: &HashMap<LevelId, usize>,
+        scenes_to_load: ScenesToLoad,
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        let loaded_scenes = self.read_scenes(path, flag_counts, scenes_to_load)?;
+        self.spawn(loaded_scenes, commands);
+        Ok(())
+    }
+
+    fn read_scenes<P>(
+        &self,
+        path: P,
+        flag_counts: &HashMap<LevelId, usize>,
+        scenes_to_load: ScenesToLoad,
+    ) -> Result<LoadedScenes, Box<dyn std::error::Error>>
+    where
+        P: AsRef<Path>,
+    {
+        // TODO: open issue on gltf repository (working with buffers and images is unintuitive and not very good documented)
+        let (doc, buffers, images) = meshopt_compression::import(path.as_ref())?;
+
+        let mut scene_loading_data = SceneLoadingData::new(buffers, images);
+
+        let mut scenes = vec![];
         for scene in doc.scenes() {
+            let mut scene_loading_result = SceneLoadingResult::new();
+
             let scene_extras = scene
                 .extras()
                 .as_ref()
                 .map(|extra| {
-                    let str = extra.get();
-
-                    let result: GLFTSceneExtras = serde_json::from_str(str).expect(str);
-
-                    result
+                    parse_extras(extra.get()).unwrap_or_else(|error| {
+                        scene_loading_result.errors.push(format!(
+                            "Scene {:?}: {}",
+                            scene.name().unwrap_or_default(),
+                            error
+                        ));
+                        GLFTSceneExtras::default()
+                    })
                 })
                 .unwrap_or_default();
 
-            let level_id = LevelId::new(scene_extras.level_id);
-
-            let mut scene_loading_result = SceneLoadingResult::new();
+            let level_id = match scenes_to_load {
+                ScenesToLoad::All => LevelId::new(scene_extras.level_id),
+                ScenesToLoad::Level(level_id) if level_id.id() == scene_extras.level_id => level_id,
+                ScenesToLoad::Level(_) => continue,
+                ScenesToLoad::AsLevel(level_id) => level_id,
+            };
 
             for node in scene.nodes() {
                 Self::read_node(
@@ -137,27 +551,124 @@ impl SceneLoader {
                     &mut scene_loading_data,
                     &mut scene_loading_result,
                     &Transform::default(),
+                    None,
                 );
             }
 
+            Self::read_videos(path.as_ref(), &mut scene_loading_result);
+
+            if let Some(fog) = scene_extras.fog {
+                if fog.start >= fog.end {
+                    scene_loading_result.errors.push(format!(
+                        "Scene {:?}: The fog has to start before it ends",
+                        scene.name().unwrap_or_default()
+                    ));
+                }
+                scene_loading_result.fog = Some(Fog {
+                    color: fog.color.into(),
+                    start: fog.start,
+                    end: fog.end,
+                });
+            }
+
+            self.validate_scene(level_id, &mut scene_loading_result, flag_counts);
+            scenes.push((level_id, scene_loading_result));
+        }
+
+        // Report every mistake in the levels at once, instead of only the first one
+        let errors: Vec<String> = scenes
+            .iter_mut()
+            .flat_map(|(_, scene_loading_result)| scene_loading_result.errors.drain(..))
+            .collect();
+        if !errors.is_empty() {
+            return Err(Box::new(SceneValidationError { errors }));
+        }
+
+        Ok(LoadedScenes { scenes })
+    }
+
+    /// Spawns the scenes that have been read before, they have already been checked for mistakes
+    pub fn spawn(&self, loaded_scenes: LoadedScenes, commands: &mut Commands) {
+        let inactive_pressure_plate_material = Arc::new(CpuMaterial {
+            base_color: [0.0, 0.5, 0.8].into(),
+            ..CpuMaterial::default()
+        });
+
+        let active_pressure_plate_material = Arc::new(CpuMaterial {
+            base_color: inactive_pressure_plate_material.base_color.into(),
+            emissivity: inactive_pressure_plate_material
+                .base_color
+                .scale(2.0)
+                .into(),
+            ..CpuMaterial::default()
+        });
+
+        for (level_id, scene_loading_result) in loaded_scenes.scenes {
+            // By the names of their nodes, to rebuild the hierarchy of the levels file
+            let mut entities = HashMap::new();
+            let mut static_entities = vec![];
+
+            if let Some(fog) = scene_loading_result.fog {
+                commands.spawn((LevelFog { fog }, level_id.clone()));
+            }
+
             for (transform, light, extras, name) in scene_loading_result.lights {
+                let base_intensity = match &light {
+                    Light::Point(point_light) => point_light.intensity,
+                };
+                let node_name = name.0.clone();
                 let mut light_entity = commands.spawn((name, light, transform, level_id.clone()));
+                entities.insert(node_name.clone(), light_entity.id());
+                static_entities.push((node_name, light_entity.id()));
 
                 if let Some(true) = extras.shadow_caster {
                     light_entity.insert(LightCastShadow);
                 }
+
+                if let Some(true) = extras.time_of_day {
+                    light_entity.insert(TimeOfDayLight { base_intensity });
+                }
             }
 
             for (transform, name) in scene_loading_result.cameras {
                 commands.spawn((name, Spawnpoint, transform, level_id.clone()));
             }
 
-            for (transform, model, extras, name) in scene_loading_result.models {
+            for (transform, cutscene_point, name) in scene_loading_result.cutscene_points {
+                commands.spawn((name, cutscene_point, transform, level_id.clone()));
+            }
+
+            for (transform, decal, name) in scene_loading_result.decals {
+                commands.spawn((name, decal, transform, level_id.clone()));
+            }
+
+            for (transform, billboard, name) in scene_loading_result.billboards {
+                commands.spawn((name, billboard, transform, level_id.clone()));
+            }
+
+            for (transform, model, mut extras, name) in scene_loading_result.models {
+                let model = match extras.prefab.take() {
+                    Some(prefab_name) => {
+                        let prefab = self
+                            .prefabs
+                            .get(&prefab_name)
+                            .expect("Prefabs have been validated");
+                        Self::apply_prefab(prefab, &mut extras);
+                        prefab.create_model(model)
+                    }
+                    None => model,
+                };
+
                 let box_collider = BoxCollider {
                     bounds: model.bounding_box(),
                 };
 
+                let node_name = name.0.clone();
                 let mut entity = commands.spawn((name, transform.clone(), level_id.clone()));
+                entities.insert(node_name.clone(), entity.id());
+                if extras.rigid_body.is_none() && extras.animation.is_none() {
+                    static_entities.push((node_name, entity.id()));
+                }
 
                 if let Some(true) = extras.casts_shadow {
                     entity.insert(CastsShadow);
@@ -166,11 +677,8 @@ impl SceneLoader {
                 let mut has_model = true;
                 if let Some(flag) = extras.flag_trigger {
                     entity.insert((
-                        FlagTrigger {
-                            level_id: level_id.clone(),
-                            flag_id: flag as usize,
-                            current_intersections: 0,
-                        },
+                        FlagTrigger::new(level_id.clone(), flag as usize)
+                            .with_min_mass(extras.min_mass),
                         box_collider.clone(),
                         EntityEvent::<CollisionEvent>::default(),
                     ));
@@ -182,6 +690,45 @@ impl SceneLoader {
                         EntityEvent::<CollisionEvent>::default(),
                     ));
                     has_model = false;
+                } else if let Some(gravity_scale) = extras.gravity_zone {
+                    entity.insert((
+                        GravityZone { gravity_scale },
+                        box_collider.clone(),
+                        EntityEvent::<CollisionEvent>::default(),
+                    ));
+                    has_model = false;
+                } else if let Some(up) = extras.gravity_boots {
+                    entity.insert((
+                        GravityBootZone {
+                            up: UnitVector3::new_normalize(Vector3::from(up)),
+                        },
+                        box_collider.clone(),
+                    ));
+                    has_model = false;
+                } else if let Some(exit) = &extras.teleporter {
+                    entity.insert((
+                        Teleporter { exit: exit.clone() },
+                        box_collider.clone(),
+                        EntityEvent::<CollisionEvent>::default(),
+                    ));
+                    has_model = false;
+                } else if let Some(fluid) = &extras.fluid {
+                    // The texture of the surface flows, use a translucent material for water
+                    entity.insert((
+                        FluidVolume {
+                            buoyancy: fluid.buoyancy,
+                            drag: fluid.drag,
+                        },
+                        box_collider.clone(),
+                        TextureScroll::default(),
+                    ));
+                }
+
+                // Triggers don't have a model, but they can still be found with the spatial queries
+                if !has_model {
+                    entity.insert(SpatialBounds {
+                        bounds: box_collider.bounds.clone(),
+                    });
                 }
 
                 // add box collider component
@@ -189,54 +736,218 @@ impl SceneLoader {
                     entity.insert(box_collider);
                 }
 
-                if let Some(str) = extras.rigid_body {
-                    if str == "kinematic" {
-                        entity.insert((RigidBody(KinematicPositionBased), TimeTracked::new()));
-                    } else if str == "dynamic" {
-                        entity.insert((RigidBody(Dynamic), TimeTracked::new()));
-                    } else {
-                        panic!("Unknown rigid_body type: {}", str);
+                if let Some(true) = extras.compound_collider {
+                    entity.insert(CompoundCollider {
+                        parts: model
+                            .primitives
+                            .iter()
+                            .map(|primitive| primitive.mesh.bounding_box.clone())
+                            .collect(),
+                    });
+                }
+
+                if let Some(collider) = extras.collider {
+                    let bounds = model.bounding_box();
+                    let center = bounds.min + bounds.size() * 0.5;
+                    let meshes = || {
+                        model
+                            .primitives
+                            .iter()
+                            .map(|primitive| primitive.mesh.as_ref())
+                    };
+
+                    match collider {
+                        ColliderKind::Box => {
+                            entity.insert(BoxCollider { bounds });
+                        }
+                        ColliderKind::Sphere => {
+                            let radius = bounds.size().max() * 0.5;
+                            entity.insert(SphereCollider { center, radius });
+                        }
+                        ColliderKind::Capsule => {
+                            let size = bounds.size();
+                            let radius = size.x.max(size.z) * 0.5;
+                            let half_height = (size.y * 0.5 - radius).max(0.0);
+                            entity.insert(CapsuleCollider {
+                                center,
+                                half_height,
+                                radius,
+                            });
+                        }
+                        ColliderKind::ConvexHull => {
+                            entity.insert(MeshCollider::from_meshes(
+                                meshes(),
+                                MeshColliderKind::ConvexHull,
+                            ));
+                        }
+                        ColliderKind::Mesh => {
+                            entity.insert(MeshCollider::from_meshes(
+                                meshes(),
+                                MeshColliderKind::TriMesh,
+                            ));
+                        }
                     }
                 }
 
+                if let Some(rigid_body) = extras.rigid_body {
+                    let body_type = match rigid_body {
+                        RigidBodyKind::Kinematic => KinematicPositionBased,
+                        RigidBodyKind::Dynamic => Dynamic,
+                    };
+                    entity.insert((RigidBody(body_type), TimeTracked::new()));
+                    if let Some(mass) = extras.mass {
+                        entity.insert(Mass(mass));
+                    }
+                }
+
+                let to_flags = |pairs: Option<Vec<[u32; 2]>>| {
+                    pairs
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|[level, flag]| (LevelId::new(level), flag as FlagId))
+                        .collect()
+                };
+
                 if let Some(true) = extras.door {
-                    entity.insert(Door {});
+                    entity.insert(Door {
+                        opens_when: to_flags(extras.opens_when),
+                        closes_when: to_flags(extras.closes_when),
+                        locked: extras.locked.unwrap_or(false),
+                        locked_by_alarm: extras.locked_by_alarm.unwrap_or(false),
+                    });
                 }
 
-                if let Some(true) = extras.pressure_plate {
-                    entity.insert(PressurePlate {
-                        active_material: active_pressure_plate_material.clone(),
-                        inactive_material: inactive_pressure_plate_material.clone(),
+                if let Some(magnet) = extras.magnet {
+                    entity.insert(Magnet {
+                        powered_when: to_flags(magnet.powered_when),
+                        direction: Vector3::from(magnet.direction).normalize(),
+                        range: magnet.range,
+                        cone_angle: magnet.angle.unwrap_or(30.0).to_radians(),
+                        strength: magnet.strength.unwrap_or(20.0),
                     });
+                }
+
+                if let Some(true) = extras.pressure_plate {
+                    entity.insert((
+                        PressurePlate {
+                            active_material: active_pressure_plate_material.clone(),
+                            inactive_material: inactive_pressure_plate_material.clone(),
+                        },
+                        MaterialOverride::default(),
+                    ));
                     has_model = true;
                 }
 
+                if let Some(conveyor_belt) = extras.conveyor_belt {
+                    entity.insert((
+                        ConveyorBelt::new(conveyor_belt.direction.into(), conveyor_belt.speed),
+                        TextureScroll::default(),
+                    ));
+                }
+
+                if let Some(collision_layers) = &extras.collision_layers {
+                    let to_layers = |kinds: &Vec<CollisionLayerKind>| {
+                        kinds
+                            .iter()
+                            .map(|kind| CollisionLayer::from(*kind))
+                            .collect()
+                    };
+                    let mut layers = CollisionLayers::new();
+                    if let Some(memberships) = &collision_layers.layers {
+                        layers = layers.with_memberships(to_layers(memberships));
+                    }
+                    if let Some(filter) = &collision_layers.collides_with {
+                        layers = layers.with_filter(to_layers(filter));
+                    }
+                    entity.insert(layers);
+                }
+
+                if let Some(surface) = extras.surface {
+                    entity.insert(match surface {
+                        SurfaceKind::Metal => SurfaceType::Metal,
+                        SurfaceKind::Plastic => SurfaceType::Plastic,
+                        SurfaceKind::Glass => SurfaceType::Glass,
+                    });
+                }
+
                 if let Some(true) = extras.platform {
                     entity.insert(Platform);
                 }
 
-                if let Some(animation) = extras.animation {
-                    let start_transform = transform.clone();
-                    let mut end_transform = transform.clone();
-                    let test: Vector3<f32> = animation.translation.into();
-                    end_transform.position = end_transform.position.add(test);
+                if let Some(true) = extras.elevator {
+                    entity.insert((Elevator, Platform));
+                }
+
+                if let Some(button) = extras.elevator_button {
+                    entity.insert(ElevatorButton {
+                        elevator: button.elevator,
+                        stop: button.stop,
+                    });
+                    if extras.interactable.is_none() {
+                        extras.interactable = Some("call the elevator".to_string());
+                    }
+                }
 
-                    let animation = Animation {
-                        start_transform,
-                        end_transform,
-                        duration: Duration::from_secs_f32(animation.duration),
+                if let Some(property) = extras.animation {
+                    let mut animation = Animation::new(
+                        transform.clone(),
+                        Duration::from_secs_f32(property.duration),
+                    );
+                    animation.translation = property.translation.into();
+                    if let Some(rotation) = property.rotation {
+                        animation.rotation = Vector3::from(rotation).map(f32::to_radians);
+                    }
+                    if let Some(scale) = property.scale {
+                        animation.scale = scale.into();
+                    }
+                    animation.repeat = match property.repeat {
+                        Some(AnimationRepeatKind::Loop) => AnimationRepeat::Loop,
+                        Some(AnimationRepeatKind::PingPong) => AnimationRepeat::PingPong,
+                        None => AnimationRepeat::Once,
                     };
+                    animation.markers = property
+                        .markers
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|(name, progress)| AnimationMarker { name, progress })
+                        .collect();
 
                     let playing_animation = PlayingAnimation::new_frozen(animation);
 
-                    entity.insert(playing_animation);
+                    entity.insert((
+                        playing_animation,
+                        EntityEvent::<AnimationFinished>::default(),
+                        EntityEvent::<AnimationMarkerReached>::default(),
+                    ));
 
                     // May not have a time tracked if it's animated
                     entity.remove::<TimeTracked>();
                 }
 
                 if let Some(true) = extras.pickupable {
-                    entity.insert(Pickupable);
+                    let default_pickupable = Pickupable::default();
+                    let pickupable = Pickupable {
+                        hold_distance: extras
+                            .hold_distance
+                            .unwrap_or(default_pickupable.hold_distance),
+                        can_rotate: extras.rotatable.unwrap_or(default_pickupable.can_rotate),
+                        break_speed: extras.break_speed,
+                    };
+                    if pickupable.break_speed.is_some() {
+                        entity.insert(EntityEvent::<Broken>::default());
+                    }
+                    entity.insert(pickupable);
+                }
+
+                if let Some(video) = extras.video {
+                    entity.insert(video_loader::load_image_sequence(video.frames, video.fps));
+                }
+
+                if let Some(prompt) = extras.interactable {
+                    entity.insert((
+                        Interactable { prompt },
+                        EntityEvent::<Interacted>::default(),
+                    ));
                 }
 
                 if has_model {
@@ -244,13 +955,304 @@ impl SceneLoader {
                     entity.insert(model);
                 }
             }
-        }
 
-        Ok(())
+            // Static objects stay attached to their parent node, like a handle on a door.
+            // Rigid bodies and animations move in world space, so they are never children.
+            for (node_name, child) in static_entities {
+                let mut parent_name = scene_loading_result.parents.get(&node_name);
+                while let Some(name) = parent_name {
+                    if let Some(parent) = entities.get(name) {
+                        commands.entity(child).set_parent(*parent);
+                        break;
+                    }
+                    // Empty nodes are not spawned, so the next node above them becomes the parent
+                    parent_name = scene_loading_result.parents.get(name);
+                }
+            }
+        }
     }
 
     pub fn new() -> Self {
-        SceneLoader {}
+        SceneLoader {
+            prefabs: HashMap::new(),
+        }
+    }
+
+    pub fn with_prefab(mut self, name: impl Into<String>, prefab: Prefab) -> Self {
+        self.prefabs.insert(name.into(), prefab);
+        self
+    }
+
+    /// Finds the frames of the videos, so that a missing directory is a mistake in the level
+    fn read_videos(path: &Path, scene_loading_result: &mut SceneLoadingResult) {
+        let directory = path.parent().unwrap_or(Path::new(""));
+        for (_, _, extras, name) in scene_loading_result.models.iter_mut() {
+            let video = match &mut extras.video {
+                Some(video) => video,
+                None => continue,
+            };
+            let video_directory = directory.join(&video.directory);
+            match video_loader::image_sequence_frames(&video_directory) {
+                Ok(frames) if !frames.is_empty() => video.frames = frames,
+                Ok(_) => scene_loading_result.errors.push(format!(
+                    "Node {:?}: The video directory {:?} has no PNG files",
+                    name.0, video_directory
+                )),
+                Err(err) => scene_loading_result.errors.push(format!(
+                    "Node {:?}: Could not read the video directory {:?}: {}",
+                    name.0, video_directory, err
+                )),
+            }
+        }
+    }
+
+    /// Checks the parts of the extras that serde cannot check on its own
+    fn validate_scene(
+        &self,
+        level_id: LevelId,
+        scene_loading_result: &mut SceneLoadingResult,
+        flag_counts: &HashMap<LevelId, usize>,
+    ) {
+        let errors = &mut scene_loading_result.errors;
+
+        // Demo scenes are not levels of the game, so they do not need a spawnpoint
+        let is_game_level = flag_counts.contains_key(&level_id);
+        if is_game_level && scene_loading_result.cameras.is_empty() {
+            errors.push(format!(
+                "Level {} has no spawnpoint, add a camera to it",
+                level_id.id()
+            ));
+        }
+
+        let mut check_flag = |name: &DebugName, flag_level_id: LevelId, flag_id: FlagId| {
+            let count = match flag_counts.get(&flag_level_id) {
+                Some(count) => *count,
+                None => {
+                    errors.push(format!(
+                        "Node {:?}: Level {} has no flags",
+                        name.0,
+                        flag_level_id.id()
+                    ));
+                    return;
+                }
+            };
+            if flag_id >= count {
+                errors.push(format!(
+                    "Node {:?}: Flag {} is out of range, level {} only has {} flags",
+                    name.0,
+                    flag_id,
+                    flag_level_id.id(),
+                    count
+                ));
+            }
+        };
+
+        for (_, _, extras, name) in &scene_loading_result.models {
+            if let Some(flag) = extras.flag_trigger {
+                check_flag(name, level_id, flag as FlagId);
+            }
+            let magnet_flags = extras
+                .magnet
+                .as_ref()
+                .and_then(|magnet| magnet.powered_when.as_ref());
+            let flag_pairs = extras
+                .opens_when
+                .iter()
+                .chain(extras.closes_when.iter())
+                .chain(magnet_flags);
+            for [flag_level, flag] in flag_pairs.flatten() {
+                check_flag(name, LevelId::new(*flag_level), *flag as FlagId);
+            }
+        }
+
+        for (_, model, extras, name) in &scene_loading_result.models {
+            let prefab = extras
+                .prefab
+                .as_ref()
+                .and_then(|prefab_name| self.prefabs.get(prefab_name));
+            if let Some(prefab_name) = &extras.prefab {
+                if prefab.is_none() {
+                    errors.push(format!(
+                        "Node {:?}: Unknown prefab {:?}",
+                        name.0, prefab_name
+                    ));
+                }
+            }
+            let collider = extras
+                .collider
+                .or(prefab.and_then(|prefab| prefab.collider));
+            let rigid_body = extras
+                .rigid_body
+                .or(prefab.and_then(|prefab| prefab.rigid_body));
+            let has_collider = collider.is_some()
+                || extras.box_collider == Some(true)
+                || extras.compound_collider == Some(true);
+            if rigid_body.is_some() && !has_collider {
+                errors.push(format!("Node {:?}: A rigid body needs a collider", name.0));
+            }
+            // The player only inherits the velocity of kinematic bodies
+            if extras.elevator == Some(true) && rigid_body != Some(RigidBodyKind::Kinematic) {
+                errors.push(format!(
+                    "Node {:?}: An elevator needs \"rigid_body\": \"kinematic\"",
+                    name.0
+                ));
+            }
+            // Prefabs can be placed with empty nodes, but then the collider would have no size
+            let model = prefab
+                .and_then(|prefab| prefab.model.as_ref())
+                .unwrap_or(model);
+            if collider.is_some() && model.primitives.is_empty() {
+                errors.push(format!(
+                    "Node {:?}: A collider needs a mesh, either from the node or from its prefab",
+                    name.0
+                ));
+            } else if let Some(kind @ (ColliderKind::ConvexHull | ColliderKind::Mesh)) = collider {
+                let kind = match kind {
+                    ColliderKind::ConvexHull => MeshColliderKind::ConvexHull,
+                    _ => MeshColliderKind::TriMesh,
+                };
+                let meshes = model
+                    .primitives
+                    .iter()
+                    .map(|primitive| primitive.mesh.as_ref());
+                if !MeshCollider::from_meshes(meshes, kind).is_valid() {
+                    errors.push(format!(
+                        "Node {:?}: The mesh can't be used as a collider, it needs triangles and a convex hull needs a volume",
+                        name.0
+                    ));
+                }
+            }
+            if let Some(magnet) = &extras.magnet {
+                if Vector3::from(magnet.direction).norm() == 0.0 {
+                    errors.push(format!("Node {:?}: A magnet needs a direction", name.0));
+                }
+                if magnet.range <= 0.0 {
+                    errors.push(format!(
+                        "Node {:?}: A magnet needs a range above zero",
+                        name.0
+                    ));
+                }
+                if !(0.0..=180.0).contains(&magnet.angle.unwrap_or(30.0)) {
+                    errors.push(format!(
+                        "Node {:?}: The angle of a magnet has to be between 0 and 180 degrees",
+                        name.0
+                    ));
+                }
+            }
+            if let Some(conveyor_belt) = &extras.conveyor_belt {
+                if Vector3::from(conveyor_belt.direction).norm() == 0.0 {
+                    errors.push(format!(
+                        "Node {:?}: A conveyor belt needs a direction",
+                        name.0
+                    ));
+                }
+            }
+            if let Some(up) = extras.gravity_boots {
+                if Vector3::from(up).norm() == 0.0 {
+                    errors.push(format!(
+                        "Node {:?}: A gravity boot zone needs an up direction",
+                        name.0
+                    ));
+                }
+            }
+            if let Some(hold_distance) = extras.hold_distance {
+                if hold_distance <= 0.0 {
+                    errors.push(format!(
+                        "Node {:?}: The hold distance has to be above zero",
+                        name.0
+                    ));
+                }
+            }
+            if let Some(break_speed) = extras.break_speed {
+                if break_speed <= 0.0 {
+                    errors.push(format!(
+                        "Node {:?}: The break speed has to be above zero",
+                        name.0
+                    ));
+                }
+            }
+            if let Some(mass) = extras.mass {
+                if mass <= 0.0 {
+                    errors.push(format!("Node {:?}: The mass has to be above zero", name.0));
+                }
+            }
+            if let Some(fluid) = &extras.fluid {
+                if fluid.drag < 0.0 {
+                    errors.push(format!(
+                        "Node {:?}: The drag of a fluid can't be negative",
+                        name.0
+                    ));
+                }
+            }
+            if let Some(animation) = &extras.animation {
+                if animation.duration <= 0.0 {
+                    errors.push(format!(
+                        "Node {:?}: An animation needs a duration above zero",
+                        name.0
+                    ));
+                }
+                for (marker, progress) in animation.markers.iter().flatten() {
+                    if !(0.0..=1.0).contains(progress) {
+                        errors.push(format!(
+                            "Node {:?}: The animation marker {:?} has to be between 0 and 1",
+                            name.0, marker
+                        ));
+                    }
+                }
+            }
+        }
+
+        // Only static objects can be exits
+        let model_names: Vec<&str> = scene_loading_result
+            .models
+            .iter()
+            .filter(|(_, _, extras, _)| extras.rigid_body.is_none())
+            .map(|(_, _, _, name)| name.0.as_str())
+            .collect();
+        for (_, _, extras, name) in &scene_loading_result.models {
+            if let Some(exit) = &extras.teleporter {
+                if exit == &name.0 {
+                    errors.push(format!(
+                        "Node {:?}: A teleporter can't be its own exit",
+                        name.0
+                    ));
+                } else if !model_names.contains(&exit.as_str()) {
+                    errors.push(format!(
+                        "Node {:?}: There is no teleporter exit called {:?} in this level",
+                        name.0, exit
+                    ));
+                }
+            }
+        }
+
+        let elevators: Vec<&str> = scene_loading_result
+            .models
+            .iter()
+            .filter(|(_, _, extras, _)| extras.elevator == Some(true))
+            .map(|(_, _, _, name)| name.0.as_str())
+            .collect();
+        for (_, _, extras, name) in &scene_loading_result.models {
+            if extras.elevator == Some(true) && extras.animation.is_none() {
+                errors.push(format!("Node {:?}: An elevator needs an animation", name.0));
+            }
+            if let Some(button) = &extras.elevator_button {
+                if !elevators.contains(&button.elevator.as_str()) {
+                    errors.push(format!(
+                        "Node {:?}: There is no elevator called {:?} in this level",
+                        name.0, button.elevator
+                    ));
+                }
+            }
+        }
+    }
+
+    /// The extras of the node take precedence over the prefab
+    fn apply_prefab(prefab: &Prefab, extras: &mut GLTFModelExtras) {
+        extras.collider = extras.collider.or(prefab.collider);
+        extras.rigid_body = extras.rigid_body.or(prefab.rigid_body);
+        extras.pickupable = extras.pickupable.or(Some(prefab.pickupable));
+        extras.casts_shadow = extras.casts_shadow.or(Some(prefab.casts_shadow));
+        extras.mass = extras.mass.or(prefab.mass);
     }
 
     fn read_node(
@@ -258,16 +1260,24 @@ impl SceneLoader {
         scene_loading_data: &mut SceneLoadingData,
         scene_loading_result: &mut SceneLoadingResult,
         parent_transform: &Transform,
+        parent_name: Option<&str>,
     ) {
         let local_transform: Transform = from_gltf_transform(node.transform());
         let global_transform = parent_transform * local_transform;
 
+        if let (Some(name), Some(parent_name)) = (node.name(), parent_name) {
+            scene_loading_result
+                .parents
+                .insert(name.to_string(), parent_name.to_string());
+        }
+
         for child in node.children() {
             SceneLoader::read_node(
                 &child,
                 scene_loading_data,
                 scene_loading_result,
                 &global_transform,
+                node.name(),
             );
         }
 
@@ -276,9 +1286,14 @@ impl SceneLoader {
                 .extras()
                 .as_ref()
                 .map(|extra| {
-                    let str = extra.get();
-                    let result: GLTFLightExtras = serde_json::from_str(str).expect(str);
-                    result
+                    parse_extras(extra.get()).unwrap_or_else(|error| {
+                        scene_loading_result.errors.push(format!(
+                            "Light of node {:?}: {}",
+                            node.name().unwrap_or_default(),
+                            error
+                        ));
+                        GLTFLightExtras::default()
+                    })
                 })
                 .unwrap_or_default();
             scene_loading_result.lights.push((
@@ -296,22 +1311,115 @@ impl SceneLoader {
             ));
         }
 
-        let model_extras = node
+        let mut model_extras: GLTFModelExtras = node
             .extras()
             .as_ref()
             .map(|extra| {
-                let str = extra.get();
-
-                let result: GLTFModelExtras = serde_json::from_str(str).expect(str);
-
-                result
+                parse_extras(extra.get()).unwrap_or_else(|error| {
+                    scene_loading_result.errors.push(format!(
+                        "Node {:?}: {}",
+                        node.name().unwrap_or_default(),
+                        error
+                    ));
+                    GLTFModelExtras::default()
+                })
             })
             .unwrap_or_default();
 
-        if let Some(mesh) = node.mesh() {
+        let cutscene_camera = model_extras.cutscene_camera.take();
+        let cutscene_look_at = model_extras.cutscene_look_at.take();
+        let cutscene_points = [
+            (cutscene_camera, CutscenePointKind::Camera),
+            (cutscene_look_at, CutscenePointKind::LookAt),
+        ];
+        for (property, kind) in cutscene_points {
+            if let Some(property) = property {
+                scene_loading_result.cutscene_points.push((
+                    global_transform.clone(),
+                    CutscenePoint {
+                        cutscene: property.cutscene,
+                        kind,
+                        time: property.time,
+                    },
+                    DebugName(node.name().unwrap_or_default().to_string()),
+                ));
+            }
+        }
+
+        if let (Some(mesh), Some(depth)) = (node.mesh(), model_extras.decal) {
+            let model =
+                Self::load_model(mesh, scene_loading_data, &mut scene_loading_result.errors);
+            let texture = model
+                .primitives
+                .first()
+                .and_then(|primitive| primitive.material.base_color_texture.clone());
+            match texture {
+                Some(texture) => {
+                    let bounding_box = model.bounding_box();
+                    let size = bounding_box.max - bounding_box.min;
+                    scene_loading_result.decals.push((
+                        global_transform.clone(),
+                        Decal {
+                            texture,
+                            extent: Vector3::new(size.x, depth, size.z),
+                        },
+                        DebugName(node.name().unwrap_or_default().to_string()),
+                    ));
+                }
+                None => scene_loading_result.errors.push(format!(
+                    "Node {:?}: A decal needs a base color texture",
+                    node.name().unwrap_or_default()
+                )),
+            }
+        } else if let (Some(mesh), Some(property)) = (node.mesh(), &model_extras.billboard) {
+            let model =
+                Self::load_model(mesh, scene_loading_data, &mut scene_loading_result.errors);
+            let texture = model
+                .primitives
+                .first()
+                .and_then(|primitive| primitive.material.base_color_texture.clone());
+            match texture {
+                Some(texture) => {
+                    // The billboard ignores the scale, so it gets baked into the size
+                    let size = model
+                        .bounding_box()
+                        .size()
+                        .component_mul(&global_transform.scale);
+                    let mut billboard = Billboard::new(texture, Vector2::new(size.x, size.z));
+                    if let Some(color) = property.color {
+                        billboard = billboard.with_color(color.into());
+                    }
+                    if property.axis_locked.unwrap_or(false) {
+                        billboard = billboard.with_locked_axis(Vector3::y());
+                    }
+                    if property.additive.unwrap_or(false) {
+                        billboard = billboard.with_blend(BillboardBlend::Additive);
+                    }
+                    scene_loading_result.billboards.push((
+                        global_transform.clone(),
+                        billboard,
+                        DebugName(node.name().unwrap_or_default().to_string()),
+                    ));
+                }
+                None => scene_loading_result.errors.push(format!(
+                    "Node {:?}: A billboard needs a base color texture",
+                    node.name().unwrap_or_default()
+                )),
+            }
+        } else if let Some(mesh) = node.mesh() {
+            scene_loading_result.models.push((
+                global_transform.clone(),
+                Self::load_model(mesh, scene_loading_data, &mut scene_loading_result.errors),
+                model_extras,
+                DebugName(node.name().unwrap_or_default().to_string()),
+            ));
+        } else if model_extras.prefab.is_some() {
+            // The prefab brings its own model
             scene_loading_result.models.push((
                 global_transform.clone(),
-                Self::load_model(mesh, scene_loading_data),
+                Model {
+                    primitives: Vec::new(),
+                },
                 model_extras,
                 DebugName(node.name().unwrap_or_default().to_string()),
             ));
@@ -335,13 +1443,17 @@ impl SceneLoader {
         }
     }
 
-    fn load_model(mesh: gltf::Mesh, scene_loading_data: &mut SceneLoadingData) -> Model {
+    fn load_model(
+        mesh: gltf::Mesh,
+        scene_loading_data: &mut SceneLoadingData,
+        errors: &mut Vec<String>,
+    ) -> Model {
         let mut model = Model {
             primitives: Vec::new(),
         };
 
         for primitive in mesh.primitives() {
-            let material = scene_loading_data.get_material(&primitive);
+            let material = scene_loading_data.get_material(&primitive, errors);
             let mesh = scene_loading_data.get_mesh(&primitive);
 
             model.primitives.push(CpuPrimitive { mesh, material })
@@ -374,21 +1486,72 @@ struct SceneLoadingData {
     missing_material: Arc<CpuMaterial>,
 }
 
+/// Scenes that have been read from a file, but not spawned yet
+pub struct LoadedScenes {
+    scenes: Vec<(LevelId, SceneLoadingResult)>,
+}
+
+impl LoadedScenes {
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+}
+
 struct SceneLoadingResult {
     lights: Vec<(Transform, Light, GLTFLightExtras, DebugName)>,
     cameras: Vec<(Transform, DebugName)>,
+    cutscene_points: Vec<(Transform, CutscenePoint, DebugName)>,
     models: Vec<(Transform, Model, GLTFModelExtras, DebugName)>,
+    decals: Vec<(Transform, Decal, DebugName)>,
+    billboards: Vec<(Transform, Billboard, DebugName)>,
+    fog: Option<Fog>,
+    /// The names of the nodes in the levels file, and the names of their parent nodes
+    parents: HashMap<String, String>,
+    /// Mistakes in the level, with the names of the nodes
+    errors: Vec<String>,
 }
 impl SceneLoadingResult {
     fn new() -> Self {
         Self {
             lights: vec![],
             cameras: vec![],
+            cutscene_points: vec![],
             models: vec![],
+            decals: vec![],
+            billboards: vec![],
+            fog: None,
+            parents: HashMap::new(),
+            errors: vec![],
         }
     }
 }
 
+/// Lists everything that is wrong with the levels
+#[derive(Debug)]
+pub struct SceneValidationError {
+    pub errors: Vec<String>,
+}
+
+impl std::fmt::Display for SceneValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "The levels have {} error(s):", self.errors.len())?;
+        for error in &self.errors {
+            writeln!(f, "- {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SceneValidationError {}
+
+/// Unlike the raw serde error, this includes the extras that could not be read
+fn parse_extras<'a, T>(str: &'a str) -> Result<T, String>
+where
+    T: Deserialize<'a>,
+{
+    serde_json::from_str(str).map_err(|error| format!("{} in the extras {}", error, str))
+}
+
 impl SceneLoadingData {
     fn new(buffers: Vec<gltf::buffer::Data>, images: Vec<gltf::image::Data>) -> Self {
         let images = images.into_iter().enumerate().collect();
@@ -424,15 +1587,21 @@ impl SceneLoadingData {
                     } else {
                         Box::new(repeat([0.0f32, 0.0f32]))
                     };
+                // Meshes without a lightmap UV set simply reuse the first one
+                let uv1s: Option<Vec<_>> = reader
+                    .read_tex_coords(1)
+                    .map(|read_tex_coords| read_tex_coords.into_f32().collect());
 
                 let mut vertices = vec![];
 
                 // zippy zip https://stackoverflow.com/a/71494478/3492994
-                for (position, (normal, uv)) in positions.zip(normals.zip(uvs)) {
+                for (index, (position, (normal, uv))) in positions.zip(normals.zip(uvs)).enumerate()
+                {
                     vertices.push(CpuMeshVertex {
                         position,
                         normal,
                         uv,
+                        uv1: uv1s.as_ref().map(|uv1s| uv1s[index]).unwrap_or(uv),
                     });
                 }
 
@@ -452,7 +1621,11 @@ impl SceneLoadingData {
             .clone()
     }
 
-    fn get_material(&mut self, primitive: &gltf::Primitive) -> Arc<CpuMaterial> {
+    fn get_material(
+        &mut self,
+        primitive: &gltf::Primitive,
+        errors: &mut Vec<String>,
+    ) -> Arc<CpuMaterial> {
         let gltf_material = primitive.material();
 
         if let Some(material_index) = gltf_material.index() {
@@ -464,6 +1637,20 @@ impl SceneLoadingData {
                 let emissive_factor = gltf_material
                     .emissive_factor()
                     .map(|v| v * emissive_strength);
+                let material_extras: GLTFMaterialExtras = gltf_material
+                    .extras()
+                    .as_ref()
+                    .map(|extra| {
+                        parse_extras(extra.get()).unwrap_or_else(|error| {
+                            errors.push(format!(
+                                "Material {:?}: {}",
+                                gltf_material.name().unwrap_or_default(),
+                                error
+                            ));
+                            GLTFMaterialExtras::default()
+                        })
+                    })
+                    .unwrap_or_default();
                 let material = Arc::new(CpuMaterial {
                     id: AssetId::new_v4(),
                     base_color: Vector3::from_row_slice(
@@ -473,9 +1660,31 @@ impl SceneLoadingData {
                         let sampler = self.get_sampler(&info.texture());
                         self.get_texture(&info.texture(), sampler)
                     }),
+                    // Blender can only export the baked lighting through the occlusion slot
+                    lightmap_texture: gltf_material
+                        .occlusion_texture()
+                        .filter(|_| material_extras.lightmap.unwrap_or(false))
+                        .map(|info| {
+                            let sampler = self.get_sampler(&info.texture());
+                            self.get_texture(&info.texture(), sampler)
+                        }),
                     roughness_factor: gltf_material_pbr.roughness_factor(),
                     metallic_factor: gltf_material_pbr.metallic_factor(),
                     emissivity: emissive_factor.into(),
+                    reflectivity: material_extras.reflectivity.unwrap_or(0.0).clamp(0.0, 1.0),
+                    wind: material_extras.wind.map(|wind| Wind {
+                        amplitude: wind.amplitude,
+                        frequency: wind.frequency,
+                    }),
+                    flipbook: material_extras
+                        .flipbook
+                        .filter(|flipbook| flipbook.frames > 0)
+                        .map(|flipbook| Flipbook {
+                            frame_count: flipbook.frames,
+                            columns: flipbook.columns.unwrap_or(flipbook.frames).max(1),
+                            fps: flipbook.fps,
+                        }),
+                    opacity: material_extras.opacity.unwrap_or(1.0).clamp(0.0, 1.0),
                 });
 
                 self.materials.insert(material_index, material.clone());
@@ -0,0 +1,131 @@
+use image::GenericImageView;
+use scene::asset::AssetId;
+use scene::color_grading::ColorLut;
+use std::path::Path;
+
+/// Loads a color lookup table, either an Adobe `.cube` file or a PNG strip.
+/// A PNG strip has the blue slices next to each other, so a table with the size 16 is a 256×16 image.
+pub fn load_lut<P>(path: P) -> ColorLut
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("cube") => load_cube_lut(path),
+        Some("png") => load_png_lut(path),
+        _ => panic!(
+            "Color lookup table {:?} has to be a .cube or .png file",
+            path
+        ),
+    }
+}
+
+fn load_cube_lut(path: &Path) -> ColorLut {
+    let text =
+        std::fs::read_to_string(path).unwrap_or_else(|_| panic!("Failed to open {:?}", path));
+
+    let mut size = None;
+    let mut domain_min = [0.0, 0.0, 0.0];
+    let mut domain_max = [1.0, 1.0, 1.0];
+    let mut entries = vec![];
+    for line in text.lines().map(|line| line.trim()) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with("TITLE") {
+            continue;
+        }
+
+        let mut words = line.split_whitespace();
+        let keyword = words.next().unwrap();
+        let parse_floats = |words: std::str::SplitWhitespace| -> [f32; 3] {
+            let values = words
+                .map(|word| {
+                    word.parse::<f32>()
+                        .unwrap_or_else(|_| panic!("{:?}: Invalid number {:?}", path, word))
+                })
+                .collect::<Vec<_>>();
+            assert!(
+                values.len() == 3,
+                "{:?}: Expected three numbers in {:?}",
+                path,
+                line
+            );
+            [values[0], values[1], values[2]]
+        };
+
+        match keyword {
+            "LUT_3D_SIZE" => {
+                size = Some(
+                    words
+                        .next()
+                        .and_then(|word| word.parse::<u32>().ok())
+                        .unwrap_or_else(|| panic!("{:?}: Invalid LUT_3D_SIZE", path)),
+                );
+            }
+            "LUT_1D_SIZE" => panic!("{:?}: Only 3D lookup tables are supported", path),
+            "DOMAIN_MIN" => domain_min = parse_floats(words),
+            "DOMAIN_MAX" => domain_max = parse_floats(words),
+            _ => entries.push(parse_floats(line.split_whitespace())),
+        }
+    }
+
+    let size = size.unwrap_or_else(|| panic!("{:?}: Missing LUT_3D_SIZE", path));
+    assert!(
+        size >= 2,
+        "{:?}: The LUT_3D_SIZE has to be at least 2",
+        path
+    );
+    assert!(
+        entries.len() == (size * size * size) as usize,
+        "{:?}: Expected {} entries, but found {}",
+        path,
+        size * size * size,
+        entries.len()
+    );
+
+    let to_byte = |value: f32, channel: usize| {
+        let normalized =
+            (value - domain_min[channel]) / (domain_max[channel] - domain_min[channel]);
+        (normalized.clamp(0.0, 1.0) * 255.0).round() as u8
+    };
+
+    // The .cube format has the same order as the table, red changes the fastest
+    let data = entries
+        .into_iter()
+        .map(|[r, g, b]| [to_byte(r, 0), to_byte(g, 1), to_byte(b, 2), 255])
+        .collect();
+
+    ColorLut {
+        id: AssetId::new_v4(),
+        size,
+        data,
+    }
+}
+
+fn load_png_lut(path: &Path) -> ColorLut {
+    let image = image::open(path).unwrap_or_else(|_| panic!("Failed to open {:?}", path));
+    let (width, height) = image.dimensions();
+    let size = height;
+    assert!(
+        size >= 2 && width == size * size,
+        "{:?}: A PNG lookup table has to be size² pixels wide and size pixels high, but it is {}×{}",
+        path,
+        width,
+        height
+    );
+
+    let image = image.to_rgba8();
+    let mut data = Vec::with_capacity((size * size * size) as usize);
+    for blue in 0..size {
+        for green in 0..size {
+            for red in 0..size {
+                let pixel = image.get_pixel(blue * size + red, green);
+                data.push([pixel[0], pixel[1], pixel[2], 255]);
+            }
+        }
+    }
+
+    ColorLut {
+        id: AssetId::new_v4(),
+        size,
+        data,
+    }
+}
@@ -0,0 +1,300 @@
+//! Support for the `EXT_meshopt_compression` extension, which is what gltfpack uses for compressing meshes.
+//! The compressed buffer views get decoded into their fallback buffers,
+//! so that the rest of the loader can read them like uncompressed ones.
+
+use std::error::Error;
+use std::os::raw::c_void;
+use std::path::Path;
+
+use gltf::{buffer, image, Document, Gltf};
+use serde::Deserialize;
+
+const EXTENSION_NAME: &str = "EXT_meshopt_compression";
+
+#[derive(Deserialize, Debug, Default)]
+struct CompressedBuffer {
+    /// The buffer has no data of its own, the decoded buffer views get written into it
+    #[serde(default)]
+    fallback: bool,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CompressedBufferView {
+    buffer: usize,
+    #[serde(default)]
+    byte_offset: usize,
+    byte_length: usize,
+    byte_stride: usize,
+    count: usize,
+    mode: CompressionMode,
+    #[serde(default)]
+    filter: CompressionFilter,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum CompressionMode {
+    Attributes,
+    Triangles,
+    Indices,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum CompressionFilter {
+    #[default]
+    None,
+    Octahedral,
+    Quaternion,
+    Exponential,
+}
+
+/// Replaces [`gltf::import`], which refuses files that require the extension
+pub fn import(
+    path: &Path,
+) -> Result<(Document, Vec<buffer::Data>, Vec<image::Data>), Box<dyn Error>> {
+    let Gltf { document, blob } = Gltf::from_slice_without_validation(&std::fs::read(path)?)?;
+
+    if !document
+        .extensions_used()
+        .any(|extension| extension == EXTENSION_NAME)
+    {
+        // Uncompressed files still get validated
+        return Ok(gltf::import(path)?);
+    }
+
+    let base = path.parent().unwrap_or_else(|| Path::new("./"));
+    let mut buffers = import_buffers(&document, base, blob)?;
+    decode_buffer_views(&document, &mut buffers)?;
+    let images = gltf::import_images(&document, Some(base), &buffers)?;
+
+    Ok((document, buffers, images))
+}
+
+fn import_buffers(
+    document: &Document,
+    base: &Path,
+    mut blob: Option<Vec<u8>>,
+) -> Result<Vec<buffer::Data>, Box<dyn Error>> {
+    let mut buffers = vec![];
+    for buffer in document.buffers() {
+        let compressed_buffer: CompressedBuffer = buffer
+            .extension_value(EXTENSION_NAME)
+            .map(|value| serde_json::from_value(value.clone()))
+            .transpose()?
+            .unwrap_or_default();
+
+        let mut data = if compressed_buffer.fallback {
+            vec![0; buffer.length()]
+        } else {
+            match buffer.source() {
+                buffer::Source::Bin => blob.take().ok_or("The .glb file has no binary chunk")?,
+                buffer::Source::Uri(uri) if uri.starts_with("data:") => {
+                    return Err("Compressed scenes cannot have embedded buffers".into());
+                }
+                buffer::Source::Uri(uri) => std::fs::read(base.join(uri))?,
+            }
+        };
+
+        // Same padding as gltf::import
+        while data.len() % 4 != 0 {
+            data.push(0);
+        }
+        buffers.push(buffer::Data(data));
+    }
+    Ok(buffers)
+}
+
+/// Decodes the compressed buffer views one after another, and writes them into their fallback buffers
+fn decode_buffer_views(
+    document: &Document,
+    buffers: &mut [buffer::Data],
+) -> Result<(), Box<dyn Error>> {
+    for view in document.views() {
+        let value = match view.extension_value(EXTENSION_NAME) {
+            Some(value) => value,
+            None => continue,
+        };
+        let compressed_view: CompressedBufferView = serde_json::from_value(value.clone())?;
+        validate_buffer_view(&compressed_view, view.length())
+            .map_err(|error| format!("Buffer view {}: {}", view.index(), error))?;
+
+        let source = compressed_view
+            .byte_offset
+            .checked_add(compressed_view.byte_length)
+            .and_then(|end| {
+                buffers
+                    .get(compressed_view.buffer)?
+                    .0
+                    .get(compressed_view.byte_offset..end)
+            })
+            .ok_or_else(|| {
+                format!(
+                    "Buffer view {}: The compressed data is outside of its buffer",
+                    view.index()
+                )
+            })?;
+        let decoded = decode_buffer_view(&compressed_view, source)
+            .map_err(|error| format!("Buffer view {}: {}", view.index(), error))?;
+
+        let target = buffers
+            .get_mut(view.buffer().index())
+            .and_then(|target| {
+                target
+                    .0
+                    .get_mut(view.offset()..view.offset() + decoded.len())
+            })
+            .ok_or_else(|| {
+                format!(
+                    "Buffer view {}: The decoded data doesn't fit into its buffer",
+                    view.index()
+                )
+            })?;
+        target.copy_from_slice(&decoded);
+    }
+
+    Ok(())
+}
+
+/// Checks the parts of the extension that meshoptimizer would otherwise trust blindly
+fn validate_buffer_view(view: &CompressedBufferView, decoded_length: usize) -> Result<(), String> {
+    let stride = view.byte_stride;
+    match view.mode {
+        CompressionMode::Attributes if stride % 4 != 0 || !(4..=256).contains(&stride) => {
+            return Err(format!(
+                "The stride of attributes has to be a multiple of 4 up to 256, not {}",
+                stride
+            ));
+        }
+        CompressionMode::Triangles | CompressionMode::Indices if stride != 2 && stride != 4 => {
+            return Err(format!(
+                "The stride of indices has to be 2 or 4, not {}",
+                stride
+            ));
+        }
+        CompressionMode::Triangles if view.count % 3 != 0 => {
+            return Err(format!(
+                "Triangles need a count that is a multiple of 3, not {}",
+                view.count
+            ));
+        }
+        _ => {}
+    }
+
+    let is_valid_filter = match (view.mode, view.filter) {
+        (_, CompressionFilter::None) => true,
+        (CompressionMode::Attributes, CompressionFilter::Octahedral) => stride == 4 || stride == 8,
+        (CompressionMode::Attributes, CompressionFilter::Quaternion) => stride == 8,
+        (CompressionMode::Attributes, CompressionFilter::Exponential) => true,
+        _ => false,
+    };
+    if !is_valid_filter {
+        return Err(format!(
+            "The filter {:?} can't be used for {:?} with a stride of {}",
+            view.filter, view.mode, stride
+        ));
+    }
+
+    if view.count.checked_mul(stride) != Some(decoded_length) {
+        return Err(format!(
+            "{} elements with a stride of {} don't fill the {} bytes of the buffer view",
+            view.count, stride, decoded_length
+        ));
+    }
+    Ok(())
+}
+
+/// The buffer view has to be validated first
+fn decode_buffer_view(view: &CompressedBufferView, source: &[u8]) -> Result<Vec<u8>, String> {
+    let decoded: Result<Vec<u8>, meshopt::Error> = match (view.mode, view.byte_stride) {
+        (CompressionMode::Attributes, _) => return decode_vertex_buffer(view, source),
+        (CompressionMode::Triangles, 2) => meshopt::decode_index_buffer::<u16>(source, view.count)
+            .map(|indices| {
+                indices
+                    .iter()
+                    .flat_map(|index| index.to_le_bytes())
+                    .collect()
+            }),
+        (CompressionMode::Triangles, _) => meshopt::decode_index_buffer::<u32>(source, view.count)
+            .map(|indices| {
+                indices
+                    .iter()
+                    .flat_map(|index| index.to_le_bytes())
+                    .collect()
+            }),
+        (CompressionMode::Indices, _) => return decode_index_sequence(view, source),
+    };
+    // Only attributes can have a filter
+    decoded.map_err(|error| format!("Could not decode the compressed data: {:?}", error))
+}
+
+/// The safe vertex decoder takes the stride from the size of a vertex type,
+/// but the stride of a buffer view is only known at runtime
+fn decode_vertex_buffer(view: &CompressedBufferView, source: &[u8]) -> Result<Vec<u8>, String> {
+    let mut decoded = vec![0u8; view.count * view.byte_stride];
+    // Safety: The destination has room for count * byte_stride bytes, which is exactly how much
+    // meshoptimizer writes, and the validation made sure that the stride is supported
+    let result = unsafe {
+        meshopt::ffi::meshopt_decodeVertexBuffer(
+            decoded.as_mut_ptr() as *mut c_void,
+            view.count,
+            view.byte_stride,
+            source.as_ptr(),
+            source.len(),
+        )
+    };
+    if result != 0 {
+        return Err(format!(
+            "Could not decode the compressed data, error code {}",
+            result
+        ));
+    }
+
+    apply_filter(view, &mut decoded);
+    Ok(decoded)
+}
+
+/// meshopt only has raw bindings for the filters
+fn apply_filter(view: &CompressedBufferView, decoded: &mut [u8]) {
+    let destination = decoded.as_mut_ptr() as *mut c_void;
+    // Safety: The validation made sure that the decoded data is exactly count * byte_stride bytes,
+    // with a stride that the filter supports. The filters decode the data in place.
+    unsafe {
+        match view.filter {
+            CompressionFilter::None => {}
+            CompressionFilter::Octahedral => {
+                meshopt::ffi::meshopt_decodeFilterOct(destination, view.count, view.byte_stride)
+            }
+            CompressionFilter::Quaternion => {
+                meshopt::ffi::meshopt_decodeFilterQuat(destination, view.count, view.byte_stride)
+            }
+            CompressionFilter::Exponential => {
+                meshopt::ffi::meshopt_decodeFilterExp(destination, view.count, view.byte_stride)
+            }
+        }
+    }
+}
+
+/// meshopt only has raw bindings for index sequences, which gltfpack uses for the indices of lines and points
+fn decode_index_sequence(view: &CompressedBufferView, source: &[u8]) -> Result<Vec<u8>, String> {
+    let mut decoded = vec![0u8; view.count * view.byte_stride];
+    // Safety: The destination has room for count * byte_stride bytes, which is exactly how much
+    // meshoptimizer writes, and the validation made sure that the stride is 2 or 4
+    let result = unsafe {
+        meshopt::ffi::meshopt_decodeIndexSequence(
+            decoded.as_mut_ptr() as *mut c_void,
+            view.count,
+            view.byte_stride,
+            source.as_ptr(),
+            source.len(),
+        )
+    };
+    if result != 0 {
+        return Err(format!(
+            "Could not decode the compressed data, error code {}",
+            result
+        ));
+    }
+    Ok(decoded)
+}
@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use bevy_ecs::prelude::*;
+use levels::level_id::LevelId;
+use scene::debug_name::DebugName;
+
+/// Finds the spawned objects of the levels by their name in the levels file,
+/// like `registry.get("Level1.Door.001")`. The level part is the id of the level, so
+/// `Level1` is the scene with `"level_id": 1` in its extras.
+/// Names are only unique within a level, if two objects share a name, the last one wins.
+#[derive(Resource, Default)]
+pub struct NameRegistry {
+    entities: HashMap<String, Entity>,
+    /// For forgetting the despawned objects
+    paths: HashMap<Entity, String>,
+}
+
+impl NameRegistry {
+    pub fn path(level_id: LevelId, name: &str) -> String {
+        format!("Level{}.{}", level_id.id(), name)
+    }
+
+    /// The path is `Level<id>.<name>`, see [`NameRegistry::path`]
+    pub fn get(&self, path: &str) -> Option<Entity> {
+        self.entities.get(path).copied()
+    }
+
+    pub fn get_in_level(&self, level_id: LevelId, name: &str) -> Option<Entity> {
+        self.get(&Self::path(level_id, name))
+    }
+
+    fn insert(&mut self, entity: Entity, path: String) {
+        self.remove(entity);
+        self.entities.insert(path.clone(), entity);
+        self.paths.insert(entity, path);
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        if let Some(path) = self.paths.remove(&entity) {
+            // A respawned object with the same name might already have taken over the path
+            if self.entities.get(&path) == Some(&entity) {
+                self.entities.remove(&path);
+            }
+        }
+    }
+}
+
+/// Keeps up with the loader, which spawns and despawns the levels with commands.
+/// The names are available one frame after spawning.
+pub fn update_name_registry(
+    mut registry: ResMut<NameRegistry>,
+    mut removed: RemovedComponents<DebugName>,
+    added: Query<(Entity, &DebugName, &LevelId), Added<DebugName>>,
+) {
+    for entity in removed.iter() {
+        registry.remove(entity);
+    }
+
+    for (entity, name, level_id) in added.iter() {
+        registry.insert(entity, NameRegistry::path(*level_id, &name.0));
+    }
+}
@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use scene::{material::CpuMaterial, model::Model};
+
+use crate::loader::{ColliderKind, RigidBodyKind};
+
+/// A template for objects that appear in many levels, like the crates.
+/// A glTF node with the extras `"prefab": "crate"` gets everything from the prefab that was registered
+/// with [`crate::loader::SceneLoader::with_prefab`]. The extras of the node still override single properties.
+#[derive(Clone, Default)]
+pub struct Prefab {
+    /// Replaces the mesh of the node, which also allows placing prefabs with empty nodes.
+    /// Without it, a prefab with a collider has to be placed on a node with a mesh.
+    pub model: Option<Model>,
+    /// Replaces the materials of the model
+    pub material: Option<Arc<CpuMaterial>>,
+    pub collider: Option<ColliderKind>,
+    pub rigid_body: Option<RigidBodyKind>,
+    pub pickupable: bool,
+    pub casts_shadow: bool,
+    /// In kilograms, heavy objects slow the player down while they are held
+    pub mass: Option<f32>,
+}
+
+impl Prefab {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    pub fn with_material(mut self, material: Arc<CpuMaterial>) -> Self {
+        self.material = Some(material);
+        self
+    }
+
+    pub fn with_collider(mut self, collider: ColliderKind) -> Self {
+        self.collider = Some(collider);
+        self
+    }
+
+    pub fn with_rigid_body(mut self, rigid_body: RigidBodyKind) -> Self {
+        self.rigid_body = Some(rigid_body);
+        self
+    }
+
+    pub fn with_pickupable(mut self, pickupable: bool) -> Self {
+        self.pickupable = pickupable;
+        self
+    }
+
+    pub fn with_casts_shadow(mut self, casts_shadow: bool) -> Self {
+        self.casts_shadow = casts_shadow;
+        self
+    }
+
+    pub fn with_mass(mut self, mass: f32) -> Self {
+        self.mass = Some(mass);
+        self
+    }
+
+    /// The model that the prefab spawns with, based on the model of the glTF node
+    pub(crate) fn create_model(&self, node_model: Model) -> Model {
+        let mut model = self.model.clone().unwrap_or(node_model);
+        if let Some(material) = &self.material {
+            for primitive in model.primitives.iter_mut() {
+                primitive.material = material.clone();
+            }
+        }
+        model
+    }
+}
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// How far the player got, which decides what the main menu offers.
+/// Kept separate from the [`crate::config_loader::LoadableConfig`], so that resetting the settings
+/// doesn't lose the progress.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LoadableProgress {
+    /// The furthest level that was reached, every level up to it can be selected
+    pub unlocked_level: u32,
+    /// The ids of the unlocked achievements
+    pub achievements: Vec<String>,
+}
+
+impl LoadableProgress {
+    /// A missing file means that the game has never been played
+    pub fn load<P>(path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+
+        match std::fs::File::open(path) {
+            Ok(file) => serde_json::from_reader(file).unwrap(),
+            Err(err) => {
+                if path.exists() {
+                    panic!("Failed to open {:?}: {}", path, err);
+                }
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save<P>(&self, path: P)
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let progress_file =
+            std::fs::File::create(path).unwrap_or_else(|_| panic!("Failed to create {:?}", path));
+        serde_json::to_writer_pretty(progress_file, self)
+            .unwrap_or_else(|_| panic!("Failed to write to {:?}", path));
+    }
+}
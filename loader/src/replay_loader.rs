@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use windowing::event::{MouseButton, VirtualKeyCode};
+
+/// A recorded run through a single level, with one step for every fixed update.
+/// The inputs drive the actions, like picking up objects and rewinding, while the recorded
+/// player state keeps the player on the recorded path, even if the physics behave slightly differently.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LoadableReplay {
+    pub level_id: u32,
+    pub steps: Vec<ReplayStep>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReplayStep {
+    pub inputs: Vec<ReplayInput>,
+    /// Where the player was at the end of the step
+    pub position: [f32; 3],
+    /// in radians
+    pub yaw: f32,
+    /// in radians
+    pub pitch: f32,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum ReplayInput {
+    Key {
+        key_code: VirtualKeyCode,
+        pressed: bool,
+    },
+    MouseButton {
+        button: MouseButton,
+        pressed: bool,
+    },
+    MouseMovement {
+        dx: f64,
+        dy: f64,
+    },
+    MouseWheel {
+        lines: f32,
+    },
+}
+
+impl LoadableReplay {
+    pub fn new(level_id: u32) -> Self {
+        Self {
+            level_id,
+            steps: vec![],
+        }
+    }
+
+    pub fn load<P>(path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let replay_file =
+            std::fs::File::open(path).unwrap_or_else(|_| panic!("Failed to open {:?}", path));
+        serde_json::from_reader(replay_file)
+            .unwrap_or_else(|err| panic!("Failed to read {:?}: {}", path, err))
+    }
+
+    /// Replays are long, so they don't get pretty printed
+    pub fn save<P>(&self, path: P)
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)
+                .unwrap_or_else(|_| panic!("Failed to create {:?}", directory));
+        }
+        let replay_file =
+            std::fs::File::create(path).unwrap_or_else(|_| panic!("Failed to create {:?}", path));
+        serde_json::to_writer(replay_file, self)
+            .unwrap_or_else(|_| panic!("Failed to write to {:?}", path));
+    }
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+/// One line of a telemetry file, like
+/// `{"time":12.5,"event":"position","level_id":1,"position":[1.0,0.5,-3.0]}`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TelemetryRecord {
+    /// In seconds since the game was started
+    pub time: f32,
+    #[serde(flatten)]
+    pub event: TelemetryEvent,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TelemetryEvent {
+    LevelStart {
+        level_id: u32,
+    },
+    LevelFinish {
+        level_id: u32,
+        /// In seconds, including the time spent in the menus
+        duration: f32,
+    },
+    Death {
+        level_id: u32,
+        position: [f32; 3],
+        cause: DeathCause,
+    },
+    Rewind {
+        level_id: u32,
+        /// In seconds of real time
+        duration: f32,
+    },
+    Restart {
+        level_id: u32,
+    },
+    /// Where the player was, sampled every second while playing
+    Position {
+        level_id: u32,
+        position: [f32; 3],
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeathCause {
+    OutOfRewindPower,
+    FellOutOfWorld,
+}
+
+impl TelemetryRecord {
+    /// Appends the record as a single line
+    pub fn write_line(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        serde_json::to_writer(&mut *writer, self)?;
+        writeln!(writer)
+    }
+}
+
+/// Reads a telemetry file, or every `.jsonl` file in a directory to combine several playtests
+pub fn load_telemetry<P>(path: P) -> Vec<TelemetryRecord>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    if !path.is_dir() {
+        return load_telemetry_file(path);
+    }
+
+    let mut paths: Vec<_> = std::fs::read_dir(path)
+        .unwrap_or_else(|_| panic!("Failed to open {:?}", path))
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .map_or(false, |extension| extension == "jsonl")
+        })
+        .collect();
+    paths.sort();
+    paths
+        .iter()
+        .flat_map(|path| load_telemetry_file(path))
+        .collect()
+}
+
+fn load_telemetry_file(path: &Path) -> Vec<TelemetryRecord> {
+    let telemetry_file =
+        std::fs::File::open(path).unwrap_or_else(|_| panic!("Failed to open {:?}", path));
+    BufReader::new(telemetry_file)
+        .lines()
+        .map(|line| line.unwrap_or_else(|err| panic!("Failed to read {:?}: {}", path, err)))
+        // The game might have been closed in the middle of writing the last line
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect()
+}
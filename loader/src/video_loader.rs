@@ -0,0 +1,64 @@
+use image::GenericImageView;
+use scene::asset::AssetId;
+use scene::texture::{
+    AddressMode, BytesTextureData, CpuTexture, Filter, MipmapMode, SamplerInfo, TextureFormat,
+};
+use scene::video_texture::VideoTexture;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::sync_channel;
+use std::sync::Arc;
+
+/// How many frames get decoded ahead of time
+const BUFFERED_FRAMES: usize = 4;
+
+/// The PNG files in the directory, in the order of their names, like `frame0001.png`
+pub fn image_sequence_frames<P>(directory: P) -> std::io::Result<Vec<PathBuf>>
+where
+    P: AsRef<Path>,
+{
+    let mut frames: Vec<PathBuf> = std::fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .map_or(false, |extension| extension == "png")
+        })
+        .collect();
+    frames.sort();
+    Ok(frames)
+}
+
+/// Plays the frames over and over. They get decoded on another thread, since decoding them
+/// all at once would take too long and too much memory.
+pub fn load_image_sequence(frames: Vec<PathBuf>, fps: f32) -> VideoTexture {
+    let (sender, receiver) = sync_channel(BUFFERED_FRAMES);
+    std::thread::spawn(move || {
+        for path in frames.iter().cycle() {
+            let image = match image::open(path) {
+                Ok(image) => image,
+                Err(err) => {
+                    println!("Failed to decode video frame {:?}: {}", path, err);
+                    return;
+                }
+            };
+            let frame = Arc::new(CpuTexture {
+                id: AssetId::new_v4(),
+                data: Box::new(BytesTextureData::new(
+                    image.dimensions(),
+                    TextureFormat::R8G8B8A8_UNORM,
+                    image.to_rgba8().into_raw(),
+                )),
+                sampler_info: SamplerInfo {
+                    min_filter: Filter::Linear,
+                    mag_filter: Filter::Linear,
+                    mipmap_mode: MipmapMode::Nearest,
+                    address_mode: [AddressMode::ClampToEdge; 3],
+                },
+            });
+            // The video texture has been despawned
+            if sender.send(frame).is_err() {
+                return;
+            }
+        }
+    });
+    VideoTexture::new(receiver, fps)
+}
@@ -1,4 +1,4 @@
-use nalgebra::Vector3;
+use nalgebra::{Matrix4, Point3, Vector3};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct BoundingBox<T> {
@@ -46,4 +46,30 @@ impl BoundingBox<Vector3<f32>> {
         let radius = (self.max - self.min).norm() / 2.0;
         (center, radius)
     }
+
+    /// The axis aligned box around the transformed corners
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> BoundingBox<Vector3<f32>> {
+        let corners = (0..8).map(|index: usize| {
+            Vector3::from_fn(|axis, _| {
+                if index & (1 << axis) == 0 {
+                    self.min[axis]
+                } else {
+                    self.max[axis]
+                }
+            })
+        });
+        corners.fold(BoundingBox::empty(), |bounds, corner| {
+            let corner = matrix.transform_point(&Point3::from(corner)).coords;
+            BoundingBox {
+                min: bounds.min.inf(&corner),
+                max: bounds.max.sup(&corner),
+            }
+        })
+    }
+
+    /// Zero for points inside of the box
+    pub fn distance_squared(&self, point: &Vector3<f32>) -> f32 {
+        let closest_point = point.sup(&self.min).inf(&self.max);
+        (point - closest_point).norm_squared()
+    }
 }
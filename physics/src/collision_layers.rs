@@ -0,0 +1,146 @@
+use bevy_ecs::{
+    prelude::{Added, Component, Or},
+    query::Changed,
+    system::{Query, ResMut},
+};
+use rapier3d::prelude::{Group, InteractionGroups};
+use scene::pickup::Pickupable;
+
+use crate::physics_context::{PhysicsContext, RapierColliderHandle, RapierRigidBodyHandle};
+use crate::player_physics::PlayerCharacterController;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionLayer {
+    Player,
+    Pickup,
+    /// The sensors, like flag triggers and gravity zones
+    Trigger,
+    /// The level, and everything else that isn't on another layer
+    Static,
+    /// Only on this layer when it has been set, for example for the shards of a broken object
+    Debris,
+}
+
+impl CollisionLayer {
+    pub const ALL: [CollisionLayer; 5] = [
+        CollisionLayer::Player,
+        CollisionLayer::Pickup,
+        CollisionLayer::Trigger,
+        CollisionLayer::Static,
+        CollisionLayer::Debris,
+    ];
+
+    fn group(&self) -> Group {
+        match self {
+            CollisionLayer::Player => Group::GROUP_1,
+            CollisionLayer::Pickup => Group::GROUP_2,
+            CollisionLayer::Trigger => Group::GROUP_3,
+            CollisionLayer::Static => Group::GROUP_4,
+            CollisionLayer::Debris => Group::GROUP_5,
+        }
+    }
+}
+
+fn to_group(layers: &[CollisionLayer]) -> Group {
+    layers
+        .iter()
+        .fold(Group::NONE, |group, layer| group | layer.group())
+}
+
+/// Which layers a collider is on, and which layers it collides with.
+/// Two colliders only collide, or trigger each other, when both of them collide with the layer of the other one.
+/// Colliders without this component are on their default layer, and collide with everything.
+#[derive(Component, Debug, Clone)]
+pub struct CollisionLayers {
+    /// None puts the collider on its default layer, which depends on what it is
+    pub memberships: Option<Vec<CollisionLayer>>,
+    pub filter: Vec<CollisionLayer>,
+}
+
+impl CollisionLayers {
+    pub fn new() -> Self {
+        Self {
+            memberships: None,
+            filter: CollisionLayer::ALL.to_vec(),
+        }
+    }
+
+    pub fn with_memberships(mut self, memberships: Vec<CollisionLayer>) -> Self {
+        self.memberships = Some(memberships);
+        self
+    }
+
+    pub fn with_filter(mut self, filter: Vec<CollisionLayer>) -> Self {
+        self.filter = filter;
+        self
+    }
+}
+
+impl Default for CollisionLayers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Runs after the sensors have been set up, so that they end up on the trigger layer
+pub(super) fn apply_collision_layers(
+    mut physics_context: ResMut<PhysicsContext>,
+    query: Query<
+        (
+            Option<&CollisionLayers>,
+            Option<&RapierColliderHandle>,
+            Option<&RapierRigidBodyHandle>,
+            Option<&PlayerCharacterController>,
+            Option<&Pickupable>,
+        ),
+        Or<(
+            Changed<CollisionLayers>,
+            Added<RapierColliderHandle>,
+            Added<RapierRigidBodyHandle>,
+        )>,
+    >,
+) {
+    let context = physics_context.as_mut();
+
+    for (collision_layers, collider_handle, rigid_body_handle, player, pickupable) in query.iter() {
+        let collider_handles = match (collider_handle, rigid_body_handle) {
+            (Some(collider_handle), _) => vec![collider_handle.handle],
+            (None, Some(rigid_body_handle)) => {
+                match context.rigid_bodies.get(rigid_body_handle.handle) {
+                    Some(rigid_body) => rigid_body.colliders().to_vec(),
+                    None => continue,
+                }
+            }
+            (None, None) => continue,
+        };
+
+        for collider_handle in collider_handles {
+            let collider = match context.colliders.get_mut(collider_handle) {
+                Some(collider) => collider,
+                None => continue,
+            };
+
+            let default_layer = if player.is_some() {
+                CollisionLayer::Player
+            } else if pickupable.is_some() {
+                CollisionLayer::Pickup
+            } else if collider.is_sensor() {
+                CollisionLayer::Trigger
+            } else {
+                CollisionLayer::Static
+            };
+            let (memberships, filter) = match collision_layers {
+                Some(CollisionLayers {
+                    memberships: Some(memberships),
+                    filter,
+                }) => (to_group(memberships), to_group(filter)),
+                Some(CollisionLayers {
+                    memberships: None,
+                    filter,
+                }) => (default_layer.group(), to_group(filter)),
+                None => (default_layer.group(), Group::ALL),
+            };
+            collider.set_collision_groups(InteractionGroups::new(memberships, filter));
+        }
+    }
+}
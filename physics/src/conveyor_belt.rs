@@ -0,0 +1,94 @@
+use bevy_ecs::prelude::{Component, Query, Res, ResMut};
+use nalgebra::{Vector2, Vector3};
+use scene::material::TextureScroll;
+use time::time_manager::TimeManager;
+
+use crate::physics_context::PhysicsContext;
+use crate::physics_events::collider2entity;
+
+/// How quickly the bodies on a belt take on its speed, from 0 to 1 per step
+const BELT_GRIP: f32 = 0.2;
+
+/// Moves everything that stands on it
+#[derive(Component, Debug, Clone)]
+pub struct ConveyorBelt {
+    /// In world space
+    pub direction: Vector3<f32>,
+    /// In meters per second
+    pub speed: f32,
+    /// Which way the texture scrolls, one unit of the texture per meter that the belt moves
+    pub texture_direction: Vector2<f32>,
+}
+
+impl ConveyorBelt {
+    pub fn new(direction: Vector3<f32>, speed: f32) -> Self {
+        Self {
+            direction: direction.normalize(),
+            speed,
+            texture_direction: Vector2::new(1.0, 0.0),
+        }
+    }
+
+    pub fn velocity(&self) -> Vector3<f32> {
+        self.direction * self.speed
+    }
+}
+
+/// Pushes the dynamic bodies that touch a belt along it
+pub(super) fn apply_conveyor_belts(
+    mut physics_context: ResMut<PhysicsContext>,
+    belts: Query<&ConveyorBelt>,
+) {
+    let context = physics_context.as_mut();
+
+    let mut pushed_bodies = vec![];
+    for contact_pair in context.narrow_phase.contact_pairs() {
+        if !contact_pair.has_any_active_contact {
+            continue;
+        }
+
+        let colliders = [
+            (contact_pair.collider1, contact_pair.collider2),
+            (contact_pair.collider2, contact_pair.collider1),
+        ];
+        for (belt_collider, other_collider) in colliders {
+            let belt = match belts.get(collider2entity(&context.colliders, belt_collider)) {
+                Ok(belt) => belt,
+                Err(_) => continue,
+            };
+            let body_handle = match context
+                .colliders
+                .get(other_collider)
+                .and_then(|collider| collider.parent())
+            {
+                Some(body_handle) => body_handle,
+                None => continue,
+            };
+            pushed_bodies.push((body_handle, belt.direction, belt.speed));
+        }
+    }
+
+    for (body_handle, direction, speed) in pushed_bodies {
+        let rigid_body = match context.rigid_bodies.get_mut(body_handle) {
+            Some(rigid_body) if rigid_body.is_dynamic() => rigid_body,
+            _ => continue,
+        };
+        let velocity = *rigid_body.linvel();
+        let missing_speed = speed - velocity.dot(&direction);
+        if missing_speed > 0.0 {
+            rigid_body.set_linvel(velocity + direction * missing_speed * BELT_GRIP, true);
+        }
+    }
+}
+
+/// Only depends on the level time, so the belts also scroll backwards when rewinding
+pub(super) fn scroll_conveyor_belt_textures(
+    time_manager: Res<TimeManager>,
+    mut query: Query<(&ConveyorBelt, &mut TextureScroll)>,
+) {
+    let level_time = time_manager.level_time().as_secs_f32();
+    for (belt, mut texture_scroll) in query.iter_mut() {
+        let distance = (belt.speed * level_time).fract();
+        texture_scroll.offset = belt.texture_direction * distance;
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+use bevy_ecs::{
+    prelude::{Added, Commands, Component, Entity, Or, With},
+    query::Without,
+    system::{Query, Res, ResMut},
+};
+use nalgebra::Vector2;
+use rapier3d::prelude::{Aabb, RigidBodyHandle};
+use scene::material::TextureScroll;
+use time::{time::Time, time_manager::TimeManager};
+
+use crate::physics_context::{PhysicsContext, RapierRigidBodyHandle, RigidBody};
+use crate::physics_events::collider2entity;
+use crate::pickup_physics::PickedUp;
+use crate::player_physics::PlayerCharacterController;
+
+/// How fast the texture of a fluid surface flows, in texture units per second
+const SURFACE_FLOW: [f32; 2] = [0.05, 0.02];
+
+/// A trigger volume filled with water, or with a force field,
+/// that slows everything inside of it down and pushes it up towards its surface
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct FluidVolume {
+    /// 1 cancels out gravity for a body that is completely below the surface, larger values make it float
+    pub buoyancy: f32,
+    /// How much of its velocity a body loses per second
+    pub drag: f32,
+}
+
+/// The fluid volume that a body is in. Gets recomputed from the physics world after every step,
+/// so it doesn't need to be tracked for rewinding.
+#[derive(Component, Debug, Clone, Default, PartialEq)]
+pub struct InFluid {
+    pub fluid: Option<FluidVolume>,
+    /// Between 0 and 1, how much of the body is below the surface of the fluid
+    pub submerged: f32,
+}
+
+impl InFluid {
+    pub fn buoyancy(&self) -> f32 {
+        self.fluid
+            .map_or(0.0, |fluid| fluid.buoyancy * self.submerged)
+    }
+
+    /// What the velocity gets multiplied with during one step
+    pub fn drag_factor(&self, delta_seconds: f32) -> f32 {
+        let drag = self.fluid.map_or(0.0, |fluid| fluid.drag * self.submerged);
+        (1.0 - drag * delta_seconds).max(0.0)
+    }
+}
+
+pub(super) fn add_in_fluid(
+    mut commands: Commands,
+    query: Query<
+        Entity,
+        (
+            Or<(Added<RigidBody>, Added<PlayerCharacterController>)>,
+            Without<InFluid>,
+        ),
+    >,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).insert(InFluid::default());
+    }
+}
+
+/// A body that is in several fluids at once is in the one that it is deepest in
+pub(super) fn update_in_fluid(
+    physics_context: Res<PhysicsContext>,
+    fluids: Query<&FluidVolume>,
+    mut bodies: Query<(&RapierRigidBodyHandle, &mut InFluid)>,
+) {
+    let context = physics_context.as_ref();
+
+    let mut submerged_bodies: HashMap<RigidBodyHandle, InFluid> = HashMap::new();
+    for (collider1, collider2, intersecting) in context.narrow_phase.intersection_pairs() {
+        if !intersecting {
+            continue;
+        }
+
+        for (fluid_collider, other_collider) in [(collider1, collider2), (collider2, collider1)] {
+            let fluid = match fluids.get(collider2entity(&context.colliders, fluid_collider)) {
+                Ok(fluid) => fluid,
+                Err(_) => continue,
+            };
+            let (fluid_collider, other_collider) = match (
+                context.colliders.get(fluid_collider),
+                context.colliders.get(other_collider),
+            ) {
+                (Some(fluid_collider), Some(other_collider)) => (fluid_collider, other_collider),
+                _ => continue,
+            };
+            let body_handle = match other_collider.parent() {
+                Some(body_handle) => body_handle,
+                None => continue,
+            };
+
+            let submerged = submerged_fraction(
+                &fluid_collider.compute_aabb(),
+                &other_collider.compute_aabb(),
+            );
+            let in_fluid = submerged_bodies.entry(body_handle).or_default();
+            if in_fluid.fluid.is_none() || submerged > in_fluid.submerged {
+                *in_fluid = InFluid {
+                    fluid: Some(*fluid),
+                    submerged,
+                };
+            }
+        }
+    }
+
+    for (rigid_body_handle, mut in_fluid) in bodies.iter_mut() {
+        let new_in_fluid = submerged_bodies
+            .remove(&rigid_body_handle.handle)
+            .unwrap_or_default();
+        if *in_fluid != new_in_fluid {
+            *in_fluid = new_in_fluid;
+        }
+    }
+}
+
+/// The surface is the top of the fluid volume
+fn submerged_fraction(fluid: &Aabb, body: &Aabb) -> f32 {
+    let height = body.maxs.y - body.mins.y;
+    if height <= 0.0 {
+        return 1.0;
+    }
+    ((fluid.maxs.y - body.mins.y) / height).clamp(0.0, 1.0)
+}
+
+/// Pushes the dynamic bodies inside of a fluid against gravity, and slows them down.
+/// The player moves on its own, see the player controller.
+pub(super) fn apply_fluid_volumes(
+    mut physics_context: ResMut<PhysicsContext>,
+    time: Res<Time>,
+    query: Query<(&RapierRigidBodyHandle, &InFluid), Without<PickedUp>>,
+) {
+    let delta_seconds = time.delta_seconds();
+    let gravity = physics_context.gravity;
+
+    for (rigid_body_handle, in_fluid) in query.iter() {
+        if in_fluid.fluid.is_none() {
+            continue;
+        }
+        let rigid_body = match physics_context
+            .rigid_bodies
+            .get_mut(rigid_body_handle.handle)
+        {
+            Some(rigid_body) if rigid_body.is_dynamic() => rigid_body,
+            _ => continue,
+        };
+
+        // Upside down gravity also turns the buoyancy upside down
+        let buoyancy = -gravity
+            * rigid_body.gravity_scale()
+            * rigid_body.mass()
+            * in_fluid.buoyancy()
+            * delta_seconds;
+        rigid_body.apply_impulse(buoyancy, true);
+
+        let drag_factor = in_fluid.drag_factor(delta_seconds);
+        rigid_body.set_linvel(rigid_body.linvel() * drag_factor, true);
+        rigid_body.set_angvel(rigid_body.angvel() * drag_factor, true);
+    }
+}
+
+/// Only depends on the level time, so the surfaces also flow backwards when rewinding
+pub(super) fn scroll_fluid_surfaces(
+    time_manager: Res<TimeManager>,
+    mut query: Query<&mut TextureScroll, With<FluidVolume>>,
+) {
+    let level_time = time_manager.level_time().as_secs_f32();
+    for mut texture_scroll in query.iter_mut() {
+        texture_scroll.offset = (Vector2::from(SURFACE_FLOW) * level_time).map(f32::fract);
+    }
+}
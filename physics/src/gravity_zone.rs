@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use app::entity_event::EntityEvent;
+use bevy_ecs::{
+    prelude::{Added, Commands, Component, Entity, EventReader, Or},
+    query::{Changed, Without},
+    system::{Query, Res, ResMut},
+    world::Mut,
+};
+use levels::{
+    current_level::{CurrentLevel, NextLevel},
+    level_id::LevelId,
+};
+use nalgebra::{UnitVector3, Vector3};
+use time::time_manager::{
+    game_change::{GameChange, GameChangeHistory},
+    TimeManager,
+};
+
+use crate::physics_context::{PhysicsContext, RapierRigidBodyHandle, RigidBody};
+use crate::physics_events::{collider2entity, CollisionEvent};
+use crate::pickup_physics::PickedUp;
+use crate::player_physics::PlayerCharacterController;
+
+/// A trigger volume that changes the gravity of everything inside of it
+#[derive(Component, Debug, Clone)]
+pub struct GravityZone {
+    /// Negative values turn gravity upside down
+    pub gravity_scale: f32,
+}
+
+/// A trigger volume along a wall or a ceiling, where the player stands in its up direction.
+/// Usually a thin box in front of the surface that the player walks on.
+#[derive(Component, Debug, Clone)]
+pub struct GravityBootZone {
+    pub up: UnitVector3<f32>,
+}
+
+/// How strongly gravity pulls on a body, gets changed by the gravity zones
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct GravityScale(pub f32);
+
+impl Default for GravityScale {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+impl GravityScale {
+    pub fn is_inverted(&self) -> bool {
+        self.0 < 0.0
+    }
+}
+
+pub(super) fn add_gravity_scales(
+    mut commands: Commands,
+    query: Query<
+        Entity,
+        (
+            Or<(Added<RigidBody>, Added<PlayerCharacterController>)>,
+            Without<GravityScale>,
+        ),
+    >,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).insert(GravityScale::default());
+    }
+}
+
+/// Bodies that enter a zone take on its gravity, and get the normal gravity back when they leave
+pub(super) fn apply_gravity_zones(
+    zones: Query<(&GravityZone, &EntityEvent<CollisionEvent>)>,
+    mut gravity_scales: Query<&mut GravityScale>,
+) {
+    for (zone, collision_events) in zones.iter() {
+        for collision_event in collision_events.iter() {
+            let (entity, gravity_scale) = match collision_event {
+                CollisionEvent::Started(entity) => (entity, GravityScale(zone.gravity_scale)),
+                CollisionEvent::Stopped(entity) => (entity, GravityScale::default()),
+            };
+            if let Ok(mut current_gravity_scale) = gravity_scales.get_mut(*entity) {
+                if *current_gravity_scale != gravity_scale {
+                    *current_gravity_scale = gravity_scale;
+                }
+            }
+        }
+    }
+}
+
+/// The player stands in the direction of the gravity boot zone that they are in, and upright outside of them.
+/// Gets recomputed from the physics world after every step, so it doesn't need to be tracked for rewinding.
+pub(super) fn apply_gravity_boot_zones(
+    physics_context: Res<PhysicsContext>,
+    zones: Query<&GravityBootZone>,
+    mut players: Query<(&RapierRigidBodyHandle, &mut PlayerCharacterController)>,
+) {
+    let context = physics_context.as_ref();
+
+    for (rigid_body_handle, mut character_controller) in players.iter_mut() {
+        let collider_handle = match context.rigid_bodies.get(rigid_body_handle.handle) {
+            Some(rigid_body) => rigid_body.colliders()[0],
+            None => continue,
+        };
+
+        let up = context
+            .narrow_phase
+            .intersection_pairs_with(collider_handle)
+            .filter(|(_, _, intersecting)| *intersecting)
+            .find_map(|(collider1, collider2, _)| {
+                let other_collider = if collider1 == collider_handle {
+                    collider2
+                } else {
+                    collider1
+                };
+                zones
+                    .get(collider2entity(&context.colliders, other_collider))
+                    .ok()
+            })
+            .map_or_else(Vector3::y_axis, |zone| zone.up);
+
+        if character_controller.up != up {
+            character_controller.up = up;
+        }
+    }
+}
+
+pub(super) fn apply_gravity_scale_changes(
+    mut physics_context: ResMut<PhysicsContext>,
+    query: Query<
+        (&GravityScale, &RapierRigidBodyHandle),
+        (Changed<GravityScale>, Without<PickedUp>),
+    >,
+) {
+    for (gravity_scale, rigid_body_handle) in query.iter() {
+        let rigid_body = physics_context
+            .rigid_bodies
+            .get_mut(rigid_body_handle.handle)
+            .expect("Rigid body not found");
+        rigid_body.set_gravity_scale(gravity_scale.0, true);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct GravityScaleChange {
+    entity: Entity,
+    gravity_scale: GravityScale,
+}
+
+impl GameChange for GravityScaleChange {}
+
+pub(super) fn time_manager_track_gravity_scale(
+    mut history: ResMut<GameChangeHistory<GravityScaleChange>>,
+    current_level: Res<CurrentLevel>,
+    query: Query<(Entity, &GravityScale, &LevelId), Changed<GravityScale>>,
+) {
+    for (entity, gravity_scale, level_id) in &query {
+        if level_id != &current_level.level_id {
+            continue;
+        }
+        history.add_command(GravityScaleChange {
+            entity,
+            gravity_scale: *gravity_scale,
+        });
+    }
+}
+
+pub(super) fn time_manager_start_track_gravity_scale(
+    mut next_level_events: EventReader<NextLevel>,
+    mut history: ResMut<GameChangeHistory<GravityScaleChange>>,
+    query: Query<(Entity, &GravityScale, &LevelId)>,
+) {
+    for next_level_event in next_level_events.iter() {
+        for (entity, gravity_scale, level_id) in &query {
+            if level_id != &next_level_event.level_id {
+                continue;
+            }
+            history.add_command(GravityScaleChange {
+                entity,
+                gravity_scale: *gravity_scale,
+            });
+        }
+    }
+}
+
+pub(super) fn time_manager_rewind_gravity_scale(
+    time_manager: Res<TimeManager>,
+    mut history: ResMut<GameChangeHistory<GravityScaleChange>>,
+    mut query: Query<(Entity, &mut GravityScale)>,
+) {
+    let mut entities: HashMap<_, Mut<GravityScale>> = query.iter_mut().collect();
+
+    let commands = history.take_commands_to_apply(&time_manager);
+
+    for command_collection in commands {
+        for command in command_collection.commands {
+            if let Some(gravity_scale) = entities.get_mut(&command.entity) {
+                if **gravity_scale != command.gravity_scale {
+                    **gravity_scale = command.gravity_scale;
+                }
+            }
+        }
+    }
+}
@@ -1,5 +1,10 @@
+pub mod collision_layers;
+pub mod conveyor_belt;
+pub mod fluid_volume;
+pub mod gravity_zone;
 pub mod physics_change;
 pub mod physics_context;
+pub mod physics_debug;
 pub mod physics_events;
 pub mod pickup_physics;
 pub mod player_physics;
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use bevy_ecs::{
-    prelude::EventReader,
+    prelude::{Added, Entity, EventReader, RemovedComponents},
     query::{Changed, Without},
     system::{Query, Res, ResMut, Resource},
     world::Mut,
@@ -10,15 +10,15 @@ use levels::{
     current_level::{CurrentLevel, NextLevel},
     level_id::LevelId,
 };
-use rapier3d::prelude::RigidBodyType;
+use rapier3d::prelude::{RigidBodyType, Vector};
 
 use scene::pickup::Pickupable;
 use time::time_manager::{
     game_change::{GameChange, GameChangeHistory},
-    TimeManager, TimeState, TimeTracked, TimeTrackedId,
+    RewindTarget, TimeManager, TimeState, TimeTracked, TimeTrackedId,
 };
 
-use super::physics_context::RigidBody;
+use super::physics_context::{PhysicsContext, RapierRigidBodyHandle, RigidBody};
 
 #[derive(Debug, Clone)]
 pub(super) struct RigidBodyTypeChange {
@@ -40,7 +40,10 @@ impl GameChange for RigidBodyTypeChange {}
 pub(super) fn time_manager_track_rigid_body_type(
     mut history: ResMut<GameChangeHistory<RigidBodyTypeChange>>,
     current_level: Res<CurrentLevel>,
-    query: Query<(&TimeTracked, &RigidBody, &LevelId), (Changed<RigidBody>, Without<Pickupable>)>,
+    query: Query<
+        (&TimeTracked, &RigidBody, &LevelId),
+        (Changed<RigidBody>, Without<Pickupable>, Without<RewindTarget>),
+    >,
 ) {
     for (time_tracked, rigidbody, level_id) in &query {
         if level_id != &current_level.level_id {
@@ -68,6 +71,44 @@ pub(super) fn time_manager_start_track_rigid_body_type(
 #[derive(Resource, Default)]
 pub(super) struct RigidBodyTypes {
     pub previous_types: HashMap<TimeTrackedId, RigidBodyType>,
+    pub previous_target_types: HashMap<Entity, RigidBodyType>,
+}
+
+/// Rewind targets get moved by the time rewinding instead of the physics
+pub(super) fn start_rewind_target(
+    mut query: Query<(Entity, &mut RigidBody), Added<RewindTarget>>,
+    mut previous_types: ResMut<RigidBodyTypes>,
+) {
+    for (entity, mut rigidbody) in query.iter_mut() {
+        previous_types
+            .previous_target_types
+            .insert(entity, rigidbody.0);
+        rigidbody.0 = RigidBodyType::KinematicPositionBased;
+    }
+}
+
+pub(super) fn stop_rewind_target(
+    mut removals: RemovedComponents<RewindTarget>,
+    mut physics_context: ResMut<PhysicsContext>,
+    mut query: Query<(&mut RigidBody, &RapierRigidBodyHandle)>,
+    mut previous_types: ResMut<RigidBodyTypes>,
+) {
+    for entity in &mut removals {
+        let previous_type = previous_types.previous_target_types.remove(&entity);
+        if let (Ok((mut rigidbody, body_handle)), Some(previous_type)) =
+            (query.get_mut(entity), previous_type)
+        {
+            rigidbody.0 = previous_type;
+
+            // Otherwise it would keep flying in the direction that it got rewound in
+            let rigid_body = physics_context
+                .rigid_bodies
+                .get_mut(body_handle.handle)
+                .expect("Rigid body not found");
+            rigid_body.set_linvel(Vector::zeros(), true);
+            rigid_body.set_angvel(Vector::zeros(), true);
+        }
+    }
 }
 
 // Doesn't handle RigidBodies that have been inserted at runtime
@@ -2,10 +2,12 @@ use app::entity_event::EntityEvent;
 use levels::current_level::ResetLevel;
 use levels::level_id::LevelId;
 use scene::level::NextLevelTrigger;
+use scene::teleporter::Teleporter;
 use time::time::Time;
 
 use bevy_ecs::prelude::{
-    Added, Commands, Component, Entity, EventReader, Query, Res, ResMut, Resource, With,
+    Added, Commands, Component, Entity, EventReader, Query, RemovedComponents, Res, ResMut,
+    Resource, With,
 };
 use bevy_ecs::query::{Changed, Or, Without};
 
@@ -15,10 +17,14 @@ use rapier3d::na::Vector3;
 pub use rapier3d::prelude::QueryFilter;
 pub use rapier3d::prelude::Ray;
 use rapier3d::prelude::*;
+use scene::mesh::CpuMesh;
 use scene::transform::{Transform, TransformBuilder};
+use std::collections::HashSet;
 
 use super::player_physics::PlayerCharacterController;
 
+use crate::fluid_volume::FluidVolume;
+use crate::gravity_zone::{GravityBootZone, GravityZone};
 use crate::physics_events::{collider2entity, handle_collision_event, CollisionEvent};
 use crate::pickup_physics::PickedUp;
 pub use rapier3d::prelude::RigidBodyType;
@@ -165,33 +171,193 @@ pub(crate) fn step_physics_simulation(
 
 #[derive(Component)]
 pub struct RapierRigidBodyHandle {
-    pub(crate) handle: RigidBodyHandle,
+    pub handle: RigidBodyHandle,
 }
 
 #[derive(Component)]
 pub(crate) struct RapierColliderHandle {
-    handle: ColliderHandle,
+    pub(crate) handle: ColliderHandle,
 }
 
 #[derive(Component)]
 pub struct RigidBody(pub RigidBodyType);
 
+/// Replaces the mass that a rigid body gets from the size of its collider, in kilograms
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Mass(pub f32);
+
 // for now colliders are created once and never changed or deleted
 #[derive(Component, Clone)]
 pub struct BoxCollider {
     pub bounds: BoundingBox<Vector3<f32>>,
 }
 
-fn create_box_collider(
-    entity: &Entity,
-    box_collider: &BoxCollider,
-    transform: &Transform,
-) -> Collider {
-    let scaled_bounds = box_collider.bounds.scale(&transform.scale);
-    let half_size: Vector3<f32> = scaled_bounds.size() * 0.5;
-    let collider_offset = scaled_bounds.min + half_size;
+#[derive(Component, Clone)]
+pub struct SphereCollider {
+    /// in object space
+    pub center: Vector3<f32>,
+    pub radius: f32,
+}
+
+/// A capsule that is aligned with the y axis
+#[derive(Component, Clone)]
+pub struct CapsuleCollider {
+    /// in object space
+    pub center: Vector3<f32>,
+    /// Half of the height of the cylindrical part, without the two half-spheres
+    pub half_height: f32,
+    pub radius: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MeshColliderKind {
+    /// Wraps the mesh in a convex hull, works well for dynamic rigid bodies
+    ConvexHull,
+    /// Uses the triangles as they are, meant for static level geometry like ramps
+    TriMesh,
+}
+
+#[derive(Component, Clone)]
+pub struct MeshCollider {
+    /// in object space
+    pub vertices: Vec<Point<Real>>,
+    pub indices: Vec<[u32; 3]>,
+    pub kind: MeshColliderKind,
+}
+
+impl MeshCollider {
+    pub fn from_meshes<'a>(
+        meshes: impl Iterator<Item = &'a CpuMesh>,
+        kind: MeshColliderKind,
+    ) -> Self {
+        let mut vertices = vec![];
+        let mut indices = vec![];
+        for mesh in meshes {
+            let index_offset = vertices.len() as u32;
+            vertices.extend(
+                mesh.vertices
+                    .iter()
+                    .map(|vertex| Point::from(vertex.position)),
+            );
+            indices.extend(mesh.indices.chunks_exact(3).map(|triangle| {
+                [
+                    triangle[0] + index_offset,
+                    triangle[1] + index_offset,
+                    triangle[2] + index_offset,
+                ]
+            }));
+        }
+
+        Self {
+            vertices,
+            indices,
+            kind,
+        }
+    }
+
+    /// Whether rapier can create a collider from this, a convex hull for example needs a few points that aren't flat
+    pub fn is_valid(&self) -> bool {
+        match self.kind {
+            MeshColliderKind::ConvexHull => SharedShape::convex_hull(&self.vertices).is_some(),
+            MeshColliderKind::TriMesh => !self.indices.is_empty(),
+        }
+    }
+}
+
+/// One cuboid per part, useful for models that consist of multiple primitives, like L-shaped rooms
+#[derive(Component, Clone)]
+pub struct CompoundCollider {
+    /// in object space
+    pub parts: Vec<BoundingBox<Vector3<f32>>>,
+}
 
-    ColliderBuilder::cuboid(half_size.x, half_size.y, half_size.z)
+/// All the collider shapes that an entity can have. Only the first one that exists will be used.
+type ColliderShapes<'a> = (
+    Option<&'a BoxCollider>,
+    Option<&'a SphereCollider>,
+    Option<&'a CapsuleCollider>,
+    Option<&'a MeshCollider>,
+    Option<&'a CompoundCollider>,
+);
+
+type ColliderShapesAdded = Or<(
+    Added<BoxCollider>,
+    Added<SphereCollider>,
+    Added<CapsuleCollider>,
+    Added<MeshCollider>,
+    Added<CompoundCollider>,
+)>;
+
+/// Returns the collider and its offset, both already scaled
+fn create_collider_shape(
+    shapes: ColliderShapes,
+    scale: &Vector3<f32>,
+) -> Option<(ColliderBuilder, Vector3<f32>)> {
+    match shapes {
+        (Some(box_collider), _, _, _, _) => {
+            let scaled_bounds = box_collider.bounds.scale(scale);
+            let half_size: Vector3<f32> = scaled_bounds.size() * 0.5;
+            let collider_offset = scaled_bounds.min + half_size;
+
+            Some((
+                ColliderBuilder::cuboid(half_size.x, half_size.y, half_size.z),
+                collider_offset,
+            ))
+        }
+        (_, Some(sphere_collider), _, _, _) => Some((
+            ColliderBuilder::ball(sphere_collider.radius * scale.max()),
+            sphere_collider.center.component_mul(scale),
+        )),
+        (_, _, Some(capsule_collider), _, _) => Some((
+            ColliderBuilder::capsule_y(
+                capsule_collider.half_height * scale.y,
+                capsule_collider.radius * scale.x.max(scale.z),
+            ),
+            capsule_collider.center.component_mul(scale),
+        )),
+        (_, _, _, Some(mesh_collider), _) => {
+            let vertices: Vec<Point<Real>> = mesh_collider
+                .vertices
+                .iter()
+                .map(|vertex| Point::from(vertex.coords.component_mul(scale)))
+                .collect();
+
+            let collider = match mesh_collider.kind {
+                MeshColliderKind::ConvexHull => ColliderBuilder::convex_hull(&vertices)
+                    .expect("The loader checks that the mesh collider is valid"),
+                MeshColliderKind::TriMesh => {
+                    ColliderBuilder::trimesh(vertices, mesh_collider.indices.clone())
+                }
+            };
+
+            Some((collider, Vector3::zeros()))
+        }
+        (_, _, _, _, Some(compound_collider)) => {
+            let shapes = compound_collider
+                .parts
+                .iter()
+                .map(|part| {
+                    let scaled_bounds = part.scale(scale);
+                    let half_size: Vector3<f32> = scaled_bounds.size() * 0.5;
+                    let part_offset = scaled_bounds.min + half_size;
+                    (
+                        Isometry::translation(part_offset.x, part_offset.y, part_offset.z),
+                        SharedShape::cuboid(half_size.x, half_size.y, half_size.z),
+                    )
+                })
+                .collect();
+
+            Some((ColliderBuilder::compound(shapes), Vector3::zeros()))
+        }
+        (None, None, None, None, None) => None,
+    }
+}
+
+fn create_collider(entity: &Entity, shapes: ColliderShapes, transform: &Transform) -> Collider {
+    let (collider, collider_offset) = create_collider_shape(shapes, &transform.scale)
+        .expect("The loader checks that every rigid body has a collider");
+
+    collider
         .position(
             transform.to_isometry()
                 * Isometry::translation(collider_offset.x, collider_offset.y, collider_offset.z),
@@ -204,13 +370,13 @@ fn create_box_collider(
 pub(crate) fn apply_collider_changes(
     mut commands: Commands,
     mut physics_context: ResMut<PhysicsContext>,
-    box_collider_query: Query<
-        (Entity, &BoxCollider, &Transform),
-        (Added<BoxCollider>, Without<RigidBody>),
+    collider_query: Query<
+        (Entity, ColliderShapes, &Transform),
+        (ColliderShapesAdded, Without<RigidBody>),
     >,
 ) {
-    for (entity, collider, transform) in &box_collider_query {
-        let physics_collider = create_box_collider(&entity, collider, transform);
+    for (entity, shapes, transform) in &collider_query {
+        let physics_collider = create_collider(&entity, shapes, transform);
         let handle = physics_context.colliders.insert(physics_collider);
         commands
             .entity(entity)
@@ -218,15 +384,73 @@ pub(crate) fn apply_collider_changes(
     }
 }
 
+/// Removes the physics objects of despawned entities, for example when a level gets reloaded
+pub(crate) fn apply_despawned_entities(
+    mut physics_context: ResMut<PhysicsContext>,
+    mut collider_removals: RemovedComponents<RapierColliderHandle>,
+    mut rigid_body_removals: RemovedComponents<RapierRigidBodyHandle>,
+) {
+    let removed_entities: HashSet<Entity> = collider_removals
+        .iter()
+        .chain(rigid_body_removals.iter())
+        .collect();
+    if removed_entities.is_empty() {
+        return;
+    }
+
+    let context = physics_context.as_mut();
+    let removed_colliders: Vec<_> = context
+        .colliders
+        .iter()
+        .filter(|(handle, _)| {
+            removed_entities.contains(&collider2entity(&context.colliders, *handle))
+        })
+        .map(|(handle, collider)| (handle, collider.parent()))
+        .collect();
+
+    for (collider_handle, parent) in removed_colliders {
+        match parent {
+            Some(rigid_body_handle) => {
+                // Also removes the colliders of the rigid body
+                context.rigid_bodies.remove(
+                    rigid_body_handle,
+                    &mut context.island_manager,
+                    &mut context.colliders,
+                    &mut context.impulse_joints,
+                    &mut context.multi_body_joints,
+                    true,
+                );
+            }
+            None => {
+                context.colliders.remove(
+                    collider_handle,
+                    &mut context.island_manager,
+                    &mut context.rigid_bodies,
+                    true,
+                );
+            }
+        }
+    }
+}
+
 pub(crate) fn apply_rigid_body_added(
     mut commands: Commands,
     mut physics_context: ResMut<PhysicsContext>,
-    mut rigid_body_query: Query<(Entity, &BoxCollider, &Transform, &RigidBody), Added<RigidBody>>,
+    mut rigid_body_query: Query<
+        (
+            Entity,
+            ColliderShapes,
+            &Transform,
+            &RigidBody,
+            Option<&Mass>,
+        ),
+        Added<RigidBody>,
+    >,
 ) {
     let context = physics_context.as_mut();
 
     // Rigid bodies like the cube
-    for (entity, collider, transform, RigidBody(body_type)) in rigid_body_query.iter_mut() {
+    for (entity, shapes, transform, RigidBody(body_type), mass) in rigid_body_query.iter_mut() {
         let physics_rigid_body = RigidBodyBuilder::new(body_type.clone())
             .position(transform.to_isometry())
             .ccd_enabled(true)
@@ -236,7 +460,10 @@ pub(crate) fn apply_rigid_body_added(
 
         let scale_transform = TransformBuilder::new().scale(transform.scale).build();
 
-        let physics_collider = create_box_collider(&entity, collider, &scale_transform);
+        let mut physics_collider = create_collider(&entity, shapes, &scale_transform);
+        if let Some(Mass(mass)) = mass {
+            physics_collider.set_mass(*mass);
+        }
 
         context
             .colliders
@@ -265,7 +492,17 @@ pub(crate) fn apply_rigid_body_type_change(
 
 pub(crate) fn apply_collider_sensor_change(
     mut physics_context: ResMut<PhysicsContext>,
-    mut query: Query<&RapierColliderHandle, Or<(With<FlagTrigger>, With<NextLevelTrigger>)>>,
+    mut query: Query<
+        &RapierColliderHandle,
+        Or<(
+            With<FlagTrigger>,
+            With<NextLevelTrigger>,
+            With<GravityZone>,
+            With<GravityBootZone>,
+            With<FluidVolume>,
+            With<Teleporter>,
+        )>,
+    >,
 ) {
     for RapierColliderHandle { handle } in query.iter_mut() {
         let collider = physics_context
@@ -304,7 +541,7 @@ pub(crate) fn write_transform_back(
     physics_context: Res<PhysicsContext>,
     mut query: Query<
         (&mut Transform, &RapierRigidBodyHandle),
-        (Without<PlayerCharacterController>, Without<PickedUp>),
+        Without<PlayerCharacterController>,
     >,
 ) {
     for (mut transform, body_handle) in query.iter_mut() {
@@ -0,0 +1,83 @@
+use crate::physics_context::PhysicsContext;
+use bevy_ecs::prelude::{Res, ResMut, Resource};
+use nalgebra::Vector3;
+use rapier3d::pipeline::{
+    DebugRenderBackend, DebugRenderMode, DebugRenderObject, DebugRenderPipeline, DebugRenderStyle,
+};
+use rapier3d::prelude::{Point, Real};
+use scene::debug_lines::DebugLines;
+
+/// Lets rapier draw the colliders, joints and contact points of the physics world
+#[derive(Resource)]
+pub(crate) struct PhysicsDebugRender {
+    pipeline: DebugRenderPipeline,
+}
+
+impl PhysicsDebugRender {
+    pub(crate) fn new() -> Self {
+        Self {
+            pipeline: DebugRenderPipeline::new(
+                DebugRenderStyle::default(),
+                DebugRenderMode::COLLIDER_SHAPES
+                    | DebugRenderMode::JOINTS
+                    | DebugRenderMode::CONTACTS,
+            ),
+        }
+    }
+}
+
+struct DebugLinesBackend<'a> {
+    debug_lines: &'a mut DebugLines,
+}
+
+impl DebugRenderBackend for DebugLinesBackend<'_> {
+    fn draw_line(
+        &mut self,
+        _object: DebugRenderObject,
+        a: Point<Real>,
+        b: Point<Real>,
+        color: [f32; 4],
+    ) {
+        self.debug_lines.line(a, b, hsl_to_rgb(color));
+    }
+}
+
+/// Rapier uses HSLA colors, with the hue going from 0 to 360
+fn hsl_to_rgb([hue, saturation, lightness, _alpha]: [f32; 4]) -> Vector3<f32> {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    Vector3::new(r + m, g + m, b + m)
+}
+
+pub(crate) fn draw_physics_debug_lines(
+    physics_context: Res<PhysicsContext>,
+    mut physics_debug_render: ResMut<PhysicsDebugRender>,
+    debug_lines: Option<ResMut<DebugLines>>,
+) {
+    let mut debug_lines = match debug_lines {
+        Some(debug_lines) if debug_lines.enabled => debug_lines,
+        _ => return,
+    };
+
+    let mut backend = DebugLinesBackend {
+        debug_lines: debug_lines.as_mut(),
+    };
+    physics_debug_render.pipeline.render(
+        &mut backend,
+        &physics_context.rigid_bodies,
+        &physics_context.colliders,
+        &physics_context.impulse_joints,
+        &physics_context.multi_body_joints,
+        &physics_context.narrow_phase,
+    );
+}
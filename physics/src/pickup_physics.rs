@@ -1,109 +1,210 @@
-use crate::physics_context::{PhysicsContext, RapierRigidBodyHandle, RigidBody};
-use bevy_ecs::prelude::{Added, Component, Query, RemovedComponents, Res, ResMut};
-use nalgebra::Point3;
-use rapier3d::control::KinematicCharacterController;
-use rapier3d::dynamics::RigidBodyType;
-use rapier3d::prelude::QueryFilter;
+use std::collections::HashMap;
+
+use crate::gravity_zone::GravityScale;
+use crate::physics_context::{PhysicsContext, RapierRigidBodyHandle};
+use app::entity_event::EntityEvent;
+use bevy_ecs::prelude::{
+    Added, Commands, Component, Entity, Query, RemovedComponents, Res, ResMut, Resource,
+};
+use nalgebra::{Point3, UnitQuaternion, Vector3};
+use rapier3d::prelude::RigidBodyHandle;
 use scene::camera::Camera;
-use scene::transform::Transform;
+use scene::pickup::{Broken, Pickupable};
+
+/// How strongly the held object gets pulled towards the hold point, per second
+const SPRING_STIFFNESS: f32 = 15.0;
+/// How quickly the held object adapts to the spring velocity, from 0 to 1 per step
+const SPRING_DAMPING: f32 = 0.5;
+/// Caps how fast a held object can be swung around
+const MAX_HELD_SPEED: f32 = 8.0;
+/// A held object that stays this far away from the hold point is stuck
+const STUCK_DISTANCE: f32 = 1.5;
+/// After being stuck for this many seconds, the object gets dropped
+const STUCK_DURATION: f32 = 0.3;
+/// How strongly the held object gets turned towards its target rotation, per second
+const ROTATION_STIFFNESS: f32 = 10.0;
 
 #[derive(Component)]
 pub struct PickedUp {
     pub position: Point3<f32>,
+    /// The held object gets turned towards this, starts out as the rotation that it had when it was picked up
+    rotation: Option<UnitQuaternion<f32>>,
+    stuck_duration: f32,
 }
 
-pub(super) fn start_pickup(mut query: Query<&mut RigidBody, Added<PickedUp>>) {
-    for mut rigidbody in query.iter_mut() {
-        rigidbody.0 = RigidBodyType::KinematicPositionBased;
+impl PickedUp {
+    pub fn new(position: Point3<f32>) -> Self {
+        Self {
+            position,
+            rotation: None,
+            stuck_duration: 0.0,
+        }
+    }
+
+    /// Turns the held object, for example around the view axis
+    pub fn rotate(&mut self, rotation: UnitQuaternion<f32>) {
+        if let Some(target_rotation) = self.rotation.as_mut() {
+            *target_rotation = rotation * *target_rotation;
+        }
+    }
+}
+
+/// Gets applied once to a thrown object, and then removed again
+#[derive(Component)]
+pub struct ThrowImpulse(pub Vector3<f32>);
+
+/// Held objects stay dynamic, so that they collide with walls instead of clipping through them
+pub(super) fn start_pickup(
+    mut physics_context: ResMut<PhysicsContext>,
+    query: Query<&RapierRigidBodyHandle, Added<PickedUp>>,
+) {
+    for rigid_body_handle in query.iter() {
+        let rigid_body = physics_context
+            .rigid_bodies
+            .get_mut(rigid_body_handle.handle)
+            .unwrap();
+        rigid_body.set_gravity_scale(0.0, true);
+        rigid_body.enable_ccd(true);
     }
 }
 
 pub(super) fn stop_pickup(
     mut removals: RemovedComponents<PickedUp>,
-    mut query: Query<&mut RigidBody>,
+    mut physics_context: ResMut<PhysicsContext>,
+    query: Query<(&RapierRigidBodyHandle, Option<&GravityScale>)>,
 ) {
     for entity in &mut removals {
-        if let Ok(mut rigidbody) = query.get_mut(entity) {
-            rigidbody.0 = RigidBodyType::Dynamic;
+        if let Ok((rigid_body_handle, gravity_scale)) = query.get(entity) {
+            if let Some(rigid_body) = physics_context
+                .rigid_bodies
+                .get_mut(rigid_body_handle.handle)
+            {
+                // The object could have been carried into a gravity zone
+                rigid_body.set_gravity_scale(gravity_scale.map_or(1.0, |v| v.0), true);
+            }
         }
     }
 }
 
-pub(super) fn update_pickup_target_position(camera: Res<Camera>, mut query: Query<&mut PickedUp>) {
-    for mut pickup in query.iter_mut() {
+pub(super) fn update_pickup_target_position(
+    camera: Res<Camera>,
+    mut query: Query<(&mut PickedUp, Option<&Pickupable>)>,
+) {
+    for (mut pickup, pickupable) in query.iter_mut() {
+        let hold_distance = pickupable.cloned().unwrap_or_default().hold_distance;
         pickup.position =
-            camera.position + camera.orientation * Camera::forward().into_inner() * 3.0
+            camera.position + camera.orientation * Camera::forward().into_inner() * hold_distance
     }
 }
 
-pub(super) fn update_pickup_transform(
-    mut query: Query<(&mut Transform, &PickedUp, &RapierRigidBodyHandle)>,
+/// Pulls the held objects towards the hold point with a damped spring,
+/// and drops them when they get stuck behind something
+pub(super) fn update_pickup_velocity(
+    mut commands: Commands,
     mut physics_context: ResMut<PhysicsContext>,
+    mut query: Query<(Entity, &mut PickedUp, &RapierRigidBodyHandle)>,
 ) {
-    let controller = KinematicCharacterController {
-        slide: true,
-        snap_to_ground: None,
-        autostep: None,
-        ..Default::default()
-    };
-
     let context = physics_context.as_mut();
+    let dt = context.integration_parameters.dt;
 
-    for (mut transform, picked_up, rigid_body_handle) in query.iter_mut() {
-        let character_rigid_body = context
+    for (entity, mut picked_up, rigid_body_handle) in query.iter_mut() {
+        let rigid_body = context
             .rigid_bodies
             .get_mut(rigid_body_handle.handle)
             .unwrap();
-        character_rigid_body.enable_ccd(true);
 
-        let character_collider = context
-            .colliders
-            .get(character_rigid_body.colliders()[0])
-            .unwrap();
-
-        let current_target_offset = picked_up.position - transform.position;
-
-        let character_mass = character_rigid_body.mass();
-
-        let mut collisions = vec![];
-        let effective_movement = controller.move_shape(
-            context.integration_parameters.dt,
-            &context.rigid_bodies,
-            &context.colliders,
-            &context.query_pipeline,
-            character_collider.shape(),
-            character_collider.position(),
-            current_target_offset,
-            QueryFilter::new()
-                .exclude_rigid_body(rigid_body_handle.handle)
-                .exclude_sensors(),
-            |c| collisions.push(c),
-        );
-
-        for collision in &collisions {
-            controller.solve_character_collision_impulses(
-                context.integration_parameters.dt,
-                &mut context.rigid_bodies,
-                &context.colliders,
-                &context.query_pipeline,
-                character_collider.shape(),
-                character_mass,
-                collision,
-                QueryFilter::new().exclude_rigid_body(rigid_body_handle.handle),
-            )
+        let offset: Vector3<f32> = picked_up.position.coords - rigid_body.translation();
+        if offset.norm() > STUCK_DISTANCE {
+            picked_up.stuck_duration += dt;
+            if picked_up.stuck_duration > STUCK_DURATION {
+                commands.entity(entity).remove::<PickedUp>();
+                continue;
+            }
+        } else {
+            picked_up.stuck_duration = 0.0;
         }
 
-        let character_rigid_body = context
+        let target_velocity = (offset * SPRING_STIFFNESS).cap_magnitude(MAX_HELD_SPEED);
+        let velocity = rigid_body
+            .linvel()
+            .lerp(&target_velocity, SPRING_DAMPING)
+            .cap_magnitude(MAX_HELD_SPEED);
+        rigid_body.set_linvel(velocity, true);
+        let target_rotation = *picked_up.rotation.get_or_insert(*rigid_body.rotation());
+        let rotation_offset = (target_rotation * rigid_body.rotation().inverse()).scaled_axis();
+        // Also stops held objects from spinning wildly after bumping into something
+        let angular_velocity = rigid_body
+            .angvel()
+            .lerp(&(rotation_offset * ROTATION_STIFFNESS), SPRING_DAMPING);
+        rigid_body.set_angvel(angular_velocity, true);
+    }
+}
+
+pub(super) fn apply_throw_impulses(
+    mut commands: Commands,
+    mut physics_context: ResMut<PhysicsContext>,
+    query: Query<(Entity, &ThrowImpulse, &RapierRigidBodyHandle)>,
+) {
+    for (entity, throw_impulse, rigid_body_handle) in query.iter() {
+        let rigid_body = physics_context
             .rigid_bodies
             .get_mut(rigid_body_handle.handle)
             .unwrap();
+        rigid_body.apply_impulse(throw_impulse.0, true);
+        commands.entity(entity).remove::<ThrowImpulse>();
+    }
+}
 
-        character_rigid_body.enable_ccd(true);
+/// The velocities of the fragile bodies right before the physics step
+#[derive(Resource, Default)]
+pub(super) struct VelocitiesBeforeStep(HashMap<RigidBodyHandle, Vector3<f32>>);
 
-        let position = character_rigid_body.position();
-        let new_position = position.translation.vector + effective_movement.translation;
-        character_rigid_body.set_next_kinematic_translation(new_position);
+pub(super) fn record_velocities_before_step(
+    physics_context: Res<PhysicsContext>,
+    mut velocities: ResMut<VelocitiesBeforeStep>,
+    query: Query<(&Pickupable, &RapierRigidBodyHandle)>,
+) {
+    velocities.0.clear();
+    for (pickupable, rigid_body_handle) in query.iter() {
+        if pickupable.break_speed.is_none() {
+            continue;
+        }
+        if let Some(rigid_body) = physics_context.rigid_bodies.get(rigid_body_handle.handle) {
+            velocities
+                .0
+                .insert(rigid_body_handle.handle, *rigid_body.linvel());
+        }
+    }
+}
 
-        transform.position = new_position.into();
+/// A fragile body that got stopped or bounced back too suddenly during the step has hit something too hard
+pub(super) fn break_fragile_bodies(
+    physics_context: Res<PhysicsContext>,
+    velocities: Res<VelocitiesBeforeStep>,
+    mut query: Query<(
+        &Pickupable,
+        &RapierRigidBodyHandle,
+        &mut EntityEvent<Broken>,
+    )>,
+) {
+    for (pickupable, rigid_body_handle, mut broken_events) in query.iter_mut() {
+        broken_events.clear();
+
+        let break_speed = match pickupable.break_speed {
+            Some(break_speed) => break_speed,
+            None => continue,
+        };
+        let velocity_before_step = match velocities.0.get(&rigid_body_handle.handle) {
+            Some(velocity) => velocity,
+            None => continue,
+        };
+        let rigid_body = match physics_context.rigid_bodies.get(rigid_body_handle.handle) {
+            Some(rigid_body) => rigid_body,
+            None => continue,
+        };
+
+        if (rigid_body.linvel() - velocity_before_step).norm() > break_speed {
+            broken_events.add(Broken);
+        }
     }
 }
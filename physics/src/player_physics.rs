@@ -1,33 +1,131 @@
 use bevy_ecs::prelude::*;
-use nalgebra::Vector3;
+use nalgebra::{UnitQuaternion, UnitVector3, Vector3};
 use rapier3d::geometry::ActiveCollisionTypes;
 use rapier3d::pipeline::ActiveEvents;
 use rapier3d::{
-    control::KinematicCharacterController,
-    prelude::{ColliderBuilder, QueryFilter, RigidBodyBuilder},
+    control::{CharacterAutostep, CharacterLength, KinematicCharacterController},
+    prelude::{ColliderBuilder, QueryFilter, Ray, RigidBodyBuilder, SharedShape},
 };
 
 use scene::transform::Transform;
 
-use super::physics_context::{BoxCollider, PhysicsContext, RapierRigidBodyHandle, RigidBody};
+use super::conveyor_belt::ConveyorBelt;
+use super::gravity_zone::GravityScale;
+use super::physics_context::{
+    BoxCollider, CapsuleCollider, CompoundCollider, MeshCollider, PhysicsContext,
+    RapierRigidBodyHandle, RigidBody, SphereCollider,
+};
+use super::physics_events::collider2entity;
+
+const CAPSULE_RADIUS: f32 = 0.15;
 
 #[derive(Component)]
 pub struct PlayerCharacterController {
     pub collider_height: f32,
+    pub crouch_collider_height: f32,
+    /// set by the input handling
+    pub wants_to_crouch: bool,
+    /// set by the physics, stays true while there is no room for standing up
+    pub crouched: bool,
     pub desired_movement: Vector3<f32>,
     pub grounded: bool,
+    /// in radians, steeper slopes cannot be walked up
+    pub max_slope_angle: f32,
+    /// obstacles up to this height, like stairs, are climbed automatically. Zero disables it.
+    pub step_height: f32,
+    /// keeps the player on the ground when walking down slopes or stairs. Zero disables it.
+    pub snap_to_ground_distance: f32,
+    /// the direction that the player stands in, gravity pulls the other way.
+    /// Changed by the gravity boot zones
+    pub up: UnitVector3<f32>,
+    /// the up direction that the collider has been built for
+    collider_up: UnitVector3<f32>,
 }
 
 impl Default for PlayerCharacterController {
     fn default() -> Self {
         Self {
             collider_height: 1.85,
+            crouch_collider_height: 0.9,
+            wants_to_crouch: false,
+            crouched: false,
             desired_movement: Vector3::zeros(),
             grounded: false,
+            max_slope_angle: std::f32::consts::FRAC_PI_4,
+            step_height: 0.3,
+            snap_to_ground_distance: 0.2,
+            up: Vector3::y_axis(),
+            collider_up: Vector3::y_axis(),
         }
     }
 }
 
+impl PlayerCharacterController {
+    fn create_kinematic_character_controller(
+        &self,
+        gravity_scale: Option<&GravityScale>,
+    ) -> KinematicCharacterController {
+        let autostep = if self.step_height > 0.0 {
+            Some(CharacterAutostep {
+                max_height: CharacterLength::Absolute(self.step_height),
+                min_width: CharacterLength::Absolute(0.1),
+                include_dynamic_bodies: false,
+            })
+        } else {
+            None
+        };
+
+        let snap_to_ground = if self.snap_to_ground_distance > 0.0 {
+            Some(CharacterLength::Absolute(self.snap_to_ground_distance))
+        } else {
+            None
+        };
+
+        // With inverted gravity, the player walks on the ceiling
+        let up = if gravity_scale.map_or(false, |v| v.is_inverted()) {
+            -self.up
+        } else {
+            self.up
+        };
+
+        KinematicCharacterController {
+            up,
+            autostep,
+            max_slope_climb_angle: self.max_slope_angle,
+            snap_to_ground,
+            ..KinematicCharacterController::default()
+        }
+    }
+
+    /// Turns the world up axis to the up direction of the player
+    pub fn up_rotation(&self) -> UnitQuaternion<f32> {
+        UnitQuaternion::rotation_between_axis(&Vector3::y_axis(), &self.up).unwrap_or_else(|| {
+            UnitQuaternion::from_axis_angle(&Vector3::x_axis(), std::f32::consts::PI)
+        })
+    }
+
+    fn current_collider_height(&self) -> f32 {
+        if self.crouched {
+            self.crouch_collider_height
+        } else {
+            self.collider_height
+        }
+    }
+}
+
+/// The capsule stands on the feet of the player, along the up direction
+fn create_capsule_shape(
+    collider_height: f32,
+    up: &UnitVector3<f32>,
+) -> (SharedShape, Vector3<f32>) {
+    let half_height = up.into_inner() * (collider_height / 2.0);
+    (
+        SharedShape::capsule((-half_height).into(), half_height.into(), CAPSULE_RADIUS),
+        // TODO: understand why this is needed
+        half_height,
+    )
+}
+
 pub(super) fn apply_player_character_controller_changes(
     mut commands: Commands,
     mut character_controller_query: Query<
@@ -36,6 +134,10 @@ pub(super) fn apply_player_character_controller_changes(
             Added<PlayerCharacterController>,
             Without<RigidBody>,
             Without<BoxCollider>,
+            Without<SphereCollider>,
+            Without<CapsuleCollider>,
+            Without<MeshCollider>,
+            Without<CompoundCollider>,
         ),
     >,
     mut physics_context: ResMut<PhysicsContext>,
@@ -49,15 +151,15 @@ pub(super) fn apply_player_character_controller_changes(
             .build();
 
         let handle = context.rigid_bodies.insert(physics_rigid_body);
-        let collider =
-            ColliderBuilder::capsule_y(player_character_controller.collider_height / 2.0, 0.15)
-                .translation(
-                    // TODO: understand why this is needed
-                    Vector3::new(0.0, player_character_controller.collider_height / 2.0, 0.0),
-                )
-                .user_data(entity.to_bits() as u128)
-                .active_events(ActiveEvents::COLLISION_EVENTS)
-                .active_collision_types(ActiveCollisionTypes::all());
+        let (shape, offset) = create_capsule_shape(
+            player_character_controller.current_collider_height(),
+            &player_character_controller.up,
+        );
+        let collider = ColliderBuilder::new(shape)
+            .translation(offset)
+            .user_data(entity.to_bits() as u128)
+            .active_events(ActiveEvents::COLLISION_EVENTS)
+            .active_collision_types(ActiveCollisionTypes::all());
 
         context
             .colliders
@@ -75,13 +177,19 @@ pub(super) fn step_character_controllers(
         &mut Transform,
         &mut PlayerCharacterController,
         &RapierRigidBodyHandle,
+        Option<&GravityScale>,
     )>,
+    belts: Query<&ConveyorBelt>,
 ) {
-    for (mut transform, mut character_controller, rigid_body_handle) in query.iter_mut() {
-        let controller = KinematicCharacterController::default();
+    for (mut transform, mut character_controller, rigid_body_handle, gravity_scale) in
+        query.iter_mut()
+    {
+        let controller = character_controller.create_kinematic_character_controller(gravity_scale);
 
         let context = physics_context.as_mut();
 
+        update_collider_shape(context, &mut character_controller, rigid_body_handle);
+
         let character_rigid_body = context.rigid_bodies.get(rigid_body_handle.handle).unwrap();
 
         let character_collider = &context
@@ -90,6 +198,14 @@ pub(super) fn step_character_controllers(
             .unwrap();
 
         let character_mass = character_rigid_body.mass();
+        // The character controller only sees the colliders on the layers that the player collides with
+        let collision_groups = character_collider.collision_groups();
+
+        let belt_velocity = if character_controller.grounded {
+            ground_belt_velocity(context, &character_controller, rigid_body_handle, &belts)
+        } else {
+            Vector3::zeros()
+        };
 
         let mut collisions = vec![];
         let effective_movement = controller.move_shape(
@@ -99,10 +215,12 @@ pub(super) fn step_character_controllers(
             &context.query_pipeline,
             character_collider.shape(),
             character_collider.position(),
-            character_controller.desired_movement * context.integration_parameters.dt,
+            (character_controller.desired_movement + belt_velocity)
+                * context.integration_parameters.dt,
             QueryFilter::new()
                 .exclude_rigid_body(rigid_body_handle.handle)
-                .exclude_sensors(),
+                .exclude_sensors()
+                .groups(collision_groups),
             |c| collisions.push(c),
         );
 
@@ -120,7 +238,9 @@ pub(super) fn step_character_controllers(
                 character_collider.shape(),
                 character_mass,
                 collision,
-                QueryFilter::new().exclude_rigid_body(rigid_body_handle.handle),
+                QueryFilter::new()
+                    .exclude_rigid_body(rigid_body_handle.handle)
+                    .groups(collision_groups),
             )
         }
 
@@ -136,3 +256,82 @@ pub(super) fn step_character_controllers(
         transform.position = new_position.into();
     }
 }
+
+/// The velocity of the conveyor belt that the player is standing on
+fn ground_belt_velocity(
+    context: &PhysicsContext,
+    character_controller: &PlayerCharacterController,
+    rigid_body_handle: &RapierRigidBodyHandle,
+    belts: &Query<&ConveyorBelt>,
+) -> Vector3<f32> {
+    let character_rigid_body = context.rigid_bodies.get(rigid_body_handle.handle).unwrap();
+    let up = character_controller.up.into_inner();
+    let ray = Ray::new(
+        (character_rigid_body.translation() + up * CAPSULE_RADIUS).into(),
+        -up,
+    );
+    let hit = context.query_pipeline.cast_ray(
+        &context.rigid_bodies,
+        &context.colliders,
+        &ray,
+        CAPSULE_RADIUS + 0.2,
+        true,
+        QueryFilter::new()
+            .exclude_rigid_body(rigid_body_handle.handle)
+            .exclude_sensors(),
+    );
+
+    hit.map(|(collider_handle, _toi)| collider2entity(&context.colliders, collider_handle))
+        .and_then(|entity| belts.get(entity).ok())
+        .map(|belt| belt.velocity())
+        .unwrap_or_else(Vector3::zeros)
+}
+
+/// Crouches, and turns the collider to the up direction
+fn update_collider_shape(
+    context: &mut PhysicsContext,
+    character_controller: &mut PlayerCharacterController,
+    rigid_body_handle: &RapierRigidBodyHandle,
+) {
+    let character_rigid_body = context.rigid_bodies.get(rigid_body_handle.handle).unwrap();
+    let up = character_controller.up;
+
+    let crouched = if character_controller.wants_to_crouch {
+        true
+    } else if character_controller.crouched {
+        // only stand up if nothing is above the player
+        let height_difference =
+            character_controller.collider_height - character_controller.crouch_collider_height;
+        let ray = Ray::new(
+            (character_rigid_body.translation()
+                + up.into_inner() * character_controller.crouch_collider_height)
+                .into(),
+            up.into_inner(),
+        );
+        let hit = context.query_pipeline.cast_ray(
+            &context.rigid_bodies,
+            &context.colliders,
+            &ray,
+            height_difference + CAPSULE_RADIUS,
+            true,
+            QueryFilter::new()
+                .exclude_rigid_body(rigid_body_handle.handle)
+                .exclude_sensors(),
+        );
+        hit.is_some()
+    } else {
+        false
+    };
+
+    if crouched == character_controller.crouched && up == character_controller.collider_up {
+        return;
+    }
+    character_controller.crouched = crouched;
+    character_controller.collider_up = up;
+
+    let collider_handle = character_rigid_body.colliders()[0];
+    let (shape, offset) = create_capsule_shape(character_controller.current_collider_height(), &up);
+    let collider = context.colliders.get_mut(collider_handle).unwrap();
+    collider.set_shape(shape);
+    collider.set_translation_wrt_parent(offset);
+}
@@ -1,19 +1,32 @@
 use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::not;
 use bevy_ecs::schedule::{apply_system_buffers, IntoSystemConfig, IntoSystemSetConfig, SystemSet};
-use time::time_manager::game_change::GameChangeHistoryPlugin;
+use time::time_manager::{game_change::GameChangeHistoryPlugin, is_rewinding};
 
 use crate::{
+    collision_layers::apply_collision_layers,
+    conveyor_belt::{apply_conveyor_belts, scroll_conveyor_belt_textures},
+    fluid_volume::{add_in_fluid, apply_fluid_volumes, scroll_fluid_surfaces, update_in_fluid},
+    gravity_zone::{
+        add_gravity_scales, apply_gravity_boot_zones, apply_gravity_scale_changes,
+        apply_gravity_zones, time_manager_rewind_gravity_scale,
+        time_manager_start_track_gravity_scale, time_manager_track_gravity_scale,
+        GravityScaleChange,
+    },
     physics_change::{
-        time_manager_rewind_rigid_body_type, time_manager_start_track_rigid_body_type,
-        time_manager_track_rigid_body_type, RigidBodyTypeChange, RigidBodyTypes,
+        start_rewind_target, stop_rewind_target, time_manager_rewind_rigid_body_type,
+        time_manager_start_track_rigid_body_type, time_manager_track_rigid_body_type,
+        RigidBodyTypeChange, RigidBodyTypes,
     },
     physics_context::{
-        apply_collider_changes, apply_collider_sensor_change, apply_rigid_body_added,
-        apply_rigid_body_type_change, apply_transform_changes, reset_velocities,
-        step_physics_simulation, write_transform_back, PhysicsContext,
+        apply_collider_changes, apply_collider_sensor_change, apply_despawned_entities,
+        apply_rigid_body_added, apply_rigid_body_type_change, apply_transform_changes,
+        reset_velocities, step_physics_simulation, write_transform_back, PhysicsContext,
     },
+    physics_debug::{draw_physics_debug_lines, PhysicsDebugRender},
     pickup_physics::{
-        start_pickup, stop_pickup, update_pickup_target_position, update_pickup_transform,
+        apply_throw_impulses, break_fragile_bodies, record_velocities_before_step, start_pickup,
+        stop_pickup, update_pickup_target_position, update_pickup_velocity, VelocitiesBeforeStep,
     },
     player_physics::{apply_player_character_controller_changes, step_character_controllers},
 };
@@ -33,6 +46,8 @@ pub struct PhysicsPlugin;
 impl Plugin for PhysicsPlugin {
     fn build(&mut self, app: &mut PluginAppAccess) {
         app.with_resource(PhysicsContext::new())
+            .with_resource(PhysicsDebugRender::new())
+            .with_resource(VelocitiesBeforeStep::default())
             .with_set(PhysicsPluginSets::PickupUpdate.before(PhysicsPluginSets::TimeRewinding))
             .with_set(PhysicsPluginSets::TimeRewinding.before(PhysicsPluginSets::BeforePhysics))
             .with_set(PhysicsPluginSets::BeforePhysics.before(PhysicsPluginSets::Physics))
@@ -57,6 +72,29 @@ impl Plugin for PhysicsPlugin {
                 GameChangeHistoryPlugin::<RigidBodyTypeChange>::system_set()
                     .in_set(PhysicsPluginSets::TimeRewinding),
             )
+            .with_plugin(
+                GameChangeHistoryPlugin::<GravityScaleChange>::new()
+                    .with_tracker(time_manager_start_track_gravity_scale)
+                    .with_tracker(
+                        time_manager_track_gravity_scale
+                            .after(time_manager_start_track_gravity_scale),
+                    )
+                    .with_rewinder(time_manager_rewind_gravity_scale),
+            )
+            .with_set(
+                GameChangeHistoryPlugin::<GravityScaleChange>::system_set()
+                    .in_set(PhysicsPluginSets::TimeRewinding),
+            )
+            .with_system(
+                start_rewind_target
+                    .in_set(PhysicsPluginSets::TimeRewinding)
+                    .before(GameChangeHistoryPlugin::<RigidBodyTypeChange>::system_set()),
+            )
+            .with_system(
+                stop_rewind_target
+                    .in_set(PhysicsPluginSets::TimeRewinding)
+                    .before(GameChangeHistoryPlugin::<RigidBodyTypeChange>::system_set()),
+            )
             .with_system(
                 apply_system_buffers
                     .after(PhysicsPluginSets::TimeRewinding)
@@ -65,7 +103,12 @@ impl Plugin for PhysicsPlugin {
 
         // Keep ECS and physics world in sync, do note that we should probably do this after update and before physics.
         app //
-            .with_system(apply_collider_changes.in_set(PhysicsPluginSets::BeforePhysics))
+            .with_system(apply_despawned_entities.in_set(PhysicsPluginSets::BeforePhysics))
+            .with_system(
+                apply_collider_changes
+                    .in_set(PhysicsPluginSets::BeforePhysics)
+                    .after(apply_despawned_entities),
+            )
             .with_system(
                 apply_rigid_body_added
                     .in_set(PhysicsPluginSets::BeforePhysics)
@@ -86,6 +129,11 @@ impl Plugin for PhysicsPlugin {
                     .in_set(PhysicsPluginSets::BeforePhysics)
                     .after(apply_collider_sensor_change),
             )
+            .with_system(
+                apply_collision_layers
+                    .in_set(PhysicsPluginSets::BeforePhysics)
+                    .after(apply_player_character_controller_changes),
+            )
             .with_system(
                 apply_transform_changes
                     .in_set(PhysicsPluginSets::BeforePhysics)
@@ -95,6 +143,22 @@ impl Plugin for PhysicsPlugin {
                 reset_velocities
                     .in_set(PhysicsPluginSets::BeforePhysics)
                     .after(apply_transform_changes),
+            )
+            .with_system(
+                apply_conveyor_belts
+                    .in_set(PhysicsPluginSets::BeforePhysics)
+                    .after(reset_velocities),
+            )
+            .with_system(
+                apply_fluid_volumes
+                    .in_set(PhysicsPluginSets::BeforePhysics)
+                    .after(apply_conveyor_belts)
+                    .run_if(not(is_rewinding)),
+            )
+            .with_system(
+                apply_gravity_scale_changes
+                    .in_set(PhysicsPluginSets::BeforePhysics)
+                    .after(apply_rigid_body_type_change),
             );
         // The velocity change direcly modifies the physics world, so we need to do it after we have applied the rigid body type change
         // .with_plugin(
@@ -115,13 +179,48 @@ impl Plugin for PhysicsPlugin {
                 step_character_controllers
                     .in_set(PhysicsPluginSets::Physics)
                     .after(step_physics_simulation),
+            )
+            .with_system(
+                record_velocities_before_step
+                    .in_set(PhysicsPluginSets::Physics)
+                    .before(step_physics_simulation)
+                    .run_if(not(is_rewinding)),
+            )
+            .with_system(
+                break_fragile_bodies
+                    .in_set(PhysicsPluginSets::Physics)
+                    .after(step_physics_simulation)
+                    // Only the hits during the step count, not the player walking into them
+                    .before(step_character_controllers)
+                    .run_if(not(is_rewinding)),
             );
 
         // Write back
         app //
-            .with_system(write_transform_back.in_set(PhysicsPluginSets::AfterPhysics));
+            .with_system(write_transform_back.in_set(PhysicsPluginSets::AfterPhysics))
+            .with_system(scroll_conveyor_belt_textures.in_set(PhysicsPluginSets::AfterPhysics))
+            .with_system(add_gravity_scales.in_set(PhysicsPluginSets::AfterPhysics))
+            .with_system(add_in_fluid.in_set(PhysicsPluginSets::AfterPhysics))
+            .with_system(
+                update_in_fluid
+                    .in_set(PhysicsPluginSets::AfterPhysics)
+                    .after(add_in_fluid),
+            )
+            .with_system(scroll_fluid_surfaces.in_set(PhysicsPluginSets::AfterPhysics))
+            .with_system(
+                apply_gravity_zones
+                    .in_set(PhysicsPluginSets::AfterPhysics)
+                    .run_if(not(is_rewinding)),
+            )
+            .with_system(apply_gravity_boot_zones.in_set(PhysicsPluginSets::AfterPhysics))
+            .with_system(
+                draw_physics_debug_lines
+                    .in_set(PhysicsPluginSets::AfterPhysics)
+                    .after(write_transform_back),
+            );
 
-        // Pick up logic, most of it is pretty much independent of the physics and simply happens before it
+        // Pick up logic, most of it is pretty much independent of the physics and simply happens before it.
+        // The held objects are moved by setting their velocity, so that the physics can stop them at walls
         app //
             .with_system(start_pickup.in_set(PhysicsPluginSets::PickupUpdate))
             .with_system(
@@ -135,10 +234,14 @@ impl Plugin for PhysicsPlugin {
                     .after(start_pickup),
             )
             .with_system(
-                update_pickup_transform
-                    .in_set(PhysicsPluginSets::Physics)
-                    .after(step_physics_simulation)
-                    .after(step_character_controllers),
+                update_pickup_velocity
+                    .in_set(PhysicsPluginSets::BeforePhysics)
+                    .after(apply_conveyor_belts),
+            )
+            .with_system(
+                apply_throw_impulses
+                    .in_set(PhysicsPluginSets::BeforePhysics)
+                    .after(update_pickup_velocity),
             );
     }
 }
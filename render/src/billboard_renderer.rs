@@ -0,0 +1,326 @@
+use crate::context::Context;
+use crate::quad::{self, quad_mesh, QuadVertex};
+use crate::scene::billboard::GpuBillboard;
+use scene::billboard::{Billboard, BillboardBlend};
+use scene::camera::Camera;
+use scene::transform::Transform;
+use std::sync::Arc;
+use vulkano::buffer::allocator::{SubbufferAllocator, SubbufferAllocatorCreateInfo};
+use vulkano::buffer::{BufferUsage, Subbuffer};
+use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
+use vulkano::command_buffer::{
+    AutoCommandBufferBuilder, CommandBufferUsage, RenderPassBeginInfo, SubpassContents,
+};
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::format::Format;
+use vulkano::image::ImageViewAbstract;
+use vulkano::memory::allocator::StandardMemoryAllocator;
+use vulkano::pipeline::graphics::color_blend::{
+    AttachmentBlend, BlendFactor, BlendOp, ColorBlendState,
+};
+use vulkano::pipeline::graphics::depth_stencil::{CompareOp, DepthState, DepthStencilState};
+use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
+use vulkano::pipeline::graphics::rasterization::{CullMode, RasterizationState};
+use vulkano::pipeline::graphics::vertex_input::Vertex;
+use vulkano::pipeline::graphics::viewport::{Viewport, ViewportState};
+use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint, StateMode};
+use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass};
+use vulkano::sync::GpuFuture;
+
+/// Draws camera facing quads on top of the shaded scene, before the bloom.
+/// The cutouts write to the depth buffer of the scene, the additive ones only test against it.
+pub struct BillboardRenderer {
+    render_pass: Arc<RenderPass>,
+    cutout_pipeline: Arc<GraphicsPipeline>,
+    additive_pipeline: Arc<GraphicsPipeline>,
+    framebuffers: Vec<Arc<Framebuffer>>,
+    index_buffer: Subbuffer<[u32]>,
+    vertex_buffer: Subbuffer<[QuadVertex]>,
+
+    buffer_allocator: SubbufferAllocator,
+    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+    descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+}
+
+impl BillboardRenderer {
+    pub fn new(
+        context: &Context,
+        images: &[Arc<dyn ImageViewAbstract>],
+        depth_images: &[Arc<dyn ImageViewAbstract>],
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> Self {
+        let render_pass = vulkano::single_pass_renderpass!(
+            context.device(),
+            attachments: {
+                color: {
+                    load: Load,
+                    store: Store,
+                    format: Format::R16G16B16A16_SFLOAT,
+                    samples: 1,
+                },
+                depth: {
+                    load: Load,
+                    store: Store,
+                    format: Format::D32_SFLOAT,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {depth}
+            }
+        )
+        .unwrap();
+
+        let vs = vs::load(context.device()).unwrap();
+        let fs = fs::load(context.device()).unwrap();
+        let create_pipeline = |write_depth: bool, color_blend_state: ColorBlendState| {
+            GraphicsPipeline::start()
+                // Billboards can be seen from both sides
+                .rasterization_state(RasterizationState::new().cull_mode(CullMode::None))
+                .depth_stencil_state(DepthStencilState {
+                    depth: Some(DepthState {
+                        enable_dynamic: false,
+                        write_enable: StateMode::Fixed(write_depth),
+                        compare_op: StateMode::Fixed(CompareOp::LessOrEqual),
+                    }),
+                    ..DepthStencilState::disabled()
+                })
+                .color_blend_state(color_blend_state)
+                .vertex_input_state(QuadVertex::per_vertex())
+                .vertex_shader(vs.entry_point("main").unwrap(), ())
+                .input_assembly_state(InputAssemblyState::new())
+                .fragment_shader(fs.entry_point("main").unwrap(), ())
+                .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+                .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+                .build(context.device())
+                .unwrap()
+        };
+        let cutout_pipeline = create_pipeline(true, ColorBlendState::new(1));
+        let additive_pipeline = create_pipeline(
+            false,
+            ColorBlendState::new(1).blend(AttachmentBlend {
+                color_op: BlendOp::Add,
+                color_source: BlendFactor::SrcAlpha,
+                color_destination: BlendFactor::One,
+                alpha_op: BlendOp::Add,
+                alpha_source: BlendFactor::Zero,
+                alpha_destination: BlendFactor::One,
+            }),
+        );
+
+        let (vertex_buffer, index_buffer) =
+            quad::create_geometry_buffers(quad_mesh(), memory_allocator.clone());
+
+        let buffer_allocator = SubbufferAllocator::new(
+            memory_allocator,
+            SubbufferAllocatorCreateInfo {
+                buffer_usage: BufferUsage::UNIFORM_BUFFER,
+                ..Default::default()
+            },
+        );
+
+        let framebuffers = Self::create_framebuffers(images, depth_images, render_pass.clone());
+
+        Self {
+            render_pass,
+            cutout_pipeline,
+            additive_pipeline,
+            framebuffers,
+            index_buffer,
+            vertex_buffer,
+            buffer_allocator,
+            command_buffer_allocator,
+            descriptor_set_allocator,
+        }
+    }
+
+    pub fn resize(
+        &mut self,
+        images: &[Arc<dyn ImageViewAbstract>],
+        depth_images: &[Arc<dyn ImageViewAbstract>],
+    ) {
+        self.framebuffers =
+            Self::create_framebuffers(images, depth_images, self.render_pass.clone());
+    }
+
+    fn create_framebuffers(
+        images: &[Arc<dyn ImageViewAbstract>],
+        depth_images: &[Arc<dyn ImageViewAbstract>],
+        render_pass: Arc<RenderPass>,
+    ) -> Vec<Arc<Framebuffer>> {
+        images
+            .iter()
+            .zip(depth_images.iter())
+            .map(|(image, depth_image)| {
+                Framebuffer::new(
+                    render_pass.clone(),
+                    FramebufferCreateInfo {
+                        attachments: vec![image.clone(), depth_image.clone()],
+                        ..FramebufferCreateInfo::default()
+                    },
+                )
+                .expect("failed to create framebuffer")
+            })
+            .collect()
+    }
+
+    pub fn render<F>(
+        &self,
+        context: &Context,
+        future: F,
+        billboards: Vec<(&Transform, &Billboard, &GpuBillboard)>,
+        camera: &Camera,
+        rewind_time: f32,
+        image_index: u32,
+    ) -> Box<dyn GpuFuture>
+    where
+        F: GpuFuture + 'static,
+    {
+        if billboards.is_empty() {
+            return future.boxed();
+        }
+
+        let framebuffer = self.framebuffers[image_index as usize].clone();
+        let viewport = Viewport {
+            origin: [0.0, 0.0],
+            dimensions: framebuffer.extent().map(|v| v as f32),
+            depth_range: 0.0..1.0,
+        };
+
+        let (_, _, camera_up) = camera.camera_basis_vectors();
+        let uniform_subbuffer_scene = {
+            let uniform_data = vs::Scene {
+                projView: (camera.proj() * camera.view()).into(),
+                cameraPosition: camera.position.into(),
+                rewindTime: rewind_time,
+                cameraUp: camera_up.into(),
+            };
+
+            let subbuffer = self.buffer_allocator.allocate_sized().unwrap();
+            *subbuffer.write().unwrap() = uniform_data;
+
+            subbuffer
+        };
+
+        let scene_descriptor_set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            self.cutout_pipeline
+                .layout()
+                .set_layouts()
+                .get(0)
+                .unwrap()
+                .clone(),
+            [WriteDescriptorSet::buffer(0, uniform_subbuffer_scene)],
+        )
+        .unwrap();
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            context.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        builder
+            .begin_render_pass(
+                RenderPassBeginInfo {
+                    clear_values: vec![None, None],
+                    ..RenderPassBeginInfo::framebuffer(framebuffer)
+                },
+                SubpassContents::Inline,
+            )
+            .unwrap()
+            .set_viewport(0, [viewport])
+            .bind_index_buffer(self.index_buffer.clone())
+            .bind_vertex_buffers(0, self.vertex_buffer.clone());
+
+        // The additive billboards don't write to the depth buffer, so they have to come last
+        for (blend, pipeline) in [
+            (BillboardBlend::Cutout, &self.cutout_pipeline),
+            (BillboardBlend::Additive, &self.additive_pipeline),
+        ] {
+            builder
+                .bind_pipeline_graphics(pipeline.clone())
+                .bind_descriptor_sets(
+                    PipelineBindPoint::Graphics,
+                    pipeline.layout().clone(),
+                    0,
+                    scene_descriptor_set.clone(),
+                );
+
+            for (transform, billboard, gpu_billboard) in billboards
+                .iter()
+                .filter(|(_, billboard, _)| billboard.blend == blend)
+            {
+                let texture_descriptor_set = PersistentDescriptorSet::new(
+                    &self.descriptor_set_allocator,
+                    pipeline.layout().set_layouts().get(1).unwrap().clone(),
+                    [WriteDescriptorSet::image_view_sampler(
+                        0,
+                        gpu_billboard.texture.image_view.clone(),
+                        gpu_billboard.texture.sampler.clone(),
+                    )],
+                )
+                .unwrap();
+
+                builder
+                    .bind_descriptor_sets(
+                        PipelineBindPoint::Graphics,
+                        pipeline.layout().clone(),
+                        1,
+                        texture_descriptor_set,
+                    )
+                    .push_constants(
+                        pipeline.layout().clone(),
+                        0,
+                        vs::Billboard {
+                            center: transform.position.into(),
+                            width: billboard.size.x,
+                            color: billboard.color.into(),
+                            height: billboard.size.y,
+                            lockedAxis: billboard.locked_axis.unwrap_or_default().into(),
+                            axisLocked: if billboard.locked_axis.is_some() {
+                                1.0
+                            } else {
+                                0.0
+                            },
+                            additive: if blend == BillboardBlend::Additive {
+                                1.0
+                            } else {
+                                0.0
+                            },
+                        },
+                    )
+                    .draw_indexed(self.index_buffer.len() as u32, 1, 0, 0, 0)
+                    .unwrap();
+            }
+        }
+
+        builder.end_render_pass().unwrap();
+
+        let command_buffer = builder.build().unwrap();
+
+        future
+            .then_execute(context.queue(), command_buffer)
+            .unwrap()
+            .boxed()
+    }
+}
+
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "../assets/shaders/billboard/billboard.vert",
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "../assets/shaders/billboard/billboard.frag",
+    }
+}
@@ -1,30 +1,52 @@
 use crate::context::Context;
 
 use crate::custom_storage_image::CustomStorageImage;
+use crate::post_process::{PostProcessEffect, PostProcessFrame};
+use bevy_ecs::prelude::Resource;
 use std::sync::Arc;
 use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
 use vulkano::command_buffer::{
-    AutoCommandBufferBuilder, CommandBufferExecFuture, CommandBufferUsage, CopyImageInfo,
-    PrimaryAutoCommandBuffer,
+    AutoCommandBufferBuilder, CommandBufferUsage, CopyImageInfo, PrimaryAutoCommandBuffer,
 };
 use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
 use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
 use vulkano::image::view::{ImageView, ImageViewCreateInfo, ImageViewCreationError};
 use vulkano::image::{
-    AttachmentImage, ImageAccess, ImageCreateFlags, ImageDimensions, ImageLayout,
-    ImageSubresourceRange, ImageUsage, ImageViewAbstract,
+    ImageAccess, ImageCreateFlags, ImageDimensions, ImageLayout, ImageSubresourceRange,
+    ImageUsage, ImageViewAbstract,
 };
 use vulkano::memory::allocator::StandardMemoryAllocator;
 use vulkano::pipeline::{ComputePipeline, Pipeline, PipelineBindPoint};
 use vulkano::sampler::{Filter, Sampler, SamplerCreateInfo, SamplerMipmapMode};
 use vulkano::sync::GpuFuture;
 
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct BloomSettings {
+    /// only pixels brighter than this will bloom
+    pub threshold: f32,
+    /// softens the transition at the threshold
+    pub knee: f32,
+    pub intensity: f32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            knee: 0.1,
+            intensity: 1.0,
+        }
+    }
+}
+
 pub struct BloomRenderer {
     downsample_pipeline: Arc<ComputePipeline>,
     upsample_pipeline: Arc<ComputePipeline>,
     cached_command_buffer: Vec<Option<Arc<PrimaryAutoCommandBuffer>>>,
+    /// the cached command buffers were recorded with these settings
+    cached_settings: BloomSettings,
 
-    input_images: Vec<Arc<ImageView<AttachmentImage>>>,
+    input_images: Vec<Arc<dyn ImageViewAbstract>>,
     output_images: Vec<ImageWithMipViews>,
 
     sampler: Arc<Sampler>,
@@ -37,7 +59,7 @@ pub struct BloomRenderer {
 impl BloomRenderer {
     pub fn new(
         context: &Context,
-        input_images: Vec<Arc<ImageView<AttachmentImage>>>,
+        input_images: Vec<Arc<dyn ImageViewAbstract>>,
         memory_allocator: Arc<StandardMemoryAllocator>,
         command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
         descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
@@ -90,6 +112,7 @@ impl BloomRenderer {
             downsample_pipeline,
             upsample_pipeline,
             cached_command_buffer: vec![None; input_images.len()],
+            cached_settings: BloomSettings::default(),
             sampler,
 
             input_images,
@@ -100,8 +123,10 @@ impl BloomRenderer {
             descriptor_set_allocator,
         }
     }
+}
 
-    pub fn resize(&mut self, input_images: Vec<Arc<ImageView<AttachmentImage>>>) {
+impl PostProcessEffect for BloomRenderer {
+    fn resize(&mut self, input_images: Vec<Arc<dyn ImageViewAbstract>>) {
         self.input_images = input_images.clone();
         self.output_images = input_images
             .iter()
@@ -112,19 +137,23 @@ impl BloomRenderer {
         self.cached_command_buffer = vec![None; input_images.len()];
     }
 
-    pub fn render<F>(
+    fn render(
         &mut self,
         context: &Context,
-        future: F,
+        future: Box<dyn GpuFuture>,
+        frame: &PostProcessFrame,
         image_index: u32,
-    ) -> CommandBufferExecFuture<F>
-    where
-        F: GpuFuture + 'static,
-    {
+    ) -> Box<dyn GpuFuture> {
+        if self.cached_settings != frame.bloom {
+            self.cached_settings = frame.bloom.clone();
+            self.cached_command_buffer = vec![None; self.input_images.len()];
+        }
+
         if let Some(command_buffer) = self.cached_command_buffer[image_index as usize].clone() {
             return future
                 .then_execute(context.queue(), command_buffer)
-                .unwrap();
+                .unwrap()
+                .boxed();
         }
 
         let mut builder = AutoCommandBufferBuilder::primary(
@@ -134,7 +163,7 @@ impl BloomRenderer {
         )
         .unwrap();
 
-        let scene_image = self.input_images[image_index as usize].image().clone();
+        let scene_image = self.input_images[image_index as usize].image();
         let work_image = &self.output_images[image_index as usize];
 
         // copy scene image to work image
@@ -182,8 +211,8 @@ impl BloomRenderer {
             let downsample_pass = cs::downsample::Pass {
                 inputTexelSize: input_size.width_height().map(|v| 1.0 / (v as f32)),
                 isFirstPass: (input_miplevel == 0) as u32,
-                threshold: 1.0, // TODO: make this configurable
-                knee: 0.1,
+                threshold: self.cached_settings.threshold,
+                knee: self.cached_settings.knee,
             };
 
             let mut dispatch_size = output_size.width_height_depth();
@@ -243,7 +272,7 @@ impl BloomRenderer {
 
             let upsample_pass = cs::upsample::Pass {
                 inputTexelSize: input_size.width_height().map(|v| 1.0 / (v as f32)),
-                intensity: 1.0, // TODO: make this configurable
+                intensity: self.cached_settings.intensity,
             };
 
             let mut dispatch_size = output_size.width_height_depth();
@@ -267,12 +296,13 @@ impl BloomRenderer {
         future
             .then_execute(context.queue(), command_buffer)
             .unwrap()
+            .boxed()
     }
 
-    pub fn output_images(&self) -> Vec<Arc<ImageView<CustomStorageImage>>> {
+    fn output_images(&self) -> Vec<Arc<dyn ImageViewAbstract>> {
         self.output_images
             .iter()
-            .map(|image| image.image.clone())
+            .map(|image| image.image.clone() as Arc<dyn ImageViewAbstract>)
             .collect()
     }
 }
@@ -304,7 +334,7 @@ struct ImageWithMipViews {
 
 impl ImageWithMipViews {
     fn new(
-        input_image: Arc<ImageView<AttachmentImage>>,
+        input_image: Arc<dyn ImageViewAbstract>,
         memory_allocator: Arc<StandardMemoryAllocator>,
     ) -> Self {
         let image = Self::create_output_image(input_image, memory_allocator);
@@ -313,11 +343,11 @@ impl ImageWithMipViews {
     }
 
     fn create_output_image(
-        input_image: Arc<ImageView<AttachmentImage>>,
+        input_image: Arc<dyn ImageViewAbstract>,
         memory_allocator: Arc<StandardMemoryAllocator>,
     ) -> Arc<ImageView<CustomStorageImage>> {
         let pass_count = 6;
-        let [width, height] = input_image.dimensions().width_height();
+        let [width, height] = input_image.image().dimensions().width_height();
         let storage_image = CustomStorageImage::uninitialized(
             &memory_allocator,
             ImageDimensions::Dim2d {
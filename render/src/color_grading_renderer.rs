@@ -0,0 +1,192 @@
+use crate::context::Context;
+use crate::fullscreen_pass::FullscreenPass;
+use crate::post_process::{PostProcessEffect, PostProcessFrame};
+use scene::asset::AssetId;
+use scene::color_grading::ColorLut;
+use std::collections::HashMap;
+use std::sync::Arc;
+use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage};
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::format::Format;
+use vulkano::image::view::ImageView;
+use vulkano::image::{ImageDimensions, ImageViewAbstract, ImmutableImage, MipmapsCount};
+use vulkano::memory::allocator::StandardMemoryAllocator;
+use vulkano::sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerMipmapMode};
+use vulkano::sync::{self, GpuFuture};
+
+/// Grades the tonemapped colors with 3D lookup tables, should come after the tonemapping.
+/// Blends between the LUT of the level and a second LUT, see [`scene::color_grading::ColorGrading`].
+pub(crate) struct ColorGradingRenderer {
+    fullscreen_pass: FullscreenPass,
+    sampler: Arc<Sampler>,
+    /// Gets used when there is no LUT
+    identity_lut: Arc<ColorLut>,
+    gpu_luts: HashMap<AssetId, Arc<ImageView<ImmutableImage>>>,
+    /// The LUTs that the descriptor set was created for
+    cached_descriptor_set: Option<((AssetId, AssetId), Arc<PersistentDescriptorSet>)>,
+
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+    descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+}
+
+impl ColorGradingRenderer {
+    pub fn new(
+        context: &Context,
+        input_images: Vec<Arc<dyn ImageViewAbstract>>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> Self {
+        let fullscreen_pass = FullscreenPass::new(
+            context,
+            fs::load(context.device()).unwrap(),
+            input_images,
+            memory_allocator.clone(),
+            command_buffer_allocator.clone(),
+            descriptor_set_allocator.clone(),
+        );
+
+        // Interpolates between the entries of the table
+        let sampler = Sampler::new(
+            context.device(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                mipmap_mode: SamplerMipmapMode::Nearest,
+                ..SamplerCreateInfo::default()
+            },
+        )
+        .unwrap();
+
+        Self {
+            fullscreen_pass,
+            sampler,
+            // With linear filtering, the two entries per channel already map every color onto itself
+            identity_lut: Arc::new(ColorLut::identity(2)),
+            gpu_luts: HashMap::new(),
+            cached_descriptor_set: None,
+
+            memory_allocator,
+            command_buffer_allocator,
+            descriptor_set_allocator,
+        }
+    }
+
+    /// Uploads the LUT the first time it gets used
+    fn gpu_lut(&mut self, context: &Context, lut: &ColorLut) -> Arc<ImageView<ImmutableImage>> {
+        if let Some(gpu_lut) = self.gpu_luts.get(&lut.id) {
+            return gpu_lut.clone();
+        }
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            context.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        let image = ImmutableImage::from_iter(
+            self.memory_allocator.as_ref(),
+            lut.data.iter().copied(),
+            ImageDimensions::Dim3d {
+                width: lut.size,
+                height: lut.size,
+                depth: lut.size,
+            },
+            MipmapsCount::One,
+            Format::R8G8B8A8_UNORM,
+            &mut builder,
+        )
+        .expect("could not upload color lookup table to GPU");
+
+        let command_buffer = builder.build().unwrap();
+        sync::now(context.device())
+            .then_execute(context.queue(), command_buffer)
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        let gpu_lut = ImageView::new_default(image).unwrap();
+        self.gpu_luts.insert(lut.id, gpu_lut.clone());
+        gpu_lut
+    }
+
+    fn descriptor_set(
+        &mut self,
+        context: &Context,
+        lut: &ColorLut,
+        blend_lut: &ColorLut,
+    ) -> Arc<PersistentDescriptorSet> {
+        let key = (lut.id, blend_lut.id);
+        if let Some((cached_key, descriptor_set)) = &self.cached_descriptor_set {
+            if *cached_key == key {
+                return descriptor_set.clone();
+            }
+        }
+
+        let gpu_lut = self.gpu_lut(context, lut);
+        let gpu_blend_lut = self.gpu_lut(context, blend_lut);
+        let descriptor_set = PersistentDescriptorSet::new(
+            self.descriptor_set_allocator.as_ref(),
+            self.fullscreen_pass.set_layout(1),
+            [
+                WriteDescriptorSet::image_view_sampler(0, gpu_lut, self.sampler.clone()),
+                WriteDescriptorSet::image_view_sampler(1, gpu_blend_lut, self.sampler.clone()),
+            ],
+        )
+        .unwrap();
+
+        self.cached_descriptor_set = Some((key, descriptor_set.clone()));
+        descriptor_set
+    }
+}
+
+impl PostProcessEffect for ColorGradingRenderer {
+    fn resize(&mut self, input_images: Vec<Arc<dyn ImageViewAbstract>>) {
+        self.fullscreen_pass.resize(input_images);
+    }
+
+    fn render(
+        &mut self,
+        context: &Context,
+        future: Box<dyn GpuFuture>,
+        frame: &PostProcessFrame,
+        image_index: u32,
+    ) -> Box<dyn GpuFuture> {
+        let lut = frame
+            .color_lut
+            .clone()
+            .unwrap_or_else(|| self.identity_lut.clone());
+        let blend_lut = frame
+            .blend_color_lut
+            .clone()
+            .unwrap_or_else(|| self.identity_lut.clone());
+        let descriptor_set = self.descriptor_set(context, &lut, &blend_lut);
+
+        let pass = fs::Pass {
+            blend: frame.color_lut_blend.clamp(0.0, 1.0),
+            lutSize: lut.size as f32,
+            blendLutSize: blend_lut.size as f32,
+        };
+
+        self.fullscreen_pass
+            .render_with_set(context, future, image_index, pass, descriptor_set)
+    }
+
+    fn output_images(&self) -> Vec<Arc<dyn ImageViewAbstract>> {
+        self.fullscreen_pass.output_images()
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "../assets/shaders/color_grading/color_grading.frag",
+    }
+}
@@ -14,6 +14,32 @@ use vulkano_win::create_surface_from_handle;
 
 use windowing::window::Window;
 
+/// Which GPU the game should run on, for PCs with more than one
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum DeviceSelection {
+    /// Prefers discrete GPUs over integrated ones
+    #[default]
+    Automatic,
+    /// In the order that Vulkan lists the GPUs, which gets printed at startup
+    Index(usize),
+    /// Part of the name, ignoring the case
+    Name(String),
+}
+
+impl DeviceSelection {
+    /// An empty string is automatic, a number is an index and anything else is a name
+    pub fn parse(value: &str) -> Self {
+        let value = value.trim();
+        if value.is_empty() {
+            DeviceSelection::Automatic
+        } else if let Ok(index) = value.parse() {
+            DeviceSelection::Index(index)
+        } else {
+            DeviceSelection::Name(value.to_string())
+        }
+    }
+}
+
 ///
 /// see also https://gpuopen.com/learn/understanding-vulkan-objects/
 pub struct Context {
@@ -28,7 +54,7 @@ pub struct Context {
 }
 
 impl Context {
-    pub fn new(window: Arc<Window>) -> Context {
+    pub fn new(window: Arc<Window>, device_selection: &DeviceSelection) -> Context {
         let (instance, debug_callback) = create_instance();
 
         // Consume the WindowBuilder, build it, and get the surface
@@ -40,8 +66,12 @@ impl Context {
             ..DeviceExtensions::empty()
         };
 
-        let (physical_device, queue_family_index) =
-            find_physical_device(instance.clone(), surface.clone(), &device_extensions);
+        let (physical_device, queue_family_index) = find_physical_device(
+            instance.clone(),
+            surface.clone(),
+            &device_extensions,
+            device_selection,
+        );
 
         let (device, graphics_queue) = create_logical_device(
             physical_device.clone(),
@@ -193,15 +223,31 @@ fn find_physical_device(
     instance: Arc<Instance>,
     surface: Arc<Surface>,
     device_extensions: &DeviceExtensions,
+    device_selection: &DeviceSelection,
 ) -> (Arc<PhysicalDevice>, u32) {
-    instance
+    let physical_devices: Vec<_> = instance
         .enumerate_physical_devices()
         .expect("could not enumerate physical devices")
-        .filter(|p| {
+        .collect();
+
+    println!("GPUs:");
+    for (index, p) in physical_devices.iter().enumerate() {
+        println!(
+            "  {}: {} ({:?})",
+            index,
+            p.properties().device_name,
+            p.properties().device_type
+        );
+    }
+
+    let suitable_devices: Vec<_> = physical_devices
+        .into_iter()
+        .enumerate()
+        .filter(|(_, p)| {
             // check if device extensions are supported
             p.supported_extensions().contains(device_extensions)
         })
-        .filter_map(|p| {
+        .filter_map(|(index, p)| {
             p.queue_family_properties()
                 .iter()
                 .enumerate()
@@ -210,8 +256,35 @@ fn find_physical_device(
                     q.queue_flags.intersects(QueueFlags::GRAPHICS)
                         && p.surface_support(i as u32, &surface).unwrap_or(false)
                 })
-                .map(|i| (p, i as u32))
+                .map(|i| (index, p, i as u32))
         })
+        .collect();
+
+    let selected_device = match device_selection {
+        DeviceSelection::Automatic => None,
+        DeviceSelection::Index(selected_index) => suitable_devices
+            .iter()
+            .find(|(index, _, _)| index == selected_index),
+        DeviceSelection::Name(name) => suitable_devices.iter().find(|(_, p, _)| {
+            p.properties()
+                .device_name
+                .to_lowercase()
+                .contains(&name.to_lowercase())
+        }),
+    };
+    if let Some((_, p, queue_family_index)) = selected_device {
+        return (p.clone(), *queue_family_index);
+    }
+    if device_selection != &DeviceSelection::Automatic {
+        println!(
+            "The GPU {:?} is missing or not suitable, picking one automatically",
+            device_selection
+        );
+    }
+
+    suitable_devices
+        .into_iter()
+        .map(|(_, p, queue_family_index)| (p, queue_family_index))
         .min_by_key(|(p, _)| {
             // prefer discrete gpus
             match p.properties().device_type {
@@ -236,6 +309,10 @@ fn create_logical_device(
         DeviceCreateInfo {
             enabled_features: Features {
                 fill_mode_non_solid: true,
+                // Lets the final image get written straight into the swapchain images
+                shader_storage_image_write_without_format: physical_device
+                    .supported_features()
+                    .shader_storage_image_write_without_format,
                 ..Default::default()
             },
             enabled_extensions: *device_extensions,
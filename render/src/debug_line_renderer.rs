@@ -0,0 +1,216 @@
+use crate::context::Context;
+use nalgebra::Matrix4;
+use scene::debug_lines::DebugLine;
+use std::sync::Arc;
+use vulkano::buffer::allocator::{SubbufferAllocator, SubbufferAllocatorCreateInfo};
+use vulkano::buffer::{BufferContents, BufferUsage};
+use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
+use vulkano::command_buffer::{
+    AutoCommandBufferBuilder, CommandBufferUsage, RenderPassBeginInfo, SubpassContents,
+};
+use vulkano::format::Format;
+use vulkano::image::ImageViewAbstract;
+use vulkano::memory::allocator::StandardMemoryAllocator;
+use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
+use vulkano::pipeline::graphics::vertex_input::Vertex;
+use vulkano::pipeline::graphics::viewport::{Viewport, ViewportState};
+use vulkano::pipeline::{GraphicsPipeline, Pipeline};
+use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass};
+use vulkano::sync::GpuFuture;
+
+#[repr(C)]
+#[derive(BufferContents, Vertex)]
+struct DebugLineVertex {
+    #[format(R32G32B32_SFLOAT)]
+    position: [f32; 3],
+
+    #[format(R32G32B32_SFLOAT)]
+    color: [f32; 3],
+}
+
+/// Draws wireframe lines on top of the final image, without any depth testing
+pub struct DebugLineRenderer {
+    render_pass: Arc<RenderPass>,
+    pipeline: Arc<GraphicsPipeline>,
+    framebuffers: Vec<Arc<Framebuffer>>,
+
+    buffer_allocator: SubbufferAllocator,
+    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+}
+
+impl DebugLineRenderer {
+    pub fn new(
+        context: &Context,
+        images: &[Arc<dyn ImageViewAbstract>],
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+    ) -> Self {
+        let render_pass = vulkano::single_pass_renderpass!(
+            context.device(),
+            attachments: {
+                color: {
+                    load: Load,
+                    store: Store,
+                    format: Format::R16G16B16A16_SFLOAT,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        )
+        .unwrap();
+
+        let pipeline = {
+            let vs = vs::load(context.device()).unwrap();
+            let fs = fs::load(context.device()).unwrap();
+
+            GraphicsPipeline::start()
+                .vertex_input_state(DebugLineVertex::per_vertex())
+                .vertex_shader(vs.entry_point("main").unwrap(), ())
+                .input_assembly_state(
+                    InputAssemblyState::new().topology(PrimitiveTopology::LineList),
+                )
+                .fragment_shader(fs.entry_point("main").unwrap(), ())
+                .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+                .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+                .build(context.device())
+                .unwrap()
+        };
+
+        let buffer_allocator = SubbufferAllocator::new(
+            memory_allocator,
+            SubbufferAllocatorCreateInfo {
+                buffer_usage: BufferUsage::VERTEX_BUFFER,
+                ..Default::default()
+            },
+        );
+
+        let framebuffers = Self::create_framebuffers(images, render_pass.clone());
+
+        Self {
+            render_pass,
+            pipeline,
+            framebuffers,
+            buffer_allocator,
+            command_buffer_allocator,
+        }
+    }
+
+    pub fn resize(&mut self, images: &[Arc<dyn ImageViewAbstract>]) {
+        self.framebuffers = Self::create_framebuffers(images, self.render_pass.clone());
+    }
+
+    fn create_framebuffers(
+        images: &[Arc<dyn ImageViewAbstract>],
+        render_pass: Arc<RenderPass>,
+    ) -> Vec<Arc<Framebuffer>> {
+        images
+            .iter()
+            .map(|image| {
+                Framebuffer::new(
+                    render_pass.clone(),
+                    FramebufferCreateInfo {
+                        attachments: vec![image.clone()],
+                        ..FramebufferCreateInfo::default()
+                    },
+                )
+                .expect("failed to create framebuffer")
+            })
+            .collect()
+    }
+
+    pub fn render<F>(
+        &self,
+        context: &Context,
+        future: F,
+        lines: &[DebugLine],
+        view_proj: Matrix4<f32>,
+        image_index: u32,
+    ) -> Box<dyn GpuFuture>
+    where
+        F: GpuFuture + 'static,
+    {
+        if lines.is_empty() {
+            return future.boxed();
+        }
+
+        let vertex_buffer = self
+            .buffer_allocator
+            .allocate_slice(lines.len() as u64 * 2)
+            .unwrap();
+        {
+            let mut vertices = vertex_buffer.write().unwrap();
+            for (line, vertices) in lines.iter().zip(vertices.chunks_exact_mut(2)) {
+                vertices[0] = DebugLineVertex {
+                    position: line.start.into(),
+                    color: line.color.into(),
+                };
+                vertices[1] = DebugLineVertex {
+                    position: line.end.into(),
+                    color: line.color.into(),
+                };
+            }
+        }
+
+        let framebuffer = self.framebuffers[image_index as usize].clone();
+        let viewport = Viewport {
+            origin: [0.0, 0.0],
+            dimensions: framebuffer.extent().map(|v| v as f32),
+            depth_range: 0.0..1.0,
+        };
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            context.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        builder
+            .begin_render_pass(
+                RenderPassBeginInfo {
+                    clear_values: vec![None],
+                    ..RenderPassBeginInfo::framebuffer(framebuffer)
+                },
+                SubpassContents::Inline,
+            )
+            .unwrap()
+            .set_viewport(0, [viewport])
+            .bind_pipeline_graphics(self.pipeline.clone())
+            .push_constants(
+                self.pipeline.layout().clone(),
+                0,
+                vs::PushConstants {
+                    viewProj: view_proj.into(),
+                },
+            )
+            .bind_vertex_buffers(0, vertex_buffer.clone())
+            .draw(vertex_buffer.len() as u32, 1, 0, 0)
+            .unwrap()
+            .end_render_pass()
+            .unwrap();
+
+        let command_buffer = builder.build().unwrap();
+
+        future
+            .then_execute(context.queue(), command_buffer)
+            .unwrap()
+            .boxed()
+    }
+}
+
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "../assets/shaders/debug_lines/debug_lines.vert",
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "../assets/shaders/debug_lines/debug_lines.frag",
+    }
+}
@@ -0,0 +1,66 @@
+use crate::context::Context;
+pub use egui_winit_vulkano::egui;
+use egui_winit_vulkano::{Gui, GuiConfig};
+use std::sync::Arc;
+use vulkano::format::Format;
+use vulkano::image::view::ImageView;
+use vulkano::image::SwapchainImage;
+use vulkano::sync::GpuFuture;
+use windowing::event::WindowEvent;
+use windowing::event_loop::EventLoop;
+
+/// Developer UI that gets drawn on top of everything else
+pub struct DevGui {
+    gui: Gui,
+    pub visible: bool,
+    /// The gui can only be drawn after it has been laid out in the current frame
+    has_layout: bool,
+}
+
+impl DevGui {
+    pub(crate) fn new(context: &Context, event_loop: &EventLoop<()>, format: Format) -> Self {
+        let gui = Gui::new(
+            event_loop,
+            context.surface(),
+            context.queue(),
+            GuiConfig {
+                preferred_format: Some(format),
+                is_overlay: true,
+                ..GuiConfig::default()
+            },
+        );
+
+        Self {
+            gui,
+            visible: false,
+            has_layout: false,
+        }
+    }
+
+    /// Returns true if the gui consumed the event
+    pub fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        self.visible && self.gui.update(event)
+    }
+
+    /// Lays out the gui for this frame, does nothing while it is hidden
+    pub fn ui(&mut self, add_contents: impl FnOnce(&egui::Context)) {
+        if !self.visible {
+            return;
+        }
+
+        self.gui.immediate_ui(|gui| add_contents(&gui.context()));
+        self.has_layout = true;
+    }
+
+    pub(crate) fn render(
+        &mut self,
+        future: Box<dyn GpuFuture>,
+        image: Arc<ImageView<SwapchainImage>>,
+    ) -> Box<dyn GpuFuture> {
+        if !std::mem::take(&mut self.has_layout) {
+            return future;
+        }
+
+        self.gui.draw_on_image(future, image)
+    }
+}
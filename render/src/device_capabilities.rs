@@ -0,0 +1,118 @@
+use crate::context::Context;
+use crate::memory_usage::device_local_memory;
+use bevy_ecs::prelude::Resource;
+use vulkano::device::physical::PhysicalDeviceType;
+use vulkano::format::{Format, FormatFeatures};
+use vulkano::image::ImageUsage;
+use vulkano::swapchain::{ColorSpace, PresentMode, SurfaceInfo};
+
+/// What the chosen GPU can do, so that features can be turned off on weaker GPUs
+#[derive(Resource, Debug, Clone)]
+pub struct DeviceCapabilities {
+    pub device_name: String,
+    pub device_type: PhysicalDeviceType,
+    pub api_version: String,
+    pub driver: String,
+    /// in bytes
+    pub device_local_memory: u64,
+    pub max_image_dimension_2d: u32,
+    pub max_push_constants_size: u32,
+    pub max_compute_work_group_size: [u32; 3],
+    pub max_sampler_anisotropy: f32,
+    pub present_modes: Vec<PresentMode>,
+    pub surface_formats: Vec<(Format, ColorSpace)>,
+    /// The surface formats that a compute shader can write to directly
+    pub storage_surface_formats: Vec<Format>,
+}
+
+impl DeviceCapabilities {
+    pub fn new(context: &Context) -> Self {
+        let device = context.device();
+        let physical_device = device.physical_device();
+        let surface = context.surface();
+        let properties = physical_device.properties();
+
+        let surface_capabilities = physical_device
+            .surface_capabilities(&surface, SurfaceInfo::default())
+            .expect("could not fetch surface capabilities");
+        let surface_formats = physical_device
+            .surface_formats(&surface, SurfaceInfo::default())
+            .expect("could not fetch surface formats");
+        let present_modes = physical_device
+            .surface_present_modes(&surface)
+            .expect("could not fetch present modes")
+            .collect();
+
+        // Sadly, the sRGB formats usually can't be used as storage images
+        let supports_storage = surface_capabilities
+            .supported_usage_flags
+            .intersects(ImageUsage::STORAGE)
+            && device
+                .enabled_features()
+                .shader_storage_image_write_without_format;
+        let storage_surface_formats = surface_formats
+            .iter()
+            .map(|(format, _)| *format)
+            .filter(|format| {
+                supports_storage
+                    && physical_device.format_properties(*format).map_or(
+                        false,
+                        |format_properties| {
+                            format_properties
+                                .optimal_tiling_features
+                                .intersects(FormatFeatures::STORAGE_IMAGE)
+                        },
+                    )
+            })
+            .collect();
+
+        Self {
+            device_name: properties.device_name.clone(),
+            device_type: properties.device_type,
+            api_version: physical_device.api_version().to_string(),
+            driver: properties
+                .driver_info
+                .clone()
+                .unwrap_or_else(|| properties.driver_version.to_string()),
+            device_local_memory: device_local_memory(physical_device),
+            max_image_dimension_2d: properties.max_image_dimension2_d,
+            max_push_constants_size: properties.max_push_constants_size,
+            max_compute_work_group_size: properties.max_compute_work_group_size,
+            max_sampler_anisotropy: properties.max_sampler_anisotropy,
+            present_modes,
+            surface_formats,
+            storage_surface_formats,
+        }
+    }
+
+    pub fn supports_storage_swapchain(&self, format: Format) -> bool {
+        self.storage_surface_formats.contains(&format)
+    }
+
+    pub fn print_report(&self) {
+        println!(
+            "Using {} ({:?}), Vulkan {}, driver {}",
+            self.device_name, self.device_type, self.api_version, self.driver
+        );
+        println!(
+            "  device local memory: {} MB",
+            self.device_local_memory / (1024 * 1024)
+        );
+        println!("  max 2D image size: {}", self.max_image_dimension_2d);
+        println!(
+            "  max push constants size: {} bytes",
+            self.max_push_constants_size
+        );
+        println!(
+            "  max compute work group size: {:?}",
+            self.max_compute_work_group_size
+        );
+        println!("  max sampler anisotropy: {}", self.max_sampler_anisotropy);
+        println!("  present modes: {:?}", self.present_modes);
+        println!("  surface formats: {:?}", self.surface_formats);
+        println!(
+            "  surface formats usable as storage images: {:?}",
+            self.storage_surface_formats
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use crate::main_renderer::{RenderScale, Renderer};
+use bevy_ecs::prelude::Resource;
+use bevy_ecs::system::{NonSend, Res, ResMut};
+use std::time::Duration;
+use time::time::Time;
+
+/// How much the render scale gets lowered at once
+const RENDER_SCALE_STEP: f32 = 0.1;
+/// How much the render scale gets raised at once, smaller so that it doesn't overshoot
+const RENDER_SCALE_RAISE_STEP: f32 = 0.05;
+/// In seconds, how long the GPU has to stay below the raise threshold before the render scale goes up again
+const RAISE_COOLDOWN: u32 = 3;
+/// The render scale gets lowered once the GPU needs more than this fraction of the frame budget
+const LOWER_THRESHOLD: f32 = 0.9;
+/// The render scale gets raised once the GPU needs less than this fraction of the frame budget.
+/// The gap between the thresholds and the [`RAISE_COOLDOWN`] keep it from jumping back and forth.
+const RAISE_THRESHOLD: f32 = 0.7;
+
+/// Adjusts the render scale once per second, based on how long the GPU took for the frames,
+/// to hold the target frame rate. It never goes above the render scale from the settings.
+#[derive(Resource)]
+pub struct DynamicResolution {
+    pub enabled: bool,
+    pub target_frame_rate: f32,
+    /// The render scale from the settings
+    pub max_scale: f32,
+    /// Of the last second
+    pub average_gpu_frame_time: Duration,
+    elapsed: Duration,
+    gpu_frame_time_sum: Duration,
+    frame_count: u32,
+    /// In seconds, how long the GPU has been below the raise threshold
+    seconds_below_raise_threshold: u32,
+}
+
+impl DynamicResolution {
+    pub fn new(enabled: bool, target_frame_rate: f32, max_scale: f32) -> Self {
+        Self {
+            enabled,
+            target_frame_rate,
+            max_scale,
+            average_gpu_frame_time: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            gpu_frame_time_sum: Duration::ZERO,
+            frame_count: 0,
+            seconds_below_raise_threshold: 0,
+        }
+    }
+
+    /// The render scale for the next second.
+    /// The GPU time grows with the square of the scale, so a step down can easily drop below the
+    /// raise threshold. Raising it waits for a few calm seconds, and only happens when the
+    /// higher scale is expected to stay below the lower threshold.
+    fn next_scale(&mut self, scale: f32) -> f32 {
+        let frame_budget = 1.0 / self.target_frame_rate.max(1.0);
+        let load = self.average_gpu_frame_time.as_secs_f32() / frame_budget;
+        let max_scale = self.max_scale.clamp(RenderScale::MIN, RenderScale::MAX);
+        if load > LOWER_THRESHOLD {
+            self.seconds_below_raise_threshold = 0;
+            return (scale - RENDER_SCALE_STEP).max(RenderScale::MIN);
+        }
+        if load >= RAISE_THRESHOLD {
+            self.seconds_below_raise_threshold = 0;
+            return scale.min(max_scale);
+        }
+
+        self.seconds_below_raise_threshold += 1;
+        let raised_scale = (scale + RENDER_SCALE_RAISE_STEP).min(max_scale);
+        let raised_load = load * (raised_scale / scale.max(RenderScale::MIN)).powi(2);
+        if self.seconds_below_raise_threshold >= RAISE_COOLDOWN && raised_load < LOWER_THRESHOLD {
+            self.seconds_below_raise_threshold = 0;
+            raised_scale
+        } else {
+            scale.min(max_scale)
+        }
+    }
+}
+
+pub(crate) fn update_dynamic_resolution(
+    renderer: NonSend<Renderer>,
+    time: Res<Time>,
+    mut dynamic_resolution: ResMut<DynamicResolution>,
+    mut render_scale: ResMut<RenderScale>,
+) {
+    if let Some(gpu_frame_time) = renderer.gpu_frame_time() {
+        dynamic_resolution.gpu_frame_time_sum += gpu_frame_time;
+        dynamic_resolution.frame_count += 1;
+    }
+    dynamic_resolution.elapsed += time.delta();
+    if dynamic_resolution.elapsed < Duration::from_secs(1) {
+        return;
+    }
+
+    if dynamic_resolution.frame_count > 0 {
+        dynamic_resolution.average_gpu_frame_time =
+            dynamic_resolution.gpu_frame_time_sum / dynamic_resolution.frame_count;
+
+        if dynamic_resolution.enabled {
+            let scale = dynamic_resolution.next_scale(render_scale.scale);
+            // Resizing the render targets waits for the GPU, so it only happens when needed
+            if scale != render_scale.scale {
+                render_scale.scale = scale;
+            }
+        }
+    }
+    dynamic_resolution.elapsed = Duration::ZERO;
+    dynamic_resolution.gpu_frame_time_sum = Duration::ZERO;
+    dynamic_resolution.frame_count = 0;
+}
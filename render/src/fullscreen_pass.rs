@@ -0,0 +1,365 @@
+use crate::context::Context;
+use crate::quad::{self, quad_mesh, QuadVertex};
+use std::sync::Arc;
+use vulkano::buffer::{BufferContents, Subbuffer};
+use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
+use vulkano::command_buffer::{
+    AutoCommandBufferBuilder, CommandBufferUsage, RenderPassBeginInfo, SubpassContents,
+};
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::descriptor_set::layout::DescriptorSetLayout;
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::format::Format;
+use vulkano::image::view::ImageView;
+use vulkano::image::{AttachmentImage, ImageAccess, ImageUsage, ImageViewAbstract};
+use vulkano::memory::allocator::StandardMemoryAllocator;
+use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
+use vulkano::pipeline::graphics::vertex_input::Vertex;
+use vulkano::pipeline::graphics::viewport::{Viewport, ViewportState};
+use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint};
+use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass};
+use vulkano::sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerMipmapMode};
+use vulkano::shader::ShaderModule;
+use vulkano::sync::GpuFuture;
+
+/// Renders a full-screen quad with a custom fragment shader into HDR images.
+/// The fragment shader gets the previous image at set 0, binding 0 and can use push constants.
+/// Additional images, like the depth buffer, come after it at binding 1, 2, ...
+/// Resources that do not change with the swapchain image can be bound at set 1, see [`FullscreenPass::render_with_set`].
+/// Meant to be used by post-processing effects.
+pub(crate) struct FullscreenPass {
+    pipeline: Arc<GraphicsPipeline>,
+    render_pass: Arc<RenderPass>,
+    framebuffers: Vec<Arc<Framebuffer>>,
+    output_images: Vec<Arc<ImageView<AttachmentImage>>>,
+
+    sampler: Arc<Sampler>,
+    /// For the additional images, which are usually depth or normal buffers that cannot be filtered
+    nearest_sampler: Arc<Sampler>,
+    descriptor_sets: Vec<Arc<PersistentDescriptorSet>>,
+    index_buffer: Subbuffer<[u32]>,
+    vertex_buffer: Subbuffer<[QuadVertex]>,
+
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+    descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+}
+
+impl FullscreenPass {
+    pub fn new(
+        context: &Context,
+        fragment_shader: Arc<ShaderModule>,
+        input_images: Vec<Arc<dyn ImageViewAbstract>>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> Self {
+        Self::new_with_additional_images(
+            context,
+            fragment_shader,
+            input_images,
+            vec![],
+            memory_allocator,
+            command_buffer_allocator,
+            descriptor_set_allocator,
+        )
+    }
+
+    /// Every entry of the additional images has one image per swapchain image
+    pub fn new_with_additional_images(
+        context: &Context,
+        fragment_shader: Arc<ShaderModule>,
+        input_images: Vec<Arc<dyn ImageViewAbstract>>,
+        additional_images: Vec<Vec<Arc<dyn ImageViewAbstract>>>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> Self {
+        let (vertex_buffer, index_buffer) =
+            quad::create_geometry_buffers(quad_mesh(), memory_allocator.clone());
+
+        let render_pass = vulkano::single_pass_renderpass!(context.device(),
+            attachments: {
+                color: {
+                    load: DontCare,
+                    store: Store,
+                    format: Format::R16G16B16A16_SFLOAT,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        )
+        .unwrap();
+
+        let pipeline = {
+            let vertex_shader = vs::load(context.device()).unwrap();
+
+            GraphicsPipeline::start()
+                .vertex_input_state(QuadVertex::per_vertex())
+                .vertex_shader(vertex_shader.entry_point("main").unwrap(), ())
+                .input_assembly_state(InputAssemblyState::new())
+                .fragment_shader(fragment_shader.entry_point("main").unwrap(), ())
+                .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+                .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+                .build(context.device())
+                .unwrap()
+        };
+
+        let sampler = Sampler::new(
+            context.device(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                mipmap_mode: SamplerMipmapMode::Nearest,
+                ..SamplerCreateInfo::default()
+            },
+        )
+        .unwrap();
+
+        let nearest_sampler = Sampler::new(
+            context.device(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Nearest,
+                min_filter: Filter::Nearest,
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                mipmap_mode: SamplerMipmapMode::Nearest,
+                ..SamplerCreateInfo::default()
+            },
+        )
+        .unwrap();
+
+        let mut fullscreen_pass = Self {
+            pipeline,
+            render_pass,
+            framebuffers: vec![],
+            output_images: vec![],
+
+            sampler,
+            nearest_sampler,
+            descriptor_sets: vec![],
+            index_buffer,
+            vertex_buffer,
+
+            memory_allocator,
+            command_buffer_allocator,
+            descriptor_set_allocator,
+        };
+        fullscreen_pass.resize_with_additional_images(input_images, additional_images);
+        fullscreen_pass
+    }
+
+    pub fn resize(&mut self, input_images: Vec<Arc<dyn ImageViewAbstract>>) {
+        self.resize_with_additional_images(input_images, vec![]);
+    }
+
+    pub fn resize_with_additional_images(
+        &mut self,
+        input_images: Vec<Arc<dyn ImageViewAbstract>>,
+        additional_images: Vec<Vec<Arc<dyn ImageViewAbstract>>>,
+    ) {
+        let dimensions = input_images[0].image().dimensions().width_height();
+
+        self.output_images = (0..input_images.len())
+            .map(|_| {
+                ImageView::new_default(
+                    AttachmentImage::with_usage(
+                        &self.memory_allocator,
+                        dimensions,
+                        Format::R16G16B16A16_SFLOAT,
+                        ImageUsage::SAMPLED | ImageUsage::COLOR_ATTACHMENT,
+                    )
+                    .unwrap(),
+                )
+                .unwrap()
+            })
+            .collect();
+
+        self.framebuffers = self
+            .output_images
+            .iter()
+            .map(|image| {
+                Framebuffer::new(
+                    self.render_pass.clone(),
+                    FramebufferCreateInfo {
+                        attachments: vec![image.clone()],
+                        ..FramebufferCreateInfo::default()
+                    },
+                )
+                .expect("failed to create framebuffer")
+            })
+            .collect();
+
+        let layout = self.pipeline.layout().set_layouts().get(0).unwrap();
+        self.descriptor_sets = Self::create_descriptor_sets(
+            layout,
+            &input_images,
+            &additional_images,
+            self.sampler.clone(),
+            self.nearest_sampler.clone(),
+            &self.descriptor_set_allocator,
+        );
+    }
+
+    fn create_descriptor_sets(
+        layout: &Arc<DescriptorSetLayout>,
+        images: &[Arc<dyn ImageViewAbstract>],
+        additional_images: &[Vec<Arc<dyn ImageViewAbstract>>],
+        sampler: Arc<Sampler>,
+        nearest_sampler: Arc<Sampler>,
+        descriptor_set_allocator: &StandardDescriptorSetAllocator,
+    ) -> Vec<Arc<PersistentDescriptorSet>> {
+        images
+            .iter()
+            .enumerate()
+            .map(|(index, image)| {
+                let additional_writes =
+                    additional_images
+                        .iter()
+                        .enumerate()
+                        .map(|(binding, images)| {
+                            WriteDescriptorSet::image_view_sampler(
+                                binding as u32 + 1,
+                                images[index].clone(),
+                                nearest_sampler.clone(),
+                            )
+                        });
+
+                PersistentDescriptorSet::new(
+                    descriptor_set_allocator,
+                    layout.clone(),
+                    [WriteDescriptorSet::image_view_sampler(
+                        0,
+                        image.clone(),
+                        sampler.clone(),
+                    )]
+                    .into_iter()
+                    .chain(additional_writes),
+                )
+                .unwrap()
+            })
+            .collect()
+    }
+
+    pub fn render<Pc>(
+        &self,
+        context: &Context,
+        future: Box<dyn GpuFuture>,
+        image_index: u32,
+        push_constants: Pc,
+    ) -> Box<dyn GpuFuture>
+    where
+        Pc: BufferContents,
+    {
+        self.render_with_optional_set(context, future, image_index, push_constants, None)
+    }
+
+    /// Also binds a descriptor set at set 1, which has to use [`FullscreenPass::set_layout`]
+    pub fn render_with_set<Pc>(
+        &self,
+        context: &Context,
+        future: Box<dyn GpuFuture>,
+        image_index: u32,
+        push_constants: Pc,
+        descriptor_set: Arc<PersistentDescriptorSet>,
+    ) -> Box<dyn GpuFuture>
+    where
+        Pc: BufferContents,
+    {
+        self.render_with_optional_set(
+            context,
+            future,
+            image_index,
+            push_constants,
+            Some(descriptor_set),
+        )
+    }
+
+    /// The layout of a descriptor set of the fragment shader
+    pub fn set_layout(&self, set: usize) -> Arc<DescriptorSetLayout> {
+        self.pipeline.layout().set_layouts()[set].clone()
+    }
+
+    fn render_with_optional_set<Pc>(
+        &self,
+        context: &Context,
+        future: Box<dyn GpuFuture>,
+        image_index: u32,
+        push_constants: Pc,
+        descriptor_set: Option<Arc<PersistentDescriptorSet>>,
+    ) -> Box<dyn GpuFuture>
+    where
+        Pc: BufferContents,
+    {
+        let output_image = &self.output_images[image_index as usize];
+        let viewport = Viewport {
+            origin: [0.0, 0.0],
+            dimensions: output_image
+                .image()
+                .dimensions()
+                .width_height()
+                .map(|v| v as f32),
+            depth_range: 0.0..1.0,
+        };
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            context.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        builder
+            .set_viewport(0, [viewport])
+            .begin_render_pass(
+                RenderPassBeginInfo {
+                    clear_values: vec![None],
+                    ..RenderPassBeginInfo::framebuffer(
+                        self.framebuffers[image_index as usize].clone(),
+                    )
+                },
+                SubpassContents::Inline,
+            )
+            .unwrap()
+            .bind_pipeline_graphics(self.pipeline.clone())
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                self.pipeline.layout().clone(),
+                0,
+                std::iter::once(self.descriptor_sets[image_index as usize].clone())
+                    .chain(descriptor_set)
+                    .collect::<Vec<_>>(),
+            )
+            .push_constants(self.pipeline.layout().clone(), 0, push_constants)
+            .bind_index_buffer(self.index_buffer.clone())
+            .bind_vertex_buffers(0, self.vertex_buffer.clone())
+            .draw_indexed(6, 1, 0, 0, 0)
+            .unwrap()
+            .end_render_pass()
+            .unwrap();
+
+        let command_buffer = builder.build().unwrap();
+
+        future
+            .then_execute(context.queue(), command_buffer)
+            .unwrap()
+            .boxed()
+    }
+
+    pub fn output_images(&self) -> Vec<Arc<dyn ImageViewAbstract>> {
+        self.output_images
+            .iter()
+            .map(|image| image.clone() as Arc<dyn ImageViewAbstract>)
+            .collect()
+    }
+}
+
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "../assets/shaders/quad/quad.vert",
+    }
+}
@@ -0,0 +1,160 @@
+use crate::context::Context;
+use std::sync::Arc;
+use std::time::Duration;
+use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage};
+use vulkano::device::Device;
+use vulkano::query::{QueryPool, QueryPoolCreateInfo, QueryResultFlags, QueryType};
+use vulkano::sync::{GpuFuture, PipelineStage};
+
+/// Measures how long the GPU needs for a frame, with a timestamp query before the first
+/// and after the last command buffer of the frame.
+/// Every swapchain image gets its own pair of queries, which can be read once the fence of
+/// the image has been signalled.
+pub(crate) struct GpuTimer {
+    device: Arc<Device>,
+    /// None if the queue doesn't support timestamps
+    query_pool: Option<Arc<QueryPool>>,
+    is_supported: bool,
+    /// Whether the queries of a swapchain image have been submitted
+    submitted: Vec<bool>,
+    /// in nanoseconds per tick
+    timestamp_period: f32,
+    /// Only the valid bits of a timestamp get compared
+    timestamp_mask: u64,
+    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+}
+
+impl GpuTimer {
+    pub fn new(
+        context: &Context,
+        swapchain_image_count: u32,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+    ) -> Self {
+        let device = context.device();
+        let physical_device = device.physical_device();
+        let timestamp_valid_bits = physical_device.queue_family_properties()
+            [context.queue_family_index() as usize]
+            .timestamp_valid_bits;
+        if timestamp_valid_bits.is_none() {
+            println!("The GPU doesn't support timestamps, the GPU frame time won't be measured");
+        }
+        let timestamp_mask = match timestamp_valid_bits {
+            Some(bits) if bits < 64 => (1 << bits) - 1,
+            _ => u64::MAX,
+        };
+
+        let mut gpu_timer = Self {
+            device: device.clone(),
+            query_pool: None,
+            is_supported: timestamp_valid_bits.is_some(),
+            submitted: vec![],
+            timestamp_period: physical_device.properties().timestamp_period,
+            timestamp_mask,
+            command_buffer_allocator,
+        };
+        gpu_timer.resize(swapchain_image_count);
+        gpu_timer
+    }
+
+    pub fn resize(&mut self, swapchain_image_count: u32) {
+        if !self.is_supported {
+            return;
+        }
+
+        self.query_pool = Some(
+            QueryPool::new(
+                self.device.clone(),
+                QueryPoolCreateInfo {
+                    query_count: swapchain_image_count * 2,
+                    ..QueryPoolCreateInfo::query_type(QueryType::Timestamp)
+                },
+            )
+            .unwrap(),
+        );
+        self.submitted = vec![false; swapchain_image_count as usize];
+    }
+
+    /// Has to be called after waiting for the fence of the swapchain image
+    pub fn read(&self, image_index: u32) -> Option<Duration> {
+        let query_pool = self.query_pool.as_ref()?;
+        if !self.submitted[image_index as usize] {
+            return None;
+        }
+
+        let mut timestamps = [0u64; 2];
+        let is_available = query_pool
+            .queries_range(image_index * 2..image_index * 2 + 2)
+            .unwrap()
+            .get_results(&mut timestamps, QueryResultFlags::empty())
+            .unwrap_or(false);
+        if !is_available {
+            return None;
+        }
+
+        let ticks = timestamps[1].wrapping_sub(timestamps[0]) & self.timestamp_mask;
+        Some(Duration::from_nanos(
+            (ticks as f64 * self.timestamp_period as f64) as u64,
+        ))
+    }
+
+    pub fn start<F>(&mut self, context: &Context, future: F, image_index: u32) -> Box<dyn GpuFuture>
+    where
+        F: GpuFuture + 'static,
+    {
+        let query_pool = match &self.query_pool {
+            Some(query_pool) => query_pool.clone(),
+            None => return future.boxed(),
+        };
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            context.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        // Safety: the fence of the swapchain image has been waited for, so the queries aren't in use
+        unsafe {
+            builder
+                .reset_query_pool(query_pool.clone(), image_index * 2..image_index * 2 + 2)
+                .unwrap()
+                .write_timestamp(query_pool, image_index * 2, PipelineStage::TopOfPipe)
+                .unwrap();
+        }
+        self.submitted[image_index as usize] = true;
+
+        future
+            .then_execute(context.queue(), builder.build().unwrap())
+            .unwrap()
+            .boxed()
+    }
+
+    pub fn end<F>(&self, context: &Context, future: F, image_index: u32) -> Box<dyn GpuFuture>
+    where
+        F: GpuFuture + 'static,
+    {
+        let query_pool = match &self.query_pool {
+            Some(query_pool) => query_pool.clone(),
+            None => return future.boxed(),
+        };
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            context.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        unsafe {
+            builder
+                .write_timestamp(query_pool, image_index * 2 + 1, PipelineStage::BottomOfPipe)
+                .unwrap();
+        }
+
+        future
+            .then_execute(context.queue(), builder.build().unwrap())
+            .unwrap()
+            .boxed()
+    }
+}
@@ -1,14 +1,39 @@
+mod billboard_renderer;
 mod bloom_renderer;
+mod color_grading_renderer;
 pub mod context;
 mod custom_storage_image;
+mod debug_line_renderer;
+#[cfg(feature = "dev-tools")]
+pub mod dev_gui;
+mod device_capabilities;
+mod dynamic_resolution;
+mod fullscreen_pass;
+mod ghost_renderer;
+mod gpu_timer;
 mod main_renderer;
+mod memory_usage;
 mod model_uploader;
+mod occlusion_culling;
+mod outline_renderer;
+mod post_process;
 mod quad;
 mod quad_renderer;
+mod reflection_renderer;
+mod rewind_distortion_renderer;
 mod scene;
 mod scene_renderer;
+mod screenshot;
 mod shadow_renderer;
+mod tonemap_renderer;
 mod ui_renderer;
 
+pub use crate::bloom_renderer::BloomSettings;
+pub use crate::context::DeviceSelection;
+pub use crate::device_capabilities::DeviceCapabilities;
+pub use crate::dynamic_resolution::DynamicResolution;
 pub use crate::main_renderer::*;
+pub use crate::memory_usage::{GpuMemoryUsage, MemoryCategory};
 pub use crate::model_uploader::create_gpu_models;
+pub use crate::shadow_renderer::ShadowSettings;
+pub use crate::tonemap_renderer::{Tonemapper, TonemappingSettings};
@@ -1,66 +1,186 @@
-use crate::bloom_renderer::BloomRenderer;
-use crate::context::Context;
+use crate::billboard_renderer::BillboardRenderer;
+use crate::bloom_renderer::{BloomRenderer, BloomSettings};
+use crate::color_grading_renderer::ColorGradingRenderer;
+use crate::context::{Context, DeviceSelection};
 use crate::create_gpu_models;
+use crate::debug_line_renderer::DebugLineRenderer;
+#[cfg(feature = "dev-tools")]
+use crate::dev_gui::DevGui;
+use crate::device_capabilities::DeviceCapabilities;
+use crate::dynamic_resolution::{update_dynamic_resolution, DynamicResolution};
+use crate::ghost_renderer::GhostRenderer;
+use crate::gpu_timer::GpuTimer;
+use crate::memory_usage::{image_views_bytes, GpuMemoryUsage, MemoryCategory};
 use crate::model_uploader::{
-    create_ui_component, update_gpu_models, ModelUploaderAllocator, SamplerInfoMap,
+    create_gpu_billboards, create_gpu_decals, create_ui_component, free_unused_gpu_assets,
+    update_gpu_models, update_gpu_video_textures, ModelUploaderAllocator, SamplerInfoMap,
 };
+use crate::occlusion_culling::DepthPyramidRenderer;
+use crate::outline_renderer::OutlineRenderer;
+use crate::post_process::{PostProcessChain, PostProcessFrame};
 use crate::quad_renderer::QuadRenderer;
+use crate::reflection_renderer::ReflectionRenderer;
+use crate::rewind_distortion_renderer::RewindDistortionRenderer;
+use crate::scene::billboard::GpuBillboard;
+use crate::scene::decal::GpuDecal;
 use crate::scene::material::Material;
 use crate::scene::mesh::Mesh;
 use crate::scene::model::GpuModel;
 use crate::scene::texture::Texture;
-use crate::scene_renderer::SceneRenderer;
-use crate::shadow_renderer::ShadowRenderer;
+use crate::scene::video_texture::GpuVideoTexture;
+use crate::scene_renderer::{frustum_bounding_sphere, SceneRenderer};
+use crate::screenshot::Screenshot;
+use crate::shadow_renderer::{
+    ShadowRenderer, ShadowSettings, MAX_PCF_SAMPLES, MAX_SHADOW_LIGHTS, SHADOW_FAR,
+};
+use crate::tonemap_renderer::{TonemapRenderer, TonemappingSettings};
 use app::plugin::{Plugin, PluginAppAccess};
-use bevy_ecs::prelude::{Local, Resource};
+use bevy_ecs::prelude::{Entity, Local, Resource};
 use bevy_ecs::query::With;
 use bevy_ecs::schedule::{IntoSystemConfig, SystemSet};
-use bevy_ecs::system::{NonSend, NonSendMut, Query, Res};
+use bevy_ecs::system::{NonSend, NonSendMut, Query, Res, ResMut, SystemParam};
 use levels::current_level::CurrentLevel;
 use levels::level_id::LevelId;
+use nalgebra::{Point3, Vector3};
 use scene::asset::Assets;
+use scene::billboard::Billboard;
 use scene::camera::Camera;
-use scene::light::{CastsShadow, Light, LightCastShadow};
+use scene::color_grading::ColorGrading;
+use scene::debug_lines::DebugLines;
+use scene::flash::Flash;
+use scene::fog::Fog;
+use scene::highlight::Highlighted;
+use scene::light::{AmbientLight, CastsShadow, Light, LightCastShadow};
+use scene::material::TextureScroll;
+use scene::rewind_ghosts::RewindGhosts;
+use scene::screen_glitch::ScreenGlitch;
+use scene::screen_shake::ScreenShake;
+use scene::sky::Sky;
+use scene::spatial_grid::{update_spatial_grid, SpatialGrid};
 use scene::transform::Transform;
-use scene::ui_component::UIComponent;
+use scene::ui_component::{UIComponent, UIProgressBar, UIScaling, UIVisibility};
+use std::collections::HashSet;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use time::time_manager::TimeManager;
+use vulkano::buffer::Subbuffer;
 use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
+use vulkano::command_buffer::{
+    AutoCommandBufferBuilder, CommandBufferUsage, CopyBufferToImageInfo,
+};
 use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
 use vulkano::device::Device;
 use vulkano::format::Format;
 use vulkano::image::view::ImageView;
-use vulkano::image::{ImageAccess, ImageUsage, SwapchainImage};
+use vulkano::image::{ImageAccess, ImageUsage, ImageViewAbstract, StorageImage, SwapchainImage};
 use vulkano::memory::allocator::StandardMemoryAllocator;
 use vulkano::pipeline::graphics::viewport::Viewport;
 use vulkano::swapchain::{
-    acquire_next_image, AcquireError, ColorSpace, PresentMode, Surface, SurfaceInfo, Swapchain,
-    SwapchainCreateInfo, SwapchainCreationError, SwapchainPresentInfo,
+    acquire_next_image, AcquireError, ColorSpace, PresentFuture, PresentMode, Surface, SurfaceInfo,
+    Swapchain, SwapchainCreateInfo, SwapchainCreationError, SwapchainPresentInfo,
 };
 use vulkano::sync;
+use vulkano::sync::future::FenceSignalFuture;
 use vulkano::sync::{FlushError, GpuFuture};
+#[cfg(feature = "dev-tools")]
+use windowing::window::EventLoopContainer;
 use windowing::window::WindowManager;
 
 use crate::scene::ui_component::GpuUIComponent;
 use crate::ui_renderer::UIRenderer;
 use windowing::window::Window;
 
+/// The CPU prepares the next frame while the GPU renders the current one
+pub const DEFAULT_FRAMES_IN_FLIGHT: u32 = 2;
+
 #[derive(Resource)]
 pub struct ViewFrustumCullingMode {
     pub enabled: bool,
 }
 
+/// Skips objects that were hidden behind other objects in the last frame
+#[derive(Resource)]
+pub struct OcclusionCullingMode {
+    pub enabled: bool,
+}
+
+/// Renders the depth and normals of the scene before shading it
+#[derive(Resource)]
+pub struct DepthPrepassMode {
+    pub enabled: bool,
+}
+
+/// Renders the scene at a fraction of the window resolution, the final image gets scaled to fit the
+/// window. Lower values are faster, higher values are a form of supersampling.
+#[derive(Resource)]
+pub struct RenderScale {
+    pub scale: f32,
+}
+
+impl RenderScale {
+    pub const MIN: f32 = 0.5;
+    pub const MAX: f32 = 2.0;
+
+    /// The resolution of the scene images, at least one pixel
+    pub fn scaled_dimensions(&self, dimensions: [u32; 2]) -> [u32; 2] {
+        let scale = self.scale.clamp(Self::MIN, Self::MAX);
+        dimensions.map(|i| ((i as f32 * scale).round() as u32).max(1))
+    }
+}
+
+/// Waits for the GPU to finish every frame before the next one gets started.
+/// Lowers the input delay, especially in fullscreen mode, but the CPU and the GPU don't work
+/// at the same time anymore.
+#[derive(Resource)]
+pub struct LowLatencyMode {
+    pub enabled: bool,
+}
+
+type FrameFence = FenceSignalFuture<PresentFuture<Box<dyn GpuFuture>>>;
+
 /// Responsible for keeping the swapchain up-to-date and calling the sub-rendersystems
 pub struct Renderer {
     recreate_swapchain: bool,
-    previous_frame_end: Option<Box<dyn GpuFuture>>,
+    /// One fence per swapchain image, which gets signalled once the GPU is done with the last frame
+    /// that was rendered into that image. The sub-renderers have one set of resources per
+    /// swapchain image, so waiting for the fence makes them safe to reuse.
+    frame_fences: Vec<Option<Arc<FrameFence>>>,
+    previous_fence_index: usize,
     swapchain: SwapchainContainer,
     shadow_renderer: ShadowRenderer,
     scene_renderer: SceneRenderer,
-    bloom_renderer: BloomRenderer,
+    depth_pyramid_renderer: DepthPyramidRenderer,
+    ghost_renderer: GhostRenderer,
+    outline_renderer: OutlineRenderer,
+    billboard_renderer: BillboardRenderer,
+    reflection_renderer: ReflectionRenderer,
+    post_process_chain: PostProcessChain,
+    debug_line_renderer: DebugLineRenderer,
     quad_renderer: QuadRenderer,
     ui_renderer: UIRenderer,
+    gpu_timer: GpuTimer,
+    /// How long the GPU took for the last frame that has finished
+    gpu_frame_time: Option<Duration>,
     viewport: Viewport,
+    /// The viewport of the scaled scene images
+    scene_viewport: Viewport,
+    render_scale: RenderScale,
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+    /// GPU memory of the render targets, they get recreated when the window gets resized
+    attachment_bytes: u64,
+    /// Where the next frame should be saved to
+    screenshot_path: Option<PathBuf>,
+    frame_capture: Option<FrameCapture>,
+    /// The video frames that get copied into their images at the start of the next frame
+    video_uploads: Vec<(Subbuffer<[u8]>, Arc<StorageImage>)>,
+}
+
+/// Saves every rendered frame, for recording videos
+struct FrameCapture {
+    directory: PathBuf,
+    frame_index: u32,
 }
 
 struct SwapchainContainer {
@@ -70,10 +190,21 @@ struct SwapchainContainer {
 }
 
 impl Renderer {
-    pub fn new(context: &Context, brightness: f32) -> Renderer {
-        let previous_frame_end = Some(sync::now(context.device()).boxed());
-
-        let swapchain = SwapchainContainer::new(context.device(), context.surface());
+    pub fn new(
+        context: &Context,
+        capabilities: &DeviceCapabilities,
+        brightness: f32,
+        max_shadow_lights: usize,
+        shadow_settings: &ShadowSettings,
+        frames_in_flight: u32,
+        render_scale: RenderScale,
+    ) -> Renderer {
+        let swapchain = SwapchainContainer::new(
+            context.device(),
+            context.surface(),
+            frames_in_flight,
+            capabilities,
+        );
 
         let viewport = Viewport {
             origin: [0.0, 0.0],
@@ -91,12 +222,19 @@ impl Renderer {
         let descriptor_set_allocator =
             Arc::new(StandardDescriptorSetAllocator::new(context.device()));
 
-        let dimensions = swapchain.swapchain.image_extent();
+        let dimensions = render_scale.scaled_dimensions(swapchain.dimensions);
+        let scene_viewport = Viewport {
+            origin: [0.0, 0.0],
+            dimensions: dimensions.map(|i| i as f32),
+            depth_range: 0.0..1.0,
+        };
         let swapchain_image_count = swapchain.swapchain.image_count();
 
         let shadow_renderer = ShadowRenderer::new(
             context,
             swapchain_image_count,
+            max_shadow_lights,
+            shadow_settings.resolution,
             memory_allocator.clone(),
             command_buffer_allocator.clone(),
             descriptor_set_allocator.clone(),
@@ -112,23 +250,104 @@ impl Renderer {
             descriptor_set_allocator.clone(),
         );
 
-        let bloom_renderer = BloomRenderer::new(
+        let depth_pyramid_renderer = DepthPyramidRenderer::new(
             context,
-            scene_renderer.output_images().clone(),
+            scene_renderer.depth_images(),
             memory_allocator.clone(),
             command_buffer_allocator.clone(),
             descriptor_set_allocator.clone(),
         );
 
+        let ghost_renderer = GhostRenderer::new(
+            context,
+            &scene_renderer.output_images(),
+            &scene_renderer.depth_images(),
+            memory_allocator.clone(),
+            command_buffer_allocator.clone(),
+            descriptor_set_allocator.clone(),
+        );
+
+        let outline_renderer = OutlineRenderer::new(
+            context,
+            &scene_renderer.output_images(),
+            &scene_renderer.depth_images(),
+            memory_allocator.clone(),
+            command_buffer_allocator.clone(),
+            descriptor_set_allocator.clone(),
+        );
+
+        let billboard_renderer = BillboardRenderer::new(
+            context,
+            &scene_renderer.output_images(),
+            &scene_renderer.depth_images(),
+            memory_allocator.clone(),
+            command_buffer_allocator.clone(),
+            descriptor_set_allocator.clone(),
+        );
+
+        let reflection_renderer = ReflectionRenderer::new(
+            context,
+            scene_renderer.output_images(),
+            scene_renderer.gbuffer(),
+            memory_allocator.clone(),
+            command_buffer_allocator.clone(),
+            descriptor_set_allocator.clone(),
+        );
+
+        let mut post_process_chain = PostProcessChain::new(reflection_renderer.output_images());
+        post_process_chain.add_effect(|input_images| {
+            BloomRenderer::new(
+                context,
+                input_images,
+                memory_allocator.clone(),
+                command_buffer_allocator.clone(),
+                descriptor_set_allocator.clone(),
+            )
+        });
+        post_process_chain.add_effect(|input_images| {
+            TonemapRenderer::new(
+                context,
+                input_images,
+                memory_allocator.clone(),
+                command_buffer_allocator.clone(),
+                descriptor_set_allocator.clone(),
+                brightness,
+            )
+        });
+        post_process_chain.add_effect(|input_images| {
+            RewindDistortionRenderer::new(
+                context,
+                input_images,
+                memory_allocator.clone(),
+                command_buffer_allocator.clone(),
+                descriptor_set_allocator.clone(),
+            )
+        });
+        post_process_chain.add_effect(|input_images| {
+            ColorGradingRenderer::new(
+                context,
+                input_images,
+                memory_allocator.clone(),
+                command_buffer_allocator.clone(),
+                descriptor_set_allocator.clone(),
+            )
+        });
+
+        let debug_line_renderer = DebugLineRenderer::new(
+            context,
+            &post_process_chain.output_images(),
+            memory_allocator.clone(),
+            command_buffer_allocator.clone(),
+        );
+
         let quad_renderer = QuadRenderer::new(
             context,
-            &bloom_renderer.output_images(),
+            &post_process_chain.output_images(),
             &swapchain.images,
             swapchain.swapchain.image_format(),
             memory_allocator.clone(),
             command_buffer_allocator.clone(),
             descriptor_set_allocator.clone(),
-            brightness,
         );
 
         let ui_renderer = UIRenderer::new(
@@ -140,36 +359,264 @@ impl Renderer {
             descriptor_set_allocator.clone(),
         );
 
-        Renderer {
+        let gpu_timer = GpuTimer::new(
+            context,
+            swapchain_image_count,
+            command_buffer_allocator.clone(),
+        );
+
+        let mut renderer = Renderer {
             recreate_swapchain: false,
-            previous_frame_end,
+            frame_fences: vec![None; swapchain.images.len()],
+            previous_fence_index: 0,
             swapchain,
             shadow_renderer,
             scene_renderer,
-            bloom_renderer,
+            depth_pyramid_renderer,
+            ghost_renderer,
+            outline_renderer,
+            billboard_renderer,
+            reflection_renderer,
+            post_process_chain,
+            debug_line_renderer,
             quad_renderer,
             ui_renderer,
+            gpu_timer,
+            gpu_frame_time: None,
             viewport,
-        }
+            scene_viewport,
+            render_scale,
+            memory_allocator,
+            command_buffer_allocator,
+            attachment_bytes: 0,
+            screenshot_path: None,
+            frame_capture: None,
+            video_uploads: vec![],
+        };
+        renderer.update_attachment_bytes();
+        renderer
+    }
+
+    /// Recreates everything that depends on the window size or on the render scale
+    fn resize_render_targets(&mut self) {
+        let dimensions = self
+            .render_scale
+            .scaled_dimensions(self.swapchain.dimensions);
+        self.scene_viewport.dimensions = dimensions.map(|i| i as f32);
+
+        self.shadow_renderer
+            .resize(self.swapchain.images.len() as u32);
+        self.scene_renderer.resize(
+            dimensions,
+            self.swapchain.images.len() as u32,
+            self.shadow_renderer.get_shadow_cube_maps(),
+        );
+        self.depth_pyramid_renderer
+            .resize(self.scene_renderer.depth_images());
+        self.ghost_renderer.resize(
+            &self.scene_renderer.output_images(),
+            &self.scene_renderer.depth_images(),
+        );
+        self.outline_renderer.resize(
+            &self.scene_renderer.output_images(),
+            &self.scene_renderer.depth_images(),
+        );
+        self.billboard_renderer.resize(
+            &self.scene_renderer.output_images(),
+            &self.scene_renderer.depth_images(),
+        );
+        self.reflection_renderer.resize(
+            self.scene_renderer.output_images(),
+            self.scene_renderer.gbuffer(),
+        );
+        self.post_process_chain
+            .resize(self.reflection_renderer.output_images());
+        self.debug_line_renderer
+            .resize(&self.post_process_chain.output_images());
+
+        self.quad_renderer.resize(
+            &self.swapchain.images,
+            &self.post_process_chain.output_images(),
+        );
+
+        self.ui_renderer.resize(&self.swapchain.images);
+
+        self.update_attachment_bytes();
+    }
+
+    /// The swapchain images don't count, since the driver allocates them
+    fn update_attachment_bytes(&mut self) {
+        let shadow_maps: Vec<Arc<dyn ImageViewAbstract>> = self
+            .shadow_renderer
+            .get_shadow_cube_maps()
+            .into_iter()
+            .flatten()
+            .map(|image| image as Arc<dyn ImageViewAbstract>)
+            .collect();
+        let gbuffer = self.scene_renderer.gbuffer();
+        let gbuffer_images: Vec<Arc<dyn ImageViewAbstract>> = gbuffer
+            .depth
+            .iter()
+            .chain(gbuffer.normals.iter())
+            .map(|image| image.clone() as Arc<dyn ImageViewAbstract>)
+            .collect();
+
+        self.attachment_bytes = image_views_bytes(
+            shadow_maps
+                .iter()
+                .chain(gbuffer_images.iter())
+                .chain(self.scene_renderer.output_images().iter())
+                .chain(self.reflection_renderer.output_images().iter())
+                .chain(self.post_process_chain.all_output_images().iter()),
+        );
+    }
+
+    pub(crate) fn memory_allocator(&self) -> &Arc<StandardMemoryAllocator> {
+        &self.memory_allocator
+    }
+
+    /// Goes into the command buffers of the next frame, instead of waiting for an upload of its own
+    pub(crate) fn upload_video_frame(&mut self, frame: Subbuffer<[u8]>, image: Arc<StorageImage>) {
+        self.video_uploads.push((frame, image));
+    }
+
+    pub(crate) fn gpu_frame_time(&self) -> Option<Duration> {
+        self.gpu_frame_time
     }
 
     pub fn recreate_swapchain(&mut self) {
         self.recreate_swapchain = true;
     }
+
+    /// Saves the next rendered frame as a PNG
+    pub fn capture_screenshot(&mut self, path: impl Into<PathBuf>) {
+        self.screenshot_path = Some(path.into());
+    }
+
+    /// Saves every following frame as `frame_000000.png`, `frame_000001.png`, ... in the directory
+    pub fn start_frame_capture(&mut self, directory: impl Into<PathBuf>) {
+        self.frame_capture = Some(FrameCapture {
+            directory: directory.into(),
+            frame_index: 0,
+        });
+    }
+
+    /// Returns how many frames were captured
+    pub fn stop_frame_capture(&mut self) -> u32 {
+        self.frame_capture
+            .take()
+            .map(|frame_capture| frame_capture.frame_index)
+            .unwrap_or(0)
+    }
+
+    pub fn is_capturing_frames(&self) -> bool {
+        self.frame_capture.is_some()
+    }
+
+    /// Screenshots wait until the frame capture is done, so that no video frame gets skipped
+    fn next_capture_path(&mut self) -> Option<(PathBuf, bool)> {
+        match self.frame_capture.as_mut() {
+            Some(frame_capture) => {
+                let path = frame_capture
+                    .directory
+                    .join(format!("frame_{:06}.png", frame_capture.frame_index));
+                frame_capture.frame_index += 1;
+                Some((path, true))
+            }
+            None => self.screenshot_path.take().map(|path| (path, false)),
+        }
+    }
 }
 
 #[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum RendererPluginSets {
     Render,
+    /// adds debug lines, has to run after the game logic
+    DebugLines,
 }
 
 pub struct RendererPlugin {
     brightness: f32,
+    bloom_settings: BloomSettings,
+    max_shadow_lights: usize,
+    shadow_settings: ShadowSettings,
+    frames_in_flight: u32,
+    low_latency_mode: bool,
+    /// in bytes, None uses all of the GPU memory
+    memory_budget: Option<u64>,
+    device_selection: DeviceSelection,
+    render_scale: f32,
+    dynamic_resolution: bool,
+    target_frame_rate: f32,
 }
 
 impl RendererPlugin {
     pub fn new(brightness: f32) -> Self {
-        Self { brightness }
+        Self {
+            brightness,
+            bloom_settings: BloomSettings::default(),
+            max_shadow_lights: MAX_SHADOW_LIGHTS,
+            shadow_settings: ShadowSettings::default(),
+            frames_in_flight: DEFAULT_FRAMES_IN_FLIGHT,
+            low_latency_mode: false,
+            memory_budget: None,
+            device_selection: DeviceSelection::Automatic,
+            render_scale: 1.0,
+            dynamic_resolution: false,
+            target_frame_rate: 60.0,
+        }
+    }
+
+    pub fn with_shadow_settings(mut self, shadow_settings: ShadowSettings) -> Self {
+        self.shadow_settings = shadow_settings;
+        self
+    }
+
+    /// How many lights get a shadow map every frame, at most [`MAX_SHADOW_LIGHTS`]
+    pub fn with_max_shadow_lights(mut self, max_shadow_lights: usize) -> Self {
+        self.max_shadow_lights = max_shadow_lights;
+        self
+    }
+
+    pub fn with_bloom_settings(mut self, bloom_settings: BloomSettings) -> Self {
+        self.bloom_settings = bloom_settings;
+        self
+    }
+
+    /// How many frames the CPU can prepare while the GPU is still busy, limited by what the
+    /// surface supports
+    pub fn with_frames_in_flight(mut self, frames_in_flight: u32) -> Self {
+        self.frames_in_flight = frames_in_flight.max(1);
+        self
+    }
+
+    pub fn with_low_latency_mode(mut self, low_latency_mode: bool) -> Self {
+        self.low_latency_mode = low_latency_mode;
+        self
+    }
+
+    pub fn with_device_selection(mut self, device_selection: DeviceSelection) -> Self {
+        self.device_selection = device_selection;
+        self
+    }
+
+    /// Between [`RenderScale::MIN`] and [`RenderScale::MAX`], 1.0 renders at the window resolution
+    pub fn with_render_scale(mut self, render_scale: f32) -> Self {
+        self.render_scale = render_scale.clamp(RenderScale::MIN, RenderScale::MAX);
+        self
+    }
+
+    /// Lowers the render scale when the GPU can't hold the target frame rate
+    pub fn with_dynamic_resolution(mut self, enabled: bool, target_frame_rate: f32) -> Self {
+        self.dynamic_resolution = enabled;
+        self.target_frame_rate = target_frame_rate;
+        self
+    }
+
+    /// Limits how much GPU memory the renderer can use, in bytes. None uses all of it.
+    pub fn with_memory_budget(mut self, memory_budget: Option<u64>) -> Self {
+        self.memory_budget = memory_budget;
+        self
     }
 }
 
@@ -181,14 +628,50 @@ impl Plugin for RendererPlugin {
                 .unwrap()
                 .window
                 .clone(),
+            &self.device_selection,
         );
-        let renderer = Renderer::new(&context, self.brightness);
+        let capabilities = DeviceCapabilities::new(&context);
+        capabilities.print_report();
+        let renderer = Renderer::new(
+            &context,
+            &capabilities,
+            self.brightness,
+            self.max_shadow_lights,
+            &self.shadow_settings,
+            self.frames_in_flight,
+            RenderScale {
+                scale: self.render_scale,
+            },
+        );
+        #[cfg(feature = "dev-tools")]
+        let dev_gui = DevGui::new(
+            &context,
+            &app
+                .world_hack_access()
+                .get_non_send_resource::<EventLoopContainer>()
+                .unwrap()
+                .event_loop,
+            renderer.swapchain.swapchain.image_format(),
+        );
+        let memory_budget = self
+            .memory_budget
+            .unwrap_or(capabilities.device_local_memory);
         let model_uploading_allocator = ModelUploaderAllocator::new(context.device());
         let sampler_info_map = SamplerInfoMap::new();
 
         app //
             .with_non_send_resource(context)
             .with_non_send_resource(renderer)
+            .with_system(
+                update_spatial_grid
+                    .in_set(RendererPluginSets::Render)
+                    .before(render),
+            )
+            .with_system(
+                free_unused_gpu_assets
+                    .in_set(RendererPluginSets::Render)
+                    .before(create_gpu_models),
+            )
             .with_system(
                 create_gpu_models
                     .in_set(RendererPluginSets::Render)
@@ -206,29 +689,139 @@ impl Plugin for RendererPlugin {
                     .after(update_gpu_models)
                     .before(render),
             )
+            .with_system(
+                create_gpu_decals
+                    .in_set(RendererPluginSets::Render)
+                    .after(update_gpu_models)
+                    .before(render),
+            )
+            .with_system(
+                create_gpu_billboards
+                    .in_set(RendererPluginSets::Render)
+                    .after(update_gpu_models)
+                    .before(render),
+            )
+            .with_system(
+                update_gpu_video_textures
+                    .in_set(RendererPluginSets::Render)
+                    .after(update_gpu_models)
+                    .before(render),
+            )
+            .with_system(draw_culling_debug_lines.in_set(RendererPluginSets::DebugLines))
             .with_system(render.in_set(RendererPluginSets::Render))
+            .with_system(
+                update_attachment_memory_usage
+                    .in_set(RendererPluginSets::Render)
+                    .after(render),
+            )
+            .with_system(
+                update_dynamic_resolution
+                    .in_set(RendererPluginSets::Render)
+                    .after(render),
+            )
             .with_resource(ViewFrustumCullingMode { enabled: true })
+            .with_resource(OcclusionCullingMode { enabled: true })
+            .with_resource(DepthPrepassMode { enabled: true })
+            .with_resource(LowLatencyMode {
+                enabled: self.low_latency_mode,
+            })
+            .with_resource(RenderScale {
+                scale: self.render_scale,
+            })
+            .with_resource(DynamicResolution::new(
+                self.dynamic_resolution,
+                self.target_frame_rate,
+                self.render_scale,
+            ))
+            .with_resource(DebugLines::default())
+            .with_resource(SpatialGrid::new())
+            .with_resource(RewindGhosts::default())
+            .with_resource(ScreenGlitch::default())
+            .with_resource(Flash::default())
+            // Read by the camera of the game, not by the renderer
+            .with_resource(ScreenShake::default())
+            .with_resource(ColorGrading::default())
+            .with_resource(UIScaling::default())
+            .with_resource(UIVisibility::default())
+            .with_resource(AmbientLight::default())
+            .with_resource(Fog::default())
+            .with_resource(Sky::default())
+            .with_resource(self.bloom_settings.clone())
+            .with_resource(self.shadow_settings.clone())
+            .with_resource(TonemappingSettings::default())
+            .with_resource(GpuMemoryUsage::new(memory_budget))
+            .with_resource(capabilities)
             .with_resource(model_uploading_allocator)
             .with_resource(sampler_info_map)
             .with_resource(Assets::<Mesh>::default())
             .with_resource(Assets::<Material>::default())
             .with_resource(Assets::<Texture>::default());
+
+        #[cfg(feature = "dev-tools")]
+        app.with_non_send_resource(dev_gui);
     }
 }
 
+/// Systems can only have 16 parameters, so the settings get grouped together
+#[derive(SystemParam)]
+pub struct RenderSettings<'w> {
+    ambient_light: Res<'w, AmbientLight>,
+    fog: Res<'w, Fog>,
+    sky: Res<'w, Sky>,
+    view_frustum_culling_mode: Res<'w, ViewFrustumCullingMode>,
+    occlusion_culling_mode: Res<'w, OcclusionCullingMode>,
+    depth_prepass_mode: Res<'w, DepthPrepassMode>,
+    bloom_settings: Res<'w, BloomSettings>,
+    shadow_settings: Res<'w, ShadowSettings>,
+    tonemapping_settings: Res<'w, TonemappingSettings>,
+    ui_scaling: Res<'w, UIScaling>,
+    ui_visibility: Res<'w, UIVisibility>,
+    low_latency_mode: Res<'w, LowLatencyMode>,
+    render_scale: Res<'w, RenderScale>,
+    screen_glitch: Res<'w, ScreenGlitch>,
+    flash: Res<'w, Flash>,
+    color_grading: Res<'w, ColorGrading>,
+}
+
+/// Everything that gets drawn on top of the models
+#[derive(SystemParam)]
+pub struct SceneOverlays<'w, 's> {
+    debug_lines: Res<'w, DebugLines>,
+    rewind_ghosts: Res<'w, RewindGhosts>,
+    query_highlighted: Query<'w, 's, (&'static Transform, &'static GpuModel), With<Highlighted>>,
+    query_decals: Query<'w, 's, (&'static Transform, &'static GpuDecal)>,
+    query_billboards: Query<
+        'w,
+        's,
+        (
+            &'static Transform,
+            &'static Billboard,
+            &'static GpuBillboard,
+        ),
+    >,
+}
+
 pub fn render(
     mut renderer: NonSendMut<Renderer>,
     context: NonSend<Context>,
     camera: Res<Camera>,
     time_manager: Res<TimeManager>,
     current_level: Res<CurrentLevel>,
-    query_models: Query<(&Transform, &GpuModel)>,
-    query_lights: Query<(&Transform, &Light, &LevelId)>,
-    query_shadow_light: Query<(&Transform, &LevelId), (With<LightCastShadow>, With<Light>)>,
+    query_models: Query<(
+        &Transform,
+        &GpuModel,
+        Option<&TextureScroll>,
+        Option<&GpuVideoTexture>,
+    )>,
+    query_lights: Query<(Entity, &Transform, &Light, &LevelId)>,
+    query_shadow_light: Query<(Entity, &Transform, &LevelId), (With<LightCastShadow>, With<Light>)>,
     query_shadow_casting_models: Query<(&Transform, &GpuModel, &LevelId), With<CastsShadow>>,
+    spatial_grid: Res<SpatialGrid>,
     mut frame_counter: Local<u64>,
-    query_ui_components: Query<(&GpuUIComponent, &UIComponent)>,
-    view_frustum_culling_mode: Res<ViewFrustumCullingMode>,
+    query_ui_components: Query<(&GpuUIComponent, &UIComponent, Option<&UIProgressBar>)>,
+    settings: RenderSettings,
+    overlays: SceneOverlays,
+    #[cfg(feature = "dev-tools")] dev_gui: Option<NonSendMut<DevGui>>,
     mut rewind_start_time: Local<f32>,
 ) {
     // On Windows, this can occur from minimizing the application.
@@ -239,16 +832,6 @@ pub fn render(
         return;
     }
 
-    // It is important to call this function from time to time, otherwise resources will keep
-    // accumulating and you will eventually reach an out of memory error.
-    // Calling this function polls various fences in order to determine what the GPU has
-    // already processed, and frees the resources that are no longer needed.
-    renderer
-        .previous_frame_end
-        .as_mut()
-        .unwrap()
-        .cleanup_finished();
-
     // Whenever the window resizes we need to recreate everything dependent on the window size.
     // In this example that includes the swapchain, the framebuffers and the dynamic state viewport.
     if renderer.recreate_swapchain {
@@ -266,34 +849,40 @@ pub fn render(
 
         renderer.viewport.dimensions = renderer.swapchain.dimensions.map(|i| i as f32);
 
-        let _dimensions = renderer.swapchain.swapchain.image_extent();
-        let _swapchain_image_count = renderer.swapchain.swapchain.image_count();
-
-        // https://doc.rust-lang.org/nomicon/borrow-splitting.html
-        let renderer = renderer.as_mut();
+        // The number of swapchain images can change, dropping the fences waits for the GPU
+        renderer.frame_fences = vec![None; renderer.swapchain.images.len()];
+        renderer.previous_fence_index = 0;
         renderer
-            .shadow_renderer
+            .gpu_timer
             .resize(renderer.swapchain.images.len() as u32);
-        renderer.scene_renderer.resize(
-            &renderer.swapchain.images,
-            renderer.shadow_renderer.get_shadow_cube_maps(),
-        );
-        renderer
-            .bloom_renderer
-            .resize(renderer.scene_renderer.output_images().clone());
+        renderer.resize_render_targets();
 
-        renderer.quad_renderer.resize(
-            &renderer.swapchain.images,
-            &renderer.bloom_renderer.output_images(),
-        );
+        renderer.recreate_swapchain = false;
 
-        renderer.ui_renderer.resize(&renderer.swapchain.images);
+        *frame_counter = 0;
+    }
 
-        renderer.recreate_swapchain = false;
+    if renderer.render_scale.scale != settings.render_scale.scale {
+        // The old images might still be in use by the GPU
+        renderer.frame_fences = vec![None; renderer.swapchain.images.len()];
+        renderer.previous_fence_index = 0;
+        renderer.render_scale.scale = settings.render_scale.scale;
+        renderer.resize_render_targets();
 
         *frame_counter = 0;
     }
 
+    if renderer.shadow_renderer.resolution() != settings.shadow_settings.resolution {
+        let renderer = renderer.as_mut();
+        renderer
+            .shadow_renderer
+            .set_resolution(settings.shadow_settings.resolution);
+        renderer
+            .scene_renderer
+            .set_shadow_cube_maps(renderer.shadow_renderer.get_shadow_cube_maps());
+        renderer.update_attachment_bytes();
+    }
+
     // Before we can draw on the output, we have to *acquire* an image from the swapchain. If
     // no image is available (which happens if you submit draw commands too quickly), then the
     // function will block.
@@ -318,35 +907,98 @@ pub fn render(
         renderer.recreate_swapchain = true;
     }
 
-    let future = renderer
-        .previous_frame_end
-        .take()
-        .unwrap()
-        .join(acquire_future);
+    // The last frame that used this swapchain image might still be running on the GPU
+    if let Some(image_fence) = &renderer.frame_fences[image_index as usize] {
+        image_fence.wait(None).unwrap();
+    }
+    renderer.gpu_frame_time = renderer.gpu_timer.read(image_index);
+
+    let previous_future = match renderer.frame_fences[renderer.previous_fence_index].clone() {
+        Some(fence) => fence.boxed(),
+        None => {
+            let mut now = sync::now(context.device());
+            // Frees the resources of the frames that the GPU has finished
+            now.cleanup_finished();
+            now.boxed()
+        }
+    };
+    let future = previous_future.join(acquire_future);
+    let future = renderer.gpu_timer.start(&context, future, image_index);
+
+    let future = if renderer.video_uploads.is_empty() {
+        future
+    } else {
+        let mut builder = AutoCommandBufferBuilder::primary(
+            renderer.command_buffer_allocator.as_ref(),
+            context.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        for (frame, image) in renderer.video_uploads.drain(..) {
+            builder
+                .copy_buffer_to_image(CopyBufferToImageInfo::buffer_image(frame, image))
+                .unwrap();
+        }
+        future
+            .then_execute(context.queue(), builder.build().unwrap())
+            .unwrap()
+            .boxed()
+    };
 
     let current_level_id = current_level.level_id;
-    let models = query_models.iter().collect();
-    let lights = query_lights
+    // The scene renderer still culls the single primitives
+    let models = if settings.view_frustum_culling_mode.enabled {
+        let (center, radius) = frustum_bounding_sphere(&camera);
+        query_models
+            .iter_many(spatial_grid.query_sphere(&center, radius))
+            .collect()
+    } else {
+        query_models.iter().collect()
+    };
+    let ui_components = if settings.ui_visibility.hidden {
+        vec![]
+    } else {
+        query_ui_components.iter().collect()
+    };
+
+    // The lights that are closest to the camera get the shadow maps
+    let mut shadow_lights: Vec<(Entity, &Transform)> = query_shadow_light
         .iter()
         .filter(|(_, _, level_id)| level_id == &&current_level_id)
-        .map(|(transform, light, _)| (transform, light))
+        .map(|(entity, transform, _)| (entity, transform))
         .collect();
-    let ui_components = query_ui_components.iter().collect();
-    let shadow_cast_models = query_shadow_casting_models
+    shadow_lights.sort_by(|(_, transform_a), (_, transform_b)| {
+        let distance_a = (camera.position - transform_a.position).norm_squared();
+        let distance_b = (camera.position - transform_b.position).norm_squared();
+        distance_a.total_cmp(&distance_b)
+    });
+    shadow_lights.truncate(renderer.shadow_renderer.max_shadow_lights());
+
+    let lights = query_lights
         .iter()
-        .filter(|(_, _, level_id)| level_id == &&current_level_id)
-        .map(|(transform, gpu_model, _)| (transform, gpu_model))
+        .filter(|(_, _, _, level_id)| level_id == &&current_level_id)
+        .map(|(entity, transform, light, _)| {
+            let shadow_map_index = shadow_lights
+                .iter()
+                .position(|(shadow_entity, _)| shadow_entity == &entity);
+            (transform, light, shadow_map_index)
+        })
+        .collect();
+    let shadow_light_transforms: Vec<&Transform> = shadow_lights
+        .iter()
+        .map(|(_, transform)| *transform)
         .collect();
 
-    let nearest_shadow_light = query_shadow_light
+    // Models that are out of reach of every shadow light can't cast a shadow
+    let shadow_cast_entities: HashSet<Entity> = shadow_light_transforms
         .iter()
-        .filter(|(_, level_id)| level_id == &&current_level_id)
-        .map(|(transform, _)| transform)
-        .min_by(|transform_a, transform_b| {
-            let distance_a = (camera.position - transform_a.position).norm_squared();
-            let distance_b = (camera.position - transform_b.position).norm_squared();
-            distance_a.total_cmp(&distance_b)
-        });
+        .flat_map(|transform| spatial_grid.query_sphere(&transform.position.coords, SHADOW_FAR))
+        .collect();
+    let shadow_cast_models = query_shadow_casting_models
+        .iter_many(&shadow_cast_entities)
+        .filter(|(_, _, level_id)| level_id == &&current_level_id)
+        .map(|(transform, gpu_model, _)| (transform, gpu_model))
+        .collect();
 
     let rewind_time = if time_manager.is_rewinding() {
         if time_manager.level_delta_time().duration().is_zero() {
@@ -360,17 +1012,16 @@ pub fn render(
         0.0
     };
 
-    let future = if let (Some(nearest_shadow_light), true) = (
-        nearest_shadow_light,
-        *frame_counter > renderer.swapchain.images.len() as u64,
-    ) {
+    let future = if !shadow_light_transforms.is_empty()
+        && *frame_counter > renderer.swapchain.images.len() as u64
+    {
         renderer
             .shadow_renderer
             .render(
                 &context,
                 rewind_time,
                 &shadow_cast_models,
-                nearest_shadow_light,
+                &shadow_light_transforms,
                 camera.as_ref(),
                 future,
                 image_index,
@@ -380,34 +1031,136 @@ pub fn render(
         future.boxed()
     };
 
+    // The depth pyramid gets skipped while rewinding, since the geometry gets distorted
+    let use_occlusion_culling = settings.occlusion_culling_mode.enabled && rewind_time <= 0.0;
+    if use_occlusion_culling {
+        renderer.depth_pyramid_renderer.update_readback();
+    }
+    let occlusion_culling = if use_occlusion_culling {
+        renderer.depth_pyramid_renderer.readback()
+    } else {
+        None
+    };
+
     let future = renderer.scene_renderer.render(
         &context,
         camera.as_ref(),
         rewind_time,
+        *rewind_start_time,
         models,
         lights,
+        settings.ambient_light.as_ref(),
+        settings.fog.as_ref(),
+        settings.sky.as_ref(),
+        overlays.query_decals.iter().collect(),
+        settings.shadow_settings.pcf_samples.clamp(1, MAX_PCF_SAMPLES),
         future,
-        nearest_shadow_light,
-        view_frustum_culling_mode.as_ref(),
+        settings.view_frustum_culling_mode.as_ref(),
+        occlusion_culling,
+        settings.depth_prepass_mode.as_ref(),
         image_index,
         *frame_counter,
-        &renderer.viewport,
+        &renderer.scene_viewport,
     );
 
-    let future = renderer
-        .bloom_renderer
-        .render(&context, future, image_index);
+    let future = if use_occlusion_culling {
+        renderer.depth_pyramid_renderer.render(
+            &context,
+            future,
+            camera.proj() * camera.view(),
+            image_index,
+        )
+    } else {
+        future.boxed()
+    };
+
+    let ghosts = overlays
+        .rewind_ghosts
+        .ghosts
+        .iter()
+        .filter_map(|ghost| {
+            query_models
+                .get(ghost.entity)
+                .ok()
+                .map(|(_, gpu_model, _, _)| (&ghost.transform, gpu_model))
+        })
+        .collect();
+    let future = renderer.ghost_renderer.render(
+        &context,
+        future,
+        ghosts,
+        camera.as_ref(),
+        rewind_time,
+        image_index,
+    );
+
+    let future = renderer.outline_renderer.render(
+        &context,
+        future,
+        overlays.query_highlighted.iter().collect(),
+        camera.as_ref(),
+        rewind_time,
+        image_index,
+    );
+
+    let future = renderer.billboard_renderer.render(
+        &context,
+        future,
+        overlays.query_billboards.iter().collect(),
+        camera.as_ref(),
+        rewind_time,
+        image_index,
+    );
+
+    let future = renderer.reflection_renderer.render(
+        &context,
+        future,
+        camera.proj() * camera.view(),
+        image_index,
+    );
+
+    let post_process_frame = PostProcessFrame {
+        rewind_time,
+        glitch: settings.screen_glitch.strength(),
+        flash: settings.flash.strength(),
+        flash_color: settings.flash.color,
+        bloom: settings.bloom_settings.clone(),
+        tonemapping: settings.tonemapping_settings.clone(),
+        color_lut: settings.color_grading.lut.clone(),
+        blend_color_lut: settings.color_grading.blend_lut.clone(),
+        color_lut_blend: settings.color_grading.blend,
+    };
+    let future = renderer.post_process_chain.render(
+        &context,
+        future,
+        &post_process_frame,
+        image_index,
+    );
+
+    let future = if overlays.debug_lines.enabled {
+        renderer.debug_line_renderer.render(
+            &context,
+            future,
+            overlays.debug_lines.lines(),
+            camera.proj() * camera.view(),
+            image_index,
+        )
+    } else {
+        future
+    };
 
     let future = renderer
         .quad_renderer
         .render(&context, future, image_index, &renderer.viewport);
 
     let future = if *frame_counter > renderer.swapchain.images.len() as u64 {
+        let renderer = renderer.as_mut();
         renderer
             .ui_renderer
             .render(
                 &context,
                 ui_components,
+                &settings.ui_scaling,
                 future,
                 image_index,
                 &renderer.viewport,
@@ -417,6 +1170,32 @@ pub fn render(
         future.boxed()
     };
 
+    let future = renderer.gpu_timer.end(&context, future, image_index);
+
+    #[cfg(feature = "dev-tools")]
+    let future = match dev_gui {
+        Some(mut dev_gui) => dev_gui.render(
+            future,
+            renderer.swapchain.images[image_index as usize].clone(),
+        ),
+        None => future,
+    };
+
+    let (future, screenshot) = match renderer.next_capture_path() {
+        Some((path, is_video_frame)) => {
+            let (future, screenshot) = Screenshot::capture(
+                &context,
+                future,
+                renderer.swapchain.images[image_index as usize].clone(),
+                path,
+                &renderer.memory_allocator,
+                &renderer.command_buffer_allocator,
+            );
+            (future, screenshot.map(|screenshot| (screenshot, is_video_frame)))
+        }
+        None => (future, None),
+    };
+
     let future = future
         .then_swapchain_present(
             context.queue(),
@@ -428,26 +1207,79 @@ pub fn render(
         .then_signal_fence_and_flush();
 
     *frame_counter += 1;
-    match future {
+    let fence = match future {
         Ok(future) => {
+            let future = Arc::new(future);
             // NOTE: one solution to remove the massive input delay with fullscreen-mode enabled
-            future.wait(None).unwrap();
+            // Screenshots can only be read once the GPU is done
+            if settings.low_latency_mode.enabled || screenshot.is_some() {
+                future.wait(None).unwrap();
+            }
+            match screenshot {
+                Some((screenshot, true)) => screenshot.save_blocking(),
+                Some((screenshot, false)) => screenshot.save(),
+                None => {}
+            }
 
-            renderer.previous_frame_end = Some(future.boxed());
+            Some(future)
         }
         Err(FlushError::OutOfDate) => {
             renderer.recreate_swapchain = true;
-            renderer.previous_frame_end = Some(sync::now(context.device().clone()).boxed());
+            None
         }
         Err(e) => {
             println!("Failed to flush future: {e:?}");
-            renderer.previous_frame_end = Some(sync::now(context.device()).boxed());
+            None
+        }
+    };
+    renderer.frame_fences[image_index as usize] = fence;
+    renderer.previous_fence_index = image_index as usize;
+}
+
+fn update_attachment_memory_usage(
+    renderer: NonSend<Renderer>,
+    mut memory_usage: ResMut<GpuMemoryUsage>,
+) {
+    if memory_usage.get(MemoryCategory::Attachments) != renderer.attachment_bytes {
+        memory_usage.set(MemoryCategory::Attachments, renderer.attachment_bytes);
+    }
+}
+
+/// Shows the bounding boxes of the models, green if they are visible and red if they got culled
+pub fn draw_culling_debug_lines(
+    camera: Res<Camera>,
+    query_models: Query<(&Transform, &GpuModel)>,
+    mut debug_lines: ResMut<DebugLines>,
+) {
+    if !debug_lines.enabled {
+        return;
+    }
+
+    let frustum_bounding_sphere = frustum_bounding_sphere(camera.as_ref());
+    for (transform, model) in &query_models {
+        let matrix = transform.to_matrix();
+        for primitive in &model.primitives {
+            let color = if primitive.intersects_frustum(&frustum_bounding_sphere, transform) {
+                Vector3::new(0.0, 1.0, 0.0)
+            } else {
+                Vector3::new(1.0, 0.0, 0.0)
+            };
+            debug_lines.bounding_box(&primitive.mesh.bounding_box, &matrix, color);
         }
     }
+
+    let (center, radius) = frustum_bounding_sphere;
+    debug_lines.sphere(Point3::from(center), radius, Vector3::new(1.0, 1.0, 0.0));
 }
 
 impl SwapchainContainer {
-    pub fn new(device: Arc<Device>, surface: Arc<Surface>) -> SwapchainContainer {
+    /// Every frame in flight needs its own swapchain image
+    pub fn new(
+        device: Arc<Device>,
+        surface: Arc<Surface>,
+        frames_in_flight: u32,
+        capabilities: &DeviceCapabilities,
+    ) -> SwapchainContainer {
         let (swapchain, images) = {
             let surface_capabilities = device
                 .physical_device()
@@ -474,15 +1306,33 @@ impl SwapchainContainer {
 
             let window = surface.object().unwrap().downcast_ref::<Window>().unwrap();
 
+            let min_image_count = match surface_capabilities.max_image_count {
+                Some(max_image_count) => frames_in_flight.min(max_image_count),
+                None => frames_in_flight,
+            }
+            .max(surface_capabilities.min_image_count);
+
+            let supports_storage = image_format.map_or(false, |format| {
+                capabilities.supports_storage_swapchain(format)
+            });
+            let storage_usage = if supports_storage {
+                ImageUsage::STORAGE
+            } else {
+                ImageUsage::empty()
+            };
+
             Swapchain::new(
                 device.clone(),
                 surface.clone(),
                 SwapchainCreateInfo {
                     present_mode: PresentMode::Immediate,
-                    min_image_count: surface_capabilities.min_image_count,
+                    min_image_count,
                     image_format,
                     image_extent: window.inner_size().into(),
-                    image_usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::INPUT_ATTACHMENT,
+                    image_usage: ImageUsage::COLOR_ATTACHMENT
+                        | ImageUsage::INPUT_ATTACHMENT
+                        | ImageUsage::TRANSFER_SRC
+                        | storage_usage,
                     composite_alpha: surface_capabilities
                         .supported_composite_alpha
                         .into_iter()
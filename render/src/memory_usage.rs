@@ -0,0 +1,113 @@
+use bevy_ecs::prelude::Resource;
+use std::collections::HashSet;
+use std::sync::Arc;
+use vulkano::device::physical::PhysicalDevice;
+use vulkano::image::{ImageAccess, ImageViewAbstract};
+use vulkano::memory::MemoryHeapFlags;
+
+/// What the GPU memory gets used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryCategory {
+    Meshes,
+    Textures,
+    /// Render targets, like the gbuffer and the shadow maps
+    Attachments,
+}
+
+/// How much GPU memory the renderer uses, as an estimate, since the allocators round up the sizes.
+/// Once the budget is used up, textures get loaded without their mip levels or not at all.
+#[derive(Resource, Debug, Clone)]
+pub struct GpuMemoryUsage {
+    /// in bytes
+    pub budget: u64,
+    meshes: u64,
+    textures: u64,
+    attachments: u64,
+}
+
+impl GpuMemoryUsage {
+    pub fn new(budget: u64) -> Self {
+        Self {
+            budget,
+            meshes: 0,
+            textures: 0,
+            attachments: 0,
+        }
+    }
+
+    /// in bytes
+    pub fn get(&self, category: MemoryCategory) -> u64 {
+        match category {
+            MemoryCategory::Meshes => self.meshes,
+            MemoryCategory::Textures => self.textures,
+            MemoryCategory::Attachments => self.attachments,
+        }
+    }
+
+    /// in bytes
+    pub fn total(&self) -> u64 {
+        self.meshes + self.textures + self.attachments
+    }
+
+    pub fn fits(&self, bytes: u64) -> bool {
+        self.total() + bytes <= self.budget
+    }
+
+    pub(crate) fn add(&mut self, category: MemoryCategory, bytes: u64) {
+        *self.get_mut(category) += bytes;
+    }
+
+    /// For freed meshes and textures
+    pub(crate) fn remove(&mut self, category: MemoryCategory, bytes: u64) {
+        let value = self.get_mut(category);
+        *value = value.saturating_sub(bytes);
+    }
+
+    /// For categories that get recreated as a whole, like the attachments when the window gets resized
+    pub(crate) fn set(&mut self, category: MemoryCategory, bytes: u64) {
+        *self.get_mut(category) = bytes;
+    }
+
+    fn get_mut(&mut self, category: MemoryCategory) -> &mut u64 {
+        match category {
+            MemoryCategory::Meshes => &mut self.meshes,
+            MemoryCategory::Textures => &mut self.textures,
+            MemoryCategory::Attachments => &mut self.attachments,
+        }
+    }
+}
+
+/// All of the device local memory, which is shared with the CPU on integrated GPUs
+pub(crate) fn device_local_memory(physical_device: &PhysicalDevice) -> u64 {
+    physical_device
+        .memory_properties()
+        .memory_heaps
+        .iter()
+        .filter(|heap| heap.flags.intersects(MemoryHeapFlags::DEVICE_LOCAL))
+        .map(|heap| heap.size)
+        .sum()
+}
+
+/// Size of the image with all of its mip levels and array layers
+pub(crate) fn image_bytes(image: &dyn ImageAccess) -> u64 {
+    let texel_size = image.format().block_size().unwrap_or(4);
+    let dimensions = image.dimensions();
+    (0..image.mip_levels())
+        .filter_map(|mip_level| dimensions.mip_level_dimensions(mip_level))
+        .map(|mip_dimensions| mip_dimensions.num_texels() * texel_size)
+        .sum()
+}
+
+/// Image views can share their images, for example the mip levels of the bloom images,
+/// so every image only gets counted once
+pub(crate) fn image_views_bytes<'a>(
+    image_views: impl IntoIterator<Item = &'a Arc<dyn ImageViewAbstract>>,
+) -> u64 {
+    let mut counted_images = HashSet::new();
+    image_views
+        .into_iter()
+        .map(|image_view| image_view.image())
+        .filter(|image| counted_images.insert(Arc::as_ptr(image.inner().image)))
+        .map(|image| image_bytes(image.as_ref()))
+        .sum()
+}
@@ -1,14 +1,17 @@
 use std::{collections::HashMap, sync::Arc};
 
-use bevy_ecs::prelude::Changed;
-use bevy_ecs::system::NonSend;
+use bevy_ecs::prelude::{Changed, RemovedComponents};
+use bevy_ecs::system::{Local, NonSend, NonSendMut};
 use bevy_ecs::{
     prelude::Entity,
     query::Without,
     system::{Commands, Query, Res, ResMut, Resource},
 };
 use scene::asset::{Asset, Assets};
+use scene::billboard::Billboard;
+use scene::decal::Decal;
 use scene::ui_component::UIComponent;
+use scene::video_texture::VideoTexture;
 use scene::{
     material::CpuMaterial,
     mesh::CpuMesh,
@@ -16,12 +19,23 @@ use scene::{
     texture::{CpuTexture, SamplerInfo},
 };
 use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
     device::Device,
-    memory::allocator::StandardMemoryAllocator,
+    format::Format,
+    image::{
+        view::ImageView, ImageAccess, ImageCreateFlags, ImageDimensions, ImageUsage, MipmapsCount,
+        StorageImage,
+    },
+    memory::allocator::{AllocationCreateInfo, MemoryUsage, StandardMemoryAllocator},
     sampler::{Sampler, SamplerCreateInfo},
 };
 
+use crate::main_renderer::Renderer;
+use crate::memory_usage::{image_bytes, GpuMemoryUsage, MemoryCategory};
+use crate::scene::billboard::GpuBillboard;
+use crate::scene::decal::GpuDecal;
 use crate::scene::ui_component::GpuUIComponent;
+use crate::scene::video_texture::GpuVideoTexture;
 use crate::{
     context::Context,
     scene::{
@@ -66,13 +80,19 @@ pub fn create_gpu_models(
     mut material_assets: ResMut<Assets<Material>>,
     mut texture_assets: ResMut<Assets<Texture>>,
     mut samplers: ResMut<SamplerInfoMap>,
+    mut memory_usage: ResMut<GpuMemoryUsage>,
 ) {
     for (entity, model) in query_models.iter() {
-        let primitives = model
+        let primitives: Option<Vec<_>> = model
             .primitives
             .iter()
             .map(|primitive| {
-                let mesh = create_gpu_mesh(&mut mesh_assets, &primitive.mesh, &allocator);
+                let mesh = create_gpu_mesh(
+                    &mut mesh_assets,
+                    &primitive.mesh,
+                    &allocator,
+                    &mut memory_usage,
+                )?;
 
                 let material = create_gpu_material(
                     &mut material_assets,
@@ -80,11 +100,18 @@ pub fn create_gpu_models(
                     &mut samplers,
                     &primitive.material,
                     &context,
+                    &mut memory_usage,
                 );
-                Primitive { mesh, material }
+                Some(Primitive { mesh, material })
             })
             .collect();
 
+        // A model without primitives doesn't get drawn, which is better than crashing the game
+        let primitives = primitives.unwrap_or_else(|| {
+            println!("Out of GPU memory, skipping the model of {:?}", entity);
+            vec![]
+        });
+
         let gpu_model = GpuModel { primitives };
         commands.entity(entity).insert(gpu_model);
     }
@@ -95,6 +122,7 @@ pub fn update_gpu_models(
     mut texture_assets: ResMut<Assets<Texture>>,
     mut material_assets: ResMut<Assets<Material>>,
     mut samplers: ResMut<SamplerInfoMap>,
+    mut memory_usage: ResMut<GpuMemoryUsage>,
     mut query_models: Query<(&mut GpuModel, &Model), Changed<Model>>,
 ) {
     for (mut gpu_model, cpu_model) in query_models.iter_mut() {
@@ -109,6 +137,7 @@ pub fn update_gpu_models(
                 &mut samplers,
                 cpu_primitive.material.as_ref(),
                 &context,
+                &mut memory_usage,
             );
         }
     }
@@ -118,15 +147,35 @@ pub fn create_ui_component(
     context: NonSend<Context>,
     mut commands: Commands,
     mut texture_assets: ResMut<Assets<Texture>>,
-    query_ui_components: Query<(Entity, &UIComponent), Without<GpuUIComponent>>,
+    query_ui_components: Query<(Entity, &UIComponent, Option<&GpuUIComponent>)>,
     mut samplers: ResMut<SamplerInfoMap>,
+    mut memory_usage: ResMut<GpuMemoryUsage>,
 ) {
-    for (entity, ui_component) in query_ui_components.iter() {
-        let texture = create_gpu_texture(
+    for (entity, ui_component, gpu_ui_component) in query_ui_components.iter() {
+        // The texture can be swapped out, for example when a text changes
+        let is_up_to_date = gpu_ui_component
+            .map(|gpu_ui_component| gpu_ui_component.texture.id == ui_component.texture.id)
+            .unwrap_or(false);
+        if is_up_to_date {
+            continue;
+        }
+
+        if let Some(gpu_ui_component) = gpu_ui_component {
+            free_replaced_ui_texture(
+                &mut texture_assets,
+                &mut memory_usage,
+                &gpu_ui_component.texture,
+            );
+        }
+
+        // The UI is drawn at about its original size, so it doesn't need mip levels
+        let texture = create_gpu_texture_with_mipmaps(
             &mut texture_assets,
             &mut samplers,
             &ui_component.texture,
             &context,
+            &mut memory_usage,
+            false,
         );
 
         let gpu_ui_component = GpuUIComponent { texture };
@@ -135,24 +184,225 @@ pub fn create_ui_component(
     }
 }
 
+/// Every text change creates a new texture, so the old one gets freed unless another UI component still shows it
+fn free_replaced_ui_texture(
+    texture_assets: &mut Assets<Texture>,
+    memory_usage: &mut GpuMemoryUsage,
+    texture: &Arc<Texture>,
+) {
+    // One reference is from the assets, and one from the UI component that is getting a new texture
+    if Arc::strong_count(texture) > 2 {
+        return;
+    }
+    if let Some(texture) = texture_assets.assets.remove(&texture.id) {
+        memory_usage.remove(
+            MemoryCategory::Textures,
+            image_bytes(texture.image_view.image().as_ref()),
+        );
+    }
+}
+
+pub fn create_gpu_decals(
+    context: NonSend<Context>,
+    mut commands: Commands,
+    mut texture_assets: ResMut<Assets<Texture>>,
+    query_decals: Query<(Entity, &Decal), Changed<Decal>>,
+    mut samplers: ResMut<SamplerInfoMap>,
+    mut memory_usage: ResMut<GpuMemoryUsage>,
+) {
+    for (entity, decal) in query_decals.iter() {
+        let texture = create_gpu_texture(
+            &mut texture_assets,
+            &mut samplers,
+            &decal.texture,
+            &context,
+            &mut memory_usage,
+        );
+
+        commands.entity(entity).insert(GpuDecal {
+            texture,
+            extent: decal.extent,
+        });
+    }
+}
+
+pub fn create_gpu_billboards(
+    context: NonSend<Context>,
+    mut commands: Commands,
+    mut texture_assets: ResMut<Assets<Texture>>,
+    query_billboards: Query<(Entity, &Billboard), Changed<Billboard>>,
+    mut samplers: ResMut<SamplerInfoMap>,
+    mut memory_usage: ResMut<GpuMemoryUsage>,
+) {
+    for (entity, billboard) in query_billboards.iter() {
+        let texture = create_gpu_texture(
+            &mut texture_assets,
+            &mut samplers,
+            &billboard.texture,
+            &context,
+            &mut memory_usage,
+        );
+
+        commands.entity(entity).insert(GpuBillboard { texture });
+    }
+}
+
+/// Copies the current frame of every video into the image of the video. The image gets created
+/// for the first frame, or when the size of the frames changes, and the copy is part of the next frame.
+pub fn update_gpu_video_textures(
+    context: NonSend<Context>,
+    mut renderer: NonSendMut<Renderer>,
+    mut commands: Commands,
+    mut query_videos: Query<(Entity, &VideoTexture, Option<&mut GpuVideoTexture>)>,
+    mut removed_videos: RemovedComponents<GpuVideoTexture>,
+    mut samplers: ResMut<SamplerInfoMap>,
+    mut memory_usage: ResMut<GpuMemoryUsage>,
+    mut video_bytes: Local<HashMap<Entity, u64>>,
+) {
+    for entity in removed_videos.iter() {
+        if let Some(bytes) = video_bytes.remove(&entity) {
+            memory_usage.remove(MemoryCategory::Textures, bytes);
+        }
+    }
+
+    for (entity, video_texture, gpu_video_texture) in query_videos.iter_mut() {
+        let frame = match &video_texture.current_frame {
+            Some(frame) => frame,
+            None => continue,
+        };
+        let is_uploaded = gpu_video_texture
+            .as_ref()
+            .map(|gpu_video_texture| gpu_video_texture.frame_id == frame.id())
+            .unwrap_or(false);
+        if is_uploaded {
+            continue;
+        }
+
+        let [width, height] = frame.data.dimensions();
+        let dimensions = ImageDimensions::Dim2d {
+            width,
+            height,
+            array_layers: 1,
+        };
+        let format = to_vulkano_format(frame.data.format());
+        let frame_buffer = match Buffer::from_iter(
+            renderer.memory_allocator().as_ref(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Upload,
+                ..Default::default()
+            },
+            frame.data.bytes().iter().copied(),
+        ) {
+            Ok(frame_buffer) => frame_buffer,
+            Err(err) => {
+                println!("Failed to upload video frame {}: {}", frame.id(), err);
+                continue;
+            }
+        };
+
+        let image = match gpu_video_texture {
+            Some(mut gpu_video_texture)
+                if gpu_video_texture.image_view.image().dimensions() == dimensions
+                    && gpu_video_texture.image_view.image().format() == format =>
+            {
+                gpu_video_texture.frame_id = frame.id();
+                gpu_video_texture.image_view.image().clone()
+            }
+            _ => {
+                let image = match StorageImage::with_usage(
+                    renderer.memory_allocator().as_ref(),
+                    dimensions,
+                    format,
+                    ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
+                    ImageCreateFlags::empty(),
+                    [context.queue_family_index()],
+                ) {
+                    Ok(image) => image,
+                    Err(err) => {
+                        println!("Failed to create the image of video {:?}: {}", entity, err);
+                        continue;
+                    }
+                };
+                let bytes = image_bytes(image.as_ref());
+                memory_usage.add(MemoryCategory::Textures, bytes);
+                if let Some(old_bytes) = video_bytes.insert(entity, bytes) {
+                    memory_usage.remove(MemoryCategory::Textures, old_bytes);
+                }
+
+                commands.entity(entity).insert(GpuVideoTexture {
+                    image_view: ImageView::new_default(image.clone()).unwrap(),
+                    sampler: create_gpu_sampler(&mut samplers, &frame.sampler_info, &context),
+                    frame_id: frame.id(),
+                });
+                image
+            }
+        };
+        renderer.upload_video_frame(frame_buffer, image);
+    }
+}
+
+/// Unloading a level despawns its models, and the meshes and textures that no other model uses get freed
+pub fn free_unused_gpu_assets(
+    mut removed_models: RemovedComponents<GpuModel>,
+    mut removed_decals: RemovedComponents<GpuDecal>,
+    mut removed_billboards: RemovedComponents<GpuBillboard>,
+    mut mesh_assets: ResMut<Assets<Mesh>>,
+    mut material_assets: ResMut<Assets<Material>>,
+    mut texture_assets: ResMut<Assets<Texture>>,
+    mut memory_usage: ResMut<GpuMemoryUsage>,
+) {
+    let has_removed_models = removed_models.iter().count() > 0;
+    let has_removed_decals = removed_decals.iter().count() > 0;
+    let has_removed_billboards = removed_billboards.iter().count() > 0;
+    if !has_removed_models && !has_removed_decals && !has_removed_billboards {
+        return;
+    }
+
+    // The materials keep their textures alive, so they have to go first
+    material_assets.remove_unused();
+    for texture in texture_assets.remove_unused() {
+        memory_usage.remove(
+            MemoryCategory::Textures,
+            image_bytes(texture.image_view.image().as_ref()),
+        );
+    }
+    for mesh in mesh_assets.remove_unused() {
+        memory_usage.remove(MemoryCategory::Meshes, mesh.byte_size());
+    }
+}
+
+/// Returns None if the mesh doesn't fit into the GPU memory anymore
 fn create_gpu_mesh(
     mesh_assets: &mut Assets<Mesh>,
     mesh: &CpuMesh,
     allocator: &ModelUploaderAllocator,
-) -> Arc<Mesh> {
-    mesh_assets
-        .assets
-        .entry(mesh.id())
-        .or_insert_with(|| {
-            Mesh::new(
-                mesh.id(),
-                mesh.vertices.iter().map(|vertex| vertex.into()).collect(),
-                mesh.indices.clone(),
-                mesh.bounding_sphere,
-                &allocator.allocator,
-            )
-        })
-        .to_owned()
+    memory_usage: &mut GpuMemoryUsage,
+) -> Option<Arc<Mesh>> {
+    if let Some(gpu_mesh) = mesh_assets.assets.get(&mesh.id()) {
+        return Some(gpu_mesh.clone());
+    }
+
+    match Mesh::try_new(
+        mesh.id(),
+        mesh.vertices.iter().map(|vertex| vertex.into()).collect(),
+        mesh.indices.clone(),
+        mesh.bounding_box.clone(),
+        &allocator.allocator,
+    ) {
+        Ok(gpu_mesh) => {
+            memory_usage.add(MemoryCategory::Meshes, gpu_mesh.byte_size());
+            mesh_assets.assets.insert(mesh.id(), gpu_mesh.clone());
+            Some(gpu_mesh)
+        }
+        Err(err) => {
+            println!("Failed to upload mesh {}: {}", mesh.id(), err);
+            None
+        }
+    }
 }
 
 fn create_gpu_material(
@@ -161,6 +411,7 @@ fn create_gpu_material(
     samplers: &mut SamplerInfoMap,
     material: &CpuMaterial,
     context: &Context,
+    memory_usage: &mut GpuMemoryUsage,
 ) -> Arc<Material> {
     material_assets
         .assets
@@ -169,13 +420,19 @@ fn create_gpu_material(
             Arc::new(Material {
                 id: material.id(),
                 base_color: material.base_color,
-                base_color_texture: material
-                    .base_color_texture
-                    .as_ref()
-                    .map(|texture| create_gpu_texture(texture_assets, samplers, texture, context)),
+                base_color_texture: material.base_color_texture.as_ref().map(|texture| {
+                    create_gpu_texture(texture_assets, samplers, texture, context, memory_usage)
+                }),
+                lightmap_texture: material.lightmap_texture.as_ref().map(|texture| {
+                    create_gpu_texture(texture_assets, samplers, texture, context, memory_usage)
+                }),
                 roughness_factor: material.roughness_factor,
                 metallic_factor: material.metallic_factor,
                 emissivity: material.emissivity,
+                reflectivity: material.reflectivity,
+                wind: material.wind,
+                flipbook: material.flipbook,
+                opacity: material.opacity,
             })
         })
         .to_owned()
@@ -186,22 +443,89 @@ fn create_gpu_texture(
     samplers: &mut SamplerInfoMap,
     texture: &CpuTexture,
     context: &Context,
+    memory_usage: &mut GpuMemoryUsage,
+) -> Arc<Texture> {
+    create_gpu_texture_with_mipmaps(
+        texture_assets,
+        samplers,
+        texture,
+        context,
+        memory_usage,
+        true,
+    )
+}
+
+fn create_gpu_texture_with_mipmaps(
+    texture_assets: &mut Assets<Texture>,
+    samplers: &mut SamplerInfoMap,
+    texture: &CpuTexture,
+    context: &Context,
+    memory_usage: &mut GpuMemoryUsage,
+    has_mipmaps: bool,
 ) -> Arc<Texture> {
     texture_assets
         .assets
         .entry(texture.id())
         .or_insert_with(|| {
             let [width, height] = texture.data.dimensions();
-            let a: Vec<u8> = texture.data.bytes().into();
-            Texture::new(
+            let bytes: Vec<u8> = texture.data.bytes().into();
+            let byte_size = bytes.len() as u64;
+            let format = to_vulkano_format(texture.data.format());
+            let sampler = create_gpu_sampler(samplers, &texture.sampler_info, context);
+
+            // The mip levels need about a third more memory, they are the first thing to go
+            let attempts = [
+                (MipmapsCount::Log2, byte_size + byte_size / 3),
+                (MipmapsCount::One, byte_size),
+            ];
+            let attempts = attempts
+                .into_iter()
+                .filter(|(mipmaps, _)| has_mipmaps || matches!(mipmaps, MipmapsCount::One));
+            for (mipmaps, byte_size) in attempts {
+                if !memory_usage.fits(byte_size) {
+                    continue;
+                }
+                match Texture::try_new(
+                    texture.id(),
+                    bytes.clone(),
+                    width,
+                    height,
+                    format,
+                    mipmaps,
+                    sampler.clone(),
+                    context,
+                ) {
+                    Ok(gpu_texture) => {
+                        // Same size as when it gets freed again
+                        memory_usage.add(
+                            MemoryCategory::Textures,
+                            image_bytes(gpu_texture.image_view.image().as_ref()),
+                        );
+                        return gpu_texture;
+                    }
+                    Err(err) => println!("Failed to upload texture {}: {}", texture.id(), err),
+                }
+            }
+
+            // Keeps the id, so that the texture doesn't get uploaded again and again
+            println!(
+                "Out of GPU memory, texture {} is replaced with a white one",
+                texture.id()
+            );
+            let white_texture = Texture::new(
                 texture.id(),
-                a,
-                width,
-                height,
-                to_vulkano_format(texture.data.format()),
-                create_gpu_sampler(samplers, &texture.sampler_info, context),
+                vec![u8::MAX; 4],
+                1,
+                1,
+                Format::R8G8B8A8_UNORM,
+                sampler,
                 context,
-            )
+            );
+            memory_usage.add(
+                MemoryCategory::Textures,
+                image_bytes(white_texture.image_view.image().as_ref()),
+            );
+            white_texture
         })
         .to_owned()
 }
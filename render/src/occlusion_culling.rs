@@ -0,0 +1,374 @@
+use crate::context::Context;
+use crate::custom_storage_image::CustomStorageImage;
+use nalgebra::{Matrix4, Vector3};
+use std::sync::Arc;
+use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, CopyImageToBufferInfo};
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::format::Format;
+use vulkano::image::view::{ImageView, ImageViewCreateInfo};
+use vulkano::image::{
+    ImageAccess, ImageCreateFlags, ImageDimensions, ImageLayout, ImageSubresourceRange,
+    ImageUsage, ImageViewAbstract,
+};
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryUsage, StandardMemoryAllocator};
+use vulkano::pipeline::{ComputePipeline, Pipeline, PipelineBindPoint};
+use vulkano::sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo};
+use vulkano::sync::GpuFuture;
+
+/// The smallest level of the pyramid gets read back, it should be cheap to test against
+const READBACK_MAX_WIDTH: u32 = 128;
+
+/// A small level of last frame's depth pyramid, which has been read back to the CPU.
+/// Every texel contains the farthest depth of the area that it covers.
+pub(crate) struct DepthPyramidReadback {
+    depth: Vec<f32>,
+    width: u32,
+    height: u32,
+    /// The depth buffer was rendered with this camera
+    view_proj: Matrix4<f32>,
+    frame: u64,
+}
+
+impl DepthPyramidReadback {
+    /// Returns false if the sphere was completely hidden behind other objects in the last frame
+    pub fn is_sphere_visible(&self, center: &Vector3<f32>, radius: f32) -> bool {
+        let mut min_ndc = [f32::MAX, f32::MAX];
+        let mut max_ndc = [f32::MIN, f32::MIN];
+        let mut min_depth = f32::MAX;
+
+        // The corners of the bounding box of the sphere
+        for corner in 0..8 {
+            let offset = Vector3::new(
+                if corner & 1 == 0 { -radius } else { radius },
+                if corner & 2 == 0 { -radius } else { radius },
+                if corner & 4 == 0 { -radius } else { radius },
+            );
+            let clip_position = self.view_proj * (center + offset).push(1.0);
+            if clip_position.w <= 0.0 {
+                // The sphere is (partially) behind the camera
+                return true;
+            }
+
+            let ndc = clip_position.xyz() / clip_position.w;
+            min_ndc = [min_ndc[0].min(ndc.x), min_ndc[1].min(ndc.y)];
+            max_ndc = [max_ndc[0].max(ndc.x), max_ndc[1].max(ndc.y)];
+            min_depth = min_depth.min(ndc.z);
+        }
+
+        if min_depth <= 0.0 {
+            return true;
+        }
+
+        let to_texel = |ndc: f32, size: u32| {
+            ((ndc * 0.5 + 0.5) * size as f32).clamp(0.0, (size - 1) as f32) as u32
+        };
+        let (min_x, max_x) = (
+            to_texel(min_ndc[0], self.width),
+            to_texel(max_ndc[0], self.width),
+        );
+        let (min_y, max_y) = (
+            to_texel(min_ndc[1], self.height),
+            to_texel(max_ndc[1], self.height),
+        );
+
+        (min_y..=max_y).any(|y| {
+            (min_x..=max_x).any(|x| min_depth <= self.depth[(y * self.width + x) as usize])
+        })
+    }
+}
+
+struct ReadbackSlot {
+    buffer: Subbuffer<[f32]>,
+    view_proj: Matrix4<f32>,
+    /// None if nothing has been written to the buffer yet
+    frame: Option<u64>,
+}
+
+struct DepthPyramid {
+    image: Arc<CustomStorageImage>,
+    mip_views: Vec<Arc<ImageView<CustomStorageImage>>>,
+}
+
+/// Builds a depth pyramid from the depth buffer and copies its smallest level to the CPU,
+/// so that the next frames can skip objects that are hidden behind walls.
+pub(crate) struct DepthPyramidRenderer {
+    pipeline: Arc<ComputePipeline>,
+    sampler: Arc<Sampler>,
+
+    depth_images: Vec<Arc<dyn ImageViewAbstract>>,
+    pyramids: Vec<DepthPyramid>,
+    readback_slots: Vec<ReadbackSlot>,
+    latest_readback: Option<DepthPyramidReadback>,
+    frame_counter: u64,
+
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+    descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+}
+
+impl DepthPyramidRenderer {
+    pub fn new(
+        context: &Context,
+        depth_images: Vec<Arc<dyn ImageViewAbstract>>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> Self {
+        let pipeline = {
+            let shader = cs::load(context.device()).unwrap();
+
+            ComputePipeline::new(
+                context.device(),
+                shader.entry_point("main").unwrap(),
+                &(),
+                None,
+                |_| {},
+            )
+            .unwrap()
+        };
+
+        let sampler = Sampler::new(
+            context.device(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Nearest,
+                min_filter: Filter::Nearest,
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let mut depth_pyramid_renderer = Self {
+            pipeline,
+            sampler,
+            depth_images: vec![],
+            pyramids: vec![],
+            readback_slots: vec![],
+            latest_readback: None,
+            frame_counter: 0,
+            memory_allocator,
+            command_buffer_allocator,
+            descriptor_set_allocator,
+        };
+        depth_pyramid_renderer.resize(depth_images);
+        depth_pyramid_renderer
+    }
+
+    pub fn resize(&mut self, depth_images: Vec<Arc<dyn ImageViewAbstract>>) {
+        let [width, height] = depth_images[0].image().dimensions().width_height();
+        let base_size = [(width / 2).max(1), (height / 2).max(1)];
+
+        let mut mip_levels = 1;
+        let mut readback_size = base_size;
+        while readback_size[0] > READBACK_MAX_WIDTH {
+            readback_size = readback_size.map(|v| (v / 2).max(1));
+            mip_levels += 1;
+        }
+
+        self.pyramids = depth_images
+            .iter()
+            .map(|_| Self::create_pyramid(&self.memory_allocator, base_size, mip_levels))
+            .collect();
+
+        self.readback_slots = depth_images
+            .iter()
+            .map(|_| ReadbackSlot {
+                buffer: Buffer::new_slice(
+                    &self.memory_allocator,
+                    BufferCreateInfo {
+                        usage: BufferUsage::TRANSFER_DST,
+                        ..Default::default()
+                    },
+                    AllocationCreateInfo {
+                        usage: MemoryUsage::Download,
+                        ..Default::default()
+                    },
+                    (readback_size[0] * readback_size[1]) as u64,
+                )
+                .unwrap(),
+                view_proj: Matrix4::identity(),
+                frame: None,
+            })
+            .collect();
+
+        self.depth_images = depth_images;
+        self.latest_readback = None;
+    }
+
+    fn create_pyramid(
+        memory_allocator: &StandardMemoryAllocator,
+        base_size: [u32; 2],
+        mip_levels: u32,
+    ) -> DepthPyramid {
+        let image = CustomStorageImage::uninitialized(
+            memory_allocator,
+            ImageDimensions::Dim2d {
+                width: base_size[0],
+                height: base_size[1],
+                array_layers: 1,
+            },
+            Format::R32_SFLOAT,
+            mip_levels,
+            ImageUsage::STORAGE | ImageUsage::SAMPLED | ImageUsage::TRANSFER_SRC,
+            ImageCreateFlags::empty(),
+            ImageLayout::General,
+        )
+        .unwrap();
+
+        let mip_views = (0..mip_levels)
+            .map(|mip_level| {
+                ImageView::new(
+                    image.clone(),
+                    ImageViewCreateInfo {
+                        format: Some(image.format()),
+                        subresource_range: ImageSubresourceRange {
+                            mip_levels: mip_level..(mip_level + 1),
+                            ..image.subresource_range()
+                        },
+                        ..ImageViewCreateInfo::default()
+                    },
+                )
+                .unwrap()
+            })
+            .collect();
+
+        DepthPyramid { image, mip_views }
+    }
+
+    /// Picks up the newest depth pyramid that the GPU has finished copying
+    pub fn update_readback(&mut self) {
+        let latest_frame = self.latest_readback.as_ref().map(|readback| readback.frame);
+
+        let mut slots: Vec<&ReadbackSlot> = self
+            .readback_slots
+            .iter()
+            .filter(|slot| slot.frame > latest_frame)
+            .collect();
+        slots.sort_by_key(|slot| std::cmp::Reverse(slot.frame));
+
+        let [width, height] = self.pyramids[0]
+            .image
+            .dimensions()
+            .mip_level_dimensions(self.pyramids[0].image.mip_levels() - 1)
+            .unwrap()
+            .width_height();
+
+        for slot in slots {
+            // Fails while the GPU is still using the buffer
+            if let Ok(depth) = slot.buffer.read() {
+                self.latest_readback = Some(DepthPyramidReadback {
+                    depth: depth.to_vec(),
+                    width,
+                    height,
+                    view_proj: slot.view_proj,
+                    frame: slot.frame.unwrap(),
+                });
+                break;
+            }
+        }
+    }
+
+    pub fn readback(&self) -> Option<&DepthPyramidReadback> {
+        self.latest_readback.as_ref()
+    }
+
+    /// Has to be called after the depth buffer has been rendered
+    pub fn render<F>(
+        &mut self,
+        context: &Context,
+        future: F,
+        view_proj: Matrix4<f32>,
+        image_index: u32,
+    ) -> Box<dyn GpuFuture>
+    where
+        F: GpuFuture + 'static,
+    {
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            context.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        let pyramid = &self.pyramids[image_index as usize];
+        let set_layout = self.pipeline.layout().set_layouts().get(0).unwrap();
+
+        builder.bind_pipeline_compute(self.pipeline.clone());
+
+        for mip_level in 0..pyramid.image.mip_levels() {
+            let input_image: Arc<dyn ImageViewAbstract> = if mip_level == 0 {
+                self.depth_images[image_index as usize].clone()
+            } else {
+                pyramid.mip_views[mip_level as usize - 1].clone()
+            };
+            let output_image = pyramid.mip_views[mip_level as usize].clone();
+
+            let descriptor_set = PersistentDescriptorSet::new(
+                &self.descriptor_set_allocator,
+                set_layout.clone(),
+                [
+                    WriteDescriptorSet::image_view_sampler(0, input_image, self.sampler.clone()),
+                    WriteDescriptorSet::image_view(1, output_image),
+                ],
+            )
+            .unwrap();
+
+            let output_size = pyramid
+                .image
+                .dimensions()
+                .mip_level_dimensions(mip_level)
+                .unwrap()
+                .width_height();
+
+            builder
+                .bind_descriptor_sets(
+                    PipelineBindPoint::Compute,
+                    self.pipeline.layout().clone(),
+                    0,
+                    descriptor_set,
+                )
+                .dispatch([
+                    (output_size[0] as f32 / 8.0).ceil() as u32,
+                    (output_size[1] as f32 / 8.0).ceil() as u32,
+                    1,
+                ])
+                .unwrap();
+        }
+
+        let readback_level = pyramid.image.mip_levels() - 1;
+        let readback_size = pyramid
+            .image
+            .dimensions()
+            .mip_level_dimensions(readback_level)
+            .unwrap()
+            .width_height();
+
+        let slot = &mut self.readback_slots[image_index as usize];
+        let mut copy_info =
+            CopyImageToBufferInfo::image_buffer(pyramid.image.clone(), slot.buffer.clone());
+        copy_info.regions[0].image_subresource.mip_level = readback_level;
+        copy_info.regions[0].image_extent = [readback_size[0], readback_size[1], 1];
+        builder.copy_image_to_buffer(copy_info).unwrap();
+
+        self.frame_counter += 1;
+        slot.view_proj = view_proj;
+        slot.frame = Some(self.frame_counter);
+
+        let command_buffer = builder.build().unwrap();
+
+        future
+            .then_execute(context.queue(), command_buffer)
+            .unwrap()
+            .boxed()
+    }
+}
+
+mod cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "../assets/shaders/occlusion_culling/depth_pyramid.comp",
+    }
+}
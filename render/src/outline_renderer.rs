@@ -0,0 +1,259 @@
+use crate::context::Context;
+use crate::scene::mesh::MeshVertex;
+use crate::scene::model::GpuModel;
+use scene::camera::Camera;
+use scene::transform::Transform;
+use std::sync::Arc;
+use vulkano::buffer::allocator::{SubbufferAllocator, SubbufferAllocatorCreateInfo};
+use vulkano::buffer::BufferUsage;
+use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
+use vulkano::command_buffer::{
+    AutoCommandBufferBuilder, CommandBufferUsage, RenderPassBeginInfo, SubpassContents,
+};
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::format::Format;
+use vulkano::image::ImageViewAbstract;
+use vulkano::memory::allocator::StandardMemoryAllocator;
+use vulkano::pipeline::graphics::depth_stencil::{CompareOp, DepthState, DepthStencilState};
+use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
+use vulkano::pipeline::graphics::rasterization::{CullMode, RasterizationState};
+use vulkano::pipeline::graphics::vertex_input::Vertex;
+use vulkano::pipeline::graphics::viewport::{Viewport, ViewportState};
+use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint, StateMode};
+use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass};
+use vulkano::sync::GpuFuture;
+
+/// In radians, how wide the outline is on the screen
+const OUTLINE_WIDTH: f32 = 0.003;
+
+/// Draws outlines around highlighted models, with an inverted hull.
+/// The back faces of a slightly larger copy of the model stick out behind the edges of the model.
+/// Uses the depth buffer of the scene, but doesn't write to it.
+pub struct OutlineRenderer {
+    render_pass: Arc<RenderPass>,
+    pipeline: Arc<GraphicsPipeline>,
+    framebuffers: Vec<Arc<Framebuffer>>,
+
+    buffer_allocator: SubbufferAllocator,
+    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+    descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+}
+
+impl OutlineRenderer {
+    pub fn new(
+        context: &Context,
+        images: &[Arc<dyn ImageViewAbstract>],
+        depth_images: &[Arc<dyn ImageViewAbstract>],
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> Self {
+        let render_pass = vulkano::single_pass_renderpass!(
+            context.device(),
+            attachments: {
+                color: {
+                    load: Load,
+                    store: Store,
+                    format: Format::R16G16B16A16_SFLOAT,
+                    samples: 1,
+                },
+                depth: {
+                    load: Load,
+                    store: Store,
+                    format: Format::D32_SFLOAT,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {depth}
+            }
+        )
+        .unwrap();
+
+        let pipeline = {
+            let vs = vs::load(context.device()).unwrap();
+            let fs = fs::load(context.device()).unwrap();
+
+            GraphicsPipeline::start()
+                .rasterization_state(RasterizationState::new().cull_mode(CullMode::Front))
+                .depth_stencil_state(DepthStencilState {
+                    depth: Some(DepthState {
+                        enable_dynamic: false,
+                        write_enable: StateMode::Fixed(false),
+                        compare_op: StateMode::Fixed(CompareOp::LessOrEqual),
+                    }),
+                    ..DepthStencilState::disabled()
+                })
+                .vertex_input_state(MeshVertex::per_vertex())
+                .vertex_shader(vs.entry_point("main").unwrap(), ())
+                .input_assembly_state(InputAssemblyState::new())
+                .fragment_shader(fs.entry_point("main").unwrap(), ())
+                .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+                .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+                .build(context.device())
+                .unwrap()
+        };
+
+        let buffer_allocator = SubbufferAllocator::new(
+            memory_allocator,
+            SubbufferAllocatorCreateInfo {
+                buffer_usage: BufferUsage::UNIFORM_BUFFER,
+                ..Default::default()
+            },
+        );
+
+        let framebuffers = Self::create_framebuffers(images, depth_images, render_pass.clone());
+
+        Self {
+            render_pass,
+            pipeline,
+            framebuffers,
+            buffer_allocator,
+            command_buffer_allocator,
+            descriptor_set_allocator,
+        }
+    }
+
+    pub fn resize(
+        &mut self,
+        images: &[Arc<dyn ImageViewAbstract>],
+        depth_images: &[Arc<dyn ImageViewAbstract>],
+    ) {
+        self.framebuffers =
+            Self::create_framebuffers(images, depth_images, self.render_pass.clone());
+    }
+
+    fn create_framebuffers(
+        images: &[Arc<dyn ImageViewAbstract>],
+        depth_images: &[Arc<dyn ImageViewAbstract>],
+        render_pass: Arc<RenderPass>,
+    ) -> Vec<Arc<Framebuffer>> {
+        images
+            .iter()
+            .zip(depth_images.iter())
+            .map(|(image, depth_image)| {
+                Framebuffer::new(
+                    render_pass.clone(),
+                    FramebufferCreateInfo {
+                        attachments: vec![image.clone(), depth_image.clone()],
+                        ..FramebufferCreateInfo::default()
+                    },
+                )
+                .expect("failed to create framebuffer")
+            })
+            .collect()
+    }
+
+    pub fn render<F>(
+        &self,
+        context: &Context,
+        future: F,
+        highlighted: Vec<(&Transform, &GpuModel)>,
+        camera: &Camera,
+        rewind_time: f32,
+        image_index: u32,
+    ) -> Box<dyn GpuFuture>
+    where
+        F: GpuFuture + 'static,
+    {
+        if highlighted.is_empty() {
+            return future.boxed();
+        }
+
+        let framebuffer = self.framebuffers[image_index as usize].clone();
+        let viewport = Viewport {
+            origin: [0.0, 0.0],
+            dimensions: framebuffer.extent().map(|v| v as f32),
+            depth_range: 0.0..1.0,
+        };
+
+        let uniform_subbuffer_scene = {
+            let uniform_data = vs::Scene {
+                projView: (camera.proj() * camera.view()).into(),
+                cameraPosition: camera.position.into(),
+                rewindTime: rewind_time,
+                outlineWidth: OUTLINE_WIDTH,
+            };
+
+            let subbuffer = self.buffer_allocator.allocate_sized().unwrap();
+            *subbuffer.write().unwrap() = uniform_data;
+
+            subbuffer
+        };
+
+        let scene_descriptor_set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            self.pipeline.layout().set_layouts().get(0).unwrap().clone(),
+            [WriteDescriptorSet::buffer(0, uniform_subbuffer_scene)],
+        )
+        .unwrap();
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            context.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        builder
+            .begin_render_pass(
+                RenderPassBeginInfo {
+                    clear_values: vec![None, None],
+                    ..RenderPassBeginInfo::framebuffer(framebuffer)
+                },
+                SubpassContents::Inline,
+            )
+            .unwrap()
+            .set_viewport(0, [viewport])
+            .bind_pipeline_graphics(self.pipeline.clone())
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                self.pipeline.layout().clone(),
+                0,
+                scene_descriptor_set,
+            );
+
+        for (transform, model) in highlighted {
+            builder.push_constants(
+                self.pipeline.layout().clone(),
+                0,
+                vs::Entity {
+                    model: transform.to_matrix().into(),
+                },
+            );
+
+            for primitive in &model.primitives {
+                builder
+                    .bind_index_buffer(primitive.mesh.index_buffer.clone())
+                    .bind_vertex_buffers(0, primitive.mesh.vertex_buffer.clone())
+                    .draw_indexed(primitive.mesh.index_buffer.len() as u32, 1, 0, 0, 0)
+                    .unwrap();
+            }
+        }
+
+        builder.end_render_pass().unwrap();
+
+        let command_buffer = builder.build().unwrap();
+
+        future
+            .then_execute(context.queue(), command_buffer)
+            .unwrap()
+            .boxed()
+    }
+}
+
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "../assets/shaders/outline/outline.vert",
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "../assets/shaders/outline/outline.frag",
+    }
+}
@@ -0,0 +1,104 @@
+use crate::bloom_renderer::BloomSettings;
+use crate::context::Context;
+use crate::tonemap_renderer::TonemappingSettings;
+use nalgebra::Vector3;
+use scene::color_grading::ColorLut;
+use std::sync::Arc;
+use vulkano::image::ImageViewAbstract;
+use vulkano::sync::GpuFuture;
+
+/// Per frame data that the post-processing effects can use
+pub(crate) struct PostProcessFrame {
+    pub rewind_time: f32,
+    /// Between 0 and 1, see [`scene::screen_glitch::ScreenGlitch`]
+    pub glitch: f32,
+    /// Between 0 and 1, see [`scene::flash::Flash`]
+    pub flash: f32,
+    pub flash_color: Vector3<f32>,
+    pub bloom: BloomSettings,
+    pub tonemapping: TonemappingSettings,
+    /// See [`scene::color_grading::ColorGrading`]
+    pub color_lut: Option<Arc<ColorLut>>,
+    pub blend_color_lut: Option<Arc<ColorLut>>,
+    pub color_lut_blend: f32,
+}
+
+/// A full-screen effect that reads the output of the previous effect and writes to its own images.
+/// There is one image per swapchain image.
+pub(crate) trait PostProcessEffect {
+    /// Gets called whenever the previous images change, e.g. when the window gets resized
+    fn resize(&mut self, input_images: Vec<Arc<dyn ImageViewAbstract>>);
+
+    fn render(
+        &mut self,
+        context: &Context,
+        future: Box<dyn GpuFuture>,
+        frame: &PostProcessFrame,
+        image_index: u32,
+    ) -> Box<dyn GpuFuture>;
+
+    fn output_images(&self) -> Vec<Arc<dyn ImageViewAbstract>>;
+}
+
+/// Runs the effects one after the other, starting with the scene images
+pub(crate) struct PostProcessChain {
+    input_images: Vec<Arc<dyn ImageViewAbstract>>,
+    effects: Vec<Box<dyn PostProcessEffect>>,
+}
+
+impl PostProcessChain {
+    pub fn new(input_images: Vec<Arc<dyn ImageViewAbstract>>) -> Self {
+        Self {
+            input_images,
+            effects: vec![],
+        }
+    }
+
+    /// Appends an effect, which gets created with the output images of the current last effect
+    pub fn add_effect<E, F>(&mut self, create_effect: F)
+    where
+        E: PostProcessEffect + 'static,
+        F: FnOnce(Vec<Arc<dyn ImageViewAbstract>>) -> E,
+    {
+        let effect = create_effect(self.output_images());
+        self.effects.push(Box::new(effect));
+    }
+
+    pub fn resize(&mut self, input_images: Vec<Arc<dyn ImageViewAbstract>>) {
+        self.input_images = input_images;
+
+        let mut images = self.input_images.clone();
+        for effect in self.effects.iter_mut() {
+            effect.resize(images);
+            images = effect.output_images();
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        context: &Context,
+        future: Box<dyn GpuFuture>,
+        frame: &PostProcessFrame,
+        image_index: u32,
+    ) -> Box<dyn GpuFuture> {
+        self.effects.iter_mut().fold(future, |future, effect| {
+            effect.render(context, future, frame, image_index)
+        })
+    }
+
+    /// The images of every effect
+    pub fn all_output_images(&self) -> Vec<Arc<dyn ImageViewAbstract>> {
+        self.effects
+            .iter()
+            .flat_map(|effect| effect.output_images())
+            .collect()
+    }
+
+    /// The images of the last effect, or the input images if there are no effects
+    pub fn output_images(&self) -> Vec<Arc<dyn ImageViewAbstract>> {
+        self.effects
+            .last()
+            .map(|effect| effect.output_images())
+            .unwrap_or_else(|| self.input_images.clone())
+    }
+}
@@ -1,5 +1,4 @@
 use crate::context::Context;
-use crate::custom_storage_image::CustomStorageImage;
 use crate::quad::{self, quad_mesh, QuadVertex};
 use std::sync::Arc;
 use vulkano::buffer::Subbuffer;
@@ -13,21 +12,28 @@ use vulkano::descriptor_set::layout::DescriptorSetLayout;
 use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
 use vulkano::format::Format;
 use vulkano::image::view::ImageView;
-use vulkano::image::SwapchainImage;
+use vulkano::image::{ImageUsage, ImageViewAbstract, SwapchainImage};
 use vulkano::memory::allocator::StandardMemoryAllocator;
 use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
 use vulkano::pipeline::graphics::vertex_input::Vertex;
 use vulkano::pipeline::graphics::viewport::{Viewport, ViewportState};
-use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint};
+use vulkano::pipeline::{ComputePipeline, GraphicsPipeline, Pipeline, PipelineBindPoint};
 use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass};
 use vulkano::sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerMipmapMode};
 use vulkano::sync::GpuFuture;
 
+/// Copies the final image to the swapchain.
+/// If the swapchain images can be used as storage images, a compute shader writes straight into them,
+/// otherwise a full-screen quad gets drawn.
 pub struct QuadRenderer {
     pipeline: Arc<GraphicsPipeline>,
     framebuffers: Vec<Arc<Framebuffer>>,
     render_pass: Arc<RenderPass>,
 
+    /// Only exists if the swapchain images have the storage usage
+    storage_pipeline: Option<Arc<ComputePipeline>>,
+    storage_descriptor_sets: Vec<Arc<PersistentDescriptorSet>>,
+
     sampler: Arc<Sampler>,
     descriptor_sets: Vec<Arc<PersistentDescriptorSet>>,
     index_buffer: Subbuffer<[u32]>,
@@ -40,13 +46,12 @@ pub struct QuadRenderer {
 impl QuadRenderer {
     pub fn new(
         context: &Context,
-        input_images: &[Arc<ImageView<CustomStorageImage>>],
+        input_images: &[Arc<dyn ImageViewAbstract>],
         output_images: &[Arc<ImageView<SwapchainImage>>],
         final_output_format: Format,
         memory_allocator: Arc<StandardMemoryAllocator>,
         command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
         descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
-        brightness: f32,
     ) -> Self {
         let (vertex_buffer, index_buffer) =
             quad::create_geometry_buffers(quad_mesh(), memory_allocator.clone());
@@ -71,13 +76,11 @@ impl QuadRenderer {
             let vertex_shader = vs::load(context.device()).unwrap();
             let fragment_shader = fs::load(context.device()).unwrap();
 
-            let spec_consts = fs::SpecializationConstants { brightness };
-
             GraphicsPipeline::start()
                 .vertex_input_state(QuadVertex::per_vertex())
                 .vertex_shader(vertex_shader.entry_point("main").unwrap(), ())
                 .input_assembly_state(InputAssemblyState::new())
-                .fragment_shader(fragment_shader.entry_point("main").unwrap(), spec_consts)
+                .fragment_shader(fragment_shader.entry_point("main").unwrap(), ())
                 .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
                 .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
                 .build(context.device())
@@ -86,10 +89,11 @@ impl QuadRenderer {
 
         let sampler = Sampler::new(
             context.device(),
+            // The image gets scaled up or down when the render scale isn't 100%
             SamplerCreateInfo {
-                mag_filter: Filter::Nearest,
-                min_filter: Filter::Nearest,
-                address_mode: [SamplerAddressMode::Repeat; 3],
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
                 mipmap_mode: SamplerMipmapMode::Nearest,
                 ..SamplerCreateInfo::default()
             },
@@ -107,11 +111,42 @@ impl QuadRenderer {
 
         let framebuffers = Self::create_framebuffers(render_pass.clone(), output_images);
 
+        let storage_pipeline = if supports_storage(output_images) {
+            let shader = cs::load(context.device()).unwrap();
+
+            Some(
+                ComputePipeline::new(
+                    context.device(),
+                    shader.entry_point("main").unwrap(),
+                    &(),
+                    None,
+                    |_| {},
+                )
+                .unwrap(),
+            )
+        } else {
+            None
+        };
+
+        let storage_descriptor_sets = match &storage_pipeline {
+            Some(storage_pipeline) => Self::create_storage_descriptor_sets(
+                storage_pipeline.layout().set_layouts().get(0).unwrap(),
+                input_images,
+                output_images,
+                sampler.clone(),
+                descriptor_set_allocator.clone(),
+            ),
+            None => vec![],
+        };
+
         Self {
             pipeline,
             framebuffers,
             render_pass,
 
+            storage_pipeline,
+            storage_descriptor_sets,
+
             sampler,
             descriptor_sets,
             index_buffer,
@@ -125,7 +160,7 @@ impl QuadRenderer {
     pub fn resize(
         &mut self,
         output_images: &[Arc<ImageView<SwapchainImage>>],
-        input_images: &[Arc<ImageView<CustomStorageImage>>],
+        input_images: &[Arc<dyn ImageViewAbstract>],
     ) {
         self.framebuffers = Self::create_framebuffers(self.render_pass.clone(), output_images);
 
@@ -136,6 +171,17 @@ impl QuadRenderer {
             self.sampler.clone(),
             self.descriptor_set_allocator.clone(),
         );
+
+        // The swapchain keeps its usage when it gets recreated
+        if let Some(storage_pipeline) = &self.storage_pipeline {
+            self.storage_descriptor_sets = Self::create_storage_descriptor_sets(
+                storage_pipeline.layout().set_layouts().get(0).unwrap(),
+                input_images,
+                output_images,
+                self.sampler.clone(),
+                self.descriptor_set_allocator.clone(),
+            );
+        }
     }
 
     fn create_framebuffers(
@@ -159,7 +205,7 @@ impl QuadRenderer {
 
     fn create_descriptor_sets(
         layout: &Arc<DescriptorSetLayout>,
-        images: &[Arc<ImageView<CustomStorageImage>>],
+        images: &[Arc<dyn ImageViewAbstract>],
         sampler: Arc<Sampler>,
         descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
     ) -> Vec<Arc<PersistentDescriptorSet>> {
@@ -180,6 +226,34 @@ impl QuadRenderer {
             .collect()
     }
 
+    fn create_storage_descriptor_sets(
+        layout: &Arc<DescriptorSetLayout>,
+        input_images: &[Arc<dyn ImageViewAbstract>],
+        output_images: &[Arc<ImageView<SwapchainImage>>],
+        sampler: Arc<Sampler>,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> Vec<Arc<PersistentDescriptorSet>> {
+        input_images
+            .iter()
+            .zip(output_images.iter())
+            .map(|(input_image, output_image)| {
+                PersistentDescriptorSet::new(
+                    &descriptor_set_allocator,
+                    layout.clone(),
+                    [
+                        WriteDescriptorSet::image_view_sampler(
+                            0,
+                            input_image.clone(),
+                            sampler.clone(),
+                        ),
+                        WriteDescriptorSet::image_view(1, output_image.clone()),
+                    ],
+                )
+                .unwrap()
+            })
+            .collect()
+    }
+
     pub fn render<F>(
         &self,
         context: &Context,
@@ -198,6 +272,33 @@ impl QuadRenderer {
         )
         .unwrap();
 
+        if let Some(storage_pipeline) = &self.storage_pipeline {
+            // Skips the render pass, every pixel gets written by the compute shader
+            let dimensions = self.framebuffers[swapchain_frame_index as usize].extent();
+            let dispatch_size = [
+                (dimensions[0] as f32 / 8.0).ceil() as u32,
+                (dimensions[1] as f32 / 8.0).ceil() as u32,
+                1,
+            ];
+
+            builder
+                .bind_pipeline_compute(storage_pipeline.clone())
+                .bind_descriptor_sets(
+                    PipelineBindPoint::Compute,
+                    storage_pipeline.layout().clone(),
+                    0,
+                    self.storage_descriptor_sets[swapchain_frame_index as usize].clone(),
+                )
+                .dispatch(dispatch_size)
+                .unwrap();
+
+            let command_buffer = builder.build().unwrap();
+
+            return future
+                .then_execute(context.queue(), command_buffer)
+                .unwrap();
+        }
+
         builder
             .set_viewport(0, [viewport.clone()])
             .begin_render_pass(
@@ -232,6 +333,13 @@ impl QuadRenderer {
     }
 }
 
+/// The swapchain was created with the storage usage if the surface and the image format support it
+fn supports_storage(output_images: &[Arc<ImageView<SwapchainImage>>]) -> bool {
+    output_images
+        .first()
+        .map_or(false, |image| image.usage().intersects(ImageUsage::STORAGE))
+}
+
 mod vs {
     vulkano_shaders::shader! {
         ty: "vertex",
@@ -245,3 +353,10 @@ mod fs {
         path: "../assets/shaders/quad/quad.frag",
     }
 }
+
+mod cs {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        path: "../assets/shaders/quad/quad.comp",
+    }
+}
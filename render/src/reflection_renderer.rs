@@ -0,0 +1,86 @@
+use crate::context::Context;
+use crate::fullscreen_pass::FullscreenPass;
+use crate::scene_renderer::GBuffer;
+use nalgebra::Matrix4;
+use std::sync::Arc;
+use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::image::ImageViewAbstract;
+use vulkano::memory::allocator::StandardMemoryAllocator;
+use vulkano::sync::GpuFuture;
+
+/// Screen space reflections, for the shiny floors.
+/// Marches along the reflected view ray through the depth buffer of the prepass,
+/// and the reflectivity of the materials is stored in the alpha channel of the normals.
+/// Without the depth prepass, nothing is reflective.
+pub(crate) struct ReflectionRenderer {
+    fullscreen_pass: FullscreenPass,
+}
+
+impl ReflectionRenderer {
+    pub fn new(
+        context: &Context,
+        input_images: Vec<Arc<dyn ImageViewAbstract>>,
+        gbuffer: &GBuffer,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> Self {
+        let fullscreen_pass = FullscreenPass::new_with_additional_images(
+            context,
+            fs::load(context.device()).unwrap(),
+            input_images,
+            Self::gbuffer_images(gbuffer),
+            memory_allocator,
+            command_buffer_allocator,
+            descriptor_set_allocator,
+        );
+
+        Self { fullscreen_pass }
+    }
+
+    pub fn resize(&mut self, input_images: Vec<Arc<dyn ImageViewAbstract>>, gbuffer: &GBuffer) {
+        self.fullscreen_pass
+            .resize_with_additional_images(input_images, Self::gbuffer_images(gbuffer));
+    }
+
+    fn gbuffer_images(gbuffer: &GBuffer) -> Vec<Vec<Arc<dyn ImageViewAbstract>>> {
+        let to_abstract = |images: &Vec<_>| {
+            images
+                .iter()
+                .map(|image| image.clone() as Arc<dyn ImageViewAbstract>)
+                .collect()
+        };
+        vec![to_abstract(&gbuffer.depth), to_abstract(&gbuffer.normals)]
+    }
+
+    pub fn render(
+        &self,
+        context: &Context,
+        future: Box<dyn GpuFuture>,
+        view_proj: Matrix4<f32>,
+        image_index: u32,
+    ) -> Box<dyn GpuFuture> {
+        let pass = fs::Pass {
+            viewProj: view_proj.into(),
+            inverseViewProj: view_proj
+                .try_inverse()
+                .unwrap_or_else(Matrix4::identity)
+                .into(),
+        };
+
+        self.fullscreen_pass
+            .render(context, future, image_index, pass)
+    }
+
+    pub fn output_images(&self) -> Vec<Arc<dyn ImageViewAbstract>> {
+        self.fullscreen_pass.output_images()
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "../assets/shaders/reflections/reflections.frag",
+    }
+}
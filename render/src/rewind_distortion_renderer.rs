@@ -0,0 +1,68 @@
+use crate::context::Context;
+use crate::fullscreen_pass::FullscreenPass;
+use crate::post_process::{PostProcessEffect, PostProcessFrame};
+use std::sync::Arc;
+use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::image::ImageViewAbstract;
+use vulkano::memory::allocator::StandardMemoryAllocator;
+use vulkano::sync::GpuFuture;
+
+/// Scanlines, a CRT warp and desaturation while rewinding, and the screen glitches
+pub(crate) struct RewindDistortionRenderer {
+    fullscreen_pass: FullscreenPass,
+}
+
+impl RewindDistortionRenderer {
+    pub fn new(
+        context: &Context,
+        input_images: Vec<Arc<dyn ImageViewAbstract>>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> Self {
+        let fullscreen_pass = FullscreenPass::new(
+            context,
+            fs::load(context.device()).unwrap(),
+            input_images,
+            memory_allocator,
+            command_buffer_allocator,
+            descriptor_set_allocator,
+        );
+
+        Self { fullscreen_pass }
+    }
+}
+
+impl PostProcessEffect for RewindDistortionRenderer {
+    fn resize(&mut self, input_images: Vec<Arc<dyn ImageViewAbstract>>) {
+        self.fullscreen_pass.resize(input_images);
+    }
+
+    fn render(
+        &mut self,
+        context: &Context,
+        future: Box<dyn GpuFuture>,
+        frame: &PostProcessFrame,
+        image_index: u32,
+    ) -> Box<dyn GpuFuture> {
+        let pass = fs::Pass {
+            rewindTime: frame.rewind_time,
+            glitch: frame.glitch,
+        };
+
+        self.fullscreen_pass
+            .render(context, future, image_index, pass)
+    }
+
+    fn output_images(&self) -> Vec<Arc<dyn ImageViewAbstract>> {
+        self.fullscreen_pass.output_images()
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "../assets/shaders/rewind_distortion/rewind_distortion.frag",
+    }
+}
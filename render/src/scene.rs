@@ -1,5 +1,8 @@
+pub mod billboard;
+pub mod decal;
 pub mod material;
 pub mod mesh;
 pub mod model;
 pub mod texture;
 pub mod ui_component;
+pub mod video_texture;
@@ -0,0 +1,8 @@
+use crate::scene::texture::Texture;
+use bevy_ecs::prelude::*;
+use std::sync::Arc;
+
+#[derive(Component)]
+pub struct GpuBillboard {
+    pub texture: Arc<Texture>,
+}
@@ -0,0 +1,10 @@
+use crate::scene::texture::Texture;
+use bevy_ecs::prelude::*;
+use nalgebra::Vector3;
+use std::sync::Arc;
+
+#[derive(Component)]
+pub struct GpuDecal {
+    pub texture: Arc<Texture>,
+    pub extent: Vector3<f32>,
+}
@@ -1,5 +1,6 @@
 use nalgebra::Vector3;
 use scene::asset::{Asset, AssetId};
+use scene::material::{Flipbook, Wind};
 use std::sync::Arc;
 
 use super::texture::Texture;
@@ -10,9 +11,14 @@ pub struct Material {
     pub id: AssetId,
     pub base_color: Vector3<f32>,
     pub base_color_texture: Option<Arc<Texture>>,
+    pub lightmap_texture: Option<Arc<Texture>>,
     pub roughness_factor: f32,
     pub metallic_factor: f32,
     pub emissivity: Vector3<f32>, // TODO: Add a shader/pipeline here (we only support one shader for now)
+    pub reflectivity: f32,
+    pub wind: Option<Wind>,
+    pub flipbook: Option<Flipbook>,
+    pub opacity: f32,
 }
 
 impl Asset for Material {
@@ -1,8 +1,11 @@
+use math::bounding_box::BoundingBox;
 use nalgebra::Vector3;
 use scene::asset::{Asset, AssetId};
 use scene::mesh::CpuMeshVertex;
 use std::sync::Arc;
-use vulkano::buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::buffer::{
+    Buffer, BufferContents, BufferCreateInfo, BufferError, BufferUsage, Subbuffer,
+};
 use vulkano::memory::allocator::{AllocationCreateInfo, MemoryAllocator, MemoryUsage};
 use vulkano::pipeline::graphics::vertex_input::Vertex;
 
@@ -17,6 +20,9 @@ pub struct MeshVertex {
 
     #[format(R32G32_SFLOAT)]
     pub(super) uv: [f32; 2],
+
+    #[format(R32G32_SFLOAT)]
+    pub(super) uv1: [f32; 2],
 }
 
 impl From<&CpuMeshVertex> for MeshVertex {
@@ -25,6 +31,7 @@ impl From<&CpuMeshVertex> for MeshVertex {
             position: vertex.position,
             normal: vertex.normal,
             uv: vertex.uv,
+            uv1: vertex.uv1,
         }
     }
 }
@@ -33,6 +40,7 @@ pub struct Mesh {
     pub id: AssetId,
     pub vertex_buffer: Subbuffer<[MeshVertex]>,
     pub index_buffer: Subbuffer<[u32]>,
+    pub bounding_box: BoundingBox<Vector3<f32>>,
     pub bounding_sphere: (Vector3<f32>, f32),
 }
 
@@ -41,24 +49,43 @@ impl Mesh {
         id: AssetId,
         vertices: Vec<MeshVertex>,
         indices: Vec<u32>,
-        bounding_sphere: (Vector3<f32>, f32),
+        bounding_box: BoundingBox<Vector3<f32>>,
         allocator: &(impl MemoryAllocator + ?Sized),
     ) -> Arc<Self> {
-        let (vertex_buffer, index_buffer) = Mesh::setup_buffers(&vertices, &indices, allocator);
+        Self::try_new(id, vertices, indices, bounding_box, allocator)
+            .expect("could not upload mesh to GPU")
+    }
+
+    /// Fails when the GPU runs out of memory
+    pub fn try_new(
+        id: AssetId,
+        vertices: Vec<MeshVertex>,
+        indices: Vec<u32>,
+        bounding_box: BoundingBox<Vector3<f32>>,
+        allocator: &(impl MemoryAllocator + ?Sized),
+    ) -> Result<Arc<Self>, BufferError> {
+        let (vertex_buffer, index_buffer) = Mesh::setup_buffers(&vertices, &indices, allocator)?;
+        let bounding_sphere = bounding_box.bounding_sphere();
 
-        Arc::new(Self {
+        Ok(Arc::new(Self {
             id,
             vertex_buffer,
             index_buffer,
+            bounding_box,
             bounding_sphere,
-        })
+        }))
+    }
+
+    /// Size of the vertex and index buffers in bytes
+    pub fn byte_size(&self) -> u64 {
+        self.vertex_buffer.size() + self.index_buffer.size()
     }
 
     fn setup_buffers(
         vertices: &[MeshVertex],
         indices: &[u32],
         allocator: &(impl MemoryAllocator + ?Sized),
-    ) -> (Subbuffer<[MeshVertex]>, Subbuffer<[u32]>) {
+    ) -> Result<(Subbuffer<[MeshVertex]>, Subbuffer<[u32]>), BufferError> {
         let vertex_buffer = Buffer::from_iter(
             allocator,
             BufferCreateInfo {
@@ -70,8 +97,7 @@ impl Mesh {
                 ..Default::default()
             },
             vertices.iter().cloned(),
-        )
-        .expect("could not upload vertex data to GPU");
+        )?;
 
         let index_buffer = Buffer::from_iter(
             allocator,
@@ -84,10 +110,9 @@ impl Mesh {
                 ..Default::default()
             },
             indices.iter().cloned(),
-        )
-        .expect("could not upload indices data to GPU");
+        )?;
 
-        (vertex_buffer, index_buffer)
+        Ok((vertex_buffer, index_buffer))
     }
 }
 
@@ -23,15 +23,22 @@ impl Primitive {
         frustum_bounding_sphere: &(Vector3<f32>, f32),
         transform: &Transform,
     ) -> bool {
-        let self_bounding_sphere_center = transform
-            .to_matrix()
-            .transform_point(&Point3::from(self.mesh.bounding_sphere.0))
-            .coords;
-        let self_bounding_sphere_radius = self.mesh.bounding_sphere.1 * transform.scale.max();
+        let (self_bounding_sphere_center, self_bounding_sphere_radius) =
+            self.world_bounding_sphere(transform);
 
         let distance = (frustum_bounding_sphere.0 - self_bounding_sphere_center).norm();
         distance < frustum_bounding_sphere.1 + self_bounding_sphere_radius
     }
+
+    /// The bounding sphere of the mesh in world space
+    pub(crate) fn world_bounding_sphere(&self, transform: &Transform) -> (Vector3<f32>, f32) {
+        let center = transform
+            .to_matrix()
+            .transform_point(&Point3::from(self.mesh.bounding_sphere.0))
+            .coords;
+        let radius = self.mesh.bounding_sphere.1 * transform.scale.max();
+        (center, radius)
+    }
 }
 
 impl GpuModel {}
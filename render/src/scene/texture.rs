@@ -5,6 +5,7 @@ use vulkano::buffer::BufferContents;
 use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
 use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage};
 use vulkano::format::Format;
+use vulkano::image::immutable::ImmutableImageCreationError;
 use vulkano::image::view::ImageView;
 use vulkano::image::{ImageDimensions, ImmutableImage, MipmapsCount};
 use vulkano::memory::allocator::StandardMemoryAllocator;
@@ -41,6 +42,36 @@ impl Texture {
         sampler: Arc<Sampler>,
         context: &Context,
     ) -> Arc<Texture>
+    where
+        Px: BufferContents,
+        I: IntoIterator<Item = Px>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        Self::try_new(
+            id,
+            data_iterator,
+            width,
+            height,
+            format,
+            MipmapsCount::Log2,
+            sampler,
+            context,
+        )
+        .expect("could not upload texture to GPU")
+    }
+
+    /// Fails when the GPU runs out of memory
+    #[allow(clippy::too_many_arguments)]
+    pub fn try_new<I, Px>(
+        id: AssetId,
+        data_iterator: I,
+        width: u32,
+        height: u32,
+        format: Format,
+        mipmaps: MipmapsCount,
+        sampler: Arc<Sampler>,
+        context: &Context,
+    ) -> Result<Arc<Texture>, ImmutableImageCreationError>
     where
         Px: BufferContents,
         I: IntoIterator<Item = Px>,
@@ -71,11 +102,10 @@ impl Texture {
                 &memory_allocator,
                 data_iterator,
                 dimensions,
-                MipmapsCount::Log2,
+                mipmaps,
                 format,
                 &mut command_buffer_builder,
-            )
-            .unwrap();
+            )?;
 
             ImageView::new_default(image).unwrap()
         };
@@ -90,11 +120,11 @@ impl Texture {
 
         future.wait(None).unwrap();
 
-        Arc::new(Texture {
+        Ok(Arc::new(Texture {
             id,
             image_view: texture,
             sampler,
-        })
+        }))
     }
 }
 
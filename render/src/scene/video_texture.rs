@@ -0,0 +1,15 @@
+use bevy_ecs::prelude::*;
+use scene::asset::AssetId;
+use std::sync::Arc;
+use vulkano::image::view::ImageView;
+use vulkano::image::StorageImage;
+use vulkano::sampler::Sampler;
+
+/// The image of a video, it gets created for the first frame and every later frame gets copied into it
+#[derive(Component)]
+pub struct GpuVideoTexture {
+    pub image_view: Arc<ImageView<StorageImage>>,
+    pub sampler: Arc<Sampler>,
+    /// The frame that is in the image
+    pub(crate) frame_id: AssetId,
+}
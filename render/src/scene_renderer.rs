@@ -1,13 +1,21 @@
 use crate::context::Context;
 use crate::custom_storage_image::CustomStorageImage;
+use crate::occlusion_culling::DepthPyramidReadback;
+use crate::scene::decal::GpuDecal;
 use crate::scene::material::Material;
-use crate::scene::mesh::MeshVertex;
+use crate::scene::mesh::{Mesh, MeshVertex};
 use crate::scene::model::GpuModel;
 use crate::scene::texture::Texture;
-use crate::ViewFrustumCullingMode;
-use nalgebra::Point3;
+use crate::scene::video_texture::GpuVideoTexture;
+use crate::shadow_renderer::MAX_SHADOW_LIGHTS;
+use crate::{DepthPrepassMode, ViewFrustumCullingMode};
+use nalgebra::{Matrix4, Vector2, Vector3};
 use scene::camera::Camera;
-use scene::light::{Light, PointLight};
+use scene::fog::Fog;
+use scene::light::{AmbientLight, Light, PointLight};
+use scene::material::TextureScroll;
+use scene::mesh::CpuMesh;
+use scene::sky::Sky;
 use scene::transform::Transform;
 use std::sync::Arc;
 use vulkano::buffer::allocator::{SubbufferAllocator, SubbufferAllocatorCreateInfo};
@@ -21,26 +29,48 @@ use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
 use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
 use vulkano::format::Format;
 use vulkano::image::view::ImageView;
-use vulkano::image::{AttachmentImage, ImageUsage, ImageViewAbstract, SwapchainImage};
+use vulkano::image::{AttachmentImage, ImageUsage, ImageViewAbstract};
 use vulkano::memory::allocator::StandardMemoryAllocator;
 use vulkano::padded::Padded;
-use vulkano::pipeline::graphics::depth_stencil::{CompareOp, DepthStencilState};
+use vulkano::pipeline::graphics::color_blend::{
+    AttachmentBlend, BlendFactor, BlendOp, ColorBlendState,
+};
+use vulkano::pipeline::graphics::depth_stencil::{CompareOp, DepthState, DepthStencilState};
 use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
 use vulkano::pipeline::graphics::rasterization::{CullMode, PolygonMode, RasterizationState};
 use vulkano::pipeline::graphics::vertex_input::Vertex;
 use vulkano::pipeline::graphics::viewport::{Viewport, ViewportState};
-use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint};
+use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint, StateMode};
 use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass};
 use vulkano::sampler::{
     BorderColor, Filter, Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerMipmapMode,
 };
 use vulkano::sync::GpuFuture;
 
+/// Depth and world space normals of the scene, one image per swapchain image.
+/// Written by the depth prepass, so that later passes can sample them.
+pub struct GBuffer {
+    pub depth: Vec<Arc<ImageView<AttachmentImage>>>,
+    pub normals: Vec<Arc<ImageView<AttachmentImage>>>,
+}
+
 pub struct SceneRenderer {
     render_pass: Arc<RenderPass>,
+    /// Only writes depth and normals
+    prepass_pipeline: Arc<GraphicsPipeline>,
+    /// Used when the depth prepass is disabled
     pipeline: Arc<GraphicsPipeline>,
+    /// Reuses the depth buffer of the prepass, which means that every pixel only gets shaded once
+    pipeline_after_prepass: Arc<GraphicsPipeline>,
+    /// Projects the decals onto the depth of the prepass
+    decal_pipeline: Arc<GraphicsPipeline>,
     framebuffers: Vec<Arc<Framebuffer>>,
     output_images: Vec<Arc<ImageView<AttachmentImage>>>,
+    gbuffer: GBuffer,
+    /// The decals of every pixel, which get put on top of the albedo while shading
+    decal_images: Vec<Arc<ImageView<AttachmentImage>>>,
+    /// The box that gets drawn for every decal
+    decal_cube: Arc<Mesh>,
 
     memory_allocator: Arc<StandardMemoryAllocator>,
     command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
@@ -49,7 +79,7 @@ pub struct SceneRenderer {
     buffer_allocator: SubbufferAllocator,
 
     shadow_map_sampler: Arc<Sampler>,
-    shadow_cube_map: Vec<Arc<ImageView<CustomStorageImage>>>,
+    shadow_cube_maps: Vec<Vec<Arc<ImageView<CustomStorageImage>>>>,
 
     /// The 1x1 white texture used when a model is missing a texture
     missing_texture: Arc<Texture>,
@@ -58,7 +88,7 @@ pub struct SceneRenderer {
 impl SceneRenderer {
     pub fn new(
         context: &Context,
-        shadow_cube_map: Vec<Arc<ImageView<CustomStorageImage>>>,
+        shadow_cube_maps: Vec<Vec<Arc<ImageView<CustomStorageImage>>>>,
         dimensions: [u32; 2],
         swapchain_image_count: u32,
         memory_allocator: Arc<StandardMemoryAllocator>,
@@ -67,6 +97,9 @@ impl SceneRenderer {
     ) -> Self {
         let vs = vs::load(context.device()).unwrap();
         let fs = fs::load(context.device()).unwrap();
+        let prepass_fs = prepass_fs::load(context.device()).unwrap();
+        let decal_vs = decal_vs::load(context.device()).unwrap();
+        let decal_fs = decal_fs::load(context.device()).unwrap();
 
         // TODO: consider setting the initial size of the arena
         let buffer_allocator = SubbufferAllocator::new(
@@ -77,7 +110,7 @@ impl SceneRenderer {
             },
         );
 
-        let render_pass = vulkano::single_pass_renderpass!(
+        let render_pass = vulkano::ordered_passes_renderpass!(
             context.device(),
             attachments: {
                 color: {
@@ -86,17 +119,45 @@ impl SceneRenderer {
                     format: Format::R16G16B16A16_SFLOAT,
                     samples: 1,
                 },
-                depth: {
+                normal: {
+                    load: Clear,
+                    store: Store,
+                    format: Format::R16G16B16A16_SFLOAT,
+                    samples: 1,
+                },
+                decal: {
                     load: Clear,
                     store: DontCare,
+                    format: Format::R8G8B8A8_UNORM,
+                    samples: 1,
+                },
+                depth: {
+                    load: Clear,
+                    store: Store,
                     format: Format::D32_SFLOAT,
                     samples: 1,
                 }
             },
-            pass: {
-                color: [color],
-                depth_stencil: {depth}
-            }
+            passes: [
+                // depth prepass
+                {
+                    color: [normal],
+                    depth_stencil: {depth},
+                    input: []
+                },
+                // decals
+                {
+                    color: [decal],
+                    depth_stencil: {},
+                    input: [depth]
+                },
+                // shading
+                {
+                    color: [color],
+                    depth_stencil: {depth},
+                    input: [decal]
+                }
+            ]
         )
         .unwrap();
 
@@ -107,7 +168,7 @@ impl SceneRenderer {
                     .polygon_mode(PolygonMode::Fill),
             )
             // .rasterization_state(RasterizationState::new().cull_mode(CullMode::Back))
-            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+            .render_pass(Subpass::from(render_pass.clone(), 2).unwrap())
             .depth_stencil_state(DepthStencilState::simple_depth_test())
             .vertex_input_state(MeshVertex::per_vertex())
             .input_assembly_state(InputAssemblyState::new())
@@ -117,17 +178,94 @@ impl SceneRenderer {
             .build(context.device())
             .expect("could not create pipeline");
 
+        // All pipelines share one layout, so that the descriptor sets can be reused
+        let pipeline_after_prepass = GraphicsPipeline::start()
+            .rasterization_state(
+                RasterizationState::new()
+                    .cull_mode(CullMode::Back)
+                    .polygon_mode(PolygonMode::Fill),
+            )
+            .render_pass(Subpass::from(render_pass.clone(), 2).unwrap())
+            .depth_stencil_state(DepthStencilState {
+                depth: Some(DepthState {
+                    enable_dynamic: false,
+                    write_enable: StateMode::Fixed(false),
+                    compare_op: StateMode::Fixed(CompareOp::LessOrEqual),
+                }),
+                ..DepthStencilState::disabled()
+            })
+            .vertex_input_state(MeshVertex::per_vertex())
+            .input_assembly_state(InputAssemblyState::new())
+            .vertex_shader(vs.entry_point("main").unwrap(), ())
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .fragment_shader(fs.entry_point("main").unwrap(), ())
+            .with_pipeline_layout(context.device(), pipeline.layout().clone())
+            .expect("could not create pipeline");
+
+        let prepass_pipeline = GraphicsPipeline::start()
+            .rasterization_state(
+                RasterizationState::new()
+                    .cull_mode(CullMode::Back)
+                    .polygon_mode(PolygonMode::Fill),
+            )
+            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+            .depth_stencil_state(DepthStencilState::simple_depth_test())
+            .vertex_input_state(MeshVertex::per_vertex())
+            .input_assembly_state(InputAssemblyState::new())
+            .vertex_shader(vs.entry_point("main").unwrap(), ())
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .fragment_shader(prepass_fs.entry_point("main").unwrap(), ())
+            .with_pipeline_layout(context.device(), pipeline.layout().clone())
+            .expect("could not create pipeline");
+
+        // The decals get drawn as boxes, and the back faces still get drawn when the camera is inside of a box
+        let decal_pipeline = GraphicsPipeline::start()
+            .rasterization_state(
+                RasterizationState::new()
+                    .cull_mode(CullMode::Front)
+                    .polygon_mode(PolygonMode::Fill),
+            )
+            .render_pass(Subpass::from(render_pass.clone(), 1).unwrap())
+            .color_blend_state(ColorBlendState::new(1).blend(AttachmentBlend {
+                color_op: BlendOp::Add,
+                color_source: BlendFactor::SrcAlpha,
+                color_destination: BlendFactor::OneMinusSrcAlpha,
+                alpha_op: BlendOp::Add,
+                alpha_source: BlendFactor::One,
+                alpha_destination: BlendFactor::OneMinusSrcAlpha,
+            }))
+            .vertex_input_state(MeshVertex::per_vertex())
+            .input_assembly_state(InputAssemblyState::new())
+            .vertex_shader(decal_vs.entry_point("main").unwrap(), ())
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .fragment_shader(decal_fs.entry_point("main").unwrap(), ())
+            .build(context.device())
+            .expect("could not create pipeline");
+
+        let decal_cube = {
+            let cube = CpuMesh::cube(1.0, 1.0, 1.0);
+            Mesh::new(
+                cube.id(),
+                cube.vertices.iter().map(|vertex| vertex.into()).collect(),
+                cube.indices.clone(),
+                cube.bounding_box.clone(),
+                memory_allocator.as_ref(),
+            )
+        };
+
         // TODO: let the main_renderer manage those swapchain related framebuffers?
 
         let images: Vec<Arc<ImageView<AttachmentImage>>> =
             Self::create_images(memory_allocator.clone(), swapchain_image_count, dimensions);
 
-        let framebuffers = Self::create_framebuffers(
-            memory_allocator.clone(),
-            dimensions,
-            images.clone(),
-            render_pass.clone(),
-        );
+        let gbuffer =
+            Self::create_gbuffer(memory_allocator.clone(), swapchain_image_count, dimensions);
+
+        let decal_images =
+            Self::create_decal_images(memory_allocator.clone(), swapchain_image_count, dimensions);
+
+        let framebuffers =
+            Self::create_framebuffers(images.clone(), &gbuffer, &decal_images, render_pass.clone());
 
         let missing_texture = Texture::new_one_by_one(
             Sampler::new(
@@ -158,15 +296,21 @@ impl SceneRenderer {
 
         SceneRenderer {
             render_pass,
+            prepass_pipeline,
             pipeline,
+            pipeline_after_prepass,
+            decal_pipeline,
             framebuffers,
             output_images: images,
+            gbuffer,
+            decal_images,
+            decal_cube,
             memory_allocator,
             command_buffer_allocator,
             descriptor_set_allocator,
 
             shadow_map_sampler,
-            shadow_cube_map,
+            shadow_cube_maps,
 
             buffer_allocator,
             missing_texture,
@@ -175,48 +319,61 @@ impl SceneRenderer {
 }
 
 impl SceneRenderer {
+    /// The dimensions can differ from the swapchain dimensions, when the render scale isn't 100%
     pub fn resize(
         &mut self,
-        images: &Vec<Arc<ImageView<SwapchainImage>>>,
-        shadow_cube_map: Vec<Arc<ImageView<CustomStorageImage>>>,
+        dimensions: [u32; 2],
+        swapchain_image_count: u32,
+        shadow_cube_maps: Vec<Vec<Arc<ImageView<CustomStorageImage>>>>,
     ) {
-        let dimensions = images[0].dimensions().width_height();
-        let swapchain_image_count = images.len() as u32;
-
         self.output_images = Self::create_images(
             self.memory_allocator.clone(),
             swapchain_image_count,
             dimensions,
         );
 
-        self.framebuffers = Self::create_framebuffers(
+        self.gbuffer = Self::create_gbuffer(
             self.memory_allocator.clone(),
+            swapchain_image_count,
             dimensions,
+        );
+
+        self.decal_images = Self::create_decal_images(
+            self.memory_allocator.clone(),
+            swapchain_image_count,
+            dimensions,
+        );
+
+        self.framebuffers = Self::create_framebuffers(
             self.output_images.clone(),
+            &self.gbuffer,
+            &self.decal_images,
             self.render_pass.clone(),
         );
 
-        self.shadow_cube_map = shadow_cube_map;
+        self.shadow_cube_maps = shadow_cube_maps;
     }
 
     fn create_framebuffers(
-        memory_allocator: Arc<StandardMemoryAllocator>,
-        dimensions: [u32; 2],
         images: Vec<Arc<ImageView<AttachmentImage>>>,
+        gbuffer: &GBuffer,
+        decal_images: &[Arc<ImageView<AttachmentImage>>],
         render_pass: Arc<RenderPass>,
     ) -> Vec<Arc<Framebuffer>> {
-        let depth_buffer = ImageView::new_default(
-            AttachmentImage::transient(&memory_allocator, dimensions, Format::D32_SFLOAT).unwrap(),
-        )
-        .unwrap();
-
         images
             .into_iter()
-            .map(|image| {
+            .zip(gbuffer.normals.iter().zip(gbuffer.depth.iter()))
+            .zip(decal_images.iter())
+            .map(|((image, (normal, depth)), decal)| {
                 Framebuffer::new(
                     render_pass.clone(),
                     FramebufferCreateInfo {
-                        attachments: vec![image.clone(), depth_buffer.clone()],
+                        attachments: vec![
+                            image.clone(),
+                            normal.clone(),
+                            decal.clone(),
+                            depth.clone(),
+                        ],
                         ..FramebufferCreateInfo::default()
                     },
                 )
@@ -225,6 +382,54 @@ impl SceneRenderer {
             .collect()
     }
 
+    fn create_gbuffer(
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        swapchain_image_count: u32,
+        dimensions: [u32; 2],
+    ) -> GBuffer {
+        let create_image = |format| {
+            ImageView::new_default(
+                AttachmentImage::with_usage(
+                    &memory_allocator,
+                    dimensions,
+                    format,
+                    ImageUsage::SAMPLED | ImageUsage::INPUT_ATTACHMENT,
+                )
+                .unwrap(),
+            )
+            .unwrap()
+        };
+
+        GBuffer {
+            depth: (0..swapchain_image_count)
+                .map(|_| create_image(Format::D32_SFLOAT))
+                .collect(),
+            normals: (0..swapchain_image_count)
+                .map(|_| create_image(Format::R16G16B16A16_SFLOAT))
+                .collect(),
+        }
+    }
+
+    fn create_decal_images(
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        swapchain_image_count: u32,
+        dimensions: [u32; 2],
+    ) -> Vec<Arc<ImageView<AttachmentImage>>> {
+        (0..swapchain_image_count)
+            .map(|_| {
+                ImageView::new_default(
+                    AttachmentImage::transient_input_attachment(
+                        &memory_allocator,
+                        dimensions,
+                        Format::R8G8B8A8_UNORM,
+                    )
+                    .unwrap(),
+                )
+                .unwrap()
+            })
+            .collect()
+    }
+
     fn create_images(
         memory_allocator: Arc<StandardMemoryAllocator>,
         swapchain_image_count: u32,
@@ -253,11 +458,23 @@ impl SceneRenderer {
         context: &Context,
         camera: &Camera,
         rewind_time: f32,
-        models: Vec<(&Transform, &GpuModel)>,
-        lights: Vec<(&Transform, &Light)>,
+        rewind_start_time: f32,
+        models: Vec<(
+            &Transform,
+            &GpuModel,
+            Option<&TextureScroll>,
+            Option<&GpuVideoTexture>,
+        )>,
+        lights: Vec<(&Transform, &Light, Option<usize>)>,
+        ambient_light: &AmbientLight,
+        fog: &Fog,
+        sky: &Sky,
+        decals: Vec<(&Transform, &GpuDecal)>,
+        shadow_pcf_samples: u32,
         future: F,
-        nearest_shadow_light: Option<&Transform>,
         view_frustum_culling_mode: &ViewFrustumCullingMode,
+        occlusion_culling: Option<&DepthPyramidReadback>,
+        depth_prepass_mode: &DepthPrepassMode,
         swapchain_frame_index: u32,
         frame_counter: u64,
         viewport: &Viewport,
@@ -282,7 +499,12 @@ impl SceneRenderer {
                     //
                     // Only attachments that have `LoadOp::Clear` are provided with clear
                     // values, any others should use `ClearValue::None` as the clear value.
-                    clear_values: vec![Some([0.0, 0.0, 0.0, 1.0].into()), Some(1f32.into())],
+                    clear_values: vec![
+                        Some([sky.color.x, sky.color.y, sky.color.z, 1.0].into()),
+                        Some([0.0, 0.0, 0.0, 0.0].into()),
+                        Some([0.0, 0.0, 0.0, 0.0].into()),
+                        Some(1f32.into()),
+                    ],
                     ..RenderPassBeginInfo::framebuffer(
                         self.framebuffers[swapchain_frame_index as usize].clone(),
                     )
@@ -294,8 +516,7 @@ impl SceneRenderer {
             //
             // The last two parameters contain the list of resources to pass to the shaders.
             // Since we used an `EmptyPipeline` object, the objects have to be `()`.
-            .set_viewport(0, [viewport.clone()])
-            .bind_pipeline_graphics(self.pipeline.clone());
+            .set_viewport(0, [viewport.clone()]);
 
         // TODO: models with different pipelines
         let scene_set_layout = self.pipeline.layout().set_layouts().get(0).unwrap();
@@ -303,15 +524,15 @@ impl SceneRenderer {
         let material_set_layout = self.pipeline.layout().set_layouts().get(2).unwrap();
         let entity_set_layout = self.pipeline.layout().set_layouts().get(3).unwrap();
 
-        let has_shadow_light = nearest_shadow_light.is_some();
-
         let uniform_subbuffer_scene = {
-            let src_point_lights: Vec<Padded<vs::PointLight, 12>> = lights
+            let src_point_lights: Vec<Padded<vs::PointLight, 8>> = lights
                 .iter()
-                .map(|(transform, light)| match light {
-                    Light::Point(point_light) => {
-                        Padded::from(make_shader_point_light(point_light, transform))
-                    }
+                .map(|(transform, light, shadow_map_index)| match light {
+                    Light::Point(point_light) => Padded::from(make_shader_point_light(
+                        point_light,
+                        transform,
+                        *shadow_map_index,
+                    )),
                 })
                 .collect();
 
@@ -319,16 +540,18 @@ impl SceneRenderer {
             let mut point_lights = [Padded::from(default_shader_point_light()); 32];
             point_lights[..src_point_lights.len()].copy_from_slice(src_point_lights.as_slice());
 
-            let nearest_shadow_light_position = nearest_shadow_light
-                .map(|light| light.position)
-                .unwrap_or(Point3::origin()); // nearest shadow light position is the origin if there is none
+            let ambient_light: [f32; 3] = (ambient_light.color * ambient_light.intensity).into();
 
             let uniform_data = vs::Scene {
                 pointLights: point_lights,
                 numLights: num_lights.into(),
-                hasShadowLight: has_shadow_light as i32,
-                nearestShadowLight: nearest_shadow_light_position.into(),
                 rewindTime: rewind_time.into(),
+                ambientLight: ambient_light,
+                shadowPcfSamples: shadow_pcf_samples as i32,
+                fogColor: fog.color.into(),
+                fogStart: fog.start,
+                fogEnd: fog.end,
+                rewindStartTime: rewind_start_time,
             };
 
             let subbuffer = self.buffer_allocator.allocate_sized().unwrap();
@@ -342,10 +565,20 @@ impl SceneRenderer {
             scene_set_layout.clone(),
             [
                 WriteDescriptorSet::buffer(0, uniform_subbuffer_scene),
-                WriteDescriptorSet::image_view_sampler(
+                // Unused slots of the array still need a valid image
+                WriteDescriptorSet::image_view_sampler_array(
                     1,
-                    self.shadow_cube_map[swapchain_frame_index as usize].clone(),
-                    self.shadow_map_sampler.clone(),
+                    0,
+                    self.shadow_cube_maps[swapchain_frame_index as usize]
+                        .iter()
+                        .cycle()
+                        .take(MAX_SHADOW_LIGHTS)
+                        .map(|image| {
+                            (
+                                image.clone() as Arc<dyn ImageViewAbstract>,
+                                self.shadow_map_sampler.clone(),
+                            )
+                        }),
                 ),
             ],
         )
@@ -371,50 +604,29 @@ impl SceneRenderer {
         )
         .unwrap();
 
-        builder
-            .bind_descriptor_sets(
-                PipelineBindPoint::Graphics,
-                self.pipeline.layout().clone(),
-                0,
-                scene_descriptor_set.clone(),
-            )
-            .bind_descriptor_sets(
-                PipelineBindPoint::Graphics,
-                self.pipeline.layout().clone(),
-                1,
-                camera_descriptor_set.clone(),
-            );
-
-        let frustum_bounding_sphere = {
-            // Thank you to https://stackoverflow.com/a/27872276/3492994 for the explanation
-            let (camera_forward, camera_right, camera_up) = camera.camera_basis_vectors();
-            let far_center = camera.position.coords + camera_forward * camera.far();
-            let near_center = camera.position.coords + camera_forward * camera.near();
-
-            let far_height = camera.far() * (camera.fov().0 / 2.0).tan();
-            let far_width = far_height * camera.aspect_ratio();
-
-            let far_corner = far_center + camera_right * far_width + camera_up * far_height;
-            // Approximating it as a cube
-            let near_corner = near_center + camera_right * far_width + camera_up * far_height;
-            // Finding the radius of the sphere that contains the frustum
-            let radius = (far_corner - near_corner).norm() / 2.0;
-            // Finding the center of the sphere that contains the frustum
-            let center = (far_center + near_center) / 2.0;
-            (center, radius)
-        };
+        let frustum_bounding_sphere = frustum_bounding_sphere(camera);
 
         let mut cull_counter = 0;
+        let mut occlusion_cull_counter = 0;
+
+        // Both subpasses draw the same primitives, so we only cull and create the descriptor sets
+        // once
+        let mut draws = vec![];
 
-        for (transform, model) in models {
+        for (transform, model, texture_scroll, video_texture) in models {
             // descriptor set
             let uniform_subbuffer_entity = {
                 let model_matrix = transform.to_matrix();
                 let normal_model_matrix = model_matrix.try_inverse().unwrap().transpose();
 
+                let uv_offset = texture_scroll
+                    .map(|texture_scroll| texture_scroll.offset)
+                    .unwrap_or_else(Vector2::zeros);
+
                 let uniform_data = vs::Entity {
                     model: model_matrix.into(),
                     normalMatrix: normal_model_matrix.into(),
+                    uvOffset: uv_offset.into(),
                 };
 
                 let subbuffer = self.buffer_allocator.allocate_sized().unwrap();
@@ -435,13 +647,6 @@ impl SceneRenderer {
             )
             .unwrap();
 
-            builder.bind_descriptor_sets(
-                PipelineBindPoint::Graphics,
-                self.pipeline.layout().clone(),
-                3,
-                entity_descriptor_set.clone(),
-            );
-
             for primitive in &model.primitives {
                 if view_frustum_culling_mode.enabled
                     && !primitive.intersects_frustum(&frustum_bounding_sphere, &transform)
@@ -450,6 +655,15 @@ impl SceneRenderer {
                     continue;
                 }
 
+                if let Some(occlusion_culling) = occlusion_culling {
+                    let (center, radius) = primitive.world_bounding_sphere(transform);
+                    if !occlusion_culling.is_sphere_visible(&center, radius) {
+                        cull_counter += 1;
+                        occlusion_cull_counter += 1;
+                        continue;
+                    }
+                }
+
                 // descriptor set
                 let uniform_subbuffer_material = {
                     let uniform_data: vs::Material = primitive.material.as_ref().into();
@@ -460,9 +674,28 @@ impl SceneRenderer {
                     subbuffer
                 };
 
-                let texture = primitive
+                // A video replaces the base color texture of every primitive
+                let (texture_view, texture_sampler) = match video_texture {
+                    Some(video_texture) => (
+                        video_texture.image_view.clone() as Arc<dyn ImageViewAbstract>,
+                        video_texture.sampler.clone(),
+                    ),
+                    None => {
+                        let texture = primitive
+                            .material
+                            .base_color_texture
+                            .clone()
+                            .unwrap_or(self.missing_texture.clone());
+                        (
+                            texture.image_view.clone() as Arc<dyn ImageViewAbstract>,
+                            texture.sampler.clone(),
+                        )
+                    }
+                };
+
+                let lightmap_texture = primitive
                     .material
-                    .base_color_texture
+                    .lightmap_texture
                     .clone()
                     .unwrap_or(self.missing_texture.clone());
 
@@ -471,31 +704,107 @@ impl SceneRenderer {
                     material_set_layout.clone(),
                     [
                         WriteDescriptorSet::buffer(0, uniform_subbuffer_material),
+                        WriteDescriptorSet::image_view_sampler(1, texture_view, texture_sampler),
                         WriteDescriptorSet::image_view_sampler(
-                            1,
-                            texture.image_view.clone(),
-                            texture.sampler.clone(),
+                            2,
+                            lightmap_texture.image_view.clone(),
+                            lightmap_texture.sampler.clone(),
                         ),
                     ],
                 )
                 .unwrap();
 
+                draws.push((
+                    entity_descriptor_set.clone(),
+                    material_descriptor_set,
+                    primitive,
+                ));
+            }
+        }
+
+        if frame_counter % 100 == 0 {
+            println!(
+                "Culled {} models, {} of them by occlusion culling",
+                cull_counter, occlusion_cull_counter
+            );
+        }
+
+        // Only the shading reads the decals
+        let draw_all =
+            |builder: &mut AutoCommandBufferBuilder<_, _>,
+             pipeline: &Arc<GraphicsPipeline>,
+             decal_descriptor_set: Option<&Arc<PersistentDescriptorSet>>| {
                 builder
+                    .bind_pipeline_graphics(pipeline.clone())
                     .bind_descriptor_sets(
                         PipelineBindPoint::Graphics,
-                        self.pipeline.layout().clone(),
-                        2,
-                        material_descriptor_set.clone(),
-                    )
-                    .bind_index_buffer(primitive.mesh.index_buffer.clone())
-                    .bind_vertex_buffers(0, primitive.mesh.vertex_buffer.clone())
-                    .draw_indexed(primitive.mesh.index_buffer.len() as u32, 1, 0, 0, 0)
-                    .unwrap();
-            }
+                        pipeline.layout().clone(),
+                        0,
+                        vec![scene_descriptor_set.clone(), camera_descriptor_set.clone()],
+                    );
+                if let Some(decal_descriptor_set) = decal_descriptor_set {
+                    builder.bind_descriptor_sets(
+                        PipelineBindPoint::Graphics,
+                        pipeline.layout().clone(),
+                        4,
+                        decal_descriptor_set.clone(),
+                    );
+                }
+
+                for (entity_descriptor_set, material_descriptor_set, primitive) in draws.iter() {
+                    builder
+                        .bind_descriptor_sets(
+                            PipelineBindPoint::Graphics,
+                            pipeline.layout().clone(),
+                            2,
+                            vec![
+                                material_descriptor_set.clone(),
+                                entity_descriptor_set.clone(),
+                            ],
+                        )
+                        .bind_index_buffer(primitive.mesh.index_buffer.clone())
+                        .bind_vertex_buffers(0, primitive.mesh.vertex_buffer.clone())
+                        .draw_indexed(primitive.mesh.index_buffer.len() as u32, 1, 0, 0, 0)
+                        .unwrap();
+                }
+            };
+
+        // Subpass 0: depth and normals
+        if depth_prepass_mode.enabled {
+            draw_all(&mut builder, &self.prepass_pipeline, None);
         }
 
-        if frame_counter % 100 == 0 {
-            println!("Culled {} models", cull_counter);
+        // Subpass 1: decals, they need the depth of the prepass
+        builder.next_subpass(SubpassContents::Inline).unwrap();
+        if depth_prepass_mode.enabled && !decals.is_empty() {
+            self.draw_decals(
+                &mut builder,
+                camera,
+                &decals,
+                swapchain_frame_index,
+                viewport,
+            );
+        }
+
+        // Subpass 2: shading
+        builder.next_subpass(SubpassContents::Inline).unwrap();
+        let decal_descriptor_set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            self.pipeline.layout().set_layouts().get(4).unwrap().clone(),
+            [WriteDescriptorSet::image_view(
+                0,
+                self.decal_images[swapchain_frame_index as usize].clone(),
+            )],
+        )
+        .unwrap();
+        if depth_prepass_mode.enabled {
+            draw_all(
+                &mut builder,
+                &self.pipeline_after_prepass,
+                Some(&decal_descriptor_set),
+            );
+        } else {
+            draw_all(&mut builder, &self.pipeline, Some(&decal_descriptor_set));
         }
 
         builder.end_render_pass().unwrap();
@@ -508,17 +817,145 @@ impl SceneRenderer {
             .unwrap()
     }
 
-    pub fn output_images(&self) -> &Vec<Arc<ImageView<AttachmentImage>>> {
-        &self.output_images
+    fn draw_decals<L>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L>,
+        camera: &Camera,
+        decals: &[(&Transform, &GpuDecal)],
+        swapchain_frame_index: u32,
+        viewport: &Viewport,
+    ) {
+        let layout = self.decal_pipeline.layout();
+
+        let uniform_subbuffer_scene = {
+            let view_proj = camera.proj() * camera.view();
+            let uniform_data = decal_vs::Scene {
+                viewProj: view_proj.into(),
+                inverseViewProj: view_proj.try_inverse().unwrap().into(),
+                screenSize: viewport.dimensions,
+            };
+
+            let subbuffer = self.buffer_allocator.allocate_sized().unwrap();
+            *subbuffer.write().unwrap() = uniform_data;
+
+            subbuffer
+        };
+
+        let scene_descriptor_set = PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            layout.set_layouts().get(0).unwrap().clone(),
+            [
+                WriteDescriptorSet::buffer(0, uniform_subbuffer_scene),
+                WriteDescriptorSet::image_view(
+                    1,
+                    self.gbuffer.depth[swapchain_frame_index as usize].clone(),
+                ),
+            ],
+        )
+        .unwrap();
+
+        builder
+            .bind_pipeline_graphics(self.decal_pipeline.clone())
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                layout.clone(),
+                0,
+                scene_descriptor_set,
+            )
+            .bind_index_buffer(self.decal_cube.index_buffer.clone())
+            .bind_vertex_buffers(0, self.decal_cube.vertex_buffer.clone());
+
+        for (transform, decal) in decals {
+            let model = transform.to_matrix() * Matrix4::new_nonuniform_scaling(&decal.extent);
+            let push_constants = decal_vs::Decal {
+                model: model.into(),
+                inverseModel: model.try_inverse().unwrap_or_else(Matrix4::zeros).into(),
+            };
+
+            let texture_descriptor_set = PersistentDescriptorSet::new(
+                &self.descriptor_set_allocator,
+                layout.set_layouts().get(1).unwrap().clone(),
+                [WriteDescriptorSet::image_view_sampler(
+                    0,
+                    decal.texture.image_view.clone(),
+                    decal.texture.sampler.clone(),
+                )],
+            )
+            .unwrap();
+
+            builder
+                .bind_descriptor_sets(
+                    PipelineBindPoint::Graphics,
+                    layout.clone(),
+                    1,
+                    texture_descriptor_set,
+                )
+                .push_constants(layout.clone(), 0, push_constants)
+                .draw_indexed(self.decal_cube.index_buffer.len() as u32, 1, 0, 0, 0)
+                .unwrap();
+        }
+    }
+
+    pub fn set_shadow_cube_maps(
+        &mut self,
+        shadow_cube_maps: Vec<Vec<Arc<ImageView<CustomStorageImage>>>>,
+    ) {
+        self.shadow_cube_maps = shadow_cube_maps;
+    }
+
+    /// Only contains useful data when the depth prepass is enabled
+    pub fn gbuffer(&self) -> &GBuffer {
+        &self.gbuffer
+    }
+
+    /// The depth buffer is always written, even without the depth prepass
+    pub fn depth_images(&self) -> Vec<Arc<dyn ImageViewAbstract>> {
+        self.gbuffer
+            .depth
+            .iter()
+            .map(|image| image.clone() as Arc<dyn ImageViewAbstract>)
+            .collect()
+    }
+
+    pub fn output_images(&self) -> Vec<Arc<dyn ImageViewAbstract>> {
+        self.output_images
+            .iter()
+            .map(|image| image.clone() as Arc<dyn ImageViewAbstract>)
+            .collect()
     }
 }
 
-fn make_shader_point_light(point_light: &PointLight, transform: &Transform) -> vs::PointLight {
+/// A sphere that contains the whole view frustum of the camera
+pub(crate) fn frustum_bounding_sphere(camera: &Camera) -> (Vector3<f32>, f32) {
+    // Thank you to https://stackoverflow.com/a/27872276/3492994 for the explanation
+    let (camera_forward, camera_right, camera_up) = camera.camera_basis_vectors();
+    let far_center = camera.position.coords + camera_forward * camera.far();
+    let near_center = camera.position.coords + camera_forward * camera.near();
+
+    let far_height = camera.far() * (camera.fov().0 / 2.0).tan();
+    let far_width = far_height * camera.aspect_ratio();
+
+    let far_corner = far_center + camera_right * far_width + camera_up * far_height;
+    // Approximating it as a cube
+    let near_corner = near_center + camera_right * far_width + camera_up * far_height;
+    // Finding the radius of the sphere that contains the frustum
+    let radius = (far_corner - near_corner).norm() / 2.0;
+    // Finding the center of the sphere that contains the frustum
+    let center = (far_center + near_center) / 2.0;
+    (center, radius)
+}
+
+fn make_shader_point_light(
+    point_light: &PointLight,
+    transform: &Transform,
+    shadow_map_index: Option<usize>,
+) -> vs::PointLight {
     vs::PointLight {
         position: Padded::from(<[f32; 3]>::from(transform.position)), // TODO: fix weird syntax
         color: point_light.color.into(),
         range: point_light.range,
         intensity: point_light.intensity,
+        shadowMapIndex: shadow_map_index.map(|index| index as i32).unwrap_or(-1),
     }
 }
 
@@ -528,6 +965,7 @@ fn default_shader_point_light() -> vs::PointLight {
         color: [0.0, 0.0, 0.0],
         range: 0.0,
         intensity: 0.0,
+        shadowMapIndex: -1,
     }
 }
 
@@ -538,6 +976,21 @@ impl From<&Material> for vs::Material {
             roughness: value.roughness_factor,
             metallic: Padded::from(value.metallic_factor),
             emissivity: value.emissivity.into(),
+            hasLightmap: value.lightmap_texture.is_some() as i32,
+            reflectivity: value.reflectivity,
+            hasWind: value.wind.is_some() as i32,
+            windAmplitude: value.wind.map(|wind| wind.amplitude).unwrap_or(0.0),
+            windFrequency: value.wind.map(|wind| wind.frequency).unwrap_or(0.0),
+            flipbookFrames: value
+                .flipbook
+                .map(|flipbook| flipbook.frame_count as i32)
+                .unwrap_or(0),
+            flipbookColumns: value
+                .flipbook
+                .map(|flipbook| flipbook.columns as i32)
+                .unwrap_or(0),
+            flipbookFps: value.flipbook.map(|flipbook| flipbook.fps).unwrap_or(0.0),
+            opacity: value.opacity,
         }
     }
 }
@@ -555,3 +1008,24 @@ mod fs {
         path: "../assets/shaders/scene/frag.glsl",
     }
 }
+
+mod decal_vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "../assets/shaders/decal/decal.vert",
+    }
+}
+
+mod decal_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "../assets/shaders/decal/decal.frag",
+    }
+}
+
+mod prepass_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "../assets/shaders/scene/prepass.frag",
+    }
+}
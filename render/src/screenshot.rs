@@ -0,0 +1,133 @@
+use crate::context::Context;
+use image::RgbaImage;
+use std::path::PathBuf;
+use std::sync::Arc;
+use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, CopyImageToBufferInfo};
+use vulkano::format::Format;
+use vulkano::image::view::ImageView;
+use vulkano::image::{ImageAccess, ImageViewAbstract, SwapchainImage};
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryUsage, StandardMemoryAllocator};
+use vulkano::sync::GpuFuture;
+
+/// A copy of a swapchain image that can be saved once the GPU is done with it
+pub(crate) struct Screenshot {
+    path: PathBuf,
+    buffer: Subbuffer<[u8]>,
+    dimensions: [u32; 2],
+    format: Format,
+}
+
+impl Screenshot {
+    pub fn capture<F>(
+        context: &Context,
+        future: F,
+        image: Arc<ImageView<SwapchainImage>>,
+        path: PathBuf,
+        memory_allocator: &StandardMemoryAllocator,
+        command_buffer_allocator: &StandardCommandBufferAllocator,
+    ) -> (Box<dyn GpuFuture>, Option<Self>)
+    where
+        F: GpuFuture + 'static,
+    {
+        let dimensions = image.image().dimensions().width_height();
+        let format = image.format().unwrap();
+        // 10 and 16 bit swapchains would need a conversion
+        if !matches!(
+            format,
+            Format::R8G8B8A8_SRGB
+                | Format::R8G8B8A8_UNORM
+                | Format::B8G8R8A8_SRGB
+                | Format::B8G8R8A8_UNORM
+        ) {
+            println!(
+                "Skipped the screenshot, only 8 bit RGBA or BGRA swapchains are supported, not {:?}",
+                format
+            );
+            return (future.boxed(), None);
+        }
+
+        let buffer = Buffer::new_slice(
+            memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Download,
+                ..Default::default()
+            },
+            (dimensions[0] * dimensions[1] * 4) as u64,
+        )
+        .unwrap();
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            command_buffer_allocator,
+            context.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        builder
+            .copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(
+                image.image().clone(),
+                buffer.clone(),
+            ))
+            .unwrap();
+
+        let command_buffer = builder.build().unwrap();
+
+        let future = future
+            .then_execute(context.queue(), command_buffer)
+            .unwrap()
+            .boxed();
+
+        let screenshot = Self {
+            path,
+            buffer,
+            dimensions,
+            format,
+        };
+        (future, Some(screenshot))
+    }
+
+    /// Has to be called after the GPU finished copying the image.
+    /// Encoding the image takes a while, so it is done on another thread.
+    pub fn save(self) {
+        let (image, path) = self.into_image();
+        std::thread::spawn(move || save_image(image, path));
+    }
+
+    /// Blocks until the image has been written, so that a video capture cannot fall behind
+    pub fn save_blocking(self) {
+        let (image, path) = self.into_image();
+        save_image(image, path);
+    }
+
+    fn into_image(self) -> (RgbaImage, PathBuf) {
+        let mut pixels = self.buffer.read().unwrap().to_vec();
+        if matches!(self.format, Format::B8G8R8A8_SRGB | Format::B8G8R8A8_UNORM) {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        // The window is opaque, so the alpha channel does not matter
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel[3] = u8::MAX;
+        }
+
+        let image = RgbaImage::from_raw(self.dimensions[0], self.dimensions[1], pixels).unwrap();
+        (image, self.path)
+    }
+}
+
+fn save_image(image: RgbaImage, path: PathBuf) {
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory).unwrap();
+    }
+
+    if let Err(e) = image.save(&path) {
+        println!("Failed to save {}: {e:?}", path.display());
+    }
+}
@@ -3,6 +3,7 @@ use crate::custom_storage_image::CustomStorageImage;
 use crate::scene::mesh::MeshVertex;
 use crate::scene::model::GpuModel;
 use angle::Deg;
+use bevy_ecs::prelude::Resource;
 use nalgebra::{Matrix4, Translation3};
 use scene::camera::{calculate_projection, Camera};
 use scene::transform::Transform;
@@ -34,14 +35,71 @@ use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint};
 use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass};
 use vulkano::sync::GpuFuture;
 
-const CUBE_SIZE: u32 = 4096;
+/// Has to match the size of the shadow map array in the scene shader
+pub const MAX_SHADOW_LIGHTS: usize = 4;
+
+/// Has to match the size of the Poisson disk in the scene shader
+pub const MAX_PCF_SAMPLES: u32 = 16;
+
+pub const SHADOW_RESOLUTIONS: [u32; 4] = [512, 1024, 2048, 4096];
+
+/// How far the shadows of a light reach, in meters
+pub const SHADOW_FAR: f32 = 50.0;
+
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct ShadowSettings {
+    /// size of every cube map face, one of [`SHADOW_RESOLUTIONS`]
+    pub resolution: u32,
+    /// number of Poisson disk taps for percentage-closer filtering, 1 disables the filtering
+    pub pcf_samples: u32,
+}
+
+impl ShadowSettings {
+    /// The values come from the config, so unsupported ones get corrected instead of crashing the game
+    pub fn new(resolution: u32, pcf_samples: u32) -> Self {
+        let supported_resolution = *SHADOW_RESOLUTIONS
+            .iter()
+            .min_by_key(|supported| supported.abs_diff(resolution))
+            .unwrap();
+        if supported_resolution != resolution {
+            println!(
+                "Shadow resolution has to be one of {SHADOW_RESOLUTIONS:?}, using {supported_resolution} instead of {resolution}"
+            );
+        }
+
+        let supported_pcf_samples = pcf_samples.clamp(1, MAX_PCF_SAMPLES);
+        if supported_pcf_samples != pcf_samples {
+            println!(
+                "Shadow PCF samples have to be between 1 and {MAX_PCF_SAMPLES}, using {supported_pcf_samples} instead of {pcf_samples}"
+            );
+        }
+
+        Self {
+            resolution: supported_resolution,
+            pcf_samples: supported_pcf_samples,
+        }
+    }
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            resolution: 2048,
+            pcf_samples: 8,
+        }
+    }
+}
 
 pub struct ShadowRenderer {
-    _render_pass: Arc<RenderPass>,
+    render_pass: Arc<RenderPass>,
     pipeline: Arc<GraphicsPipeline>,
-    framebuffers: Vec<[Arc<Framebuffer>; 6]>,
-    shadow_maps_views: Vec<Arc<ImageView<CustomStorageImage>>>,
+    /// Indexed by swapchain image, then by shadow light
+    framebuffers: Vec<Vec<[Arc<Framebuffer>; 6]>>,
+    shadow_maps_views: Vec<Vec<Arc<ImageView<CustomStorageImage>>>>,
+    max_shadow_lights: usize,
+    resolution: u32,
 
+    memory_allocator: Arc<StandardMemoryAllocator>,
     command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
     descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
 
@@ -55,10 +113,17 @@ impl ShadowRenderer {
     pub fn new(
         context: &Context,
         image_count: u32,
+        max_shadow_lights: usize,
+        resolution: u32,
         memory_allocator: Arc<StandardMemoryAllocator>,
         command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
         descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
     ) -> Self {
+        assert!(
+            (1..=MAX_SHADOW_LIGHTS).contains(&max_shadow_lights),
+            "max_shadow_lights has to be between 1 and {MAX_SHADOW_LIGHTS}"
+        );
+
         let render_pass = vulkano::single_pass_renderpass!(
             context.device(),
             attachments: {
@@ -106,13 +171,13 @@ impl ShadowRenderer {
                 .unwrap()
         };
 
-        let (shadow_maps, shadow_maps_views): (
-            Vec<Arc<CustomStorageImage>>,
-            Vec<Arc<ImageView<CustomStorageImage>>>,
-        ) = Self::create_images(memory_allocator.clone(), image_count);
-
-        let framebuffers: Vec<[Arc<Framebuffer>; 6]> =
-            Self::create_framebuffers(shadow_maps.clone(), render_pass.clone());
+        let (framebuffers, shadow_maps_views) = Self::create_images_and_framebuffers(
+            memory_allocator.clone(),
+            render_pass.clone(),
+            image_count,
+            max_shadow_lights,
+            resolution,
+        );
 
         let face_view_matrices = [
             // POSITIVE_X
@@ -159,16 +224,18 @@ impl ShadowRenderer {
             ]),
         ];
 
-        let far: f32 = 50.0;
         let near: f32 = 0.5;
 
-        let perspective_matrix = calculate_projection(1.0, Deg(90.0).into(), near, far);
+        let perspective_matrix = calculate_projection(1.0, Deg(90.0).into(), near, SHADOW_FAR);
 
         ShadowRenderer {
-            _render_pass: render_pass,
+            render_pass,
             pipeline,
             framebuffers,
             shadow_maps_views,
+            max_shadow_lights,
+            resolution,
+            memory_allocator,
             command_buffer_allocator,
             descriptor_set_allocator,
             buffer_allocator,
@@ -192,12 +259,31 @@ impl ShadowRenderer {
         // self.perspective_matrix = calculate_projection(aspect_ratio, Deg(45.0).into(), 0.01, 100.0);
     }
 
+    /// Recreates the shadow maps, they have to be passed to the scene renderer afterwards
+    pub fn set_resolution(&mut self, resolution: u32) {
+        let (framebuffers, shadow_maps_views) = Self::create_images_and_framebuffers(
+            self.memory_allocator.clone(),
+            self.render_pass.clone(),
+            self.framebuffers.len() as u32,
+            self.max_shadow_lights,
+            resolution,
+        );
+
+        self.framebuffers = framebuffers;
+        self.shadow_maps_views = shadow_maps_views;
+        self.resolution = resolution;
+    }
+
+    pub fn resolution(&self) -> u32 {
+        self.resolution
+    }
+
     pub fn render<F>(
         &self,
         context: &Context,
         rewind_time: f32,
         models: &Vec<(&Transform, &GpuModel)>,
-        nearest_shadow_light: &Transform,
+        shadow_lights: &[&Transform],
         camera: &Camera,
         future: F,
         swapchain_frame_index: u32,
@@ -214,100 +300,112 @@ impl ShadowRenderer {
 
         let viewport = Viewport {
             origin: [0.0, 0.0],
-            dimensions: [CUBE_SIZE as f32; 2],
+            dimensions: [self.resolution as f32; 2],
             depth_range: 0.0..1.0,
         };
 
         let scene_set_layout = self.pipeline.layout().set_layouts().get(0).unwrap();
         let entity_set_layout = self.pipeline.layout().set_layouts().get(1).unwrap();
 
-        for face_index in 0..6 {
-            builder
-                .begin_render_pass(
-                    RenderPassBeginInfo {
-                        clear_values: vec![Some(ClearValue::Depth(1f32))],
-                        ..RenderPassBeginInfo::framebuffer(
-                            self.framebuffers[swapchain_frame_index as usize][face_index].clone(),
-                        )
-                    },
-                    SubpassContents::Inline,
-                )
-                .unwrap()
-                .set_viewport(0, [viewport.clone()])
-                .bind_pipeline_graphics(self.pipeline.clone());
-
-            let view_matrix = self.face_view_matrices[face_index];
+        // The entities are the same for every shadow light and every face
+        let entity_descriptor_sets: Vec<_> = models
+            .iter()
+            .map(|(transform, _)| {
+                let uniform_subbuffer_entity = {
+                    let model_matrix = transform.to_matrix();
+                    let normal_model_matrix = model_matrix.try_inverse().unwrap().transpose();
+
+                    let uniform_data = vs::Entity {
+                        model: model_matrix.into(),
+                        normalMatrix: normal_model_matrix.into(),
+                    };
 
-            let light_position: Matrix4<f32> =
-                Translation3::from(-nearest_shadow_light.position).to_homogeneous();
-            let proj_view_matrix = self.perspective_matrix * view_matrix * light_position;
+                    let subbuffer = self.buffer_allocator.allocate_sized().unwrap();
+                    *subbuffer.write().unwrap() = uniform_data;
 
-            let uniform_subbuffer_scene = {
-                let uniform_data = vs::Scene {
-                    projView: proj_view_matrix.into(),
-                    lightPos: Padded::from(<[f32; 3]>::from(nearest_shadow_light.position)),
-                    cameraPosition: camera.position.into(),
-                    rewindTime: rewind_time.into(),
+                    subbuffer
                 };
 
-                let subbuffer = self.buffer_allocator.allocate_sized().unwrap();
-                *subbuffer.write().unwrap() = uniform_data;
-
-                subbuffer
-            };
-            let scene_descriptor_set = PersistentDescriptorSet::new(
-                &self.descriptor_set_allocator,
-                scene_set_layout.clone(),
-                [WriteDescriptorSet::buffer(0, uniform_subbuffer_scene)],
-            )
-            .unwrap();
-            builder.bind_descriptor_sets(
-                PipelineBindPoint::Graphics,
-                self.pipeline.layout().clone(),
-                0,
-                scene_descriptor_set.clone(),
-            );
+                PersistentDescriptorSet::new(
+                    &self.descriptor_set_allocator,
+                    entity_set_layout.clone(),
+                    [WriteDescriptorSet::buffer(0, uniform_subbuffer_entity)],
+                )
+                .unwrap()
+            })
+            .collect();
 
-            for (transform, model) in models.iter() {
-                for primitive in &model.primitives {
-                    // descriptor set
-                    let uniform_subbuffer_entity = {
-                        let model_matrix = transform.to_matrix();
-                        let normal_model_matrix = model_matrix.try_inverse().unwrap().transpose();
+        for (light_index, shadow_light) in shadow_lights.iter().enumerate() {
+            for face_index in 0..6 {
+                builder
+                    .begin_render_pass(
+                        RenderPassBeginInfo {
+                            clear_values: vec![Some(ClearValue::Depth(1f32))],
+                            ..RenderPassBeginInfo::framebuffer(
+                                self.framebuffers[swapchain_frame_index as usize][light_index]
+                                    [face_index]
+                                    .clone(),
+                            )
+                        },
+                        SubpassContents::Inline,
+                    )
+                    .unwrap()
+                    .set_viewport(0, [viewport.clone()])
+                    .bind_pipeline_graphics(self.pipeline.clone());
 
-                        let uniform_data = vs::Entity {
-                            model: model_matrix.into(),
-                            normalMatrix: normal_model_matrix.into(),
-                        };
+                let view_matrix = self.face_view_matrices[face_index];
 
-                        let subbuffer = self.buffer_allocator.allocate_sized().unwrap();
-                        *subbuffer.write().unwrap() = uniform_data;
+                let light_position: Matrix4<f32> =
+                    Translation3::from(-shadow_light.position).to_homogeneous();
+                let proj_view_matrix = self.perspective_matrix * view_matrix * light_position;
 
-                        subbuffer
+                let uniform_subbuffer_scene = {
+                    let uniform_data = vs::Scene {
+                        projView: proj_view_matrix.into(),
+                        lightPos: Padded::from(<[f32; 3]>::from(shadow_light.position)),
+                        cameraPosition: camera.position.into(),
+                        rewindTime: rewind_time.into(),
                     };
 
-                    let entity_descriptor_set = PersistentDescriptorSet::new(
-                        &self.descriptor_set_allocator,
-                        entity_set_layout.clone(),
-                        [WriteDescriptorSet::buffer(0, uniform_subbuffer_entity)],
-                    )
-                    .unwrap();
-
-                    builder
-                        .bind_descriptor_sets(
-                            PipelineBindPoint::Graphics,
-                            self.pipeline.layout().clone(),
-                            1,
-                            entity_descriptor_set.clone(),
-                        )
-                        .bind_index_buffer(primitive.mesh.index_buffer.clone())
-                        .bind_vertex_buffers(0, primitive.mesh.vertex_buffer.clone())
-                        .draw_indexed(primitive.mesh.index_buffer.len() as u32, 1, 0, 0, 0)
-                        .unwrap();
+                    let subbuffer = self.buffer_allocator.allocate_sized().unwrap();
+                    *subbuffer.write().unwrap() = uniform_data;
+
+                    subbuffer
+                };
+                let scene_descriptor_set = PersistentDescriptorSet::new(
+                    &self.descriptor_set_allocator,
+                    scene_set_layout.clone(),
+                    [WriteDescriptorSet::buffer(0, uniform_subbuffer_scene)],
+                )
+                .unwrap();
+                builder.bind_descriptor_sets(
+                    PipelineBindPoint::Graphics,
+                    self.pipeline.layout().clone(),
+                    0,
+                    scene_descriptor_set.clone(),
+                );
+
+                for ((_, model), entity_descriptor_set) in
+                    models.iter().zip(entity_descriptor_sets.iter())
+                {
+                    builder.bind_descriptor_sets(
+                        PipelineBindPoint::Graphics,
+                        self.pipeline.layout().clone(),
+                        1,
+                        entity_descriptor_set.clone(),
+                    );
+
+                    for primitive in &model.primitives {
+                        builder
+                            .bind_index_buffer(primitive.mesh.index_buffer.clone())
+                            .bind_vertex_buffers(0, primitive.mesh.vertex_buffer.clone())
+                            .draw_indexed(primitive.mesh.index_buffer.len() as u32, 1, 0, 0, 0)
+                            .unwrap();
+                    }
                 }
-            }
 
-            builder.end_render_pass().unwrap();
+                builder.end_render_pass().unwrap();
+            }
         }
 
         let command_buffer = builder.build().unwrap();
@@ -317,20 +415,41 @@ impl ShadowRenderer {
             .unwrap()
     }
 
-    fn create_images(
+    /// Creates one cube map per shadow light for every swapchain image
+    fn create_images_and_framebuffers(
         memory_allocator: Arc<StandardMemoryAllocator>,
+        render_pass: Arc<RenderPass>,
         num_images: u32,
+        num_lights: usize,
+        resolution: u32,
+    ) -> (
+        Vec<Vec<[Arc<Framebuffer>; 6]>>,
+        Vec<Vec<Arc<ImageView<CustomStorageImage>>>>,
+    ) {
+        (0..num_images)
+            .map(|_| {
+                let (images, views) =
+                    Self::create_cube_maps(memory_allocator.clone(), num_lights, resolution);
+                (Self::create_framebuffers(images, render_pass.clone()), views)
+            })
+            .unzip()
+    }
+
+    fn create_cube_maps(
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        num_lights: usize,
+        resolution: u32,
     ) -> (
         Vec<Arc<CustomStorageImage>>,
         Vec<Arc<ImageView<CustomStorageImage>>>,
     ) {
-        let images: Vec<Arc<CustomStorageImage>> = (0..num_images)
+        let images: Vec<Arc<CustomStorageImage>> = (0..num_lights)
             .map(|_| {
                 CustomStorageImage::uninitialized(
                     &memory_allocator,
                     Dim2d {
-                        width: CUBE_SIZE,
-                        height: CUBE_SIZE,
+                        width: resolution,
+                        height: resolution,
                         array_layers: 6,
                     },
                     Format::D32_SFLOAT,
@@ -403,9 +522,14 @@ impl ShadowRenderer {
             .collect()
     }
 
-    pub fn get_shadow_cube_maps(&self) -> Vec<Arc<ImageView<CustomStorageImage>>> {
+    /// The cube maps of every swapchain image, one per shadow light
+    pub fn get_shadow_cube_maps(&self) -> Vec<Vec<Arc<ImageView<CustomStorageImage>>>> {
         self.shadow_maps_views.clone()
     }
+
+    pub fn max_shadow_lights(&self) -> usize {
+        self.max_shadow_lights
+    }
 }
 
 mod vs {
@@ -0,0 +1,100 @@
+use crate::context::Context;
+use crate::fullscreen_pass::FullscreenPass;
+use crate::post_process::{PostProcessEffect, PostProcessFrame};
+use bevy_ecs::prelude::Resource;
+use std::sync::Arc;
+use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::image::ImageViewAbstract;
+use vulkano::memory::allocator::StandardMemoryAllocator;
+use vulkano::sync::GpuFuture;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tonemapper {
+    Aces,
+    Filmic,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct TonemappingSettings {
+    pub exposure: f32,
+    pub tonemapper: Tonemapper,
+}
+
+impl Default for TonemappingSettings {
+    fn default() -> Self {
+        Self {
+            exposure: 2.0,
+            tonemapper: Tonemapper::Aces,
+        }
+    }
+}
+
+/// Maps the HDR scene to displayable colors, the effects after it work with colors between 0 and 1
+pub(crate) struct TonemapRenderer {
+    fullscreen_pass: FullscreenPass,
+    brightness: f32,
+}
+
+impl TonemapRenderer {
+    pub fn new(
+        context: &Context,
+        input_images: Vec<Arc<dyn ImageViewAbstract>>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+        brightness: f32,
+    ) -> Self {
+        let fullscreen_pass = FullscreenPass::new(
+            context,
+            fs::load(context.device()).unwrap(),
+            input_images,
+            memory_allocator,
+            command_buffer_allocator,
+            descriptor_set_allocator,
+        );
+
+        Self {
+            fullscreen_pass,
+            brightness,
+        }
+    }
+}
+
+impl PostProcessEffect for TonemapRenderer {
+    fn resize(&mut self, input_images: Vec<Arc<dyn ImageViewAbstract>>) {
+        self.fullscreen_pass.resize(input_images);
+    }
+
+    fn render(
+        &mut self,
+        context: &Context,
+        future: Box<dyn GpuFuture>,
+        frame: &PostProcessFrame,
+        image_index: u32,
+    ) -> Box<dyn GpuFuture> {
+        let pass = fs::Pass {
+            flash: frame.flash_color.push(frame.flash).into(),
+            exposure: frame.tonemapping.exposure,
+            brightness: self.brightness,
+            tonemapper: match frame.tonemapping.tonemapper {
+                Tonemapper::Aces => 0,
+                Tonemapper::Filmic => 1,
+            },
+        };
+
+        self.fullscreen_pass
+            .render(context, future, image_index, pass)
+    }
+
+    fn output_images(&self) -> Vec<Arc<dyn ImageViewAbstract>> {
+        self.fullscreen_pass.output_images()
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "../assets/shaders/tonemap/tonemap.frag",
+    }
+}
@@ -1,11 +1,15 @@
 use crate::context::Context;
-use crate::quad::{self, unit_quad_mesh, QuadVertex};
+use crate::quad::unit_quad_mesh;
+use crate::scene::texture::Texture;
 use crate::scene::ui_component::GpuUIComponent;
-use nalgebra::{Matrix4, Point3, Vector2, Vector3};
-use scene::ui_component::UIComponent;
+use nalgebra::{Matrix4, Vector2, Vector4};
+use scene::asset::AssetId;
+use scene::ui_component::{UIComponent, UIProgressBar, UIScaling};
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use vulkano::buffer::Subbuffer;
+use vulkano::buffer::allocator::{SubbufferAllocator, SubbufferAllocatorCreateInfo};
+use vulkano::buffer::{BufferContents, BufferUsage};
 use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
 use vulkano::command_buffer::{
     AutoCommandBufferBuilder, CommandBufferExecFuture, CommandBufferUsage, RenderPassBeginInfo,
@@ -25,13 +29,51 @@ use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint};
 use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass};
 use vulkano::sync::GpuFuture;
 
+/// Already transformed into clip space, so that all quads with the same texture
+/// can be drawn at once
+#[repr(C)]
+#[derive(BufferContents, Vertex, Clone, Copy)]
+struct UIVertex {
+    #[format(R32G32B32A32_SFLOAT)]
+    position: [f32; 4],
+
+    #[format(R32G32_SFLOAT)]
+    uv: [f32; 2],
+
+    /// Where the vertex is on the component, from (0, 0) at the top left to (1, 1) at the bottom right
+    #[format(R32G32_SFLOAT)]
+    fill_coordinate: [f32; 2],
+
+    #[format(R32_SFLOAT)]
+    fill: f32,
+
+    #[format(R32_SFLOAT)]
+    highlight: f32,
+
+    /// 1 for radial progress bars
+    #[format(R32_SFLOAT)]
+    radial: f32,
+
+    #[format(R32_SFLOAT)]
+    opacity: f32,
+}
+
+/// The UI components that share a texture
+struct UIBatch {
+    texture: Arc<Texture>,
+    vertices: Vec<UIVertex>,
+}
+
+/// Draws the UI components in one draw call per texture.
+/// The depth test keeps the components in the right order, regardless of the draw order.
 pub struct UIRenderer {
     render_pass: Arc<RenderPass>,
     pipeline: Arc<GraphicsPipeline>,
     framebuffers: Vec<Arc<Framebuffer>>,
 
-    index_buffer: Subbuffer<[u32]>,
-    vertex_buffer: Subbuffer<[QuadVertex]>,
+    buffer_allocator: SubbufferAllocator,
+    /// Only the descriptor sets of the textures in the last frame are kept
+    descriptor_sets: HashMap<AssetId, (Arc<Texture>, Arc<PersistentDescriptorSet>)>,
 
     command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
     memory_allocator: Arc<StandardMemoryAllocator>,
@@ -47,9 +89,6 @@ impl UIRenderer {
         command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
         descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
     ) -> Self {
-        let (vertex_buffer, index_buffer) =
-            quad::create_geometry_buffers(unit_quad_mesh(), memory_allocator.clone());
-
         let render_pass = vulkano::single_pass_renderpass!(
             context.device(),
             attachments: {
@@ -78,7 +117,7 @@ impl UIRenderer {
             let fs = fs::load(context.device()).unwrap();
 
             GraphicsPipeline::start()
-                .vertex_input_state(QuadVertex::per_vertex())
+                .vertex_input_state(UIVertex::per_vertex())
                 .vertex_shader(vs.entry_point("main").unwrap(), ())
                 .input_assembly_state(InputAssemblyState::new())
                 .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
@@ -92,14 +131,22 @@ impl UIRenderer {
         let framebuffers =
             Self::create_framebuffers(memory_allocator.clone(), images, render_pass.clone());
 
+        let buffer_allocator = SubbufferAllocator::new(
+            memory_allocator.clone(),
+            SubbufferAllocatorCreateInfo {
+                buffer_usage: BufferUsage::VERTEX_BUFFER,
+                ..Default::default()
+            },
+        );
+
         UIRenderer {
             render_pass,
             pipeline,
 
             framebuffers,
 
-            index_buffer,
-            vertex_buffer,
+            buffer_allocator,
+            descriptor_sets: HashMap::new(),
 
             command_buffer_allocator,
             memory_allocator,
@@ -116,9 +163,10 @@ impl UIRenderer {
     }
 
     pub fn render<F>(
-        &self,
+        &mut self,
         context: &Context,
-        ui_components: Vec<(&GpuUIComponent, &UIComponent)>,
+        ui_components: Vec<(&GpuUIComponent, &UIComponent, Option<&UIProgressBar>)>,
+        ui_scaling: &UIScaling,
         future: F,
         swapchain_frame_index: u32,
         viewport: &Viewport,
@@ -147,71 +195,144 @@ impl UIRenderer {
             .set_viewport(0, [viewport.clone()])
             .bind_pipeline_graphics(self.pipeline.clone());
 
-        let set_layout = self.pipeline.layout().set_layouts().get(0).unwrap();
-
         let screen_size = Vector2::from(viewport.dimensions);
-
-        for (gpu_component, cpu_component) in ui_components {
+        let scale_factor = ui_scaling.scale_factor(screen_size);
+
+        // TODO: Fix flipped z
+        let projection = Matrix4::from_row_slice(&[
+            2.0 / screen_size.x,
+            0.0,
+            0.0,
+            -1.0, //
+            0.0,
+            2.0 / screen_size.y,
+            0.0,
+            -1.0, //
+            0.0,
+            0.0,
+            -1.0,
+            0.0, //
+            0.0,
+            0.0,
+            0.0,
+            1.0, //
+        ]);
+
+        let (quad_vertices, quad_indices) = unit_quad_mesh();
+        let mut batches: Vec<UIBatch> = vec![];
+        for (gpu_component, cpu_component, progress_bar) in ui_components {
             if !cpu_component.visible {
                 continue;
             }
-            let position = cpu_component.get_position(screen_size);
-            let origin = cpu_component.get_origin();
-            let size = cpu_component.get_size();
-
-            // TODO: Fix flipped z
-            let projection = Matrix4::from_row_slice(&[
-                2.0 / screen_size.x,
-                0.0,
-                0.0,
-                -1.0, //
-                0.0,
-                2.0 / screen_size.y,
-                0.0,
-                -1.0, //
-                0.0,
-                0.0,
-                -1.0,
-                0.0, //
-                0.0,
-                0.0,
-                0.0,
-                1.0, //
-            ]);
-
-            let mvp = projection
-                * Matrix4::new_translation(&position.coords)
-                * Matrix4::new_rotation_wrt_point(
-                    Vector3::new(0.0, 0.0, -cpu_component.texture_position.angle.0),
-                    Point3::new(origin.x, origin.y, 0.0),
-                )
-                * Matrix4::new_nonuniform_scaling(&Vector3::new(size.x, size.y, 1.0));
 
-            let component_push_constant = vs::UIComponent { MVP: mvp.into() };
+            let mvp = projection * cpu_component.get_model_matrix(screen_size, scale_factor);
 
-            let descriptor_set = PersistentDescriptorSet::new(
-                &self.descriptor_set_allocator,
-                set_layout.clone(),
-                [WriteDescriptorSet::image_view_sampler(
-                    0,
-                    gpu_component.texture.image_view.clone(),
-                    gpu_component.texture.sampler.clone(),
-                )],
-            )
-            .unwrap();
+            let (fill, highlight, radial) = progress_bar
+                .map(|progress_bar| {
+                    (
+                        progress_bar.fill.clamp(0.0, 1.0),
+                        progress_bar.highlight,
+                        if progress_bar.radial { 1.0 } else { 0.0 },
+                    )
+                })
+                .unwrap_or((1.0, 0.0, 0.0));
+            let opacity = cpu_component.opacity.clamp(0.0, 1.0);
+
+            // Nine-slice components consist of several quads
+            let vertices = cpu_component
+                .get_quads(scale_factor)
+                .into_iter()
+                .flat_map(|ui_quad| {
+                    quad_indices.iter().map(move |index| {
+                        let corner = Vector2::from(quad_vertices[*index as usize].position);
+                        let position = ui_quad.position_min
+                            + (ui_quad.position_max - ui_quad.position_min).component_mul(&corner);
+                        let uv = ui_quad.uv_min
+                            + (ui_quad.uv_max - ui_quad.uv_min).component_mul(&corner);
+                        UIVertex {
+                            position: (mvp * Vector4::new(position.x, position.y, 0.0, 1.0)).into(),
+                            uv: uv.into(),
+                            fill_coordinate: position.into(),
+                            fill,
+                            highlight,
+                            radial,
+                            opacity,
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            match batches
+                .iter_mut()
+                .find(|batch| Arc::ptr_eq(&batch.texture, &gpu_component.texture))
+            {
+                Some(batch) => batch.vertices.extend(vertices),
+                None => batches.push(UIBatch {
+                    texture: gpu_component.texture.clone(),
+                    vertices,
+                }),
+            }
+        }
 
+        let vertex_count: usize = batches.iter().map(|batch| batch.vertices.len()).sum();
+        if vertex_count > 0 {
+            let vertex_buffer = self
+                .buffer_allocator
+                .allocate_slice(vertex_count as u64)
+                .unwrap();
+            {
+                let mut buffer_vertices = vertex_buffer.write().unwrap();
+                let batch_vertices = batches.iter().flat_map(|batch| batch.vertices.iter());
+                for (buffer_vertex, vertex) in buffer_vertices.iter_mut().zip(batch_vertices) {
+                    *buffer_vertex = *vertex;
+                }
+            }
+            builder.bind_vertex_buffers(0, vertex_buffer);
+        }
+
+        self.descriptor_sets.retain(|_, (texture, _)| {
+            batches
+                .iter()
+                .any(|batch| Arc::ptr_eq(&batch.texture, texture))
+        });
+
+        let set_layout = self.pipeline.layout().set_layouts().get(0).unwrap();
+        let mut first_vertex = 0;
+        for batch in batches.iter() {
+            let descriptor_set = match self.descriptor_sets.get(&batch.texture.id) {
+                Some((texture, descriptor_set)) if Arc::ptr_eq(texture, &batch.texture) => {
+                    descriptor_set.clone()
+                }
+                _ => {
+                    let descriptor_set = PersistentDescriptorSet::new(
+                        &self.descriptor_set_allocator,
+                        set_layout.clone(),
+                        [WriteDescriptorSet::image_view_sampler(
+                            0,
+                            batch.texture.image_view.clone(),
+                            batch.texture.sampler.clone(),
+                        )],
+                    )
+                    .unwrap();
+                    self.descriptor_sets.insert(
+                        batch.texture.id,
+                        (batch.texture.clone(), descriptor_set.clone()),
+                    );
+                    descriptor_set
+                }
+            };
+
+            let batch_vertex_count = batch.vertices.len() as u32;
             builder
-                .push_constants(self.pipeline.layout().clone(), 0, component_push_constant)
                 .bind_descriptor_sets(
                     PipelineBindPoint::Graphics,
                     self.pipeline.layout().clone(),
                     0,
-                    descriptor_set.clone(),
+                    descriptor_set,
                 )
-                .bind_index_buffer(self.index_buffer.clone())
-                .bind_vertex_buffers(0, self.vertex_buffer.clone())
-                .draw_indexed(6, 1, 0, 0, 0)
-                .unwrap(); // TODO: remove magic number 6
+                .draw(batch_vertex_count, 1, first_vertex, 0)
+                .unwrap();
+            first_vertex += batch_vertex_count;
         }
 
         builder.end_render_pass().unwrap();
@@ -14,6 +14,22 @@ pub struct Assets<T: Asset> {
     pub assets: HashMap<Uuid, Arc<T>>,
 }
 
+impl<T: Asset> Assets<T> {
+    /// Removes the assets that are only kept alive by this collection, and returns them
+    pub fn remove_unused(&mut self) -> Vec<Arc<T>> {
+        let unused_ids: Vec<_> = self
+            .assets
+            .iter()
+            .filter(|(_, asset)| Arc::strong_count(asset) == 1)
+            .map(|(id, _)| *id)
+            .collect();
+        unused_ids
+            .iter()
+            .filter_map(|id| self.assets.remove(id))
+            .collect()
+    }
+}
+
 impl<T: Asset> Default for Assets<T> {
     fn default() -> Self {
         Self {
@@ -0,0 +1,55 @@
+use std::sync::Arc;
+
+use bevy_ecs::component::Component;
+use nalgebra::{Vector2, Vector3};
+
+use crate::texture::CpuTexture;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BillboardBlend {
+    /// Pixels with less than half of the opacity get cut out, for props like distant trees
+    Cutout,
+    /// Adds its color onto the scene, for glows and sparkles
+    Additive,
+}
+
+/// A textured quad that turns towards the camera, centered on the transform.
+/// The rotation and the scale of the transform are ignored.
+#[derive(Component, Clone)]
+pub struct Billboard {
+    pub texture: Arc<CpuTexture>,
+    /// Width and height in meters
+    pub size: Vector2<f32>,
+    /// Multiplies the texture, values above 1 make it glow with the bloom
+    pub color: Vector3<f32>,
+    /// Only turns around this axis, like a tree that stays upright, in world space
+    pub locked_axis: Option<Vector3<f32>>,
+    pub blend: BillboardBlend,
+}
+
+impl Billboard {
+    pub fn new(texture: Arc<CpuTexture>, size: Vector2<f32>) -> Self {
+        Self {
+            texture,
+            size,
+            color: Vector3::new(1.0, 1.0, 1.0),
+            locked_axis: None,
+            blend: BillboardBlend::Cutout,
+        }
+    }
+
+    pub fn with_color(mut self, color: Vector3<f32>) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_locked_axis(mut self, axis: Vector3<f32>) -> Self {
+        self.locked_axis = Some(axis);
+        self
+    }
+
+    pub fn with_blend(mut self, blend: BillboardBlend) -> Self {
+        self.blend = blend;
+        self
+    }
+}
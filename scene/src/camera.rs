@@ -1,6 +1,6 @@
 use angle::{Angle, Deg, Rad};
 use bevy_ecs::prelude::*;
-use nalgebra::{vector, Matrix, Matrix4, Point3, UnitQuaternion, UnitVector3, Vector3};
+use nalgebra::{vector, Isometry3, Matrix4, Point3, UnitQuaternion, UnitVector3, Vector3};
 
 // TODO: look up how to get the euler yaw and pitch angles from a quaternion
 #[derive(Resource)]
@@ -45,6 +45,17 @@ impl Camera {
         self.proj[(0, 0)] = -self.proj[(1, 1)].clone() / aspect_ratio;
     }
 
+    pub fn set_fov(&mut self, fov: Deg<f32>) {
+        self.fov = Rad::from(fov);
+        self.proj = calculate_projection(self.aspect_ratio, self.fov, self.near, self.far);
+    }
+
+    pub fn set_clip_planes(&mut self, near: f32, far: f32) {
+        self.near = near;
+        self.far = far;
+        self.proj = calculate_projection(self.aspect_ratio, self.fov, self.near, self.far);
+    }
+
     pub fn view(&self) -> &Matrix4<f32> {
         &self.view
     }
@@ -120,9 +131,9 @@ pub fn calculate_projection(aspect_ratio: f32, fov: Rad<f32>, near: f32, far: f3
     projection
 }
 
+/// The inverse of the camera transform, which also keeps the roll, like on a wall with its own gravity
 fn calculate_view(position: Point3<f32>, orientation: UnitQuaternion<f32>) -> Matrix4<f32> {
-    let cam_direction = orientation * Camera::forward();
-    let target = position + cam_direction.into_inner();
-
-    Matrix::look_at_rh(&position, &target, &Camera::up())
+    Isometry3::from_parts(position.coords.into(), orientation)
+        .inverse()
+        .to_homogeneous()
 }
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use bevy_ecs::system::Resource;
+
+use crate::asset::AssetId;
+
+/// A 3D color lookup table, which maps every color onto a graded color
+#[derive(Debug)]
+pub struct ColorLut {
+    pub id: AssetId,
+    /// The table has size × size × size entries
+    pub size: u32,
+    /// RGBA, red changes the fastest, then green, then blue
+    pub data: Vec<[u8; 4]>,
+}
+
+impl ColorLut {
+    /// Keeps every color as it is
+    pub fn identity(size: u32) -> Self {
+        let max = (size - 1) as f32;
+        let to_byte = |value: u32| ((value as f32 / max) * 255.0).round() as u8;
+
+        let mut data = Vec::with_capacity((size * size * size) as usize);
+        for blue in 0..size {
+            for green in 0..size {
+                for red in 0..size {
+                    data.push([to_byte(red), to_byte(green), to_byte(blue), 255]);
+                }
+            }
+        }
+
+        Self {
+            id: AssetId::new_v4(),
+            size,
+            data,
+        }
+    }
+}
+
+/// Color grading at the very end of the post-processing.
+/// The LUT of the level can be blended towards a second LUT, for example a cold look while rewinding.
+#[derive(Resource, Debug, Default)]
+pub struct ColorGrading {
+    /// None keeps the colors as they are
+    pub lut: Option<Arc<ColorLut>>,
+    pub blend_lut: Option<Arc<ColorLut>>,
+    /// Between 0 and 1, how much of the blend LUT gets used
+    pub blend: f32,
+}
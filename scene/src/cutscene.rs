@@ -0,0 +1,18 @@
+use bevy_ecs::prelude::Component;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CutscenePointKind {
+    /// The camera moves through the point
+    Camera,
+    /// The camera looks at the point
+    LookAt,
+}
+
+/// A point of the camera path of a cutscene, placed as an empty in the level
+#[derive(Component, Debug, Clone)]
+pub struct CutscenePoint {
+    pub cutscene: String,
+    pub kind: CutscenePointKind,
+    /// When the point is reached, in seconds since the start of the cutscene
+    pub time: f32,
+}
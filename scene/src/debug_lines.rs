@@ -0,0 +1,121 @@
+use bevy_ecs::prelude::{ResMut, Resource};
+use math::bounding_box::BoundingBox;
+use nalgebra::{Matrix4, Point3, Vector3};
+use std::f32::consts::PI;
+
+const CIRCLE_SEGMENTS: usize = 24;
+
+#[derive(Clone, Debug)]
+pub struct DebugLine {
+    pub start: Point3<f32>,
+    pub end: Point3<f32>,
+    pub color: Vector3<f32>,
+}
+
+/// Wireframe lines that get drawn on top of the scene.
+/// Systems add their lines every update, and they get cleared at the start of the next update.
+#[derive(Resource, Default)]
+pub struct DebugLines {
+    pub enabled: bool,
+    lines: Vec<DebugLine>,
+}
+
+impl DebugLines {
+    pub fn line(&mut self, start: Point3<f32>, end: Point3<f32>, color: Vector3<f32>) {
+        if !self.enabled {
+            return;
+        }
+        self.lines.push(DebugLine { start, end, color });
+    }
+
+    /// Draws the box in object space, the matrix transforms it to world space
+    pub fn bounding_box(
+        &mut self,
+        bounding_box: &BoundingBox<Vector3<f32>>,
+        matrix: &Matrix4<f32>,
+        color: Vector3<f32>,
+    ) {
+        let (min, max) = (&bounding_box.min, &bounding_box.max);
+        let corner = |index: usize| {
+            let position = Vector3::new(
+                if index & 1 == 0 { min.x } else { max.x },
+                if index & 2 == 0 { min.y } else { max.y },
+                if index & 4 == 0 { min.z } else { max.z },
+            );
+            matrix.transform_point(&Point3::from(position))
+        };
+
+        // Every corner gets connected to the corners that differ in exactly one axis
+        for index in 0..8 {
+            for axis in [1, 2, 4] {
+                if index & axis == 0 {
+                    self.line(corner(index), corner(index | axis), color);
+                }
+            }
+        }
+    }
+
+    /// Draws three circles around the center, one for each axis
+    pub fn sphere(&mut self, center: Point3<f32>, radius: f32, color: Vector3<f32>) {
+        self.circle(center, Vector3::x(), Vector3::y(), radius, color);
+        self.circle(center, Vector3::y(), Vector3::z(), radius, color);
+        self.circle(center, Vector3::z(), Vector3::x(), radius, color);
+    }
+
+    /// Draws a capsule that goes from `start` to `end`
+    pub fn capsule(
+        &mut self,
+        start: Point3<f32>,
+        end: Point3<f32>,
+        radius: f32,
+        color: Vector3<f32>,
+    ) {
+        let axis = (end - start)
+            .try_normalize(f32::EPSILON)
+            .unwrap_or(Vector3::y());
+        let side = if axis.x.abs() < 0.9 {
+            Vector3::x()
+        } else {
+            Vector3::z()
+        };
+        let tangent = axis.cross(&side).normalize();
+        let bitangent = axis.cross(&tangent);
+
+        self.sphere(start, radius, color);
+        self.sphere(end, radius, color);
+        for direction in [tangent, -tangent, bitangent, -bitangent] {
+            self.line(start + direction * radius, end + direction * radius, color);
+        }
+    }
+
+    /// Draws a circle on the plane that is spanned by the two normalized axes
+    pub fn circle(
+        &mut self,
+        center: Point3<f32>,
+        axis_a: Vector3<f32>,
+        axis_b: Vector3<f32>,
+        radius: f32,
+        color: Vector3<f32>,
+    ) {
+        let point = |segment: usize| {
+            let angle = segment as f32 / CIRCLE_SEGMENTS as f32 * 2.0 * PI;
+            center + (axis_a * angle.cos() + axis_b * angle.sin()) * radius
+        };
+
+        for segment in 0..CIRCLE_SEGMENTS {
+            self.line(point(segment), point(segment + 1), color);
+        }
+    }
+
+    pub fn lines(&self) -> &[DebugLine] {
+        &self.lines
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+}
+
+pub fn clear_debug_lines(mut debug_lines: ResMut<DebugLines>) {
+    debug_lines.clear();
+}
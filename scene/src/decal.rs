@@ -0,0 +1,16 @@
+use std::sync::Arc;
+
+use bevy_ecs::component::Component;
+use nalgebra::Vector3;
+
+use crate::texture::CpuTexture;
+
+/// A texture that gets projected onto everything inside of a box, like warning stripes or graffiti.
+/// The box is centered on the transform. The texture gets projected along the local Y axis,
+/// which means that a decal without a rotation lies flat on the floor.
+#[derive(Component, Clone)]
+pub struct Decal {
+    pub texture: Arc<CpuTexture>,
+    /// Size of the box in meters, the texture covers the X and Z axis
+    pub extent: Vector3<f32>,
+}
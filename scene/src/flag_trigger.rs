@@ -1,4 +1,4 @@
-use bevy_ecs::prelude::Component;
+use bevy_ecs::prelude::{Component, Entity};
 use levels::level_id::LevelId;
 
 use crate::level::FlagId;
@@ -8,4 +8,36 @@ pub struct FlagTrigger {
     pub level_id: LevelId,
     pub flag_id: FlagId,
     pub current_intersections: u32,
+    /// When set, the flag only gets set once the bodies inside weigh at least this much.
+    /// Otherwise any body inside sets the flag.
+    pub min_mass: Option<f32>,
+    /// The bodies that are currently inside
+    pub intersecting: Vec<Entity>,
+    /// The total mass of the bodies inside, for triggers that react to the weight
+    pub current_mass: f32,
+}
+
+impl FlagTrigger {
+    pub fn new(level_id: LevelId, flag_id: FlagId) -> Self {
+        Self {
+            level_id,
+            flag_id,
+            current_intersections: 0,
+            min_mass: None,
+            intersecting: vec![],
+            current_mass: 0.0,
+        }
+    }
+
+    pub fn with_min_mass(mut self, min_mass: Option<f32>) -> Self {
+        self.min_mass = min_mass;
+        self
+    }
+
+    pub fn is_active(&self) -> bool {
+        match self.min_mass {
+            Some(min_mass) => self.current_intersections > 0 && self.current_mass >= min_mass,
+            None => self.current_intersections > 0,
+        }
+    }
 }
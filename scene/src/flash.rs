@@ -0,0 +1,42 @@
+use std::time::{Duration, Instant};
+
+use bevy_ecs::system::Resource;
+use nalgebra::Vector3;
+
+/// Brightens the screen and tints it with a color, for example when an alarm goes off.
+/// Fades out on its own, in real time, like the [`ScreenGlitch`](crate::screen_glitch::ScreenGlitch).
+#[derive(Resource, Debug)]
+pub struct Flash {
+    pub color: Vector3<f32>,
+    duration: Duration,
+    started: Option<Instant>,
+}
+
+impl Default for Flash {
+    fn default() -> Self {
+        Self {
+            color: Vector3::new(1.0, 1.0, 1.0),
+            duration: Duration::ZERO,
+            started: None,
+        }
+    }
+}
+
+impl Flash {
+    /// Replaces the previous flash
+    pub fn trigger(&mut self, color: Vector3<f32>, duration: Duration) {
+        self.color = color;
+        self.duration = duration;
+        self.started = Some(Instant::now());
+    }
+
+    /// Between 0 and 1
+    pub fn strength(&self) -> f32 {
+        match self.started {
+            Some(started) if !self.duration.is_zero() => {
+                1.0 - (started.elapsed().as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+            }
+            _ => 0.0,
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use bevy_ecs::component::Component;
+use bevy_ecs::system::Resource;
+use nalgebra::Vector3;
+
+/// Distance fog of the current level. Surfaces fade into the fog color between the start and the end distance,
+/// so that large levels do not get cut off at the far plane of the camera.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct Fog {
+    pub color: Vector3<f32>,
+    /// in meters from the camera
+    pub start: f32,
+    /// in meters from the camera, everything further away has the fog color
+    pub end: f32,
+}
+
+impl Fog {
+    pub fn is_enabled(&self) -> bool {
+        self.end > self.start
+    }
+}
+
+impl Default for Fog {
+    /// No fog at all
+    fn default() -> Self {
+        Self {
+            color: Vector3::new(0.0, 0.0, 0.0),
+            start: 0.0,
+            end: 0.0,
+        }
+    }
+}
+
+/// The fog of a level, from the extras of its glTF scene
+#[derive(Component, Debug, Clone)]
+pub struct LevelFog {
+    pub fog: Fog,
+}
@@ -0,0 +1,203 @@
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::{Command, EntityCommands};
+
+use crate::transform::Transform;
+
+/// The entity that this entity is attached to. Add it with [`HierarchyCommands::set_parent`],
+/// so that the [`Children`] of the parent stay in sync.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parent(Entity);
+
+impl Parent {
+    pub fn get(&self) -> Entity {
+        self.0
+    }
+}
+
+#[derive(Component, Debug, Clone, Default)]
+pub struct Children(Vec<Entity>);
+
+impl Children {
+    pub fn iter(&self) -> impl Iterator<Item = &Entity> {
+        self.0.iter()
+    }
+}
+
+/// The transform of a child relative to its parent.
+/// The [`Transform`] stays the global transform, so most systems never have to care about the hierarchy.
+#[derive(Component, Debug, Clone)]
+pub struct LocalTransform {
+    pub transform: Transform,
+    /// The global transform of the parent when the local transform was last computed
+    parent_transform: Transform,
+}
+
+impl LocalTransform {
+    fn new(parent_transform: &Transform, transform: &Transform) -> Self {
+        Self {
+            transform: &parent_transform.inverse() * transform.clone(),
+            parent_transform: parent_transform.clone(),
+        }
+    }
+}
+
+/// Attaches the child to the parent, and keeps its current global transform
+pub struct SetParent {
+    pub child: Entity,
+    pub parent: Entity,
+}
+
+impl Command for SetParent {
+    fn write(self, world: &mut World) {
+        // Attaching the child to itself or to one of its descendants would make the hierarchy a cycle
+        let mut ancestor = Some(self.parent);
+        while let Some(entity) = ancestor {
+            if entity == self.child {
+                println!(
+                    "Cannot attach {:?} to {:?}, which is part of its own hierarchy",
+                    self.child, self.parent
+                );
+                return;
+            }
+            ancestor = world.get::<Parent>(entity).map(Parent::get);
+        }
+        detach(world, self.child);
+
+        let parent_transform = world.get::<Transform>(self.parent).cloned();
+        let transform = world.get::<Transform>(self.child).cloned();
+        let local_transform = LocalTransform::new(
+            &parent_transform.unwrap_or_default(),
+            &transform.unwrap_or_default(),
+        );
+
+        let mut parent = match world.get_entity_mut(self.parent) {
+            Some(parent) => parent,
+            None => return,
+        };
+        match parent.get_mut::<Children>() {
+            Some(mut children) => children.0.push(self.child),
+            None => {
+                parent.insert(Children(vec![self.child]));
+            }
+        }
+
+        if let Some(mut child) = world.get_entity_mut(self.child) {
+            child.insert((Parent(self.parent), local_transform));
+        }
+    }
+}
+
+/// Detaches the child from its parent, it stays where it is
+pub struct RemoveParent {
+    pub child: Entity,
+}
+
+impl Command for RemoveParent {
+    fn write(self, world: &mut World) {
+        detach(world, self.child);
+    }
+}
+
+fn detach(world: &mut World, child: Entity) {
+    let parent = match world.get::<Parent>(child) {
+        Some(parent) => parent.0,
+        None => return,
+    };
+
+    if let Some(mut children) = world.get_mut::<Children>(parent) {
+        children.0.retain(|entity| *entity != child);
+        if children.0.is_empty() {
+            world.entity_mut(parent).remove::<Children>();
+        }
+    }
+
+    world
+        .entity_mut(child)
+        .remove::<Parent>()
+        .remove::<LocalTransform>();
+}
+
+pub trait HierarchyCommands {
+    fn set_parent(&mut self, parent: Entity) -> &mut Self;
+    fn remove_parent(&mut self) -> &mut Self;
+}
+
+impl<'w, 's, 'a> HierarchyCommands for EntityCommands<'w, 's, 'a> {
+    fn set_parent(&mut self, parent: Entity) -> &mut Self {
+        let child = self.id();
+        self.commands().add(SetParent { child, parent });
+        self
+    }
+
+    fn remove_parent(&mut self) -> &mut Self {
+        let child = self.id();
+        self.commands().add(RemoveParent { child });
+        self
+    }
+}
+
+/// Despawning a parent leaves its children behind, they get detached and stay where they are
+pub fn detach_orphaned_children(
+    mut commands: Commands,
+    mut removed_children: RemovedComponents<Children>,
+    children: Query<(Entity, &Parent)>,
+) {
+    let removed_parents: Vec<Entity> = removed_children.iter().collect();
+    if removed_parents.is_empty() {
+        return;
+    }
+
+    for (child, parent) in children.iter() {
+        if removed_parents.contains(&parent.0) {
+            commands.entity(child).remove::<(Parent, LocalTransform)>();
+        }
+    }
+}
+
+/// Moves the children along with their parents.
+///
+/// A child that has been moved in world space since the last propagation, like the player by the
+/// character controller, gets a new local transform relative to where its parent was back then.
+/// Dynamic rigid bodies are moved by the physics in world space, so they should not be children.
+pub fn propagate_transforms(
+    roots: Query<(&Transform, &Children), Without<Parent>>,
+    children_query: Query<&Children, With<Parent>>,
+    mut transforms: Query<(&mut Transform, &mut LocalTransform), With<Parent>>,
+) {
+    for (transform, children) in roots.iter() {
+        for child in children.iter() {
+            propagate_child(*child, transform, &children_query, &mut transforms);
+        }
+    }
+}
+
+fn propagate_child(
+    entity: Entity,
+    parent_transform: &Transform,
+    children_query: &Query<&Children, With<Parent>>,
+    transforms: &mut Query<(&mut Transform, &mut LocalTransform), With<Parent>>,
+) {
+    let transform = match transforms.get_mut(entity) {
+        Ok((mut transform, mut local_transform)) => {
+            if transform.is_changed() {
+                *local_transform =
+                    LocalTransform::new(&local_transform.parent_transform, &transform);
+            }
+            local_transform.parent_transform = parent_transform.clone();
+
+            let global_transform = parent_transform * local_transform.transform.clone();
+            // Only counts as a change when the child actually moved
+            if *transform != global_transform {
+                *transform = global_transform;
+            }
+            transform.clone()
+        }
+        Err(_) => return,
+    };
+
+    if let Ok(children) = children_query.get(entity) {
+        for child in children.iter() {
+            propagate_child(*child, &transform, children_query, transforms);
+        }
+    }
+}
@@ -0,0 +1,5 @@
+use bevy_ecs::prelude::Component;
+
+/// Gets an outline around its model, for example while the player is looking at something that they can pick up
+#[derive(Component, Debug)]
+pub struct Highlighted;
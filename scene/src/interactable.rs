@@ -0,0 +1,13 @@
+use bevy_ecs::prelude::Component;
+
+/// Something that the player can interact with by looking at it and pressing a key,
+/// like a lever, a switch or a terminal.
+/// Interactions are sent as an `EntityEvent<Interacted>` on the same entity.
+#[derive(Component, Debug, Clone)]
+pub struct Interactable {
+    /// What the interaction does, for example "pull the lever"
+    pub prompt: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Interacted;
\ No newline at end of file
@@ -1,13 +1,34 @@
 pub mod asset;
+pub mod billboard;
 pub mod camera;
+pub mod color_grading;
+pub mod cutscene;
+pub mod debug_lines;
 pub mod debug_name;
+pub mod decal;
 pub mod flag_trigger;
+pub mod flash;
+pub mod fog;
+pub mod hierarchy;
+pub mod highlight;
+pub mod interactable;
 pub mod level;
 pub mod light;
 pub mod material;
 pub mod mesh;
 pub mod model;
 pub mod pickup;
+pub mod rewind_ghosts;
+pub mod screen_glitch;
+pub mod screen_shake;
+pub mod sky;
+pub mod spatial_grid;
+pub mod surface;
+pub mod teleporter;
 pub mod texture;
 pub mod transform;
+pub mod ui_atlas;
 pub mod ui_component;
+pub mod ui_text;
+pub mod ui_tween;
+pub mod video_texture;
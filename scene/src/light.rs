@@ -1,4 +1,5 @@
 use bevy_ecs::component::Component;
+use bevy_ecs::system::Resource;
 use nalgebra::Vector3;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -28,3 +29,26 @@ pub struct LightCastShadow;
 
 #[derive(Component)]
 pub struct CastsShadow;
+
+/// A light that follows the time of day animation of its level
+#[derive(Component, Debug, Clone)]
+pub struct TimeOfDayLight {
+    /// The intensity that the light had when it was loaded, gets scaled by the animation
+    pub base_intensity: f32,
+}
+
+/// The ambient term of the current level, lights every surface evenly
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct AmbientLight {
+    pub color: Vector3<f32>,
+    pub intensity: f32,
+}
+
+impl Default for AmbientLight {
+    fn default() -> Self {
+        Self {
+            color: Vector3::new(1.0, 1.0, 1.0),
+            intensity: 0.03,
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use std::sync::Arc;
 
 use crate::asset::{Asset, AssetId};
-use nalgebra::Vector3;
+use crate::model::Model;
+use bevy_ecs::prelude::*;
+use nalgebra::{Vector2, Vector3};
 
 use crate::texture::CpuTexture;
 
@@ -9,9 +11,41 @@ pub struct CpuMaterial {
     pub id: AssetId,
     pub base_color: Vector3<f32>,
     pub base_color_texture: Option<Arc<CpuTexture>>,
+    /// Baked indirect lighting, which uses the second UV set. Replaces the ambient light.
+    pub lightmap_texture: Option<Arc<CpuTexture>>,
     pub roughness_factor: f32,
     pub metallic_factor: f32,
     pub emissivity: Vector3<f32>,
+    /// How much of the scene gets reflected, between 0 and 1. Uses screen space reflections.
+    pub reflectivity: f32,
+    /// Makes the vertices sway, for example for grass
+    pub wind: Option<Wind>,
+    /// Plays the frames of the base color texture, for example for screens
+    pub flipbook: Option<Flipbook>,
+    /// Between 0 and 1. Translucent materials are dithered, like the ghosts, so they don't need to be sorted.
+    pub opacity: f32,
+}
+
+/// Moves the vertices back and forth with a sine wave that follows the level time,
+/// which means that it plays backwards while rewinding.
+/// Vertices further up in object space move more, so that the bottom of grass stays in place.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wind {
+    /// in meters, per meter above the origin of the object
+    pub amplitude: f32,
+    /// in radians per second
+    pub frequency: f32,
+}
+
+/// The base color texture is a grid of frames, which are read row by row, starting at the top left.
+/// The current frame follows the level time, so the animation plays backwards while rewinding.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Flipbook {
+    pub frame_count: u32,
+    /// how many frames are next to each other in a row
+    pub columns: u32,
+    /// in frames per second
+    pub fps: f32,
 }
 
 impl Default for CpuMaterial {
@@ -21,9 +55,14 @@ impl Default for CpuMaterial {
             id: AssetId::new_v4(),
             base_color: Vector3::new(1.0, 0.0, 1.0),
             base_color_texture: None,
+            lightmap_texture: None,
             roughness_factor: 1.0,
             metallic_factor: 0.0,
             emissivity: Vector3::new(0.0, 0.0, 0.0),
+            reflectivity: 0.0,
+            wind: None,
+            flipbook: None,
+            opacity: 1.0,
         }
     }
 }
@@ -33,3 +72,62 @@ impl Asset for CpuMaterial {
         self.id
     }
 }
+
+/// Swaps the materials of all primitives of a model, without losing the original ones.
+/// Gets tracked for rewinding, so it should be added when the model is loaded.
+#[derive(Component, Default)]
+pub struct MaterialOverride {
+    /// None means that the model uses its original materials
+    pub material: Option<Arc<CpuMaterial>>,
+    /// Filled in when the override gets applied for the first time
+    original_materials: Option<Vec<Arc<CpuMaterial>>>,
+}
+
+impl MaterialOverride {
+    pub fn new(material: Option<Arc<CpuMaterial>>) -> Self {
+        Self {
+            material,
+            original_materials: None,
+        }
+    }
+
+    /// Only touches the material when it is a different one, otherwise the change would be tracked every frame
+    pub fn set(&mut self, material: Option<Arc<CpuMaterial>>) {
+        let current_id = self.material.as_ref().map(|material| material.id());
+        let new_id = material.as_ref().map(|material| material.id());
+        if current_id != new_id {
+            self.material = material;
+        }
+    }
+}
+
+pub fn apply_material_overrides(
+    mut query: Query<(&mut MaterialOverride, &mut Model), Changed<MaterialOverride>>,
+) {
+    for (mut material_override, mut model) in query.iter_mut() {
+        // Bypassing the change detection, or this would run every frame
+        let material_override = material_override.bypass_change_detection();
+        let original_materials = material_override.original_materials.get_or_insert_with(|| {
+            model
+                .primitives
+                .iter()
+                .map(|primitive| primitive.material.clone())
+                .collect()
+        });
+
+        for (primitive, original_material) in
+            model.primitives.iter_mut().zip(original_materials.iter())
+        {
+            primitive.material = match &material_override.material {
+                Some(material) => material.clone(),
+                None => original_material.clone(),
+            };
+        }
+    }
+}
+
+/// Moves the texture coordinates of a model, for example for conveyor belts
+#[derive(Component, Default)]
+pub struct TextureScroll {
+    pub offset: Vector2<f32>,
+}
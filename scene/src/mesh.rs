@@ -8,6 +8,8 @@ pub struct CpuMeshVertex {
     pub position: [f32; 3],
     pub normal: [f32; 3],
     pub uv: [f32; 2],
+    /// The second UV set, which is used for the lightmaps
+    pub uv1: [f32; 2],
 }
 
 pub struct CpuMesh {
@@ -109,6 +111,7 @@ impl CpuMesh {
                         position: positions[*pos_index].into(),
                         normal: face.normal.into(),
                         uv: uvs_face[i].into(),
+                        uv1: uvs_face[i].into(),
                     })
             })
             .collect();
@@ -175,6 +178,7 @@ impl CpuMesh {
                     position: position.into(),
                     normal: normal.into(),
                     uv: uv.into(),
+                    uv1: uv.into(),
                 });
             }
         }
@@ -7,7 +7,7 @@ use nalgebra::Vector3;
 use crate::{material::CpuMaterial, mesh::CpuMesh};
 
 /// Due to how upload_models works, as soon as the model has been uploaded to the Gpu,
-/// nothing can be changed anymore. No material changes either.
+/// only the materials can be changed. Use a [`crate::material::MaterialOverride`] for that.
 #[derive(Component, Clone)]
 pub struct Model {
     pub primitives: Vec<CpuPrimitive>,
@@ -1,4 +1,26 @@
 use bevy_ecs::prelude::Component;
 
-#[derive(Component)]
-pub struct Pickupable;
+/// Something that the player can pick up and carry around.
+/// Fragile objects send an `EntityEvent<Broken>` on the same entity when they hit something too hard.
+#[derive(Component, Debug, Clone)]
+pub struct Pickupable {
+    /// How far in front of the camera the object is held, in meters
+    pub hold_distance: f32,
+    /// Objects that can't be rotated keep the rotation that they had when they were picked up
+    pub can_rotate: bool,
+    /// The object breaks when its velocity changes by more than this during one physics step, in meters per second
+    pub break_speed: Option<f32>,
+}
+
+impl Default for Pickupable {
+    fn default() -> Self {
+        Self {
+            hold_distance: 3.0,
+            can_rotate: true,
+            break_speed: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Broken;
@@ -0,0 +1,16 @@
+use bevy_ecs::prelude::{Entity, Resource};
+
+use crate::transform::Transform;
+
+pub struct RewindGhost {
+    /// The ghost looks like the model of this entity
+    pub entity: Entity,
+    pub transform: Transform,
+}
+
+/// Translucent copies of objects at where they were a few seconds ago.
+/// Shown while rewinding, so that players can judge how far they have to rewind.
+#[derive(Resource, Default)]
+pub struct RewindGhosts {
+    pub ghosts: Vec<RewindGhost>,
+}
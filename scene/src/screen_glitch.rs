@@ -0,0 +1,28 @@
+use std::time::{Duration, Instant};
+
+use bevy_ecs::system::Resource;
+
+const GLITCH_DURATION: Duration = Duration::from_millis(400);
+
+/// A short burst of the rewind distortion, for example when the player gets teleported.
+/// Fades out on its own, in real time, so it also fades while the game is paused.
+#[derive(Resource, Debug, Default)]
+pub struct ScreenGlitch {
+    started: Option<Instant>,
+}
+
+impl ScreenGlitch {
+    pub fn trigger(&mut self) {
+        self.started = Some(Instant::now());
+    }
+
+    /// Between 0 and 1
+    pub fn strength(&self) -> f32 {
+        match self.started {
+            Some(started) => {
+                1.0 - (started.elapsed().as_secs_f32() / GLITCH_DURATION.as_secs_f32()).min(1.0)
+            }
+            None => 0.0,
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use std::time::Instant;
+
+use bevy_ecs::system::Resource;
+
+/// How much trauma goes away every second
+const TRAUMA_DECAY: f32 = 1.5;
+
+/// Shakes the camera, for example when a door slams or the player lands hard.
+/// Events add trauma, which decays on its own in real time, like the [`ScreenGlitch`](crate::screen_glitch::ScreenGlitch).
+#[derive(Resource, Debug, Default)]
+pub struct ScreenShake {
+    /// The trauma at the time of the last change
+    trauma: f32,
+    changed: Option<Instant>,
+}
+
+impl ScreenShake {
+    /// The trauma gets capped at 1
+    pub fn add_trauma(&mut self, trauma: f32) {
+        self.trauma = (self.trauma() + trauma.max(0.0)).min(1.0);
+        self.changed = Some(Instant::now());
+    }
+
+    /// Between 0 and 1
+    pub fn trauma(&self) -> f32 {
+        match self.changed {
+            Some(changed) => {
+                (self.trauma - changed.elapsed().as_secs_f32() * TRAUMA_DECAY).max(0.0)
+            }
+            None => 0.0,
+        }
+    }
+
+    /// Between 0 and 1, squared so that a bit of trauma barely shakes the camera
+    pub fn strength(&self) -> f32 {
+        self.trauma().powi(2)
+    }
+}
@@ -0,0 +1,16 @@
+use bevy_ecs::system::Resource;
+use nalgebra::Vector3;
+
+/// The background of the current level, wherever no geometry gets rendered
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct Sky {
+    pub color: Vector3<f32>,
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Self {
+            color: Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+}
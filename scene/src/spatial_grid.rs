@@ -0,0 +1,180 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy_ecs::prelude::*;
+use math::bounding_box::BoundingBox;
+use nalgebra::Vector3;
+
+use crate::{model::Model, transform::Transform};
+
+/// In meters, about the size of a room
+const CELL_SIZE: f32 = 8.0;
+/// Entities that would be in more cells get checked by every query instead, like the floor of a level
+const MAX_CELLS_PER_ENTITY: i32 = 64;
+
+type Cell = Vector3<i32>;
+
+/// For entities without a model that should still be found by the spatial queries, like triggers
+#[derive(Component, Clone)]
+pub struct SpatialBounds {
+    pub bounds: BoundingBox<Vector3<f32>>,
+}
+
+/// A uniform grid of the world space bounding boxes of the models,
+/// so that the culling and the queries only have to look at the entities nearby
+#[derive(Resource)]
+pub struct SpatialGrid {
+    cells: HashMap<Cell, Vec<Entity>>,
+    large_entities: Vec<Entity>,
+    bounds: HashMap<Entity, BoundingBox<Vector3<f32>>>,
+}
+
+impl SpatialGrid {
+    pub fn new() -> Self {
+        Self {
+            cells: HashMap::new(),
+            large_entities: vec![],
+            bounds: HashMap::new(),
+        }
+    }
+
+    /// Also moves entities that are already in the grid
+    pub fn insert(&mut self, entity: Entity, bounds: BoundingBox<Vector3<f32>>) {
+        self.remove(entity);
+        // Models without any primitives have an empty box
+        if bounds.size().min() < 0.0 {
+            return;
+        }
+
+        let (min_cell, max_cell) = cell_range(&bounds);
+        if cell_count(&min_cell, &max_cell) > MAX_CELLS_PER_ENTITY {
+            self.large_entities.push(entity);
+        } else {
+            for cell in cells_in(min_cell, max_cell) {
+                self.cells.entry(cell).or_default().push(entity);
+            }
+        }
+        self.bounds.insert(entity, bounds);
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        let bounds = match self.bounds.remove(&entity) {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        let (min_cell, max_cell) = cell_range(&bounds);
+        if cell_count(&min_cell, &max_cell) > MAX_CELLS_PER_ENTITY {
+            self.large_entities.retain(|other| other != &entity);
+            return;
+        }
+        for cell in cells_in(min_cell, max_cell) {
+            if let Some(entities) = self.cells.get_mut(&cell) {
+                entities.retain(|other| other != &entity);
+                if entities.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+
+    /// The world space bounding box of the entity
+    pub fn bounds(&self, entity: Entity) -> Option<&BoundingBox<Vector3<f32>>> {
+        self.bounds.get(&entity)
+    }
+
+    /// The entities that touch the sphere
+    pub fn query_sphere(&self, center: &Vector3<f32>, radius: f32) -> Vec<Entity> {
+        let bounds = BoundingBox::new(
+            center - Vector3::repeat(radius),
+            center + Vector3::repeat(radius),
+        );
+        self.candidates(&bounds)
+            .filter(|(_, entity_bounds)| entity_bounds.distance_squared(center) <= radius * radius)
+            .map(|(entity, _)| entity)
+            .collect()
+    }
+
+    /// The entities in the cells that the box touches, every entity only once
+    fn candidates<'a>(
+        &'a self,
+        bounds: &BoundingBox<Vector3<f32>>,
+    ) -> impl Iterator<Item = (Entity, &'a BoundingBox<Vector3<f32>>)> + 'a {
+        let (min_cell, max_cell) = cell_range(bounds);
+        // Big queries, like the one of the camera, are quicker when going through the cells that have entities
+        let cells: Vec<&Vec<Entity>> = if cell_count(&min_cell, &max_cell) > self.cells.len() as i32
+        {
+            self.cells
+                .iter()
+                .filter(|(cell, _)| min_cell <= **cell && **cell <= max_cell)
+                .map(|(_, entities)| entities)
+                .collect()
+        } else {
+            cells_in(min_cell, max_cell)
+                .filter_map(|cell| self.cells.get(&cell))
+                .collect()
+        };
+
+        let mut seen = HashSet::new();
+        cells
+            .into_iter()
+            .flatten()
+            .chain(self.large_entities.iter())
+            .filter(move |entity| seen.insert(**entity))
+            .filter_map(|entity| self.bounds.get(entity).map(|bounds| (*entity, bounds)))
+    }
+}
+
+fn cell_range(bounds: &BoundingBox<Vector3<f32>>) -> (Cell, Cell) {
+    let to_cell =
+        |position: &Vector3<f32>| position.map(|value| (value / CELL_SIZE).floor() as i32);
+    (to_cell(&bounds.min), to_cell(&bounds.max))
+}
+
+/// Saturates instead of overflowing for huge boxes
+fn cell_count(min_cell: &Cell, max_cell: &Cell) -> i32 {
+    min_cell
+        .iter()
+        .zip(max_cell.iter())
+        .fold(1i32, |count, (min, max)| {
+            count.saturating_mul(max.saturating_sub(*min).saturating_add(1))
+        })
+}
+
+fn cells_in(min_cell: Cell, max_cell: Cell) -> impl Iterator<Item = Cell> {
+    (min_cell.x..=max_cell.x).flat_map(move |x| {
+        (min_cell.y..=max_cell.y)
+            .flat_map(move |y| (min_cell.z..=max_cell.z).map(move |z| Cell::new(x, y, z)))
+    })
+}
+
+/// Keeps the grid up to date with the models and bounds that have been added, moved or removed
+pub fn update_spatial_grid(
+    mut spatial_grid: ResMut<SpatialGrid>,
+    models_query: Query<(Entity, &Transform, &Model), Or<(Changed<Transform>, Changed<Model>)>>,
+    bounds_query: Query<
+        (Entity, &Transform, &SpatialBounds),
+        (
+            Without<Model>,
+            Or<(Changed<Transform>, Changed<SpatialBounds>)>,
+        ),
+    >,
+    mut removed_models: RemovedComponents<Model>,
+    mut removed_bounds: RemovedComponents<SpatialBounds>,
+) {
+    for entity in removed_models.iter().chain(removed_bounds.iter()) {
+        spatial_grid.remove(entity);
+    }
+
+    for (entity, transform, model) in models_query.iter() {
+        spatial_grid.insert(
+            entity,
+            model.bounding_box().transform(&transform.to_matrix()),
+        );
+    }
+    for (entity, transform, spatial_bounds) in bounds_query.iter() {
+        spatial_grid.insert(
+            entity,
+            spatial_bounds.bounds.transform(&transform.to_matrix()),
+        );
+    }
+}
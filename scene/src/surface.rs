@@ -0,0 +1,11 @@
+use bevy_ecs::component::Component;
+
+/// What the ground sounds like when the player walks on it
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SurfaceType {
+    Metal,
+    /// Most of the computer world, so untagged ground counts as plastic
+    #[default]
+    Plastic,
+    Glass,
+}
@@ -0,0 +1,14 @@
+use bevy_ecs::prelude::{Component, Entity};
+
+/// A trigger volume that moves the player and the dynamic rigid bodies that enter it to its exit.
+/// They keep their position, rotation and velocity relative to the volume.
+#[derive(Component, Debug, Clone)]
+pub struct Teleporter {
+    /// The name of the exit node in the same level, usually the other teleporter of a pair
+    pub exit: String,
+}
+
+/// Something that just came out of a teleporter, which must not send it back right away.
+/// Gets removed when it leaves the teleporter.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct TeleportedInto(pub Entity);
@@ -40,6 +40,20 @@ impl Transform {
         translated_position.into()
     }
 
+    /// Only exact for uniform scales, or without a rotation,
+    /// since the scale of a transform is always applied before its rotation
+    pub fn inverse(&self) -> Transform {
+        let rotation = self.rotation.inverse();
+        let scale = self.scale.map(|v| 1.0 / v);
+        Transform {
+            position: (rotation * -self.position.coords)
+                .component_mul(&scale)
+                .into(),
+            rotation,
+            scale,
+        }
+    }
+
     pub fn lerp(&self, other: &Transform, factor: f32) -> Transform {
         // eh, just lerp the components individually
         Transform {
@@ -0,0 +1,20 @@
+use crate::texture::CpuTexture;
+use crate::ui_component::UITextureRegion;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A single texture with many named sprites, so that UI components can share one texture
+pub struct UIAtlas {
+    pub texture: Arc<CpuTexture>,
+    regions: HashMap<String, UITextureRegion>,
+}
+
+impl UIAtlas {
+    pub fn new(texture: Arc<CpuTexture>, regions: HashMap<String, UITextureRegion>) -> Self {
+        Self { texture, regions }
+    }
+
+    pub fn region(&self, name: &str) -> Option<UITextureRegion> {
+        self.regions.get(name).copied()
+    }
+}
@@ -1,52 +1,298 @@
 use crate::texture::CpuTexture;
 use angle::Rad;
-use bevy_ecs::prelude::Component;
-use nalgebra::{Point2, Point3, Vector2};
+use bevy_ecs::prelude::{Component, Resource};
+use nalgebra::{Matrix4, Point2, Point3, Vector2, Vector3};
 use std::sync::Arc;
 
+/// The point on the screen that a UI component is attached to.
+/// Components stay at the same distance from their anchor, regardless of the aspect ratio.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UIAnchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl UIAnchor {
+    /// In 0-1 coordinates, with 0,0 in the top left corner of the screen
+    pub fn position(&self) -> Point2<f32> {
+        match self {
+            UIAnchor::TopLeft => Point2::new(0.0, 0.0),
+            UIAnchor::Top => Point2::new(0.5, 0.0),
+            UIAnchor::TopRight => Point2::new(1.0, 0.0),
+            UIAnchor::Left => Point2::new(0.0, 0.5),
+            UIAnchor::Center => Point2::new(0.5, 0.5),
+            UIAnchor::Right => Point2::new(1.0, 0.5),
+            UIAnchor::BottomLeft => Point2::new(0.0, 1.0),
+            UIAnchor::Bottom => Point2::new(0.5, 1.0),
+            UIAnchor::BottomRight => Point2::new(1.0, 1.0),
+        }
+    }
+}
+
+/// The UI is designed for the reference resolution, and gets uniformly scaled to fit other screens
+#[derive(Resource, Debug, Clone)]
+pub struct UIScaling {
+    pub reference_resolution: Vector2<f32>,
+}
+
+impl Default for UIScaling {
+    fn default() -> Self {
+        Self {
+            reference_resolution: Vector2::new(1280.0, 720.0),
+        }
+    }
+}
+
+impl UIScaling {
+    /// Uses the smaller side, so that an ultrawide screen doesn't make the UI huge
+    pub fn scale_factor(&self, screen_size: Vector2<f32>) -> f32 {
+        let scale = screen_size.component_div(&self.reference_resolution);
+        scale.x.min(scale.y)
+    }
+}
+
+/// Hides the whole UI at once, regardless of the visibility of the single components
+#[derive(Resource, Debug, Clone, Default)]
+pub struct UIVisibility {
+    pub hidden: bool,
+}
+
+/// A rectangle of a texture in pixels, for example a sprite from a [`UIAtlas`](crate::ui_atlas::UIAtlas)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UITextureRegion {
+    /// Left edge
+    pub x: u32,
+    /// Top edge
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub nine_slice: Option<NineSlice>,
+}
+
+/// Splits a sprite into 3x3 parts with the given borders, in pixels of the texture.
+/// The corners keep their size, the edges only get stretched along one axis and the center gets
+/// stretched along both. That way, panels and buttons can be scaled without looking stretched.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NineSlice {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+/// A part of a UI component, in the coordinates of the unit quad, +y is down
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UIQuad {
+    pub position_min: Point2<f32>,
+    pub position_max: Point2<f32>,
+    pub uv_min: Point2<f32>,
+    pub uv_max: Point2<f32>,
+}
+
 #[derive(Component)]
 pub struct UIComponent {
     /// width, height is determined by the texture dimensions
     pub texture: Arc<CpuTexture>,
+    /// Only shows this part of the texture, None shows the whole texture
+    pub region: Option<UITextureRegion>,
     pub texture_position: UITexturePosition,
-    /// The position of the UI component
-    /// In 0-1 coordinates, with 0,0 in the top left corner of the screen
-    /// z is the depth
-    pub position: Point3<f32>,
+    pub anchor: UIAnchor,
+    /// Offset from the anchor in pixels of the reference resolution, +y is down
+    pub offset: Vector2<f32>,
+    /// In the range 0-1
+    pub depth: f32,
+    /// In the range 0-1, see [`UITween`](crate::ui_tween::UITween) for fading components in and out
+    pub opacity: f32,
     pub visible: bool,
 }
 
 impl UIComponent {
     /// Size in screen pixels
-    pub fn get_size(&self) -> Vector2<f32> {
+    pub fn get_size(&self, scale_factor: f32) -> Vector2<f32> {
+        let texture_size = match &self.region {
+            Some(region) => Vector2::new(region.width as f32, region.height as f32),
+            None => self.get_texture_dimensions(),
+        };
+
+        texture_size.component_mul(&self.texture_position.scale) * scale_factor
+    }
+
+    fn get_texture_dimensions(&self) -> Vector2<f32> {
         let texture_size = self.texture.data.dimensions();
-        let texture_size = Vector2::new(texture_size[0] as f32, texture_size[1] as f32);
+        Vector2::new(texture_size[0] as f32, texture_size[1] as f32)
+    }
+
+    /// The shown part of the texture, as the top left and the bottom right UV coordinates
+    pub fn get_uv_rect(&self) -> (Point2<f32>, Point2<f32>) {
+        match &self.region {
+            Some(region) => {
+                let texture_size = self.get_texture_dimensions();
+                let min = Point2::new(region.x as f32, region.y as f32);
+                let max = min + Vector2::new(region.width as f32, region.height as f32);
+                (
+                    Point2::from(min.coords.component_div(&texture_size)),
+                    Point2::from(max.coords.component_div(&texture_size)),
+                )
+            }
+            None => (Point2::new(0.0, 0.0), Point2::new(1.0, 1.0)),
+        }
+    }
 
-        texture_size.component_mul(&self.texture_position.scale)
+    /// The quads that make up the component, nine for a nine-slice sprite and one otherwise
+    pub fn get_quads(&self, scale_factor: f32) -> Vec<UIQuad> {
+        let (uv_min, uv_max) = self.get_uv_rect();
+        let nine_slice = match self.region.and_then(|region| region.nine_slice) {
+            Some(nine_slice) => nine_slice,
+            None => {
+                return vec![UIQuad {
+                    position_min: Point2::new(0.0, 0.0),
+                    position_max: Point2::new(1.0, 1.0),
+                    uv_min,
+                    uv_max,
+                }]
+            }
+        };
+
+        // The borders keep their size in pixels, unless the component is too small for them
+        let size = self.get_size(scale_factor);
+        let border_min = Vector2::new(nine_slice.left as f32, nine_slice.top as f32);
+        let border_max = Vector2::new(nine_slice.right as f32, nine_slice.bottom as f32);
+        let border_fit = (size.component_div(&((border_min + border_max) * scale_factor)))
+            .min()
+            .min(1.0);
+        let position_border_min = (border_min * scale_factor * border_fit).component_div(&size);
+        let position_border_max = (border_max * scale_factor * border_fit).component_div(&size);
+        let positions = [
+            Vector2::new(0.0, 0.0),
+            position_border_min,
+            Vector2::new(1.0, 1.0) - position_border_max,
+            Vector2::new(1.0, 1.0),
+        ];
+
+        let texture_size = self.get_texture_dimensions();
+        let uv_border_min = border_min.component_div(&texture_size);
+        let uv_border_max = border_max.component_div(&texture_size);
+        let uvs = [
+            uv_min.coords,
+            uv_min.coords + uv_border_min,
+            uv_max.coords - uv_border_max,
+            uv_max.coords,
+        ];
+
+        let mut quads = Vec::with_capacity(9);
+        for row in 0..3 {
+            for column in 0..3 {
+                quads.push(UIQuad {
+                    position_min: Point2::new(positions[column].x, positions[row].y),
+                    position_max: Point2::new(positions[column + 1].x, positions[row + 1].y),
+                    uv_min: Point2::new(uvs[column].x, uvs[row].y),
+                    uv_max: Point2::new(uvs[column + 1].x, uvs[row + 1].y),
+                });
+            }
+        }
+        quads
     }
 
     /// Position of the origin around which to rotate in screen pixels
-    pub fn get_origin(&self) -> Vector2<f32> {
-        self.get_size()
+    pub fn get_origin(&self, scale_factor: f32) -> Vector2<f32> {
+        self.get_size(scale_factor)
             .component_mul(&self.texture_position.texture_origin.coords)
     }
 
     /// Position of the top left corner in screen pixels
     /// z is a depth value, in the range 0-1
-    pub fn get_position(&self, screen_size: Vector2<f32>) -> Point3<f32> {
-        let position_on_screen = self.position.xy().coords.component_mul(&screen_size);
+    pub fn get_position(&self, screen_size: Vector2<f32>, scale_factor: f32) -> Point3<f32> {
+        let anchor_on_screen = self.anchor.position().coords.component_mul(&screen_size);
+        let position_on_screen = anchor_on_screen + self.offset * scale_factor;
 
         // e.g. if the texture origin is centered (0.5, 0.5), then this is like "position - half of size"
-        let top_left_position = position_on_screen - self.get_origin();
+        let top_left_position = position_on_screen - self.get_origin(scale_factor);
+
+        Point3::new(top_left_position.x, top_left_position.y, self.depth)
+    }
+
+    /// Transforms the unit quad into screen pixels
+    pub fn get_model_matrix(&self, screen_size: Vector2<f32>, scale_factor: f32) -> Matrix4<f32> {
+        let position = self.get_position(screen_size, scale_factor);
+        let origin = self.get_origin(scale_factor);
+        let size = self.get_size(scale_factor);
+
+        Matrix4::new_translation(&position.coords)
+            * Matrix4::new_rotation_wrt_point(
+                Vector3::new(0.0, 0.0, -self.texture_position.angle.0),
+                Point3::new(origin.x, origin.y, 0.0),
+            )
+            * Matrix4::new_nonuniform_scaling(&Vector3::new(size.x, size.y, 1.0))
+    }
+
+    /// Whether the point in screen pixels is on the component, also works for rotated components
+    pub fn contains(
+        &self,
+        point: Point2<f32>,
+        screen_size: Vector2<f32>,
+        scale_factor: f32,
+    ) -> bool {
+        let inverse_model_matrix = match self
+            .get_model_matrix(screen_size, scale_factor)
+            .try_inverse()
+        {
+            Some(inverse_model_matrix) => inverse_model_matrix,
+            None => return false,
+        };
+        let point =
+            inverse_model_matrix.transform_point(&Point3::new(point.x, point.y, self.depth));
+        (0.0..=1.0).contains(&point.x) && (0.0..=1.0).contains(&point.y)
+    }
+}
+
+/// Only shows part of the texture, starting from the bottom of the texture.
+/// Radial progress bars instead fill clockwise, starting at the top.
+/// The shown part smoothly follows the target.
+#[derive(Component, Debug, Clone)]
+pub struct UIProgressBar {
+    /// From 0 to 1
+    pub target_fill: f32,
+    /// From 0 to 1, this is what gets rendered
+    pub fill: f32,
+    /// How quickly the fill follows the target
+    pub smoothing: f32,
+    /// From 0 to 1, tints the bar red
+    pub highlight: f32,
+    pub radial: bool,
+}
+
+impl UIProgressBar {
+    pub fn new(fill: f32) -> Self {
+        Self {
+            target_fill: fill,
+            fill,
+            smoothing: 8.0,
+            highlight: 0.0,
+            radial: false,
+        }
+    }
+
+    pub fn with_radial(mut self) -> Self {
+        self.radial = true;
+        self
+    }
 
-        Point3::new(top_left_position.x, top_left_position.y, self.position.z)
+    pub fn animate(&mut self, delta_seconds: f32) {
+        let factor = (self.smoothing * delta_seconds).min(1.0);
+        self.fill += (self.target_fill - self.fill) * factor;
     }
 }
 
 pub struct UITexturePosition {
     /// The size and rotation are relative to the origin
     pub texture_origin: Point2<f32>,
-    // TODO: maybe make the scale depend on the window size
     pub scale: Vector2<f32>,
     pub angle: Rad<f32>,
 }
@@ -0,0 +1,164 @@
+use crate::asset::AssetId;
+use crate::texture::{
+    AddressMode, BytesTextureData, CpuTexture, Filter, MipmapMode, SamplerInfo, TextureFormat,
+};
+use bevy_ecs::prelude::{Changed, Component, Query};
+use std::sync::Arc;
+
+use crate::ui_component::UIComponent;
+
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+/// Space between two characters and around the text
+const GLYPH_SPACING: usize = 1;
+
+/// A tiny bitmap font, every row is a bitmask with the leftmost pixel in the highest bit
+#[rustfmt::skip]
+const GLYPHS: &[(char, [u8; GLYPH_HEIGHT])] = &[
+    (' ', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('!', [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100]),
+    ('"', [0b01010, 0b01010, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('#', [0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010]),
+    ('%', [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011]),
+    ('\'', [0b00100, 0b00100, 0b01000, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('(', [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010]),
+    (')', [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000]),
+    ('*', [0b00000, 0b00100, 0b10101, 0b01110, 0b10101, 0b00100, 0b00000]),
+    ('+', [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000]),
+    (',', [0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000]),
+    ('-', [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000]),
+    ('.', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100]),
+    ('/', [0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000]),
+    ('0', [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110]),
+    ('1', [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('2', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111]),
+    ('3', [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110]),
+    ('4', [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010]),
+    ('5', [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110]),
+    ('6', [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110]),
+    ('7', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000]),
+    ('8', [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110]),
+    ('9', [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100]),
+    (':', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000]),
+    (';', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b00100, 0b01000]),
+    ('<', [0b00010, 0b00100, 0b01000, 0b10000, 0b01000, 0b00100, 0b00010]),
+    ('=', [0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000]),
+    ('>', [0b01000, 0b00100, 0b00010, 0b00001, 0b00010, 0b00100, 0b01000]),
+    ('?', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100]),
+    ('A', [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('B', [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110]),
+    ('C', [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110]),
+    ('D', [0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110]),
+    ('E', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111]),
+    ('F', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('G', [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111]),
+    ('H', [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('I', [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('J', [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100]),
+    ('K', [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001]),
+    ('L', [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111]),
+    ('M', [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001]),
+    ('N', [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001]),
+    ('O', [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('P', [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('Q', [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101]),
+    ('R', [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001]),
+    ('S', [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110]),
+    ('T', [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('U', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('V', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100]),
+    ('W', [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010]),
+    ('X', [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001]),
+    ('Y', [0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('Z', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111]),
+    ('[', [0b01110, 0b01000, 0b01000, 0b01000, 0b01000, 0b01000, 0b01110]),
+    (']', [0b01110, 0b00010, 0b00010, 0b00010, 0b00010, 0b00010, 0b01110]),
+    ('_', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111]),
+];
+
+/// Text that gets rendered into the texture of the [`UIComponent`] on the same entity.
+/// Lowercase letters are shown as uppercase ones, unknown characters as question marks.
+/// Every change recreates the texture, so texts that get updated every frame should use `set_if_neq`.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct UIText {
+    pub text: String,
+    /// Draws a line through the text, for example for completed objectives
+    pub strikethrough: bool,
+}
+
+impl UIText {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            strikethrough: false,
+        }
+    }
+
+    /// Creates a white texture with the text, one pixel per font pixel
+    pub fn create_texture(&self) -> Arc<CpuTexture> {
+        // An empty texture isn't allowed, so there's always at least one character
+        let char_count = self.text.chars().count().max(1);
+        let width = char_count * (GLYPH_WIDTH + GLYPH_SPACING) + GLYPH_SPACING;
+        let height = GLYPH_HEIGHT + 2 * GLYPH_SPACING;
+
+        let mut bytes = vec![0u8; width * height * 4];
+        let mut set_pixel = |x: usize, y: usize| {
+            let index = (y * width + x) * 4;
+            bytes[index..index + 4].copy_from_slice(&[255, 255, 255, 255]);
+        };
+
+        for (i, character) in self.text.chars().enumerate() {
+            let rows = glyph(character);
+            let left = GLYPH_SPACING + i * (GLYPH_WIDTH + GLYPH_SPACING);
+            for (y, row) in rows.iter().enumerate() {
+                for x in 0..GLYPH_WIDTH {
+                    if row & (1 << (GLYPH_WIDTH - 1 - x)) != 0 {
+                        set_pixel(left + x, GLYPH_SPACING + y);
+                    }
+                }
+            }
+        }
+
+        if self.strikethrough {
+            for x in 0..width {
+                set_pixel(x, GLYPH_SPACING + GLYPH_HEIGHT / 2);
+            }
+        }
+
+        Arc::new(CpuTexture {
+            id: AssetId::new_v4(),
+            data: Box::new(BytesTextureData::new(
+                (width as u32, height as u32),
+                TextureFormat::R8G8B8A8_UNORM,
+                bytes,
+            )),
+            sampler_info: SamplerInfo {
+                min_filter: Filter::Nearest,
+                mag_filter: Filter::Nearest,
+                mipmap_mode: MipmapMode::Nearest,
+                address_mode: [AddressMode::ClampToBorder; 3],
+            },
+        })
+    }
+}
+
+fn glyph(character: char) -> [u8; GLYPH_HEIGHT] {
+    let character = character.to_ascii_uppercase();
+    GLYPHS
+        .iter()
+        .find(|(glyph_character, _)| *glyph_character == character)
+        .or_else(|| {
+            GLYPHS
+                .iter()
+                .find(|(glyph_character, _)| *glyph_character == '?')
+        })
+        .map(|(_, rows)| *rows)
+        .unwrap()
+}
+
+/// Recreates the textures of text components whose text changed
+pub fn update_ui_text(mut query: Query<(&UIText, &mut UIComponent), Changed<UIText>>) {
+    for (text, mut ui_component) in query.iter_mut() {
+        ui_component.texture = text.create_texture();
+    }
+}
@@ -0,0 +1,149 @@
+use crate::ui_component::UIComponent;
+use bevy_ecs::prelude::Component;
+use nalgebra::Vector2;
+
+/// How a tween speeds up and slows down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    #[default]
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps the linear progress from 0 to 1 onto the eased progress
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
+
+/// The animated properties of a UI component
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UITweenState {
+    /// In the range 0-1
+    pub opacity: f32,
+    /// Offset from the anchor in pixels of the reference resolution
+    pub offset: Vector2<f32>,
+    pub scale: Vector2<f32>,
+}
+
+impl UITweenState {
+    pub fn of(ui_component: &UIComponent) -> Self {
+        Self {
+            opacity: ui_component.opacity,
+            offset: ui_component.offset,
+            scale: ui_component.texture_position.scale,
+        }
+    }
+
+    pub fn lerp(&self, other: &UITweenState, t: f32) -> Self {
+        Self {
+            opacity: self.opacity + (other.opacity - self.opacity) * t,
+            offset: self.offset.lerp(&other.offset, t),
+            scale: self.scale.lerp(&other.scale, t),
+        }
+    }
+
+    fn apply_to(&self, ui_component: &mut UIComponent) {
+        ui_component.opacity = self.opacity;
+        ui_component.offset = self.offset;
+        ui_component.texture_position.scale = self.scale;
+    }
+}
+
+/// Fades, slides and scales a UI component in and out, instead of just showing and hiding it.
+/// Set `is_shown` instead of the visibility of the component, the tween then takes care of it.
+/// While the tween runs, it owns the opacity, the offset and the scale of the component.
+#[derive(Component, Debug, Clone)]
+pub struct UITween {
+    pub hidden: UITweenState,
+    pub shown: UITweenState,
+    /// In seconds, for going from hidden to shown or back
+    pub duration: f32,
+    pub easing: Easing,
+    pub is_shown: bool,
+    /// From 0 when hidden to 1 when shown, before the easing
+    progress: f32,
+}
+
+impl UITween {
+    /// Fades the component in, the component itself is what it looks like when shown
+    pub fn fade(ui_component: &UIComponent, duration: f32) -> Self {
+        let shown = UITweenState::of(ui_component);
+        Self {
+            hidden: UITweenState {
+                opacity: 0.0,
+                ..shown
+            },
+            shown,
+            duration,
+            easing: Easing::default(),
+            is_shown: ui_component.visible,
+            progress: if ui_component.visible { 1.0 } else { 0.0 },
+        }
+    }
+
+    /// Slides the component in from the offset
+    pub fn with_hidden_offset(mut self, offset: Vector2<f32>) -> Self {
+        self.hidden.offset = offset;
+        self
+    }
+
+    /// Grows or shrinks the component from the scale
+    pub fn with_hidden_scale(mut self, scale: Vector2<f32>) -> Self {
+        self.hidden.scale = scale;
+        self
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.progress == if self.is_shown { 1.0 } else { 0.0 }
+    }
+
+    /// Turning around halfway continues from the current state, instead of jumping
+    pub fn animate(&mut self, delta_seconds: f32, ui_component: &mut UIComponent) {
+        let step = if self.duration > 0.0 {
+            delta_seconds / self.duration
+        } else {
+            1.0
+        };
+        let progress = if self.is_shown {
+            (self.progress + step).min(1.0)
+        } else {
+            (self.progress - step).max(0.0)
+        };
+        // Finished tweens leave the component alone
+        if progress == self.progress {
+            return;
+        }
+        self.progress = progress;
+
+        let visible = self.progress > 0.0;
+        if ui_component.visible != visible {
+            ui_component.visible = visible;
+        }
+        if visible {
+            self.hidden
+                .lerp(&self.shown, self.easing.apply(self.progress))
+                .apply_to(ui_component);
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+
+use bevy_ecs::component::Component;
+
+use crate::texture::CpuTexture;
+
+/// Shows a video on the base color texture of every primitive of a model, like the screen of a
+/// story terminal. The frames get decoded on another thread, which stops once this gets dropped.
+#[derive(Component)]
+pub struct VideoTexture {
+    /// in frames per second
+    pub fps: f32,
+    /// None until the first frame has been decoded
+    pub current_frame: Option<Arc<CpuTexture>>,
+    decoded_frames: Mutex<Receiver<Arc<CpuTexture>>>,
+    /// in seconds since the current frame was shown
+    frame_time: f32,
+}
+
+impl VideoTexture {
+    pub fn new(decoded_frames: Receiver<Arc<CpuTexture>>, fps: f32) -> Self {
+        Self {
+            fps,
+            current_frame: None,
+            decoded_frames: Mutex::new(decoded_frames),
+            frame_time: 0.0,
+        }
+    }
+
+    /// Shows the next frame once it is time for it. When the decoding falls behind,
+    /// the current frame stays a bit longer instead of skipping ahead.
+    pub fn advance(&mut self, delta_seconds: f32) {
+        let frame_duration = 1.0 / self.fps.max(f32::EPSILON);
+        self.frame_time += delta_seconds;
+        if self.current_frame.is_some() && self.frame_time < frame_duration {
+            return;
+        }
+
+        // Nothing to show yet, or the video has ended because its files are gone
+        if let Ok(frame) = self.decoded_frames.get_mut().unwrap().try_recv() {
+            self.current_frame = Some(frame);
+            // A late frame shouldn't make the next frames rush to catch up
+            self.frame_time = (self.frame_time - frame_duration).clamp(0.0, frame_duration);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use bevy_ecs::prelude::Resource;
+use std::time::{Duration, Instant};
+
+/// If the game falls further behind than this, it slows down instead of trying to catch up
+const MAX_STEPS_PER_FRAME: u32 = 5;
+
+/// Lets the game logic run at a fixed rate, independent of how fast frames get rendered.
+/// See https://gafferongames.com/post/fix_your_timestep/
+#[derive(Resource)]
+pub struct FixedTime {
+    timestep: Duration,
+    accumulated: Duration,
+    last_frame: Instant,
+    /// Used instead of the real frame time, for example when recording a video
+    fixed_frame_delta: Option<Duration>,
+}
+
+impl FixedTime {
+    pub fn new(timestep: Duration) -> Self {
+        assert!(!timestep.is_zero(), "the timestep must not be zero");
+        Self {
+            timestep,
+            accumulated: Duration::ZERO,
+            last_frame: Instant::now(),
+            fixed_frame_delta: None,
+        }
+    }
+
+    pub fn from_rate(steps_per_second: u32) -> Self {
+        Self::new(Duration::from_secs_f64(1.0 / steps_per_second as f64))
+    }
+
+    pub fn timestep(&self) -> Duration {
+        self.timestep
+    }
+
+    /// Makes every frame take exactly this long, regardless of how long it actually took
+    pub fn set_fixed_frame_delta(&mut self, fixed_frame_delta: Option<Duration>) {
+        self.fixed_frame_delta = fixed_frame_delta;
+    }
+
+    /// Adds the time since the last frame and returns how many fixed steps should be run.
+    /// A time scale below 1 slows the game down.
+    pub fn start_frame(&mut self, time_scale: f32) -> u32 {
+        let frame_delta = self
+            .fixed_frame_delta
+            .unwrap_or_else(|| self.last_frame.elapsed());
+        self.last_frame = Instant::now();
+        self.accumulated += frame_delta.mul_f32(time_scale);
+
+        let steps = (self.accumulated.as_nanos() / self.timestep.as_nanos()) as u32;
+        if steps > MAX_STEPS_PER_FRAME {
+            self.accumulated = Duration::ZERO;
+            return MAX_STEPS_PER_FRAME;
+        }
+
+        self.accumulated -= self.timestep * steps;
+        steps
+    }
+
+    /// How far the rendered frame is between the last and the next fixed step, from 0 to 1
+    pub fn overstep_fraction(&self) -> f32 {
+        self.accumulated.as_secs_f32() / self.timestep.as_secs_f32()
+    }
+}
@@ -0,0 +1,152 @@
+use app::plugin::{Plugin, PluginAppAccess};
+use bevy_ecs::prelude::*;
+use levels::current_level::NextLevel;
+use levels::level_id::LevelId;
+
+use crate::time_manager::game_change::{GameChange, GameChangeHistory, GameChangeHistoryPlugin};
+use crate::time_manager::TimeManager;
+
+/// Random numbers for the gameplay and the effects, which repeat exactly after rewinding.
+/// Every level starts with the same numbers for the same seed, no matter what happened before.
+///
+/// The state gets recorded once per tick, after it has been used, so the systems that use it
+/// have to run before [`GameRngPlugin::system_set`].
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    state: u64,
+    /// The state in the history, to only record it when it changed
+    recorded_state: u64,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Self {
+            seed,
+            state: 0,
+            recorded_state: 0,
+        };
+        rng.reseed(LevelId::new(0));
+        rng
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Starts the numbers of the level from the beginning
+    pub fn reseed(&mut self, level_id: LevelId) {
+        self.state = self.seed ^ (level_id.id() as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    }
+
+    /// SplitMix64, which is good enough for games and has a single number as its state
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// Between 0 inclusive and 1 exclusive
+    pub fn next_f32(&mut self) -> f32 {
+        // The 24 bits of the mantissa
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Between min inclusive and max exclusive
+    pub fn range_f32(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    /// Picks an index for a slice with that length, which can't be empty
+    pub fn index(&mut self, length: usize) -> usize {
+        assert!(length > 0, "Cannot pick an index of an empty slice");
+        (self.next_u64() % length as u64) as usize
+    }
+
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    fn record(&mut self, history: &mut GameChangeHistory<RngChange>) {
+        self.recorded_state = self.state;
+        history.add_command(RngChange { state: self.state });
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RngChange {
+    state: u64,
+}
+
+impl GameChange for RngChange {}
+
+/// The first level doesn't get a [`NextLevel`] event
+fn game_rng_first_track(
+    mut rng: ResMut<GameRng>,
+    mut history: ResMut<GameChangeHistory<RngChange>>,
+) {
+    rng.record(&mut history);
+}
+
+fn game_rng_start_track(
+    mut next_level_events: EventReader<NextLevel>,
+    mut rng: ResMut<GameRng>,
+    mut history: ResMut<GameChangeHistory<RngChange>>,
+) {
+    for next_level_event in next_level_events.iter() {
+        rng.reseed(next_level_event.level_id);
+        rng.record(&mut history);
+    }
+}
+
+fn game_rng_track(mut rng: ResMut<GameRng>, mut history: ResMut<GameChangeHistory<RngChange>>) {
+    if rng.state != rng.recorded_state {
+        rng.record(&mut history);
+    }
+}
+
+/// Also undoes the numbers that were used while rewinding
+fn game_rng_rewind(
+    time_manager: Res<TimeManager>,
+    mut rng: ResMut<GameRng>,
+    mut history: ResMut<GameChangeHistory<RngChange>>,
+) {
+    let commands = history.take_commands_to_apply(&time_manager);
+
+    for command_collection in commands {
+        for command in command_collection.commands {
+            rng.state = command.state;
+            rng.recorded_state = command.state;
+        }
+    }
+}
+
+pub struct GameRngPlugin {
+    seed: u64,
+}
+
+impl GameRngPlugin {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+}
+
+impl Plugin for GameRngPlugin {
+    fn build(&mut self, app: &mut PluginAppAccess) {
+        app //
+            .with_resource(GameRng::new(self.seed))
+            .with_startup_system(game_rng_first_track)
+            .with_plugin(
+                GameChangeHistoryPlugin::<RngChange>::new()
+                    .with_tracker(game_rng_start_track)
+                    .with_tracker(game_rng_track.after(game_rng_start_track))
+                    .with_rewinder(game_rng_rewind),
+            );
+    }
+}
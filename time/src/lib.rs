@@ -1,4 +1,6 @@
 pub mod events;
+pub mod fixed_time;
+pub mod game_rng;
 pub mod signed_duration;
 pub mod time;
 pub mod time_manager;
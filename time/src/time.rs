@@ -7,7 +7,9 @@ pub struct Time {
     delta: Duration,
     delta_seconds: f64,
     last_update: Instant,
-    start_time: Instant,
+    since_startup: Duration,
+    /// Used instead of the real time, for example when recording a video
+    fixed_delta: Option<Duration>,
 }
 
 impl Time {
@@ -16,7 +18,8 @@ impl Time {
             delta: Duration::from_secs(0),
             delta_seconds: 0.0,
             last_update: Instant::now(),
-            start_time: Instant::now(),
+            since_startup: Duration::from_secs(0),
+            fixed_delta: None,
         }
     }
 
@@ -29,16 +32,24 @@ impl Time {
     }
 
     pub fn update(&mut self) {
-        let delta_time = self.last_update.elapsed();
+        let delta_time = self
+            .fixed_delta
+            .unwrap_or_else(|| self.last_update.elapsed());
         self.last_update = Instant::now();
 
         self.delta = delta_time;
         self.delta_seconds = delta_time.as_secs_f64();
+        self.since_startup += delta_time;
+    }
+
+    /// Makes every frame take exactly this long, regardless of how long it actually took
+    pub fn set_fixed_delta(&mut self, fixed_delta: Option<Duration>) {
+        self.fixed_delta = fixed_delta;
     }
 
     /// Remember to usually use LevelTime instead
     pub fn time_since_startup(&self) -> Duration {
-        self.start_time.elapsed()
+        self.since_startup
     }
 }
 
@@ -1,5 +1,6 @@
 pub mod game_change;
 pub mod level_time;
+pub mod save_state;
 
 use crate::{
     signed_duration::SignedDuration,
@@ -9,13 +10,14 @@ use std::{sync::Mutex, time::Duration};
 
 use app::plugin::{Plugin, PluginAppAccess};
 use bevy_ecs::{
-    prelude::{Component, EventReader},
+    prelude::{Component, EventReader, Events},
     schedule::{IntoSystemConfig, SystemSet},
     system::{Res, ResMut, Resource},
 };
 use levels::current_level::NextLevel;
 
 use self::level_time::LevelTime;
+use self::save_state::SaveStateCommand;
 
 #[derive(Component)]
 pub struct TimeTracked {
@@ -24,6 +26,14 @@ pub struct TimeTracked {
 
 pub type TimeTrackedId = uuid::Uuid;
 
+/// Only entities with this marker get rewound by [`TimeManager::rewind_target_next_frame`],
+/// while the rest of the world keeps running
+#[derive(Component)]
+pub struct RewindTarget;
+
+/// The number of fixed simulation steps since the start of the level
+pub type LevelTick = u64;
+
 impl TimeTracked {
     pub fn new() -> Self {
         Self {
@@ -49,28 +59,77 @@ pub struct TimeManager {
     level_delta_time: SignedDuration,
     /// If this is Some, then we're rewinding with a certain factor/speed
     rewind_next_frame: Mutex<Option<f32>>,
+    /// Keeps rewinding with this speed until it is set to zero
+    rewind_speed: f32,
+    /// The speed of the current frame, zero when not rewinding
+    current_rewind_speed: f32,
+    /// Same as rewind_next_frame, but only for the rewind targets
+    rewind_target_next_frame: Mutex<Option<f32>>,
     time_state: TimeState,
     level_time: LevelTime,
+    /// Only advances while the game runs normally, the game changes are recorded with it
+    level_tick: LevelTick,
+    tick_duration: Duration,
+    /// Is Some while the rewind targets are being rewound
+    target_level_tick: Option<f64>,
+    /// Stops the level time completely, for example for the photo mode
+    frozen: bool,
+    /// Slows down the whole game, 1 is the normal speed
+    time_scale: f32,
+    /// The tick of the save state in the current level
+    saved_level_tick: Option<LevelTick>,
 }
 
 pub fn is_rewinding(time_manager: Res<TimeManager>) -> bool {
     time_manager.is_rewinding()
 }
 
+pub fn is_time_frozen(time_manager: Res<TimeManager>) -> bool {
+    time_manager.is_frozen()
+}
+
 impl TimeManager {
     fn new() -> Self {
         Self {
             level_delta_time: Default::default(),
             rewind_next_frame: Mutex::new(None),
+            rewind_speed: 0.0,
+            current_rewind_speed: 0.0,
+            rewind_target_next_frame: Mutex::new(None),
             time_state: TimeState::Normal,
             level_time: LevelTime::zero(),
+            level_tick: 0,
+            tick_duration: Duration::ZERO,
+            target_level_tick: None,
+            frozen: false,
+            time_scale: 1.0,
+            saved_level_tick: None,
         }
     }
 
+    /// Has to be called once per fixed step, so the delta is the duration of a tick
     pub fn start_frame(&mut self, delta: Duration) {
         let old_level_time = self.level_time;
+        self.tick_duration = delta;
+
+        if self.frozen {
+            // Nothing moves and nothing gets recorded, rewinding continues after unfreezing
+            self.rewind_next_frame.lock().unwrap().take();
+            self.rewind_target_next_frame.lock().unwrap().take();
+            self.current_rewind_speed = 0.0;
+            self.level_delta_time = SignedDuration::default();
+            return;
+        }
+
+        let rewind_speed = self
+            .rewind_next_frame
+            .lock()
+            .unwrap()
+            .take()
+            .or(Some(self.rewind_speed).filter(|speed| *speed > 0.0));
 
-        if let Some(rewind_speed_factor) = self.rewind_next_frame.lock().unwrap().take() {
+        self.current_rewind_speed = rewind_speed.unwrap_or(0.0);
+        if let Some(rewind_speed_factor) = rewind_speed {
             // Rewinding
             self.level_time = self
                 .level_time
@@ -90,10 +149,14 @@ impl TimeManager {
         } else {
             match self.time_state {
                 TimeState::Normal => {
-                    self.level_time += delta;
+                    self.level_tick += 1;
+                    self.level_time = LevelTime::from_ticks(self.level_tick, delta);
                 }
                 TimeState::StartRewinding | TimeState::Rewinding => {
-                    // Keep level time unchanged and stop interpolating
+                    // Snap to the last tick and stop interpolating,
+                    // so that the game continues from exactly the recorded state
+                    self.level_tick = self.level_time.as_ticks_f64(delta).floor() as LevelTick;
+                    self.level_time = LevelTime::from_ticks(self.level_tick, delta);
                     self.time_state = TimeState::StopRewinding;
                 }
                 TimeState::StopRewinding => {
@@ -104,6 +167,15 @@ impl TimeManager {
         }
 
         self.level_delta_time = self.level_time - old_level_time;
+
+        let rewind_target = self.rewind_target_next_frame.lock().unwrap().take();
+        self.target_level_tick = match rewind_target {
+            Some(rewind_speed_factor) if !self.is_rewinding() => {
+                let target_level_tick = self.target_level_tick.unwrap_or(self.level_tick as f64);
+                Some((target_level_tick - rewind_speed_factor as f64).max(0.0))
+            }
+            _ => None,
+        };
     }
 
     pub fn level_time_seconds(&self) -> f32 {
@@ -114,6 +186,23 @@ impl TimeManager {
         &self.level_time
     }
 
+    pub fn level_tick(&self) -> LevelTick {
+        self.level_tick
+    }
+
+    /// How much level time passes with every tick
+    pub fn tick_duration(&self) -> Duration {
+        self.tick_duration
+    }
+
+    /// Lies between two ticks while rewinding
+    pub fn fractional_level_tick(&self) -> f64 {
+        if self.tick_duration.is_zero() {
+            return self.level_tick as f64;
+        }
+        self.level_time.as_ticks_f64(self.tick_duration)
+    }
+
     pub fn level_delta_time(&self) -> &SignedDuration {
         &self.level_delta_time
     }
@@ -130,6 +219,38 @@ impl TimeManager {
 
     fn next_level(&mut self) {
         self.level_time = LevelTime::zero();
+        self.level_tick = 0;
+        self.target_level_tick = None;
+        self.saved_level_tick = None;
+    }
+
+    /// Goes back or forward to the saved tick, and rewinds there without moving,
+    /// so that the histories can apply their saved changes
+    fn load_save_state(&mut self) {
+        if let Some(saved_level_tick) = self.saved_level_tick {
+            self.level_tick = saved_level_tick;
+            self.level_time = LevelTime::from_ticks(saved_level_tick, self.tick_duration);
+            self.target_level_tick = None;
+            self.rewind_next_frame(0.0);
+        }
+    }
+
+    /// The level time stays the same until it gets unfrozen again
+    pub fn set_frozen(&mut self, frozen: bool) {
+        self.frozen = frozen;
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Slow motion, the fixed steps happen less often so that everything slows down evenly
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.clamp(0.0, 1.0);
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
     }
 
     pub fn is_rewinding(&self) -> bool {
@@ -160,6 +281,33 @@ impl TimeManager {
             .unwrap()
             .replace(rewind_speed_factor);
     }
+
+    /// Rewinds every frame until the speed is set to zero again, for analog inputs
+    pub fn set_rewind_speed(&mut self, rewind_speed_factor: f32) {
+        self.rewind_speed = rewind_speed_factor.max(0.0);
+    }
+
+    /// How fast the level time currently goes backwards, zero when not rewinding
+    pub fn rewind_speed(&self) -> f32 {
+        self.current_rewind_speed
+    }
+
+    /// Only rewinds the entities with a [`RewindTarget`], unless everything is rewinding
+    pub fn rewind_target_next_frame(&self, rewind_speed_factor: f32) {
+        self.rewind_target_next_frame
+            .lock()
+            .unwrap()
+            .replace(rewind_speed_factor);
+    }
+
+    pub fn is_rewinding_target(&self) -> bool {
+        self.target_level_tick.is_some()
+    }
+
+    /// The tick that the rewind targets are currently at
+    pub fn target_level_tick(&self) -> Option<f64> {
+        self.target_level_tick
+    }
 }
 
 fn start_frame(time: Res<Time>, mut time_manager: ResMut<TimeManager>) {
@@ -172,6 +320,30 @@ fn next_level(mut time_manager: ResMut<TimeManager>, mut next_level: EventReader
     }
 }
 
+/// After the tick has advanced, like the histories which save their state later in the frame
+fn save_level_time(
+    mut time_manager: ResMut<TimeManager>,
+    mut save_state_commands: EventReader<SaveStateCommand>,
+) {
+    for command in save_state_commands.iter() {
+        if command == &SaveStateCommand::Save {
+            time_manager.saved_level_tick = Some(time_manager.level_tick);
+        }
+    }
+}
+
+/// Before the tick advances, so that the rewinding starts in the same frame
+fn load_level_time(
+    mut time_manager: ResMut<TimeManager>,
+    mut save_state_commands: EventReader<SaveStateCommand>,
+) {
+    for command in save_state_commands.iter() {
+        if command == &SaveStateCommand::Load {
+            time_manager.load_save_state();
+        }
+    }
+}
+
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum TimeManagerPluginSet {
     StartFrame,
@@ -182,11 +354,28 @@ pub struct TimeManagerPlugin;
 impl Plugin for TimeManagerPlugin {
     fn build(&mut self, app: &mut PluginAppAccess) {
         app.with_resource(TimeManager::new())
+            .with_resource(Events::<SaveStateCommand>::default())
+            .with_system(
+                Events::<SaveStateCommand>::update_system
+                    .in_set(TimeManagerPluginSet::StartFrame)
+                    .before(load_level_time),
+            )
             .with_system(
                 next_level
                     .in_set(TimeManagerPluginSet::StartFrame)
                     .before(start_frame),
             )
+            .with_system(
+                load_level_time
+                    .in_set(TimeManagerPluginSet::StartFrame)
+                    .after(next_level)
+                    .before(start_frame),
+            )
+            .with_system(
+                save_level_time
+                    .in_set(TimeManagerPluginSet::StartFrame)
+                    .after(start_frame),
+            )
             .with_system(
                 start_frame
                     .in_set(TimeManagerPluginSet::StartFrame)
@@ -8,7 +8,8 @@ use bevy_ecs::{
 };
 use levels::current_level::NextLevel;
 
-use super::{is_rewinding, level_time::LevelTime, TimeManager, TimeManagerPluginSet};
+use super::save_state::SaveStateCommand;
+use super::{is_rewinding, LevelTick, TimeManager, TimeManagerPluginSet};
 
 pub trait GameChange
 where
@@ -16,6 +17,9 @@ where
 {
 }
 
+/// While rewinding, the level time lies between two recorded ticks.
+/// Only values that were recorded at both ticks should be interpolated,
+/// everything else already has its exact state.
 pub struct GameChangeInterpolation<'history, T>
 where
     T: GameChange,
@@ -25,6 +29,17 @@ where
     pub factor: f32,
 }
 
+/// The recorded state of a single object at a tick, which lies between two changes.
+/// `to` is None if the object never changed afterwards.
+pub struct GameChangeSample<'history, T>
+where
+    T: GameChange,
+{
+    pub from: &'history T,
+    pub to: Option<&'history T>,
+    pub factor: f32,
+}
+
 /// All game changes in one frame
 /// Multiple commands, because we have multiple entities
 /// A GameChange describes the state of an object starting at a certain time. Until the next GameChange happens.
@@ -33,10 +48,22 @@ pub struct GameChanges<T>
 where
     T: GameChange,
 {
-    timestamp: LevelTime,
+    tick: LevelTick,
     pub commands: Vec<T>,
 }
 
+impl<T> GameChanges<T>
+where
+    T: GameChange,
+{
+    fn find<F>(&self, is_object: &F) -> Option<&T>
+    where
+        F: Fn(&T) -> bool,
+    {
+        self.commands.iter().find(|&command| is_object(command))
+    }
+}
+
 /// Systems change object values.
 /// Time rewinding restores the state of an object before a system acts on it.
 /// To limit the size of this, we could either
@@ -50,8 +77,14 @@ where
     T: GameChange,
 {
     is_rewinding: bool,
-    level_time: LevelTime,
+    level_tick: LevelTick,
     history: VecDeque<GameChanges<T>>,
+    /// The most recently undone changes, which the rewinding interpolates towards
+    undone: Option<GameChanges<T>>,
+    /// The history at the time of the save state
+    saved: Option<VecDeque<GameChanges<T>>>,
+    /// Set after loading a save state, until the changes have been applied
+    is_restoring: bool,
 }
 
 impl<T> GameChangeHistory<T>
@@ -61,21 +94,28 @@ where
     pub fn new() -> Self {
         Self {
             is_rewinding: false,
-            level_time: LevelTime::zero(),
+            level_tick: 0,
             history: VecDeque::new(),
+            undone: None,
+            saved: None,
+            is_restoring: false,
         }
     }
 
     fn update_with_time(&mut self, time_manager: &TimeManager) {
         self.is_rewinding = time_manager.is_rewinding();
-        self.level_time = time_manager.level_time;
+        self.level_tick = time_manager.level_tick;
+        if !self.is_rewinding {
+            self.undone = None;
+            self.is_restoring = false;
+        }
     }
 
     pub fn add_command(&mut self, command: T) {
         assert!(!self.is_rewinding, "Cannot add commands while rewinding");
 
         if let Some(last) = self.history.back_mut() {
-            if last.timestamp == self.level_time {
+            if last.tick == self.level_tick {
                 last.commands.push(command);
                 return;
             }
@@ -83,21 +123,42 @@ where
 
         // This logic avoids adding commands to the history that are not needed
         self.history.push_back(GameChanges {
-            timestamp: self.level_time,
+            tick: self.level_tick,
             commands: vec![command],
         });
     }
 
     fn clear(&mut self) {
         self.history.clear();
+        self.undone = None;
+        self.saved = None;
         self.history.push_back(GameChanges {
-            timestamp: LevelTime::zero(),
+            tick: 0,
             commands: Vec::new(),
         });
     }
 
+    fn save_state(&mut self) {
+        self.saved = Some(self.history.clone());
+    }
+
+    fn load_state(&mut self) {
+        if let Some(saved) = &self.saved {
+            self.history = saved.clone();
+            self.undone = None;
+            self.is_restoring = true;
+        }
+    }
+
     /// Returns the commands that need to be applied to the game state
     pub fn take_commands_to_apply(&mut self, time_manager: &TimeManager) -> Vec<GameChanges<T>> {
+        if self.is_restoring {
+            // Every change from the oldest to the newest ends up with the state of the save state
+            self.is_restoring = false;
+            return self.history.iter().cloned().collect();
+        }
+
+        let level_tick = time_manager.fractional_level_tick();
         let mut commands = Vec::new();
         loop {
             if self.history.len() <= 0 {
@@ -107,10 +168,11 @@ where
             let top = self.history.back().unwrap();
 
             // If we're further back in the past
-            if time_manager.level_time < top.timestamp {
+            if level_tick < top.tick as f64 {
                 // We can pop the top and apply it
                 let top = self.history.pop_back().unwrap();
-                commands.push(top);
+                commands.push(top.clone());
+                self.undone = Some(top);
             } else {
                 // Nothing to do
                 break;
@@ -131,6 +193,57 @@ where
 
         commands
     }
+
+    /// Has to be called after applying the commands from [`Self::take_commands_to_apply`]
+    pub fn interpolation(&self, time_manager: &TimeManager) -> Option<GameChangeInterpolation<T>> {
+        if !time_manager.is_interpolating() {
+            return None;
+        }
+
+        let from = self.history.back()?;
+        let to = self.undone.as_ref()?;
+        let factor = (time_manager.fractional_level_tick() - from.tick as f64)
+            / (to.tick - from.tick) as f64;
+
+        Some(GameChangeInterpolation {
+            from,
+            to,
+            factor: factor.clamp(0.0, 1.0) as f32,
+        })
+    }
+
+    /// Looks up the state of a single object at a tick, without undoing any changes.
+    /// Used for rewinding single objects while the rest of the world keeps running.
+    pub fn sample<F>(&self, tick: f64, is_object: F) -> Option<GameChangeSample<T>>
+    where
+        F: Fn(&T) -> bool,
+    {
+        // The history is sorted by tick, so only the changes right around the tick have to be searched
+        let split = self
+            .history
+            .partition_point(|changes| changes.tick as f64 <= tick);
+        let from = self
+            .history
+            .range(..split)
+            .rev()
+            .find_map(|changes| changes.find(&is_object))?;
+        let to = self
+            .history
+            .range(split..)
+            .find_map(|changes| Some((changes.tick, changes.find(&is_object)?)));
+
+        // The object keeps its state until the next change, so we only interpolate
+        // during the tick right before it
+        let factor = match to {
+            Some((to_tick, _)) => (tick - (to_tick - 1) as f64).clamp(0.0, 1.0) as f32,
+            None => 0.0,
+        };
+        Some(GameChangeSample {
+            from,
+            to: to.map(|(_, command)| command),
+            factor,
+        })
+    }
 }
 
 fn read_timestamp<T>(time_manager: Res<TimeManager>, mut history: ResMut<GameChangeHistory<T>>)
@@ -151,6 +264,20 @@ fn clear_on_next_level<T>(
     }
 }
 
+fn save_or_load_state<T>(
+    mut history: ResMut<GameChangeHistory<T>>,
+    mut save_state_commands: EventReader<SaveStateCommand>,
+) where
+    T: GameChange + 'static,
+{
+    for command in save_state_commands.iter() {
+        match command {
+            SaveStateCommand::Save => history.save_state(),
+            SaveStateCommand::Load => history.load_state(),
+        }
+    }
+}
+
 #[derive(SystemSet)]
 enum GameChangeHistoryPluginSet<T> {
     UpdateInfo,
@@ -294,6 +421,11 @@ where
                     .in_set(GameChangeHistoryPluginSet::<T>::UpdateInfo)
                     .before(read_timestamp::<T>),
             )
-            .with_system(read_timestamp::<T>.in_set(GameChangeHistoryPluginSet::<T>::UpdateInfo));
+            .with_system(read_timestamp::<T>.in_set(GameChangeHistoryPluginSet::<T>::UpdateInfo))
+            .with_system(
+                save_or_load_state::<T>
+                    .in_set(GameChangeHistoryPluginSet::<T>::UpdateInfo)
+                    .after(read_timestamp::<T>),
+            );
     }
 }
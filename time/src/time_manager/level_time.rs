@@ -16,6 +16,16 @@ impl LevelTime {
         }
     }
 
+    pub fn from_ticks(ticks: u64, tick_duration: Duration) -> LevelTime {
+        LevelTime {
+            elapsed: Duration::from_nanos(tick_duration.as_nanos() as u64 * ticks),
+        }
+    }
+
+    pub fn as_ticks_f64(&self, tick_duration: Duration) -> f64 {
+        self.elapsed.as_nanos() as f64 / tick_duration.as_nanos() as f64
+    }
+
     pub fn as_secs_f32(&self) -> f32 {
         self.elapsed.as_secs_f32()
     }
@@ -0,0 +1,8 @@
+/// Snapshots of the current level, for trying out a tricky puzzle state again and again.
+/// The time manager and every [`super::game_change::GameChangeHistory`] react to them in the next frame.
+/// Loading rewinds in place for a moment, so that every rewinder applies the saved changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveStateCommand {
+    Save,
+    Load,
+}
@@ -1,11 +1,20 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FullscreenMode {
+    Windowed,
+    /// A window without borders that covers the whole monitor, which makes alt-tabbing painless
+    Borderless,
+    /// Changes the video mode of the monitor
+    Exclusive,
+}
+
 #[derive(Debug, Clone)]
 pub struct WindowConfig {
     pub resolution: (u32, u32),
-    pub fullscreen: bool,
-    /// The desired refresh rate of the game in fullscreen mode.
+    pub fullscreen: FullscreenMode,
+    /// Index of the monitor that the game should be on, falls back to the primary monitor
+    pub monitor: usize,
+    /// The desired refresh rate of the game in exclusive fullscreen mode.
     /// Maps to "GLFW_REFRESH_RATE" in an OpenGL application, which only applies to fullscreen mode.
-    /// We should query all video modes https://docs.rs/winit/latest/winit/monitor/struct.MonitorHandle.html#method.video_modes
-    /// and pick the closest one to the desired refresh rate. https://docs.rs/winit/latest/winit/monitor/struct.VideoMode.html#method.refresh_rate_millihertz
-    /// Then, we use that video mode to create the window in fullscreen mode.
+    /// The video mode that is closest to the resolution and the refresh rate gets picked.
     pub refresh_rate: u32,
 }
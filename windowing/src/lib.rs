@@ -14,5 +14,5 @@ pub mod event {
 }
 
 pub mod event_loop {
-    pub use winit::event_loop::ControlFlow;
+    pub use winit::event_loop::{ControlFlow, EventLoop};
 }
@@ -10,7 +10,13 @@ use winit::{
     window::{Fullscreen, WindowBuilder},
 };
 
-use crate::{config::WindowConfig, icon::get_icon};
+use crate::{
+    config::{FullscreenMode, WindowConfig},
+    icon::get_icon,
+};
+
+/// Smaller windows would squish the UI too much
+const MIN_RESOLUTION: (u32, u32) = (640, 360);
 
 #[derive(Resource)]
 pub struct WindowManager {
@@ -48,7 +54,15 @@ impl Plugin for WindowPlugin {
 fn create_window_builder(config: WindowConfig, event_loop: &EventLoop<()>) -> WindowBuilder {
     let monitor = event_loop
         .available_monitors()
-        .next()
+        .nth(config.monitor)
+        .or_else(|| {
+            println!(
+                "Monitor {} not found, using the primary one",
+                config.monitor
+            );
+            event_loop.primary_monitor()
+        })
+        .or_else(|| event_loop.available_monitors().next())
         .expect("no monitor found!");
 
     let mut window_builder = WindowBuilder::new()
@@ -56,6 +70,10 @@ fn create_window_builder(config: WindowConfig, event_loop: &EventLoop<()>) -> Wi
             width: config.resolution.0,
             height: config.resolution.1,
         })
+        .with_min_inner_size(LogicalSize {
+            width: MIN_RESOLUTION.0,
+            height: MIN_RESOLUTION.1,
+        })
         .with_title("Cat to the past");
 
     if let Ok(icon) = get_icon() {
@@ -63,14 +81,34 @@ fn create_window_builder(config: WindowConfig, event_loop: &EventLoop<()>) -> Wi
         window_builder = window_builder.with_window_icon(Some(icon));
     }
 
-    if config.fullscreen {
-        if let Some(video_mode) = monitor.video_modes().find(|v| {
-            let PhysicalSize { width, height } = v.size();
+    match config.fullscreen {
+        FullscreenMode::Windowed => {
+            window_builder = window_builder.with_position(monitor.position());
+        }
+        FullscreenMode::Borderless => {
+            window_builder =
+                window_builder.with_fullscreen(Some(Fullscreen::Borderless(Some(monitor))));
+        }
+        FullscreenMode::Exclusive => {
+            // Falling back to the closest video mode, since an exact match rarely exists
+            let video_mode = monitor.video_modes().min_by_key(|v| {
+                let PhysicalSize { width, height } = v.size();
+                let resolution_difference =
+                    width.abs_diff(config.resolution.0) + height.abs_diff(config.resolution.1);
+                let refresh_rate_difference = v
+                    .refresh_rate_millihertz()
+                    .abs_diff(config.refresh_rate * 1000);
+                (resolution_difference, refresh_rate_difference)
+            });
 
-            v.refresh_rate_millihertz() == config.refresh_rate * 1000
-                && (width, height) == config.resolution
-        }) {
-            window_builder = window_builder.with_fullscreen(Some(Fullscreen::Exclusive(video_mode)))
+            let fullscreen = match video_mode {
+                Some(video_mode) => Fullscreen::Exclusive(video_mode),
+                None => {
+                    println!("No video mode found, using borderless fullscreen instead");
+                    Fullscreen::Borderless(Some(monitor))
+                }
+            };
+            window_builder = window_builder.with_fullscreen(Some(fullscreen));
         }
     }
     window_builder